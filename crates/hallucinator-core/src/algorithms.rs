@@ -1,4 +1,4 @@
-//! Algorithmic composition tools (Euclidean rhythms, chord generation)
+//! Algorithmic composition tools (Euclidean and probability rhythms, chord generation)
 
 use serde::{Deserialize, Serialize};
 
@@ -83,6 +83,30 @@ pub fn euclidean_rhythm(steps: u8, hits: u8, rotation: u8) -> Vec<bool> {
     pattern
 }
 
+// ============================================================================
+// Probability Rhythm Generator
+// ============================================================================
+
+/// Generate a random rhythm where each step hits with probability `density`
+///
+/// The same seed always gives the same pattern, so a result can be regenerated
+/// or nudged by changing the seed.
+///
+/// # Arguments
+/// * `steps` - Total number of steps in the pattern
+/// * `density` - Chance of a hit on each step, 0.0 to 1.0
+/// * `seed` - Random seed
+pub fn probability_rhythm(steps: usize, density: f32, seed: u64) -> Vec<bool> {
+    let density = density.clamp(0.0, 1.0);
+    let mut state = seed ^ 0x9E37_79B9_7F4A_7C15;
+    (0..steps)
+        .map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((state >> 33) as f32 / (1u64 << 31) as f32) < density
+        })
+        .collect()
+}
+
 // ============================================================================
 // Scale and Chord Types
 // ============================================================================
@@ -169,6 +193,21 @@ pub enum ChordQuality {
 }
 
 impl ChordQuality {
+    pub const ALL: [ChordQuality; 12] = [
+        Self::Major,
+        Self::Minor,
+        Self::Diminished,
+        Self::Augmented,
+        Self::Major7,
+        Self::Minor7,
+        Self::Dominant7,
+        Self::Diminished7,
+        Self::HalfDiminished7,
+        Self::Sus2,
+        Self::Sus4,
+        Self::Add9,
+    ];
+
     /// Get chord intervals from root
     pub fn intervals(&self) -> &'static [u8] {
         match self {
@@ -203,6 +242,24 @@ impl ChordQuality {
             Self::Add9 => "Add9",
         }
     }
+
+    /// Suffix after the root in a chord symbol ("" for major, "m7" for minor seventh)
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Major => "",
+            Self::Minor => "m",
+            Self::Diminished => "dim",
+            Self::Augmented => "aug",
+            Self::Major7 => "maj7",
+            Self::Minor7 => "m7",
+            Self::Dominant7 => "7",
+            Self::Diminished7 => "dim7",
+            Self::HalfDiminished7 => "m7b5",
+            Self::Sus2 => "sus2",
+            Self::Sus4 => "sus4",
+            Self::Add9 => "add9",
+        }
+    }
 }
 
 // ============================================================================
@@ -383,6 +440,15 @@ mod tests {
         assert_eq!(euclidean_rhythm(16, 4, 0), vec![true, false, false, false, true, false, false, false, true, false, false, false, true, false, false, false]);
     }
 
+    #[test]
+    fn test_probability_rhythm() {
+        assert_eq!(probability_rhythm(16, 0.0, 7), vec![false; 16]);
+        assert_eq!(probability_rhythm(16, 1.0, 7), vec![true; 16]);
+        assert_eq!(probability_rhythm(32, 0.5, 7), probability_rhythm(32, 0.5, 7));
+        let hits = probability_rhythm(1000, 0.25, 42).iter().filter(|&&h| h).count();
+        assert!((200..300).contains(&hits));
+    }
+
     #[test]
     fn test_chord_generator() {
         let chord = ChordGenerator::new(60, ChordQuality::Major);
@@ -0,0 +1,295 @@
+//! Breakpoint automation of instrument/effect parameters
+//!
+//! A lane holds points at beat positions with a normalized value (0-1 of the lane's
+//! parameter range). Each point's shape and tension set the curve of the segment up to
+//! the next point. Lanes are read once per audio block at the block's start beat.
+
+use serde::{Deserialize, Serialize};
+use crate::track::DeviceTarget;
+
+/// Largest bend of exponential and S-curve segments (the curve's exponent at full tension)
+const MAX_BEND: f32 = 8.0;
+
+/// Curve of the segment from a point to the next one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CurveShape {
+    #[default]
+    Linear,
+    /// Bends towards a slow start (positive tension) or a slow end (negative)
+    Exponential,
+    /// Eases in and out (positive tension) or rushes through both ends (negative)
+    SCurve,
+    /// Stays at the point's value until the next point
+    Hold,
+}
+
+impl CurveShape {
+    pub const ALL: [CurveShape; 4] = [CurveShape::Linear, CurveShape::Exponential, CurveShape::SCurve, CurveShape::Hold];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CurveShape::Linear => "Linear",
+            CurveShape::Exponential => "Exponential",
+            CurveShape::SCurve => "S-Curve",
+            CurveShape::Hold => "Hold",
+        }
+    }
+
+    /// Whether the tension changes this shape
+    pub fn has_tension(&self) -> bool {
+        matches!(self, CurveShape::Exponential | CurveShape::SCurve)
+    }
+
+    /// Tension a point gets when switched to this shape
+    pub fn default_tension(&self) -> f32 {
+        if self.has_tension() { 0.5 } else { 0.0 }
+    }
+
+    /// Progress (0-1) through a segment at `t` (0-1 of its length)
+    pub fn apply(&self, tension: f32, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        let bend = tension.clamp(-1.0, 1.0) * MAX_BEND;
+        match self {
+            CurveShape::Linear => t,
+            CurveShape::Exponential => exp_bend(t, bend),
+            CurveShape::SCurve if t < 0.5 => 0.5 * exp_bend(2.0 * t, bend),
+            CurveShape::SCurve => 1.0 - 0.5 * exp_bend(2.0 - 2.0 * t, bend),
+            CurveShape::Hold => 0.0,
+        }
+    }
+}
+
+/// `t` bent by an exponential of strength `bend`: (e^(bend·t) - 1) / (e^bend - 1)
+fn exp_bend(t: f32, bend: f32) -> f32 {
+    if bend.abs() < 1e-3 {
+        return t;
+    }
+    ((bend * t).exp() - 1.0) / (bend.exp() - 1.0)
+}
+
+/// One breakpoint
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AutomationPoint {
+    /// Position in beats from the start of the arrangement
+    pub beat: f64,
+    /// Value as a fraction (0-1) of the lane's parameter range
+    pub value: f32,
+    /// Curve to the next point
+    #[serde(default)]
+    pub shape: CurveShape,
+    /// Bend of the curve to the next point (-1 to 1)
+    #[serde(default)]
+    pub tension: f32,
+}
+
+impl AutomationPoint {
+    pub fn new(beat: f64, value: f32) -> Self {
+        Self { beat: beat.max(0.0), value: value.clamp(0.0, 1.0), shape: CurveShape::Linear, tension: 0.0 }
+    }
+}
+
+/// Automation of one parameter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutomationLane {
+    pub target: DeviceTarget,
+    pub param: String,
+    pub min: f32,
+    pub max: f32,
+    /// Points sorted by beat
+    pub points: Vec<AutomationPoint>,
+    /// Read during playback (off leaves the parameter where it is)
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl AutomationLane {
+    pub fn new(target: DeviceTarget, param: impl Into<String>, min: f32, max: f32) -> Self {
+        Self { target, param: param.into(), min, max, points: Vec::new(), enabled: true }
+    }
+
+    /// Normalized value (0-1) at a beat: the first point's value before it, the last
+    /// point's after it, None if the lane has no points
+    pub fn normalized_at(&self, beat: f64) -> Option<f32> {
+        let next = self.points.partition_point(|p| p.beat <= beat);
+        let Some(from) = next.checked_sub(1).map(|i| &self.points[i]) else {
+            return self.points.first().map(|p| p.value);
+        };
+        let Some(to) = self.points.get(next) else {
+            return Some(from.value);
+        };
+        let length = to.beat - from.beat;
+        if length <= 0.0 {
+            return Some(to.value);
+        }
+        let t = ((beat - from.beat) / length) as f32;
+        Some(from.value + (to.value - from.value) * from.shape.apply(from.tension, t))
+    }
+
+    /// Parameter value at a beat
+    pub fn value_at(&self, beat: f64) -> Option<f32> {
+        self.normalized_at(beat).map(|v| self.denormalize(v))
+    }
+
+    pub fn normalize(&self, value: f32) -> f32 {
+        let range = self.max - self.min;
+        if range == 0.0 { 0.0 } else { ((value - self.min) / range).clamp(0.0, 1.0) }
+    }
+
+    pub fn denormalize(&self, normalized: f32) -> f32 {
+        self.min + normalized.clamp(0.0, 1.0) * (self.max - self.min)
+    }
+
+    /// Insert a point in beat order, returning its index
+    pub fn add_point(&mut self, point: AutomationPoint) -> usize {
+        let idx = self.points.partition_point(|p| p.beat <= point.beat);
+        self.points.insert(idx, point);
+        idx
+    }
+
+    pub fn remove_point(&mut self, index: usize) -> Option<AutomationPoint> {
+        (index < self.points.len()).then(|| self.points.remove(index))
+    }
+
+    /// Move a point, kept between its neighbours so the order (and its index) never changes
+    pub fn move_point(&mut self, index: usize, beat: f64, value: f32) {
+        let lo = index.checked_sub(1).map_or(0.0, |i| self.points[i].beat);
+        let hi = self.points.get(index + 1).map_or(f64::MAX, |p| p.beat);
+        if let Some(point) = self.points.get_mut(index) {
+            point.beat = beat.clamp(lo, hi);
+            point.value = value.clamp(0.0, 1.0);
+        }
+    }
+
+    /// Set the curve from a point to the next
+    pub fn set_shape(&mut self, index: usize, shape: CurveShape) {
+        if let Some(point) = self.points.get_mut(index)
+            && point.shape != shape
+        {
+            point.shape = shape;
+            point.tension = shape.default_tension();
+        }
+    }
+
+    pub fn set_tension(&mut self, index: usize, tension: f32) {
+        if let Some(point) = self.points.get_mut(index) {
+            point.tension = tension.clamp(-1.0, 1.0);
+        }
+    }
+}
+
+/// A track's automation lanes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Automation {
+    pub lanes: Vec<AutomationLane>,
+}
+
+impl Automation {
+    /// True if no lane would change a parameter (nothing to evaluate)
+    pub fn is_idle(&self) -> bool {
+        !self.lanes.iter().any(|l| l.enabled && !l.points.is_empty())
+    }
+
+    /// Add a lane, ignoring duplicates of an already automated parameter; returns its index
+    pub fn add_lane(&mut self, lane: AutomationLane) -> usize {
+        if let Some(idx) = self.lanes.iter().position(|l| l.target == lane.target && l.param == lane.param) {
+            return idx;
+        }
+        self.lanes.push(lane);
+        self.lanes.len() - 1
+    }
+
+    pub fn remove_lane(&mut self, index: usize) {
+        if index < self.lanes.len() {
+            self.lanes.remove(index);
+        }
+    }
+
+    /// Fix up lanes after the effect at `removed` is deleted from the chain
+    pub fn effect_removed(&mut self, removed: usize) {
+        self.lanes.retain(|l| l.target != DeviceTarget::Effect(removed));
+        for lane in &mut self.lanes {
+            if let DeviceTarget::Effect(idx) = &mut lane.target
+                && *idx > removed
+            {
+                *idx -= 1;
+            }
+        }
+    }
+
+    /// Fix up lanes after the effect at `from` is moved to `to`
+    pub fn effect_moved(&mut self, from: usize, to: usize) {
+        for lane in &mut self.lanes {
+            lane.target.effect_moved(from, to);
+        }
+    }
+
+    /// Automated (target, param, value) at a beat for every enabled lane with points
+    pub fn values(&self, beat: f64) -> impl Iterator<Item = (DeviceTarget, &str, f32)> + '_ {
+        self.lanes.iter()
+            .filter(|l| l.enabled)
+            .filter_map(move |lane| Some((lane.target, lane.param.as_str(), lane.value_at(beat)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lane(points: &[(f64, f32, CurveShape, f32)]) -> AutomationLane {
+        let mut lane = AutomationLane::new(DeviceTarget::Instrument, "cutoff", 100.0, 1100.0);
+        for &(beat, value, shape, tension) in points {
+            lane.add_point(AutomationPoint { beat, value, shape, tension });
+        }
+        lane
+    }
+
+    #[test]
+    fn test_linear_and_hold_segments() {
+        let lane = lane(&[
+            (0.0, 0.0, CurveShape::Linear, 0.0),
+            (4.0, 1.0, CurveShape::Hold, 0.0),
+            (8.0, 0.5, CurveShape::Linear, 0.0),
+        ]);
+        assert_eq!(lane.normalized_at(-1.0), Some(0.0));
+        assert_eq!(lane.normalized_at(2.0), Some(0.5));
+        assert_eq!(lane.value_at(2.0), Some(600.0));
+        assert_eq!(lane.normalized_at(7.9), Some(1.0));
+        assert_eq!(lane.normalized_at(8.0), Some(0.5));
+        assert_eq!(lane.normalized_at(100.0), Some(0.5));
+        assert_eq!(AutomationLane::new(DeviceTarget::Instrument, "x", 0.0, 1.0).normalized_at(0.0), None);
+    }
+
+    #[test]
+    fn test_curve_shapes_keep_endpoints_and_bend() {
+        for shape in CurveShape::ALL.into_iter().filter(|s| *s != CurveShape::Hold) {
+            for tension in [-1.0, -0.3, 0.0, 0.5, 1.0] {
+                assert!(shape.apply(tension, 0.0).abs() < 1e-6, "{shape:?} {tension}");
+                assert!((shape.apply(tension, 1.0) - 1.0).abs() < 1e-6, "{shape:?} {tension}");
+            }
+        }
+        // Positive tension: slow start; negative: fast start
+        assert!(CurveShape::Exponential.apply(0.5, 0.5) < 0.5);
+        assert!(CurveShape::Exponential.apply(-0.5, 0.5) > 0.5);
+        // S-curve passes the middle and eases both ends
+        assert!((CurveShape::SCurve.apply(0.5, 0.5) - 0.5).abs() < 1e-6);
+        assert!(CurveShape::SCurve.apply(0.5, 0.25) < 0.25);
+        assert!(CurveShape::SCurve.apply(0.5, 0.75) > 0.75);
+    }
+
+    #[test]
+    fn test_move_point_keeps_order() {
+        let mut lane = lane(&[
+            (0.0, 0.0, CurveShape::Linear, 0.0),
+            (4.0, 1.0, CurveShape::Linear, 0.0),
+            (8.0, 0.5, CurveShape::Linear, 0.0),
+        ]);
+        lane.move_point(1, 10.0, 2.0);
+        assert_eq!((lane.points[1].beat, lane.points[1].value), (8.0, 1.0));
+        lane.set_shape(1, CurveShape::SCurve);
+        assert_eq!(lane.points[1].tension, 0.5);
+    }
+}
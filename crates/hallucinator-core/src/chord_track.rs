@@ -0,0 +1,180 @@
+//! Global chord track: one chord per region of the arrangement, followed by MIDI effects
+//! (harmonizer, arpeggiator, chord) so a song is reharmonized by editing a single lane
+
+use serde::{Deserialize, Serialize};
+use crate::algorithms::ChordQuality;
+
+const ROOT_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+/// A chord by root pitch class and quality
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chord {
+    /// Root pitch class (0 = C ... 11 = B)
+    pub root: u8,
+    pub quality: ChordQuality,
+}
+
+impl Chord {
+    pub fn new(root: u8, quality: ChordQuality) -> Self {
+        Self { root: root % 12, quality }
+    }
+
+    /// Lead-sheet name, e.g. "Am7"
+    pub fn name(&self) -> String {
+        format!("{}{}", ROOT_NAMES[self.root as usize % 12], self.quality.symbol())
+    }
+
+    /// Pitch classes of the chord tones, root first
+    pub fn pitch_classes(&self) -> Vec<u8> {
+        self.quality.intervals().iter().map(|i| (self.root + i) % 12).collect()
+    }
+
+    pub fn contains(&self, pitch: u8) -> bool {
+        self.pitch_classes().contains(&(pitch % 12))
+    }
+
+    /// The next `count` chord tones above `pitch`
+    pub fn tones_above(&self, pitch: u8, count: usize) -> Vec<u8> {
+        (pitch as u16 + 1..=127)
+            .map(|p| p as u8)
+            .filter(|&p| self.contains(p))
+            .take(count)
+            .collect()
+    }
+
+    /// Chord tones stacked upward from the root nearest at or below `pitch`, each once,
+    /// with the lowest `inversion` tones moved up an octave
+    pub fn voice_from(&self, pitch: u8, inversion: usize) -> Vec<u8> {
+        let below = (pitch % 12 + 12 - self.root) % 12;
+        let root = pitch.saturating_sub(below) as u16;
+        let mut notes: Vec<u16> = self.quality.intervals().iter().map(|&i| root + i as u16).collect();
+        for _ in 0..inversion.min(notes.len().saturating_sub(1)) {
+            let lowest = notes.remove(0);
+            notes.push(lowest + 12);
+        }
+        notes.into_iter().filter(|&p| p <= 127).map(|p| p as u8).collect()
+    }
+}
+
+/// A chord held from `start_beat` for `length_beats`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ChordRegion {
+    pub start_beat: f64,
+    pub length_beats: f64,
+    pub chord: Chord,
+}
+
+impl ChordRegion {
+    pub fn end_beat(&self) -> f64 {
+        self.start_beat + self.length_beats
+    }
+
+    pub fn contains(&self, beat: f64) -> bool {
+        beat >= self.start_beat && beat < self.end_beat()
+    }
+}
+
+/// Chord regions of the arrangement, sorted by start and never overlapping
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChordTrack {
+    pub regions: Vec<ChordRegion>,
+}
+
+impl ChordTrack {
+    /// Chord sounding at a beat (None between regions)
+    pub fn chord_at(&self, beat: f64) -> Option<Chord> {
+        let next = self.regions.partition_point(|r| r.start_beat <= beat);
+        let region = self.regions.get(next.checked_sub(1)?)?;
+        region.contains(beat).then_some(region.chord)
+    }
+
+    /// Add a region, cutting back or removing the parts of others it covers; returns its index
+    pub fn add(&mut self, region: ChordRegion) -> usize {
+        self.clear_span(region.start_beat, region.end_beat());
+        let idx = self.regions.partition_point(|r| r.start_beat < region.start_beat);
+        self.regions.insert(idx, region);
+        idx
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.regions.len() {
+            self.regions.remove(index);
+        }
+    }
+
+    pub fn set_chord(&mut self, index: usize, chord: Chord) {
+        if let Some(region) = self.regions.get_mut(index) {
+            region.chord = chord;
+        }
+    }
+
+    /// Move or resize a region (other regions give way); returns its new index
+    pub fn set_span(&mut self, index: usize, start_beat: f64, length_beats: f64) -> Option<usize> {
+        if index >= self.regions.len() || length_beats <= 0.0 {
+            return None;
+        }
+        let mut region = self.regions.remove(index);
+        region.start_beat = start_beat.max(0.0);
+        region.length_beats = length_beats;
+        Some(self.add(region))
+    }
+
+    /// Clear `start..end`: regions inside it go, regions overlapping it are cut at its edges
+    fn clear_span(&mut self, start: f64, end: f64) {
+        let mut kept = Vec::with_capacity(self.regions.len() + 1);
+        for region in self.regions.drain(..) {
+            if region.end_beat() <= start || region.start_beat >= end {
+                kept.push(region);
+                continue;
+            }
+            if region.start_beat < start {
+                kept.push(ChordRegion { length_beats: start - region.start_beat, ..region });
+            }
+            if region.end_beat() > end {
+                kept.push(ChordRegion { start_beat: end, length_beats: region.end_beat() - end, ..region });
+            }
+        }
+        self.regions = kept;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(start_beat: f64, length_beats: f64, root: u8, quality: ChordQuality) -> ChordRegion {
+        ChordRegion { start_beat, length_beats, chord: Chord::new(root, quality) }
+    }
+
+    #[test]
+    fn test_chord_tones_and_voicing() {
+        let am7 = Chord::new(9, ChordQuality::Minor7);
+        assert_eq!(am7.name(), "Am7");
+        assert!(am7.contains(60) && !am7.contains(62));
+        // Above D4: E, G, A
+        assert_eq!(am7.tones_above(62, 3), [64, 67, 69]);
+        // From C4 the nearest A below is A3
+        assert_eq!(am7.voice_from(60, 0), [57, 60, 64, 67]);
+        assert_eq!(am7.voice_from(60, 1), [60, 64, 67, 69]);
+    }
+
+    #[test]
+    fn test_regions_give_way() {
+        let mut track = ChordTrack::default();
+        track.add(region(0.0, 8.0, 0, ChordQuality::Major));
+        track.add(region(8.0, 8.0, 7, ChordQuality::Dominant7));
+        assert_eq!(track.chord_at(4.0).map(|c| c.name()).as_deref(), Some("C"));
+        assert_eq!(track.chord_at(8.0).map(|c| c.name()).as_deref(), Some("G7"));
+        assert_eq!(track.chord_at(16.0), None);
+
+        // A region dropped across both splits them
+        track.add(region(6.0, 4.0, 9, ChordQuality::Minor));
+        let spans: Vec<(f64, f64)> = track.regions.iter().map(|r| (r.start_beat, r.length_beats)).collect();
+        assert_eq!(spans, [(0.0, 6.0), (6.0, 4.0), (10.0, 6.0)]);
+
+        let idx = track.set_span(1, 0.0, 2.0).unwrap();
+        assert_eq!(idx, 0);
+        assert_eq!(track.chord_at(1.0).map(|c| c.name()).as_deref(), Some("Am"));
+        assert_eq!(track.chord_at(3.0).map(|c| c.name()).as_deref(), Some("C"));
+    }
+}
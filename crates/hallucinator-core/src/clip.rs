@@ -1,7 +1,11 @@
 //! Audio and MIDI clip representations
 
+use std::sync::Arc;
+
 use serde::{Deserialize, Serialize};
 
+use crate::peaks::WaveformPeaks;
+
 /// Unique identifier for clips
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ClipId(pub u64);
@@ -17,6 +21,16 @@ pub struct MidiNote {
     pub start_tick: u64,
     /// Duration in ticks
     pub duration_ticks: u64,
+    /// Chance of playing on each pass, in percent (100 = always)
+    #[serde(default = "full_probability")]
+    pub probability: u8,
+    /// Microtiming: ticks the note plays early (negative) or late, without moving it off the grid
+    #[serde(default)]
+    pub timing_offset: i32,
+}
+
+fn full_probability() -> u8 {
+    100
 }
 
 impl MidiNote {
@@ -26,6 +40,8 @@ impl MidiNote {
             velocity,
             start_tick,
             duration_ticks,
+            probability: 100,
+            timing_offset: 0,
         }
     }
 
@@ -33,6 +49,81 @@ impl MidiNote {
     pub fn end_tick(&self) -> u64 {
         self.start_tick + self.duration_ticks
     }
+
+    /// Tick the note sounds at: the start shifted by the microtiming offset
+    pub fn play_tick(&self) -> u64 {
+        (self.start_tick as i64 + self.timing_offset as i64).max(0) as u64
+    }
+
+    /// Whether the note plays on a given pass. The roll is a hash of `seed` (e.g. the
+    /// note's index and the loop pass), so its note on and note off always agree.
+    pub fn plays(&self, seed: u64) -> bool {
+        if self.probability >= 100 {
+            return true;
+        }
+        // splitmix64 finaliser
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z % 100) < self.probability as u64
+    }
+}
+
+/// Which controller a `MidiControlEvent` changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MidiControl {
+    /// Control change, by controller number (0-127)
+    Cc(u8),
+    /// Pitch bend, 14-bit (8192 = centre)
+    PitchBend,
+    /// Channel aftertouch
+    ChannelPressure,
+    /// Polyphonic aftertouch on one key
+    PolyPressure(u8),
+}
+
+impl MidiControl {
+    /// Largest value: 16383 for pitch bend, 127 for the rest
+    pub fn max_value(&self) -> u16 {
+        match self {
+            Self::PitchBend => 16383,
+            _ => 127,
+        }
+    }
+
+    /// Resting value: centre for pitch bend, zero for the rest
+    pub fn default_value(&self) -> u16 {
+        match self {
+            Self::PitchBend => 8192,
+            _ => 0,
+        }
+    }
+
+    /// A value scaled to 0-1
+    pub fn normalized(&self, value: u16) -> f32 {
+        value.min(self.max_value()) as f32 / self.max_value() as f32
+    }
+}
+
+/// A controller, pitch bend or aftertouch change in a MIDI clip
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MidiControlEvent {
+    pub control: MidiControl,
+    /// Position in ticks (PPQ-based)
+    pub tick: u64,
+    /// Raw MIDI value, 0 to `control.max_value()`
+    pub value: u16,
+}
+
+impl MidiControlEvent {
+    pub fn new(control: MidiControl, tick: u64, value: u16) -> Self {
+        Self {
+            control,
+            tick,
+            value: value.min(control.max_value()),
+        }
+    }
 }
 
 /// MIDI clip containing note events
@@ -49,6 +140,9 @@ pub struct MidiClip {
     pub notes: Vec<MidiNote>,
     /// Pulses per quarter note (default 480)
     pub ppq: u16,
+    /// Controller, pitch bend and aftertouch changes sorted by tick
+    #[serde(default)]
+    pub controls: Vec<MidiControlEvent>,
 }
 
 impl MidiClip {
@@ -60,6 +154,7 @@ impl MidiClip {
             name: String::new(),
             notes: Vec::new(),
             ppq: 480,
+            controls: Vec::new(),
         }
     }
 
@@ -84,6 +179,61 @@ impl MidiClip {
         }
         None
     }
+
+    /// Add a controller change, keeping changes sorted by tick
+    pub fn add_control(&mut self, event: MidiControlEvent) {
+        let idx = self.controls
+            .iter()
+            .position(|c| c.tick > event.tick)
+            .unwrap_or(self.controls.len());
+        self.controls.insert(idx, event);
+    }
+
+    /// Remove controller change at index
+    pub fn remove_control(&mut self, index: usize) -> Option<MidiControlEvent> {
+        if index < self.controls.len() {
+            return Some(self.controls.remove(index));
+        }
+        None
+    }
+
+    /// Value of a controller at a tick: the last change at or before it, else the resting value
+    pub fn control_value_at(&self, control: MidiControl, tick: u64) -> u16 {
+        self.controls
+            .iter()
+            .take_while(|c| c.tick <= tick)
+            .filter(|c| c.control == control)
+            .last()
+            .map_or(control.default_value(), |c| c.value)
+    }
+
+    /// One clip spanning `start_sample..end_sample` with the notes and controller changes of
+    /// `clips` at the same places on the timeline, named after the first of them; events past
+    /// the end of their clip are left out, as they don't play
+    pub fn consolidate(id: ClipId, clips: &[&MidiClip], start_sample: u64, end_sample: u64, samples_per_beat: f64) -> Self {
+        let mut merged = Self::new(id, end_sample.saturating_sub(start_sample));
+        merged.start_sample = start_sample;
+        merged.name = clips.first().map(|c| c.name.clone()).unwrap_or_default();
+
+        let ppq = merged.ppq as f64;
+        for clip in clips {
+            let scale = ppq / clip.ppq as f64;
+            let offset = clip.start_sample.saturating_sub(start_sample) as f64 / samples_per_beat * ppq;
+            let length = (clip.length_samples as f64 / samples_per_beat * clip.ppq as f64) as u64;
+            let place = |tick: u64| (offset + tick as f64 * scale).round() as u64;
+            for note in clip.notes.iter().filter(|n| n.start_tick < length) {
+                merged.add_note(MidiNote {
+                    start_tick: place(note.start_tick),
+                    duration_ticks: ((note.duration_ticks as f64 * scale).round() as u64).max(1),
+                    ..*note
+                });
+            }
+            for event in clip.controls.iter().filter(|c| c.tick < length) {
+                merged.add_control(MidiControlEvent { tick: place(event.tick), ..*event });
+            }
+        }
+        merged
+    }
 }
 
 /// Audio clip on a track
@@ -94,9 +244,10 @@ pub struct AudioClip {
     pub start_sample: u64,
     /// Length in samples
     pub length_samples: u64,
-    /// Source audio data (interleaved f32 samples)
+    /// Source audio data (interleaved f32 samples), shared by copies of the clip; saved
+    /// projects keep it in a media file beside the timeline
     #[serde(skip)]
-    pub samples: Vec<f32>,
+    pub samples: Arc<Vec<f32>>,
     /// Sample rate of the audio data
     pub sample_rate: u32,
     /// Number of channels
@@ -105,6 +256,12 @@ pub struct AudioClip {
     pub name: String,
     /// Gain multiplier (1.0 = unity)
     pub gain: f32,
+    /// Frames of the source skipped before the clip starts (trimmed off its start)
+    #[serde(default)]
+    pub source_offset: u64,
+    /// Overview of `samples` for drawing; None until built or loaded from a peak file
+    #[serde(skip)]
+    pub peaks: Option<Arc<WaveformPeaks>>,
 }
 
 impl AudioClip {
@@ -114,14 +271,21 @@ impl AudioClip {
             id,
             start_sample: 0,
             length_samples,
-            samples,
+            samples: Arc::new(samples),
             sample_rate,
             channels,
             name: String::new(),
             gain: 1.0,
+            source_offset: 0,
+            peaks: None,
         }
     }
 
+    /// Summarise `samples` for drawing, replacing any peaks the clip had
+    pub fn build_peaks(&mut self) {
+        self.peaks = Some(Arc::new(WaveformPeaks::from_samples(&self.samples, self.channels as usize)));
+    }
+
     /// End position in samples
     pub fn end_sample(&self) -> u64 {
         self.start_sample + self.length_samples
@@ -132,6 +296,55 @@ impl AudioClip {
         self.length_samples as f64 / self.sample_rate as f64
     }
 
+    /// Frames of source audio
+    pub fn source_frames(&self) -> u64 {
+        self.samples.len() as u64 / self.channels.max(1) as u64
+    }
+
+    /// The interleaved samples that play, from the trimmed start to the clip's end
+    pub fn audible_samples(&self) -> &[f32] {
+        let channels = self.channels.max(1) as usize;
+        let start = (self.source_offset as usize * channels).min(self.samples.len());
+        let end = ((self.source_offset + self.length_samples) as usize * channels).min(self.samples.len());
+        &self.samples[start..end]
+    }
+
+    /// Move the clip's edges on the timeline with its audio staying in place: trimming the
+    /// start skips source audio and dragging it back out restores it, within the source
+    pub fn set_bounds(&mut self, start_sample: u64, end_sample: u64) {
+        let source_start = self.start_sample as i64 - self.source_offset as i64;
+        let source_end = source_start + self.source_frames() as i64;
+        let start = (start_sample as i64).max(source_start).max(0).min(source_end - 1);
+        let end = (end_sample as i64).min(source_end).max(start + 1);
+        self.source_offset = (start - source_start) as u64;
+        self.start_sample = start as u64;
+        self.length_samples = (end - start) as u64;
+    }
+
+    /// Timeline position within `radius` frames of `timeline_sample`, and nearest to it,
+    /// where the clip's source audio crosses zero, for edits without a click
+    pub fn zero_crossing_near(&self, timeline_sample: u64, radius: u64) -> Option<u64> {
+        let channels = self.channels.max(1) as usize;
+        let source_start = self.start_sample as i64 - self.source_offset as i64;
+        let frames = self.source_frames() as i64;
+        let mono = |frame: i64| {
+            let frame = &self.samples[frame as usize * channels..(frame as usize + 1) * channels];
+            frame.iter().sum::<f32>()
+        };
+        // A crossing sits between a frame and the one before it, or on a silent frame
+        let crosses = |frame: i64| {
+            frame > 0 && frame < frames && {
+                let (before, at) = (mono(frame - 1), mono(frame));
+                at == 0.0 || (before < 0.0) != (at < 0.0)
+            }
+        };
+        let target = timeline_sample as i64 - source_start;
+        (0..=radius as i64)
+            .flat_map(|d| [target - d, target + d])
+            .find(|&frame| crosses(frame))
+            .map(|frame| (frame + source_start).max(0) as u64)
+    }
+
     /// Get sample at timeline position (returns mono sum if stereo)
     pub fn sample_at(&self, timeline_sample: u64) -> Option<f32> {
         let clip_offset = timeline_sample.checked_sub(self.start_sample)?;
@@ -140,7 +353,7 @@ impl AudioClip {
             return None;
         }
 
-        let frame_idx = clip_offset as usize * self.channels as usize;
+        let frame_idx = (clip_offset + self.source_offset) as usize * self.channels as usize;
 
         if frame_idx >= self.samples.len() {
             return None;
@@ -153,4 +366,67 @@ impl AudioClip {
             .sum();
         Some(sum / self.channels as f32 * self.gain)
     }
+
+    /// Get the (left, right) frame at timeline position; mono clips play on both sides
+    pub fn frame_at(&self, timeline_sample: u64) -> Option<(f32, f32)> {
+        let clip_offset = timeline_sample.checked_sub(self.start_sample)?;
+        if clip_offset >= self.length_samples {
+            return None;
+        }
+
+        let channels = self.channels as usize;
+        let frame_idx = (clip_offset + self.source_offset) as usize;
+        let frame = self.samples.get(frame_idx * channels..(frame_idx + 1) * channels)?;
+        let left = frame[0] * self.gain;
+        let right = frame.get(1).map_or(left, |r| r * self.gain);
+        Some((left, right))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audio_clip_trim_and_zero_crossings() {
+        // Mono ramp from -4 to 5: crosses zero at frame 4
+        let samples: Vec<f32> = (0..10).map(|i| i as f32 - 4.0).collect();
+        let mut clip = AudioClip::new(ClipId(1), samples, 48000, 1);
+        clip.start_sample = 100;
+
+        // Trimming the start skips source audio, which stays in place on the timeline
+        clip.set_bounds(103, 110);
+        assert_eq!((clip.start_sample, clip.length_samples, clip.source_offset), (103, 7, 3));
+        assert_eq!(clip.sample_at(103), Some(-1.0));
+        assert_eq!(clip.audible_samples(), [-1.0, 0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        // Edges stop at the ends of the source
+        clip.set_bounds(90, 120);
+        assert_eq!((clip.start_sample, clip.length_samples, clip.source_offset), (100, 10, 0));
+
+        assert_eq!(clip.zero_crossing_near(106, 3), Some(104));
+        assert_eq!(clip.zero_crossing_near(108, 2), None);
+    }
+
+    #[test]
+    fn test_midi_clip_consolidate() {
+        // 1000 samples per beat: a one-beat clip at 0 and a 96 PPQ one at two beats
+        let mut first = MidiClip::new(ClipId(1), 1000);
+        first.name = "Verse".to_string();
+        first.add_note(MidiNote::new(60, 100, 0, 240));
+        first.add_note(MidiNote::new(62, 100, 480, 240));
+        first.add_control(MidiControlEvent::new(MidiControl::Cc(1), 120, 64));
+        let mut second = MidiClip::new(ClipId(2), 1000);
+        second.start_sample = 2000;
+        second.ppq = 96;
+        second.add_note(MidiNote::new(64, 90, 48, 48));
+
+        let merged = MidiClip::consolidate(ClipId(3), &[&first, &second], 0, 3000, 1000.0);
+        assert_eq!((merged.start_sample, merged.length_samples, merged.name.as_str()), (0, 3000, "Verse"));
+        // The note past the end of the first clip is dropped; the second clip's note is
+        // converted to 480 PPQ two beats in
+        let notes: Vec<_> = merged.notes.iter().map(|n| (n.pitch, n.start_tick, n.duration_ticks)).collect();
+        assert_eq!(notes, [(60, 0, 240), (64, 1200, 240)]);
+        assert_eq!(merged.controls, [MidiControlEvent::new(MidiControl::Cc(1), 120, 64)]);
+    }
 }
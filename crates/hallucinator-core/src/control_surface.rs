@@ -0,0 +1,246 @@
+//! Control surface profiles: hardware buttons, faders and knobs bound to
+//! transport, mixer strips and the selected device's parameters
+
+use serde::{Deserialize, Serialize};
+
+/// Number of mixer strips a surface bank covers
+pub const SURFACE_STRIPS: usize = 8;
+
+/// A physical control, identified by the MIDI message it sends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SurfaceControl {
+    /// Control change (channel 0-15)
+    Cc { channel: u8, controller: u8 },
+    /// Note on/off, used by buttons
+    Note { channel: u8, note: u8 },
+    /// 14-bit pitch bend, used by Mackie Control faders
+    PitchBend { channel: u8 },
+}
+
+impl SurfaceControl {
+    pub fn label(&self) -> String {
+        match self {
+            SurfaceControl::Cc { channel, controller } => format!("Ch{} CC{}", channel + 1, controller),
+            SurfaceControl::Note { channel, note } => format!("Ch{} Note {}", channel + 1, note),
+            SurfaceControl::PitchBend { channel } => format!("Ch{} Pitch Bend", channel + 1),
+        }
+    }
+
+    /// Raw value (7-bit, or 14-bit for pitch bend) scaled to 0-1
+    pub fn normalize(&self, raw: u16) -> f32 {
+        match self {
+            SurfaceControl::PitchBend { .. } => raw.min(16383) as f32 / 16383.0,
+            _ => raw.min(127) as f32 / 127.0,
+        }
+    }
+
+    /// True if a raw value is a button press (note-on, or CC at or above 64)
+    pub fn is_press(&self, raw: u16) -> bool {
+        match self {
+            SurfaceControl::Note { .. } => raw > 0,
+            _ => raw >= 64,
+        }
+    }
+}
+
+/// Mackie-style relative encoder value (bit 6 = counter-clockwise, low bits = steps)
+/// as a fraction of the full range
+pub fn relative_delta(raw: u16) -> f32 {
+    let steps = (raw & 0x3F) as f32;
+    let sign = if raw & 0x40 != 0 { -1.0 } else { 1.0 };
+    sign * steps / 100.0
+}
+
+/// What a control does. Strip indices are relative to the current bank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SurfaceAction {
+    Play,
+    Stop,
+    Record,
+    Rewind,
+    ToggleLoop,
+    PreviousTrack,
+    NextTrack,
+    /// Shift the strips by one bank
+    BankLeft,
+    BankRight,
+    Volume(usize),
+    Pan(usize),
+    Mute(usize),
+    Solo(usize),
+    Arm(usize),
+    /// Select the strip's track
+    Select(usize),
+    /// Nth parameter of the selected device on the selected track
+    DeviceParam(usize),
+}
+
+impl SurfaceAction {
+    /// Every assignable action (per-strip actions for each strip), for pickers
+    pub fn choices() -> Vec<SurfaceAction> {
+        let mut choices = vec![
+            SurfaceAction::Play,
+            SurfaceAction::Stop,
+            SurfaceAction::Record,
+            SurfaceAction::Rewind,
+            SurfaceAction::ToggleLoop,
+            SurfaceAction::PreviousTrack,
+            SurfaceAction::NextTrack,
+            SurfaceAction::BankLeft,
+            SurfaceAction::BankRight,
+        ];
+        let per_strip: [fn(usize) -> SurfaceAction; 7] = [
+            SurfaceAction::Volume,
+            SurfaceAction::Pan,
+            SurfaceAction::Mute,
+            SurfaceAction::Solo,
+            SurfaceAction::Arm,
+            SurfaceAction::Select,
+            SurfaceAction::DeviceParam,
+        ];
+        for make in per_strip {
+            choices.extend((0..SURFACE_STRIPS).map(make));
+        }
+        choices
+    }
+
+    /// Continuous actions take fader/knob values; the rest trigger on press
+    pub fn is_continuous(&self) -> bool {
+        matches!(self, SurfaceAction::Volume(_) | SurfaceAction::Pan(_) | SurfaceAction::DeviceParam(_))
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            SurfaceAction::Play => "Play".to_string(),
+            SurfaceAction::Stop => "Stop".to_string(),
+            SurfaceAction::Record => "Record".to_string(),
+            SurfaceAction::Rewind => "Rewind".to_string(),
+            SurfaceAction::ToggleLoop => "Loop".to_string(),
+            SurfaceAction::PreviousTrack => "Previous Track".to_string(),
+            SurfaceAction::NextTrack => "Next Track".to_string(),
+            SurfaceAction::BankLeft => "Bank Left".to_string(),
+            SurfaceAction::BankRight => "Bank Right".to_string(),
+            SurfaceAction::Volume(s) => format!("Volume {}", s + 1),
+            SurfaceAction::Pan(s) => format!("Pan {}", s + 1),
+            SurfaceAction::Mute(s) => format!("Mute {}", s + 1),
+            SurfaceAction::Solo(s) => format!("Solo {}", s + 1),
+            SurfaceAction::Arm(s) => format!("Arm {}", s + 1),
+            SurfaceAction::Select(s) => format!("Select {}", s + 1),
+            SurfaceAction::DeviceParam(p) => format!("Device Param {}", p + 1),
+        }
+    }
+}
+
+/// One control bound to one action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurfaceBinding {
+    pub control: SurfaceControl,
+    pub action: SurfaceAction,
+    /// Endless encoder sending relative steps (Mackie V-Pot style)
+    #[serde(default)]
+    pub relative: bool,
+}
+
+impl SurfaceBinding {
+    pub fn new(control: SurfaceControl, action: SurfaceAction) -> Self {
+        Self { control, action, relative: false }
+    }
+
+    pub fn relative(mut self) -> Self {
+        self.relative = true;
+        self
+    }
+}
+
+/// A named set of bindings for one controller
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurfaceProfile {
+    pub name: String,
+    #[serde(default)]
+    pub bindings: Vec<SurfaceBinding>,
+}
+
+impl SurfaceProfile {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), bindings: Vec::new() }
+    }
+
+    /// Generic CC controller laid out like a Korg nanoKONTROL2 in CC mode:
+    /// faders CC 0-7, knobs CC 16-23, solo/mute/arm buttons CC 32/48/64 + strip
+    pub fn generic() -> Self {
+        let cc = |controller| SurfaceControl::Cc { channel: 0, controller };
+        let mut bindings = Vec::new();
+        for strip in 0..SURFACE_STRIPS {
+            let s = strip as u8;
+            bindings.push(SurfaceBinding::new(cc(s), SurfaceAction::Volume(strip)));
+            bindings.push(SurfaceBinding::new(cc(16 + s), SurfaceAction::Pan(strip)));
+            bindings.push(SurfaceBinding::new(cc(32 + s), SurfaceAction::Solo(strip)));
+            bindings.push(SurfaceBinding::new(cc(48 + s), SurfaceAction::Mute(strip)));
+            bindings.push(SurfaceBinding::new(cc(64 + s), SurfaceAction::Arm(strip)));
+        }
+        bindings.extend([
+            SurfaceBinding::new(cc(41), SurfaceAction::Play),
+            SurfaceBinding::new(cc(42), SurfaceAction::Stop),
+            SurfaceBinding::new(cc(43), SurfaceAction::Rewind),
+            SurfaceBinding::new(cc(45), SurfaceAction::Record),
+            SurfaceBinding::new(cc(46), SurfaceAction::ToggleLoop),
+            SurfaceBinding::new(cc(58), SurfaceAction::PreviousTrack),
+            SurfaceBinding::new(cc(59), SurfaceAction::NextTrack),
+            SurfaceBinding::new(cc(61), SurfaceAction::BankLeft),
+            SurfaceBinding::new(cc(62), SurfaceAction::BankRight),
+        ]);
+        Self { name: "Generic CC".to_string(), bindings }
+    }
+
+    /// Basic Mackie Control Universal: pitch-bend faders, V-Pot pan, strip buttons
+    /// and transport (no display or motor fader feedback)
+    pub fn mackie() -> Self {
+        let note = |note| SurfaceControl::Note { channel: 0, note };
+        let mut bindings = Vec::new();
+        for strip in 0..SURFACE_STRIPS {
+            let s = strip as u8;
+            bindings.push(SurfaceBinding::new(SurfaceControl::PitchBend { channel: s }, SurfaceAction::Volume(strip)));
+            bindings.push(
+                SurfaceBinding::new(SurfaceControl::Cc { channel: 0, controller: 16 + s }, SurfaceAction::Pan(strip))
+                    .relative(),
+            );
+            bindings.push(SurfaceBinding::new(note(s), SurfaceAction::Arm(strip)));
+            bindings.push(SurfaceBinding::new(note(8 + s), SurfaceAction::Solo(strip)));
+            bindings.push(SurfaceBinding::new(note(16 + s), SurfaceAction::Mute(strip)));
+            bindings.push(SurfaceBinding::new(note(24 + s), SurfaceAction::Select(strip)));
+        }
+        bindings.extend([
+            SurfaceBinding::new(note(46), SurfaceAction::BankLeft),
+            SurfaceBinding::new(note(47), SurfaceAction::BankRight),
+            SurfaceBinding::new(note(48), SurfaceAction::PreviousTrack),
+            SurfaceBinding::new(note(49), SurfaceAction::NextTrack),
+            SurfaceBinding::new(note(86), SurfaceAction::ToggleLoop),
+            SurfaceBinding::new(note(91), SurfaceAction::Rewind),
+            SurfaceBinding::new(note(93), SurfaceAction::Stop),
+            SurfaceBinding::new(note(94), SurfaceAction::Play),
+            SurfaceBinding::new(note(95), SurfaceAction::Record),
+        ]);
+        Self { name: "Mackie Control".to_string(), bindings }
+    }
+
+    /// Profiles that ship with the app
+    pub fn built_in() -> Vec<SurfaceProfile> {
+        vec![Self::generic(), Self::mackie()]
+    }
+
+    pub fn binding(&self, control: SurfaceControl) -> Option<&SurfaceBinding> {
+        self.bindings.iter().find(|b| b.control == control)
+    }
+
+    /// Assign a control to the binding at `index`, dropping other bindings of that control
+    pub fn bind(&mut self, index: usize, control: SurfaceControl) {
+        let Some(binding) = self.bindings.get_mut(index) else { return };
+        binding.control = control;
+        let mut i = 0;
+        self.bindings.retain(|b| {
+            let keep = i == index || b.control != control;
+            i += 1;
+            keep
+        });
+    }
+}
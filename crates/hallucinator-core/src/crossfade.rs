@@ -0,0 +1,143 @@
+//! Crossfades between overlapping audio clips on a track: equal-power, with an adjustable
+//! length and curve
+
+use std::f32::consts::FRAC_PI_2;
+
+use serde::{Deserialize, Serialize};
+
+use crate::clip::{AudioClip, ClipId};
+
+/// Fade from one audio clip into another where they overlap, centred in the overlap
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Crossfade {
+    /// Clip that starts first and fades out
+    pub out_clip: ClipId,
+    /// Clip that starts inside it and fades in
+    pub in_clip: ClipId,
+    /// Timeline position the fade starts at, in samples
+    pub start: u64,
+    /// Length in samples, at most the overlap
+    pub length: u64,
+    /// Bend of the fade, -1.0 to 1.0: 0 is even, negative holds the outgoing clip longer,
+    /// positive brings the incoming clip in sooner
+    pub curve: f32,
+}
+
+impl Crossfade {
+    pub fn end(&self) -> u64 {
+        self.start + self.length
+    }
+
+    /// (outgoing, incoming) gains at `position` (0.0 to 1.0) through the fade; their squares
+    /// sum to one, so the loudness holds across it
+    pub fn gains_at(&self, position: f32) -> (f32, f32) {
+        let bent = position.clamp(0.0, 1.0).powf(4f32.powf(-self.curve.clamp(-1.0, 1.0)));
+        let angle = bent * FRAC_PI_2;
+        (angle.cos(), angle.sin())
+    }
+
+    /// (outgoing, incoming) gains at a timeline position: the incoming clip is silent before
+    /// the fade and the outgoing clip after it
+    pub fn gains(&self, timeline_sample: u64) -> (f32, f32) {
+        if timeline_sample < self.start {
+            (1.0, 0.0)
+        } else if timeline_sample >= self.end() {
+            (0.0, 1.0)
+        } else {
+            self.gains_at((timeline_sample - self.start) as f32 / self.length as f32)
+        }
+    }
+
+    /// Gain the fade applies to a clip at a timeline position (1.0 for clips not in it)
+    pub fn clip_gain(&self, clip_id: ClipId, timeline_sample: u64) -> f32 {
+        if clip_id == self.out_clip {
+            self.gains(timeline_sample).0
+        } else if clip_id == self.in_clip {
+            self.gains(timeline_sample).1
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Crossfades for every pair of overlapping clips, where the later one runs past the end of
+/// the earlier; the length and curve of a pair's fade in `existing` are kept, its length
+/// limited to the new overlap, and a new fade spans the whole overlap
+pub fn crossfades_for(clips: &[AudioClip], existing: &[Crossfade]) -> Vec<Crossfade> {
+    let mut spans: Vec<(ClipId, u64, u64)> = clips.iter().map(|c| (c.id, c.start_sample, c.end_sample())).collect();
+    spans.sort_by_key(|&(_, start, _)| start);
+
+    let mut crossfades = Vec::new();
+    for (idx, &(out_clip, _, out_end)) in spans.iter().enumerate() {
+        for &(in_clip, in_start, in_end) in &spans[idx + 1..] {
+            if in_start >= out_end {
+                break;
+            }
+            // A clip inside another plays over it without a fade
+            if in_end <= out_end {
+                continue;
+            }
+            let overlap = out_end - in_start;
+            let previous = existing.iter().find(|x| x.out_clip == out_clip && x.in_clip == in_clip);
+            let length = previous.map_or(overlap, |x| x.length.min(overlap)).max(1);
+            crossfades.push(Crossfade {
+                out_clip,
+                in_clip,
+                start: in_start + (overlap - length) / 2,
+                length,
+                curve: previous.map_or(0.0, |x| x.curve),
+            });
+        }
+    }
+    crossfades
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip(id: u64, start: u64, length: usize) -> AudioClip {
+        let mut clip = AudioClip::new(ClipId(id), vec![1.0; length], 48000, 1);
+        clip.start_sample = start;
+        clip
+    }
+
+    #[test]
+    fn test_crossfades_follow_overlaps() {
+        let mut clips = vec![clip(1, 0, 100), clip(2, 60, 100), clip(3, 10, 20)];
+        let fades = crossfades_for(&clips, &[]);
+        // Clip 3 sits inside clip 1; clips 1 and 2 overlap from 60 to 100
+        assert_eq!(fades.len(), 1);
+        assert_eq!((fades[0].out_clip, fades[0].in_clip, fades[0].start, fades[0].length), (ClipId(1), ClipId(2), 60, 40));
+
+        // An edited fade keeps its curve and stays centred, its length limited to the overlap
+        let edited = [Crossfade { length: 20, curve: 0.5, ..fades[0].clone() }];
+        let fades = crossfades_for(&clips, &edited);
+        assert_eq!((fades[0].start, fades[0].length, fades[0].curve), (70, 20, 0.5));
+        clips[1].start_sample = 90;
+        let fades = crossfades_for(&clips, &edited);
+        assert_eq!((fades[0].start, fades[0].length), (90, 10));
+
+        clips[1].start_sample = 100;
+        assert!(crossfades_for(&clips, &edited).is_empty());
+    }
+
+    #[test]
+    fn test_crossfade_equal_power() {
+        let fade = Crossfade { out_clip: ClipId(1), in_clip: ClipId(2), start: 100, length: 100, curve: 0.0 };
+        assert_eq!(fade.clip_gain(ClipId(1), 50), 1.0);
+        assert_eq!(fade.clip_gain(ClipId(2), 50), 0.0);
+        assert_eq!(fade.clip_gain(ClipId(2), 200), 1.0);
+        assert_eq!(fade.clip_gain(ClipId(3), 150), 1.0);
+        for sample in [100, 125, 150, 199] {
+            let (out, inn) = fade.gains(sample);
+            assert!((out * out + inn * inn - 1.0).abs() < 1e-5);
+        }
+        let (out, inn) = fade.gains(150);
+        assert!((out - inn).abs() < 1e-5);
+
+        // Bending the curve brings the incoming clip in sooner
+        let bent = Crossfade { curve: 0.5, ..fade };
+        assert!(bent.gains(150).1 > inn);
+    }
+}
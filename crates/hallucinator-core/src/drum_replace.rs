@@ -0,0 +1,66 @@
+//! Drum replacement: a MIDI clip of the hits in an audio clip
+//!
+//! Hits are the onsets the groove extractor finds, each becoming a note on one pitch with
+//! its velocity from the hit's peak level, so a recorded kit or beatboxing can trigger
+//! drum instruments (808 kick is C1, snare D1, closed hat F#1; Sample Kit pads start at C1).
+
+use crate::clip::{AudioClip, ClipId, MidiClip, MidiNote};
+use crate::groove::detect_onsets;
+
+/// Longest note written for a hit (a 16th); a closer next hit cuts it short
+const MAX_HIT_BEATS: f64 = 0.25;
+
+/// MIDI clip `id` with a `pitch` note per hit of `clip` at `bpm`, covering the same span
+/// of the timeline; None without hits
+pub fn hits_to_midi(clip: &AudioClip, id: ClipId, pitch: u8, bpm: f64) -> Option<MidiClip> {
+    let onsets = detect_onsets(clip);
+    if onsets.is_empty() {
+        return None;
+    }
+    let mut midi = MidiClip::new(id, clip.length_samples);
+    midi.start_sample = clip.start_sample;
+    midi.name = format!("{} hits", clip.name).trim_start().to_string();
+
+    let ticks_per_frame = midi.ppq as f64 * bpm / (60.0 * clip.sample_rate as f64);
+    let ticks: Vec<u64> = onsets.iter().map(|&(frame, _)| (frame as f64 * ticks_per_frame).round() as u64).collect();
+    let max_ticks = (MAX_HIT_BEATS * midi.ppq as f64) as u64;
+    for (i, &(_, strength)) in onsets.iter().enumerate() {
+        let start = ticks[i];
+        let until_next = ticks.get(i + 1).map_or(max_ticks, |&next| next.saturating_sub(start));
+        let velocity = (1.0 + strength * 126.0).round().clamp(1.0, 127.0) as u8;
+        midi.add_note(MidiNote::new(pitch, velocity, start, until_next.clamp(1, max_ticks)));
+    }
+    Some(midi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hits_become_notes() {
+        // A loud click on the beat and a quieter one an 8th later at 120 BPM
+        let sample_rate = 48_000;
+        let mut samples = vec![0.0f32; sample_rate as usize / 2];
+        for (start, level) in [(0usize, 1.0f32), (12_000, 0.25)] {
+            for s in &mut samples[start..start + 480] {
+                *s = level;
+            }
+        }
+        let mut clip = AudioClip::new(ClipId(1), samples, sample_rate, 1);
+        clip.start_sample = 96_000;
+        clip.name = "Beatbox".into();
+
+        let midi = hits_to_midi(&clip, ClipId(2), 36, 120.0).unwrap();
+        assert_eq!(midi.start_sample, 96_000);
+        assert_eq!(midi.length_samples, clip.length_samples);
+        assert_eq!(midi.name, "Beatbox hits");
+        let notes: Vec<(u8, u64, u64)> = midi.notes.iter().map(|n| (n.pitch, n.start_tick, n.duration_ticks)).collect();
+        assert_eq!(notes, [(36, 0, 120), (36, 240, 120)]);
+        assert_eq!(midi.notes[0].velocity, 127);
+        assert!(midi.notes[1].velocity < 80);
+
+        let silent = AudioClip::new(ClipId(3), vec![0.0; 4800], sample_rate, 1);
+        assert!(hits_to_midi(&silent, ClipId(4), 36, 120.0).is_none());
+    }
+}
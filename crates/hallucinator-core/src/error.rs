@@ -12,6 +12,8 @@ pub enum HallucinatorError {
     TrackNotFound(u64),
     #[error("Clip not found: {0}")]
     ClipNotFound(u64),
+    #[error("MIDI file error: {0}")]
+    MidiFile(String),
 }
 
 pub type Result<T> = std::result::Result<T, HallucinatorError>;
@@ -0,0 +1,108 @@
+//! Events timed on a running frame clock and handed out block by block at their frame, so
+//! an event that turns up partway through a block plays a steady delay after it was sent
+//! rather than at whichever block boundary comes next
+
+/// Events waiting for their frame on a clock that advances one block at a time
+pub struct EventSchedule<E> {
+    /// Frame the next block starts at
+    clock: u64,
+    /// Waiting events by frame; events for the same frame keep the order they were pushed in
+    events: Vec<(u64, E)>,
+}
+
+impl<E> EventSchedule<E> {
+    /// An empty schedule with room for `capacity` waiting events before it allocates
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { clock: 0, events: Vec::with_capacity(capacity) }
+    }
+
+    /// Frame the next block starts at
+    pub fn clock(&self) -> u64 {
+        self.clock
+    }
+
+    /// Events still waiting
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Schedule `event` for `frame`; a frame already past plays at the start of the next block
+    pub fn push(&mut self, frame: u64, event: E) {
+        let index = self.events.partition_point(|&(f, _)| f <= frame);
+        self.events.insert(index, (frame, event));
+    }
+
+    /// Drop every waiting event; the clock runs on
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// Hand the events due in the next `frames` frames to `f` with their offset into the
+    /// block, in frame order, then move the clock past the block
+    pub fn next_block(&mut self, frames: usize, mut f: impl FnMut(u32, E)) {
+        let end = self.clock + frames as u64;
+        let due = self.events.partition_point(|&(frame, _)| frame < end);
+        for (frame, event) in self.events.drain(..due) {
+            f(frame.saturating_sub(self.clock) as u32, event);
+        }
+        self.clock = end;
+    }
+}
+
+impl<E> Default for EventSchedule<E> {
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(schedule: &mut EventSchedule<char>, frames: usize) -> Vec<(u32, char)> {
+        let mut out = Vec::new();
+        schedule.next_block(frames, |offset, event| out.push((offset, event)));
+        out
+    }
+
+    #[test]
+    fn test_events_play_at_their_offset() {
+        let mut schedule = EventSchedule::with_capacity(8);
+        schedule.push(300, 'c');
+        schedule.push(10, 'a');
+        schedule.push(10, 'b');
+        schedule.push(700, 'd');
+
+        assert_eq!(block(&mut schedule, 256), vec![(10, 'a'), (10, 'b')]);
+        assert_eq!(schedule.clock(), 256);
+        assert_eq!(block(&mut schedule, 256), vec![(44, 'c')]);
+        assert_eq!(schedule.len(), 1);
+        assert_eq!(block(&mut schedule, 256), vec![(188, 'd')]);
+        assert!(schedule.is_empty());
+    }
+
+    #[test]
+    fn test_late_events_play_at_block_start() {
+        let mut schedule = EventSchedule::with_capacity(4);
+        block(&mut schedule, 512);
+        schedule.push(100, 'a');
+        schedule.push(600, 'b');
+        assert_eq!(block(&mut schedule, 512), vec![(0, 'a'), (88, 'b')]);
+    }
+
+    #[test]
+    fn test_clear_keeps_the_clock() {
+        let mut schedule = EventSchedule::with_capacity(4);
+        schedule.push(10, 'a');
+        schedule.push(600, 'b');
+        block(&mut schedule, 256);
+        schedule.clear();
+        assert!(schedule.is_empty());
+        assert_eq!(schedule.clock(), 256);
+        assert!(block(&mut schedule, 512).is_empty());
+    }
+}
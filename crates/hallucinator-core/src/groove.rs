@@ -0,0 +1,222 @@
+//! Groove templates: per-16th timing and velocity offsets taken from a played part
+//!
+//! A groove is extracted from the notes of a MIDI clip or the onsets of an audio clip,
+//! measured against the straight 16th grid from the start of the clip. Applying it pulls
+//! notes onto the grooved grid, so a programmed part takes on the feel of the played one.
+
+use serde::{Deserialize, Serialize};
+use crate::clip::{AudioClip, MidiClip};
+
+/// Groove resolution: one step per 16th note
+pub const GROOVE_STEPS_PER_BEAT: usize = 4;
+
+/// Longest groove taken from a clip (four bars of 4/4); longer clips repeat on it
+const MAX_GROOVE_STEPS: usize = 64;
+
+/// Onset detection: analysis hop, rise over the recent level that counts as a hit,
+/// quietest hit relative to the loudest part, and shortest gap between hits (seconds)
+const ONSET_HOP_SECS: f64 = 0.005;
+const ONSET_RISE: f32 = 2.0;
+const ONSET_FLOOR: f32 = 0.02;
+const ONSET_MIN_GAP_SECS: f64 = 0.05;
+
+/// Offsets for one 16th step
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct GrooveStep {
+    /// Timing offset as a fraction of a 16th (-0.5 to 0.5, positive = late)
+    pub timing: f32,
+    /// Velocity offset from the part's average
+    pub velocity: f32,
+}
+
+/// A groove template in the project's groove pool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Groove {
+    pub name: String,
+    pub steps: Vec<GrooveStep>,
+}
+
+impl Groove {
+    /// Build a groove from hits as (beat from the clip start, velocity 1-127) over a
+    /// part `length_beats` long; None without hits
+    pub fn from_hits(name: impl Into<String>, hits: &[(f64, f32)], length_beats: f64) -> Option<Self> {
+        if hits.is_empty() {
+            return None;
+        }
+        let len = ((length_beats * GROOVE_STEPS_PER_BEAT as f64).round() as usize).clamp(1, MAX_GROOVE_STEPS);
+        let average = hits.iter().map(|(_, v)| v).sum::<f32>() / hits.len() as f32;
+
+        // (timing sum, velocity sum, hits) per step
+        let mut sums = vec![(0.0f32, 0.0f32, 0u32); len];
+        for &(beat, velocity) in hits {
+            let position = beat.max(0.0) * GROOVE_STEPS_PER_BEAT as f64;
+            let nearest = position.round();
+            let sum = &mut sums[nearest as usize % len];
+            sum.0 += (position - nearest) as f32;
+            sum.1 += velocity - average;
+            sum.2 += 1;
+        }
+        let steps = sums.into_iter()
+            .map(|(timing, velocity, count)| match count {
+                0 => GrooveStep::default(),
+                n => GrooveStep { timing: timing / n as f32, velocity: velocity / n as f32 },
+            })
+            .collect();
+        Some(Self { name: name.into(), steps })
+    }
+
+    /// Groove of a MIDI clip's notes (microtiming included); `length_beats` is the clip length
+    pub fn from_midi_clip(clip: &MidiClip, length_beats: f64) -> Option<Self> {
+        let ppq = clip.ppq as f64;
+        let hits: Vec<(f64, f32)> = clip.notes.iter()
+            .map(|n| ((n.start_tick as f64 + n.timing_offset as f64) / ppq, n.velocity as f32))
+            .collect();
+        Self::from_hits(clip.name.clone(), &hits, length_beats)
+    }
+
+    /// Groove of an audio clip's onsets (their loudness becomes velocity) at `bpm`
+    pub fn from_audio_clip(clip: &AudioClip, bpm: f64) -> Option<Self> {
+        let samples_per_beat = clip.sample_rate as f64 * 60.0 / bpm;
+        let hits: Vec<(f64, f32)> = detect_onsets(clip)
+            .into_iter()
+            .map(|(frame, strength)| (frame as f64 / samples_per_beat, 1.0 + strength * 126.0))
+            .collect();
+        Self::from_hits(clip.name.clone(), &hits, clip.length_samples as f64 / samples_per_beat)
+    }
+
+    pub fn length_beats(&self) -> f64 {
+        self.steps.len() as f64 / GROOVE_STEPS_PER_BEAT as f64
+    }
+
+    /// Step governing the 16th nearest `beat`, and that 16th's straight position in beats
+    fn step_near(&self, beat: f64) -> (GrooveStep, f64) {
+        let nearest = (beat.max(0.0) * GROOVE_STEPS_PER_BEAT as f64).round();
+        let step = self.steps.get(nearest as usize % self.steps.len().max(1)).copied().unwrap_or_default();
+        (step, nearest / GROOVE_STEPS_PER_BEAT as f64)
+    }
+
+    /// Where a grid line falls with the groove: lines on a 16th take its timing, others stay put
+    pub fn shift(&self, beat: f64) -> f64 {
+        let (step, straight) = self.step_near(beat);
+        if (straight - beat).abs() > 1e-6 {
+            return beat;
+        }
+        beat + step.timing as f64 / GROOVE_STEPS_PER_BEAT as f64
+    }
+
+    /// Pull the given notes toward the grooved 16th grid and add the velocity offsets,
+    /// both scaled by `amount` (0-1)
+    pub fn apply(&self, clip: &mut MidiClip, notes: &[usize], amount: f32) {
+        let ppq = clip.ppq as f64;
+        let amount = amount.clamp(0.0, 1.0);
+        for &i in notes {
+            let Some(note) = clip.notes.get_mut(i) else { continue };
+            let beat = note.start_tick as f64 / ppq;
+            let (step, straight) = self.step_near(beat);
+            let target = (straight + step.timing as f64 / GROOVE_STEPS_PER_BEAT as f64) * ppq;
+            let start = note.start_tick as f64 + (target - note.start_tick as f64) * amount as f64;
+            note.start_tick = start.round().max(0.0) as u64;
+            note.velocity = (note.velocity as f32 + step.velocity * amount).round().clamp(1.0, 127.0) as u8;
+        }
+    }
+}
+
+/// Onsets of an audio clip as (frame, strength 0-1): hops whose level jumps well above
+/// the level just before them
+pub(crate) fn detect_onsets(clip: &AudioClip) -> Vec<(u64, f32)> {
+    let channels = clip.channels.max(1) as usize;
+    let hop = ((clip.sample_rate as f64 * ONSET_HOP_SECS) as usize).max(1);
+    let samples = clip.audible_samples();
+    let frames = samples.len() / channels;
+
+    // Mean square of the mono mix per hop
+    let levels: Vec<f32> = (0..frames.div_ceil(hop))
+        .map(|h| {
+            let range = h * hop * channels..((h + 1) * hop).min(frames) * channels;
+            let chunk = &samples[range];
+            let sum: f32 = chunk.chunks(channels)
+                .map(|frame| {
+                    let mono = frame.iter().sum::<f32>() / channels as f32;
+                    mono * mono
+                })
+                .sum();
+            sum / (chunk.len() / channels).max(1) as f32
+        })
+        .collect();
+    let loudest = levels.iter().copied().fold(0.0f32, f32::max);
+    if loudest <= 0.0 {
+        return Vec::new();
+    }
+
+    let min_gap = ((ONSET_MIN_GAP_SECS / ONSET_HOP_SECS) as usize).max(1);
+    let mut onsets = Vec::new();
+    let mut last: Option<usize> = None;
+    for h in 0..levels.len() {
+        let recent = levels[h.saturating_sub(4)..h].iter().sum::<f32>() / h.clamp(1, 4) as f32;
+        let is_onset = levels[h] > loudest * ONSET_FLOOR
+            && levels[h] > recent * ONSET_RISE
+            && last.is_none_or(|l| h - l >= min_gap);
+        if is_onset {
+            // Strength from the peak just after the attack
+            let peak = levels[h..(h + min_gap).min(levels.len())].iter().copied().fold(0.0f32, f32::max);
+            onsets.push(((h * hop) as u64, (peak / loudest).sqrt()));
+            last = Some(h);
+        }
+    }
+    onsets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clip::{ClipId, MidiNote};
+
+    fn note(start_tick: u64, velocity: u8) -> MidiNote {
+        MidiNote { pitch: 60, velocity, start_tick, duration_ticks: 60, probability: 100, timing_offset: 0 }
+    }
+
+    #[test]
+    fn test_extract_and_apply_midi_groove() {
+        // One beat of 16ths at 480 PPQ: the second and fourth late, accents on the beat
+        let mut played = MidiClip::new(ClipId(1), 0);
+        for (tick, velocity) in [(0, 110), (150, 90), (240, 100), (380, 80)] {
+            played.add_note(note(tick, velocity));
+        }
+        let groove = Groove::from_midi_clip(&played, 1.0).unwrap();
+        assert_eq!(groove.steps.len(), 4);
+        assert!((groove.steps[1].timing - 0.25).abs() < 1e-6);
+        assert!((groove.steps[0].velocity - 15.0).abs() < 1e-6);
+        assert!((groove.shift(0.25) - 0.3125).abs() < 1e-9);
+        assert_eq!(groove.shift(0.3), 0.3);
+
+        // Straight 16ths over two beats take the feel in every beat
+        let mut straight = MidiClip::new(ClipId(2), 0);
+        for i in 0..8 {
+            straight.add_note(note(i * 120, 100));
+        }
+        groove.apply(&mut straight, &(0..8).collect::<Vec<_>>(), 1.0);
+        let starts: Vec<u64> = straight.notes.iter().map(|n| n.start_tick).collect();
+        assert_eq!(starts, [0, 150, 240, 380, 480, 630, 720, 860]);
+        assert_eq!(straight.notes[4].velocity, 115);
+    }
+
+    #[test]
+    fn test_audio_onsets_become_hits() {
+        // Clicks on the first two 8ths of a beat at 120 BPM, the second 10 ms late
+        let sample_rate = 48_000;
+        let mut samples = vec![0.0f32; sample_rate as usize / 2];
+        for (start, level) in [(0usize, 1.0f32), (12_480, 0.5)] {
+            for s in &mut samples[start..start + 480] {
+                *s = level;
+            }
+        }
+        let clip = AudioClip::new(ClipId(3), samples, sample_rate, 1);
+        let onsets = detect_onsets(&clip);
+        assert_eq!(onsets.iter().map(|(frame, _)| *frame).collect::<Vec<_>>(), [0, 12_480]);
+
+        let groove = Groove::from_audio_clip(&clip, 120.0).unwrap();
+        assert_eq!(groove.steps.len(), 4);
+        assert!((groove.steps[2].timing - 0.08).abs() < 1e-4);
+        assert!(groove.steps[0].velocity > 0.0 && groove.steps[2].velocity < 0.0);
+    }
+}
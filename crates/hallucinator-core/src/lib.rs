@@ -1,25 +1,53 @@
 //! hallucinator-core: Domain types for the hallucinator DAW
 
 pub mod algorithms;
+pub mod chord_track;
+pub mod automation;
 mod clip;
+pub mod control_surface;
+pub mod crossfade;
+pub mod drum_replace;
+pub mod event_schedule;
 mod error;
+pub mod groove;
+pub mod macro_knobs;
+pub mod midi_file;
 pub mod midi_fx;
+pub mod midi_map;
+pub mod modulation;
+pub mod pan;
 pub mod pattern;
+pub mod peaks;
+pub mod snapshot;
 pub mod song;
 mod timeline;
 mod track;
 mod transport;
 
 pub use algorithms::{
-    euclidean_rhythm, quantize_to_scale, scale_notes,
+    euclidean_rhythm, probability_rhythm, quantize_to_scale, scale_notes,
     ChordGenerator, ChordQuality, ScaleMode, Voicing,
 };
-pub use clip::{AudioClip, ClipId, MidiClip, MidiNote};
+pub use automation::{Automation, AutomationLane, AutomationPoint, CurveShape};
+pub use chord_track::{Chord, ChordRegion, ChordTrack};
+pub use clip::{AudioClip, ClipId, MidiClip, MidiControl, MidiControlEvent, MidiNote};
+pub use control_surface::{SurfaceAction, SurfaceBinding, SurfaceControl, SurfaceProfile, SURFACE_STRIPS};
+pub use crossfade::Crossfade;
+pub use drum_replace::hits_to_midi;
 pub use error::{HallucinatorError, Result};
+pub use event_schedule::EventSchedule;
+pub use groove::{Groove, GrooveStep, GROOVE_STEPS_PER_BEAT};
+pub use macro_knobs::{MacroBank, MacroCurve, MacroKnob, MacroMapping, MACRO_COUNT};
+pub use midi_file::{read_midi_file, MidiFileData};
 pub use midi_fx::{MidiEffect, MidiEvent, MidiFx, MidiFxChain, MidiFxParam};
-pub use midi_fx::{TransposeFx, QuantizeFx, SwingFx, HumanizeFx, ChanceFx, EchoFx, ArpeggiatorFx, HarmonizerFx};
-pub use pattern::{PatternBank, PatternSlot};
+pub use midi_fx::{TransposeFx, QuantizeFx, SwingFx, HumanizeFx, ChanceFx, EchoFx, ArpeggiatorFx, HarmonizerFx, NoteFilterFx, ScaleForceFx, StrumFx, RatchetFx};
+pub use midi_map::{CcMode, MidiMap, MidiMapping, ParamOwner};
+pub use modulation::{LfoShape, ModDestination, ModSource, ModulationMatrix, LFO_RATES};
+pub use pan::{pan_stereo, PanLaw, PanMode};
+pub use peaks::{WaveformPeaks, PEAK_BLOCK};
+pub use pattern::{DrumPatternData, DrumRowData, DrumStepData, PatternBank, PatternSlot, StepLocks};
+pub use snapshot::{DeviceState, MixSnapshot, SnapshotBank, SnapshotMorph, TrackState, SNAPSHOT_SLOTS};
 pub use song::{PlaybackMode, SongArrangement, SongSection};
 pub use timeline::Timeline;
-pub use track::{Track, TrackId, TrackKind};
+pub use track::{moved_index, DeviceTarget, InstrumentOutput, PluginState, Track, TrackId, TrackInput, TrackKind, TrackMonitor, TrackOutput};
 pub use transport::{Transport, TransportState};
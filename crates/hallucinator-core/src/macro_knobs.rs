@@ -0,0 +1,160 @@
+//! Per-track macro knobs, each driving several device parameters at once
+
+use serde::{Deserialize, Serialize};
+use crate::track::DeviceTarget;
+
+/// Number of macro knobs per track
+pub const MACRO_COUNT: usize = 8;
+
+/// Response curve from macro position to mapped parameter position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MacroCurve {
+    #[default]
+    Linear,
+    /// Slow start, fast end (x²)
+    Exponential,
+    /// Fast start, slow end (√x)
+    Logarithmic,
+    /// Smoothstep ease-in/ease-out
+    SCurve,
+}
+
+impl MacroCurve {
+    pub const ALL: [MacroCurve; 4] = [
+        MacroCurve::Linear,
+        MacroCurve::Exponential,
+        MacroCurve::Logarithmic,
+        MacroCurve::SCurve,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MacroCurve::Linear => "Linear",
+            MacroCurve::Exponential => "Exp",
+            MacroCurve::Logarithmic => "Log",
+            MacroCurve::SCurve => "S-Curve",
+        }
+    }
+
+    /// Shape a 0-1 position
+    pub fn apply(&self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            MacroCurve::Linear => x,
+            MacroCurve::Exponential => x * x,
+            MacroCurve::Logarithmic => x.sqrt(),
+            MacroCurve::SCurve => x * x * (3.0 - 2.0 * x),
+        }
+    }
+}
+
+/// One parameter driven by a macro knob
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MacroMapping {
+    pub target: DeviceTarget,
+    /// Parameter name as reported by the device
+    pub param: String,
+    /// Parameter value at macro position 0
+    pub min: f32,
+    /// Parameter value at macro position 1 (may be below `min` to invert)
+    pub max: f32,
+    #[serde(default)]
+    pub curve: MacroCurve,
+}
+
+impl MacroMapping {
+    pub fn new(target: DeviceTarget, param: impl Into<String>, min: f32, max: f32) -> Self {
+        Self {
+            target,
+            param: param.into(),
+            min,
+            max,
+            curve: MacroCurve::Linear,
+        }
+    }
+
+    /// Parameter value for a 0-1 macro position
+    pub fn value_at(&self, position: f32) -> f32 {
+        self.min + (self.max - self.min) * self.curve.apply(position)
+    }
+}
+
+/// A single macro knob
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MacroKnob {
+    /// User label (empty = "Macro N")
+    #[serde(default)]
+    pub name: String,
+    /// Knob position (0.0 to 1.0)
+    #[serde(default)]
+    pub value: f32,
+    #[serde(default)]
+    pub mappings: Vec<MacroMapping>,
+}
+
+/// The macro knobs of one track
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MacroBank {
+    pub knobs: [MacroKnob; MACRO_COUNT],
+}
+
+impl MacroBank {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Display label for a knob
+    pub fn label(&self, index: usize) -> String {
+        match self.knobs.get(index) {
+            Some(knob) if !knob.name.is_empty() => knob.name.clone(),
+            _ => format!("Macro {}", index + 1),
+        }
+    }
+
+    /// Move a knob and return the (target, param, value) updates it drives
+    pub fn set_value(&mut self, index: usize, value: f32) -> Vec<(DeviceTarget, String, f32)> {
+        let Some(knob) = self.knobs.get_mut(index) else {
+            return Vec::new();
+        };
+        knob.value = value.clamp(0.0, 1.0);
+        knob.mappings
+            .iter()
+            .map(|m| (m.target, m.param.clone(), m.value_at(knob.value)))
+            .collect()
+    }
+
+    /// Map a parameter to a knob, replacing any existing mapping of the same parameter
+    pub fn add_mapping(&mut self, index: usize, mapping: MacroMapping) {
+        let Some(knob) = self.knobs.get_mut(index) else { return };
+        knob.mappings.retain(|m| m.target != mapping.target || m.param != mapping.param);
+        knob.mappings.push(mapping);
+    }
+
+    pub fn remove_mapping(&mut self, index: usize, mapping_idx: usize) {
+        let Some(knob) = self.knobs.get_mut(index) else { return };
+        if mapping_idx < knob.mappings.len() {
+            knob.mappings.remove(mapping_idx);
+        }
+    }
+
+    /// Fix up mappings after the effect at `removed` is deleted from the chain
+    pub fn effect_removed(&mut self, removed: usize) {
+        for knob in &mut self.knobs {
+            knob.mappings.retain(|m| m.target != DeviceTarget::Effect(removed));
+            for mapping in &mut knob.mappings {
+                if let DeviceTarget::Effect(idx) = &mut mapping.target
+                    && *idx > removed
+                {
+                    *idx -= 1;
+                }
+            }
+        }
+    }
+
+    /// Fix up mappings after the effect at `from` is moved to `to`
+    pub fn effect_moved(&mut self, from: usize, to: usize) {
+        for mapping in self.knobs.iter_mut().flat_map(|k| &mut k.mappings) {
+            mapping.target.effect_moved(from, to);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Standard MIDI File (.mid) reading
+//!
+//! Formats 0 and 1 are read with every track and channel merged into one set of notes
+//! and controller changes, which is what a dropped or imported file becomes: one clip.
+
+use crate::clip::{MidiControl, MidiControlEvent, MidiNote};
+use crate::error::{HallucinatorError, Result};
+
+/// Notes and controller changes read from a MIDI file
+#[derive(Debug, Clone, Default)]
+pub struct MidiFileData {
+    /// Pulses per quarter note of the file's ticks
+    pub ppq: u16,
+    /// Notes sorted by start tick
+    pub notes: Vec<MidiNote>,
+    /// Controller, pitch bend and aftertouch changes sorted by tick
+    pub controls: Vec<MidiControlEvent>,
+    /// Tick of the last event in any track
+    pub length_ticks: u64,
+    /// First tempo in the file, if it sets one
+    pub bpm: Option<f64>,
+}
+
+fn error(msg: impl Into<String>) -> HallucinatorError {
+    HallucinatorError::MidiFile(msg.into())
+}
+
+/// Byte reader over one chunk
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.data.len())
+            .ok_or_else(|| error("unexpected end of file"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Variable-length quantity (7 bits per byte, high bit set on all but the last)
+    fn vlq(&mut self) -> Result<u32> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = self.u8()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(error("variable-length value longer than 4 bytes"))
+    }
+
+    /// Next chunk's type and contents
+    fn chunk(&mut self) -> Result<([u8; 4], &'a [u8])> {
+        let id = self.bytes(4)?;
+        let len = self.u32()? as usize;
+        // Some writers get the last chunk's length wrong; read what is there
+        let len = len.min(self.data.len() - self.pos);
+        Ok(([id[0], id[1], id[2], id[3]], self.bytes(len)?))
+    }
+}
+
+/// Parse a Standard MIDI File
+pub fn read_midi_file(data: &[u8]) -> Result<MidiFileData> {
+    let mut file = Reader::new(data);
+    let (id, header) = file.chunk()?;
+    if &id != b"MThd" {
+        return Err(error("not a MIDI file"));
+    }
+    let mut header = Reader::new(header);
+    let format = header.u16()?;
+    let _track_count = header.u16()?;
+    let division = header.u16()?;
+    if format > 1 {
+        return Err(error(format!("format {format} files are not supported")));
+    }
+    if division & 0x8000 != 0 || division == 0 {
+        return Err(error("SMPTE-timed files are not supported"));
+    }
+
+    let mut out = MidiFileData { ppq: division, ..Default::default() };
+    while !file.is_empty() {
+        let (id, track) = file.chunk()?;
+        // Unknown chunk types are skipped, as the format requires
+        if &id == b"MTrk" {
+            read_track(track, &mut out)?;
+        }
+    }
+
+    out.notes.sort_by_key(|n| n.start_tick);
+    out.controls.sort_by_key(|c| c.tick);
+    Ok(out)
+}
+
+fn read_track(data: &[u8], out: &mut MidiFileData) -> Result<()> {
+    let mut track = Reader::new(data);
+    let mut tick = 0u64;
+    let mut running_status: Option<u8> = None;
+    // Start tick and velocity of sounding notes, by channel and key
+    let mut sounding: Vec<Option<(u64, u8)>> = vec![None; 16 * 128];
+
+    while !track.is_empty() {
+        tick += track.vlq()? as u64;
+        let mut status = track.u8()?;
+
+        match status {
+            0xFF => {
+                let kind = track.u8()?;
+                let len = track.vlq()? as usize;
+                let body = track.bytes(len)?;
+                match kind {
+                    0x2F => break,
+                    0x51 if len == 3 && out.bpm.is_none() => {
+                        let micros = u32::from_be_bytes([0, body[0], body[1], body[2]]);
+                        if micros > 0 {
+                            out.bpm = Some(60_000_000.0 / micros as f64);
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            0xF0 | 0xF7 => {
+                let len = track.vlq()? as usize;
+                track.bytes(len)?;
+                continue;
+            }
+            _ => {}
+        }
+
+        // A data byte where a status should be reuses the previous channel status
+        let first = if status & 0x80 == 0 {
+            let data = status;
+            status = running_status.ok_or_else(|| error("data byte without a status"))?;
+            data
+        } else {
+            running_status = Some(status);
+            track.u8()?
+        };
+        let channel = (status & 0x0F) as usize;
+        let has_second = !matches!(status & 0xF0, 0xC0 | 0xD0);
+        let second = if has_second { track.u8()? } else { 0 };
+
+        match status & 0xF0 {
+            0x90 if second > 0 => {
+                let slot = &mut sounding[channel * 128 + (first & 0x7F) as usize];
+                // Retriggering a sounding key ends the previous note
+                if let Some((start, velocity)) = slot.take() {
+                    out.notes.push(MidiNote::new(first, velocity, start, tick - start));
+                }
+                *slot = Some((tick, second));
+            }
+            0x80 | 0x90 => {
+                if let Some((start, velocity)) = sounding[channel * 128 + (first & 0x7F) as usize].take() {
+                    out.notes.push(MidiNote::new(first, velocity, start, tick - start));
+                }
+            }
+            0xA0 => out.controls.push(MidiControlEvent::new(MidiControl::PolyPressure(first), tick, second as u16)),
+            0xB0 => out.controls.push(MidiControlEvent::new(MidiControl::Cc(first), tick, second as u16)),
+            0xD0 => out.controls.push(MidiControlEvent::new(MidiControl::ChannelPressure, tick, first as u16)),
+            0xE0 => {
+                let value = (first as u16 & 0x7F) | ((second as u16 & 0x7F) << 7);
+                out.controls.push(MidiControlEvent::new(MidiControl::PitchBend, tick, value));
+            }
+            // Program changes have nothing to map to
+            _ => {}
+        }
+    }
+
+    // Notes still held when the track ends run to its end
+    for (idx, slot) in sounding.iter().enumerate() {
+        if let Some((start, velocity)) = slot {
+            out.notes.push(MidiNote::new((idx % 128) as u8, *velocity, *start, tick - start));
+        }
+    }
+    out.length_ticks = out.length_ticks.max(tick);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn smf(division: u16, track: &[u8]) -> Vec<u8> {
+        let mut data = b"MThd".to_vec();
+        data.extend_from_slice(&6u32.to_be_bytes());
+        data.extend_from_slice(&[0, 0, 0, 1]);
+        data.extend_from_slice(&division.to_be_bytes());
+        data.extend_from_slice(b"MTrk");
+        data.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        data.extend_from_slice(track);
+        data
+    }
+
+    #[test]
+    fn test_read_notes_with_running_status() {
+        let track = [
+            0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // 120 bpm
+            0x00, 0x90, 60, 100, // C4 on
+            0x00, 64, 90, // E4 on (running status)
+            0x83, 0x60, 60, 0, // C4 off after 480 ticks (velocity 0)
+            0x00, 0x80, 64, 0, // E4 off
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let file = read_midi_file(&smf(480, &track)).unwrap();
+        assert_eq!(file.ppq, 480);
+        assert_eq!(file.bpm, Some(120.0));
+        assert_eq!(file.length_ticks, 480);
+        let notes: Vec<_> = file.notes.iter().map(|n| (n.pitch, n.velocity, n.start_tick, n.duration_ticks)).collect();
+        assert_eq!(notes, vec![(60, 100, 0, 480), (64, 90, 0, 480)]);
+    }
+
+    #[test]
+    fn test_read_controls_and_rejects() {
+        let track = [0x00, 0xB0, 64, 127, 0x10, 0xE0, 0x00, 0x40, 0x00, 0xFF, 0x2F, 0x00];
+        let file = read_midi_file(&smf(96, &track)).unwrap();
+        assert_eq!(file.controls, vec![
+            MidiControlEvent::new(MidiControl::Cc(64), 0, 127),
+            MidiControlEvent::new(MidiControl::PitchBend, 16, 8192),
+        ]);
+        assert!(read_midi_file(b"RIFF0000").is_err());
+        assert!(read_midi_file(&smf(0xE728, &track)).is_err());
+    }
+}
@@ -3,6 +3,7 @@
 use serde::{Deserialize, Serialize};
 
 use super::{impl_midi_fx_boilerplate, MidiEvent, MidiFxParam};
+use crate::chord_track::Chord;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ArpMode {
@@ -13,31 +14,110 @@ pub enum ArpMode {
     Order,
 }
 
+/// Step lengths selectable by the `division` parameter: (label, length in beats)
+pub const ARP_DIVISIONS: [(&str, f64); 8] = [
+    ("1/4", 1.0),
+    ("1/4T", 2.0 / 3.0),
+    ("1/8", 0.5),
+    ("1/8T", 1.0 / 3.0),
+    ("1/16", 0.25),
+    ("1/16T", 1.0 / 6.0),
+    ("1/32", 0.125),
+    ("1/32T", 1.0 / 12.0),
+];
+
+/// Longest custom step pattern
+pub const ARP_MAX_STEPS: usize = 16;
+
+fn default_pattern() -> u16 {
+    u16::MAX
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArpeggiatorFx {
     params: Vec<MidiFxParam>,
     bypass: bool,
     held_notes: Vec<u8>,
     rng_state: u64,
+    /// Step pattern, bit N = step N plays (a cleared bit is a rest)
+    #[serde(default = "default_pattern")]
+    pattern: u16,
+    /// Keys physically down; with latch on, `held_notes` outlives them
+    #[serde(skip)]
+    pressed: Vec<u8>,
+    /// Length of the current audio block, set by the chain before `process`
+    #[serde(skip)]
+    block_frames: u32,
+    /// Samples from the start of the next block to the next step
+    #[serde(skip)]
+    until_step: f64,
+    /// Steps played since the arp started (position in the pattern)
+    #[serde(skip)]
+    step_count: usize,
+    /// Notes played since the arp started (position in the note sequence)
+    #[serde(skip)]
+    note_index: usize,
+    /// Note currently sounding and the samples until its note-off
+    #[serde(skip)]
+    sounding: Option<(u8, f64)>,
+    #[serde(skip)]
+    channel: u8,
+    #[serde(skip)]
+    velocity: u8,
+    /// Chord track chord at the current block, set by the chain before `process`
+    #[serde(skip)]
+    chord: Option<Chord>,
 }
 
 impl Default for ArpeggiatorFx {
     fn default() -> Self {
         Self {
-            params: vec![
-                MidiFxParam::new("mode", 0.0, 0.0, 4.0),
-                MidiFxParam::new("rate", 8.0, 1.0, 32.0),
-                MidiFxParam::new("octaves", 1.0, 1.0, 4.0),
-                MidiFxParam::new("gate", 80.0, 10.0, 100.0),
-            ],
+            params: Self::default_params(),
             bypass: false,
             held_notes: Vec::new(),
             rng_state: 99999,
+            pattern: default_pattern(),
+            pressed: Vec::new(),
+            block_frames: 0,
+            until_step: 0.0,
+            step_count: 0,
+            note_index: 0,
+            sounding: None,
+            channel: 0,
+            velocity: 100,
+            chord: None,
         }
     }
 }
 
 impl ArpeggiatorFx {
+    fn default_params() -> Vec<MidiFxParam> {
+        vec![
+            MidiFxParam::new("mode", 0.0, 0.0, 4.0),
+            MidiFxParam::new("division", 4.0, 0.0, (ARP_DIVISIONS.len() - 1) as f32),
+            MidiFxParam::new("octaves", 1.0, 1.0, 4.0),
+            MidiFxParam::new("gate", 80.0, 10.0, 100.0),
+            MidiFxParam::new("latch", 0.0, 0.0, 1.0),
+            MidiFxParam::new("steps", 8.0, 1.0, ARP_MAX_STEPS as f32),
+            // 1 = play the chord track's chord from the lowest held note instead of the held notes
+            MidiFxParam::new("chords", 0.0, 0.0, 1.0),
+        ]
+    }
+
+    /// Bring params saved by an older version up to the current set, keeping matching values
+    fn upgrade_params(&mut self) {
+        if self.params.len() == 7 {
+            return;
+        }
+        let mut params = Self::default_params();
+        for param in &mut params {
+            if let Some(old) = self.params.iter().find(|p| p.name == param.name) {
+                param.value = old.value.clamp(param.min, param.max);
+            }
+        }
+        self.params = params;
+    }
+
     fn get_mode(&self) -> ArpMode {
         match self.params[0].value as u8 {
             0 => ArpMode::Up,
@@ -48,56 +128,79 @@ impl ArpeggiatorFx {
         }
     }
 
-    fn next_random(&mut self) -> usize {
-        self.rng_state = self.rng_state.wrapping_mul(6364136223846793005).wrapping_add(1);
-        (self.rng_state >> 33) as usize
+    /// Label of the step length, e.g. "1/16T"
+    pub fn division_label(&self) -> &'static str {
+        let index = self.params.get(1).map_or(4, |p| p.value as usize);
+        ARP_DIVISIONS[index.min(ARP_DIVISIONS.len() - 1)].0
     }
 
-    fn process_impl(&mut self, events: Vec<MidiEvent>, sample_rate: f32, bpm: f64) -> Vec<MidiEvent> {
-        for event in &events {
-            if event.is_note_on {
-                if !self.held_notes.contains(&event.pitch) {
-                    self.held_notes.push(event.pitch);
-                }
-            } else {
-                self.held_notes.retain(|&p| p != event.pitch);
-            }
+    fn step_beats(&self) -> f64 {
+        ARP_DIVISIONS[(self.params[1].value as usize).min(ARP_DIVISIONS.len() - 1)].1
+    }
+
+    fn latched(&self) -> bool {
+        self.params[4].value >= 0.5
+    }
+
+    /// Number of steps in the custom pattern
+    pub fn pattern_len(&self) -> usize {
+        self.params.get(5).map_or(ARP_MAX_STEPS, |p| p.value as usize).clamp(1, ARP_MAX_STEPS)
+    }
+
+    pub fn pattern_step(&self, step: usize) -> bool {
+        step < ARP_MAX_STEPS && self.pattern & (1 << step) != 0
+    }
+
+    pub fn set_pattern_step(&mut self, step: usize, on: bool) {
+        if step >= ARP_MAX_STEPS {
+            return;
+        }
+        if on {
+            self.pattern |= 1 << step;
+        } else {
+            self.pattern &= !(1 << step);
         }
+    }
 
-        if self.held_notes.is_empty() { return vec![]; }
+    /// Chord track chord for the coming block
+    pub fn set_chord(&mut self, chord: Option<Chord>) {
+        self.chord = chord;
+    }
 
-        let rate = self.params[1].value;
-        let octaves = self.params[2].value as u8;
-        let gate = self.params[3].value / 100.0;
+    fn follows_chords(&self) -> bool {
+        self.params[6].value >= 0.5
+    }
 
-        let samples_per_beat = (sample_rate as f64 * 60.0 / bpm) as u32;
-        let note_samples = samples_per_beat * 4 / rate as u32;
-        let gate_samples = (note_samples as f32 * gate) as u32;
+    /// Audio block length the next `process` call covers
+    pub fn set_block_frames(&mut self, frames: u32) {
+        self.block_frames = frames;
+    }
 
-        let mut sequence: Vec<u8> = Vec::new();
-        let mode = self.get_mode();
+    fn next_random(&mut self) -> usize {
+        self.rng_state = self.rng_state.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (self.rng_state >> 33) as usize
+    }
 
-        let mut sorted_notes = self.held_notes.clone();
-        sorted_notes.sort();
+    /// Notes to cycle through, from the held notes (or the chord track's chord), mode and octave range
+    fn sequence(&mut self) -> Vec<u8> {
+        let octaves = self.params[2].value as u8;
+        let mode = self.get_mode();
+        let lowest = self.held_notes.iter().min().copied();
+        let mut notes = match (self.chord.filter(|_| self.follows_chords()), lowest) {
+            (Some(chord), Some(lowest)) => chord.voice_from(lowest, 0),
+            _ => self.held_notes.clone(),
+        };
+        match mode {
+            ArpMode::Order => {}
+            ArpMode::Down => notes.sort_by(|a, b| b.cmp(a)),
+            _ => notes.sort(),
+        }
 
+        let mut sequence: Vec<u8> = Vec::new();
         for oct in 0..octaves {
             let oct_offset = oct * 12;
-            match mode {
-                ArpMode::Up | ArpMode::UpDown | ArpMode::Order => {
-                    for &note in &sorted_notes {
-                        sequence.push((note as u16 + oct_offset as u16).min(127) as u8);
-                    }
-                }
-                ArpMode::Down => {
-                    for &note in sorted_notes.iter().rev() {
-                        sequence.push((note as u16 + oct_offset as u16).min(127) as u8);
-                    }
-                }
-                ArpMode::Random => {
-                    for &note in &sorted_notes {
-                        sequence.push((note as u16 + oct_offset as u16).min(127) as u8);
-                    }
-                }
+            for &note in &notes {
+                sequence.push((note as u16 + oct_offset as u16).min(127) as u8);
             }
         }
 
@@ -112,28 +215,102 @@ impl ArpeggiatorFx {
                 sequence.swap(i, j);
             }
         }
+        sequence
+    }
+
+    fn process_impl(&mut self, events: Vec<MidiEvent>, sample_rate: f32, bpm: f64) -> Vec<MidiEvent> {
+        self.upgrade_params();
+        let was_idle = self.held_notes.is_empty();
+        let mut start = 0.0;
+
+        for event in &events {
+            if event.is_note_on {
+                if self.latched() && self.pressed.is_empty() {
+                    // First key of a new chord replaces the latched one
+                    self.held_notes.clear();
+                }
+                if self.held_notes.is_empty() {
+                    start = event.sample_offset as f64;
+                }
+                if !self.held_notes.contains(&event.pitch) {
+                    self.held_notes.push(event.pitch);
+                }
+                if !self.pressed.contains(&event.pitch) {
+                    self.pressed.push(event.pitch);
+                }
+                self.channel = event.channel;
+                self.velocity = event.velocity;
+            } else {
+                self.pressed.retain(|&p| p != event.pitch);
+            }
+        }
+        if !self.latched() {
+            let pressed = &self.pressed;
+            self.held_notes.retain(|p| pressed.contains(p));
+        }
 
         let mut result = Vec::new();
-        let channel = events.first().map(|e| e.channel).unwrap_or(0);
-
-        for (i, &pitch) in sequence.iter().enumerate() {
-            let offset = note_samples * i as u32;
-            result.push(MidiEvent {
-                pitch,
-                velocity: 100,
-                channel,
-                sample_offset: offset,
-                is_note_on: true,
-            });
-            result.push(MidiEvent {
-                pitch,
-                velocity: 0,
-                channel,
-                sample_offset: offset + gate_samples,
-                is_note_on: false,
-            });
+        let channel = self.channel;
+        let note_off = |pitch: u8, at: f64| MidiEvent {
+            pitch,
+            velocity: 0,
+            channel,
+            sample_offset: at as u32,
+            is_note_on: false,
+        };
+
+        if self.held_notes.is_empty() {
+            if let Some((pitch, _)) = self.sounding.take() {
+                result.push(note_off(pitch, 0.0));
+            }
+            return result;
         }
+        if was_idle {
+            self.until_step = start;
+            self.step_count = 0;
+            self.note_index = 0;
+        }
+
+        let sequence = self.sequence();
+        let step_samples = (self.step_beats() * sample_rate as f64 * 60.0 / bpm).max(1.0);
+        let gate_samples = step_samples * self.params[3].value as f64 / 100.0;
+        let frames = self.block_frames as f64;
 
+        loop {
+            if let Some((pitch, off_at)) = self.sounding
+                && off_at < frames
+                && off_at <= self.until_step
+            {
+                result.push(note_off(pitch, off_at));
+                self.sounding = None;
+            }
+            if self.until_step >= frames {
+                break;
+            }
+            if let Some((pitch, _)) = self.sounding.take() {
+                result.push(note_off(pitch, self.until_step));
+            }
+            if self.pattern_step(self.step_count % self.pattern_len()) {
+                let pitch = sequence[self.note_index % sequence.len()];
+                self.note_index += 1;
+                result.push(MidiEvent {
+                    pitch,
+                    velocity: self.velocity,
+                    channel,
+                    sample_offset: self.until_step as u32,
+                    is_note_on: true,
+                });
+                self.sounding = Some((pitch, self.until_step + gate_samples));
+            }
+            self.step_count += 1;
+            self.until_step += step_samples;
+        }
+
+        // Carry the clock over into the next block
+        self.until_step -= frames;
+        if let Some((_, off_at)) = &mut self.sounding {
+            *off_at -= frames;
+        }
         result
     }
 }
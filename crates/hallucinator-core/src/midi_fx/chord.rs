@@ -0,0 +1,91 @@
+//! Chord MIDI effect
+
+use serde::{Deserialize, Serialize};
+
+use super::{impl_midi_fx_boilerplate, MidiEvent, MidiFxParam};
+use crate::chord_track::Chord;
+
+/// Held notes remembered for releasing their chords; the oldest are forgotten past this
+const MAX_HELD: usize = 128;
+
+/// Plays the chord track's current chord for every note, voiced around the played pitch;
+/// notes pass through unchanged where the chord track has no chord
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChordFx {
+    params: Vec<MidiFxParam>,
+    bypass: bool,
+    /// Chord track chord at the current block, set by the chain before `process`
+    #[serde(skip)]
+    chord: Option<Chord>,
+    /// (channel, played pitch, chord notes) of held notes, so each note-off
+    /// releases the chord its note-on played even after the chord changes
+    #[serde(skip)]
+    held: Vec<(u8, u8, Vec<u8>)>,
+}
+
+impl Default for ChordFx {
+    fn default() -> Self {
+        Self {
+            params: vec![
+                MidiFxParam::new("inversion", 0.0, 0.0, 3.0),
+                // 1 = add the root an octave below
+                MidiFxParam::new("bass", 0.0, 0.0, 1.0),
+            ],
+            bypass: false,
+            chord: None,
+            held: Vec::new(),
+        }
+    }
+}
+
+impl ChordFx {
+    /// Chord track chord for the coming block
+    pub fn set_chord(&mut self, chord: Option<Chord>) {
+        self.chord = chord;
+    }
+
+    /// Notes played for `pitch`: the chord from the root at or below it
+    fn notes(&self, pitch: u8) -> Vec<u8> {
+        let Some(chord) = self.chord else {
+            return vec![pitch];
+        };
+        let mut notes = chord.voice_from(pitch, self.params[0].value as usize);
+        if self.params[1].value >= 0.5 {
+            let below = (pitch % 12 + 12 - chord.root) % 12;
+            if let Some(bass) = pitch.checked_sub(below + 12) {
+                notes.insert(0, bass);
+            }
+        }
+        notes
+    }
+
+    fn process_impl(&mut self, events: Vec<MidiEvent>, _sample_rate: f32, _bpm: f64) -> Vec<MidiEvent> {
+        let mut result = Vec::with_capacity(events.len() * 4);
+        for event in events {
+            if event.is_note_on {
+                let notes = self.notes(event.pitch);
+                result.extend(notes.iter().map(|&pitch| MidiEvent { pitch, ..event }));
+                if self.held.len() >= MAX_HELD {
+                    self.held.remove(0);
+                }
+                self.held.push((event.channel, event.pitch, notes));
+                continue;
+            }
+            let notes = match self.held.iter().position(|(ch, played, _)| *ch == event.channel && *played == event.pitch) {
+                Some(i) => self.held.remove(i).2,
+                None => vec![event.pitch],
+            };
+            // Notes still held by another played key keep sounding
+            for pitch in notes {
+                let shared = self.held.iter().any(|(ch, _, sounding)| *ch == event.channel && sounding.contains(&pitch));
+                if !shared {
+                    result.push(MidiEvent { pitch, ..event });
+                }
+            }
+        }
+        result
+    }
+}
+
+impl_midi_fx_boilerplate!(ChordFx, "Chord");
+
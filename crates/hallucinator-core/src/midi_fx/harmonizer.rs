@@ -3,44 +3,98 @@
 use serde::{Deserialize, Serialize};
 
 use super::{impl_midi_fx_boilerplate, MidiEvent, MidiFxParam};
+use crate::chord_track::Chord;
+
+/// Held notes remembered for releasing their voices; the oldest are forgotten past this
+const MAX_HELD: usize = 128;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HarmonizerFx {
     params: Vec<MidiFxParam>,
     bypass: bool,
+    /// Chord track chord at the current block, set by the chain before `process`
+    #[serde(skip)]
+    chord: Option<Chord>,
+    /// (channel, played pitch, voices added) of held notes, so each note-off
+    /// releases the voices its note-on added even after the chord changes
+    #[serde(skip)]
+    held: Vec<(u8, u8, Vec<u8>)>,
 }
 
 impl Default for HarmonizerFx {
     fn default() -> Self {
         Self {
-            params: vec![
-                MidiFxParam::new("interval1", 4.0, -12.0, 12.0),
-                MidiFxParam::new("interval2", 7.0, -12.0, 12.0),
-                MidiFxParam::new("voices", 2.0, 0.0, 2.0),
-            ],
+            params: Self::default_params(),
             bypass: false,
+            chord: None,
+            held: Vec::new(),
         }
     }
 }
 
 impl HarmonizerFx {
-    fn process_impl(&mut self, events: Vec<MidiEvent>, _sample_rate: f32, _bpm: f64) -> Vec<MidiEvent> {
-        let interval1 = self.params[0].value as i8;
-        let interval2 = self.params[1].value as i8;
-        let voices = self.params[2].value as u8;
+    fn default_params() -> Vec<MidiFxParam> {
+        vec![
+            MidiFxParam::new("interval1", 4.0, -12.0, 12.0),
+            MidiFxParam::new("interval2", 7.0, -12.0, 12.0),
+            MidiFxParam::new("voices", 2.0, 0.0, 2.0),
+            // 1 = voices are the next chord tones of the chord track instead of the intervals
+            MidiFxParam::new("follow", 0.0, 0.0, 1.0),
+        ]
+    }
 
-        let mut result = events.clone();
+    /// Add params introduced since an effect was saved
+    fn upgrade_params(&mut self) {
+        let defaults = Self::default_params();
+        if self.params.len() < defaults.len() {
+            self.params.extend(defaults.into_iter().skip(self.params.len()));
+        }
+    }
 
-        for event in &events {
-            if !event.is_note_on { continue; }
+    /// Semitone offsets of the voices added above (or below) each note
+    pub fn intervals(&self) -> Vec<i8> {
+        let voices = self.params[2].value as usize;
+        self.params[..2].iter().take(voices).map(|p| p.value as i8).collect()
+    }
 
-            if voices >= 1 {
-                let pitch1 = (event.pitch as i16 + interval1 as i16).clamp(0, 127) as u8;
-                result.push(MidiEvent { pitch: pitch1, ..*event });
-            }
-            if voices >= 2 {
-                let pitch2 = (event.pitch as i16 + interval2 as i16).clamp(0, 127) as u8;
-                result.push(MidiEvent { pitch: pitch2, ..*event });
+    /// Chord track chord for the coming block
+    pub fn set_chord(&mut self, chord: Option<Chord>) {
+        self.chord = chord;
+    }
+
+    /// Whether voices come from the chord track rather than the intervals
+    pub fn follows_chords(&self) -> bool {
+        self.params.get(3).is_some_and(|p| p.value >= 0.5)
+    }
+
+    /// Pitches added to a played note
+    fn voices(&self, pitch: u8) -> Vec<u8> {
+        let voices = self.params[2].value as usize;
+        match self.chord.filter(|_| self.follows_chords()) {
+            Some(chord) => chord.tones_above(pitch, voices),
+            None => self.intervals()
+                .into_iter()
+                .map(|interval| (pitch as i16 + interval as i16).clamp(0, 127) as u8)
+                .collect(),
+        }
+    }
+
+    fn process_impl(&mut self, events: Vec<MidiEvent>, _sample_rate: f32, _bpm: f64) -> Vec<MidiEvent> {
+        self.upgrade_params();
+        let mut result = Vec::with_capacity(events.len() * 3);
+
+        for event in events {
+            result.push(event);
+            if event.is_note_on {
+                let voices = self.voices(event.pitch);
+                result.extend(voices.iter().map(|&pitch| MidiEvent { pitch, ..event }));
+                if self.held.len() >= MAX_HELD {
+                    self.held.remove(0);
+                }
+                self.held.push((event.channel, event.pitch, voices));
+            } else if let Some(i) = self.held.iter().position(|(ch, played, _)| *ch == event.channel && *played == event.pitch) {
+                let (_, _, voices) = self.held.remove(i);
+                result.extend(voices.into_iter().map(|pitch| MidiEvent { pitch, ..event }));
             }
         }
 
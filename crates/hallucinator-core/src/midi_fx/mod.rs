@@ -2,24 +2,36 @@
 
 mod arpeggiator;
 mod chance;
+mod chord;
 mod echo;
 mod harmonizer;
 mod humanize;
+mod note_filter;
 mod quantize;
+mod ratchet;
+mod scale_force;
+mod strum;
 mod swing;
 mod transpose;
 
-pub use arpeggiator::{ArpMode, ArpeggiatorFx};
+pub use arpeggiator::{ArpMode, ArpeggiatorFx, ARP_DIVISIONS, ARP_MAX_STEPS};
 pub use chance::ChanceFx;
+pub use chord::ChordFx;
 pub use echo::EchoFx;
 pub use harmonizer::HarmonizerFx;
 pub use humanize::HumanizeFx;
+pub use note_filter::NoteFilterFx;
 pub use quantize::QuantizeFx;
+pub use ratchet::RatchetFx;
+pub use scale_force::ScaleForceFx;
+pub use strum::{StrumDirection, StrumFx};
 pub use swing::SwingFx;
 pub use transpose::TransposeFx;
 
 use serde::{Deserialize, Serialize};
 
+use crate::chord_track::Chord;
+
 /// A MIDI event for FX processing
 #[derive(Debug, Clone, Copy)]
 pub struct MidiEvent {
@@ -94,6 +106,11 @@ pub enum MidiEffect {
     Echo(EchoFx),
     Arpeggiator(ArpeggiatorFx),
     Harmonizer(HarmonizerFx),
+    NoteFilter(NoteFilterFx),
+    ScaleForce(ScaleForceFx),
+    Strum(StrumFx),
+    Ratchet(RatchetFx),
+    Chord(ChordFx),
 }
 
 impl MidiEffect {
@@ -107,6 +124,11 @@ impl MidiEffect {
             Self::Echo(fx) => fx.name(),
             Self::Arpeggiator(fx) => fx.name(),
             Self::Harmonizer(fx) => fx.name(),
+            Self::NoteFilter(fx) => fx.name(),
+            Self::ScaleForce(fx) => fx.name(),
+            Self::Strum(fx) => fx.name(),
+            Self::Ratchet(fx) => fx.name(),
+            Self::Chord(fx) => fx.name(),
         }
     }
 
@@ -120,6 +142,28 @@ impl MidiEffect {
             Self::Echo(fx) => fx.process(events, sample_rate, bpm),
             Self::Arpeggiator(fx) => fx.process(events, sample_rate, bpm),
             Self::Harmonizer(fx) => fx.process(events, sample_rate, bpm),
+            Self::NoteFilter(fx) => fx.process(events, sample_rate, bpm),
+            Self::ScaleForce(fx) => fx.process(events, sample_rate, bpm),
+            Self::Strum(fx) => fx.process(events, sample_rate, bpm),
+            Self::Ratchet(fx) => fx.process(events, sample_rate, bpm),
+            Self::Chord(fx) => fx.process(events, sample_rate, bpm),
+        }
+    }
+
+    /// Tell effects that follow the chord track which chord the coming block is in
+    pub fn set_chord(&mut self, chord: Option<Chord>) {
+        match self {
+            Self::Harmonizer(fx) => fx.set_chord(chord),
+            Self::Arpeggiator(fx) => fx.set_chord(chord),
+            Self::Chord(fx) => fx.set_chord(chord),
+            _ => {}
+        }
+    }
+
+    /// Tell effects that run on their own clock how long the coming block is
+    pub fn set_block_frames(&mut self, frames: u32) {
+        if let Self::Arpeggiator(fx) = self {
+            fx.set_block_frames(frames);
         }
     }
 
@@ -133,6 +177,11 @@ impl MidiEffect {
             Self::Echo(fx) => fx.get_params(),
             Self::Arpeggiator(fx) => fx.get_params(),
             Self::Harmonizer(fx) => fx.get_params(),
+            Self::NoteFilter(fx) => fx.get_params(),
+            Self::ScaleForce(fx) => fx.get_params(),
+            Self::Strum(fx) => fx.get_params(),
+            Self::Ratchet(fx) => fx.get_params(),
+            Self::Chord(fx) => fx.get_params(),
         }
     }
 
@@ -146,6 +195,11 @@ impl MidiEffect {
             Self::Echo(fx) => fx.set_param(name, value),
             Self::Arpeggiator(fx) => fx.set_param(name, value),
             Self::Harmonizer(fx) => fx.set_param(name, value),
+            Self::NoteFilter(fx) => fx.set_param(name, value),
+            Self::ScaleForce(fx) => fx.set_param(name, value),
+            Self::Strum(fx) => fx.set_param(name, value),
+            Self::Ratchet(fx) => fx.set_param(name, value),
+            Self::Chord(fx) => fx.set_param(name, value),
         }
     }
 
@@ -159,6 +213,11 @@ impl MidiEffect {
             Self::Echo(fx) => fx.is_bypassed(),
             Self::Arpeggiator(fx) => fx.is_bypassed(),
             Self::Harmonizer(fx) => fx.is_bypassed(),
+            Self::NoteFilter(fx) => fx.is_bypassed(),
+            Self::ScaleForce(fx) => fx.is_bypassed(),
+            Self::Strum(fx) => fx.is_bypassed(),
+            Self::Ratchet(fx) => fx.is_bypassed(),
+            Self::Chord(fx) => fx.is_bypassed(),
         }
     }
 
@@ -172,6 +231,11 @@ impl MidiEffect {
             Self::Echo(fx) => fx.set_bypass(bypass),
             Self::Arpeggiator(fx) => fx.set_bypass(bypass),
             Self::Harmonizer(fx) => fx.set_bypass(bypass),
+            Self::NoteFilter(fx) => fx.set_bypass(bypass),
+            Self::ScaleForce(fx) => fx.set_bypass(bypass),
+            Self::Strum(fx) => fx.set_bypass(bypass),
+            Self::Ratchet(fx) => fx.set_bypass(bypass),
+            Self::Chord(fx) => fx.set_bypass(bypass),
         }
     }
 }
@@ -201,10 +265,14 @@ impl MidiFxChain {
         None
     }
 
-    pub fn process(&mut self, mut events: Vec<MidiEvent>, sample_rate: f32, bpm: f64) -> Vec<MidiEvent> {
+    /// Run one audio block of `num_frames` samples worth of events through the chain;
+    /// `chord` is the chord track's chord at the start of the block
+    pub fn process(&mut self, mut events: Vec<MidiEvent>, num_frames: usize, chord: Option<Chord>, sample_rate: f32, bpm: f64) -> Vec<MidiEvent> {
         if self.bypass_all { return events; }
 
         for effect in &mut self.effects {
+            effect.set_block_frames(num_frames as u32);
+            effect.set_chord(chord);
             if !effect.is_bypassed() {
                 events = effect.process(events, sample_rate, bpm);
             }
@@ -0,0 +1,99 @@
+//! Note filter and keyboard split MIDI effect
+
+use serde::{Deserialize, Serialize};
+
+use super::{impl_midi_fx_boilerplate, MidiEvent, MidiFxParam};
+
+/// Held notes remembered for routing their note-offs; the oldest are forgotten past this
+const MAX_HELD: usize = 128;
+
+/// Passes notes inside a pitch range, velocity range and channel. In split mode
+/// the notes that pass go out on one channel below the split note and another
+/// from it upward, so a multitimbral instrument plays two sounds across one keyboard.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteFilterFx {
+    params: Vec<MidiFxParam>,
+    bypass: bool,
+    /// (input channel, pitch, output channel) of notes let through and still sounding,
+    /// so each note-off follows its note-on even after the settings change
+    #[serde(skip)]
+    held: Vec<(u8, u8, u8)>,
+}
+
+impl Default for NoteFilterFx {
+    fn default() -> Self {
+        Self {
+            params: vec![
+                MidiFxParam::new("low_note", 0.0, 0.0, 127.0),
+                MidiFxParam::new("high_note", 127.0, 0.0, 127.0),
+                MidiFxParam::new("low_vel", 1.0, 1.0, 127.0),
+                MidiFxParam::new("high_vel", 127.0, 1.0, 127.0),
+                // 0 = any channel
+                MidiFxParam::new("channel", 0.0, 0.0, 16.0),
+                MidiFxParam::new("split", 0.0, 0.0, 1.0),
+                MidiFxParam::new("split_note", 60.0, 0.0, 127.0),
+                MidiFxParam::new("lower_ch", 1.0, 1.0, 16.0),
+                MidiFxParam::new("upper_ch", 2.0, 1.0, 16.0),
+            ],
+            bypass: false,
+            held: Vec::new(),
+        }
+    }
+}
+
+impl NoteFilterFx {
+    fn value(&self, index: usize) -> u8 {
+        self.params[index].value as u8
+    }
+
+    fn velocity_passes(&self, velocity: u8) -> bool {
+        let (low, high) = (self.value(2), self.value(3));
+        (low.min(high)..=low.max(high)).contains(&velocity)
+    }
+
+    /// Output channel for a note, or None when the pitch or channel is filtered out
+    fn route(&self, pitch: u8, channel: u8) -> Option<u8> {
+        let listen = self.value(4);
+        if listen != 0 && channel != listen - 1 {
+            return None;
+        }
+        let (low, high) = (self.value(0), self.value(1));
+        if !(low.min(high)..=low.max(high)).contains(&pitch) {
+            return None;
+        }
+        if self.value(5) == 0 {
+            return Some(channel);
+        }
+        let out = if pitch < self.value(6) { self.value(7) } else { self.value(8) };
+        Some(out.saturating_sub(1))
+    }
+
+    fn process_impl(&mut self, events: Vec<MidiEvent>, _sample_rate: f32, _bpm: f64) -> Vec<MidiEvent> {
+        events
+            .into_iter()
+            .filter_map(|mut e| {
+                if e.is_note_on {
+                    if !self.velocity_passes(e.velocity) {
+                        return None;
+                    }
+                    let out = self.route(e.pitch, e.channel)?;
+                    if self.held.len() >= MAX_HELD {
+                        self.held.remove(0);
+                    }
+                    self.held.push((e.channel, e.pitch, out));
+                    e.channel = out;
+                    return Some(e);
+                }
+                // A note-off goes where its note-on went; one we never saw is routed afresh
+                let out = match self.held.iter().position(|&(ch, pitch, _)| ch == e.channel && pitch == e.pitch) {
+                    Some(i) => self.held.remove(i).2,
+                    None => self.route(e.pitch, e.channel)?,
+                };
+                e.channel = out;
+                Some(e)
+            })
+            .collect()
+    }
+}
+
+impl_midi_fx_boilerplate!(NoteFilterFx, "Note Filter");
@@ -0,0 +1,102 @@
+//! Ratchet (tempo-synced retrigger) MIDI effect
+
+use serde::{Deserialize, Serialize};
+
+use super::{impl_midi_fx_boilerplate, MidiEvent, MidiFxParam};
+
+/// Ratcheted notes waiting for their original note-off; the oldest are forgotten past this
+const MAX_HELD: usize = 128;
+
+/// Turns each note into a burst of short retriggers on a note grid
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RatchetFx {
+    params: Vec<MidiFxParam>,
+    bypass: bool,
+    rng_state: u64,
+    /// (channel, pitch) of ratcheted notes; their own note-offs are dropped,
+    /// since every retrigger carries a note-off of its own
+    #[serde(skip)]
+    held: Vec<(u8, u8)>,
+}
+
+impl Default for RatchetFx {
+    fn default() -> Self {
+        Self {
+            params: vec![
+                MidiFxParam::new("rate", 16.0, 4.0, 32.0),
+                MidiFxParam::new("repeats", 4.0, 1.0, 16.0),
+                MidiFxParam::new("decay", 80.0, 0.0, 100.0),
+                MidiFxParam::new("probability", 100.0, 0.0, 100.0),
+                MidiFxParam::new("gate", 50.0, 10.0, 100.0),
+            ],
+            bypass: false,
+            rng_state: 24680,
+            held: Vec::new(),
+        }
+    }
+}
+
+impl RatchetFx {
+    fn next_random(&mut self) -> f32 {
+        self.rng_state = self.rng_state.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (self.rng_state >> 33) as f32 / u32::MAX as f32
+    }
+
+    fn process_impl(&mut self, events: Vec<MidiEvent>, sample_rate: f32, bpm: f64) -> Vec<MidiEvent> {
+        let rate = self.params[0].value;
+        let repeats = self.params[1].value as u32;
+        let decay = self.params[2].value / 100.0;
+        let prob = self.params[3].value / 100.0;
+        let gate = self.params[4].value / 100.0;
+
+        let samples_per_beat = (sample_rate as f64 * 60.0 / bpm) as u32;
+        let step_samples = samples_per_beat * 4 / rate as u32;
+        let gate_samples = ((step_samples as f32 * gate) as u32).max(1);
+
+        let mut result = Vec::with_capacity(events.len() * repeats as usize * 2);
+
+        for event in events {
+            if !event.is_note_on {
+                match self.held.iter().position(|&(ch, pitch)| ch == event.channel && pitch == event.pitch) {
+                    Some(i) => {
+                        self.held.remove(i);
+                    }
+                    None => result.push(event),
+                }
+                continue;
+            }
+
+            if self.held.len() >= MAX_HELD {
+                self.held.remove(0);
+            }
+            self.held.push((event.channel, event.pitch));
+
+            // The first hit always plays; later ones fade and may be skipped
+            let mut vel = event.velocity as f32;
+            for i in 0..repeats {
+                if i > 0 {
+                    vel *= decay;
+                    if vel < 1.0 {
+                        break;
+                    }
+                    if self.next_random() >= prob {
+                        continue;
+                    }
+                }
+                let offset = event.sample_offset + step_samples * i;
+                result.push(MidiEvent { velocity: vel as u8, sample_offset: offset, ..event });
+                result.push(MidiEvent {
+                    velocity: 0,
+                    sample_offset: offset + gate_samples,
+                    is_note_on: false,
+                    ..event
+                });
+            }
+        }
+
+        result.sort_by_key(|e| e.sample_offset);
+        result
+    }
+}
+
+impl_midi_fx_boilerplate!(RatchetFx, "Ratchet");
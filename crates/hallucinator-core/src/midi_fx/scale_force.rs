@@ -0,0 +1,86 @@
+//! Scale force MIDI effect
+
+use serde::{Deserialize, Serialize};
+
+use super::{impl_midi_fx_boilerplate, MidiEvent, MidiFxParam};
+use crate::algorithms::{quantize_to_scale, ScaleMode};
+
+/// Scales selectable by the `mode` parameter, in order
+const MODES: [ScaleMode; 11] = [
+    ScaleMode::Major,
+    ScaleMode::Minor,
+    ScaleMode::Dorian,
+    ScaleMode::Phrygian,
+    ScaleMode::Lydian,
+    ScaleMode::Mixolydian,
+    ScaleMode::Locrian,
+    ScaleMode::HarmonicMinor,
+    ScaleMode::MelodicMinor,
+    ScaleMode::Pentatonic,
+    ScaleMode::Blues,
+];
+
+/// Held notes remembered for routing their note-offs; the oldest are forgotten past this
+const MAX_HELD: usize = 128;
+
+/// Snaps every note to the nearest note of a scale
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScaleForceFx {
+    params: Vec<MidiFxParam>,
+    bypass: bool,
+    /// (channel, played pitch, sounding pitch) of held notes, so each note-off
+    /// releases what its note-on played even after the scale changes
+    #[serde(skip)]
+    held: Vec<(u8, u8, u8)>,
+}
+
+impl Default for ScaleForceFx {
+    fn default() -> Self {
+        Self {
+            params: vec![
+                // 0 = C ... 11 = B
+                MidiFxParam::new("root", 0.0, 0.0, 11.0),
+                MidiFxParam::new("mode", 0.0, 0.0, (MODES.len() - 1) as f32),
+            ],
+            bypass: false,
+            held: Vec::new(),
+        }
+    }
+}
+
+impl ScaleForceFx {
+    fn scale(&self) -> ScaleMode {
+        MODES[(self.params[1].value as usize).min(MODES.len() - 1)]
+    }
+
+    fn process_impl(&mut self, events: Vec<MidiEvent>, _sample_rate: f32, _bpm: f64) -> Vec<MidiEvent> {
+        let root = self.params[0].value as u8;
+        let scale = self.scale();
+        events
+            .into_iter()
+            .filter_map(|mut e| {
+                if e.is_note_on {
+                    let pitch = quantize_to_scale(e.pitch, root, scale);
+                    if self.held.len() >= MAX_HELD {
+                        self.held.remove(0);
+                    }
+                    self.held.push((e.channel, e.pitch, pitch));
+                    e.pitch = pitch;
+                    return Some(e);
+                }
+                let pitch = match self.held.iter().position(|&(ch, played, _)| ch == e.channel && played == e.pitch) {
+                    Some(i) => self.held.remove(i).2,
+                    None => quantize_to_scale(e.pitch, root, scale),
+                };
+                // Two played notes can land on one scale note; it keeps sounding until both are released
+                if self.held.iter().any(|&(ch, _, sounding)| ch == e.channel && sounding == pitch) {
+                    return None;
+                }
+                e.pitch = pitch;
+                Some(e)
+            })
+            .collect()
+    }
+}
+
+impl_midi_fx_boilerplate!(ScaleForceFx, "Scale Force");
@@ -0,0 +1,122 @@
+//! Strum MIDI effect
+
+use serde::{Deserialize, Serialize};
+
+use super::{impl_midi_fx_boilerplate, MidiEvent, MidiFxParam};
+
+/// Note-ons this close together count as one chord
+const CHORD_WINDOW_MS: f32 = 15.0;
+
+/// Delayed notes remembered for delaying their note-offs; the oldest are forgotten past this
+const MAX_HELD: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StrumDirection {
+    /// Lowest note first
+    Up,
+    /// Highest note first
+    Down,
+    /// Up and down on successive chords
+    Alternate,
+}
+
+/// Spreads the notes of each chord out over time, like a pick across strings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrumFx {
+    params: Vec<MidiFxParam>,
+    bypass: bool,
+    /// (channel, pitch, delay in samples) of strummed notes still sounding, so
+    /// each note-off is delayed as much as its note-on and no note gets shorter
+    #[serde(skip)]
+    held: Vec<(u8, u8, u32)>,
+    /// Alternate direction: the last chord was strummed downward
+    #[serde(skip)]
+    last_down: bool,
+}
+
+impl Default for StrumFx {
+    fn default() -> Self {
+        Self {
+            params: vec![
+                MidiFxParam::new("direction", 0.0, 0.0, 2.0),
+                MidiFxParam::new("spacing", 30.0, 1.0, 200.0),
+                // 0 = spacing in ms, 1 = spacing of one `division` note
+                MidiFxParam::new("sync", 0.0, 0.0, 1.0),
+                MidiFxParam::new("division", 32.0, 4.0, 64.0),
+            ],
+            bypass: false,
+            held: Vec::new(),
+            last_down: false,
+        }
+    }
+}
+
+impl StrumFx {
+    fn get_direction(&self) -> StrumDirection {
+        match self.params[0].value as u8 {
+            0 => StrumDirection::Up,
+            1 => StrumDirection::Down,
+            _ => StrumDirection::Alternate,
+        }
+    }
+
+    /// Time between successive notes of a chord, in samples
+    fn spacing_samples(&self, sample_rate: f32, bpm: f64) -> u32 {
+        if self.params[2].value >= 0.5 {
+            let samples_per_beat = (sample_rate as f64 * 60.0 / bpm) as u32;
+            samples_per_beat * 4 / self.params[3].value as u32
+        } else {
+            (self.params[1].value / 1000.0 * sample_rate) as u32
+        }
+    }
+
+    fn process_impl(&mut self, mut events: Vec<MidiEvent>, sample_rate: f32, bpm: f64) -> Vec<MidiEvent> {
+        let spacing = self.spacing_samples(sample_rate, bpm);
+        let window = (CHORD_WINDOW_MS / 1000.0 * sample_rate) as u32;
+        events.sort_by_key(|e| e.sample_offset);
+
+        // Group note-ons into chords by index, in time order
+        let mut chords: Vec<Vec<usize>> = Vec::new();
+        for (i, event) in events.iter().enumerate().filter(|(_, e)| e.is_note_on) {
+            match chords.last_mut() {
+                Some(chord) if event.sample_offset - events[chord[0]].sample_offset <= window => chord.push(i),
+                _ => chords.push(vec![i]),
+            }
+        }
+
+        for mut chord in chords.into_iter().filter(|c| c.len() > 1) {
+            let down = match self.get_direction() {
+                StrumDirection::Up => false,
+                StrumDirection::Down => true,
+                StrumDirection::Alternate => {
+                    self.last_down = !self.last_down;
+                    self.last_down
+                }
+            };
+            chord.sort_by_key(|&i| events[i].pitch);
+            if down {
+                chord.reverse();
+            }
+            for (n, &i) in chord.iter().enumerate().skip(1) {
+                let delay = spacing * n as u32;
+                let event = &mut events[i];
+                event.sample_offset += delay;
+                if self.held.len() >= MAX_HELD {
+                    self.held.remove(0);
+                }
+                self.held.push((event.channel, event.pitch, delay));
+            }
+        }
+
+        for event in events.iter_mut().filter(|e| !e.is_note_on) {
+            if let Some(i) = self.held.iter().position(|&(ch, pitch, _)| ch == event.channel && pitch == event.pitch) {
+                event.sample_offset += self.held.remove(i).2;
+            }
+        }
+
+        events.sort_by_key(|e| e.sample_offset);
+        events
+    }
+}
+
+impl_midi_fx_boilerplate!(StrumFx, "Strum");
@@ -0,0 +1,182 @@
+//! MIDI CC mappings from hardware controllers to device parameters
+
+use serde::{Deserialize, Serialize};
+
+use crate::track::moved_index;
+
+/// Device owning a mappable parameter, addressed the way the engine stores it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ParamOwner {
+    /// Instrument by instrument ID
+    Instrument(u64),
+    /// Effect in a track chain
+    TrackEffect { chain_id: u64, index: usize },
+    /// Effect in the master chain
+    MasterEffect(usize),
+}
+
+/// How incoming CC values drive the parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CcMode {
+    /// CC value sets the parameter directly
+    #[default]
+    Absolute,
+    /// Ignore the controller until it passes the current parameter value (no jumps)
+    Pickup,
+    /// Endless encoder: 1-63 increments, 65-127 decrements (two's complement)
+    Relative,
+}
+
+impl CcMode {
+    pub const ALL: [CcMode; 3] = [CcMode::Absolute, CcMode::Pickup, CcMode::Relative];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CcMode::Absolute => "Absolute",
+            CcMode::Pickup => "Pickup",
+            CcMode::Relative => "Relative",
+        }
+    }
+}
+
+/// Pickup tolerance as a fraction of the mapped range
+const PICKUP_TOLERANCE: f32 = 0.03;
+
+/// One controller (channel, CC) driving one parameter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MidiMapping {
+    /// MIDI channel (0-15)
+    pub channel: u8,
+    pub controller: u8,
+    pub owner: ParamOwner,
+    pub param: String,
+    /// Parameter value at CC 0
+    pub min: f32,
+    /// Parameter value at CC 127
+    pub max: f32,
+    #[serde(default)]
+    pub mode: CcMode,
+    /// Pickup: controller has caught the parameter
+    #[serde(skip)]
+    picked_up: bool,
+    /// Value most recently sent, to notice edits made elsewhere
+    #[serde(skip)]
+    last_sent: Option<f32>,
+}
+
+impl MidiMapping {
+    pub fn new(channel: u8, controller: u8, owner: ParamOwner, param: impl Into<String>, min: f32, max: f32) -> Self {
+        Self {
+            channel,
+            controller,
+            owner,
+            param: param.into(),
+            min,
+            max,
+            mode: CcMode::Absolute,
+            picked_up: false,
+            last_sent: None,
+        }
+    }
+
+    /// Short label, e.g. "Ch1 CC74"
+    pub fn label(&self) -> String {
+        format!("Ch{} CC{}", self.channel + 1, self.controller)
+    }
+
+    /// New parameter value for a CC value given the parameter's current value,
+    /// or None if the mapping should not move it yet (pickup not reached)
+    pub fn resolve(&mut self, cc_value: u8, current: f32) -> Option<f32> {
+        let range = self.max - self.min;
+
+        // Someone else moved the parameter: pickup must catch it again
+        if self.last_sent.is_some_and(|sent| (sent - current).abs() > range.abs() * 0.001) {
+            self.picked_up = false;
+        }
+
+        let value = match self.mode {
+            CcMode::Absolute => self.min + range * cc_value as f32 / 127.0,
+            CcMode::Pickup => {
+                let target = self.min + range * cc_value as f32 / 127.0;
+                if !self.picked_up {
+                    if (target - current).abs() > range.abs() * PICKUP_TOLERANCE {
+                        return None;
+                    }
+                    self.picked_up = true;
+                }
+                target
+            }
+            CcMode::Relative => {
+                let delta = if cc_value < 64 { cc_value as f32 } else { cc_value as f32 - 128.0 };
+                (current + delta * range / 127.0).clamp(self.min.min(self.max), self.min.max(self.max))
+            }
+        };
+
+        self.last_sent = Some(value);
+        Some(value)
+    }
+}
+
+/// All controller mappings in a project
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MidiMap {
+    pub mappings: Vec<MidiMapping>,
+}
+
+impl MidiMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a mapping, replacing any existing one for the same controller or parameter
+    pub fn learn(&mut self, mapping: MidiMapping) {
+        self.mappings.retain(|m| {
+            (m.channel, m.controller) != (mapping.channel, mapping.controller)
+                && (m.owner != mapping.owner || m.param != mapping.param)
+        });
+        self.mappings.push(mapping);
+    }
+
+    pub fn forget(&mut self, owner: ParamOwner, param: &str) {
+        self.mappings.retain(|m| m.owner != owner || m.param != param);
+    }
+
+    pub fn find(&self, owner: ParamOwner, param: &str) -> Option<&MidiMapping> {
+        self.mappings.iter().find(|m| m.owner == owner && m.param == param)
+    }
+
+    pub fn find_mut(&mut self, owner: ParamOwner, param: &str) -> Option<&mut MidiMapping> {
+        self.mappings.iter_mut().find(|m| m.owner == owner && m.param == param)
+    }
+
+    /// Fix up mappings after the effect at `removed` is deleted from a track chain
+    pub fn effect_removed(&mut self, chain_id: u64, removed: usize) {
+        self.mappings.retain(|m| m.owner != ParamOwner::TrackEffect { chain_id, index: removed });
+        for mapping in &mut self.mappings {
+            if let ParamOwner::TrackEffect { chain_id: id, index } = &mut mapping.owner
+                && *id == chain_id
+                && *index > removed
+            {
+                *index -= 1;
+            }
+        }
+    }
+
+    /// Fix up mappings after the effect at `from` is moved to `to` in a track chain
+    pub fn effect_moved(&mut self, chain_id: u64, from: usize, to: usize) {
+        for mapping in &mut self.mappings {
+            if let ParamOwner::TrackEffect { chain_id: id, index } = &mut mapping.owner
+                && *id == chain_id
+            {
+                *index = moved_index(*index, from, to);
+            }
+        }
+    }
+
+    /// Mappings listening to a controller
+    pub fn for_controller(&mut self, channel: u8, controller: u8) -> impl Iterator<Item = &mut MidiMapping> {
+        self.mappings
+            .iter_mut()
+            .filter(move |m| m.channel == channel && m.controller == controller)
+    }
+}
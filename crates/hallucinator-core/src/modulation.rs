@@ -0,0 +1,358 @@
+//! Per-track modulation matrix: tempo-synced LFOs and envelope followers
+//! driving instrument/effect parameters, evaluated once per audio block
+
+use serde::{Deserialize, Serialize};
+use crate::track::DeviceTarget;
+
+/// Tempo-synced LFO rates as (label, beats per cycle)
+pub const LFO_RATES: &[(&str, f32)] = &[
+    ("1/16", 0.25),
+    ("1/8", 0.5),
+    ("1/4", 1.0),
+    ("1/2", 2.0),
+    ("1 bar", 4.0),
+    ("2 bars", 8.0),
+    ("4 bars", 16.0),
+    ("8 bars", 32.0),
+];
+
+/// LFO waveform
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LfoShape {
+    #[default]
+    Sine,
+    Triangle,
+    Saw,
+    Square,
+    /// Random value held for each cycle
+    SampleHold,
+}
+
+impl LfoShape {
+    pub const ALL: [LfoShape; 5] = [
+        LfoShape::Sine,
+        LfoShape::Triangle,
+        LfoShape::Saw,
+        LfoShape::Square,
+        LfoShape::SampleHold,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LfoShape::Sine => "Sine",
+            LfoShape::Triangle => "Triangle",
+            LfoShape::Saw => "Saw",
+            LfoShape::Square => "Square",
+            LfoShape::SampleHold => "S&H",
+        }
+    }
+}
+
+/// A modulation source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ModSource {
+    /// Bipolar (-1 to 1) LFO locked to the transport tempo
+    Lfo {
+        shape: LfoShape,
+        /// Cycle length in beats
+        rate_beats: f32,
+    },
+    /// Unipolar (0 to 1) level of the track's own signal before its effects
+    Envelope {
+        attack_ms: f32,
+        release_ms: f32,
+        /// Input gain applied before following (1.0 = unity)
+        gain: f32,
+    },
+}
+
+impl ModSource {
+    pub fn lfo() -> Self {
+        ModSource::Lfo { shape: LfoShape::Sine, rate_beats: 4.0 }
+    }
+
+    pub fn envelope() -> Self {
+        ModSource::Envelope { attack_ms: 10.0, release_ms: 150.0, gain: 1.0 }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ModSource::Lfo { .. } => "LFO",
+            ModSource::Envelope { .. } => "Envelope",
+        }
+    }
+}
+
+/// A modulated parameter: one row of the matrix
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModDestination {
+    pub target: DeviceTarget,
+    pub param: String,
+    /// Unmodulated parameter value
+    pub center: f32,
+    pub min: f32,
+    pub max: f32,
+    /// Depth per source (-1 to 1, fraction of the parameter range), indexed like `sources`
+    pub depths: Vec<f32>,
+}
+
+impl ModDestination {
+    pub fn new(target: DeviceTarget, param: impl Into<String>, center: f32, min: f32, max: f32) -> Self {
+        Self {
+            target,
+            param: param.into(),
+            center,
+            min,
+            max,
+            depths: Vec::new(),
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.depths.iter().any(|d| *d != 0.0)
+    }
+}
+
+/// Audio-thread state of a source
+#[derive(Debug, Clone, Default)]
+struct SourceState {
+    phase: f64,
+    held: f32,
+    level: f32,
+    value: f32,
+}
+
+/// Sources x destinations with a depth per cell
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModulationMatrix {
+    pub sources: Vec<ModSource>,
+    pub destinations: Vec<ModDestination>,
+    #[serde(skip)]
+    state: Vec<SourceState>,
+    #[serde(skip)]
+    rng: u32,
+}
+
+impl ModulationMatrix {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// True if no destination has any depth set (nothing to evaluate)
+    pub fn is_idle(&self) -> bool {
+        !self.destinations.iter().any(ModDestination::is_active)
+    }
+
+    pub fn add_source(&mut self, source: ModSource) {
+        self.sources.push(source);
+        for dest in &mut self.destinations {
+            dest.depths.resize(self.sources.len(), 0.0);
+        }
+    }
+
+    /// Remove a source; returns the destinations it leaves unmodulated, whose parameters
+    /// should be put back to their `center`
+    pub fn remove_source(&mut self, index: usize) -> Vec<ModDestination> {
+        if index >= self.sources.len() {
+            return Vec::new();
+        }
+        self.sources.remove(index);
+        if index < self.state.len() {
+            self.state.remove(index);
+        }
+        let mut released = Vec::new();
+        for dest in &mut self.destinations {
+            if index < dest.depths.len() {
+                let was_active = dest.is_active();
+                dest.depths.remove(index);
+                if was_active && !dest.is_active() {
+                    released.push(dest.clone());
+                }
+            }
+        }
+        released
+    }
+
+    /// Add a destination, ignoring duplicates of an already modulated parameter
+    pub fn add_destination(&mut self, mut dest: ModDestination) {
+        if self.destinations.iter().any(|d| d.target == dest.target && d.param == dest.param) {
+            return;
+        }
+        dest.depths.resize(self.sources.len(), 0.0);
+        self.destinations.push(dest);
+    }
+
+    /// Remove a destination; returns it if it was being modulated, so its parameter can be
+    /// put back to its `center`
+    pub fn remove_destination(&mut self, index: usize) -> Option<ModDestination> {
+        if index >= self.destinations.len() {
+            return None;
+        }
+        Some(self.destinations.remove(index)).filter(ModDestination::is_active)
+    }
+
+    /// Set one depth; returns the destination if this leaves it unmodulated, so its
+    /// parameter can be put back to its `center`
+    pub fn set_depth(&mut self, dest: usize, source: usize, depth: f32) -> Option<ModDestination> {
+        let dest = self.destinations.get_mut(dest)?;
+        let was_active = dest.is_active();
+        dest.depths.resize(self.sources.len(), 0.0);
+        if let Some(d) = dest.depths.get_mut(source) {
+            *d = depth.clamp(-1.0, 1.0);
+        }
+        (was_active && !dest.is_active()).then(|| dest.clone())
+    }
+
+    /// Fix up destinations after the effect at `removed` is deleted from the chain
+    pub fn effect_removed(&mut self, removed: usize) {
+        self.destinations.retain(|d| d.target != DeviceTarget::Effect(removed));
+        for dest in &mut self.destinations {
+            if let DeviceTarget::Effect(idx) = &mut dest.target
+                && *idx > removed
+            {
+                *idx -= 1;
+            }
+        }
+    }
+
+    /// Fix up destinations after the effect at `from` is moved to `to`
+    pub fn effect_moved(&mut self, from: usize, to: usize) {
+        for dest in &mut self.destinations {
+            dest.target.effect_moved(from, to);
+        }
+    }
+
+    /// Current output of a source (for display)
+    pub fn source_value(&self, index: usize) -> f32 {
+        self.state.get(index).map_or(0.0, |s| s.value)
+    }
+
+    /// Advance LFOs by one block. `beat` is the transport position in beats at the
+    /// block start while playing (LFOs lock to it), or None to free-run.
+    pub fn advance(&mut self, frames: usize, bpm: f64, sample_rate: u32, beat: Option<f64>) {
+        self.state.resize_with(self.sources.len(), SourceState::default);
+        let beats = frames as f64 * bpm / (60.0 * sample_rate.max(1) as f64);
+
+        for (source, state) in self.sources.iter().zip(self.state.iter_mut()) {
+            let ModSource::Lfo { shape, rate_beats } = *source else { continue };
+            let rate = rate_beats.max(0.0625) as f64;
+            let previous = state.phase;
+            state.phase = match beat {
+                Some(beat) => (beat / rate).fract(),
+                None => (state.phase + beats / rate).fract(),
+            };
+            if state.phase < previous {
+                self.rng ^= self.rng << 13;
+                self.rng ^= self.rng >> 17;
+                self.rng ^= self.rng << 5;
+                if self.rng == 0 {
+                    self.rng = 0x9E37_79B9;
+                }
+                state.held = (self.rng as f32 / u32::MAX as f32) * 2.0 - 1.0;
+            }
+
+            let p = state.phase as f32;
+            state.value = match shape {
+                LfoShape::Sine => (p * std::f32::consts::TAU).sin(),
+                LfoShape::Triangle => 1.0 - 4.0 * (p - 0.5).abs(),
+                LfoShape::Saw => 2.0 * p - 1.0,
+                LfoShape::Square => if p < 0.5 { 1.0 } else { -1.0 },
+                LfoShape::SampleHold => state.held,
+            };
+        }
+    }
+
+    /// Feed the track's pre-effect signal to the envelope followers
+    pub fn follow(&mut self, left: &[f32], right: &[f32], sample_rate: u32) {
+        self.state.resize_with(self.sources.len(), SourceState::default);
+        let sr = sample_rate.max(1) as f32;
+
+        for (source, state) in self.sources.iter().zip(self.state.iter_mut()) {
+            let ModSource::Envelope { attack_ms, release_ms, gain } = *source else { continue };
+            let attack = (-1.0 / (attack_ms.max(0.1) * 0.001 * sr)).exp();
+            let release = (-1.0 / (release_ms.max(1.0) * 0.001 * sr)).exp();
+            let mut level = state.level;
+            for (l, r) in left.iter().zip(right.iter()) {
+                let input = (l.abs().max(r.abs()) * gain).min(1.0);
+                let coeff = if input > level { attack } else { release };
+                level = input + coeff * (level - input);
+            }
+            state.level = level;
+            state.value = level;
+        }
+    }
+
+    /// Modulated (target, param, value) for every destination with a non-zero depth
+    pub fn values(&self) -> impl Iterator<Item = (DeviceTarget, &str, f32)> + '_ {
+        self.destinations.iter().filter(|d| d.is_active()).map(|dest| {
+            let amount: f32 = dest
+                .depths
+                .iter()
+                .enumerate()
+                .map(|(i, depth)| depth * self.source_value(i))
+                .sum();
+            let value = dest.center + amount * (dest.max - dest.min);
+            (dest.target, dest.param.as_str(), value.clamp(dest.min.min(dest.max), dest.max.max(dest.min)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(source: ModSource) -> ModulationMatrix {
+        let mut matrix = ModulationMatrix::new();
+        matrix.add_source(source);
+        matrix.add_destination(ModDestination::new(DeviceTarget::Instrument, "cutoff", 0.5, 0.0, 1.0));
+        matrix
+    }
+
+    #[test]
+    fn test_lfo_locks_to_the_transport_beat() {
+        let mut m = matrix(ModSource::Lfo { shape: LfoShape::Saw, rate_beats: 4.0 });
+        m.set_depth(0, 0, 0.25);
+        // A quarter of the way through a 4-beat cycle, wherever it free-ran to before
+        m.advance(512, 120.0, 48_000, None);
+        m.advance(512, 120.0, 48_000, Some(1.0));
+        assert!((m.source_value(0) + 0.5).abs() < 1e-6);
+        let (_, param, value) = m.values().next().unwrap();
+        assert_eq!(param, "cutoff");
+        assert!((value - 0.375).abs() < 1e-6);
+
+        // Free-running from there: 24_000 frames at 120 bpm is one more beat
+        m.advance(24_000, 120.0, 48_000, None);
+        assert!(m.source_value(0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_envelope_follows_and_releases() {
+        let mut m = matrix(ModSource::Envelope { attack_ms: 1.0, release_ms: 10.0, gain: 1.0 });
+        let loud = vec![0.5; 4800];
+        m.follow(&loud, &loud, 48_000);
+        assert!((m.source_value(0) - 0.5).abs() < 1e-3);
+
+        // 100 ms of silence is ten release time constants
+        let silence = vec![0.0; 4800];
+        m.follow(&silence, &silence, 48_000);
+        assert!(m.source_value(0) < 1e-3);
+    }
+
+    #[test]
+    fn test_unmodulated_destinations_are_handed_back() {
+        let mut m = matrix(ModSource::lfo());
+        assert_eq!(m.set_depth(0, 0, 0.5), None);
+        let released = m.set_depth(0, 0, 0.0).unwrap();
+        assert_eq!((released.param.as_str(), released.center), ("cutoff", 0.5));
+        assert!(m.is_idle());
+
+        m.set_depth(0, 0, 0.5);
+        assert_eq!(m.remove_source(0).len(), 1);
+        assert_eq!(m.remove_destination(0), None);
+
+        m.add_source(ModSource::lfo());
+        m.add_destination(ModDestination::new(DeviceTarget::Effect(0), "mix", 0.3, 0.0, 1.0));
+        m.set_depth(0, 0, -0.2);
+        assert_eq!(m.remove_destination(0).map(|d| d.center), Some(0.3));
+    }
+}
@@ -0,0 +1,148 @@
+//! Pan laws and the track panner: balance, or true stereo panning with a width control
+
+use std::f32::consts::{FRAC_PI_4, SQRT_2};
+
+use serde::{Deserialize, Serialize};
+
+/// Gain curve of a source swept across the stereo field, named by its level at the centre
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PanLaw {
+    /// Sine/cosine with the centre at unity: loudness holds across the sweep and a source
+    /// panned hard to one side rises 3 dB
+    #[default]
+    ConstantPower,
+    /// Sine/cosine, -3 dB at the centre and unity hard left or right
+    Minus3Db,
+    /// Linear, -6 dB at the centre and unity hard left or right
+    Minus6Db,
+}
+
+impl PanLaw {
+    pub const ALL: [Self; 3] = [Self::ConstantPower, Self::Minus3Db, Self::Minus6Db];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::ConstantPower => "Constant power (0 dB centre)",
+            Self::Minus3Db => "-3 dB centre",
+            Self::Minus6Db => "-6 dB centre (linear)",
+        }
+    }
+
+    /// Left and right gains of a source at `pan` (-1.0 left, 0.0 center, 1.0 right)
+    pub fn gains(self, pan: f32) -> (f32, f32) {
+        match self {
+            Self::ConstantPower => {
+                let (left, right) = sine_gains(pan);
+                (left * SQRT_2, right * SQRT_2)
+            }
+            Self::Minus3Db => sine_gains(pan),
+            Self::Minus6Db => {
+                let pan = pan.clamp(-1.0, 1.0);
+                ((1.0 - pan) * 0.5, (1.0 + pan) * 0.5)
+            }
+        }
+    }
+
+    /// The law's curve scaled to unity hard left or right, for placing the two sides of a
+    /// stereo signal without changing its level when they sit at the edges
+    fn edge_gains(self, pan: f32) -> (f32, f32) {
+        match self {
+            Self::ConstantPower => sine_gains(pan),
+            other => other.gains(pan),
+        }
+    }
+}
+
+fn sine_gains(pan: f32) -> (f32, f32) {
+    let angle = (pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+    (angle.cos(), angle.sin())
+}
+
+/// How a track's pan acts on its stereo signal
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PanMode {
+    /// Scale the left and right channels by the pan law; the image shifts but each side
+    /// stays on its own channel
+    #[default]
+    Balance,
+    /// Place each side of the signal in the field, spread around the pan position by the
+    /// track's width (full width keeps the image as recorded, none folds it to mono)
+    Stereo,
+}
+
+impl PanMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Balance => "Balance",
+            Self::Stereo => "Stereo pan",
+        }
+    }
+}
+
+/// Pan a block of stereo audio in place. `width` (0.0 to 1.0) only applies to `PanMode::Stereo`.
+pub fn pan_stereo(law: PanLaw, mode: PanMode, pan: f32, width: f32, left: &mut [f32], right: &mut [f32]) {
+    match mode {
+        PanMode::Balance => {
+            let (left_gain, right_gain) = law.gains(pan);
+            if left_gain == 1.0 && right_gain == 1.0 {
+                return;
+            }
+            left.iter_mut().for_each(|s| *s *= left_gain);
+            right.iter_mut().for_each(|s| *s *= right_gain);
+        }
+        PanMode::Stereo => {
+            let spread = width.clamp(0.0, 1.0);
+            let (ll, lr) = law.edge_gains(pan - spread);
+            let (rl, rr) = law.edge_gains(pan + spread);
+            for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+                let (from_left, from_right) = (*l, *r);
+                *l = from_left * ll + from_right * rl;
+                *r = from_left * lr + from_right * rr;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn test_pan_laws_and_modes() {
+        // Centre levels: 0, -3 and -6 dB
+        let (l, r) = PanLaw::ConstantPower.gains(0.0);
+        assert!(close(l, 1.0) && close(r, 1.0));
+        assert!(close(PanLaw::Minus3Db.gains(0.0).0, std::f32::consts::FRAC_1_SQRT_2));
+        assert_eq!(PanLaw::Minus6Db.gains(0.0), (0.5, 0.5));
+        for law in PanLaw::ALL {
+            let (l, r) = law.gains(0.3);
+            assert!(r > l);
+        }
+        assert!(close(PanLaw::Minus3Db.gains(1.0).1, 1.0));
+
+        // Balance hard right silences the left channel
+        let (mut left, mut right) = (vec![0.5; 4], vec![0.25; 4]);
+        pan_stereo(PanLaw::Minus6Db, PanMode::Balance, 1.0, 1.0, &mut left, &mut right);
+        assert_eq!(left, [0.0; 4]);
+        assert_eq!(right, [0.25; 4]);
+
+        // Stereo pan at full width and centre leaves the image as it is
+        let (mut left, mut right) = (vec![0.5; 4], vec![0.25; 4]);
+        pan_stereo(PanLaw::ConstantPower, PanMode::Stereo, 0.0, 1.0, &mut left, &mut right);
+        assert!(close(left[0], 0.5) && close(right[0], 0.25));
+
+        // Hard right at full width: the left side moves to the centre, the right stays hard right
+        pan_stereo(PanLaw::Minus3Db, PanMode::Stereo, 1.0, 1.0, &mut left, &mut right);
+        assert!(close(left[0], 0.5 * std::f32::consts::FRAC_1_SQRT_2));
+        assert!(close(right[0], 0.25 + 0.5 * std::f32::consts::FRAC_1_SQRT_2));
+
+        // No width folds to mono
+        let (mut left, mut right) = (vec![1.0], vec![0.0]);
+        pan_stereo(PanLaw::Minus6Db, PanMode::Stereo, 0.0, 0.0, &mut left, &mut right);
+        assert_eq!((left[0], right[0]), (0.5, 0.5));
+    }
+}
@@ -1,8 +1,67 @@
 //! Pattern bank for Factory Rat-style sequencing
 
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 use crate::clip::MidiClip;
 
+/// Parameter locks of one drum step (Elektron-style): values that replace the sample
+/// kit's settings for the voices that step triggers, and only for those
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct StepLocks {
+    /// Pitch in semitones
+    pub pitch: Option<f32>,
+    /// Decay in seconds
+    pub decay: Option<f32>,
+    /// Level 0-1, scaling the trigger velocity
+    pub level: Option<f32>,
+}
+
+impl StepLocks {
+    pub fn is_empty(&self) -> bool {
+        self.pitch.is_none() && self.decay.is_none() && self.level.is_none()
+    }
+}
+
+/// One step of a saved drum pattern
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DrumStepData {
+    pub active: bool,
+    /// Rows the step triggers (bit N = row N)
+    pub rows: u16,
+    #[serde(default)]
+    pub locks: StepLocks,
+}
+
+/// The sample on one drum row of a saved pattern
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DrumRowData {
+    pub sample_name: Option<String>,
+    /// File the sample was loaded from (None for samples pasted from memory)
+    pub sample_path: Option<PathBuf>,
+    pub enabled: bool,
+    /// Steps the row cycles through (0 = the whole pattern)
+    #[serde(default)]
+    pub length: u8,
+}
+
+impl Default for DrumRowData {
+    fn default() -> Self {
+        Self { sample_name: None, sample_path: None, enabled: true, length: 0 }
+    }
+}
+
+/// Keyboard sequencer drum pattern: steps, row samples and loop length
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DrumPatternData {
+    /// 4, 6, 8 or 12
+    pub step_count: usize,
+    /// Loop length in bars
+    pub loop_bars: u8,
+    pub steps: Vec<DrumStepData>,
+    pub rows: Vec<DrumRowData>,
+}
+
 /// A single pattern slot in the bank
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PatternSlot {
@@ -13,6 +72,9 @@ pub struct PatternSlot {
     pub length_bars: u8,
     /// Pattern name (e.g., "Intro", "Verse")
     pub name: String,
+    /// Keyboard sequencer drum pattern stored in this slot
+    #[serde(default)]
+    pub drum: Option<DrumPatternData>,
 }
 
 impl PatternSlot {
@@ -21,6 +83,7 @@ impl PatternSlot {
             clip: None,
             length_bars,
             name: String::new(),
+            drum: None,
         }
     }
 
@@ -29,11 +92,12 @@ impl PatternSlot {
             clip: Some(clip),
             length_bars,
             name: String::new(),
+            drum: None,
         }
     }
 
     pub fn is_empty(&self) -> bool {
-        self.clip.as_ref().map_or(true, |c| c.notes.is_empty())
+        self.clip.as_ref().is_none_or(|c| c.notes.is_empty()) && self.drum.is_none()
     }
 }
 
@@ -0,0 +1,183 @@
+//! Waveform overviews: the min and max of a clip's audio per block, at several block
+//! sizes, so drawing a long clip reads a few blocks per pixel rather than every sample
+
+use std::fmt;
+
+/// Frames per block at the finest level
+pub const PEAK_BLOCK: usize = 64;
+/// Blocks of one level merged into each block of the next
+const LEVEL_FACTOR: usize = 4;
+/// Start of a peak file
+const MAGIC: &[u8; 4] = b"HPK1";
+
+/// Min and max of the mono mix per block of frames, level 0 holding `PEAK_BLOCK` frames
+/// per block and each following level `LEVEL_FACTOR` times as many, down to a single block
+#[derive(Clone, PartialEq)]
+pub struct WaveformPeaks {
+    frames: usize,
+    levels: Vec<Vec<(f32, f32)>>,
+}
+
+impl WaveformPeaks {
+    /// Summarise interleaved samples
+    pub fn from_samples(samples: &[f32], channels: usize) -> Self {
+        let channels = channels.max(1);
+        let frames = samples.len() / channels;
+        let base = samples
+            .chunks(PEAK_BLOCK * channels)
+            .map(|block| {
+                block.chunks_exact(channels).fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), frame| {
+                    let mono = frame.iter().sum::<f32>() / channels as f32;
+                    (min.min(mono), max.max(mono))
+                })
+            })
+            .collect();
+
+        let mut levels: Vec<Vec<(f32, f32)>> = vec![base];
+        while let Some(last) = levels.last().filter(|level| level.len() > 1) {
+            let next = last.chunks(LEVEL_FACTOR).map(merge).collect();
+            levels.push(next);
+        }
+        Self { frames, levels }
+    }
+
+    /// Frames summarised
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Min and max over frames `start..end`, read from the coarsest level whose blocks are no
+    /// bigger than the range, so the range is widened to at most that level's block edges.
+    /// None for an empty range or one past the end
+    pub fn range(&self, start: usize, end: usize) -> Option<(f32, f32)> {
+        let end = end.min(self.frames);
+        if start >= end {
+            return None;
+        }
+        let span = end - start;
+        let mut level = 0;
+        let mut block = PEAK_BLOCK;
+        while level + 1 < self.levels.len() && block * LEVEL_FACTOR <= span {
+            level += 1;
+            block *= LEVEL_FACTOR;
+        }
+        let blocks = self.levels[level].get(start / block..=(end - 1) / block)?;
+        Some(merge(blocks))
+    }
+
+    /// Encode for a peak file
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16 + self.levels[0].len() * 8 * 2);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&(self.frames as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.levels.len() as u32).to_le_bytes());
+        for level in &self.levels {
+            bytes.extend_from_slice(&(level.len() as u64).to_le_bytes());
+            for &(min, max) in level {
+                bytes.extend_from_slice(&min.to_le_bytes());
+                bytes.extend_from_slice(&max.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Decode a peak file; None if it's truncated or not one
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut reader = Reader(bytes.strip_prefix(MAGIC)?);
+        let frames = reader.u64()? as usize;
+        let level_count = reader.u32()? as usize;
+        let mut levels = Vec::with_capacity(level_count.min(64));
+        for _ in 0..level_count {
+            let len = reader.u64()? as usize;
+            let level = (0..len).map(|_| Some((reader.f32()?, reader.f32()?))).collect::<Option<Vec<_>>>()?;
+            levels.push(level);
+        }
+        let expected = frames.div_ceil(PEAK_BLOCK);
+        if levels.first().is_none_or(|base| base.len() != expected) {
+            return None;
+        }
+        Some(Self { frames, levels })
+    }
+}
+
+impl fmt::Debug for WaveformPeaks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaveformPeaks")
+            .field("frames", &self.frames)
+            .field("levels", &self.levels.len())
+            .finish()
+    }
+}
+
+/// Extremes of non-empty blocks
+fn merge(blocks: &[(f32, f32)]) -> (f32, f32) {
+    blocks.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &(lo, hi)| (min.min(lo), max.max(hi)))
+}
+
+/// Little-endian fields read off the front of a byte slice
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (head, rest) = self.0.split_first_chunk::<N>()?;
+        self.0 = rest;
+        Some(*head)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.take().map(u64::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        self.take().map(f32::from_le_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ramp from -1 to 1 in stereo, both channels alike
+    fn ramp(frames: usize) -> Vec<f32> {
+        (0..frames).flat_map(|i| {
+            let value = i as f32 / (frames - 1) as f32 * 2.0 - 1.0;
+            [value, value]
+        }).collect()
+    }
+
+    #[test]
+    fn test_range_matches_samples() {
+        let samples = ramp(10_000);
+        let peaks = WaveformPeaks::from_samples(&samples, 2);
+        assert_eq!(peaks.frames(), 10_000);
+
+        let (min, max) = peaks.range(0, 10_000).unwrap();
+        assert_eq!((min, max), (-1.0, 1.0));
+
+        // A range on block edges reads exactly those blocks
+        let (min, max) = peaks.range(PEAK_BLOCK * 4, PEAK_BLOCK * 8).unwrap();
+        assert_eq!(min, samples[PEAK_BLOCK * 4 * 2]);
+        assert_eq!(max, samples[(PEAK_BLOCK * 8 - 1) * 2]);
+    }
+
+    #[test]
+    fn test_range_outside_is_none() {
+        let peaks = WaveformPeaks::from_samples(&ramp(1000), 2);
+        assert!(peaks.range(1000, 1200).is_none());
+        assert!(peaks.range(500, 500).is_none());
+        assert!(peaks.range(900, 5000).is_some());
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let peaks = WaveformPeaks::from_samples(&ramp(5000), 2);
+        let bytes = peaks.to_bytes();
+        assert_eq!(WaveformPeaks::from_bytes(&bytes), Some(peaks));
+        assert_eq!(WaveformPeaks::from_bytes(&bytes[..bytes.len() - 3]), None);
+        assert_eq!(WaveformPeaks::from_bytes(b"nope"), None);
+    }
+}
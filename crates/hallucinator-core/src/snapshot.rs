@@ -0,0 +1,175 @@
+//! Mixer and device snapshots, recalled from A/B/C slots or morphed between over time
+
+use serde::{Deserialize, Serialize};
+use crate::track::{DeviceTarget, Track, TrackId};
+
+/// Number of snapshot slots (A, B, C)
+pub const SNAPSHOT_SLOTS: usize = 3;
+
+/// Parameter values of one instrument or effect
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceState {
+    pub target: DeviceTarget,
+    /// Device name when captured; recall skips a different device now in the same place
+    pub device: String,
+    /// (parameter name, value)
+    pub params: Vec<(String, f32)>,
+}
+
+/// Mixer settings and device parameters of one track
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackState {
+    pub track: TrackId,
+    pub volume: f32,
+    pub pan: f32,
+    pub devices: Vec<DeviceState>,
+}
+
+impl TrackState {
+    /// Set the track's fader to the snapshot's volume and pan
+    pub fn apply_mixer(&self, track: &mut Track) {
+        track.volume = self.volume;
+        track.pan = self.pan;
+    }
+}
+
+/// Every track's mixer and device state at one moment
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MixSnapshot {
+    pub tracks: Vec<TrackState>,
+}
+
+impl MixSnapshot {
+    pub fn track(&self, id: TrackId) -> Option<&TrackState> {
+        self.tracks.iter().find(|t| t.track == id)
+    }
+
+    /// Blend towards `to` by `t` (0 = this snapshot, 1 = `to`). Only what `to` holds is
+    /// in the result; tracks, devices and parameters missing here take `to`'s value.
+    pub fn morph(&self, to: &MixSnapshot, t: f32) -> MixSnapshot {
+        let t = t.clamp(0.0, 1.0);
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        let tracks = to.tracks.iter().map(|end| {
+            let Some(start) = self.track(end.track) else {
+                return end.clone();
+            };
+            let devices = end.devices.iter().map(|device| {
+                let from = start.devices.iter()
+                    .find(|d| d.target == device.target && d.device == device.device);
+                let params = device.params.iter().map(|(name, value)| {
+                    let start_value = from
+                        .and_then(|d| d.params.iter().find(|(n, _)| n == name))
+                        .map_or(*value, |(_, v)| *v);
+                    (name.clone(), lerp(start_value, *value))
+                }).collect();
+                DeviceState { target: device.target, device: device.device.clone(), params }
+            }).collect();
+            TrackState {
+                track: end.track,
+                volume: lerp(start.volume, end.volume),
+                pan: lerp(start.pan, end.pan),
+                devices,
+            }
+        }).collect();
+        MixSnapshot { tracks }
+    }
+}
+
+/// The project's snapshot slots
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotBank {
+    pub slots: [Option<MixSnapshot>; SNAPSHOT_SLOTS],
+}
+
+impl SnapshotBank {
+    /// Slot letter (A, B, C)
+    pub fn slot_name(slot: usize) -> char {
+        (b'A' + slot as u8) as char
+    }
+}
+
+/// A morph in progress from one snapshot to another
+#[derive(Debug, Clone)]
+pub struct SnapshotMorph {
+    pub from: MixSnapshot,
+    pub to: MixSnapshot,
+    /// Length in beats
+    pub beats: f64,
+}
+
+impl SnapshotMorph {
+    /// Progress (0-1) after `elapsed_secs` at `bpm`
+    pub fn progress(&self, elapsed_secs: f64, bpm: f64) -> f32 {
+        if self.beats <= 0.0 {
+            return 1.0;
+        }
+        (elapsed_secs * bpm / 60.0 / self.beats).clamp(0.0, 1.0) as f32
+    }
+
+    pub fn at(&self, progress: f32) -> MixSnapshot {
+        self.from.morph(&self.to, progress)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(volume: f32, cutoff: f32, device: &str) -> MixSnapshot {
+        MixSnapshot {
+            tracks: vec![TrackState {
+                track: TrackId(1),
+                volume,
+                pan: 0.0,
+                devices: vec![DeviceState {
+                    target: DeviceTarget::Effect(0),
+                    device: device.to_string(),
+                    params: vec![("cutoff".to_string(), cutoff)],
+                }],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_morph_blends_matching_devices() {
+        let a = snapshot(0.0, 100.0, "Filter");
+        let b = snapshot(1.0, 300.0, "Filter");
+        let half = a.morph(&b, 0.5);
+        assert_eq!(half.tracks[0].volume, 0.5);
+        assert_eq!(half.tracks[0].devices[0].params[0].1, 200.0);
+        assert_eq!(a.morph(&b, 2.0), b);
+
+        // A different device in the same slot jumps straight to the target
+        let other = snapshot(1.0, 300.0, "Delay");
+        assert_eq!(a.morph(&other, 0.5).tracks[0].devices[0].params[0].1, 300.0);
+    }
+
+    #[test]
+    fn test_recall_sets_instrument_track_level() {
+        use crate::pan::PanLaw;
+        use crate::track::TrackKind;
+
+        let mut track = Track::new(TrackId(1), TrackKind::Midi, "Synth");
+        let render = |track: &Track| {
+            let (mut left, mut right) = (vec![0.8; 64], vec![0.8; 64]);
+            track.apply_fader(PanLaw::ConstantPower, &mut left, &mut right);
+            left.iter().chain(&right).fold(0.0f32, |peak, s| peak.max(s.abs()))
+        };
+        assert!((render(&track) - 0.8).abs() < 1e-6);
+
+        snapshot(0.25, 100.0, "Filter").tracks[0].apply_mixer(&mut track);
+        assert!((render(&track) - 0.2).abs() < 1e-6);
+
+        let half = snapshot(0.25, 100.0, "Filter").morph(&snapshot(0.75, 100.0, "Filter"), 0.5);
+        half.tracks[0].apply_mixer(&mut track);
+        assert!((render(&track) - 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_morph_progress_follows_tempo() {
+        let morph = SnapshotMorph { from: MixSnapshot::default(), to: MixSnapshot::default(), beats: 8.0 };
+        assert_eq!(morph.progress(2.0, 120.0), 0.5);
+        assert_eq!(morph.progress(10.0, 120.0), 1.0);
+        assert_eq!(SnapshotBank::slot_name(2), 'C');
+    }
+}
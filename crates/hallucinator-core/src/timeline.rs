@@ -1,7 +1,13 @@
 //! Timeline containing tracks
 
 use serde::{Deserialize, Serialize};
-use crate::track::{Track, TrackId, TrackKind};
+use crate::chord_track::ChordTrack;
+use crate::clip::AudioClip;
+use crate::groove::Groove;
+use crate::midi_map::MidiMap;
+use crate::pan::PanLaw;
+use crate::snapshot::SnapshotBank;
+use crate::track::{Track, TrackId, TrackKind, TrackOutput};
 use crate::transport::Transport;
 
 /// The main timeline containing all tracks
@@ -9,6 +15,21 @@ use crate::transport::Transport;
 pub struct Timeline {
     pub tracks: Vec<Track>,
     pub transport: Transport,
+    /// Hardware controller mappings (MIDI learn)
+    #[serde(default)]
+    pub midi_map: MidiMap,
+    /// Mixer/device snapshot slots
+    #[serde(default)]
+    pub snapshots: SnapshotBank,
+    /// Groove pool
+    #[serde(default)]
+    pub grooves: Vec<Groove>,
+    /// Chord per region, followed by MIDI effects
+    #[serde(default)]
+    pub chord_track: ChordTrack,
+    /// Pan law of every track's panner
+    #[serde(default)]
+    pub pan_law: PanLaw,
     next_track_id: u64,
 }
 
@@ -17,6 +38,11 @@ impl Timeline {
         Self {
             tracks: Vec::new(),
             transport: Transport::new(sample_rate),
+            midi_map: MidiMap::default(),
+            snapshots: SnapshotBank::default(),
+            grooves: Vec::new(),
+            chord_track: ChordTrack::default(),
+            pan_law: PanLaw::default(),
             next_track_id: 1,
         }
     }
@@ -34,6 +60,33 @@ impl Timeline {
         Some(self.tracks.remove(pos))
     }
 
+    /// Insert a copy of the track at `idx` just below it, under a new ID and without its
+    /// clips unless `with_clips`; returns the copy's index. The copy keeps the original's
+    /// instrument and effect chain IDs until the caller gives it its own.
+    pub fn duplicate_track(&mut self, idx: usize, with_clips: bool) -> Option<usize> {
+        let mut track = self.tracks.get(idx)?.clone();
+        track.id = TrackId(self.next_track_id);
+        self.next_track_id += 1;
+        track.name = format!("{} copy", track.name);
+        track.armed = false;
+        if !with_clips {
+            track.clips.clear();
+            track.midi_clips.clear();
+        }
+        self.tracks.insert(idx + 1, track);
+        Some(idx + 1)
+    }
+
+    /// Move the track at `from` to position `to`; routes follow track IDs and are kept
+    pub fn move_track(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.tracks.len() || to >= self.tracks.len() || from == to {
+            return false;
+        }
+        let track = self.tracks.remove(from);
+        self.tracks.insert(to, track);
+        true
+    }
+
     pub fn get_track(&self, id: TrackId) -> Option<&Track> {
         self.tracks.iter().find(|t| t.id == id)
     }
@@ -42,6 +95,113 @@ impl Timeline {
         self.tracks.iter_mut().find(|t| t.id == id)
     }
 
+    /// Every audio clip, including the renders of frozen tracks
+    pub fn audio_clips(&self) -> impl Iterator<Item = &AudioClip> {
+        self.tracks.iter().flat_map(|t| t.clips.iter().chain(&t.freeze))
+    }
+
+    pub fn audio_clips_mut(&mut self) -> impl Iterator<Item = &mut AudioClip> {
+        self.tracks.iter_mut().flat_map(|t| t.clips.iter_mut().chain(&mut t.freeze))
+    }
+
+    /// One past the highest audio or MIDI clip ID in use (1 when there are no clips)
+    pub fn next_clip_id(&self) -> u64 {
+        let midi = self.tracks.iter().flat_map(|t| &t.midi_clips).map(|c| c.id.0);
+        self.audio_clips().map(|c| c.id.0).chain(midi).max().map_or(1, |id| id + 1)
+    }
+
+    /// Indices of the tracks feeding the track at `idx`: those it takes signal from and
+    /// those whose output is routed into it
+    fn feeders(&self, idx: usize) -> Vec<usize> {
+        let track = &self.tracks[idx];
+        self.tracks.iter().enumerate()
+            .filter(|&(i, t)| i != idx && (t.output == TrackOutput::Track(track.id) || track.sources().any(|id| id == t.id)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Track indices in an order that renders each track after the tracks feeding it
+    /// (tracks caught in a feedback loop come last, in track order)
+    pub fn render_order(&self) -> Vec<usize> {
+        let feeders: Vec<Vec<usize>> = (0..self.tracks.len()).map(|idx| self.feeders(idx)).collect();
+        let mut placed = vec![false; self.tracks.len()];
+        let mut order = Vec::with_capacity(self.tracks.len());
+        loop {
+            let ready: Vec<usize> = (0..self.tracks.len())
+                .filter(|&idx| !placed[idx] && feeders[idx].iter().all(|&f| placed[f]))
+                .collect();
+            if ready.is_empty() {
+                break;
+            }
+            for idx in ready {
+                placed[idx] = true;
+                order.push(idx);
+            }
+        }
+        order.extend((0..self.tracks.len()).filter(|&idx| !placed[idx]));
+        order
+    }
+
+    /// Index of the track the track at `idx` sends its output into, if any
+    fn output_track(&self, idx: usize) -> Option<usize> {
+        match self.tracks[idx].output {
+            TrackOutput::Track(id) => self.tracks.iter().position(|t| t.id == id).filter(|&dest| dest != idx),
+            _ => None,
+        }
+    }
+
+    /// Samples to hold back each track's output (by index) so signals meet in step where
+    /// they mix, given each track's own effect latency: tracks routed into the same track
+    /// wait for the slowest of them, and tracks reaching master or a hardware output for the
+    /// slowest path there. Track inputs take their source as it comes.
+    pub fn compensation_delays(&self, latencies: &[usize]) -> Vec<usize> {
+        let count = self.tracks.len();
+        // Latency of each track's output: the slowest track routed into it, plus its own
+        let mut arrival = vec![0usize; count];
+        for idx in self.render_order() {
+            arrival[idx] += latencies.get(idx).copied().unwrap_or(0);
+            if let Some(dest) = self.output_track(idx) {
+                arrival[dest] = arrival[dest].max(arrival[idx]);
+            }
+        }
+
+        let outputs: Vec<Option<usize>> = (0..count).map(|idx| self.output_track(idx)).collect();
+        let mut slowest_into = vec![0usize; count];
+        let mut slowest_out = 0;
+        for (&output, &latency) in outputs.iter().zip(&arrival) {
+            match output {
+                Some(dest) => slowest_into[dest] = slowest_into[dest].max(latency),
+                None => slowest_out = slowest_out.max(latency),
+            }
+        }
+        outputs.iter().zip(&arrival)
+            .map(|(&output, &latency)| match output {
+                Some(dest) => slowest_into[dest].saturating_sub(latency),
+                None => slowest_out.saturating_sub(latency),
+            })
+            .collect()
+    }
+
+    /// Whether `source`'s signal can go into `dest` without coming back around to `source`
+    pub fn can_route(&self, source: TrackId, dest: TrackId) -> bool {
+        let position = |id| self.tracks.iter().position(|t| t.id == id);
+        let (Some(source), Some(dest)) = (position(source), position(dest)) else {
+            return false;
+        };
+        // Refused when `dest` already feeds `source`, directly or through other tracks
+        let mut seen = vec![false; self.tracks.len()];
+        let mut stack = vec![source];
+        while let Some(idx) = stack.pop() {
+            if idx == dest {
+                return false;
+            }
+            if !std::mem::replace(&mut seen[idx], true) {
+                stack.extend(self.feeders(idx));
+            }
+        }
+        true
+    }
+
     /// Check if any track is soloed
     pub fn has_solo(&self) -> bool {
         self.tracks.iter().any(|t| t.solo)
@@ -55,7 +215,7 @@ impl Timeline {
             .iter()
             .filter(|t| t.kind == TrackKind::Audio)
             .filter(|t| !has_solo || t.solo)
-            .map(|t| t.sample_at(timeline_sample))
+            .map(|t| t.sample_at(timeline_sample) * t.volume)
             .sum()
     }
 
@@ -74,3 +234,81 @@ impl Timeline {
         self.duration_samples() as f64 / self.transport.sample_rate as f64
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clip::{ClipId, MidiClip};
+    use crate::track::TrackInput;
+
+    #[test]
+    fn test_routing_order_and_loops() {
+        let mut timeline = Timeline::new(48_000);
+        let bus = timeline.add_track(TrackKind::Audio, "Bus");
+        let drums = timeline.add_track(TrackKind::Audio, "Drums");
+        let resample = timeline.add_track(TrackKind::Audio, "Resample");
+        timeline.tracks[1].output = TrackOutput::Track(bus);
+        timeline.tracks[2].input = TrackInput::Track(bus);
+
+        // Drums before the bus it feeds, the bus before the track taking its output
+        assert_eq!(timeline.render_order(), [1, 0, 2]);
+        assert!(timeline.can_route(bus, resample));
+        assert!(!timeline.can_route(bus, drums));
+        assert!(!timeline.can_route(resample, drums));
+        assert!(!timeline.can_route(bus, bus));
+
+        // Moving a track keeps its routes
+        assert!(timeline.move_track(1, 2));
+        let names: Vec<&str> = timeline.tracks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["Bus", "Resample", "Drums"]);
+        assert_eq!(timeline.render_order(), [2, 0, 1]);
+        assert!(!timeline.move_track(0, 3));
+
+        // A copy goes below the original under a new ID, with the same routing
+        assert_eq!(timeline.duplicate_track(2, false), Some(3));
+        let copy = &timeline.tracks[3];
+        assert_eq!((copy.name.as_str(), copy.output), ("Drums copy", TrackOutput::Track(bus)));
+        assert!(copy.id != drums && copy.clips.is_empty());
+        assert_eq!(timeline.render_order(), [2, 3, 0, 1]);
+        timeline.remove_track(copy.id);
+
+        // A loop set up anyway still renders every track once
+        timeline.tracks[0].output = TrackOutput::Track(drums);
+        assert_eq!(timeline.render_order(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn test_latency_compensation() {
+        let mut timeline = Timeline::new(48_000);
+        for name in ["Outboard", "Keys", "Snare", "Drum Bus", "Kick"] {
+            timeline.add_track(TrackKind::Audio, name);
+        }
+        let bus = timeline.tracks[3].id;
+        timeline.tracks[2].output = TrackOutput::Track(bus);
+        timeline.tracks[4].output = TrackOutput::Track(bus);
+
+        // A 100-sample hardware insert on Outboard, a 10-sample limiter on the bus, 50 on Kick
+        let delays = timeline.compensation_delays(&[100, 0, 0, 10, 50]);
+        // Snare waits for Kick at the bus; the bus (50 + 10 late) and Keys wait for Outboard
+        assert_eq!(delays, [0, 100, 50, 40, 0]);
+    }
+
+    #[test]
+    fn test_next_clip_id_counts_every_clip() {
+        let mut timeline = Timeline::new(48_000);
+        assert_eq!(timeline.next_clip_id(), 1);
+        timeline.add_track(TrackKind::Audio, "Vox");
+        timeline.add_track(TrackKind::Midi, "Keys");
+        timeline.tracks[0].clips.push(AudioClip::new(ClipId(3), vec![0.5; 8], 48_000, 1));
+        timeline.tracks[1].midi_clips.push(MidiClip::new(ClipId(5), 1000));
+        assert_eq!(timeline.next_clip_id(), 6);
+
+        // A frozen track's render counts too, and copies of the timeline share clip audio
+        timeline.tracks[1].freeze = Some(AudioClip::new(ClipId(9), vec![0.0; 8], 48_000, 2));
+        assert_eq!(timeline.next_clip_id(), 10);
+        let copy = timeline.clone();
+        let ids: Vec<u64> = copy.audio_clips().map(|c| c.id.0).collect();
+        assert_eq!(ids, [3, 9]);
+        assert!(std::sync::Arc::ptr_eq(&copy.tracks[0].clips[0].samples, &timeline.tracks[0].clips[0].samples));
+    }
+}
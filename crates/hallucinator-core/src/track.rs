@@ -1,8 +1,13 @@
 //! Track representation
 
 use serde::{Deserialize, Serialize};
+use crate::automation::Automation;
 use crate::clip::{AudioClip, ClipId, MidiClip};
+use crate::crossfade::{crossfades_for, Crossfade};
+use crate::macro_knobs::MacroBank;
+use crate::modulation::ModulationMatrix;
 use crate::midi_fx::MidiFxChain;
+use crate::pan::{pan_stereo, PanLaw, PanMode};
 use crate::pattern::PatternBank;
 
 /// Unique identifier for tracks
@@ -17,6 +22,171 @@ pub enum TrackKind {
     Master,
 }
 
+/// Device on a track whose parameters can be controlled (macros, modulation, automation)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DeviceTarget {
+    /// The track's instrument
+    Instrument,
+    /// Effect at this index in the track's effect chain
+    Effect(usize),
+}
+
+impl DeviceTarget {
+    /// Keep pointing at the same effect after the one at `from` moves to `to`
+    pub fn effect_moved(&mut self, from: usize, to: usize) {
+        if let Self::Effect(index) = self {
+            *index = moved_index(*index, from, to);
+        }
+    }
+}
+
+/// Where the chain entry at `index` ends up once the entry at `from` is moved to `to`
+pub fn moved_index(index: usize, from: usize, to: usize) -> usize {
+    if index == from {
+        to
+    } else if from < index && index <= to {
+        index - 1
+    } else if to <= index && index < from {
+        index + 1
+    } else {
+        index
+    }
+}
+
+/// Auxiliary output of another track's instrument (e.g. one pad of a drum
+/// sampler), played through this track instead of the instrument's main output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstrumentOutput {
+    /// Track whose instrument renders the output
+    pub track: TrackId,
+    /// Output bus index on that instrument (bus 0 is the main output)
+    pub bus: usize,
+}
+
+/// Input of an audio track: interface channels it records from (channel numbers are 0-based),
+/// or another track's output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrackInput {
+    /// Every input channel mixed to mono
+    #[default]
+    MonoMix,
+    /// One input channel, recorded mono
+    Mono(u16),
+    /// Two adjacent input channels from this left channel, recorded as a stereo clip
+    Stereo(u16),
+    /// Another track's output after its effects, played through this track while it
+    /// monitors (a take records the interface's mono mix)
+    Track(TrackId),
+}
+
+impl TrackInput {
+    /// Channels in a clip recorded from this input
+    pub fn channels(self) -> u16 {
+        match self {
+            Self::Stereo(_) | Self::Track(_) => 2,
+            _ => 1,
+        }
+    }
+
+    /// Short name with 1-based channel numbers, e.g. "In 3/4"
+    pub fn label(self) -> String {
+        match self {
+            Self::MonoMix => "In Mix".to_string(),
+            Self::Mono(ch) => format!("In {}", ch + 1),
+            Self::Stereo(left) => format!("In {}/{}", left + 1, left + 2),
+            Self::Track(_) => "In Track".to_string(),
+        }
+    }
+
+    /// Whether a device with `device_channels` inputs has every channel this input reads
+    pub fn fits(self, device_channels: u16) -> bool {
+        match self {
+            Self::MonoMix => true,
+            Self::Mono(ch) => ch < device_channels,
+            Self::Stereo(left) => left + 1 < device_channels,
+            Self::Track(_) => false,
+        }
+    }
+
+    /// Inputs selectable on a device with `device_channels` channels: the mix, each channel,
+    /// then each odd/even pair
+    pub fn choices(device_channels: u16) -> Vec<Self> {
+        let mut choices = vec![Self::MonoMix];
+        choices.extend((0..device_channels).map(Self::Mono));
+        choices.extend((0..device_channels.saturating_sub(1)).step_by(2).map(Self::Stereo));
+        choices
+    }
+}
+
+/// Where a track's signal goes after its effects
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrackOutput {
+    /// The master bus
+    #[default]
+    Master,
+    /// Into another track ahead of its effects, so that track acts as a bus
+    Track(TrackId),
+    /// Two adjacent interface outputs from this left channel (0-based), after the master
+    /// chain and not metered
+    Hardware(u16),
+}
+
+impl TrackOutput {
+    /// Short name with 1-based channel numbers for hardware outputs, e.g. "Out 3/4"
+    pub fn label(self) -> String {
+        match self {
+            Self::Master => "Master".to_string(),
+            Self::Track(_) => "Track".to_string(),
+            Self::Hardware(left) => format!("Out {}/{}", left + 1, left + 2),
+        }
+    }
+
+    /// Hardware outputs on a device with `device_channels` channels: each odd/even pair
+    pub fn hardware_choices(device_channels: u16) -> Vec<Self> {
+        (0..device_channels.saturating_sub(1)).step_by(2).map(Self::Hardware).collect()
+    }
+}
+
+/// When an audio track plays its live input through its effect chain (software monitoring)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrackMonitor {
+    /// Never; only the track's clips play
+    Off,
+    /// While armed, when the transport is stopped or recording
+    #[default]
+    Auto,
+    /// Always, armed or not
+    On,
+}
+
+impl TrackMonitor {
+    /// Next mode for a header button that cycles Auto → On → Off
+    pub fn next(self) -> Self {
+        match self {
+            Self::Auto => Self::On,
+            Self::On => Self::Off,
+            Self::Off => Self::Auto,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Auto => "Auto",
+            Self::On => "In",
+        }
+    }
+}
+
+/// Saved state of a track's plugin instrument (patch, samples, settings)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginState {
+    /// Class ID of the plugin the chunk belongs to
+    pub plugin_uid: String,
+    /// Opaque component state, as produced by the plugin
+    pub chunk: Vec<u8>,
+}
+
 /// A track in the timeline
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Track {
@@ -27,28 +197,68 @@ pub struct Track {
     pub volume: f32,
     /// Pan (-1.0 left, 0.0 center, 1.0 right)
     pub pan: f32,
+    /// Whether pan balances the channels or places them with `width`
+    #[serde(default)]
+    pub pan_mode: PanMode,
+    /// Stereo width for `PanMode::Stereo` (0.0 mono to 1.0 as recorded)
+    #[serde(default = "full_width")]
+    pub width: f32,
     /// Muted state
     pub mute: bool,
     /// Solo state
     pub solo: bool,
     /// Armed for recording
     pub armed: bool,
+    /// Input channels recorded when armed (audio tracks)
+    #[serde(default)]
+    pub input: TrackInput,
+    /// Whether live input plays through the track (audio tracks)
+    #[serde(default)]
+    pub monitor: TrackMonitor,
+    /// Where the track's signal goes after its effects
+    #[serde(default)]
+    pub output: TrackOutput,
     /// Audio clips on this track
-    #[serde(skip)]
+    #[serde(default)]
     pub clips: Vec<AudioClip>,
+    /// Crossfades where audio clips overlap, kept up to date by `update_crossfades`
+    #[serde(default)]
+    pub crossfades: Vec<Crossfade>,
     /// MIDI clips on this track
-    #[serde(skip)]
+    #[serde(default)]
     pub midi_clips: Vec<MidiClip>,
     /// Assigned VST instrument ID (for MIDI tracks)
     pub instrument_id: Option<u64>,
     /// Assigned effect chain ID (for per-track effects)
     pub effect_chain_id: Option<u64>,
+    /// Instrument output bus this track plays (multi-output instruments)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instrument_output: Option<InstrumentOutput>,
     /// Pattern bank (16 patterns per track, Factory Rat-style)
     #[serde(default)]
     pub pattern_bank: PatternBank,
     /// MIDI FX chain (up to 8 effects)
     #[serde(default)]
     pub midi_fx_chain: MidiFxChain,
+    /// Macro knobs mapped to instrument/effect parameters
+    #[serde(default)]
+    pub macros: MacroBank,
+    /// LFO/envelope modulation of instrument/effect parameters
+    #[serde(default)]
+    pub modulation: ModulationMatrix,
+    /// Breakpoint automation of instrument/effect parameters
+    #[serde(default)]
+    pub automation: Automation,
+    /// Rendered output of a frozen track (instrument and effects), played instead of them
+    #[serde(default)]
+    pub freeze: Option<AudioClip>,
+    /// Instrument plugin state, captured when the project is saved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instrument_state: Option<PluginState>,
+}
+
+fn full_width() -> f32 {
+    1.0
 }
 
 impl Track {
@@ -59,25 +269,144 @@ impl Track {
             name: name.into(),
             volume: 1.0,
             pan: 0.0,
+            pan_mode: PanMode::default(),
+            width: 1.0,
             mute: false,
             solo: false,
             armed: false,
+            input: TrackInput::default(),
+            monitor: TrackMonitor::default(),
+            output: TrackOutput::default(),
             clips: Vec::new(),
+            crossfades: Vec::new(),
             midi_clips: Vec::new(),
             instrument_id: None,
             effect_chain_id: None,
+            instrument_output: None,
             pattern_bank: PatternBank::default(),
             midi_fx_chain: MidiFxChain::default(),
+            macros: MacroBank::default(),
+            modulation: ModulationMatrix::default(),
+            automation: Automation::default(),
+            freeze: None,
+            instrument_state: None,
+        }
+    }
+
+    /// Drop macro mappings, modulation and automation aimed at a deleted effect, and follow the ones after it
+    pub fn effect_removed(&mut self, removed: usize) {
+        self.macros.effect_removed(removed);
+        self.modulation.effect_removed(removed);
+        self.automation.effect_removed(removed);
+    }
+
+    /// Drop routing from or to a deleted track: its instrument outputs stop, and input and
+    /// output fall back to the interface mix and master
+    pub fn track_removed(&mut self, removed: TrackId) {
+        if self.instrument_output.is_some_and(|o| o.track == removed) {
+            self.instrument_output = None;
+        }
+        if self.input == TrackInput::Track(removed) {
+            self.input = TrackInput::MonoMix;
         }
+        if self.output == TrackOutput::Track(removed) {
+            self.output = TrackOutput::Master;
+        }
+    }
+
+    /// Tracks this track takes signal from by its own settings: its input and the track
+    /// whose instrument output it plays
+    pub(crate) fn sources(&self) -> impl Iterator<Item = TrackId> + '_ {
+        let input = match self.input {
+            TrackInput::Track(id) => Some(id),
+            _ => None,
+        };
+        input.into_iter().chain(self.instrument_output.map(|o| o.track))
+    }
+
+    /// Follow an effect moved from one chain slot to another
+    pub fn effect_moved(&mut self, from: usize, to: usize) {
+        self.macros.effect_moved(from, to);
+        self.modulation.effect_moved(from, to);
+        self.automation.effect_moved(from, to);
     }
 
     pub fn add_clip(&mut self, clip: AudioClip) {
         self.clips.push(clip);
+        self.update_crossfades();
     }
 
     pub fn remove_clip(&mut self, clip_id: ClipId) -> Option<AudioClip> {
         let pos = self.clips.iter().position(|c| c.id == clip_id)?;
-        Some(self.clips.remove(pos))
+        let clip = self.clips.remove(pos);
+        self.update_crossfades();
+        Some(clip)
+    }
+
+    /// Create, fit or drop crossfades after audio clips were added, moved, trimmed or removed
+    pub fn update_crossfades(&mut self) {
+        self.crossfades = crossfades_for(&self.clips, &self.crossfades);
+    }
+
+    /// Gain the crossfades apply to a clip at a timeline position
+    fn crossfade_gain(&self, clip_id: ClipId, timeline_sample: u64) -> f32 {
+        self.crossfades.iter().map(|x| x.clip_gain(clip_id, timeline_sample)).product()
+    }
+
+    /// Replace the audio clips lying within `start_sample..end_sample` with one stereo clip
+    /// `id` rendered from them, with their gain and the crossfades between them; false, with
+    /// nothing changed, when fewer than two clips lie there
+    pub fn consolidate_audio(&mut self, start_sample: u64, end_sample: u64, id: ClipId) -> bool {
+        let mut merged: Vec<&AudioClip> = self.clips
+            .iter()
+            .filter(|c| c.start_sample >= start_sample && c.end_sample() <= end_sample)
+            .collect();
+        if merged.len() < 2 {
+            return false;
+        }
+        merged.sort_by_key(|c| c.start_sample);
+        let ids: Vec<ClipId> = merged.iter().map(|c| c.id).collect();
+        let crossfades: Vec<&Crossfade> = self.crossfades
+            .iter()
+            .filter(|x| ids.contains(&x.out_clip) && ids.contains(&x.in_clip))
+            .collect();
+
+        let mut samples = Vec::with_capacity((end_sample - start_sample) as usize * 2);
+        for timeline_sample in start_sample..end_sample {
+            let (left, right) = merged
+                .iter()
+                .filter_map(|clip| {
+                    let (left, right) = clip.frame_at(timeline_sample)?;
+                    let gain: f32 = crossfades.iter().map(|x| x.clip_gain(clip.id, timeline_sample)).product();
+                    Some((left * gain, right * gain))
+                })
+                .fold((0.0, 0.0), |(l, r), (cl, cr)| (l + cl, r + cr));
+            samples.extend([left, right]);
+        }
+        let mut clip = AudioClip::new(id, samples, merged[0].sample_rate, 2);
+        clip.start_sample = start_sample;
+        clip.name = merged[0].name.clone();
+        clip.build_peaks();
+
+        self.clips.retain(|c| !ids.contains(&c.id));
+        self.add_clip(clip);
+        true
+    }
+
+    /// Replace the MIDI clips lying within `start_sample..end_sample` with one clip `id`
+    /// holding all their notes; false, with nothing changed, when fewer than two clips lie there
+    pub fn consolidate_midi(&mut self, start_sample: u64, end_sample: u64, id: ClipId, samples_per_beat: f64) -> bool {
+        let inside = |c: &MidiClip| c.start_sample >= start_sample && c.end_sample() <= end_sample;
+        let mut merged: Vec<&MidiClip> = self.midi_clips.iter().filter(|c| inside(c)).collect();
+        if merged.len() < 2 {
+            return false;
+        }
+        merged.sort_by_key(|c| c.start_sample);
+        let clip = MidiClip::consolidate(id, &merged, start_sample, end_sample, samples_per_beat);
+
+        self.midi_clips.retain(|c| !inside(c));
+        self.midi_clips.push(clip);
+        true
     }
 
     pub fn get_clip(&self, clip_id: ClipId) -> Option<&AudioClip> {
@@ -88,18 +417,57 @@ impl Track {
         self.clips.iter_mut().find(|c| c.id == clip_id)
     }
 
-    /// Get audio sample at timeline position (summed from all clips)
+    pub fn is_frozen(&self) -> bool {
+        self.freeze.is_some()
+    }
+
+    /// Whether the track plays live input now, given the transport state
+    pub fn monitors_input(&self, recording: bool, playing: bool) -> bool {
+        if self.kind != TrackKind::Audio || self.mute {
+            return false;
+        }
+        match self.monitor {
+            TrackMonitor::Off => false,
+            TrackMonitor::Auto => self.armed && (recording || !playing),
+            TrackMonitor::On => true,
+        }
+    }
+
+    /// Get audio sample at timeline position (summed from all clips, ahead of the fader)
     pub fn sample_at(&self, timeline_sample: u64) -> f32 {
         if self.mute {
             return 0.0;
         }
 
-        let raw: f32 = self.clips
+        self.clips
+            .iter()
+            .filter_map(|clip| Some(clip.sample_at(timeline_sample)? * self.crossfade_gain(clip.id, timeline_sample)))
+            .sum()
+    }
+
+    /// Get the (left, right) frame at timeline position, summed from all clips (ahead of the
+    /// fader), so stereo clips keep their image
+    pub fn frame_at(&self, timeline_sample: u64) -> (f32, f32) {
+        if self.mute {
+            return (0.0, 0.0);
+        }
+
+        self.clips
             .iter()
-            .filter_map(|clip| clip.sample_at(timeline_sample))
-            .sum();
+            .filter_map(|clip| {
+                let (left, right) = clip.frame_at(timeline_sample)?;
+                let gain = self.crossfade_gain(clip.id, timeline_sample);
+                Some((left * gain, right * gain))
+            })
+            .fold((0.0, 0.0), |(l, r), (cl, cr)| (l + cl, r + cr))
+    }
 
-        raw * self.volume
+    /// Apply the fader to the track's signal after its effects: volume, then pan
+    pub fn apply_fader(&self, law: PanLaw, left: &mut [f32], right: &mut [f32]) {
+        if self.volume != 1.0 {
+            left.iter_mut().chain(right.iter_mut()).for_each(|s| *s *= self.volume);
+        }
+        pan_stereo(law, self.pan_mode, self.pan, self.width, left, right);
     }
 
     pub fn add_midi_clip(&mut self, clip: MidiClip) {
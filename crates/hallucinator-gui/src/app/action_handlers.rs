@@ -1,12 +1,19 @@
-use hallucinator_core::ClipId;
-use hallucinator_services::{Instrument, Vst3PluginInfo};
+use std::sync::Arc;
+use std::time::Instant;
 
-use super::HallucinatorApp;
-use super::types::SelectedClip;
+use egui::Context;
+
+use hallucinator_core::{
+    moved_index, ClipId, DeviceState, DeviceTarget, MidiEffect, MixSnapshot, SnapshotMorph, TrackInput, TrackOutput, TrackState,
+};
+use hallucinator_services::{notifications, create_native_effect, AnalyzerTap, ClockSync, EffectChain, LiveEvent, Oversampling, Vst3PluginInfo};
+
+use super::{presets, surfaces, HallucinatorApp};
+use super::types::{EffectChainRef, MidiLearnRequest, SelectedClip};
 use crate::panels::{
-    ArrangeAction, BrowserAction, DeviceInfo, DeviceRackAction, DrumRollAction,
-    KeyboardSequencerAction, MidiFxRackAction, PianoRollAction, PluginAction, SongViewAction,
-    TrackHeaderAction,
+    ArrangeAction, AudioSettingsAction, AutomationAction, BrowserAction, ChordTrackAction, DeviceInfo, DeviceRackAction, DrumRollAction,
+    KeyboardSequencerAction, KeymapEditorAction, MappableDevice, MidiFxRackAction, ModulationAction, PianoRollAction, PluginAction,
+    PreferencesAction, SnapshotAction, SongViewAction, TrackHeaderAction,
 };
 
 impl HallucinatorApp {
@@ -23,6 +30,7 @@ impl HallucinatorApp {
             }
             TrackHeaderAction::ToggleArm(idx) => {
                 self.with_track_mut(idx, |track| track.armed = !track.armed);
+                self.sync_input_monitoring();
             }
             TrackHeaderAction::SetVolume(idx, vol) => {
                 self.with_track_mut(idx, |track| track.volume = vol);
@@ -30,17 +38,33 @@ impl HallucinatorApp {
             TrackHeaderAction::SetPan(idx, pan) => {
                 self.with_track_mut(idx, |track| track.pan = pan);
             }
+            TrackHeaderAction::SetPanMode(idx, mode) => {
+                self.with_track_mut(idx, |track| track.pan_mode = mode);
+            }
+            TrackHeaderAction::SetWidth(idx, width) => {
+                self.with_track_mut(idx, |track| track.width = width.clamp(0.0, 1.0));
+            }
+            TrackHeaderAction::SetPanLaw(law) => {
+                self.engine.with_timeline(|timeline| timeline.pan_law = law);
+            }
+            TrackHeaderAction::MoveTrack(from, to) => self.move_track(from, to),
             TrackHeaderAction::DeleteTrack(idx) => {
                 self.engine.with_timeline(|timeline| {
                     if idx < timeline.tracks.len() {
-                        timeline.tracks.remove(idx);
+                        let removed = timeline.tracks.remove(idx);
+                        // Tracks routed from or to it lose that route
+                        for track in &mut timeline.tracks {
+                            track.track_removed(removed.id);
+                        }
                     }
                 });
+                self.engine_state.sync_instrument_outputs();
                 if self.selected_track_idx == Some(idx) {
                     self.selected_track_idx = None;
                     self.selected_clip = None;
                 }
             }
+            TrackHeaderAction::DuplicateTrack(idx, with_clips) => self.duplicate_track(idx, with_clips),
             TrackHeaderAction::AddAudioTrack => {
                 self.add_audio_track();
             }
@@ -50,6 +74,32 @@ impl HallucinatorApp {
             TrackHeaderAction::RenameTrack(idx, name) => {
                 self.with_track_mut(idx, |track| track.name = name);
             }
+            TrackHeaderAction::SetInput(idx, input) => {
+                let source = match input {
+                    TrackInput::Track(id) => Some(id),
+                    _ => None,
+                };
+                if self.set_route(idx, source, None, |track| track.input = input) {
+                    self.sync_input_monitoring();
+                }
+            }
+            TrackHeaderAction::SetOutput(idx, output) => {
+                let dest = match output {
+                    TrackOutput::Track(id) => Some(id),
+                    _ => None,
+                };
+                self.set_route(idx, None, dest, |track| track.output = output);
+            }
+            TrackHeaderAction::SetMonitor(idx, monitor) => {
+                self.with_track_mut(idx, |track| track.monitor = monitor);
+                self.sync_input_monitoring();
+            }
+            TrackHeaderAction::RouteInstrumentOutputs(idx) => {
+                self.route_instrument_outputs(idx);
+            }
+            TrackHeaderAction::Freeze(idx) => self.freeze_track(idx),
+            TrackHeaderAction::Unfreeze(idx) => self.unfreeze_track(idx),
+            TrackHeaderAction::BounceLoop(idx) => self.bounce_loop(idx),
             TrackHeaderAction::None => {}
         }
     }
@@ -70,6 +120,14 @@ impl HallucinatorApp {
                     self.delete_selected_clip(clip);
                 }
             }
+            ArrangeAction::BounceClip { track_idx, clip_id } => self.bounce_clip(track_idx, clip_id),
+            ArrangeAction::ConsolidateClips { track_idx, start_sample, end_sample, midi } => {
+                self.consolidate_clips(track_idx, start_sample, end_sample, midi);
+            }
+            ArrangeAction::ExtractGroove { track_idx, clip_id } => self.extract_groove(track_idx, clip_id),
+            ArrangeAction::ExtractDrumHits { track_idx, clip_id, pitch } => {
+                self.extract_drum_hits(track_idx, clip_id, pitch);
+            }
             ArrangeAction::Seek(samples) => {
                 self.engine.seek(samples);
             }
@@ -86,6 +144,40 @@ impl HallucinatorApp {
                 self.engine.set_loop_region(start_sample, end_sample);
                 self.engine.set_loop_enabled(true);
             }
+            ArrangeAction::DropSample { track_idx, path, start_sample } => {
+                self.import_audio_file(&path, track_idx, start_sample);
+            }
+            ArrangeAction::MoveClip { track_idx, clip_id, start_sample } => {
+                self.with_track_mut(track_idx, |track| {
+                    if let Some(clip) = track.get_clip_mut(clip_id) {
+                        clip.start_sample = start_sample;
+                        track.update_crossfades();
+                    } else if let Some(clip) = track.get_midi_clip_mut(clip_id) {
+                        clip.start_sample = start_sample;
+                    }
+                });
+            }
+            ArrangeAction::TrimClip { track_idx, clip_id, start_sample, end_sample } => {
+                self.with_track_mut(track_idx, |track| {
+                    if let Some(clip) = track.get_clip_mut(clip_id) {
+                        clip.set_bounds(start_sample, end_sample);
+                        track.update_crossfades();
+                    } else if let Some(clip) = track.get_midi_clip_mut(clip_id) {
+                        // MIDI clips are only trimmed at the end
+                        clip.length_samples = end_sample.saturating_sub(clip.start_sample);
+                    }
+                });
+            }
+            ArrangeAction::EditCrossfade { track_idx, out_clip, in_clip, length, curve } => {
+                self.with_track_mut(track_idx, |track| {
+                    if let Some(fade) = track.crossfades.iter_mut().find(|x| x.out_clip == out_clip && x.in_clip == in_clip) {
+                        fade.length = length;
+                        fade.curve = curve;
+                    }
+                    // Fits the length to the overlap and centres the fade again
+                    track.update_crossfades();
+                });
+            }
             ArrangeAction::None => {}
         }
     }
@@ -95,6 +187,13 @@ impl HallucinatorApp {
             DeviceRackAction::OpenPluginWindow(id) => {
                 tracing::info!("OpenPluginWindow action for id={}", id);
 
+                // VST3 instruments also get the host window (programs, preset files, sliders)
+                let vst3_name = self.engine_state.instruments.lock().ok()
+                    .and_then(|instruments| Some(instruments.get(&id)?.vst3_plugin_info()?.name.clone()));
+                if let Some(name) = vst3_name {
+                    self.open_plugin_window(id, name);
+                }
+
                 if self.gui_manager.has_window(id) {
                     if let Err(e) = self.gui_manager.show_window(id) {
                         tracing::warn!("Failed to show plugin window: {}", e);
@@ -109,27 +208,16 @@ impl HallucinatorApp {
                     return;
                 };
 
-                // Check for VST3 plugin info first
-                if let Some(info) = inst.vst3_plugin_info() {
-                    let (title, plugin_path, plugin_uid) = (
-                        info.name.clone(),
-                        info.info.path.to_string_lossy().to_string(),
-                        info.info.unique_id.clone(),
-                    );
+                // VST3 instruments open their native editor
+                if inst.vst3_plugin_info().is_some() {
                     drop(instruments);
-                    self.open_native_plugin_gui(id, &plugin_path, &plugin_uid, &title);
+                    self.open_native_plugin_gui(id);
                     return;
                 }
 
-                // Handle native instrument param window toggle
-                let is_native = inst.is_drum();
-                tracing::info!("Checking if id={} is native: {}", id, is_native);
+                // Everything that isn't VST3 is a native instrument with a param window
                 drop(instruments);
 
-                if !is_native {
-                    return;
-                }
-
                 let action = if self.native_param_windows.contains(&id) {
                     "Closing"
                 } else {
@@ -143,28 +231,299 @@ impl HallucinatorApp {
                     self.native_param_windows.insert(id);
                 }
             }
-            DeviceRackAction::ToggleBypass(_id) => {}
-            DeviceRackAction::RemoveDevice(_id) => {}
-            DeviceRackAction::AddEffect => {}
+            DeviceRackAction::ToggleBypass(id) => {
+                let bypassed = self.engine_state.is_instrument_bypassed(id);
+                self.engine_state.set_instrument_bypass(id, !bypassed);
+            }
+            DeviceRackAction::RemoveDevice(id) => {
+                let Some(chain_id) = self.selected_effect_chain_id() else { return };
+                let index = id as usize;
+                let removed = self.engine.with_track_effects(chain_id, |chain| chain.remove(index)).flatten();
+                let Some(effect) = removed else { return };
+                // Dropping the effect releases its plugin instance; keep that out of the engine lock
+                drop(effect);
+                self.engine.with_timeline(|timeline| {
+                    if let Some(track) = timeline.tracks.iter_mut().find(|t| t.effect_chain_id == Some(chain_id)) {
+                        track.effect_removed(index);
+                    }
+                    timeline.midi_map.effect_removed(chain_id, index);
+                });
+                self.remap_effect_windows(chain_id, |i| match i.cmp(&index) {
+                    std::cmp::Ordering::Less => Some(i),
+                    std::cmp::Ordering::Equal => None,
+                    std::cmp::Ordering::Greater => Some(i - 1),
+                });
+            }
+            DeviceRackAction::MoveEffect(from, to) => {
+                let Some(chain_id) = self.selected_effect_chain_id() else { return };
+                let len = self.engine.with_track_effects(chain_id, |chain| {
+                    chain.move_effect(from, to);
+                    chain.len()
+                });
+                if len.is_none_or(|len| from >= len || to >= len) {
+                    return;
+                }
+                self.engine.with_timeline(|timeline| {
+                    if let Some(track) = timeline.tracks.iter_mut().find(|t| t.effect_chain_id == Some(chain_id)) {
+                        track.effect_moved(from, to);
+                    }
+                    timeline.midi_map.effect_moved(chain_id, from, to);
+                });
+                self.remap_effect_windows(chain_id, |i| Some(moved_index(i, from, to)));
+            }
+            DeviceRackAction::AddEffect(effect_id) => {
+                let Some(track_idx) = self.selected_track_idx else { return };
+                let Some(chain_id) = self.ensure_effect_chain(track_idx) else { return };
+                let Some(mut effect) = create_native_effect(effect_id, self.engine.sample_rate() as f32) else {
+                    tracing::warn!("Unknown native effect '{}'", effect_id);
+                    return;
+                };
+                if effect.analyzer_tap().is_some() {
+                    self.layout.show_analyzer = true;
+                }
+                // Hardware inserts start from the calibrated round trip of the interface
+                let hardware_insert = effect.hardware_insert().is_some();
+                if hardware_insert {
+                    effect.set_param("latency", self.transport_panel.latency_offset as f32);
+                }
+                let index = self.engine.with_track_effects(chain_id, |chain| {
+                    chain.add(effect);
+                    chain.len() - 1
+                });
+                if hardware_insert {
+                    self.sync_input_monitoring();
+                }
+                if let Some(index) = index {
+                    self.effect_param_windows.insert((EffectChainRef::Track(chain_id), index));
+                }
+            }
+            DeviceRackAction::AddVst3Effect(info) => {
+                let Some(track_idx) = self.selected_track_idx else { return };
+                let Some(chain_id) = self.ensure_effect_chain(track_idx) else { return };
+                self.load_vst3_effect(&info, EffectChainRef::Track(chain_id));
+            }
+            DeviceRackAction::LoadSampler(path) => self.load_sampler(&path),
+            DeviceRackAction::OpenEffectWindow(index) => {
+                let Some(chain_id) = self.selected_effect_chain_id() else { return };
+                self.toggle_effect_window(EffectChainRef::Track(chain_id), index);
+            }
+            DeviceRackAction::ToggleEffectBypass(index) => {
+                let Some(chain_id) = self.selected_effect_chain_id() else { return };
+                self.engine.with_track_effects(chain_id, |chain| {
+                    let bypassed = chain.is_bypassed(index);
+                    chain.set_bypass(index, !bypassed);
+                });
+            }
+            DeviceRackAction::SetEffectOversampling(index, mode) => {
+                let Some(chain_id) = self.selected_effect_chain_id() else { return };
+                let sample_rate = self.engine.sample_rate() as f32;
+                self.engine.with_track_effects(chain_id, |chain| {
+                    chain.set_oversampling(index, mode, sample_rate);
+                });
+            }
+            DeviceRackAction::SetEffectMix(index, mix) => {
+                let Some(chain_id) = self.selected_effect_chain_id() else { return };
+                self.engine.with_track_effects(chain_id, |chain| chain.set_mix(index, mix));
+            }
+            DeviceRackAction::SetMacro(index, value) => {
+                let Some(track_idx) = self.selected_track_idx else { return };
+                let mut updates = Vec::new();
+                self.with_track_mut(track_idx, |track| updates = track.macros.set_value(index, value));
+                self.apply_macro_updates(track_idx, updates);
+            }
+            DeviceRackAction::RenameMacro(index, name) => {
+                let Some(track_idx) = self.selected_track_idx else { return };
+                self.with_track_mut(track_idx, |track| {
+                    if let Some(knob) = track.macros.knobs.get_mut(index) {
+                        knob.name = name;
+                    }
+                });
+            }
+            DeviceRackAction::MapMacro(index, mapping) => {
+                let Some(track_idx) = self.selected_track_idx else { return };
+                self.with_track_mut(track_idx, |track| track.macros.add_mapping(index, mapping));
+            }
+            DeviceRackAction::UpdateMacroMapping(index, mapping_idx, mapping) => {
+                let Some(track_idx) = self.selected_track_idx else { return };
+                self.with_track_mut(track_idx, |track| {
+                    if let Some(slot) = track.macros.knobs.get_mut(index).and_then(|k| k.mappings.get_mut(mapping_idx)) {
+                        *slot = mapping;
+                    }
+                });
+            }
+            DeviceRackAction::RemoveMacroMapping(index, mapping_idx) => {
+                let Some(track_idx) = self.selected_track_idx else { return };
+                self.with_track_mut(track_idx, |track| track.macros.remove_mapping(index, mapping_idx));
+            }
             DeviceRackAction::None => {}
         }
     }
 
-    pub(super) fn handle_plugin_action(&mut self, action: PluginAction) {
+    pub(super) fn handle_modulation_action(&mut self, action: ModulationAction) {
+        let Some(track_idx) = self.selected_track_idx else { return };
+        // Destinations no longer modulated, whose parameters go back to their center
+        let mut released = Vec::new();
+        self.with_track_mut(track_idx, |track| {
+            let matrix = &mut track.modulation;
+            match action {
+                ModulationAction::AddSource(source) => matrix.add_source(source),
+                ModulationAction::RemoveSource(idx) => released = matrix.remove_source(idx),
+                ModulationAction::UpdateSource(idx, source) => {
+                    if let Some(slot) = matrix.sources.get_mut(idx) {
+                        *slot = source;
+                    }
+                }
+                ModulationAction::AddDestination(dest) => matrix.add_destination(dest),
+                ModulationAction::RemoveDestination(idx) => released.extend(matrix.remove_destination(idx)),
+                ModulationAction::SetCenter(idx, center) => {
+                    if let Some(dest) = matrix.destinations.get_mut(idx) {
+                        dest.center = center;
+                    }
+                }
+                ModulationAction::SetDepth { dest, source, depth } => {
+                    released.extend(matrix.set_depth(dest, source, depth));
+                }
+                ModulationAction::None => {}
+            }
+        });
+        let updates = released.into_iter().map(|dest| (dest.target, dest.param, dest.center)).collect();
+        self.apply_macro_updates(track_idx, updates);
+    }
+
+    pub(super) fn handle_automation_action(&mut self, action: AutomationAction) {
+        let Some(track_idx) = self.selected_track_idx else { return };
+        self.with_track_mut(track_idx, |track| {
+            let automation = &mut track.automation;
+            match action {
+                AutomationAction::AddLane(lane) => {
+                    automation.add_lane(lane);
+                }
+                AutomationAction::RemoveLane(idx) => automation.remove_lane(idx),
+                AutomationAction::SetEnabled(idx, enabled) => {
+                    if let Some(lane) = automation.lanes.get_mut(idx) {
+                        lane.enabled = enabled;
+                    }
+                }
+                AutomationAction::AddPoint(idx, point) => {
+                    if let Some(lane) = automation.lanes.get_mut(idx) {
+                        lane.add_point(point);
+                    }
+                }
+                AutomationAction::MovePoint { lane, index, beat, value } => {
+                    if let Some(lane) = automation.lanes.get_mut(lane) {
+                        lane.move_point(index, beat, value);
+                    }
+                }
+                AutomationAction::RemovePoint(idx, index) => {
+                    if let Some(lane) = automation.lanes.get_mut(idx) {
+                        lane.remove_point(index);
+                    }
+                }
+                AutomationAction::SetShape(idx, index, shape) => {
+                    if let Some(lane) = automation.lanes.get_mut(idx) {
+                        lane.set_shape(index, shape);
+                    }
+                }
+                AutomationAction::SetTension(idx, index, tension) => {
+                    if let Some(lane) = automation.lanes.get_mut(idx) {
+                        lane.set_tension(index, tension);
+                    }
+                }
+                AutomationAction::None => {}
+            }
+        });
+    }
+
+    pub(super) fn handle_midi_learn_request(&mut self, request: MidiLearnRequest) {
+        match request {
+            MidiLearnRequest::Learn(learn) => {
+                if let Some(midi) = &self.midi_input {
+                    midi.arm_learn(learn);
+                }
+            }
+            MidiLearnRequest::Cancel => {
+                if let Some(midi) = &self.midi_input {
+                    midi.cancel_learn();
+                }
+            }
+            MidiLearnRequest::Forget { owner, param } => {
+                self.engine.with_timeline(|t| t.midi_map.forget(owner, &param));
+            }
+            MidiLearnRequest::SetMode { owner, param, mode } => {
+                self.engine.with_timeline(|t| {
+                    if let Some(mapping) = t.midi_map.find_mut(owner, &param) {
+                        mapping.mode = mode;
+                    }
+                });
+            }
+            MidiLearnRequest::SetRange { owner, param, min, max } => {
+                self.engine.with_timeline(|t| {
+                    if let Some(mapping) = t.midi_map.find_mut(owner, &param) {
+                        mapping.min = min;
+                        mapping.max = max;
+                    }
+                });
+            }
+        }
+    }
+
+    pub(super) fn handle_plugin_action(&mut self, ctx: &Context, action: PluginAction) {
         match action {
-            PluginAction::LoadPlugin(info) => self.load_vst3_effect(&info),
+            PluginAction::OpenProject(path) => self.open_project(&path),
+            PluginAction::SaveProject(path) => self.save_project(path),
+            PluginAction::RecoverAutosave => self.open_project(&self.autosave_path()),
+            PluginAction::LoadPlugin(info) => self.load_vst3_effect(&info, EffectChainRef::Master),
             PluginAction::CreateMidiTrack(info) => self.load_instrument_to_track(&info),
             PluginAction::AddAudioTrack => self.add_audio_track(),
             PluginAction::AddMidiTrack => self.add_empty_midi_track(),
+            PluginAction::AddMasterEffect(effect_id) => {
+                let Some(mut effect) = create_native_effect(effect_id, self.engine.sample_rate() as f32) else {
+                    tracing::warn!("Unknown native effect '{}'", effect_id);
+                    return;
+                };
+                if effect.analyzer_tap().is_some() {
+                    self.layout.show_analyzer = true;
+                }
+                // Hardware inserts start from the calibrated round trip of the interface
+                let hardware_insert = effect.hardware_insert().is_some();
+                if hardware_insert {
+                    effect.set_param("latency", self.transport_panel.latency_offset as f32);
+                }
+                let index = self.engine.with_master_effects(|chain| {
+                    chain.add(effect);
+                    chain.len() - 1
+                });
+                if hardware_insert {
+                    self.sync_input_monitoring();
+                }
+                if let Some(index) = index {
+                    self.effect_param_windows.insert((EffectChainRef::Master, index));
+                }
+            }
+            PluginAction::OpenMasterEffect(index) => {
+                self.toggle_effect_window(EffectChainRef::Master, index);
+            }
+            PluginAction::ToggleFavorite(unique_id) => {
+                self.plugin_menu.toggle_favorite(&unique_id);
+                self.save_plugins_config();
+            }
+            PluginAction::SwitchWorkspace(workspace) => {
+                let layout = self.workspaces.switch(&self.layout, workspace);
+                self.apply_layout(ctx, layout);
+            }
+            PluginAction::ResetWorkspace => {
+                let layout = self.workspaces.reset();
+                self.apply_layout(ctx, layout);
+            }
             PluginAction::None => {}
         }
     }
 
     pub(super) fn handle_browser_action(&mut self, action: BrowserAction) {
         match action {
-            BrowserAction::LoadEffect(info) => self.load_vst3_effect(&info),
+            BrowserAction::LoadEffect(info) => self.load_vst3_effect_to_selection(&info),
             BrowserAction::LoadInstrument(info) => self.load_instrument_to_track(&info),
-            BrowserAction::LoadNativeInstrument(info) => self.load_native_drum(info.id),
+            BrowserAction::LoadNativeInstrument(info) => self.load_native_instrument(info.id),
             BrowserAction::AddPlace(path) => {
                 self.browser_panel.add_place(path);
                 self.save_library_config();
@@ -175,12 +534,18 @@ impl HallucinatorApp {
             }
             BrowserAction::SelectFile(path) => {
                 tracing::debug!("Browser SelectFile → clipboard = {:?}", path);
+                if self.browser_panel.auto_preview() {
+                    self.engine.preview_sample(&path);
+                }
                 self.clipboard
                     .copy(crate::clipboard::ClipboardContent::FilePath(path));
             }
             BrowserAction::PreviewSample(path) => {
                 self.engine.preview_sample(&path);
             }
+            BrowserAction::StopPreview => self.engine.stop_preview(),
+            BrowserAction::SetPreview(settings) => self.engine.set_preview_settings(settings),
+            BrowserAction::LoadVstPreset(path) => self.load_vst_preset_file(&path, None),
             BrowserAction::None => {}
         }
     }
@@ -201,6 +566,10 @@ impl HallucinatorApp {
                     tracing::debug!("Toggle drum step {}", step);
                     pattern_changed = true;
                 }
+                KeyboardSequencerAction::SetStepLocks(step) => {
+                    tracing::debug!("Set locks on drum step {}", step);
+                    pattern_changed = true;
+                }
                 KeyboardSequencerAction::PlayNote { pitch, velocity } => {
                     self.send_note_on(track_idx, pitch, velocity);
                 }
@@ -231,14 +600,13 @@ impl HallucinatorApp {
                                 .and_then(|track| track.instrument_id)
                         })
                         .flatten();
-                    let Some(id) = inst_id else { continue };
-                    let Ok(mut instruments) = self.engine_state.instruments.lock() else {
-                        continue;
-                    };
-                    let Some(Instrument::SampleKit(kit)) = instruments.get_mut(&id) else {
-                        continue;
-                    };
-                    kit.trigger_step(step, velocity, active_layers);
+                    let Some(instrument_id) = inst_id else { continue };
+                    self.engine_state.send_live_event(LiveEvent::KitStep {
+                        instrument_id,
+                        step,
+                        velocity,
+                        active_layers,
+                    });
                 }
                 KeyboardSequencerAction::CopyStepSample {
                     from_step,
@@ -306,6 +674,52 @@ impl HallucinatorApp {
                     // State already toggled in UI, just mark pattern as changed
                     pattern_changed = true;
                 }
+                KeyboardSequencerAction::RecordDrumHit { row, velocity, beat } => {
+                    self.record_drum_hit(track_idx, row, velocity, beat);
+                }
+                KeyboardSequencerAction::SetRowLength { row } => {
+                    tracing::debug!("Set drum row {} length", row);
+                    pattern_changed = true;
+                }
+                KeyboardSequencerAction::SavePatternFile(name) => {
+                    let pattern = self.keyboard_sequencer_panel.pattern_data();
+                    match presets::save_drum_pattern(&name, &pattern) {
+                        Ok(()) => self.keyboard_sequencer_panel.set_pattern_files(presets::list_drum_patterns()),
+                        Err(e) => tracing::warn!("Failed to save drum pattern '{}': {}", name, e),
+                    }
+                }
+                KeyboardSequencerAction::LoadPatternFile(name) => {
+                    let Some(pattern) = presets::load_drum_pattern(&name) else {
+                        tracing::warn!("Failed to load drum pattern '{}'", name);
+                        continue;
+                    };
+                    self.recall_drum_pattern(track_idx, &pattern);
+                    pattern_changed = true;
+                }
+                KeyboardSequencerAction::StorePatternSlot(slot) => {
+                    let pattern = self.keyboard_sequencer_panel.pattern_data();
+                    self.with_track_mut(track_idx, |track| {
+                        if let Some(bank_slot) = track.pattern_bank.patterns.get_mut(slot) {
+                            bank_slot.drum = Some(pattern);
+                        }
+                    });
+                }
+                KeyboardSequencerAction::RecallPatternSlot(slot) => {
+                    let pattern = self
+                        .engine
+                        .with_timeline(|t| t.tracks.get(track_idx)?.pattern_bank.patterns.get(slot)?.drum.clone())
+                        .flatten();
+                    let Some(pattern) = pattern else { continue };
+                    self.recall_drum_pattern(track_idx, &pattern);
+                    pattern_changed = true;
+                }
+                KeyboardSequencerAction::ClearPatternSlot(slot) => {
+                    self.with_track_mut(track_idx, |track| {
+                        if let Some(bank_slot) = track.pattern_bank.patterns.get_mut(slot) {
+                            bank_slot.drum = None;
+                        }
+                    });
+                }
             }
         }
 
@@ -352,6 +766,29 @@ impl HallucinatorApp {
                     }
                 });
             }
+            MidiFxRackAction::LoadPreset(name) => {
+                let Some(effects) = presets::load_midi_fx_preset(&name) else {
+                    tracing::warn!("Failed to load MIDI FX preset '{}'", name);
+                    return;
+                };
+                self.with_track_mut(track_idx, |track| {
+                    track.midi_fx_chain.effects.clear();
+                    for effect in effects {
+                        track.midi_fx_chain.add(effect);
+                    }
+                });
+            }
+            MidiFxRackAction::SavePreset(name) => {
+                let chain = self
+                    .engine
+                    .with_timeline(|timeline| Some(timeline.tracks.get(track_idx)?.midi_fx_chain.clone()))
+                    .flatten();
+                let Some(chain) = chain else { return };
+                match presets::save_midi_fx_preset(&name, &chain) {
+                    Ok(()) => self.midi_fx_presets = presets::list_midi_fx_presets(),
+                    Err(e) => tracing::warn!("Failed to save MIDI FX preset '{}': {}", name, e),
+                }
+            }
             MidiFxRackAction::SetParam {
                 effect_idx,
                 param_name,
@@ -363,6 +800,13 @@ impl HallucinatorApp {
                     }
                 });
             }
+            MidiFxRackAction::SetArpStep { effect_idx, step, on } => {
+                self.with_track_mut(track_idx, |track| {
+                    if let Some(MidiEffect::Arpeggiator(arp)) = track.midi_fx_chain.effects.get_mut(effect_idx) {
+                        arp.set_pattern_step(step, on);
+                    }
+                });
+            }
             MidiFxRackAction::None => {}
         }
     }
@@ -406,6 +850,17 @@ impl HallucinatorApp {
                 self.engine.set_loop_region(start_sample, end_sample);
                 self.engine.set_loop_enabled(true);
             }
+            PianoRollAction::CopyNotes { ppq, notes } => {
+                self.clipboard
+                    .copy(crate::clipboard::ClipboardContent::MidiNotes { ppq, notes });
+            }
+            PianoRollAction::RemoveGroove(idx) => {
+                self.engine.with_timeline(|t| {
+                    if idx < t.grooves.len() {
+                        t.grooves.remove(idx);
+                    }
+                });
+            }
             PianoRollAction::RecordNote { .. } => {}
             PianoRollAction::ClipModified | PianoRollAction::None => {}
         }
@@ -456,24 +911,386 @@ impl HallucinatorApp {
             .flatten();
 
         let instrument = inst_id.and_then(|id| {
+            let pending = self.pending_instruments.get(&id);
             let instruments = self.engine_state.instruments.lock().ok()?;
             let name = instruments
                 .get(&id)
                 .map(|i| i.name().to_string())
+                .or_else(|| pending.map(|p| p.name.clone()))
                 .unwrap_or_else(|| "Instrument".to_string());
             Some(DeviceInfo {
                 id,
                 name,
                 is_instrument: true,
-                is_bypassed: false,
+                is_bypassed: self.engine_state.is_instrument_bypassed(id),
                 has_ui: true,
+                oversampling: Oversampling::Off,
+                mix: 1.0,
+                loading: pending.is_some(),
             })
         });
 
-        let effects = Vec::new();
+        let chain_id = self
+            .engine
+            .with_timeline(|timeline| {
+                timeline
+                    .tracks
+                    .get(track_idx)
+                    .and_then(|track| track.effect_chain_id)
+            })
+            .flatten();
+
+        let effects = chain_id
+            .and_then(|id| {
+                self.engine.with_track_effects(id, |chain| {
+                    chain
+                        .effects()
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, effect)| DeviceInfo {
+                            id: idx as u64,
+                            name: effect.name().to_string(),
+                            is_instrument: false,
+                            is_bypassed: chain.is_bypassed(idx),
+                            has_ui: false,
+                            oversampling: chain.oversampling(idx),
+                            mix: chain.mix(idx),
+                            loading: false,
+                        })
+                        .collect()
+                })
+            })
+            .unwrap_or_default();
+
         (instrument, effects)
     }
 
+    /// Access a track or master effect chain
+    /// Instrument and effects of a track whose params macros and modulators can target
+    pub(super) fn mappable_devices(&self, track_idx: usize) -> Vec<MappableDevice> {
+        let Some((inst_id, chain_id)) = self
+            .engine
+            .with_timeline(|timeline| {
+                let track = timeline.tracks.get(track_idx)?;
+                Some((track.instrument_id, track.effect_chain_id))
+            })
+            .flatten()
+        else {
+            return Vec::new();
+        };
+
+        let mut devices = Vec::new();
+        let instrument = inst_id.and_then(|id| {
+            let instruments = self.engine_state.instruments.lock().ok()?;
+            instruments.get(&id).map(|inst| MappableDevice {
+                target: DeviceTarget::Instrument,
+                name: inst.name().to_string(),
+                params: inst.get_params().to_vec(),
+            })
+        });
+        devices.extend(instrument);
+        if let Some(chain_id) = chain_id {
+            let effects = self
+                .engine
+                .with_track_effects(chain_id, |chain| {
+                    chain
+                        .effects()
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, effect)| MappableDevice {
+                            target: DeviceTarget::Effect(idx),
+                            name: format!("{}. {}", idx + 1, effect.name()),
+                            params: effect.get_params(),
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            devices.extend(effects);
+        }
+
+        devices
+    }
+
+    /// Push parameter values (from macros, or a modulation's center) to the track's
+    /// instrument and effects
+    pub(super) fn apply_macro_updates(&self, track_idx: usize, updates: Vec<(DeviceTarget, String, f32)>) {
+        if updates.is_empty() {
+            return;
+        }
+        let Some((inst_id, chain_id)) = self
+            .engine
+            .with_timeline(|timeline| {
+                let track = timeline.tracks.get(track_idx)?;
+                Some((track.instrument_id, track.effect_chain_id))
+            })
+            .flatten()
+        else {
+            return;
+        };
+
+        for (target, param, value) in updates {
+            match target {
+                DeviceTarget::Instrument => {
+                    let Some(id) = inst_id else { continue };
+                    if let Ok(mut instruments) = self.engine_state.instruments.lock() {
+                        if let Some(inst) = instruments.get_mut(&id) {
+                            inst.set_param(&param, value);
+                        }
+                    }
+                }
+                DeviceTarget::Effect(index) => {
+                    let Some(chain_id) = chain_id else { continue };
+                    self.engine.with_track_effects(chain_id, |chain| {
+                        if let Some(effect) = chain.effects_mut().get_mut(index) {
+                            effect.set_param(&param, value);
+                        }
+                    });
+                }
+            }
+        }
+    }
+
+    pub(super) fn handle_snapshot_action(&mut self, action: SnapshotAction) {
+        match action {
+            SnapshotAction::Store(slot) => {
+                let snapshot = self.capture_snapshot();
+                self.engine.with_timeline(|t| t.snapshots.slots[slot] = Some(snapshot));
+            }
+            SnapshotAction::Recall(slot) => {
+                self.snapshot_morph = None;
+                let snapshot = self.engine.with_timeline(|t| t.snapshots.slots[slot].clone()).flatten();
+                if let Some(snapshot) = snapshot {
+                    self.apply_snapshot(&snapshot);
+                }
+            }
+            SnapshotAction::Clear(slot) => {
+                self.engine.with_timeline(|t| t.snapshots.slots[slot] = None);
+            }
+            SnapshotAction::Morph { from, to, bars } => {
+                let morph = self.engine.with_timeline(|t| {
+                    let beats = bars as f64 * t.transport.time_sig_num as f64;
+                    Some(SnapshotMorph {
+                        from: t.snapshots.slots[from].clone()?,
+                        to: t.snapshots.slots[to].clone()?,
+                        beats,
+                    })
+                }).flatten();
+                self.snapshot_morph = morph.map(|morph| (morph, Instant::now()));
+            }
+            SnapshotAction::StopMorph => self.snapshot_morph = None,
+            SnapshotAction::None => {}
+        }
+    }
+
+    pub(super) fn handle_chord_track_action(&mut self, action: ChordTrackAction) {
+        self.engine.with_timeline(|t| match action {
+            ChordTrackAction::Add(region) => {
+                t.chord_track.add(region);
+            }
+            ChordTrackAction::Remove(index) => t.chord_track.remove(index),
+            ChordTrackAction::SetChord(index, chord) => t.chord_track.set_chord(index, chord),
+            ChordTrackAction::SetSpan { index, start_beat, length_beats } => {
+                t.chord_track.set_span(index, start_beat, length_beats);
+            }
+            ChordTrackAction::None => {}
+        });
+    }
+
+    pub(super) fn handle_audio_settings_action(&mut self, action: AudioSettingsAction) {
+        match action {
+            AudioSettingsAction::SetCue(settings) => {
+                if let Err(e) = self.engine.set_cue_settings(settings) {
+                    tracing::warn!("Cue output unavailable, cue sources play in the main mix: {}", e);
+                }
+                let cue = self.engine.cue_settings();
+                self.save_audio_config(|audio| audio.cue = cue);
+            }
+            AudioSettingsAction::SetMetronomeVolume(volume) => {
+                self.engine.set_metronome_volume(volume);
+                self.save_audio_config(|audio| audio.metronome_volume = volume);
+            }
+            AudioSettingsAction::None => {}
+        }
+    }
+
+    /// Apply changed preferences straight away and save them to config.toml
+    pub(super) fn handle_preferences_action(&mut self, ctx: &Context, action: PreferencesAction) {
+        let mut preferences = match action {
+            PreferencesAction::Update(preferences) => preferences,
+            PreferencesAction::Audio(action) => {
+                self.handle_audio_settings_action(action);
+                return;
+            }
+            PreferencesAction::None => return,
+        };
+
+        if preferences.output != self.preferences.output {
+            if let Err(e) = self.engine.set_output_settings(preferences.output.clone()) {
+                notifications::warning("Output unavailable, playing through the default device", e);
+            }
+            preferences.output = self.engine.output_settings();
+        }
+        if preferences.midi_inputs_off != self.preferences.midi_inputs_off {
+            // Close the ports before opening them again
+            self.midi_input = None;
+            self.midi_input = surfaces::start_midi_input(ctx, self.engine_state.clone(), &preferences.midi_inputs_off);
+            if matches!(self.clock_sync, ClockSync::Receive) {
+                self.set_clock_sync(ClockSync::Receive);
+            }
+        }
+        if preferences.theme != self.preferences.theme || preferences.accents != self.preferences.accents {
+            preferences.theme.apply(ctx, &preferences.accents);
+        }
+
+        self.preferences = preferences;
+        self.save_preferences_config();
+    }
+
+    pub(super) fn handle_keymap_editor_action(&mut self, action: KeymapEditorAction) {
+        let keymap = Arc::make_mut(&mut self.keymap);
+        match action {
+            KeymapEditorAction::SetPreset(preset) => keymap.set_preset(preset),
+            KeymapEditorAction::Rebind(command, shortcut) => keymap.rebind(command, shortcut.into_iter().collect()),
+            KeymapEditorAction::Reset(command) => keymap.reset(command),
+            KeymapEditorAction::None => return,
+        }
+        self.save_keymap_config();
+    }
+
+    /// Capture every track's volume, pan and device parameters
+    fn capture_snapshot(&self) -> MixSnapshot {
+        let mixer = self.engine.with_timeline(|t| {
+            t.tracks.iter().map(|track| (track.id, track.volume, track.pan)).collect::<Vec<_>>()
+        }).unwrap_or_default();
+        let tracks = mixer.into_iter().enumerate().map(|(idx, (track, volume, pan))| {
+            let devices = self.mappable_devices(idx).into_iter().map(|device| DeviceState {
+                target: device.target,
+                params: device.params.iter().map(|p| (p.name.clone(), p.value)).collect(),
+                device: device.name,
+            }).collect();
+            TrackState { track, volume, pan, devices }
+        }).collect();
+        MixSnapshot { tracks }
+    }
+
+    /// Set track volumes, pans and device parameters from a snapshot (devices replaced
+    /// since it was taken are left alone)
+    fn apply_snapshot(&self, snapshot: &MixSnapshot) {
+        for state in &snapshot.tracks {
+            let idx = self.engine
+                .with_timeline(|t| t.tracks.iter().position(|track| track.id == state.track))
+                .flatten();
+            let Some(idx) = idx else { continue };
+            self.with_track_mut(idx, |track| state.apply_mixer(track));
+            let devices = self.mappable_devices(idx);
+            let updates = state.devices.iter()
+                .filter(|d| devices.iter().any(|current| current.target == d.target && current.name == d.device))
+                .flat_map(|d| d.params.iter().map(|(name, value)| (d.target, name.clone(), *value)))
+                .collect();
+            self.apply_macro_updates(idx, updates);
+        }
+    }
+
+    /// Apply the running snapshot morph for this frame, ending it once complete
+    pub(super) fn advance_snapshot_morph(&mut self) {
+        let Some((morph, started)) = &self.snapshot_morph else { return };
+        let bpm = self.engine.with_timeline(|t| t.transport.bpm).unwrap_or(120.0);
+        let progress = morph.progress(started.elapsed().as_secs_f64(), bpm);
+        let snapshot = morph.at(progress);
+        if progress >= 1.0 {
+            self.snapshot_morph = None;
+        }
+        self.apply_snapshot(&snapshot);
+    }
+
+    pub(super) fn with_effect_chain<F, R>(&self, chain: EffectChainRef, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut EffectChain) -> R,
+    {
+        match chain {
+            EffectChainRef::Master => self.engine.with_master_effects(f),
+            EffectChainRef::Track(chain_id) => self.engine.with_track_effects(chain_id, f),
+        }
+    }
+
+    /// Keep open parameter windows on their effects after a track chain is rearranged
+    fn remap_effect_windows(&mut self, chain_id: u64, remap: impl Fn(usize) -> Option<usize>) {
+        self.effect_param_windows = std::mem::take(&mut self.effect_param_windows)
+            .into_iter()
+            .filter_map(|(chain, index)| match chain {
+                EffectChainRef::Track(id) if id == chain_id => Some((chain, remap(index)?)),
+                _ => Some((chain, index)),
+            })
+            .collect();
+    }
+
+    fn toggle_effect_window(&mut self, chain: EffectChainRef, index: usize) {
+        if !self.effect_param_windows.remove(&(chain, index)) {
+            self.effect_param_windows.insert((chain, index));
+        }
+    }
+
+    /// Analyzer taps on the master chain and all track chains, labelled by source
+    pub(super) fn analyzer_sources(&self) -> Vec<(String, Arc<AnalyzerTap>)> {
+        let mut sources: Vec<(String, Arc<AnalyzerTap>)> = self
+            .engine
+            .with_master_effects(|chain| {
+                chain.effects().iter().filter_map(|e| e.analyzer_tap()).collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tap| ("Master".to_string(), tap))
+            .collect();
+
+        let track_chains = self
+            .engine
+            .with_timeline(|timeline| {
+                timeline
+                    .tracks
+                    .iter()
+                    .filter_map(|t| Some((t.name.clone(), t.effect_chain_id?)))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        for (name, chain_id) in track_chains {
+            let taps = self
+                .engine
+                .with_track_effects(chain_id, |chain| {
+                    chain.effects().iter().filter_map(|e| e.analyzer_tap()).collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            sources.extend(taps.into_iter().map(|tap| (name.clone(), tap)));
+        }
+
+        sources
+    }
+
+    /// Effect chain ID of the selected track, if it has one
+    fn selected_effect_chain_id(&self) -> Option<u64> {
+        let track_idx = self.selected_track_idx?;
+        self.engine
+            .with_timeline(|timeline| timeline.tracks.get(track_idx)?.effect_chain_id)
+            .flatten()
+    }
+
+    /// Effect chain ID of a track, allocating one on first use
+    pub(super) fn ensure_effect_chain(&mut self, track_idx: usize) -> Option<u64> {
+        let next_id = self.next_effect_chain_id;
+        let chain_id = self
+            .engine
+            .with_timeline(|timeline| {
+                let track = timeline.tracks.get_mut(track_idx)?;
+                Some(*track.effect_chain_id.get_or_insert(next_id))
+            })
+            .flatten()?;
+        if chain_id == next_id {
+            self.next_effect_chain_id += 1;
+        }
+        self.engine.ensure_track_effects(chain_id);
+        Some(chain_id)
+    }
+
     pub(super) fn get_plugins(&self) -> Vec<Vst3PluginInfo> {
         self.plugin_menu
             .scanner()
@@ -506,7 +1323,8 @@ impl HallucinatorApp {
         })
     }
 
-    /// Send note-on to the instrument on the given track.
+    /// Send note-on to the instrument on the given track; the engine plays it a block after
+    /// now, at the same delay wherever in the block it lands
     pub(super) fn send_note_on(&self, track_idx: usize, pitch: u8, velocity: u8) {
         let inst_id = self
             .engine
@@ -516,14 +1334,8 @@ impl HallucinatorApp {
                     .and_then(|track| track.instrument_id)
             })
             .flatten();
-        let Some(id) = inst_id else { return };
-        let Ok(mut instruments) = self.engine_state.instruments.lock() else {
-            return;
-        };
-        let Some(inst) = instruments.get_mut(&id) else {
-            return;
-        };
-        inst.queue_note_on(pitch, velocity, 0, 0);
+        let Some(instrument_id) = inst_id else { return };
+        self.engine_state.send_live_event(LiveEvent::NoteOn { instrument_id, pitch, velocity });
     }
 
     /// Send note-off to the instrument on the given track.
@@ -536,13 +1348,7 @@ impl HallucinatorApp {
                     .and_then(|track| track.instrument_id)
             })
             .flatten();
-        let Some(id) = inst_id else { return };
-        let Ok(mut instruments) = self.engine_state.instruments.lock() else {
-            return;
-        };
-        let Some(inst) = instruments.get_mut(&id) else {
-            return;
-        };
-        inst.queue_note_off(pitch, 0, 0, 0);
+        let Some(instrument_id) = inst_id else { return };
+        self.engine_state.send_live_event(LiveEvent::NoteOff { instrument_id, pitch });
     }
 }
@@ -1,50 +1,88 @@
-use hallucinator_core::{AudioClip, ClipId};
-use hallucinator_services::wav_reader;
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use super::config::{AppConfig, LibraryConfig, save_config};
+use hallucinator_core::{read_midi_file, AudioClip, ClipId, HallucinatorError, MidiClip, TrackInput, TrackKind, TrackMonitor};
+use hallucinator_services::latency::{measure_offset, CALIBRATION_SECS, PULSE_DELAY_SECS};
+use hallucinator_services::peak_file;
+use hallucinator_services::wav_reader::to_mono;
+use hallucinator_services::wav_writer::recording_file_name;
+use hallucinator_services::{is_audio_file, notifications, read_audio, read_audio_mono, DecodedAudio, LoadEvent, Project};
+
+use super::config::{
+    AudioConfig, KeymapConfig, LibraryConfig, PluginsConfig, RecordingConfig, load_config, peak_cache_dir, projects_dir,
+    recordings_dir, save_config,
+};
+use super::types::{LatencyCalibration, LoadTarget, PendingLoad, SelectedClip};
 use super::HallucinatorApp;
+use crate::panels::Layout;
+
+/// Standard MIDI Files accepted when dropped on the window
+const MIDI_EXTENSIONS: &[&str] = &["mid", "midi"];
+
+/// Autosaved project, in the projects folder
+const AUTOSAVE_FILE: &str = "autosave.json";
 
 impl HallucinatorApp {
     pub(super) fn start_recording(&mut self) {
         if !self.input_monitor.is_running() {
             if let Err(e) = self.input_monitor.start("default") {
-                tracing::error!("Failed to start input monitor: {}", e);
+                notifications::error("Failed to start input monitor", e);
                 return;
             }
         }
 
+        self.track_headers_panel.input_channels = self.input_monitor.channels();
         self.recording_start_sample = self.engine.position();
+        let input = self.record_input();
 
-        if let Err(e) = self.input_monitor.start_recording() {
-            tracing::error!("Failed to start recording: {}", e);
+        let started = if self.transport_panel.record_to_disk {
+            let path = recordings_dir(&load_config().recording).join(recording_file_name());
+            self.input_monitor.start_recording_to_file(&path, self.recording_start_sample, input)
+        } else {
+            self.input_monitor.start_recording(input)
+        };
+        if let Err(e) = started {
+            notifications::error("Failed to start recording", e);
             return;
         }
+        self.engine_state.recording.store(true, Ordering::Relaxed);
 
         self.engine.play();
         tracing::info!("Recording started at sample {}", self.recording_start_sample);
     }
 
     pub(super) fn stop_recording(&mut self) {
+        self.engine_state.recording.store(false, Ordering::Relaxed);
         let Ok(recorded) = self.input_monitor.stop_recording() else {
             tracing::error!("Failed to stop recording");
             return;
         };
 
-        if recorded.samples.is_empty() {
+        // Sound lands in the take late by the round-trip latency; trim it so the take lines up
+        let mut samples = recorded.samples;
+        let trim = (self.transport_panel.latency_offset as usize * recorded.channels as usize).min(samples.len());
+        samples.drain(..trim);
+
+        if samples.is_empty() {
             tracing::warn!("No samples recorded");
             return;
         }
 
         let mut clip = AudioClip::new(
             ClipId(self.next_clip_id),
-            recorded.samples,
+            samples,
             recorded.sample_rate,
             recorded.channels,
         );
+        clip.build_peaks();
         self.next_clip_id += 1;
 
         clip.start_sample = self.recording_start_sample;
-        clip.name = format!("Recording {}", self.next_clip_id - 1);
+        clip.name = recorded.file.as_ref()
+            .and_then(|path| path.file_stem())
+            .map_or_else(|| format!("Recording {}", self.next_clip_id - 1), |stem| stem.to_string_lossy().into_owned());
 
         // Add to armed track or first track
         self.engine.with_timeline(|timeline| {
@@ -59,48 +97,476 @@ impl HallucinatorApp {
         });
     }
 
-    pub(super) fn load_audio_file(&mut self, path: &std::path::Path) {
-        let (samples, channels, sample_rate) = match wav_reader::read_wav(path) {
-            Ok(result) => result,
-            Err(e) => {
-                tracing::error!("Failed to open WAV file: {} - {}", path.display(), e);
+    /// Play a pulse on the transport while recording the armed track's input; the pulse's
+    /// lateness in the take (read in `poll_latency_calibration`) is the latency to compensate
+    pub(super) fn start_latency_calibration(&mut self) {
+        if self.input_monitor.is_recording() {
+            return;
+        }
+        if !self.input_monitor.is_running() {
+            if let Err(e) = self.input_monitor.start("default") {
+                tracing::error!("Failed to start input for latency calibration: {}", e);
                 return;
             }
+        }
+
+        // Start inside the loop so the pulse isn't skipped by a wrap
+        let start_position = self.engine.position();
+        let (loop_start, _) = self.engine.loop_region();
+        if self.engine.is_loop_enabled() {
+            self.engine.seek(loop_start);
+        }
+        let take_start = self.engine.position();
+        let pulse_position = take_start + (PULSE_DELAY_SECS * self.engine.sample_rate() as f64) as u64;
+        let pulse_index = (PULSE_DELAY_SECS * self.input_monitor.sample_rate() as f64) as usize;
+        self.engine_state.calibration_pulse.store(pulse_position, Ordering::Relaxed);
+
+        // Same order as a real take, so the measured offset matches what recordings see
+        if let Err(e) = self.input_monitor.start_recording(self.record_input()) {
+            tracing::error!("Failed to start latency calibration: {}", e);
+            self.engine_state.calibration_pulse.store(u64::MAX, Ordering::Relaxed);
+            return;
+        }
+        self.engine.play();
+        self.latency_calibration = Some(LatencyCalibration { started: Instant::now(), start_position, pulse_index });
+    }
+
+    /// Finish a latency calibration once its take is long enough
+    pub(super) fn poll_latency_calibration(&mut self) {
+        let Some(calibration) = self.latency_calibration.take_if(|c| c.started.elapsed().as_secs_f64() >= CALIBRATION_SECS) else {
+            return;
         };
+        self.engine.pause();
+        self.engine.seek(calibration.start_position);
+        self.engine_state.calibration_pulse.store(u64::MAX, Ordering::Relaxed);
 
-        let mut clip = AudioClip::new(
-            ClipId(self.next_clip_id),
-            samples,
-            sample_rate,
-            channels,
-        );
-        self.next_clip_id += 1;
+        let Ok(recorded) = self.input_monitor.stop_recording() else {
+            tracing::error!("Latency calibration take was lost");
+            return;
+        };
+        let mono = to_mono(&recorded.samples, recorded.channels as usize);
+        match measure_offset(&mono, calibration.pulse_index) {
+            Some(offset) => {
+                tracing::info!(offset, "Measured round-trip latency");
+                self.transport_panel.latency_offset = offset;
+                self.save_recording_config(|recording| recording.latency_offset = offset);
+            }
+            None => tracing::warn!("Latency calibration heard no pulse; check the loopback cable and armed input"),
+        }
+    }
 
+    /// Input of the armed track (or the first), which a take records and monitoring plays
+    fn record_input(&self) -> TrackInput {
+        self.engine.with_timeline(|timeline| {
+            timeline.tracks.iter().find(|t| t.armed).or(timeline.tracks.first()).map(|t| t.input)
+        }).flatten().unwrap_or_default()
+    }
+
+    /// After arming, a monitor/input change or adding a hardware insert: start the input when
+    /// an audio track wants to monitor it or a hardware insert returns from it, and feed the
+    /// engine the armed track's input
+    pub(super) fn sync_input_monitoring(&mut self) {
+        let wants_input = self.engine.with_timeline(|timeline| {
+            timeline.tracks.iter().any(|t| {
+                t.kind == TrackKind::Audio && match t.monitor {
+                    TrackMonitor::Off => false,
+                    TrackMonitor::Auto => t.armed,
+                    TrackMonitor::On => true,
+                }
+            })
+        }).unwrap_or(false) || self.engine.has_hardware_inserts();
+
+        if wants_input && !self.input_monitor.is_running() {
+            if let Err(e) = self.input_monitor.start("default") {
+                tracing::error!("Failed to start input for monitoring: {}", e);
+                return;
+            }
+            self.track_headers_panel.input_channels = self.input_monitor.channels();
+        }
+        if !self.input_monitor.is_recording() {
+            self.input_monitor.set_record_input(self.record_input());
+        }
+    }
+
+    /// Import a file dropped on the window onto the arrange track under the pointer
+    /// (the selected track at the start when dropped elsewhere)
+    pub(super) fn import_dropped_file(&mut self, path: &std::path::Path) {
+        let (track_idx, start_sample) = self.arrange_panel.drop_target().unwrap_or((self.selected_track_idx, 0));
+        let ext = path.extension().and_then(|e| e.to_str()).map(str::to_lowercase).unwrap_or_default();
+        if MIDI_EXTENSIONS.contains(&ext.as_str()) {
+            self.import_midi_file(path, track_idx, start_sample);
+        } else if is_audio_file(path) {
+            self.import_audio_file(path, track_idx, start_sample);
+        } else {
+            tracing::warn!("Ignoring dropped file of unknown type: {}", path.display());
+        }
+    }
+
+    /// Place an audio file as an audio clip at `start_sample` on a track. A MIDI track or no
+    /// track (None, or past the last one) gets a new audio track for the clip instead. The
+    /// clip is a bar-long placeholder until the file has been decoded in the background.
+    pub(super) fn import_audio_file(&mut self, path: &std::path::Path, track_idx: Option<usize>, start_sample: u64) {
+        let Some((sample_rate, bar_samples)) = self.engine.with_timeline(|timeline| {
+            let transport = &timeline.transport;
+            let bar = transport.sample_rate as f64 * 60.0 / transport.bpm * transport.time_sig_num as f64;
+            (transport.sample_rate, bar as u64)
+        }) else {
+            return;
+        };
+
+        let clip_id = ClipId(self.next_clip_id);
+        self.next_clip_id += 1;
+        let mut clip = AudioClip::new(clip_id, Vec::new(), sample_rate, 2);
+        clip.length_samples = bar_samples.max(1);
+        clip.start_sample = start_sample;
         clip.name = path.file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("clip")
             .to_string();
 
+        let audio_track = track_idx.filter(|&idx| {
+            self.engine.with_timeline(|timeline| {
+                timeline.tracks.get(idx).is_some_and(|t| t.kind == TrackKind::Audio)
+            }).unwrap_or(false)
+        });
+        let idx = match audio_track {
+            Some(idx) => idx,
+            None => {
+                self.add_audio_track();
+                let Some(idx) = self.engine.with_timeline(|timeline| timeline.tracks.len().checked_sub(1)).flatten() else {
+                    return;
+                };
+                idx
+            }
+        };
+        self.with_track_mut(idx, |track| track.add_clip(clip));
+        self.selected_track_idx = Some(idx);
+
+        self.load_audio(path, LoadTarget::Clip(clip_id));
+    }
+
+    /// Decode an audio file on the loader thread, then use it for `target`. Without a loader
+    /// thread the file is decoded right away
+    pub(super) fn load_audio(&mut self, path: &std::path::Path, target: LoadTarget) {
+        let peak_cache = matches!(target, LoadTarget::Clip(_)).then(peak_cache_dir);
+        let load = PendingLoad { path: path.to_path_buf(), target, progress: 0.0 };
+        match self.audio_loader.as_mut() {
+            Some(loader) => {
+                let id = loader.request(path, peak_cache);
+                self.pending_loads.insert(id, load);
+            }
+            None => {
+                let result = read_audio(path).map(|(samples, channels, sample_rate)| {
+                    let peaks = peak_cache.map(|dir| peak_file::load_or_build(&dir, path, &samples, channels));
+                    DecodedAudio { samples, channels, sample_rate, peaks }
+                });
+                self.finish_audio_load(load, result);
+            }
+        }
+    }
+
+    /// Take in the loader's progress and the files it has finished
+    pub(super) fn poll_audio_loads(&mut self, ctx: &egui::Context) {
+        let Some(loader) = &self.audio_loader else { return };
+        for event in loader.poll() {
+            match event {
+                LoadEvent::Progress { id, fraction } => {
+                    if let Some(load) = self.pending_loads.get_mut(&id) {
+                        load.progress = fraction;
+                    }
+                }
+                LoadEvent::Loaded { id, result } => {
+                    if let Some(load) = self.pending_loads.remove(&id) {
+                        self.finish_audio_load(load, result);
+                    }
+                }
+            }
+        }
+        if !self.pending_loads.is_empty() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+    }
+
+    /// Files being decoded, by name, and how far along each is, for the status bar
+    pub(super) fn audio_load_progress(&self) -> Vec<(String, f32)> {
+        self.pending_loads.values()
+            .map(|load| {
+                let name = load.path.file_name().and_then(|n| n.to_str()).unwrap_or("audio file");
+                (name.to_string(), load.progress)
+            })
+            .collect()
+    }
+
+    /// Placeholder clips still waiting for their audio, and how far along it is
+    pub(super) fn loading_clips(&self) -> HashMap<ClipId, f32> {
+        self.pending_loads.values()
+            .filter_map(|load| match load.target {
+                LoadTarget::Clip(clip_id) => Some((clip_id, load.progress)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn finish_audio_load(&mut self, load: PendingLoad, result: Result<DecodedAudio, String>) {
+        let audio = match result {
+            Ok(audio) => audio,
+            Err(e) => {
+                let path = load.path.display();
+                match load.target {
+                    LoadTarget::Clip(clip_id) => {
+                        notifications::error(format!("Failed to open audio file {}", path), e);
+                        self.remove_placeholder_clip(clip_id);
+                    }
+                    LoadTarget::Sampler => notifications::error(format!("Failed to load sampler from {}", path), e),
+                    LoadTarget::StepSample { .. } | LoadTarget::RowSample { .. } => {
+                        notifications::error(format!("Failed to read sample {}", path), e);
+                    }
+                }
+                return;
+            }
+        };
+
+        match load.target {
+            LoadTarget::Clip(clip_id) => self.fill_placeholder_clip(clip_id, &load.path, audio),
+            LoadTarget::Sampler => self.add_sampler(&load.path, audio),
+            LoadTarget::StepSample { track_idx, step, layer } => {
+                let mono = to_mono(&audio.samples, audio.channels as usize);
+                self.set_step_sample(track_idx, step, layer, &load.path, mono);
+            }
+            LoadTarget::RowSample { track_idx, row } => {
+                let mono = to_mono(&audio.samples, audio.channels as usize);
+                self.set_row_sample(track_idx, row, &load.path, mono);
+            }
+        }
+    }
+
+    /// Give a placeholder clip its decoded audio and full length, where it has been moved to
+    fn fill_placeholder_clip(&mut self, clip_id: ClipId, path: &std::path::Path, audio: DecodedAudio) {
+        let filled = self.engine.with_timeline(|timeline| {
+            let track = timeline.tracks.iter_mut().find(|track| track.get_clip(clip_id).is_some())?;
+            let placeholder = track.get_clip_mut(clip_id)?;
+            let mut clip = AudioClip::new(clip_id, audio.samples, audio.sample_rate, audio.channels);
+            clip.start_sample = placeholder.start_sample;
+            clip.name = std::mem::take(&mut placeholder.name);
+            clip.gain = placeholder.gain;
+            clip.peaks = audio.peaks.map(Arc::new);
+            *placeholder = clip;
+            track.update_crossfades();
+            Some(())
+        }).flatten();
+        match filled {
+            Some(()) => tracing::info!("Loaded audio file: {}", path.display()),
+            None => tracing::debug!("Clip for {} was deleted while loading", path.display()),
+        }
+    }
+
+    fn remove_placeholder_clip(&mut self, clip_id: ClipId) {
         self.engine.with_timeline(|timeline| {
-            if let Some(track) = timeline.tracks.first_mut() {
-                track.add_clip(clip);
+            for track in &mut timeline.tracks {
+                track.remove_clip(clip_id);
             }
         });
+        if matches!(self.selected_clip, Some(SelectedClip::Audio { clip_id: id, .. }) if id == clip_id) {
+            self.selected_clip = None;
+        }
+    }
+
+    /// Place a Standard MIDI File as a MIDI clip at `start_sample` on a track. An audio
+    /// track or no track gets a new MIDI track for the clip instead.
+    pub(super) fn import_midi_file(&mut self, path: &std::path::Path, track_idx: Option<usize>, start_sample: u64) {
+        let file = match std::fs::read(path).map_err(HallucinatorError::from).and_then(|data| read_midi_file(&data)) {
+            Ok(file) => file,
+            Err(e) => {
+                notifications::error(format!("Failed to open MIDI file {}", path.display()), e);
+                return;
+            }
+        };
 
-        tracing::info!("Loaded audio file: {}", path.display());
+        let Some(samples_per_beat) = self.engine.with_timeline(|timeline| {
+            timeline.transport.sample_rate as f64 * 60.0 / timeline.transport.bpm
+        }) else {
+            return;
+        };
+
+        // The file's ticks rescaled to the clip's resolution, length rounded up to whole beats
+        let mut clip = MidiClip::new(ClipId(self.next_clip_id), 0);
+        self.next_clip_id += 1;
+        let (clip_ppq, file_ppq) = (clip.ppq as u64, file.ppq.max(1) as u64);
+        let scale = |tick: u64| tick * clip_ppq / file_ppq;
+        let beats = file.length_ticks.div_ceil(file_ppq).max(1);
+        clip.length_samples = (beats as f64 * samples_per_beat) as u64;
+        clip.start_sample = start_sample;
+        clip.name = path.file_stem()
+            .and_then(|n| n.to_str())
+            .unwrap_or("MIDI")
+            .to_string();
+        clip.notes = file.notes.iter().map(|n| {
+            let mut note = *n;
+            note.start_tick = scale(n.start_tick);
+            note.duration_ticks = scale(n.duration_ticks).max(1);
+            note
+        }).collect();
+        clip.controls = file.controls.iter().map(|c| {
+            let mut event = *c;
+            event.tick = scale(c.tick);
+            event
+        }).collect();
+
+        let midi_track = track_idx.filter(|&idx| {
+            self.engine.with_timeline(|timeline| {
+                timeline.tracks.get(idx).is_some_and(|t| t.kind == TrackKind::Midi)
+            }).unwrap_or(false)
+        });
+        let idx = match midi_track {
+            Some(idx) => idx,
+            None => {
+                self.add_empty_midi_track();
+                let Some(idx) = self.engine.with_timeline(|timeline| timeline.tracks.len().checked_sub(1)).flatten() else {
+                    return;
+                };
+                idx
+            }
+        };
+        self.with_track_mut(idx, |track| track.add_midi_clip(clip));
+        self.selected_track_idx = Some(idx);
+
+        tracing::info!("Imported MIDI file: {} ({} notes)", path.display(), file.notes.len());
     }
 
     pub(super) fn save_library_config(&self) {
-        let config = AppConfig {
-            library: LibraryConfig {
-                places: self.browser_panel.place_paths().iter().map(|p| p.display().to_string()).collect(),
-            },
+        let mut config = load_config();
+        config.library = LibraryConfig {
+            places: self.browser_panel.place_paths().iter().map(|p| p.display().to_string()).collect(),
+        };
+        save_config(&config);
+    }
+
+    pub(super) fn save_recording_config(&self, f: impl FnOnce(&mut RecordingConfig)) {
+        let mut config = load_config();
+        f(&mut config.recording);
+        save_config(&config);
+    }
+
+    pub(super) fn save_audio_config(&self, f: impl FnOnce(&mut AudioConfig)) {
+        let mut config = load_config();
+        f(&mut config.audio);
+        save_config(&config);
+    }
+
+    pub(super) fn save_keymap_config(&self) {
+        let mut config = load_config();
+        config.keymap = KeymapConfig { preset: self.keymap.preset, rebound: self.keymap.rebound_by_name() };
+        save_config(&config);
+    }
+
+    pub(super) fn save_preferences_config(&self) {
+        let preferences = &self.preferences;
+        let mut config = load_config();
+        config.audio.output = preferences.output.clone();
+        config.midi.disabled_inputs = preferences.midi_inputs_off.clone();
+        config.project.folder = preferences.projects_folder.clone();
+        config.project.autosave_minutes = preferences.autosave_minutes;
+        config.recording.folder = preferences.recordings_folder.clone();
+        config.ui.theme = preferences.theme;
+        config.ui.accents = preferences.accents;
+        save_config(&config);
+    }
+
+    /// Keep the current layout for its workspace and save every workspace's layout
+    pub(super) fn save_layout_config(&mut self) {
+        self.workspaces.store(&self.layout);
+        let mut config = load_config();
+        config.ui.workspaces = self.workspaces.clone();
+        save_config(&config);
+    }
+
+    /// Open and close panels and windows as `layout` has them, sized on the next frame
+    pub(super) fn apply_layout(&mut self, ctx: &egui::Context, layout: Layout) {
+        self.keyboard_sequencer_panel.is_floating = layout.sequencer_floating;
+        self.layout = layout;
+        self.layout.restore(ctx);
+        self.save_layout_config();
+    }
+
+    /// Write the project to the projects folder once the autosave interval has passed (and
+    /// no save is still being written)
+    pub(super) fn autosave_if_due(&mut self) {
+        let minutes = self.preferences.autosave_minutes;
+        if minutes == 0 || self.last_autosave.elapsed() < Duration::from_secs(u64::from(minutes) * 60) {
+            return;
+        }
+        if self.project_save.as_ref().is_some_and(|save| !save.is_finished()) {
+            return;
+        }
+        self.last_autosave = Instant::now();
+        self.save_project(self.autosave_path());
+    }
+
+    pub(super) fn autosave_path(&self) -> std::path::PathBuf {
+        projects_dir(&self.preferences.projects_folder).join(AUTOSAVE_FILE)
+    }
+
+    /// Save the project to `path`, writing it out in the background once an earlier save
+    /// has finished, so two saves never write the same media folder at once
+    pub(super) fn save_project(&mut self, path: std::path::PathBuf) {
+        let Some(mut project) = Project::capture(&self.engine_state) else { return };
+        let previous = self.project_save.take();
+        self.project_save = Some(std::thread::spawn(move || {
+            if let Some(previous) = previous {
+                let _ = previous.join();
+            }
+            match project.save(&path) {
+                Ok(()) => tracing::info!("Saved project to {}", path.display()),
+                Err(e) => notifications::error(format!("Failed to save project to {}", path.display()), e),
+            }
+        }));
+    }
+
+    /// Open a saved project in place of the current one
+    pub(super) fn open_project(&mut self, path: &std::path::Path) {
+        let project = match Project::load(path) {
+            Ok(project) => project,
+            Err(e) => {
+                notifications::error(format!("Failed to open {}", path.display()), e);
+                return;
+            }
+        };
+        let devices = project.load_devices(self.plugin_menu.scanner(), self.engine.sample_rate() as f32);
+        if !devices.missing.is_empty() {
+            notifications::warning("Some devices of the project couldn't be loaded", devices.missing.join(", "));
+        }
+
+        // Windows of the current project's devices close with them
+        let open_editors = self.engine.with_instruments(|instruments| instruments.keys().copied().collect::<Vec<_>>());
+        for id in open_editors.unwrap_or_default() {
+            if self.gui_manager.has_window(id) {
+                let _ = self.gui_manager.destroy_window(id);
+            }
+        }
+        self.plugin_windows.clear();
+        self.native_param_windows.clear();
+        self.effect_param_windows.clear();
+        self.pending_instruments.clear();
+        self.selected_track_idx = None;
+        self.selected_clip = None;
+
+        self.next_clip_id = project.timeline.next_clip_id();
+        (self.next_instrument_id, self.next_effect_chain_id) = project.next_device_ids();
+        self.engine_state.open_project(project.timeline, devices);
+        tracing::info!("Opened project {}", path.display());
+    }
+
+    pub(super) fn save_plugins_config(&self) {
+        let mut config = load_config();
+        config.plugins = PluginsConfig {
+            favorites: self.plugin_menu.favorites(),
+            recent: self.plugin_menu.recent().to_vec(),
         };
         save_config(&config);
     }
 
-    /// Read a WAV file to mono f32 samples.
-    pub(super) fn read_wav_samples(path: &std::path::Path) -> Result<(Vec<f32>, u32), String> {
-        wav_reader::read_wav_mono(path)
+    /// Read an audio file to mono f32 samples.
+    pub(super) fn read_sample_file(path: &std::path::Path) -> Result<(Vec<f32>, u32), String> {
+        read_audio_mono(path)
     }
 }
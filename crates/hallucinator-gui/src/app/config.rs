@@ -1,9 +1,33 @@
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::PathBuf;
 
+use hallucinator_services::{CueSettings, OutputSettings};
+
+use crate::panels::{Accents, KeymapPreset, Theme, Workspaces};
+
 #[derive(serde::Serialize, serde::Deserialize, Default)]
 pub(super) struct AppConfig {
     #[serde(default)]
     pub library: LibraryConfig,
+    #[serde(default)]
+    pub control_surface: ControlSurfaceConfig,
+    #[serde(default)]
+    pub osc: OscConfig,
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+    #[serde(default)]
+    pub recording: RecordingConfig,
+    #[serde(default)]
+    pub audio: AudioConfig,
+    #[serde(default)]
+    pub keymap: KeymapConfig,
+    #[serde(default)]
+    pub midi: MidiConfig,
+    #[serde(default)]
+    pub project: ProjectConfig,
+    #[serde(default)]
+    pub ui: UiConfig,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Default)]
@@ -12,6 +36,129 @@ pub(super) struct LibraryConfig {
     pub places: Vec<String>,
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub(super) struct PluginsConfig {
+    /// Starred plugins, by unique ID
+    #[serde(default)]
+    pub favorites: Vec<String>,
+    /// Recently loaded plugins, by unique ID, newest first
+    #[serde(default)]
+    pub recent: Vec<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub(super) struct RecordingConfig {
+    /// Stream takes to WAV files instead of holding them in memory
+    #[serde(default)]
+    pub to_disk: bool,
+    /// Folder for recorded takes (empty = `recordings_dir()` default)
+    #[serde(default)]
+    pub folder: String,
+    /// Round-trip latency in samples, trimmed from the start of every take
+    #[serde(default)]
+    pub latency_offset: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(super) struct AudioConfig {
+    /// Main output device and buffer size
+    #[serde(default)]
+    pub output: OutputSettings,
+    /// Cue bus output and the sources routed to it
+    #[serde(default)]
+    pub cue: CueSettings,
+    /// Metronome click level, linear gain
+    #[serde(default = "default_metronome_volume")]
+    pub metronome_volume: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self { output: OutputSettings::default(), cue: CueSettings::default(), metronome_volume: default_metronome_volume() }
+    }
+}
+
+fn default_metronome_volume() -> f32 {
+    0.5
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub(super) struct KeymapConfig {
+    /// Preset the shortcuts start from
+    #[serde(default)]
+    pub preset: KeymapPreset,
+    /// Commands bound differently from the preset, by name, to shortcuts like "Ctrl+Shift+F"
+    /// (an empty list leaves a command unbound)
+    #[serde(default)]
+    pub rebound: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub(super) struct MidiConfig {
+    /// MIDI inputs left closed, by port name
+    #[serde(default)]
+    pub disabled_inputs: Vec<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub(super) struct ProjectConfig {
+    /// Folder autosaves are written to (empty = `projects_dir()` default)
+    #[serde(default)]
+    pub folder: String,
+    /// Minutes between autosaves (0 = off)
+    #[serde(default)]
+    pub autosave_minutes: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub(super) struct UiConfig {
+    #[serde(default)]
+    pub theme: Theme,
+    #[serde(default)]
+    pub accents: Accents,
+    /// Workspace in use and how each one's panels were left
+    #[serde(default)]
+    pub workspaces: Workspaces,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub(super) struct ControlSurfaceConfig {
+    /// Active profile name (empty = generic CC)
+    #[serde(default)]
+    pub profile: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(super) struct OscConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_osc_port")]
+    pub port: u16,
+    /// Interface to listen on; localhost unless network access was turned on
+    #[serde(default = "default_osc_bind")]
+    pub bind: IpAddr,
+}
+
+impl OscConfig {
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind, self.port)
+    }
+}
+
+impl Default for OscConfig {
+    fn default() -> Self {
+        Self { enabled: false, port: default_osc_port(), bind: default_osc_bind() }
+    }
+}
+
+fn default_osc_port() -> u16 {
+    hallucinator_services::OscServer::DEFAULT_PORT
+}
+
+fn default_osc_bind() -> IpAddr {
+    Ipv4Addr::LOCALHOST.into()
+}
+
 pub(super) fn config_path() -> PathBuf {
     dirs::config_dir()
         .unwrap_or_else(|| PathBuf::from("."))
@@ -35,3 +182,51 @@ pub(super) fn save_config(config: &AppConfig) {
     let Ok(s) = toml::to_string_pretty(config) else { return };
     let _ = std::fs::write(&path, s);
 }
+
+/// Browser waveform thumbnails and metadata, in the user cache directory
+pub(super) fn sample_info_cache_dir() -> PathBuf {
+    dirs::cache_dir().map_or_else(
+        || config_path().with_file_name("sample-info"),
+        |dir| dir.join("hallucinator").join("sample-info"),
+    )
+}
+
+/// Peak files of imported audio, for drawing long clips without scanning their samples
+pub(super) fn peak_cache_dir() -> PathBuf {
+    dirs::cache_dir().map_or_else(
+        || config_path().with_file_name("peaks"),
+        |dir| dir.join("hallucinator").join("peaks"),
+    )
+}
+
+/// Folder takes recorded to disk are written to: the configured one, else the user's audio folder
+pub(super) fn recordings_dir(config: &RecordingConfig) -> PathBuf {
+    if !config.folder.is_empty() {
+        return PathBuf::from(&config.folder);
+    }
+    dirs::audio_dir().map_or_else(
+        || config_path().with_file_name("recordings"),
+        |dir| dir.join("hallucinator"),
+    )
+}
+
+/// Folder the project is autosaved to: the configured one, else the user's documents folder
+pub(super) fn projects_dir(folder: &str) -> PathBuf {
+    if !folder.is_empty() {
+        return PathBuf::from(folder);
+    }
+    dirs::document_dir().map_or_else(
+        || config_path().with_file_name("projects"),
+        |dir| dir.join("Hallucinator"),
+    )
+}
+
+/// Sample tags, ratings, saved searches and search index database, next to the config file
+pub(super) fn sample_library_path() -> PathBuf {
+    config_path().with_file_name("sample-library.db")
+}
+
+/// VST3 scan results, next to the config file
+pub(super) fn plugin_cache_path() -> PathBuf {
+    config_path().with_file_name("vst3-cache.json")
+}
@@ -4,34 +4,39 @@ mod action_handlers;
 mod audio_ops;
 mod config;
 mod plugin_windows;
+mod presets;
 mod sample_kit_ops;
+mod surfaces;
 mod track_ops;
 mod types;
+mod viewports;
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Instant;
 
 use eframe::CreationContext;
 use egui::{Context, Vec2};
-use hallucinator_core::{PlaybackMode, SongSection, TrackKind};
+use hallucinator_core::{MidiEffect, ParamOwner, PlaybackMode, SnapshotMorph, SongSection, SurfaceProfile, TrackKind};
 use hallucinator_services::{
-    AudioEngine, EngineState, InputMonitor, MeterState, PluginGuiManager,
+    notifications, AudioEngine, AudioFileLoader, AudioInputService, ClockSync, EngineState, InputMonitor, MeterState,
+    MidiClockSender, MidiInputService, OscServer, OutputSettings, ParamGesture, PluginGuiManager,
 };
 
 pub use types::SelectedClip;
-use config::load_config;
-use types::PluginWindow;
+use config::{load_config, plugin_cache_path, sample_info_cache_dir, sample_library_path};
+use types::{EffectChainRef, LatencyCalibration, MidiLearnState, PendingInstrument, PendingLoad, PluginWindow};
 
 use crate::clipboard::DawClipboard;
 use crate::panels::{
-    ArrangePanel, BrowserPanel, ClipEditorPanel,
+    AnalyzerPanel, ArrangePanel, AutomationPanel, BrowserPanel, ChordTrackPanel, ClipEditorPanel, ControlSurfacePanel,
     DeviceRackAction, DeviceRackPanel, DrumRollAction, DrumRollPanel,
-    KeyboardSequencerPanel,
-    MidiFxRackPanel,
-    PianoRollAction, PluginBrowserPanel,
-    RecordingPreview, SongViewPanel,
-    TrackHeadersPanel, TransportAction, TransportPanel,
+    Command, KeyboardSequencerPanel, Keymap, KeymapEditorPanel, Layout,
+    MidiFxRackPanel, ModulationPanel, NotificationCenter,
+    EngineStatus, PianoRollAction, PluginBrowserPanel, Preferences, PreferencesPanel,
+    RecordingPreview, ScriptConsoleAction, ScriptConsolePanel, SnapshotPanel, SongViewPanel, StatusBarPanel,
+    TrackHeadersPanel, TrackLayout, TransportAction, TransportPanel, Workspaces,
 };
 
 
@@ -40,6 +45,10 @@ pub struct HallucinatorApp {
     engine_state: Arc<EngineState>,
     input_monitor: InputMonitor,
     meter_state: Arc<MeterState>,
+    midi_input: Option<MidiInputService>,
+    osc_server: Option<OscServer>,
+    clock_sync: ClockSync,
+    midi_clock: Option<MidiClockSender>,
 
     // Panels
     transport_panel: TransportPanel,
@@ -47,18 +56,49 @@ pub struct HallucinatorApp {
     browser_panel: BrowserPanel,
     track_headers_panel: TrackHeadersPanel,
     arrange_panel: ArrangePanel,
+    /// Track row heights and vertical scroll, shared by the headers and the arrange view
+    track_layout: TrackLayout,
     device_rack_panel: DeviceRackPanel,
     clip_editor_panel: ClipEditorPanel,
     drum_roll_panel: DrumRollPanel,
     keyboard_sequencer_panel: KeyboardSequencerPanel,
     midi_fx_rack_panel: MidiFxRackPanel,
+    /// Saved MIDI FX chain preset names
+    midi_fx_presets: Vec<String>,
     song_view_panel: SongViewPanel,
+    analyzer_panel: AnalyzerPanel,
+    modulation_panel: ModulationPanel,
+    automation_panel: AutomationPanel,
+    control_surface_panel: ControlSurfacePanel,
+    script_console_panel: ScriptConsolePanel,
+    snapshot_panel: SnapshotPanel,
+    chord_track_panel: ChordTrackPanel,
+    preferences_panel: PreferencesPanel,
+    status_bar_panel: StatusBarPanel,
+    notification_center: NotificationCenter,
+    /// Settings edited in the preferences window, as saved in config.toml
+    preferences: Preferences,
+    keymap_editor_panel: KeymapEditorPanel,
+    /// Keyboard shortcuts, installed in the egui context for the panels each frame
+    keymap: Arc<Keymap>,
 
     // App-wide clipboard
     clipboard: DawClipboard,
 
-    // Factory Rat panel visibility
-    show_factory_rat_panels: bool,
+    // Open panels and windows and their sizes, and the workspaces each keep a layout
+    layout: Layout,
+    workspaces: Workspaces,
+    show_preferences: bool,
+    show_keymap_editor: bool,
+
+    // Snapshot morph in progress and when it started
+    snapshot_morph: Option<(SnapshotMorph, Instant)>,
+
+    // Control surface: active profile, saved profile names, first strip's track, binding being learned
+    surface_profile: SurfaceProfile,
+    surface_profiles: Vec<String>,
+    surface_bank: usize,
+    surface_learn: Option<usize>,
 
     // Selection state
     selected_track_idx: Option<usize>,
@@ -68,27 +108,56 @@ pub struct HallucinatorApp {
     // Floating windows
     plugin_windows: Vec<PluginWindow>,
     native_param_windows: HashSet<u64>,  // IDs of native instruments with open param windows
+    effect_param_windows: HashSet<(EffectChainRef, usize)>,  // (chain, effect index) with open param windows
+    param_window_states: HashMap<egui::Id, presets::ParamWindowState>,  // Preset, A/B and lock state per param window
     gui_manager: PluginGuiManager,
 
     // ID counters
     next_clip_id: u64,
     next_instrument_id: u64,
+    next_effect_chain_id: u64,
+
+    // Audio files decoding in the background, by loader request id
+    audio_loader: Option<AudioFileLoader>,
+    pending_loads: BTreeMap<u64, PendingLoad>,
+    // VST3 instruments loading in the background, by instrument id
+    pending_instruments: HashMap<u64, PendingInstrument>,
 
     // Recording state
     recording_start_sample: u64,
+    latency_calibration: Option<LatencyCalibration>,
 
     // Playback start position (for space toggle return-to-start)
     playback_start_position: u64,
+
+    // When the project was last autosaved (or the app started)
+    last_autosave: Instant,
+    // The latest background project save; each save waits for the one before it
+    project_save: Option<std::thread::JoinHandle<()>>,
 }
 
 impl HallucinatorApp {
-    pub fn new(_cc: &CreationContext<'_>) -> Self {
+    pub fn new(cc: &CreationContext<'_>) -> Self {
+        let config = load_config();
+        config.ui.theme.apply(&cc.egui_ctx, &config.ui.accents);
+        // Notifications posted from audio or watchdog threads show without waiting for input
+        let repaint_ctx = cc.egui_ctx.clone();
+        notifications::set_waker(move || repaint_ctx.request_repaint());
+
         let sample_rate = 44100;
         let mut engine = AudioEngine::new(sample_rate);
         let engine_state = engine.state();
 
-        if let Err(e) = engine.start() {
-            tracing::error!("Failed to start audio engine: {}", e);
+        // Output device and buffer size from the preferences, the default ones if they fail
+        let _ = engine.set_output_settings(config.audio.output.clone());
+        let mut started = engine.start();
+        if started.is_err() && config.audio.output != OutputSettings::default() {
+            tracing::warn!("Configured output unavailable, using the default device");
+            let _ = engine.set_output_settings(OutputSettings::default());
+            started = engine.start();
+        }
+        if let Err(e) = started {
+            notifications::error("Failed to start audio engine", e);
         }
 
         // Create a default MIDI track and set one-bar loop
@@ -101,86 +170,260 @@ impl HallucinatorApp {
             timeline.transport.loop_enabled = true;
         });
 
-        let input_monitor = InputMonitor::new();
+        let mut input_monitor = InputMonitor::new();
+        input_monitor.set_live_input(engine_state.live_input.clone());
         let meter_state = input_monitor.meter_state();
 
+        // Track input selectors list every channel of the default input device
+        let mut track_headers_panel = TrackHeadersPanel::new();
+        if let Ok((_, _, channels)) = AudioInputService::get_default_device_info() {
+            track_headers_panel.input_channels = channels;
+        }
+
+        // Hardware MIDI controllers drive parameters through the project's MIDI map
+        let midi_input = surfaces::start_midi_input(&cc.egui_ctx, engine_state.clone(), &config.midi.disabled_inputs);
+
         // Initialize native GUI manager for plugin windows
         let mut gui_manager = PluginGuiManager::new();
         if let Err(e) = gui_manager.initialize() {
             tracing::warn!("Failed to initialize native GUI manager: {}", e);
         }
 
-        // Load config and initialize sample library places
-        let config = load_config();
+        // Initialize sample library places
         let mut browser_panel = BrowserPanel::new();
         let place_paths: Vec<PathBuf> = config.library.places.iter().map(PathBuf::from).collect();
         browser_panel.set_places(place_paths);
+        browser_panel.set_preset_roots(presets::vst_preset_roots());
+        browser_panel.start_sample_info(sample_info_cache_dir());
+        browser_panel.load_library(sample_library_path());
+        let surface_profile = surfaces::load_profile(&config.control_surface.profile).unwrap_or_else(SurfaceProfile::generic);
+        let mut plugin_menu = PluginBrowserPanel::new(Some(plugin_cache_path()));
+        plugin_menu.set_library(config.plugins.favorites.clone(), config.plugins.recent.clone());
+
+        // Cue bus routing and metronome level from the audio settings
+        if let Err(e) = engine.set_cue_settings(config.audio.cue.clone()) {
+            tracing::warn!("Cue output unavailable, cue sources play in the main mix: {}", e);
+        }
+        engine.set_metronome_volume(config.audio.metronome_volume);
+
+        // Optional OSC remote control
+        let osc_server = if config.osc.enabled {
+            OscServer::start(engine_state.clone(), config.osc.addr())
+                .inspect_err(|e| tracing::warn!("Failed to start OSC server: {}", e))
+                .ok()
+        } else {
+            None
+        };
+
+        let preferences = Preferences {
+            output: engine.output_settings(),
+            midi_inputs_off: config.midi.disabled_inputs.clone(),
+            projects_folder: config.project.folder.clone(),
+            recordings_folder: config.recording.folder.clone(),
+            autosave_minutes: config.project.autosave_minutes,
+            theme: config.ui.theme,
+            accents: config.ui.accents,
+        };
+
+        let workspaces = config.ui.workspaces.clone();
+        let layout = workspaces.layout();
+
+        let mut keyboard_sequencer_panel = KeyboardSequencerPanel::new();
+        keyboard_sequencer_panel.set_pattern_files(presets::list_drum_patterns());
+        keyboard_sequencer_panel.is_floating = layout.sequencer_floating;
 
         Self {
             engine,
             engine_state,
             input_monitor,
             meter_state,
-            transport_panel: TransportPanel::new(),
-            plugin_menu: PluginBrowserPanel::new(),
+            midi_input,
+            osc_server,
+            clock_sync: ClockSync::Internal,
+            midi_clock: None,
+            transport_panel: TransportPanel::new(config.recording.to_disk, config.recording.latency_offset),
+            plugin_menu,
             browser_panel,
-            track_headers_panel: TrackHeadersPanel::new(),
+            track_headers_panel,
             arrange_panel: ArrangePanel::new(),
+            track_layout: TrackLayout::new(),
             device_rack_panel: DeviceRackPanel::new(),
             clip_editor_panel: ClipEditorPanel::new(),
             drum_roll_panel: DrumRollPanel::new(),
-            keyboard_sequencer_panel: KeyboardSequencerPanel::new(),
+            keyboard_sequencer_panel,
             midi_fx_rack_panel: MidiFxRackPanel::new(),
+            midi_fx_presets: presets::list_midi_fx_presets(),
             song_view_panel: SongViewPanel::new(),
+            analyzer_panel: AnalyzerPanel::new(),
+            modulation_panel: ModulationPanel::new(),
+            automation_panel: AutomationPanel::new(),
+            control_surface_panel: ControlSurfacePanel::new(),
+            script_console_panel: ScriptConsolePanel::new(),
+            snapshot_panel: SnapshotPanel::new(),
+            chord_track_panel: ChordTrackPanel::new(),
+            preferences_panel: PreferencesPanel::new(),
+            status_bar_panel: StatusBarPanel::new(),
+            notification_center: NotificationCenter::new(),
+            preferences,
+            keymap_editor_panel: KeymapEditorPanel::new(),
+            keymap: Arc::new(Keymap::from_config(config.keymap.preset, &config.keymap.rebound)),
             clipboard: DawClipboard::default(),
-            show_factory_rat_panels: true,
+            layout,
+            workspaces,
+            show_preferences: false,
+            show_keymap_editor: false,
+            snapshot_morph: None,
+            surface_profile,
+            surface_profiles: surfaces::list_profiles(),
+            surface_bank: 0,
+            surface_learn: None,
             selected_track_idx: Some(0),
             selected_clip: None,
             show_clip_editor: false,
             plugin_windows: Vec::new(),
             native_param_windows: HashSet::new(),
+            effect_param_windows: HashSet::new(),
+            param_window_states: HashMap::new(),
             gui_manager,
             next_clip_id: 1,
             next_instrument_id: 1,
+            next_effect_chain_id: 1,
+            audio_loader: AudioFileLoader::spawn()
+                .inspect_err(|e| tracing::warn!("Audio loader thread failed to start, files load in the foreground: {}", e))
+                .ok(),
+            pending_loads: BTreeMap::new(),
+            pending_instruments: HashMap::new(),
             recording_start_sample: 0,
+            latency_calibration: None,
             playback_start_position: 0,
+            last_autosave: Instant::now(),
+            project_save: None,
         }
     }
 
+    /// Piano roll, drum roll or audio clip view of the selected clip, under a Close/Detach bar
+    fn clip_editor_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("✕ Close").clicked() {
+                self.show_clip_editor = false;
+            }
+            if viewports::detach_button(ui, self.layout.clip_editor_detached) {
+                self.layout.clip_editor_detached = !self.layout.clip_editor_detached;
+            }
+            ui.separator();
+        });
 
+        let mut piano_roll_action = PianoRollAction::None;
+        let mut drum_roll_action = DrumRollAction::None;
+
+        if let Some(selected) = self.selected_clip {
+            match selected {
+                SelectedClip::Midi { track_idx, clip_id } => {
+                    let (bpm, sample_rate) = self.engine.with_timeline(|t| {
+                        (t.transport.bpm, t.transport.sample_rate)
+                    }).unwrap_or((120.0, 44100));
+
+                    let playback_position = self.engine.position();
+
+                    // Check if this track uses a drum instrument
+                    let is_drum = self.engine.with_timeline(|timeline| {
+                        timeline.tracks.get(track_idx)
+                            .and_then(|track| track.instrument_id)
+                    }).flatten().and_then(|inst_id| {
+                        let instruments = self.engine_state.instruments.lock().ok()?;
+                        Some(instruments.get(&inst_id)?.is_drum())
+                    }).unwrap_or(false);
+
+                    if is_drum {
+                        // Use drum roll panel for drum instruments
+                        let action = self.engine.with_timeline(|timeline| {
+                            if let Some(track) = timeline.tracks.get_mut(track_idx) {
+                                if let Some(clip) = track.midi_clips.iter_mut().find(|c| c.id == clip_id) {
+                                    let clip_start = clip.start_sample;
+                                    return Some(self.drum_roll_panel.ui(
+                                        ui, clip, bpm, sample_rate, clip_start, playback_position, &self.clipboard
+                                    ));
+                                }
+                            }
+                            None
+                        }).flatten();
 
-}
+                        if let Some(a) = action {
+                            drum_roll_action = a;
+                        }
+                    } else {
+                        // Use piano roll panel for melodic instruments
+                        let action = self.engine.with_timeline(|timeline| {
+                            self.clip_editor_panel.set_grooves(timeline.grooves.clone());
+                            if let Some(track) = timeline.tracks.get_mut(track_idx) {
+                                let harmony = track.midi_fx_chain.effects.iter()
+                                    .filter(|e| !e.is_bypassed())
+                                    .filter_map(|e| match e {
+                                        MidiEffect::Harmonizer(h) if !h.follows_chords() => Some(h.intervals()),
+                                        _ => None,
+                                    })
+                                    .flatten()
+                                    .collect();
+                                self.clip_editor_panel.set_harmony(harmony);
+                                if let Some(clip) = track.midi_clips.iter_mut().find(|c| c.id == clip_id) {
+                                    let clip_start = clip.start_sample;
+                                    return Some(self.clip_editor_panel.ui_midi(
+                                        ui, clip, bpm, sample_rate, clip_start, playback_position, &self.clipboard
+                                    ));
+                                }
+                            }
+                            None
+                        }).flatten();
 
-impl eframe::App for HallucinatorApp {
-    fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
-        // Handle dropped files
-        ctx.input(|i| {
-            for file in &i.raw.dropped_files {
-                if let Some(path) = &file.path {
-                    if path.extension().is_some_and(|e| e == "wav") {
-                        self.load_audio_file(path);
+                        if let Some(a) = action {
+                            piano_roll_action = a;
+                        }
                     }
                 }
+                SelectedClip::Audio { track_idx, clip_id } => {
+                    let sample_rate = self.engine.sample_rate();
+
+                    self.engine.with_timeline(|timeline| {
+                        if let Some(track) = timeline.tracks.get(track_idx) {
+                            if let Some(clip) = track.clips.iter().find(|c| c.id == clip_id) {
+                                self.clip_editor_panel.ui_audio(ui, clip, sample_rate);
+                            }
+                        }
+                    });
+                }
             }
-        });
+        }
 
-        // Consume Tab globally to prevent egui's focus navigation from stealing it
-        // Check if shift is held, then consume Tab with any modifiers
-        let shift_held = ctx.input(|i| i.modifiers.shift);
-        let tab_pressed = ctx.input(|i| i.key_pressed(egui::Key::Tab));
-        if tab_pressed {
-            // Consume both variants to fully remove Tab from the event queue
-            ctx.input_mut(|i| {
-                i.consume_key(egui::Modifiers::NONE, egui::Key::Tab);
-                i.consume_key(egui::Modifiers::SHIFT, egui::Key::Tab);
-            });
-            self.keyboard_sequencer_panel.set_pending_tab(shift_held);
+        self.handle_piano_roll_action(piano_roll_action);
+        self.handle_drum_roll_action(drum_roll_action);
+    }
+}
+
+impl eframe::App for HallucinatorApp {
+    fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
+        // Handle files dropped from outside the app
+        let dropped: Vec<PathBuf> = ctx.input(|i| i.raw.dropped_files.iter().filter_map(|f| f.path.clone()).collect());
+        for path in dropped {
+            self.import_dropped_file(&path);
+        }
+
+        // Panels look their shortcuts up in the keymap; while a new shortcut is being
+        // pressed nothing is bound, so the keys reach the Keyboard Shortcuts window
+        if self.show_keymap_editor && self.keymap_editor_panel.is_capturing() {
+            Arc::new(Keymap::default()).install(ctx);
+        } else {
+            self.keymap.install(ctx);
+        }
+
+        // Consumed globally so egui's focus navigation doesn't also take them (Tab by default)
+        if let Some(command) = Command::consume_any(ctx, &[Command::SequencerPrevious, Command::SequencerNext]) {
+            self.keyboard_sequencer_panel.set_pending_tab(command == Command::SequencerPrevious);
         }
 
-        // Global spacebar → toggle playback (skip if a text field is focused)
+        // Global play/stop (skip if a text field is focused)
         let text_focused = ctx.memory(|mem| mem.focused().is_some())
             && ctx.input(|i| i.events.iter().any(|e| matches!(e, egui::Event::Text(_))));
-        if !text_focused && ctx.input(|i| i.key_pressed(egui::Key::Space)) {
+        if !text_focused && Command::PlayPause.pressed(ctx) {
             if self.engine.is_playing() {
                 self.engine.pause();
                 self.engine.seek(self.playback_start_position);
@@ -190,8 +433,21 @@ impl eframe::App for HallucinatorApp {
             }
         }
 
+        if self.latency_calibration.is_some() {
+            self.poll_latency_calibration();
+            ctx.request_repaint_after(std::time::Duration::from_millis(50));
+        }
+
+        // Hardware control surface input
+        self.poll_control_surface();
+        self.poll_audio_loads(ctx);
+        self.poll_instrument_loads(ctx);
+        self.engine_state.update_loudness_meter();
+
+        self.autosave_if_due();
+
         // Global Delete → delete selected clip (if any)
-        if !text_focused && ctx.input(|i| i.key_pressed(egui::Key::Delete) || i.key_pressed(egui::Key::Backspace)) {
+        if !text_focused && Command::Delete.pressed(ctx) {
             if let Some(selected) = self.selected_clip.take() {
                 self.delete_selected_clip(selected);
             }
@@ -199,23 +455,59 @@ impl eframe::App for HallucinatorApp {
 
         // 1. Menu bar
         let plugin_action = egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
-            self.plugin_menu.menu_ui(ui, &mut self.arrange_panel.snap_to_grid)
+            let master_effects = self.engine.with_master_effects(|chain| {
+                chain.effects().iter().map(|e| e.name().to_string()).collect::<Vec<_>>()
+            }).unwrap_or_default();
+            self.plugin_menu.menu_ui(
+                ui,
+                &mut self.arrange_panel.grid,
+                &mut self.layout,
+                self.workspaces.current,
+                &mut self.show_preferences,
+                &mut self.show_keymap_editor,
+                &mut self.notification_center.show_log,
+                &master_effects,
+            )
         }).inner;
 
-        self.handle_plugin_action(plugin_action);
+        self.handle_plugin_action(ctx, plugin_action);
 
         // 2. Transport bar
         let transport_action = egui::TopBottomPanel::top("transport").show(ctx, |ui| {
-            self.transport_panel.ui(ui, &self.engine, &self.engine_state, &mut self.input_monitor, &self.meter_state)
+            self.transport_panel.ui(
+                ui,
+                &self.engine,
+                &self.engine_state,
+                &mut self.input_monitor,
+                &self.meter_state,
+                &self.clock_sync,
+            )
         }).inner;
 
         match transport_action {
             TransportAction::StartRecording => self.start_recording(),
             TransportAction::StopRecording => self.stop_recording(),
+            TransportAction::SetClockSync(sync) => self.set_clock_sync(sync),
+            TransportAction::SetRecordToDisk(to_disk) => self.save_recording_config(|recording| recording.to_disk = to_disk),
+            TransportAction::SetLatencyOffset(offset) => {
+                self.save_recording_config(|recording| recording.latency_offset = offset);
+            }
+            TransportAction::CalibrateLatency => self.start_latency_calibration(),
             TransportAction::None => {}
         }
 
-        // 3. Bottom panel: Device Rack (always visible, at very bottom)
+        // Scanned VST3 plugins, offered by the device rack and the browser
+        let plugins = self.get_plugins();
+
+        // 2b. Status bar (at the very bottom); its contents are drawn last, once the other
+        // panels have set this frame's hints
+        let status_bar_rect = egui::TopBottomPanel::bottom("status_bar")
+            .resizable(false)
+            .exact_height(20.0)
+            .show(ctx, |ui| ui.max_rect())
+            .inner;
+
+        // 3. Bottom panel: Device Rack (always visible, above the status bar)
         egui::TopBottomPanel::bottom("device_rack_panel")
             .resizable(false)
             .exact_height(110.0)
@@ -231,11 +523,23 @@ impl eframe::App for HallucinatorApp {
                         .map(|idx| self.get_device_info_for_track(idx))
                         .unwrap_or((None, Vec::new()));
 
+                    let macros = self.selected_track_idx.and_then(|idx| {
+                        self.engine.with_timeline(|t| {
+                            t.tracks.get(idx).map(|track| track.macros.clone())
+                        }).flatten()
+                    });
+                    let macro_sources = self.selected_track_idx
+                        .map(|idx| self.mappable_devices(idx))
+                        .unwrap_or_default();
+
                     let action = self.device_rack_panel.ui(
                         ui,
                         track_name.as_deref(),
                         instrument.clone(),
                         &effects,
+                        macros.as_ref(),
+                        &macro_sources,
+                        &plugins,
                     );
                     if !matches!(action, DeviceRackAction::None) {
                         tracing::info!("Device rack action: {:?}, instrument: {:?}",
@@ -243,7 +547,21 @@ impl eframe::App for HallucinatorApp {
                                 DeviceRackAction::OpenPluginWindow(id) => format!("OpenPluginWindow({})", id),
                                 DeviceRackAction::ToggleBypass(id) => format!("ToggleBypass({})", id),
                                 DeviceRackAction::RemoveDevice(id) => format!("RemoveDevice({})", id),
-                                DeviceRackAction::AddEffect => "AddEffect".to_string(),
+                                DeviceRackAction::MoveEffect(from, to) => format!("MoveEffect({}, {})", from, to),
+                                DeviceRackAction::AddEffect(id) => format!("AddEffect({})", id),
+                                DeviceRackAction::AddVst3Effect(info) => format!("AddVst3Effect({})", info.name),
+                                DeviceRackAction::LoadSampler(path) => format!("LoadSampler({})", path.display()),
+                                DeviceRackAction::OpenEffectWindow(idx) => format!("OpenEffectWindow({})", idx),
+                                DeviceRackAction::ToggleEffectBypass(idx) => format!("ToggleEffectBypass({})", idx),
+                                DeviceRackAction::SetEffectMix(idx, mix) => format!("SetEffectMix({}, {:.2})", idx, mix),
+                                DeviceRackAction::SetEffectOversampling(idx, mode) => {
+                                    format!("SetEffectOversampling({}, {})", idx, mode.label())
+                                }
+                                DeviceRackAction::SetMacro(idx, value) => format!("SetMacro({}, {:.3})", idx, value),
+                                DeviceRackAction::RenameMacro(idx, name) => format!("RenameMacro({}, {})", idx, name),
+                                DeviceRackAction::MapMacro(idx, m) => format!("MapMacro({}, {})", idx, m.param),
+                                DeviceRackAction::UpdateMacroMapping(idx, m, _) => format!("UpdateMacroMapping({}, {})", idx, m),
+                                DeviceRackAction::RemoveMacroMapping(idx, m) => format!("RemoveMacroMapping({}, {})", idx, m),
                                 DeviceRackAction::None => "None".to_string(),
                             },
                             instrument.as_ref().map(|i| format!("id={} name={}", i.id, i.name))
@@ -254,10 +572,12 @@ impl eframe::App for HallucinatorApp {
             });
 
         // 3b. Factory Rat Sequencer panel (above device rack)
-        if self.show_factory_rat_panels {
+        let bank_mask = self.selected_track_idx.map_or(0, |idx| self.drum_bank_mask(idx));
+        self.keyboard_sequencer_panel.set_bank_filled(bank_mask);
+        if self.layout.show_sequencer {
             egui::TopBottomPanel::bottom("factory_rat_panel")
                 .resizable(true)
-                .default_height(160.0)
+                .default_height(self.layout.sequencer_height)
                 .min_height(100.0)
                 .show(ctx, |ui| {
                     // Get selected track info
@@ -282,7 +602,7 @@ impl eframe::App for HallucinatorApp {
                         }
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                             if ui.button("Hide").clicked() {
-                                self.show_factory_rat_panels = false;
+                                self.layout.show_sequencer = false;
                             }
                             if ui.small_button("?").on_hover_text("Open docs/sequencer.md").clicked() {
                                 let _ = open::that("docs/sequencer.md");
@@ -319,6 +639,7 @@ impl eframe::App for HallucinatorApp {
                                     ui,
                                     track_name.as_deref(),
                                     midi_fx_chain.as_ref(),
+                                    &self.midi_fx_presets,
                                 );
                                 self.handle_midi_fx_rack_action(action);
                             });
@@ -352,7 +673,7 @@ impl eframe::App for HallucinatorApp {
                 .show(ctx, |ui| {
                     ui.horizontal(|ui| {
                         if ui.button("▲ Show Sequencer").clicked() {
-                            self.show_factory_rat_panels = true;
+                            self.layout.show_sequencer = true;
                         }
                     });
                 });
@@ -366,120 +687,44 @@ impl eframe::App for HallucinatorApp {
             });
 
             let mut still_open = true;
-            egui::Window::new("FACTORY RAT Sequencer")
-                .open(&mut still_open)
-                .resizable(true)
-                .default_size([1020.0, 460.0])
-                .show(ctx, |ui| {
-                    let actions = self.keyboard_sequencer_panel.ui(
-                        ui,
-                        track_name.as_deref(),
-                        is_playing,
-                        &self.clipboard,
-                        &self.engine_state,
-                    );
-                    self.handle_keyboard_sequencer_actions(actions);
-                });
+            let actions = self.layout.show_window(ctx, "FACTORY RAT Sequencer", &mut still_open, Some([1020.0, 460.0]), |ui| {
+                self.keyboard_sequencer_panel.ui(
+                    ui,
+                    track_name.as_deref(),
+                    is_playing,
+                    &self.clipboard,
+                    &self.engine_state,
+                )
+            });
+            if let Some(actions) = actions {
+                self.handle_keyboard_sequencer_actions(actions);
+            }
             if !still_open {
                 self.keyboard_sequencer_panel.is_floating = false;
             }
         }
 
-        // 4. Clip Editor / Piano Roll panel (above sequencer, only when clip selected)
+        // 4. Clip Editor / Piano Roll panel (above sequencer, only when clip selected), or torn off
         if self.show_clip_editor {
-            egui::TopBottomPanel::bottom("clip_editor_panel")
-                .resizable(true)
-                .default_height(200.0)
-                .min_height(100.0)
-                .show(ctx, |ui| {
-                    ui.horizontal(|ui| {
-                        if ui.button("✕ Close").clicked() {
-                            self.show_clip_editor = false;
-                        }
-                        ui.separator();
-                    });
-
-                    let mut piano_roll_action = PianoRollAction::None;
-                    let mut drum_roll_action = DrumRollAction::None;
-
-                    if let Some(selected) = self.selected_clip {
-                        match selected {
-                            SelectedClip::Midi { track_idx, clip_id } => {
-                                let (bpm, sample_rate) = self.engine.with_timeline(|t| {
-                                    (t.transport.bpm, t.transport.sample_rate)
-                                }).unwrap_or((120.0, 44100));
-
-                                let playback_position = self.engine.position();
-
-                                // Check if this track uses a drum instrument
-                                let is_drum = self.engine.with_timeline(|timeline| {
-                                    timeline.tracks.get(track_idx)
-                                        .and_then(|track| track.instrument_id)
-                                }).flatten().and_then(|inst_id| {
-                                    let instruments = self.engine_state.instruments.lock().ok()?;
-                                    Some(instruments.get(&inst_id)?.is_drum())
-                                }).unwrap_or(false);
-
-                                if is_drum {
-                                    // Use drum roll panel for drum instruments
-                                    let action = self.engine.with_timeline(|timeline| {
-                                        if let Some(track) = timeline.tracks.get_mut(track_idx) {
-                                            if let Some(clip) = track.midi_clips.iter_mut().find(|c| c.id == clip_id) {
-                                                let clip_start = clip.start_sample;
-                                                return Some(self.drum_roll_panel.ui(
-                                                    ui, clip, bpm, sample_rate, clip_start, playback_position, &self.clipboard
-                                                ));
-                                            }
-                                        }
-                                        None
-                                    }).flatten();
-
-                                    if let Some(a) = action {
-                                        drum_roll_action = a;
-                                    }
-                                } else {
-                                    // Use piano roll panel for melodic instruments
-                                    let action = self.engine.with_timeline(|timeline| {
-                                        if let Some(track) = timeline.tracks.get_mut(track_idx) {
-                                            if let Some(clip) = track.midi_clips.iter_mut().find(|c| c.id == clip_id) {
-                                                let clip_start = clip.start_sample;
-                                                return Some(self.clip_editor_panel.ui_midi(
-                                                    ui, clip, bpm, sample_rate, clip_start, playback_position, &self.clipboard
-                                                ));
-                                            }
-                                        }
-                                        None
-                                    }).flatten();
-
-                                    if let Some(a) = action {
-                                        piano_roll_action = a;
-                                    }
-                                }
-                            }
-                            SelectedClip::Audio { track_idx, clip_id } => {
-                                let sample_rate = self.engine.sample_rate();
-
-                                self.engine.with_timeline(|timeline| {
-                                    if let Some(track) = timeline.tracks.get(track_idx) {
-                                        if let Some(clip) = track.clips.iter().find(|c| c.id == clip_id) {
-                                            self.clip_editor_panel.ui_audio(ui, clip, sample_rate);
-                                        }
-                                    }
-                                });
-                            }
-                        }
-                    }
-
-                    self.handle_piano_roll_action(piano_roll_action);
-                    self.handle_drum_roll_action(drum_roll_action);
+            if self.layout.clip_editor_detached {
+                let mut open = true;
+                viewports::show_native(ctx, egui::Id::new("clip_editor_window"), "Clip Editor", [960.0, 420.0], &mut open, |ui| {
+                    self.clip_editor_ui(ui);
                 });
+                self.show_clip_editor &= open;
+            } else {
+                egui::TopBottomPanel::bottom("clip_editor_panel")
+                    .resizable(true)
+                    .default_height(self.layout.clip_editor_height)
+                    .min_height(100.0)
+                    .show(ctx, |ui| self.clip_editor_ui(ui));
+            }
         }
 
         // 4. Left sidebar: Browser
-        let plugins = self.get_plugins();
         egui::SidePanel::left("browser")
             .resizable(true)
-            .default_width(180.0)
+            .default_width(self.layout.browser_width)
             .min_width(120.0)
             .show(ctx, |ui| {
                 let action = self.browser_panel.ui(ui, &plugins);
@@ -492,8 +737,8 @@ impl eframe::App for HallucinatorApp {
 
             ui.horizontal(|ui| {
                 // Track headers (fixed width)
-                let tracks: Vec<_> = self.engine.with_timeline(|t| {
-                    t.tracks.clone()
+                let (tracks, pan_law) = self.engine.with_timeline(|t| {
+                    (t.tracks.clone(), t.pan_law)
                 }).unwrap_or_default();
 
                 // Use fixed size for track headers column
@@ -501,7 +746,9 @@ impl eframe::App for HallucinatorApp {
                     Vec2::new(120.0, panel_height),
                     egui::Layout::top_down(egui::Align::LEFT),
                     |ui| {
-                        let action = self.track_headers_panel.ui(ui, &tracks, self.selected_track_idx);
+                        self.track_headers_panel.output_channels = self.engine.output_channels();
+                        self.track_headers_panel.pan_law = pan_law;
+                        let action = self.track_headers_panel.ui(ui, &tracks, self.selected_track_idx, &mut self.track_layout);
                         self.handle_track_header_action(action);
                     }
                 );
@@ -514,6 +761,7 @@ impl eframe::App for HallucinatorApp {
                         RecordingPreview {
                             samples,
                             start_sample: self.recording_start_sample,
+                            length_samples: self.input_monitor.recording_length() as u64,
                         }
                     })
                 } else {
@@ -524,6 +772,7 @@ impl eframe::App for HallucinatorApp {
                     SelectedClip::Audio { track_idx, clip_id } => (track_idx, clip_id),
                     SelectedClip::Midi { track_idx, clip_id } => (track_idx, clip_id),
                 });
+                let loading_clips = self.loading_clips();
 
                 let action = self.arrange_panel.ui(
                     ui,
@@ -532,64 +781,106 @@ impl eframe::App for HallucinatorApp {
                     self.selected_track_idx,
                     selected_clip_tuple,
                     recording_preview,
+                    &loading_clips,
                     &self.clipboard,
+                    &mut self.track_layout,
                 );
                 self.handle_arrange_action(action);
             });
         });
 
+        // MIDI learn snapshot shared by all parameter windows this frame
+        let midi_learn = MidiLearnState {
+            map: self.engine.with_timeline(|t| t.midi_map.clone()).unwrap_or_default(),
+            learning: self.midi_input.as_ref().and_then(|m| m.learning()),
+            available: self.midi_input.is_some(),
+        };
+        let mut midi_requests = Vec::new();
+
         // 6. Floating plugin windows with parameter controls
         let mut param_updates: Vec<(u64, String, f32)> = Vec::new();
-        let mut native_window_requests: Vec<(u64, String, String, String)> = Vec::new();
+        let mut native_window_requests: Vec<u64> = Vec::new();
+        let mut program_requests: Vec<(u64, usize)> = Vec::new();
+        let mut preset_file_requests = Vec::new();
 
         self.plugin_windows.retain_mut(|window| {
             let mut still_open = window.open;
 
-            // Get params from instrument (clone to release lock quickly)
-            let params = self.engine_state.instruments.lock().ok()
-                .and_then(|instruments| instruments.get(&window.id).map(|inst| inst.get_params().to_vec()));
-
-            egui::Window::new(&window.title)
-                .open(&mut still_open)
-                .resizable(true)
-                .default_size([350.0, 500.0])
-                .show(ctx, |ui| {
-                    let Some(params) = params else {
-                        ui.label("Plugin not found");
-                        return;
-                    };
+            // Get params and programs from instrument (clone to release lock quickly)
+            let (params, programs, current_program, instance, sandbox_restarts) = self.engine_state.instruments.lock().ok()
+                .and_then(|instruments| instruments.get(&window.id).map(|inst| {
+                    (
+                        Some(inst.get_params().to_vec()),
+                        inst.programs().to_vec(),
+                        inst.current_program(),
+                        inst.vst3_instance_handle(),
+                        inst.sandbox_restarts(),
+                    )
+                }))
+                .unwrap_or_default();
+            // Value text straight from the plugin, formatted outside the instruments lock
+            let display = |index: usize, value: f32| instance.as_ref()?.param_display(index, value);
+
+            let window_id = egui::Id::new(format!("plugin_param_{}", window.id));
+            let window_state = self.param_window_states.entry(window_id).or_default();
+
+            let detached = window_state.detached;
+            let detached = viewports::show_detachable(ctx, window_id, &window.title, [350.0, 500.0], &mut still_open, detached, |ui| {
+                let Some(params) = params else {
+                    ui.label("Plugin not found");
+                    return;
+                };
 
-                    // Native GUI button
-                    ui.horizontal(|ui| {
-                        if !window.native_window_created {
-                            if ui.button("Open Native GUI").clicked() {
-                                native_window_requests.push((
-                                    window.id,
-                                    window.plugin_path.clone(),
-                                    window.plugin_uid.clone(),
-                                    window.title.clone(),
-                                ));
-                            }
-                        } else {
-                            ui.label("Native GUI active");
-                            if ui.button("Show").clicked() {
-                                let _ = self.gui_manager.show_window(window.id);
-                            }
+                // Native GUI button (sandboxed plugins have no editor here)
+                ui.horizontal(|ui| {
+                    if let Some(restarts) = sandbox_restarts {
+                        ui.label("Sandboxed");
+                        if restarts > 0 {
+                            ui.colored_label(
+                                egui::Color32::YELLOW,
+                                format!("restarted {} time{}", restarts, if restarts == 1 { "" } else { "s" }),
+                            );
                         }
-                    });
-                    ui.separator();
+                    } else if !window.native_window_created {
+                        if ui.button("Open Native GUI").clicked() {
+                            native_window_requests.push(window.id);
+                        }
+                    } else {
+                        ui.label("Native GUI active");
+                        if ui.button("Show").clicked() {
+                            let _ = self.gui_manager.show_window(window.id);
+                        }
+                    }
+                });
+                if !programs.is_empty()
+                    && let Some(idx) = plugin_windows::render_program_bar(ui, window.id, &programs, current_program)
+                {
+                    program_requests.push((window.id, idx));
+                }
+                if let Some(request) = plugin_windows::render_preset_file_buttons(ui, &window.title) {
+                    preset_file_requests.push((window.id, request));
+                }
+                ui.separator();
 
-                    ui.horizontal(|ui| {
-                        ui.label(format!("{} parameters", params.len()));
-                    });
-                    ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} parameters", params.len()));
+                });
+                for (name, value) in plugin_windows::render_param_actions(ui, &params, &window_state.locked) {
+                    param_updates.push((window.id, name, value));
+                }
+                ui.separator();
 
-                    egui::ScrollArea::vertical().show(ui, |ui| {
-                        for (name, value) in plugin_windows::render_param_sliders(ui, &params) {
-                            param_updates.push((window.id, name, value));
-                        }
-                    });
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let owner = ParamOwner::Instrument(window.id);
+                    for (name, value) in plugin_windows::render_param_sliders(
+                        ui, &params, &mut window_state.locked, owner, &midi_learn, &mut midi_requests,
+                        Some(&display),
+                    ) {
+                        param_updates.push((window.id, name, value));
+                    }
                 });
+            });
+            window_state.detached = detached;
             window.open = still_open;
             still_open
         });
@@ -606,21 +897,32 @@ impl eframe::App for HallucinatorApp {
                 }))
                 .unwrap_or_else(|| ("Unknown".to_string(), Vec::new()));
 
-            egui::Window::new(&name)
-                .id(egui::Id::new(format!("native_param_{}", inst_id)))
-                .open(&mut still_open)
-                .resizable(true)
-                .default_size([300.0, 400.0])
-                .show(ctx, |ui| {
-                    ui.label(format!("{} parameters", params.len()));
-                    ui.separator();
+            let window_id = egui::Id::new(format!("native_param_{}", inst_id));
+            let window_state = self.param_window_states.entry(window_id).or_default();
 
-                    egui::ScrollArea::vertical().show(ui, |ui| {
-                        for (name, value) in plugin_windows::render_param_sliders(ui, &params) {
-                            param_updates.push((inst_id, name, value));
-                        }
-                    });
+            let detached = window_state.detached;
+            let detached = viewports::show_detachable(ctx, window_id, &name, [300.0, 400.0], &mut still_open, detached, |ui| {
+                for (name, value) in plugin_windows::render_preset_bar(ui, window_state, &name, &params) {
+                    param_updates.push((inst_id, name, value));
+                }
+                ui.separator();
+
+                ui.label(format!("{} parameters", params.len()));
+                for (name, value) in plugin_windows::render_param_actions(ui, &params, &window_state.locked) {
+                    param_updates.push((inst_id, name, value));
+                }
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let owner = ParamOwner::Instrument(inst_id);
+                    for (name, value) in plugin_windows::render_param_sliders(
+                        ui, &params, &mut window_state.locked, owner, &midi_learn, &mut midi_requests, None,
+                    ) {
+                        param_updates.push((inst_id, name, value));
+                    }
                 });
+            });
+            window_state.detached = detached;
 
             if !still_open {
                 windows_to_close.push(inst_id);
@@ -630,20 +932,269 @@ impl eframe::App for HallucinatorApp {
             self.native_param_windows.remove(&id);
         }
 
-        // Create native windows for requested plugins
-        for (id, path, uid, title) in native_window_requests {
-            if let Err(e) = self.gui_manager.create_window(id, &path, &uid, &title, 800, 600) {
-                tracing::warn!("Failed to create native window for plugin {}: {}", id, e);
+        // 8. Effect parameter windows (track inserts and master chain)
+        let mut effect_windows_to_close: Vec<(EffectChainRef, usize)> = Vec::new();
+        let mut effect_param_updates: Vec<(EffectChainRef, usize, String, f32)> = Vec::new();
+        for &(chain, idx) in &self.effect_param_windows {
+            let mut still_open = true;
+
+            let Some((name, params, meter)) = self.with_effect_chain(chain, |chain| {
+                chain.effects().get(idx).map(|e| {
+                    (e.name().to_string(), e.get_params(), e.gain_reduction_meter())
+                })
+            }).flatten() else {
+                effect_windows_to_close.push((chain, idx));
                 continue;
+            };
+
+            let window_id = egui::Id::new(match chain {
+                EffectChainRef::Master => format!("effect_param_master_{}", idx),
+                EffectChainRef::Track(chain_id) => format!("effect_param_{}_{}", chain_id, idx),
+            });
+            let window_state = self.param_window_states.entry(window_id).or_default();
+
+            let detached = window_state.detached;
+            let detached = viewports::show_detachable(ctx, window_id, &name, [300.0, 400.0], &mut still_open, detached, |ui| {
+                for (name, value) in plugin_windows::render_preset_bar(ui, window_state, &name, &params) {
+                    effect_param_updates.push((chain, idx, name, value));
+                }
+                ui.separator();
+
+                if let Some(meter) = &meter {
+                    plugin_windows::render_gain_reduction_meter(ui, meter.reduction_db());
+                    ui.separator();
+                }
+
+                ui.label(format!("{} parameters", params.len()));
+                for (name, value) in plugin_windows::render_param_actions(ui, &params, &window_state.locked) {
+                    effect_param_updates.push((chain, idx, name, value));
+                }
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let owner = match chain {
+                        EffectChainRef::Master => ParamOwner::MasterEffect(idx),
+                        EffectChainRef::Track(chain_id) => ParamOwner::TrackEffect { chain_id, index: idx },
+                    };
+                    for (name, value) in plugin_windows::render_param_sliders(
+                        ui, &params, &mut window_state.locked, owner, &midi_learn, &mut midi_requests, None,
+                    ) {
+                        effect_param_updates.push((chain, idx, name, value));
+                    }
+                });
+            });
+            window_state.detached = detached;
+
+            if meter.is_some() {
+                ctx.request_repaint();
             }
-            if let Err(e) = self.gui_manager.show_window(id) {
-                tracing::warn!("Failed to show native window: {}", e);
+            if !still_open {
+                effect_windows_to_close.push((chain, idx));
             }
-            // Mark window as having native GUI
-            if let Some(window) = self.plugin_windows.iter_mut().find(|w| w.id == id) {
-                window.native_window_created = true;
+        }
+        for key in effect_windows_to_close {
+            self.effect_param_windows.remove(&key);
+        }
+        for (chain, idx, name, value) in effect_param_updates {
+            self.with_effect_chain(chain, |chain| {
+                if let Some(effect) = chain.effects_mut().get_mut(idx) {
+                    effect.set_param(&name, value);
+                }
+            });
+        }
+
+        for request in midi_requests {
+            self.handle_midi_learn_request(request);
+        }
+        if midi_learn.learning.is_some() {
+            ctx.request_repaint();
+        }
+
+        // 9. Analyzer window
+        if self.layout.show_analyzer {
+            let sources = self.analyzer_sources();
+            let mut open = true;
+            self.layout.show_window(ctx, "Analyzer", &mut open, Some([520.0, 260.0]), |ui| {
+                self.analyzer_panel.ui(ui, &sources)
+            });
+            self.layout.show_analyzer = open;
+            ctx.request_repaint();
+        }
+
+        // 10. Modulation matrix window (selected track)
+        if self.layout.show_modulation {
+            let (track_name, matrix) = self.selected_track_idx
+                .and_then(|idx| {
+                    self.engine.with_timeline(|t| {
+                        t.tracks.get(idx).map(|track| (track.name.clone(), track.modulation.clone()))
+                    }).flatten()
+                })
+                .unzip();
+            let devices = self.selected_track_idx
+                .map(|idx| self.mappable_devices(idx))
+                .unwrap_or_default();
+            let mut open = true;
+            let action = self.layout.show_window(ctx, "Modulation", &mut open, Some([480.0, 240.0]), |ui| {
+                self.modulation_panel.ui(ui, track_name.as_deref(), matrix.as_ref(), &devices)
+            });
+            self.layout.show_modulation = open;
+            if let Some(action) = action {
+                self.handle_modulation_action(action);
+            }
+            ctx.request_repaint();
+        }
+
+        // 11. Automation lanes window (selected track)
+        if self.layout.show_automation {
+            let (track_name, automation) = self.selected_track_idx
+                .and_then(|idx| {
+                    self.engine.with_timeline(|t| {
+                        t.tracks.get(idx).map(|track| (track.name.clone(), track.automation.clone()))
+                    }).flatten()
+                })
+                .unzip();
+            let devices = self.selected_track_idx
+                .map(|idx| self.mappable_devices(idx))
+                .unwrap_or_default();
+            let playhead_beat = self.engine.with_timeline(|t| {
+                self.engine.position() as f64 * t.transport.bpm / (60.0 * t.transport.sample_rate as f64)
+            }).unwrap_or(0.0);
+            let mut open = true;
+            let action = self.layout.show_window(ctx, "Automation", &mut open, Some([560.0, 220.0]), |ui| {
+                self.automation_panel.ui(ui, track_name.as_deref(), automation.as_ref(), &devices, playhead_beat)
+            });
+            self.layout.show_automation = open;
+            if let Some(action) = action {
+                self.handle_automation_action(action);
+            }
+            if self.engine.is_playing() {
+                ctx.request_repaint();
+            }
+        }
+
+        // 12. Control surface mapping editor
+        if self.layout.show_control_surface {
+            let mut open = true;
+            let action = self.layout.show_window(ctx, "Control Surface", &mut open, Some([420.0, 360.0]), |ui| {
+                self.control_surface_panel.ui(
+                    ui,
+                    &self.surface_profile,
+                    &self.surface_profiles,
+                    self.surface_bank,
+                    self.surface_learn,
+                    self.midi_input.is_some(),
+                    self.osc_server.as_ref().map(OscServer::local_addr),
+                )
+            });
+            self.layout.show_control_surface = open;
+            if let Some(action) = action {
+                self.handle_control_surface_action(action);
+            }
+        }
+
+        // 13. Script console
+        if self.layout.show_script_console {
+            let mut open = true;
+            let action = self.layout.show_window(ctx, "Script Console", &mut open, Some([520.0, 420.0]), |ui| {
+                self.script_console_panel.ui(ui)
+            });
+            self.layout.show_script_console = open;
+            if let Some(ScriptConsoleAction::Run(source)) = action {
+                self.run_script(&source);
+            }
+        }
+
+        // 14. Mixer/device snapshots
+        self.advance_snapshot_morph();
+        if self.snapshot_morph.is_some() {
+            ctx.request_repaint();
+        }
+        if self.layout.show_snapshots {
+            let bank = self.engine.with_timeline(|t| t.snapshots.clone()).unwrap_or_default();
+            let bpm = self.engine.with_timeline(|t| t.transport.bpm).unwrap_or(120.0);
+            let progress = self.snapshot_morph.as_ref()
+                .map(|(morph, started)| morph.progress(started.elapsed().as_secs_f64(), bpm));
+            let mut open = true;
+            let action = self.layout.show_window(ctx, "Snapshots", &mut open, None, |ui| {
+                self.snapshot_panel.ui(ui, &bank, progress)
+            });
+            self.layout.show_snapshots = open;
+            if let Some(action) = action {
+                self.handle_snapshot_action(action);
             }
-            tracing::info!("Created native GUI window for plugin {}", id);
+        }
+
+        // 15. Chord track
+        if self.layout.show_chord_track {
+            let (track, beats_per_bar, bpm) = self.engine.with_timeline(|t| {
+                (t.chord_track.clone(), t.transport.time_sig_num, t.transport.bpm)
+            }).unwrap_or_default();
+            let playhead_beat = self.engine.position() as f64 * bpm / (60.0 * self.engine.sample_rate() as f64);
+            let mut open = true;
+            let action = self.layout.show_window(ctx, "Chord Track", &mut open, Some([640.0, 120.0]), |ui| {
+                self.chord_track_panel.ui(ui, &track, beats_per_bar, playhead_beat)
+            });
+            self.layout.show_chord_track = open;
+            if self.engine.is_playing() {
+                ctx.request_repaint();
+            }
+            if let Some(action) = action {
+                self.handle_chord_track_action(action);
+            }
+        }
+
+        // 16. Preferences
+        if self.show_preferences {
+            let cue = self.engine.cue_settings();
+            let metronome_volume = self.engine.metronome_volume();
+            let output_channels = self.engine.output_channels();
+            let mut open = true;
+            let action = egui::Window::new("Preferences")
+                .open(&mut open)
+                .resizable(false)
+                .show(ctx, |ui| self.preferences_panel.ui(ui, &self.preferences, &cue, metronome_volume, output_channels))
+                .and_then(|response| response.inner);
+            self.show_preferences = open;
+            if let Some(action) = action {
+                self.handle_preferences_action(ctx, action);
+            }
+        }
+
+        // 17. Keyboard shortcuts
+        if self.show_keymap_editor {
+            let keymap = self.keymap.clone();
+            let mut open = true;
+            let action = egui::Window::new("Keyboard Shortcuts")
+                .open(&mut open)
+                .resizable(false)
+                .show(ctx, |ui| self.keymap_editor_panel.ui(ui, &keymap))
+                .and_then(|response| response.inner);
+            self.show_keymap_editor = open;
+            if let Some(action) = action {
+                self.handle_keymap_editor_action(action);
+            }
+        }
+
+        // Create native windows for requested plugins
+        for id in native_window_requests {
+            self.open_native_plugin_gui(id);
+        }
+
+        // Switch plugin programs picked in plugin windows
+        if !program_requests.is_empty()
+            && let Ok(mut instruments) = self.engine_state.instruments.lock()
+        {
+            for (id, idx) in program_requests {
+                if let Some(inst) = instruments.get_mut(&id)
+                    && let Err(e) = inst.set_program(idx)
+                {
+                    tracing::warn!("Failed to select program {} on instrument {}: {}", idx, id, e);
+                }
+            }
+        }
+
+        for (id, request) in preset_file_requests {
+            self.handle_preset_file_request(id, request);
         }
 
         // Apply parameter updates outside of UI loop
@@ -660,34 +1211,19 @@ impl eframe::App for HallucinatorApp {
         // Process native window events
         let _ = self.gui_manager.process_events();
 
-        // NOTE: Full state/preset syncing from native plugin GUIs is disabled due to
-        // thread-safety issues with VST3 plugins. The GUI and audio are separate plugin
-        // instances. Parameter tweaks are synced via get_parameter_changes() below, but
-        // preset changes in the plugin's native UI won't affect audio playback.
-        // TODO: Consider sharing a single plugin instance between GUI and audio.
-        let _state_changes = self.gui_manager.get_state_changes();
-        if !_state_changes.is_empty() {
-            tracing::warn!(
-                "Preset changed in native plugin GUI - this won't affect audio playback. \
-                 Use the DAW's preset management instead."
-            );
-        }
-
-        // Sync parameter changes from native plugin GUIs to audio instruments
+        // Native editors share their instrument's plugin instance, so the audio
+        // already follows editor changes (presets included); only refresh the
+        // host's cached parameter values shown in parameter windows.
         let param_changes = self.gui_manager.get_parameter_changes();
-        if !param_changes.is_empty() {
-            tracing::info!("Syncing {} parameter changes from GUI", param_changes.len());
-            if let Ok(mut instruments) = self.engine_state.instruments.lock() {
-                let inst_ids: Vec<_> = instruments.keys().copied().collect();
-                tracing::info!("Available instrument IDs: {:?}", inst_ids);
-                for (plugin_id, param_index, value) in param_changes {
-                    tracing::info!("Looking for plugin_id={} to set param[{}]={}", plugin_id, param_index, value);
-                    if let Some(inst) = instruments.get_mut(&plugin_id) {
-                        tracing::info!("Found instrument, setting param {} = {}", param_index, value);
-                        inst.set_param_by_index(param_index, value);
-                    } else {
-                        tracing::warn!("Plugin {} not found in instruments (available: {:?})", plugin_id, inst_ids);
-                    }
+        if !param_changes.is_empty()
+            && let Ok(mut instruments) = self.engine_state.instruments.lock()
+        {
+            for (plugin_id, param_index, gesture) in param_changes {
+                // Begin/End only mark the user's touch; values are what the cache needs
+                if let ParamGesture::Change { value, .. } = gesture
+                    && let Some(inst) = instruments.get_mut(&plugin_id)
+                {
+                    inst.sync_param_from_editor(param_index, value);
                 }
             }
         }
@@ -696,9 +1232,28 @@ impl eframe::App for HallucinatorApp {
         if self.engine.is_playing() || self.input_monitor.is_running() {
             ctx.request_repaint();
         }
+
+        // Failures posted by the engine, plugin hosts and the call sites above
+        self.notification_center.poll();
+        self.notification_center.ui(ctx);
+
+        let engine_status = EngineStatus {
+            sample_rate: self.engine.sample_rate(),
+            buffer_size: self.engine.output_settings().buffer_size,
+        };
+        let mut status_bar_ui = egui::Ui::new(
+            ctx.clone(),
+            egui::Id::new("status_bar_contents"),
+            egui::UiBuilder::new().max_rect(status_bar_rect),
+        );
+        self.status_bar_panel.ui(&mut status_bar_ui, &engine_status, &self.audio_load_progress());
+
+        self.layout.sequencer_floating = self.keyboard_sequencer_panel.is_floating;
+        self.layout.end_frame(ctx);
     }
 
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.save_layout_config();
         let _ = self.input_monitor.stop();
         let _ = self.engine.stop();
     }
@@ -1,37 +1,177 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use hallucinator_core::{CcMode, MidiMapping, ParamOwner};
 use hallucinator_services::audio_effects::EffectParam;
+use hallucinator_services::{notifications, Instrument, LearnRequest, VstPreset, VST_PRESET_EXTENSION};
 
+use super::presets::{self, ParamWindowState};
+use super::types::{MidiLearnRequest, MidiLearnState, PluginWindow, PresetFileRequest};
 use super::HallucinatorApp;
 
 impl HallucinatorApp {
     /// Open native plugin GUI window directly (no egui parameter window).
-    pub(super) fn open_native_plugin_gui(&mut self, plugin_id: u64, plugin_path: &str, plugin_uid: &str, title: &str) {
-        if plugin_path.is_empty() || plugin_uid.is_empty() {
-            tracing::warn!("Cannot open native GUI: missing plugin path or UID");
+    ///
+    /// The editor is attached to the instrument's own plugin instance.
+    pub(super) fn open_native_plugin_gui(&mut self, plugin_id: u64) {
+        let handle = self.engine_state.instruments.lock().ok()
+            .and_then(|instruments| instruments.get(&plugin_id)?.vst3_instance_handle());
+        let Some(handle) = handle else {
+            tracing::warn!("Cannot open native GUI: plugin {} has no in-process VST3 instance", plugin_id);
             return;
-        }
+        };
+        let title = handle.info.name.clone();
 
         tracing::info!("Opening native GUI for plugin_id={} title={}", plugin_id, title);
 
-        if let Err(e) = self.gui_manager.create_window(plugin_id, plugin_path, plugin_uid, title, 800, 600) {
-            tracing::error!("Failed to create native plugin window: {}", e);
+        if let Err(e) = self.gui_manager.create_window(plugin_id, handle, &title, 800, 600) {
+            notifications::error("Failed to create native plugin window", e);
             return;
         }
+        // Mark the host window as having a native GUI
+        if let Some(window) = self.plugin_windows.iter_mut().find(|w| w.id == plugin_id) {
+            window.native_window_created = true;
+        }
         if let Err(e) = self.gui_manager.show_window(plugin_id) {
             tracing::warn!("Failed to show native plugin window: {}", e);
             return;
         }
         tracing::info!("Opened native GUI for plugin {}", title);
     }
+
+    /// Open (or reopen) the host parameter window for a VST3 instrument
+    pub(super) fn open_plugin_window(&mut self, plugin_id: u64, title: String) {
+        match self.plugin_windows.iter_mut().find(|w| w.id == plugin_id) {
+            Some(window) => window.open = true,
+            None => self.plugin_windows.push(PluginWindow {
+                id: plugin_id,
+                title,
+                open: true,
+                native_window_created: self.gui_manager.has_window(plugin_id),
+            }),
+        }
+    }
+
+    pub(super) fn handle_preset_file_request(&mut self, plugin_id: u64, request: PresetFileRequest) {
+        match request {
+            PresetFileRequest::Load(path) => self.load_vst_preset_file(&path, Some(plugin_id)),
+            PresetFileRequest::Save(path) => self.save_vst_preset_file(plugin_id, &path),
+        }
+    }
+
+    /// Apply a `.vstpreset` file to `target`, or else to the selected track's
+    /// instrument if it is the preset's plugin, or else to the first loaded
+    /// instance of that plugin.
+    pub(super) fn load_vst_preset_file(&mut self, path: &Path, target: Option<u64>) {
+        let preset = match VstPreset::read(path) {
+            Ok(preset) => preset,
+            Err(e) => {
+                notifications::warning(format!("Failed to read preset {}", path.display()), e);
+                return;
+            }
+        };
+        let selected = self.selected_track_idx.and_then(|idx| {
+            self.engine.with_timeline(|t| t.tracks.get(idx).and_then(|track| track.instrument_id)).flatten()
+        });
+
+        let Ok(mut instruments) = self.engine_state.instruments.lock() else { return };
+        let id = {
+            let matches = |id: &u64| {
+                instruments
+                    .get(id)
+                    .and_then(Instrument::vst3_plugin_info)
+                    .is_some_and(|info| preset.matches(&info.info.unique_id))
+            };
+            target
+                .or_else(|| selected.filter(matches))
+                .or_else(|| instruments.keys().copied().filter(matches).min())
+        };
+        let Some(inst) = id.and_then(|id| instruments.get_mut(&id)) else {
+            tracing::warn!("No loaded plugin matches preset {:?}", path);
+            return;
+        };
+        match inst.load_vst_preset(&preset) {
+            Ok(()) => tracing::info!("Loaded preset {:?} into {}", path, inst.name()),
+            Err(e) => notifications::warning(format!("Failed to load preset {}", path.display()), e),
+        }
+    }
+
+    fn save_vst_preset_file(&mut self, plugin_id: u64, path: &Path) {
+        let preset = self.engine_state.instruments.lock().ok()
+            .and_then(|instruments| instruments.get(&plugin_id).map(Instrument::save_vst_preset));
+        let result = match preset {
+            Some(Ok(preset)) => preset.write(&path.with_extension(VST_PRESET_EXTENSION)),
+            Some(Err(e)) => Err(e),
+            None => return,
+        };
+        match result {
+            Ok(()) => self.browser_panel.refresh_presets(),
+            Err(e) => tracing::warn!("Failed to save preset {:?}: {}", path, e),
+        }
+    }
 }
 
-/// Render parameter sliders for a list of effect params.
+/// Render the Load/Save `.vstpreset` buttons for a plugin window header.
+/// Returns the file picked in the dialog, if any.
+pub(super) fn render_preset_file_buttons(ui: &mut egui::Ui, plugin: &str) -> Option<PresetFileRequest> {
+    let mut request = None;
+    let dialog = || {
+        rfd::FileDialog::new()
+            .add_filter("VST3 preset", &[VST_PRESET_EXTENSION])
+            .set_directory(presets::vst_preset_dir(plugin))
+    };
+
+    ui.horizontal(|ui| {
+        if ui.button("Load preset file...").clicked()
+            && let Some(path) = dialog().pick_file()
+        {
+            request = Some(PresetFileRequest::Load(path));
+        }
+        if ui.button("Save preset file...").clicked()
+            && let Some(path) = dialog().set_file_name(format!("{plugin}.{VST_PRESET_EXTENSION}")).save_file()
+        {
+            request = Some(PresetFileRequest::Save(path));
+        }
+    });
+
+    request
+}
+
+/// Render parameter sliders for a list of effect params, each with a lock
+/// toggle that excludes it from randomization and a right-click MIDI learn menu.
+/// `display` formats a (param index, value) as the plugin shows it, where available.
 /// Returns a vec of (param_name, new_value) for any changed params.
-pub(super) fn render_param_sliders(ui: &mut egui::Ui, params: &[EffectParam]) -> Vec<(String, f32)> {
+pub(super) fn render_param_sliders(
+    ui: &mut egui::Ui,
+    params: &[EffectParam],
+    locked: &mut HashSet<String>,
+    owner: ParamOwner,
+    midi: &MidiLearnState,
+    midi_requests: &mut Vec<MidiLearnRequest>,
+    display: Option<&dyn Fn(usize, f32) -> Option<String>>,
+) -> Vec<(String, f32)> {
     let mut changes = Vec::new();
 
-    for param in params {
+    for (index, param) in params.iter().enumerate() {
+        let mapping = midi.map.find(owner, &param.name);
+        let learning = midi.learning.as_ref().is_some_and(|l| l.owner == owner && l.param == param.name);
+
         ui.horizontal(|ui| {
+            let is_locked = locked.contains(&param.name);
+            let lock_icon = if is_locked { "\u{1F512}" } else { "\u{1F513}" };
+            if ui.selectable_label(is_locked, lock_icon).on_hover_text("Lock against randomize").clicked() {
+                if is_locked {
+                    locked.remove(&param.name);
+                } else {
+                    locked.insert(param.name.clone());
+                }
+            }
             ui.label(&param.name);
+            if learning {
+                ui.colored_label(egui::Color32::from_rgb(255, 180, 60), "move a control...");
+            } else if let Some(mapping) = mapping {
+                ui.weak(mapping.label());
+            }
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                 if !param.unit.is_empty() {
                     ui.label(&param.unit);
@@ -41,15 +181,222 @@ pub(super) fn render_param_sliders(ui: &mut egui::Ui, params: &[EffectParam]) ->
 
         let mut value = param.value;
         let range = param.min..=param.max;
-        let slider = egui::Slider::new(&mut value, range)
+        let mut slider = egui::Slider::new(&mut value, range)
             .show_value(true)
             .clamping(egui::SliderClamping::Always);
+        if param.steps > 0 {
+            slider = slider.step_by(((param.max - param.min) / param.steps as f32) as f64);
+        }
+        if let Some(display) = display {
+            slider = slider.custom_formatter(move |v, decimals| {
+                display(index, v as f32).unwrap_or_else(|| format!("{:.*}", *decimals.end(), v))
+            });
+        }
 
-        if ui.add(slider).changed() {
+        let response = ui.add(slider);
+        if response.changed() {
             changes.push((param.name.clone(), value));
         }
+        response.context_menu(|ui| {
+            render_midi_learn_menu(ui, param, owner, mapping, learning, midi.available, midi_requests);
+        });
         ui.add_space(4.0);
     }
 
     changes
 }
+
+/// Context menu for a parameter slider: arm MIDI learn or edit the existing mapping
+fn render_midi_learn_menu(
+    ui: &mut egui::Ui,
+    param: &EffectParam,
+    owner: ParamOwner,
+    mapping: Option<&MidiMapping>,
+    learning: bool,
+    available: bool,
+    requests: &mut Vec<MidiLearnRequest>,
+) {
+    if learning {
+        if ui.button("Cancel MIDI Learn").clicked() {
+            requests.push(MidiLearnRequest::Cancel);
+            ui.close_menu();
+        }
+    } else {
+        let learn = ui
+            .add_enabled(available, egui::Button::new("MIDI Learn"))
+            .on_disabled_hover_text("No MIDI input device");
+        if learn.clicked() {
+            let (min, max) = mapping.map_or((param.min, param.max), |m| (m.min, m.max));
+            requests.push(MidiLearnRequest::Learn(LearnRequest { owner, param: param.name.clone(), min, max }));
+            ui.close_menu();
+        }
+    }
+
+    let Some(mapping) = mapping else { return };
+    ui.separator();
+    ui.label(mapping.label());
+
+    // Plain selectables rather than a combo box: nested popups close the menu
+    ui.horizontal(|ui| {
+        for mode in CcMode::ALL {
+            if ui.selectable_label(mapping.mode == mode, mode.name()).clicked() {
+                requests.push(MidiLearnRequest::SetMode { owner, param: param.name.clone(), mode });
+            }
+        }
+    });
+
+    let (lo, hi) = (param.min.min(param.max), param.max.max(param.min));
+    let speed = ((hi - lo) / 200.0).clamp(0.001, 100.0);
+    let (mut min, mut max) = (mapping.min, mapping.max);
+    ui.horizontal(|ui| {
+        ui.label("Min");
+        let min_changed = ui.add(egui::DragValue::new(&mut min).range(lo..=hi).speed(speed)).changed();
+        ui.label("Max");
+        let max_changed = ui.add(egui::DragValue::new(&mut max).range(lo..=hi).speed(speed)).changed();
+        if min_changed || max_changed {
+            requests.push(MidiLearnRequest::SetRange { owner, param: param.name.clone(), min, max });
+        }
+    });
+
+    if ui.button("Clear Mapping").clicked() {
+        requests.push(MidiLearnRequest::Forget { owner, param: param.name.clone() });
+        ui.close_menu();
+    }
+}
+
+/// Render the Randomize / Reset buttons for a device.
+/// Returns a vec of (param_name, value) to apply: random values within range for
+/// unlocked params, or every param's default value.
+pub(super) fn render_param_actions(
+    ui: &mut egui::Ui,
+    params: &[EffectParam],
+    locked: &HashSet<String>,
+) -> Vec<(String, f32)> {
+    let mut changes = Vec::new();
+
+    ui.horizontal(|ui| {
+        if ui.button("Randomize").on_hover_text("Randomize unlocked parameters").clicked() {
+            changes = params
+                .iter()
+                .filter(|p| !locked.contains(&p.name))
+                .map(|p| (p.name.clone(), p.min + fastrand::f32() * (p.max - p.min)))
+                .collect();
+        }
+        if ui.button("Reset").on_hover_text("Reset all parameters to defaults").clicked() {
+            changes = params.iter().map(|p| (p.name.clone(), p.default)).collect();
+        }
+    });
+
+    changes
+}
+
+/// Render a horizontal gain reduction meter (0-24 dB, filling from the left).
+pub(super) fn render_gain_reduction_meter(ui: &mut egui::Ui, reduction_db: f32) {
+    const RANGE_DB: f32 = 24.0;
+
+    ui.horizontal(|ui| {
+        ui.label("GR");
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(200.0, 12.0), egui::Sense::hover());
+        let painter = ui.painter();
+        painter.rect_filled(rect, 2.0, egui::Color32::from_gray(30));
+
+        let fraction = (reduction_db / RANGE_DB).clamp(0.0, 1.0);
+        let mut fill = rect;
+        fill.set_width(rect.width() * fraction);
+        painter.rect_filled(fill, 2.0, egui::Color32::from_rgb(220, 160, 50));
+
+        ui.label(format!("-{:.1} dB", reduction_db.max(0.0)));
+    });
+}
+
+/// Render the plugin's program dropdown with previous/next buttons.
+/// Returns the program to switch to, if one was picked.
+pub(super) fn render_program_bar(
+    ui: &mut egui::Ui,
+    id: u64,
+    programs: &[String],
+    current: Option<usize>,
+) -> Option<usize> {
+    let mut selected = None;
+    let last = programs.len().saturating_sub(1);
+
+    ui.horizontal(|ui| {
+        if ui.add_enabled(current.is_some_and(|c| c > 0), egui::Button::new("◀"))
+            .on_hover_text("Previous preset")
+            .clicked()
+        {
+            selected = current.map(|c| c - 1);
+        }
+
+        let label = current.and_then(|c| programs.get(c)).map_or("Factory presets", String::as_str);
+        egui::ComboBox::from_id_salt(("program_combo", id))
+            .selected_text(label)
+            .width(180.0)
+            .show_ui(ui, |ui| {
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for (idx, name) in programs.iter().enumerate() {
+                        if ui.selectable_label(current == Some(idx), name).clicked() {
+                            selected = Some(idx);
+                        }
+                    }
+                });
+            });
+
+        if ui.add_enabled(current.is_none_or(|c| c < last), egui::Button::new("▶"))
+            .on_hover_text("Next preset")
+            .clicked()
+        {
+            selected = Some(current.map_or(0, |c| c + 1));
+        }
+    });
+
+    selected
+}
+
+/// Render the preset dropdown, save field and A/B compare toggle for a device.
+/// Returns a vec of (param_name, value) to apply from a loaded preset or A/B switch.
+pub(super) fn render_preset_bar(
+    ui: &mut egui::Ui,
+    bar: &mut ParamWindowState,
+    device: &str,
+    params: &[EffectParam],
+) -> Vec<(String, f32)> {
+    let mut changes = Vec::new();
+    let presets_list = bar.presets.get_or_insert_with(|| presets::list_presets(device)).clone();
+
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt(("preset_combo", device))
+            .selected_text("Presets")
+            .width(110.0)
+            .show_ui(ui, |ui| {
+                if presets_list.is_empty() {
+                    ui.label("No presets saved");
+                }
+                for name in presets_list.iter() {
+                    if ui.selectable_label(false, name).clicked() {
+                        if let Some(values) = presets::load_preset(device, name) {
+                            changes = values;
+                            bar.name = name.clone();
+                        }
+                    }
+                }
+            });
+
+        ui.add(egui::TextEdit::singleline(&mut bar.name).hint_text("name").desired_width(90.0));
+        let can_save = !bar.name.trim().is_empty();
+        if ui.add_enabled(can_save, egui::Button::new("Save")).clicked() {
+            match presets::save_preset(device, bar.name.trim(), params) {
+                Ok(()) => bar.presets = None,
+                Err(e) => tracing::warn!("Failed to save preset '{}': {}", bar.name, e),
+            }
+        }
+
+        ui.separator();
+        let ab_label = if bar.editing_b { "B" } else { "A" };
+        if ui.button(ab_label).on_hover_text("A/B compare: switch slot").clicked() {
+            changes = bar.toggle_ab(params);
+        }
+    });
+
+    changes
+}
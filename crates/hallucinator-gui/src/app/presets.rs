@@ -0,0 +1,220 @@
+//! Named parameter presets for native effects and instruments
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use hallucinator_core::{DrumPatternData, MidiEffect, MidiFxChain};
+use hallucinator_services::EffectParam;
+
+use crate::panels::MidiEffectType;
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+struct PresetFile {
+    #[serde(default)]
+    params: BTreeMap<String, f32>,
+}
+
+/// A saved MIDI FX chain: each effect by name, with its settings
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+struct MidiFxPresetFile {
+    #[serde(default)]
+    effects: Vec<MidiFxPresetSlot>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct MidiFxPresetSlot {
+    effect: String,
+    #[serde(default)]
+    bypass: bool,
+    #[serde(default)]
+    params: BTreeMap<String, f32>,
+}
+
+/// A device or preset name made safe to use as a file or folder name
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// Stems of the `.toml` files in a folder, sorted alphabetically
+fn toml_names(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "toml"))
+        .filter_map(|p| p.file_stem().and_then(|s| s.to_str()).map(String::from))
+        .collect();
+    names.sort_by_key(|n| n.to_lowercase());
+    names
+}
+
+/// Preset directory for a device, e.g. `~/.config/hallucinator/presets/Limiter`
+fn presets_dir(device: &str) -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("hallucinator")
+        .join("presets")
+        .join(file_name(device))
+}
+
+fn preset_path(device: &str, name: &str) -> PathBuf {
+    presets_dir(device).join(format!("{}.toml", file_name(name)))
+}
+
+/// Names of saved presets for a device, sorted alphabetically
+pub(super) fn list_presets(device: &str) -> Vec<String> {
+    toml_names(&presets_dir(device))
+}
+
+pub(super) fn save_preset(device: &str, name: &str, params: &[EffectParam]) -> std::io::Result<()> {
+    let dir = presets_dir(device);
+    std::fs::create_dir_all(&dir)?;
+    let file = PresetFile {
+        params: params.iter().map(|p| (p.name.clone(), p.value)).collect(),
+    };
+    let s = toml::to_string_pretty(&file).map_err(std::io::Error::other)?;
+    std::fs::write(preset_path(device, name), s)
+}
+
+/// Load a preset as (param name, value) pairs
+pub(super) fn load_preset(device: &str, name: &str) -> Option<Vec<(String, f32)>> {
+    let s = std::fs::read_to_string(preset_path(device, name)).ok()?;
+    let file: PresetFile = toml::from_str(&s).ok()?;
+    Some(file.params.into_iter().collect())
+}
+
+/// MIDI FX chain preset directory, `~/.config/hallucinator/midi_fx`
+fn midi_fx_presets_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("hallucinator")
+        .join("midi_fx")
+}
+
+fn midi_fx_preset_path(name: &str) -> PathBuf {
+    midi_fx_presets_dir().join(format!("{}.toml", file_name(name)))
+}
+
+/// Names of saved MIDI FX chains, sorted alphabetically
+pub(super) fn list_midi_fx_presets() -> Vec<String> {
+    toml_names(&midi_fx_presets_dir())
+}
+
+pub(super) fn save_midi_fx_preset(name: &str, chain: &MidiFxChain) -> std::io::Result<()> {
+    std::fs::create_dir_all(midi_fx_presets_dir())?;
+    let file = MidiFxPresetFile {
+        effects: chain
+            .effects
+            .iter()
+            .map(|effect| MidiFxPresetSlot {
+                effect: effect.name().to_string(),
+                bypass: effect.is_bypassed(),
+                params: effect.get_params().iter().map(|p| (p.name.clone(), p.value)).collect(),
+            })
+            .collect(),
+    };
+    let s = toml::to_string_pretty(&file).map_err(std::io::Error::other)?;
+    std::fs::write(midi_fx_preset_path(name), s)
+}
+
+/// Effects of a saved MIDI FX chain, in order (effects this build doesn't know are skipped)
+pub(super) fn load_midi_fx_preset(name: &str) -> Option<Vec<MidiEffect>> {
+    let s = std::fs::read_to_string(midi_fx_preset_path(name)).ok()?;
+    let file: MidiFxPresetFile = toml::from_str(&s).ok()?;
+    let effects = file
+        .effects
+        .into_iter()
+        .filter_map(|slot| {
+            let mut effect = MidiEffectType::from_name(&slot.effect)?.create_effect();
+            for (param, value) in &slot.params {
+                effect.set_param(param, *value);
+            }
+            effect.set_bypass(slot.bypass);
+            Some(effect)
+        })
+        .collect();
+    Some(effects)
+}
+
+/// Keyboard sequencer drum pattern directory, `~/.config/hallucinator/drum_patterns`
+fn drum_patterns_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("hallucinator")
+        .join("drum_patterns")
+}
+
+fn drum_pattern_path(name: &str) -> PathBuf {
+    drum_patterns_dir().join(format!("{}.toml", file_name(name)))
+}
+
+/// Names of saved drum patterns, sorted alphabetically
+pub(super) fn list_drum_patterns() -> Vec<String> {
+    toml_names(&drum_patterns_dir())
+}
+
+pub(super) fn save_drum_pattern(name: &str, pattern: &DrumPatternData) -> std::io::Result<()> {
+    std::fs::create_dir_all(drum_patterns_dir())?;
+    let s = toml::to_string_pretty(pattern).map_err(std::io::Error::other)?;
+    std::fs::write(drum_pattern_path(name), s)
+}
+
+pub(super) fn load_drum_pattern(name: &str) -> Option<DrumPatternData> {
+    let s = std::fs::read_to_string(drum_pattern_path(name)).ok()?;
+    toml::from_str(&s).ok()
+}
+
+/// Folders searched for `.vstpreset` files: `~/.vst3/presets` and the system preset folders
+pub(super) fn vst_preset_roots() -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = dirs::home_dir()
+        .map(|home| home.join(".vst3").join("presets"))
+        .into_iter()
+        .collect();
+    roots.push(PathBuf::from("/usr/share/vst3/presets"));
+    roots.push(PathBuf::from("/usr/local/share/vst3/presets"));
+    roots
+}
+
+/// Folder for a plugin's saved `.vstpreset` files, e.g. `~/.vst3/presets/Surge XT`
+pub(super) fn vst_preset_dir(plugin: &str) -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".vst3")
+        .join("presets")
+        .join(file_name(plugin))
+}
+
+/// Per-window parameter UI state: preset name entry, cached preset list,
+/// A/B slots, params locked against randomization and whether it's torn off
+#[derive(Default)]
+pub(super) struct ParamWindowState {
+    pub name: String,
+    pub presets: Option<Vec<String>>,
+    pub slot_a: Option<Vec<(String, f32)>>,
+    pub slot_b: Option<Vec<(String, f32)>>,
+    pub editing_b: bool,
+    pub locked: HashSet<String>,
+    /// Shown in a native window of its own
+    pub detached: bool,
+}
+
+impl ParamWindowState {
+    /// Store the current params in the active slot and switch to the other one.
+    /// Returns the params to apply (unchanged if the other slot is still empty).
+    pub fn toggle_ab(&mut self, params: &[EffectParam]) -> Vec<(String, f32)> {
+        let current: Vec<(String, f32)> = params.iter().map(|p| (p.name.clone(), p.value)).collect();
+        let (active, other) = if self.editing_b {
+            (&mut self.slot_b, &mut self.slot_a)
+        } else {
+            (&mut self.slot_a, &mut self.slot_b)
+        };
+        *active = Some(current.clone());
+        let target = other.get_or_insert(current).clone();
+        self.editing_b = !self.editing_b;
+        target
+    }
+}
@@ -1,8 +1,10 @@
 use std::sync::Arc;
 
+use hallucinator_core::{ClipId, DrumPatternData, MidiClip, MidiNote, TrackKind};
 use hallucinator_services::{Instrument, SampleKit};
 
 use crate::clipboard::ClipboardContent;
+use super::types::LoadTarget;
 use super::HallucinatorApp;
 
 impl HallucinatorApp {
@@ -70,13 +72,13 @@ impl HallucinatorApp {
         self.keyboard_sequencer_panel.set_step_sample_name(step, layer, name);
     }
 
+    /// Put an audio file in a step's sample layer once it has been decoded in the background
     pub(super) fn load_step_sample(&mut self, track_idx: usize, step: usize, layer: usize, path: &std::path::Path) {
-        let engine_sr = self.engine.sample_rate() as f32;
+        self.load_audio(path, LoadTarget::StepSample { track_idx, step, layer });
+    }
 
-        let Ok((mono, _sample_rate)) = Self::read_wav_samples(path) else {
-            tracing::error!("Failed to read WAV: {}", path.display());
-            return;
-        };
+    pub(super) fn set_step_sample(&mut self, track_idx: usize, step: usize, layer: usize, path: &std::path::Path, mono: Vec<f32>) {
+        let engine_sr = self.engine.sample_rate() as f32;
 
         let sample_name = path.file_stem()
             .and_then(|s| s.to_str())
@@ -111,14 +113,13 @@ impl HallucinatorApp {
         tracing::debug!("Cleared sample at step {} layer {}", step, layer);
     }
 
-    /// Load a sample for an entire row (slot index = row)
+    /// Load a sample for an entire row (slot index = row) once it has been decoded in the background
     pub(super) fn load_row_sample(&mut self, track_idx: usize, row: usize, path: &std::path::Path) {
-        let engine_sr = self.engine.sample_rate() as f32;
+        self.load_audio(path, LoadTarget::RowSample { track_idx, row });
+    }
 
-        let Ok((mono, _sample_rate)) = Self::read_wav_samples(path) else {
-            tracing::error!("Failed to read WAV: {}", path.display());
-            return;
-        };
+    pub(super) fn set_row_sample(&mut self, track_idx: usize, row: usize, path: &std::path::Path, mono: Vec<f32>) {
+        let engine_sr = self.engine.sample_rate() as f32;
 
         let sample_name = path.file_stem()
             .and_then(|s| s.to_str())
@@ -134,7 +135,7 @@ impl HallucinatorApp {
             }
         }
 
-        self.keyboard_sequencer_panel.set_row_sample(row, sample_name);
+        self.keyboard_sequencer_panel.set_row_sample(row, sample_name, Some(path.to_path_buf()));
         tracing::info!("Loaded row {} sample: {}", row, path.display());
     }
 
@@ -170,14 +171,15 @@ impl HallucinatorApp {
     /// Paste sample from clipboard to a row
     pub(super) fn paste_row_sample_from_clipboard(&mut self, track_idx: usize, row: usize) {
         let Some(content) = self.clipboard.content() else { return };
-        let (name, data) = match content {
-            crate::clipboard::ClipboardContent::SampleData { name, data } => (name.clone(), Arc::clone(data)),
+        let (name, data, path) = match content {
+            crate::clipboard::ClipboardContent::SampleData { name, data } => (name.clone(), Arc::clone(data), None),
             crate::clipboard::ClipboardContent::FilePath(path) => {
                 // Load from file path
-                let Ok((mono, _)) = Self::read_wav_samples(&path) else { return };
+                let Ok((mono, _)) = Self::read_sample_file(&path) else { return };
                 let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("sample").to_string();
-                (name, Arc::new(mono))
+                (name, Arc::new(mono), Some(path.clone()))
             }
+            crate::clipboard::ClipboardContent::MidiNotes { .. } => return,
         };
 
         let engine_sr = self.engine.sample_rate() as f32;
@@ -189,7 +191,7 @@ impl HallucinatorApp {
             }
         }
 
-        self.keyboard_sequencer_panel.set_row_sample(row, name);
+        self.keyboard_sequencer_panel.set_row_sample(row, name, path);
         tracing::debug!("Pasted sample to row {}", row);
     }
 
@@ -226,11 +228,105 @@ impl HallucinatorApp {
         kit.clear_slot(from_row);
 
         // Update panel state
-        self.keyboard_sequencer_panel.set_row_sample(to_row, name);
+        let path = self.keyboard_sequencer_panel.row_sample_path(from_row);
+        self.keyboard_sequencer_panel.set_row_sample(to_row, name, path);
         self.keyboard_sequencer_panel.clear_row_sample(from_row);
         tracing::debug!("Moved sample from row {} to row {}", from_row, to_row);
     }
 
+    /// Load a saved drum pattern into the sequencer and restore its row samples in the track's kit.
+    /// Rows saved without a file path (pasted sample data) keep whatever the kit slot holds.
+    pub(super) fn recall_drum_pattern(&mut self, track_idx: usize, pattern: &DrumPatternData) {
+        self.keyboard_sequencer_panel.apply_pattern_data(pattern);
+
+        // Read sample files before taking the instrument lock
+        let mut loads = Vec::new();
+        let mut clears = Vec::new();
+        let mut kept = Vec::new();
+        for (row, saved) in pattern.rows.iter().enumerate().take(12) {
+            match (&saved.sample_name, &saved.sample_path) {
+                (Some(name), Some(path)) => match Self::read_sample_file(path) {
+                    Ok((mono, _)) => loads.push((row, name.clone(), Arc::new(mono))),
+                    Err(e) => tracing::warn!("Drum pattern row {} sample {}: {}", row, path.display(), e),
+                },
+                (Some(name), None) => kept.push((row, name)),
+                (None, _) => clears.push(row),
+            }
+        }
+
+        let engine_sr = self.engine.sample_rate() as f32;
+        let kit_id = self.get_or_create_sample_kit(track_idx, engine_sr);
+        let Ok(mut instruments) = self.engine_state.instruments.lock() else { return };
+        let Some(Instrument::SampleKit(kit)) = instruments.get_mut(&kit_id) else { return };
+        for (row, name, data) in loads {
+            kit.set_slot(row, name, data);
+        }
+        for row in clears {
+            kit.clear_slot(row);
+        }
+        for (row, name) in kept {
+            if kit.slots().get(row).and_then(|s| s.as_ref()).is_none_or(|slot| &slot.name != name) {
+                tracing::warn!("Drum pattern row {} sample '{}' has no file to reload", row, name);
+            }
+        }
+    }
+
+    /// Write a pad hit (note repeat recording) into the MIDI clip under `beat` on a track.
+    /// With no clip there, one is created over the loop region, or the hit's bar outside it.
+    pub(super) fn record_drum_hit(&mut self, track_idx: usize, row: usize, velocity: u8, beat: f64) {
+        let clip_id = self.next_clip_id;
+        let created = self.engine.with_timeline(|t| {
+            let samples_per_beat = t.transport.sample_rate as f64 * 60.0 / t.transport.bpm;
+            let sample = (beat.max(0.0) * samples_per_beat) as u64;
+            let (loop_start, loop_end) = (t.transport.loop_start, t.transport.loop_end);
+            let in_loop = t.transport.loop_enabled && (loop_start..loop_end).contains(&sample);
+            let track = t.tracks.get_mut(track_idx).filter(|track| track.kind == TrackKind::Midi)?;
+
+            let existing = track.midi_clips.iter().position(|c| (c.start_sample..c.end_sample()).contains(&sample));
+            let idx = match existing {
+                Some(idx) => idx,
+                None => {
+                    let bar_samples = samples_per_beat * 4.0;
+                    let (start, end) = if in_loop {
+                        (loop_start, loop_end)
+                    } else {
+                        let bar_start = ((sample as f64 / bar_samples).floor() * bar_samples) as u64;
+                        (bar_start, bar_start + bar_samples as u64)
+                    };
+                    let mut clip = MidiClip::new(ClipId(clip_id), end - start);
+                    clip.start_sample = start;
+                    clip.name = "Note Repeat".to_string();
+                    track.add_midi_clip(clip);
+                    track.midi_clips.len() - 1
+                }
+            };
+
+            let clip = &mut track.midi_clips[idx];
+            let ppq = clip.ppq as f64;
+            let tick = ((sample - clip.start_sample) as f64 / samples_per_beat * ppq).round() as u64;
+            let pitch = SampleKit::ROW_BASE_NOTE + row as u8;
+            clip.add_note(MidiNote::new(pitch, velocity, tick, (ppq / 4.0) as u64));
+            Some(existing.is_none())
+        }).flatten();
+
+        if created == Some(true) {
+            self.next_clip_id += 1;
+        }
+    }
+
+    /// Bank slots of a track that hold a drum pattern (bit N = slot N)
+    pub(super) fn drum_bank_mask(&self, track_idx: usize) -> u16 {
+        self.engine
+            .with_timeline(|t| {
+                let track = t.tracks.get(track_idx)?;
+                Some(track.pattern_bank.patterns.iter().enumerate()
+                    .filter(|(_, slot)| slot.drum.is_some())
+                    .fold(0u16, |mask, (i, _)| mask | 1 << i))
+            })
+            .flatten()
+            .unwrap_or(0)
+    }
+
     pub(super) fn get_or_create_sample_kit(&mut self, track_idx: usize, engine_sr: f32) -> u64 {
         let inst_id = self.engine.with_timeline(|t| {
             t.tracks.get(track_idx).and_then(|track| track.instrument_id)
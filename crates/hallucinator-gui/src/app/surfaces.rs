@@ -0,0 +1,324 @@
+//! Control surface profiles on disk, MIDI input, dispatch of surface input to the app,
+//! the OSC server toggle and MIDI clock sync
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use hallucinator_core::control_surface::relative_delta;
+use hallucinator_core::{DeviceTarget, SurfaceAction, SurfaceBinding, SurfaceControl, SurfaceProfile, SURFACE_STRIPS};
+use hallucinator_services::{ClockSync, EngineState, MidiClockSender, MidiInputError, MidiInputService, MidiMessage, OscServer};
+
+use super::config::{load_config, save_config};
+use super::HallucinatorApp;
+use crate::panels::ControlSurfaceAction;
+
+/// Highest track volume reachable from a fader (matches the track header range)
+const MAX_VOLUME: f32 = 1.5;
+
+/// Saved profile directory, `~/.config/hallucinator/surfaces`
+fn profiles_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("hallucinator")
+        .join("surfaces")
+}
+
+fn profile_path(name: &str) -> PathBuf {
+    let file: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' { c } else { '_' })
+        .collect();
+    profiles_dir().join(format!("{file}.toml"))
+}
+
+fn load_saved(path: &std::path::Path) -> Option<SurfaceProfile> {
+    let s = std::fs::read_to_string(path).ok()?;
+    toml::from_str(&s).ok()
+}
+
+/// Built-in profile names followed by saved ones
+pub(super) fn list_profiles() -> Vec<String> {
+    let mut names: Vec<String> = SurfaceProfile::built_in().into_iter().map(|p| p.name).collect();
+    let mut saved: Vec<String> = std::fs::read_dir(profiles_dir())
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|e| e == "toml"))
+                .filter_map(|p| load_saved(&p).map(|profile| profile.name))
+                .filter(|name| !names.contains(name))
+                .collect()
+        })
+        .unwrap_or_default();
+    saved.sort_by_key(|n| n.to_lowercase());
+    names.extend(saved);
+    names
+}
+
+/// Load a profile by name; saved profiles override built-ins of the same name
+pub(super) fn load_profile(name: &str) -> Option<SurfaceProfile> {
+    load_saved(&profile_path(name)).or_else(|| SurfaceProfile::built_in().into_iter().find(|p| p.name == name))
+}
+
+pub(super) fn save_profile(profile: &SurfaceProfile) -> std::io::Result<()> {
+    std::fs::create_dir_all(profiles_dir())?;
+    let s = toml::to_string_pretty(profile).map_err(std::io::Error::other)?;
+    std::fs::write(profile_path(&profile.name), s)
+}
+
+/// Open the MIDI inputs but those named in `disabled`; None without any. Incoming
+/// messages repaint `ctx`, so nothing has to poll for them
+pub(super) fn start_midi_input(
+    ctx: &egui::Context,
+    engine: Arc<EngineState>,
+    disabled: &[String],
+) -> Option<MidiInputService> {
+    match MidiInputService::start(engine, disabled) {
+        Ok(service) => {
+            let ctx = ctx.clone();
+            service.set_waker(move || ctx.request_repaint());
+            Some(service)
+        }
+        Err(MidiInputError::NoPorts) => None,
+        Err(e) => {
+            tracing::warn!("MIDI input unavailable: {}", e);
+            None
+        }
+    }
+}
+
+impl HallucinatorApp {
+    /// Apply queued MIDI from control surfaces (or bind it while learning)
+    pub(super) fn poll_control_surface(&mut self) {
+        let Some(midi) = &self.midi_input else { return };
+        let messages: Vec<MidiMessage> = midi.poll_messages().collect();
+
+        // Keyboard notes go to piano roll step input while it is on
+        let step_input = self.clip_editor_panel.step_input_active();
+        let mut inputs: Vec<(SurfaceControl, u16)> = Vec::new();
+        for message in messages {
+            match message {
+                MidiMessage::NoteOn { note, velocity, .. } if step_input => {
+                    self.clip_editor_panel.queue_step_note(note, velocity);
+                }
+                MidiMessage::NoteOff { note, .. } if step_input => {
+                    self.clip_editor_panel.queue_step_note(note, 0);
+                }
+                _ => inputs.extend(message.surface_input()),
+            }
+        }
+
+        for (control, raw) in inputs {
+            if let Some(index) = self.surface_learn {
+                // Ignore releases so a button binds on press
+                if raw > 0 {
+                    self.surface_profile.bind(index, control);
+                    self.surface_learn = None;
+                }
+                continue;
+            }
+            if let Some(binding) = self.surface_profile.binding(control).cloned() {
+                self.apply_surface_binding(&binding, raw);
+            }
+        }
+    }
+
+    fn apply_surface_binding(&mut self, binding: &SurfaceBinding, raw: u16) {
+        let control = binding.control;
+        if binding.action.is_continuous() {
+            // (current, min, max) -> new value, absolute or relative
+            let resolve = |current: f32, min: f32, max: f32| {
+                if binding.relative {
+                    (current + relative_delta(raw) * (max - min)).clamp(min.min(max), max.max(min))
+                } else {
+                    min + control.normalize(raw) * (max - min)
+                }
+            };
+            self.apply_surface_value(binding.action, resolve);
+        } else if control.is_press(raw) {
+            self.apply_surface_press(binding.action);
+        }
+    }
+
+    fn apply_surface_value(&mut self, action: SurfaceAction, resolve: impl Fn(f32, f32, f32) -> f32) {
+        match action {
+            SurfaceAction::Volume(strip) => {
+                let idx = self.surface_bank + strip;
+                self.with_track_mut(idx, |track| track.volume = resolve(track.volume, 0.0, MAX_VOLUME));
+            }
+            SurfaceAction::Pan(strip) => {
+                let idx = self.surface_bank + strip;
+                self.with_track_mut(idx, |track| track.pan = resolve(track.pan, -1.0, 1.0));
+            }
+            SurfaceAction::DeviceParam(n) => {
+                let Some(track_idx) = self.selected_track_idx else { return };
+                let target = self.device_rack_panel.selected_target().unwrap_or(DeviceTarget::Instrument);
+                let devices = self.mappable_devices(track_idx);
+                let Some(param) = devices.iter().find(|d| d.target == target).and_then(|d| d.params.get(n)) else {
+                    return;
+                };
+                let value = resolve(param.value, param.min, param.max);
+                self.apply_macro_updates(track_idx, vec![(target, param.name.clone(), value)]);
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_surface_press(&mut self, action: SurfaceAction) {
+        let track_count = self.engine.with_timeline(|t| t.tracks.len()).unwrap_or(0);
+        match action {
+            SurfaceAction::Play => {
+                if self.engine.is_playing() {
+                    self.engine.pause();
+                } else {
+                    self.playback_start_position = self.engine.position();
+                    self.engine.play();
+                }
+            }
+            SurfaceAction::Stop => {
+                self.engine.stop_playback();
+                if self.input_monitor.is_recording() {
+                    self.stop_recording();
+                }
+            }
+            SurfaceAction::Record => {
+                if self.input_monitor.is_recording() {
+                    self.stop_recording();
+                } else {
+                    self.start_recording();
+                }
+            }
+            SurfaceAction::Rewind => self.engine.seek(0),
+            SurfaceAction::ToggleLoop => {
+                self.engine.with_timeline(|t| t.transport.loop_enabled = !t.transport.loop_enabled);
+            }
+            SurfaceAction::PreviousTrack => {
+                self.selected_track_idx = Some(self.selected_track_idx.map_or(0, |idx| idx.saturating_sub(1)));
+            }
+            SurfaceAction::NextTrack => {
+                let next = self.selected_track_idx.map_or(0, |idx| idx + 1);
+                if next < track_count {
+                    self.selected_track_idx = Some(next);
+                }
+            }
+            SurfaceAction::BankLeft => self.surface_bank = self.surface_bank.saturating_sub(SURFACE_STRIPS),
+            SurfaceAction::BankRight => {
+                if self.surface_bank + SURFACE_STRIPS < track_count {
+                    self.surface_bank += SURFACE_STRIPS;
+                }
+            }
+            SurfaceAction::Mute(strip) => {
+                self.with_track_mut(self.surface_bank + strip, |track| track.mute = !track.mute);
+            }
+            SurfaceAction::Solo(strip) => {
+                self.with_track_mut(self.surface_bank + strip, |track| track.solo = !track.solo);
+            }
+            SurfaceAction::Arm(strip) => {
+                self.with_track_mut(self.surface_bank + strip, |track| track.armed = !track.armed);
+                self.sync_input_monitoring();
+            }
+            SurfaceAction::Select(strip) => {
+                let idx = self.surface_bank + strip;
+                if idx < track_count {
+                    self.selected_track_idx = Some(idx);
+                }
+            }
+            SurfaceAction::Volume(_) | SurfaceAction::Pan(_) | SurfaceAction::DeviceParam(_) => {}
+        }
+    }
+
+    /// Switch MIDI clock between internal, send and follow
+    pub(super) fn set_clock_sync(&mut self, sync: ClockSync) {
+        self.midi_clock = None;
+        if let Some(midi) = &self.midi_input {
+            midi.set_follow_clock(false);
+        }
+        self.clock_sync = ClockSync::Internal;
+
+        match &sync {
+            ClockSync::Internal => {}
+            ClockSync::Send(port) => match MidiClockSender::start(self.engine_state.clone(), port.clone()) {
+                Ok(sender) => self.midi_clock = Some(sender),
+                Err(e) => {
+                    tracing::warn!("Failed to send MIDI clock to {}: {}", port.name, e);
+                    return;
+                }
+            },
+            ClockSync::Receive => {
+                let Some(midi) = &self.midi_input else {
+                    tracing::warn!("Cannot follow MIDI clock: no MIDI input device");
+                    return;
+                };
+                midi.set_follow_clock(true);
+            }
+        }
+        self.clock_sync = sync;
+    }
+
+    pub(super) fn handle_control_surface_action(&mut self, action: ControlSurfaceAction) {
+        match action {
+            ControlSurfaceAction::SelectProfile(name) => {
+                let Some(profile) = load_profile(&name) else {
+                    tracing::warn!("Control surface profile '{}' not found", name);
+                    return;
+                };
+                self.surface_profile = profile;
+                self.surface_learn = None;
+                let mut config = load_config();
+                config.control_surface.profile = name;
+                save_config(&config);
+            }
+            ControlSurfaceAction::AddBinding => {
+                let control = SurfaceControl::Cc { channel: 0, controller: 0 };
+                self.surface_profile.bindings.push(SurfaceBinding::new(control, SurfaceAction::Play));
+                self.surface_learn = Some(self.surface_profile.bindings.len() - 1);
+            }
+            ControlSurfaceAction::RemoveBinding(idx) => {
+                if idx < self.surface_profile.bindings.len() {
+                    self.surface_profile.bindings.remove(idx);
+                }
+                self.surface_learn = None;
+            }
+            ControlSurfaceAction::SetAction(idx, surface_action) => {
+                if let Some(binding) = self.surface_profile.bindings.get_mut(idx) {
+                    binding.action = surface_action;
+                    binding.relative &= surface_action.is_continuous();
+                }
+            }
+            ControlSurfaceAction::SetRelative(idx, relative) => {
+                if let Some(binding) = self.surface_profile.bindings.get_mut(idx) {
+                    binding.relative = relative;
+                }
+            }
+            ControlSurfaceAction::Learn(idx) => self.surface_learn = Some(idx),
+            ControlSurfaceAction::CancelLearn => self.surface_learn = None,
+            ControlSurfaceAction::SaveProfile(name) => {
+                self.surface_profile.name = name.clone();
+                if let Err(e) = save_profile(&self.surface_profile) {
+                    tracing::warn!("Failed to save control surface profile '{}': {}", name, e);
+                    return;
+                }
+                self.surface_profiles = list_profiles();
+                let mut config = load_config();
+                config.control_surface.profile = name;
+                save_config(&config);
+            }
+            ControlSurfaceAction::SetOscServer(addr) => {
+                self.osc_server = None;
+                if let Some(addr) = addr {
+                    match OscServer::start(self.engine_state.clone(), addr) {
+                        Ok(server) => self.osc_server = Some(server),
+                        Err(e) => tracing::warn!("Failed to start OSC server on {}: {}", addr, e),
+                    }
+                }
+                let mut config = load_config();
+                config.osc.enabled = self.osc_server.is_some();
+                if let Some(addr) = addr {
+                    (config.osc.bind, config.osc.port) = (addr.ip(), addr.port());
+                }
+                save_config(&config);
+            }
+            ControlSurfaceAction::None => {}
+        }
+    }
+}
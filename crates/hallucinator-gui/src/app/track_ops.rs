@@ -1,9 +1,19 @@
-use hallucinator_core::{ClipId, MidiClip, TrackKind};
-use hallucinator_services::{Drum808, Instrument, Vst3Effect, Vst3Instrument, Vst3PluginInfo};
-
-use super::types::SelectedClip;
+use hallucinator_core::{hits_to_midi, moved_index, AudioClip, ClipId, Groove, InstrumentOutput, MidiClip, TrackId, TrackKind};
+use hallucinator_services::notifications;
+use hallucinator_services::wav_reader::to_mono;
+use hallucinator_services::{
+    create_native_effect, AudioEffect, DecodedAudio, Drum808, EffectChain, EffectSource, Instrument, RenderDevices, Sampler,
+    Script, SignalGenerator, Vst3Effect, Vst3Instrument, Vst3PluginInfo,
+};
+
+use super::types::{EffectChainRef, LoadTarget, PendingInstrument, SelectedClip};
 use super::HallucinatorApp;
 
+/// Seconds rendered past the end of a freeze or bounce, for releases and reverb tails
+const RENDER_TAIL_SECS: f64 = 2.0;
+/// Level below which the end of a bounce's tail is trimmed (-80 dB)
+const SILENCE_LEVEL: f32 = 1e-4;
+
 impl HallucinatorApp {
     /// Helper to mutate a track by index within the timeline.
     pub(super) fn with_track_mut<F>(&self, track_idx: usize, f: F)
@@ -17,6 +27,27 @@ impl HallucinatorApp {
         });
     }
 
+    /// Change the routing of the track at `idx` with `f`, unless taking signal from `from` or
+    /// sending it to `to` would feed the track back into itself; returns whether it changed
+    pub(super) fn set_route<F>(&self, idx: usize, from: Option<TrackId>, to: Option<TrackId>, f: F) -> bool
+    where
+        F: FnOnce(&mut hallucinator_core::Track),
+    {
+        let changed = self.engine.with_timeline(|timeline| {
+            let Some(id) = timeline.tracks.get(idx).map(|t| t.id) else { return false };
+            let allowed = from.is_none_or(|from| timeline.can_route(from, id))
+                && to.is_none_or(|to| timeline.can_route(id, to));
+            if allowed {
+                f(&mut timeline.tracks[idx]);
+            }
+            allowed
+        }).unwrap_or(false);
+        if !changed {
+            tracing::warn!("Routing refused: the track would feed back into itself");
+        }
+        changed
+    }
+
     pub(super) fn add_audio_track(&mut self) {
         let track_idx = self.engine.with_timeline(|timeline| {
             let idx = timeline.tracks.len();
@@ -47,6 +78,316 @@ impl HallucinatorApp {
         tracing::info!("Added new MIDI track");
     }
 
+    /// Add an audio track for each auxiliary output of a track's instrument
+    /// that no track plays yet, then enable those outputs on the instrument.
+    pub(super) fn route_instrument_outputs(&mut self, track_idx: usize) {
+        let source = self.engine.with_timeline(|timeline| {
+            let track = timeline.tracks.get(track_idx)?;
+            Some((track.id, track.instrument_id?, track.name.clone()))
+        }).flatten();
+        let Some((source_id, inst_id, source_name)) = source else { return };
+
+        let buses: Vec<(usize, String)> = self.engine_state.instruments.lock().ok()
+            .and_then(|instruments| {
+                let inst = instruments.get(&inst_id)?;
+                Some(inst.output_buses().iter().enumerate().skip(1).map(|(i, b)| (i, b.name.clone())).collect())
+            })
+            .unwrap_or_default();
+        if buses.is_empty() {
+            tracing::info!("Instrument on '{}' has no auxiliary outputs", source_name);
+            return;
+        }
+
+        let added = self.engine.with_timeline(|timeline| {
+            let mut added = 0;
+            for (bus, bus_name) in &buses {
+                let output = InstrumentOutput { track: source_id, bus: *bus };
+                if timeline.tracks.iter().any(|t| t.instrument_output == Some(output)) {
+                    continue;
+                }
+                timeline.add_track(TrackKind::Audio, format!("{} - {}", source_name, bus_name));
+                if let Some(track) = timeline.tracks.last_mut() {
+                    track.instrument_output = Some(output);
+                }
+                added += 1;
+            }
+            added
+        }).unwrap_or(0);
+
+        self.engine_state.sync_instrument_outputs();
+        tracing::info!("Routed {} instrument outputs of '{}' to tracks", added, source_name);
+    }
+
+    /// Freeze a track: render its instrument or clips and its effects to a clip that plays in
+    /// their place, leaving the instrument idle until the track is unfrozen
+    pub(super) fn freeze_track(&mut self, track_idx: usize) {
+        let duration = self.engine.with_timeline(|timeline| timeline.duration_samples()).unwrap_or(0);
+        let tail = (RENDER_TAIL_SECS * self.engine.sample_rate() as f64) as u64;
+        let devices = self.render_devices(track_idx);
+        let Some(samples) = self.engine.render_track(track_idx, 0, duration + tail, None, devices, false) else {
+            tracing::warn!("Track {} has no instrument to freeze", track_idx);
+            return;
+        };
+
+        let mut clip = AudioClip::new(ClipId(self.next_clip_id), samples, self.engine.sample_rate(), 2);
+        clip.build_peaks();
+        self.next_clip_id += 1;
+        self.with_track_mut(track_idx, |track| {
+            clip.name = format!("{} (frozen)", track.name);
+            track.freeze = Some(clip);
+        });
+        tracing::info!("Froze track {}", track_idx);
+    }
+
+    pub(super) fn unfreeze_track(&mut self, track_idx: usize) {
+        self.with_track_mut(track_idx, |track| track.freeze = None);
+    }
+
+    /// Move a track to another position, keeping the selected track and clip on the same tracks
+    pub(super) fn move_track(&mut self, from: usize, to: usize) {
+        if !self.engine.with_timeline(|timeline| timeline.move_track(from, to)).unwrap_or(false) {
+            return;
+        }
+        self.selected_track_idx = self.selected_track_idx.map(|idx| moved_index(idx, from, to));
+        if let Some(SelectedClip::Audio { track_idx, .. } | SelectedClip::Midi { track_idx, .. }) = self.selected_clip.as_mut() {
+            *track_idx = moved_index(*track_idx, from, to);
+        }
+    }
+
+    /// Copy a track below itself with its own instrument, effect chain and MIDI FX, and its
+    /// clips (under new IDs) when `with_clips`
+    pub(super) fn duplicate_track(&mut self, track_idx: usize, with_clips: bool) {
+        let Some((instrument_id, chain_id)) = self.engine
+            .with_timeline(|timeline| timeline.tracks.get(track_idx).map(|t| (t.instrument_id, t.effect_chain_id)))
+            .flatten()
+        else {
+            return;
+        };
+        let instrument_id = instrument_id.and_then(|id| self.duplicate_instrument(id));
+        let effect_chain_id = chain_id.map(|id| self.duplicate_effect_chain(id));
+
+        let mut next_clip_id = self.next_clip_id;
+        let copy_idx = self.engine.with_timeline(|timeline| {
+            let idx = timeline.duplicate_track(track_idx, with_clips)?;
+            let track = &mut timeline.tracks[idx];
+            track.instrument_id = instrument_id;
+            track.effect_chain_id = effect_chain_id;
+            let ids = track.clips.iter_mut().map(|c| &mut c.id).chain(track.midi_clips.iter_mut().map(|c| &mut c.id));
+            for id in ids {
+                *id = ClipId(next_clip_id);
+                next_clip_id += 1;
+            }
+            Some(idx)
+        }).flatten();
+        self.next_clip_id = next_clip_id;
+        let Some(copy_idx) = copy_idx else { return };
+
+        // Tracks below the copy moved down one
+        self.selected_track_idx = Some(copy_idx);
+        if let Some(SelectedClip::Audio { track_idx, .. } | SelectedClip::Midi { track_idx, .. }) = self.selected_clip.as_mut()
+            && *track_idx >= copy_idx
+        {
+            *track_idx += 1;
+        }
+        if effect_chain_id.is_some() {
+            self.sync_input_monitoring();
+        }
+        tracing::info!("Duplicated track {} to {}", track_idx, copy_idx);
+    }
+
+    /// A copy of an instrument under a new ID; returns the ID
+    fn duplicate_instrument(&mut self, inst_id: u64) -> Option<u64> {
+        let instrument = self.copy_instrument(inst_id)?;
+        let new_id = self.next_instrument_id;
+        self.next_instrument_id += 1;
+        self.engine.add_instrument(new_id, instrument);
+        Some(new_id)
+    }
+
+    /// Rebuild a track effect chain as a new chain with the same effects, settings and
+    /// parameter values; returns the new chain's ID
+    fn duplicate_effect_chain(&mut self, chain_id: u64) -> u64 {
+        let new_id = self.next_effect_chain_id;
+        self.next_effect_chain_id += 1;
+        if let Some(copy) = self.copy_effect_chain(chain_id) {
+            self.engine.set_track_effects(new_id, copy);
+        }
+        new_id
+    }
+
+    /// Run a console script on a snapshot of the MIDI clips and apply its edits if it succeeds
+    pub(super) fn run_script(&mut self, source: &str) {
+        let selection = match self.selected_clip {
+            Some(SelectedClip::Midi { track_idx, clip_id }) => Some((track_idx, clip_id)),
+            _ => None,
+        };
+        let Some(script) = self.engine.with_timeline(|timeline| {
+            Script::snapshot(timeline, selection, self.selected_track_idx, self.next_clip_id)
+        }) else {
+            return;
+        };
+
+        match script.run(source) {
+            Ok(outcome) => {
+                for line in &outcome.log {
+                    self.script_console_panel.push_log(line.clone(), false);
+                }
+                let edited = outcome.edited_tracks();
+                self.next_clip_id = outcome.next_clip_id;
+                self.engine.with_timeline(|timeline| outcome.apply(timeline));
+                self.script_console_panel.push_log(format!("Done ({} track(s) edited)", edited), false);
+            }
+            Err(e) => self.script_console_panel.push_log(e.to_string(), true),
+        }
+    }
+
+    /// Bounce a MIDI clip in place: render it through its track's instrument and effects
+    pub(super) fn bounce_clip(&mut self, track_idx: usize, clip_id: ClipId) {
+        let span = self.engine.with_timeline(|timeline| {
+            let clip = timeline.tracks.get(track_idx)?.get_midi_clip(clip_id)?;
+            Some((clip.start_sample, clip.length_samples, clip.name.clone()))
+        }).flatten();
+        let Some((start, length, name)) = span else { return };
+        self.bounce_track(track_idx, start, length, Some(clip_id), &name);
+    }
+
+    /// Glue the clips between two samples on a track into one, which becomes the selection:
+    /// audio clips are rendered to a single clip, MIDI clips merged into one note list
+    pub(super) fn consolidate_clips(&mut self, track_idx: usize, start_sample: u64, end_sample: u64, midi: bool) {
+        let clip_id = ClipId(self.next_clip_id);
+        let merged = self.engine.with_timeline(|timeline| {
+            let samples_per_beat = timeline.transport.sample_rate as f64 * 60.0 / timeline.transport.bpm;
+            let track = timeline.tracks.get_mut(track_idx)?;
+            Some(if midi {
+                track.consolidate_midi(start_sample, end_sample, clip_id, samples_per_beat)
+            } else {
+                track.consolidate_audio(start_sample, end_sample, clip_id)
+            })
+        }).flatten().unwrap_or(false);
+        if !merged {
+            return;
+        }
+        self.next_clip_id += 1;
+        self.selected_track_idx = Some(track_idx);
+        self.selected_clip = Some(if midi {
+            SelectedClip::Midi { track_idx, clip_id }
+        } else {
+            SelectedClip::Audio { track_idx, clip_id }
+        });
+        tracing::info!("Consolidated clips on track {} into {:?}", track_idx, clip_id);
+    }
+
+    /// Add a MIDI or audio clip's groove to the pool, replacing one of the same name
+    pub(super) fn extract_groove(&mut self, track_idx: usize, clip_id: ClipId) {
+        let added = self.engine.with_timeline(|timeline| {
+            let bpm = timeline.transport.bpm;
+            let samples_per_beat = timeline.transport.sample_rate as f64 * 60.0 / bpm;
+            let track = timeline.tracks.get(track_idx)?;
+            let mut groove = match (track.get_midi_clip(clip_id), track.get_clip(clip_id)) {
+                (Some(clip), _) => Groove::from_midi_clip(clip, clip.length_samples as f64 / samples_per_beat),
+                (None, Some(clip)) => Groove::from_audio_clip(clip, bpm),
+                (None, None) => None,
+            }?;
+            if groove.name.is_empty() {
+                groove.name = format!("Groove {}", timeline.grooves.len() + 1);
+            }
+            let name = groove.name.clone();
+            match timeline.grooves.iter_mut().find(|g| g.name == groove.name) {
+                Some(existing) => *existing = groove,
+                None => timeline.grooves.push(groove),
+            }
+            Some(name)
+        }).flatten();
+        match added {
+            Some(name) => tracing::info!("Added groove '{}' to the pool", name),
+            None => tracing::warn!("No notes or hits to take a groove from"),
+        }
+    }
+
+    /// Write the hits of an audio clip as `pitch` notes to a MIDI clip at the same position on
+    /// the track's hits track, "<track> Hits", which is created with 808 Drums on first use
+    /// so hits extracted for other drum sounds layer onto it
+    pub(super) fn extract_drum_hits(&mut self, track_idx: usize, clip_id: ClipId, pitch: u8) {
+        let id = ClipId(self.next_clip_id);
+        let extracted = self.engine.with_timeline(|timeline| {
+            let track = timeline.tracks.get(track_idx)?;
+            let clip = hits_to_midi(track.get_clip(clip_id)?, id, pitch, timeline.transport.bpm)?;
+            Some((clip, format!("{} Hits", track.name)))
+        }).flatten();
+        let Some((clip, hits_name)) = extracted else {
+            tracing::warn!("No hits found in the clip");
+            return;
+        };
+        self.next_clip_id += 1;
+
+        let existing = self.engine.with_timeline(|timeline| {
+            timeline.tracks.iter().position(|t| t.kind == TrackKind::Midi && t.name == hits_name)
+        }).flatten();
+        let hits_track = match existing {
+            Some(idx) => Some(idx),
+            None => {
+                let inst_id = self.next_instrument_id;
+                self.next_instrument_id += 1;
+                self.engine.add_instrument(inst_id, Instrument::Drum808(Drum808::new(self.engine.sample_rate() as f32)));
+                self.engine.with_timeline(|timeline| {
+                    timeline.add_track(TrackKind::Midi, &hits_name);
+                    let idx = timeline.tracks.len() - 1;
+                    timeline.tracks[idx].instrument_id = Some(inst_id);
+                    idx
+                })
+            }
+        };
+        let Some(idx) = hits_track else { return };
+        let notes = clip.notes.len();
+        self.with_track_mut(idx, |track| track.add_midi_clip(clip));
+        self.selected_track_idx = Some(idx);
+        tracing::info!("Extracted {} hits to '{}'", notes, hits_name);
+    }
+
+    /// Bounce the loop region of a track with an instrument
+    pub(super) fn bounce_loop(&mut self, track_idx: usize) {
+        let (start, end) = self.engine.loop_region();
+        if end <= start {
+            tracing::warn!("No loop region to bounce");
+            return;
+        }
+        self.bounce_track(track_idx, start, end - start, None, "Loop");
+    }
+
+    /// Render `length` samples of a track from `start` through its fader (only `clip` when
+    /// given, plus a tail trimmed once silent) to an audio clip at the same position on the
+    /// track's bounce track, "<track> Bounce", which is created on first use so later
+    /// bounces layer onto it
+    fn bounce_track(&mut self, track_idx: usize, start: u64, length: u64, clip: Option<ClipId>, clip_name: &str) {
+        let sample_rate = self.engine.sample_rate();
+        let tail = (RENDER_TAIL_SECS * sample_rate as f64) as u64;
+        let devices = self.render_devices(track_idx);
+        let Some(mut samples) = self.engine.render_track(track_idx, start, length + tail, clip, devices, true) else {
+            tracing::warn!("Track {} has no instrument to bounce", track_idx);
+            return;
+        };
+        trim_silent_tail(&mut samples, length as usize);
+
+        let mut audio_clip = AudioClip::new(ClipId(self.next_clip_id), samples, sample_rate, 2);
+        audio_clip.build_peaks();
+        self.next_clip_id += 1;
+        audio_clip.start_sample = start;
+        audio_clip.name = format!("{} (bounce)", clip_name);
+
+        self.engine.with_timeline(|timeline| {
+            let Some(source) = timeline.tracks.get(track_idx) else { return };
+            let bounce_name = format!("{} Bounce", source.name);
+            let idx = match timeline.tracks.iter().position(|t| t.kind == TrackKind::Audio && t.name == bounce_name) {
+                Some(idx) => idx,
+                None => {
+                    timeline.add_track(TrackKind::Audio, bounce_name);
+                    timeline.tracks.len() - 1
+                }
+            };
+            timeline.tracks[idx].add_clip(audio_clip);
+        });
+    }
+
     /// Finds selected MIDI track or creates a new one, assigns instrument,
     /// and ensures it has a MIDI clip. Returns the track index.
     pub(super) fn ensure_midi_track(
@@ -102,23 +443,28 @@ impl HallucinatorApp {
         }).flatten()
     }
 
-    pub(super) fn load_native_drum(&mut self, inst_id_str: &str) {
-        if inst_id_str != "drum808" {
-            return;
-        }
-
+    pub(super) fn load_native_instrument(&mut self, inst_id_str: &str) {
         let sample_rate = self.engine.sample_rate() as f32;
-        let drum808 = Drum808::new(sample_rate);
+        let (instrument, track_name, clip_name) = match inst_id_str {
+            "drum808" => (Instrument::Drum808(Drum808::new(sample_rate)), "808 Drums", "Drum Pattern"),
+            "signal_generator" => (
+                Instrument::SignalGenerator(SignalGenerator::new(sample_rate)),
+                "Signal Generator",
+                "Test Tone",
+            ),
+            _ => return,
+        };
+        let opens_params = !instrument.is_drum();
 
         let inst_id = self.next_instrument_id;
         self.next_instrument_id += 1;
 
-        self.engine.add_instrument(inst_id, Instrument::Drum808(drum808));
+        self.engine.add_instrument(inst_id, instrument);
 
         let clip_id = self.next_clip_id;
         self.next_clip_id += 1;
 
-        let track_idx = self.ensure_midi_track(inst_id, "808 Drums", "Drum Pattern", clip_id);
+        let track_idx = self.ensure_midi_track(inst_id, track_name, clip_name, clip_id);
 
         if let Some(idx) = track_idx {
             self.selected_track_idx = Some(idx);
@@ -129,53 +475,206 @@ impl HallucinatorApp {
             self.show_clip_editor = true;
         }
 
-        tracing::info!("Loaded 808 Drum Machine");
+        // Instruments without a dedicated editor are driven from their parameter window
+        if opens_params {
+            self.native_param_windows.insert(inst_id);
+        }
+
+        tracing::info!("Loaded native instrument {}", track_name);
+    }
+
+    /// Play an audio file from a new Sampler on the selected MIDI track (or a new one), once
+    /// it has been decoded in the background
+    pub(super) fn load_sampler(&mut self, path: &std::path::Path) {
+        self.load_audio(path, LoadTarget::Sampler);
+    }
+
+    /// Add a Sampler playing a decoded audio file
+    pub(super) fn add_sampler(&mut self, path: &std::path::Path, audio: DecodedAudio) {
+        let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("Sample");
+        let mono = to_mono(&audio.samples, audio.channels as usize);
+        let sampler = match Sampler::from_samples(name.to_string(), &mono, audio.sample_rate, self.engine.sample_rate() as f32) {
+            Ok(sampler) => sampler,
+            Err(e) => {
+                notifications::error(format!("Failed to load sampler from {}", path.display()), e);
+                return;
+            }
+        };
+        let track_name = format!("Sampler - {name}");
+
+        let inst_id = self.next_instrument_id;
+        self.next_instrument_id += 1;
+        self.engine.add_instrument(inst_id, Instrument::Sampler(sampler));
+
+        let clip_id = self.next_clip_id;
+        self.next_clip_id += 1;
+
+        let Some(idx) = self.ensure_midi_track(inst_id, &track_name, "New MIDI Clip", clip_id) else { return };
+        self.selected_track_idx = Some(idx);
+        self.selected_clip = Some(SelectedClip::Midi {
+            track_idx: idx,
+            clip_id: ClipId(clip_id),
+        });
+        self.show_clip_editor = true;
+        self.native_param_windows.insert(inst_id);
+
+        tracing::info!("Loaded sampler {} to track {}", path.display(), idx);
     }
 
-    pub(super) fn load_vst3_effect(&mut self, info: &Vst3PluginInfo) {
+    /// Load a VST3 effect at the end of a chain and open its parameter window
+    /// A new instance of an instrument that sounds the same: native instruments are copied,
+    /// VST3 ones loaded again and given the original's state
+    fn copy_instrument(&self, inst_id: u64) -> Option<Instrument> {
+        let sample_rate = self.engine.sample_rate() as f32;
+        let native = self.engine
+            .with_instruments(|instruments| instruments.get(&inst_id)?.duplicate_native(sample_rate))
+            .flatten();
+        let instrument = match native {
+            Some(copy) => copy,
+            None => {
+                let (info, reader, sandboxed) = self.engine.with_instruments(|instruments| {
+                    let inst = instruments.get(&inst_id)?;
+                    Some((inst.vst3_plugin_info()?.clone(), inst.plugin_state_reader(), inst.sandbox_restarts().is_some()))
+                }).flatten()?;
+                // Read outside the instruments lock, which the audio thread plays them under
+                let state = match reader.map(|reader| reader.state()) {
+                    Some(Ok(state)) => Some(state),
+                    Some(Err(e)) => {
+                        tracing::warn!("Failed to capture state of {}: {}", info.name, e);
+                        None
+                    }
+                    None => None,
+                };
+                let rack_scanner = self.plugin_menu.scanner()?.scanner()?;
+                let loaded = if sandboxed {
+                    Vst3Instrument::new_sandboxed(rack_scanner, &info, sample_rate)
+                } else {
+                    Vst3Instrument::new(rack_scanner, &info, sample_rate)
+                };
+                let mut instrument = match loaded {
+                    Ok(inst) => Instrument::Vst3(inst),
+                    Err(e) => {
+                        notifications::error(format!("Failed to load VST3 instrument {}", info.name), e);
+                        return None;
+                    }
+                };
+                if let Some(state) = &state
+                    && let Err(e) = instrument.restore_plugin_state(state)
+                {
+                    tracing::warn!("Failed to copy the state of {}: {}", info.name, e);
+                }
+                instrument
+            }
+        };
+        Some(instrument)
+    }
+
+    /// A chain with the same effects, settings and parameter values as a track's chain,
+    /// built apart from the engine so the audio thread never waits on plugin loads
+    fn copy_effect_chain(&self, chain_id: u64) -> Option<EffectChain> {
+        let (copies, bypass_all) = self.engine
+            .with_track_effects(chain_id, |chain| (chain.slot_copies(), chain.is_bypass_all()))?;
+        let mut chain = EffectChain::new();
+        let sample_rate = self.engine.sample_rate() as f32;
+        for copy in &copies {
+            let effect: Option<Box<dyn AudioEffect>> = match &copy.source {
+                EffectSource::Native(id) => create_native_effect(id, sample_rate),
+                EffectSource::Vst3(info) => {
+                    let rack_scanner = self.plugin_menu.scanner().and_then(|s| s.scanner());
+                    match rack_scanner.map(|scanner| Vst3Effect::new(scanner, info, sample_rate)) {
+                        Some(Ok(effect)) => Some(Box::new(effect)),
+                        Some(Err(e)) => {
+                            notifications::error(format!("Failed to load VST3 plugin {}", info.name), e);
+                            None
+                        }
+                        None => None,
+                    }
+                }
+            };
+            let Some(effect) = effect else { continue };
+            chain.add_copy(effect, copy, sample_rate);
+        }
+        chain.set_bypass_all(bypass_all);
+        Some(chain)
+    }
+
+    /// Offline copies of a track's instrument and effects to render through
+    fn render_devices(&self, track_idx: usize) -> RenderDevices {
+        let ids = self.engine
+            .with_timeline(|timeline| timeline.tracks.get(track_idx).map(|t| (t.instrument_id, t.effect_chain_id)))
+            .flatten();
+        let Some((instrument_id, chain_id)) = ids else {
+            return RenderDevices::default();
+        };
+        RenderDevices {
+            instrument: instrument_id.and_then(|id| self.copy_instrument(id)),
+            effects: chain_id.and_then(|id| self.copy_effect_chain(id)),
+        }
+    }
+
+    pub(super) fn load_vst3_effect(&mut self, info: &Vst3PluginInfo, chain: EffectChainRef) {
         let Some(scanner) = self.plugin_menu.scanner() else { return };
         let Some(rack_scanner) = scanner.scanner() else { return };
 
         let sample_rate = self.engine.sample_rate() as f32;
 
-        match Vst3Effect::new(rack_scanner, info, sample_rate) {
-            Ok(effect) => {
-                tracing::info!("Loaded VST3 effect: {}", info.name);
-                self.engine.with_master_effects(|chain| {
-                    chain.add(Box::new(effect));
-                });
-            }
+        let effect = match Vst3Effect::new(rack_scanner, info, sample_rate) {
+            Ok(effect) => effect,
             Err(e) => {
-                tracing::error!("Failed to load VST3 plugin {}: {}", info.name, e);
+                notifications::error(format!("Failed to load VST3 plugin {}", info.name), e);
+                return;
             }
+        };
+
+        tracing::info!("Loaded VST3 effect: {}", info.name);
+        self.plugin_menu.note_used(&info.info.unique_id);
+        self.save_plugins_config();
+        let index = self.with_effect_chain(chain, |chain| {
+            chain.add(Box::new(effect));
+            chain.len() - 1
+        });
+        if let Some(index) = index {
+            self.effect_param_windows.insert((chain, index));
         }
     }
 
-    pub(super) fn load_instrument_to_track(&mut self, info: &Vst3PluginInfo) {
-        let Some(scanner) = self.plugin_menu.scanner() else {
-            tracing::warn!("No scanner available");
-            return;
-        };
-        let Some(rack_scanner) = scanner.scanner() else {
-            tracing::warn!("No rack scanner available");
-            return;
+    /// Load a VST3 effect onto the selected track, or the master chain when no track is selected
+    pub(super) fn load_vst3_effect_to_selection(&mut self, info: &Vst3PluginInfo) {
+        let chain = match self.selected_track_idx {
+            Some(track_idx) => match self.ensure_effect_chain(track_idx) {
+                Some(chain_id) => EffectChainRef::Track(chain_id),
+                None => return,
+            },
+            None => EffectChainRef::Master,
         };
+        self.load_vst3_effect(info, chain);
+    }
 
+    /// Load a VST3 instrument onto the selected MIDI track, or a new one. The plugin loads on
+    /// its own thread while the device rack shows it loading; its editor opens once it plays
+    pub(super) fn load_instrument_to_track(&mut self, info: &Vst3PluginInfo) {
         let sample_rate = self.engine.sample_rate() as f32;
-
-        let instrument = match Vst3Instrument::new(rack_scanner, info, sample_rate) {
-            Ok(inst) => inst,
+        let sandboxed = self.plugin_menu.sandbox_instruments();
+        let job = match Vst3Instrument::spawn_load(info.clone(), sample_rate, sandboxed) {
+            Ok(job) => job,
             Err(e) => {
-                tracing::error!("Failed to load VST3 instrument {}: {}", info.name, e);
+                notifications::error(format!("Failed to load VST3 instrument {}", info.name), e);
                 return;
             }
         };
 
+        self.plugin_menu.note_used(&info.info.unique_id);
+        self.save_plugins_config();
+
         let inst_id = self.next_instrument_id;
         self.next_instrument_id += 1;
 
-        tracing::info!("Adding instrument with inst_id={}", inst_id);
-        self.engine.add_instrument(inst_id, Instrument::Vst3(instrument));
+        tracing::info!("Loading instrument with inst_id={}", inst_id);
+        self.pending_instruments.insert(inst_id, PendingInstrument {
+            name: info.name.clone(),
+            job: Some(job),
+            sandboxed,
+        });
 
         let clip_id = self.next_clip_id;
         self.next_clip_id += 1;
@@ -191,14 +690,60 @@ impl HallucinatorApp {
             clip_id: ClipId(clip_id),
         });
         self.show_clip_editor = true;
+        tracing::info!("Loading instrument {} to track {}", info.name, idx);
+    }
+
+    /// Hand instruments that have finished loading to the audio thread, and open their
+    /// editors once they're in place there; instruments they replaced are dropped here
+    pub(super) fn poll_instrument_loads(&mut self, ctx: &egui::Context) {
+        self.engine.drop_retired_instruments();
+        if self.pending_instruments.is_empty() {
+            return;
+        }
 
-        self.open_native_plugin_gui(
-            inst_id,
-            &info.info.path.to_string_lossy(),
-            &info.info.unique_id,
-            &info.name,
-        );
-        tracing::info!("Loaded instrument {} to track {}", info.name, idx);
+        let mut in_place = Vec::new();
+        let mut failed = Vec::new();
+        for (&inst_id, pending) in &mut self.pending_instruments {
+            match pending.job.as_mut().map(|job| job.poll()) {
+                Some(Some(Ok(instrument))) => {
+                    self.engine.add_instrument_queued(inst_id, Instrument::Vst3(instrument));
+                    pending.job = None;
+                }
+                Some(Some(Err(e))) => failed.push((inst_id, e)),
+                Some(None) => {}
+                None => {
+                    if self.engine.has_instrument(inst_id) {
+                        in_place.push(inst_id);
+                    }
+                }
+            }
+        }
+
+        for (inst_id, e) in failed {
+            let Some(pending) = self.pending_instruments.remove(&inst_id) else { continue };
+            notifications::error(format!("Failed to load VST3 instrument {}", pending.name), e);
+            // The track set up for it is left without an instrument
+            self.engine.with_timeline(|timeline| {
+                for track in timeline.tracks.iter_mut().filter(|t| t.instrument_id == Some(inst_id)) {
+                    track.instrument_id = None;
+                }
+            });
+        }
+
+        for inst_id in in_place {
+            let Some(pending) = self.pending_instruments.remove(&inst_id) else { continue };
+            tracing::info!("Loaded instrument {}", pending.name);
+            // A sandboxed plugin has no editor here; its parameter window stands in
+            if pending.sandboxed {
+                self.open_plugin_window(inst_id, pending.name);
+            } else {
+                self.open_native_plugin_gui(inst_id);
+            }
+        }
+
+        if !self.pending_instruments.is_empty() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
     }
 
     /// Delete a clip from the timeline.
@@ -215,7 +760,7 @@ impl HallucinatorApp {
             SelectedClip::Audio { track_idx, clip_id } => {
                 self.engine.with_timeline(|timeline| {
                     if let Some(track) = timeline.tracks.get_mut(track_idx) {
-                        track.clips.retain(|c| c.id != clip_id);
+                        track.remove_clip(clip_id);
                     }
                 });
                 tracing::info!("Deleted audio clip {:?} from track {}", clip_id, track_idx);
@@ -231,3 +776,11 @@ fn track_time_params(timeline: &hallucinator_core::Timeline) -> f64 {
     let bpm = timeline.transport.bpm;
     sample_rate as f64 * 60.0 / bpm
 }
+
+/// Drop near-silent frames from the end of interleaved stereo, keeping at least `min_frames`
+fn trim_silent_tail(samples: &mut Vec<f32>, min_frames: usize) {
+    let audible = samples.chunks(2)
+        .rposition(|frame| frame.iter().any(|s| s.abs() > SILENCE_LEVEL))
+        .map_or(0, |last| last + 1);
+    samples.truncate(audible.max(min_frames) * 2);
+}
@@ -1,4 +1,8 @@
-use hallucinator_core::ClipId;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use hallucinator_core::{CcMode, ClipId, MidiMap, ParamOwner};
+use hallucinator_services::{InstrumentLoadJob, LearnRequest};
 
 /// Selected clip info
 #[derive(Clone, Copy)]
@@ -11,8 +15,74 @@ pub enum SelectedClip {
 pub(super) struct PluginWindow {
     pub id: u64,
     pub title: String,
-    pub plugin_path: String,
-    pub plugin_uid: String,
     pub open: bool,
     pub native_window_created: bool,
 }
+
+/// `.vstpreset` file operation requested from a plugin window header
+pub(super) enum PresetFileRequest {
+    Load(PathBuf),
+    Save(PathBuf),
+}
+
+/// Effect chain an effect parameter window belongs to
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) enum EffectChainRef {
+    Master,
+    Track(u64),
+}
+
+/// Loopback latency measurement in progress
+pub(super) struct LatencyCalibration {
+    pub started: Instant,
+    /// Transport position the take started at (returned to afterwards)
+    pub start_position: u64,
+    /// Frames into the take the pulse is played
+    pub pulse_index: usize,
+}
+
+/// MIDI learn snapshot for one frame: project mappings and the armed parameter
+pub(super) struct MidiLearnState {
+    pub map: MidiMap,
+    pub learning: Option<LearnRequest>,
+    /// False when no MIDI input is running (learn is unavailable)
+    pub available: bool,
+}
+
+/// MIDI learn edit requested from a parameter slider's context menu
+pub(super) enum MidiLearnRequest {
+    Learn(LearnRequest),
+    Cancel,
+    Forget { owner: ParamOwner, param: String },
+    SetMode { owner: ParamOwner, param: String, mode: CcMode },
+    SetRange { owner: ParamOwner, param: String, min: f32, max: f32 },
+}
+
+/// What a file on the audio loader thread is decoded for
+pub(super) enum LoadTarget {
+    /// The placeholder audio clip of this id, wherever it has been moved since
+    Clip(ClipId),
+    /// A new Sampler on the selected MIDI track
+    Sampler,
+    /// A sequencer step's sample layer in a track's kit
+    StepSample { track_idx: usize, step: usize, layer: usize },
+    /// A sequencer row's sample in a track's kit
+    RowSample { track_idx: usize, row: usize },
+}
+
+/// Audio file being decoded on the loader thread
+pub(super) struct PendingLoad {
+    pub path: PathBuf,
+    pub target: LoadTarget,
+    /// Share decoded so far, 0-1
+    pub progress: f32,
+}
+
+/// VST3 instrument loading on its own thread, for a track already set up to play it
+pub(super) struct PendingInstrument {
+    pub name: String,
+    /// None once the instrument has gone to the audio thread, until it is in place there
+    pub job: Option<InstrumentLoadJob>,
+    /// Sandboxed instruments open their parameter window rather than a native editor
+    pub sandboxed: bool,
+}
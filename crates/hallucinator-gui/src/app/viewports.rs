@@ -0,0 +1,76 @@
+//! Windows torn off into native windows of their own (egui viewports), so the piano roll
+//! or a plugin's parameters can sit on another monitor
+
+use egui::{Context, Id, Ui, ViewportBuilder, ViewportClass, ViewportId};
+
+/// Button that tears a window off, or docks it back while it's torn off. Returns whether it was clicked
+pub(super) fn detach_button(ui: &mut Ui, detached: bool) -> bool {
+    let (text, hover) = if detached {
+        ("⤓ Dock", "Back into the main window")
+    } else {
+        ("⧉ Detach", "Move to a window of its own, e.g. on another monitor")
+    };
+    ui.small_button(text).on_hover_text(hover).clicked()
+}
+
+/// Show `add_contents` in a native window titled `title`; `open` is cleared when it's
+/// closed. Falls back to a window inside the main one if the backend can't open more
+pub(super) fn show_native<R>(
+    ctx: &Context,
+    id: Id,
+    title: &str,
+    default_size: [f32; 2],
+    open: &mut bool,
+    add_contents: impl FnOnce(&mut Ui) -> R,
+) -> Option<R> {
+    let builder = ViewportBuilder::default().with_title(title).with_inner_size(default_size);
+    // An immediate viewport runs its callback once per frame
+    let mut add_contents = Some(add_contents);
+    ctx.show_viewport_immediate(ViewportId(id), builder, |ctx, class| {
+        let add_contents = add_contents.take()?;
+        if class == ViewportClass::Embedded {
+            return egui::Window::new(title)
+                .id(id)
+                .open(&mut *open)
+                .resizable(true)
+                .default_size(default_size)
+                .show(ctx, add_contents)?
+                .inner;
+        }
+        if ctx.input(|i| i.viewport().close_requested()) {
+            *open = false;
+        }
+        Some(egui::CentralPanel::default().show(ctx, add_contents).inner)
+    })
+}
+
+/// A resizable window with a Detach/Dock button above `add_contents`: an egui window in the
+/// main one, or a native window while `detached`. Returns whether it's detached afterwards
+pub(super) fn show_detachable(
+    ctx: &Context,
+    id: Id,
+    title: &str,
+    default_size: [f32; 2],
+    open: &mut bool,
+    detached: bool,
+    add_contents: impl FnOnce(&mut Ui),
+) -> bool {
+    let mut toggled = false;
+    let contents = |ui: &mut Ui| {
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Min), |ui| {
+            toggled = detach_button(ui, detached);
+        });
+        add_contents(ui);
+    };
+    if detached {
+        show_native(ctx, id, title, default_size, open, contents);
+    } else {
+        egui::Window::new(title)
+            .id(id)
+            .open(open)
+            .resizable(true)
+            .default_size(default_size)
+            .show(ctx, contents);
+    }
+    detached != toggled
+}
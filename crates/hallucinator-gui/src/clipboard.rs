@@ -3,6 +3,8 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use hallucinator_core::MidiNote;
+
 /// Content that can live on the DAW clipboard.
 /// Extend with new variants as more panels gain copy/paste support.
 #[derive(Clone)]
@@ -11,6 +13,8 @@ pub enum ClipboardContent {
     FilePath(PathBuf),
     /// Loaded sample data (e.g. copied from a drum kit slot)
     SampleData { name: String, data: Arc<Vec<f32>> },
+    /// Notes copied in the piano roll, ticks relative to the earliest note
+    MidiNotes { ppq: u16, notes: Vec<MidiNote> },
 }
 
 /// Shared clipboard that lives on `HallucinatorApp`.
@@ -39,4 +43,8 @@ impl DawClipboard {
     pub fn has_sample(&self) -> bool {
         matches!(self.content, Some(ClipboardContent::SampleData { .. }))
     }
+
+    pub fn has_notes(&self) -> bool {
+        matches!(self.content, Some(ClipboardContent::MidiNotes { .. }))
+    }
 }
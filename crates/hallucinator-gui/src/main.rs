@@ -3,6 +3,8 @@
 mod app;
 pub mod clipboard;
 mod panels;
+mod render;
+mod status_log;
 
 use app::HallucinatorApp;
 use eframe::NativeOptions;
@@ -72,12 +74,28 @@ fn main() -> eframe::Result<()> {
     // Initialize logging
     tracing_subscriber::registry()
         .with(tracing_subscriber::fmt::layer())
+        .with(status_log::StatusLog)
         .with(tracing_subscriber::EnvFilter::from_default_env()
             .add_directive("hallucinator=debug".parse().unwrap())
             .add_directive("wgpu=warn".parse().unwrap())
             .add_directive("eframe=warn".parse().unwrap()))
         .init();
 
+    // Relaunched as the host process of a sandboxed plugin
+    #[cfg(target_os = "linux")]
+    {
+        let args: Vec<String> = std::env::args().collect();
+        if args.get(1).is_some_and(|arg| arg == hallucinator_services::PLUGIN_HOST_ARG) {
+            std::process::exit(hallucinator_services::run_plugin_host(&args[2..]));
+        }
+    }
+
+    // Headless render of a saved project to a WAV file
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).is_some_and(|arg| arg == render::RENDER_ARG) {
+        std::process::exit(render::run(&args[2..]));
+    }
+
     tracing::info!("Starting Hallucinator");
 
     let options = NativeOptions {
@@ -0,0 +1,150 @@
+//! Analyzer panel - real-time spectrum and oscilloscope views of analyzer taps
+
+use std::sync::Arc;
+
+use egui::{Color32, Pos2, Sense, Stroke, Ui, Vec2};
+use hallucinator_services::audio_effects::analyzer::{FFT_SIZE, SCOPE_SIZE};
+use hallucinator_services::AnalyzerTap;
+
+const MIN_FREQ: f32 = 20.0;
+const MAX_FREQ: f32 = 20000.0;
+const FLOOR_DB: f32 = -96.0;
+
+/// What the analyzer draws
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum AnalyzerView {
+    Spectrum,
+    Scope,
+}
+
+/// Analyzer panel state
+pub struct AnalyzerPanel {
+    selected: usize,
+    view: AnalyzerView,
+    scope_samples: usize,
+    spectrum: Vec<f32>,
+    peak_hold: Vec<f32>,
+    scope: Vec<f32>,
+}
+
+impl AnalyzerPanel {
+    pub fn new() -> Self {
+        Self {
+            selected: 0,
+            view: AnalyzerView::Spectrum,
+            scope_samples: 1024,
+            spectrum: vec![FLOOR_DB; FFT_SIZE / 2],
+            peak_hold: vec![FLOOR_DB; FFT_SIZE / 2],
+            scope: vec![0.0; SCOPE_SIZE],
+        }
+    }
+
+    /// Draw the analyzer for one of the available taps, labelled by source
+    pub fn ui(&mut self, ui: &mut Ui, sources: &[(String, Arc<AnalyzerTap>)]) {
+        if sources.is_empty() {
+            ui.centered_and_justified(|ui| {
+                ui.label("Insert an Analyzer on a track or the master chain");
+            });
+            return;
+        }
+
+        self.selected = self.selected.min(sources.len() - 1);
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("analyzer_source")
+                .selected_text(&sources[self.selected].0)
+                .show_ui(ui, |ui| {
+                    for (idx, (label, _)) in sources.iter().enumerate() {
+                        ui.selectable_value(&mut self.selected, idx, label);
+                    }
+                });
+
+            ui.separator();
+            ui.selectable_value(&mut self.view, AnalyzerView::Spectrum, "Spectrum");
+            ui.selectable_value(&mut self.view, AnalyzerView::Scope, "Scope");
+
+            if self.view == AnalyzerView::Scope {
+                ui.separator();
+                ui.add(egui::Slider::new(&mut self.scope_samples, 128..=SCOPE_SIZE).text("samples"));
+            }
+        });
+
+        let tap = &sources[self.selected].1;
+        match self.view {
+            AnalyzerView::Spectrum => self.draw_spectrum(ui, tap),
+            AnalyzerView::Scope => self.draw_scope(ui, tap),
+        }
+    }
+
+    fn draw_spectrum(&mut self, ui: &mut Ui, tap: &AnalyzerTap) {
+        tap.read_spectrum(&mut self.spectrum);
+        for (hold, &value) in self.peak_hold.iter_mut().zip(self.spectrum.iter()) {
+            *hold = if value > *hold { value } else { (*hold - 0.5).max(value) };
+        }
+
+        let (response, painter) = ui.allocate_painter(ui.available_size().max(Vec2::new(200.0, 120.0)), Sense::hover());
+        let rect = response.rect;
+        painter.rect_filled(rect, 2.0, Color32::from_gray(20));
+
+        let freq_to_x = |freq: f32| {
+            let t = (freq / MIN_FREQ).ln() / (MAX_FREQ / MIN_FREQ).ln();
+            rect.left() + t * rect.width()
+        };
+        let db_to_y = |db: f32| {
+            let t = (db.max(FLOOR_DB) / FLOOR_DB).clamp(0.0, 1.0);
+            rect.top() + t * rect.height()
+        };
+
+        // Grid: decades and 12 dB steps
+        for freq in [100.0, 1000.0, 10000.0] {
+            let x = freq_to_x(freq);
+            painter.line_segment([Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())], Stroke::new(1.0, Color32::from_gray(45)));
+        }
+        for step in 1..8 {
+            let y = db_to_y(-12.0 * step as f32);
+            painter.line_segment([Pos2::new(rect.left(), y), Pos2::new(rect.right(), y)], Stroke::new(1.0, Color32::from_gray(35)));
+        }
+
+        let bin_hz = tap.sample_rate() / FFT_SIZE as f32;
+        let to_points = |values: &[f32]| -> Vec<Pos2> {
+            values
+                .iter()
+                .enumerate()
+                .skip(1)
+                .map(|(bin, &db)| (bin as f32 * bin_hz, db))
+                .filter(|&(freq, _)| (MIN_FREQ..=MAX_FREQ).contains(&freq))
+                .map(|(freq, db)| Pos2::new(freq_to_x(freq), db_to_y(db)))
+                .collect()
+        };
+
+        painter.add(egui::Shape::line(to_points(&self.peak_hold), Stroke::new(1.0, Color32::from_rgb(90, 90, 140))));
+        painter.add(egui::Shape::line(to_points(&self.spectrum), Stroke::new(1.5, Color32::from_rgb(120, 200, 255))));
+    }
+
+    fn draw_scope(&mut self, ui: &mut Ui, tap: &AnalyzerTap) {
+        let samples = &mut self.scope[..self.scope_samples];
+        tap.read_scope(samples);
+
+        let (response, painter) = ui.allocate_painter(ui.available_size().max(Vec2::new(200.0, 120.0)), Sense::hover());
+        let rect = response.rect;
+        painter.rect_filled(rect, 2.0, Color32::from_gray(20));
+        painter.line_segment(
+            [Pos2::new(rect.left(), rect.center().y), Pos2::new(rect.right(), rect.center().y)],
+            Stroke::new(1.0, Color32::from_gray(45)),
+        );
+
+        let step = rect.width() / (samples.len().max(2) - 1) as f32;
+        let points: Vec<Pos2> = samples
+            .iter()
+            .enumerate()
+            .map(|(i, &s)| Pos2::new(rect.left() + i as f32 * step, rect.center().y - s.clamp(-1.0, 1.0) * rect.height() * 0.5))
+            .collect();
+        painter.add(egui::Shape::line(points, Stroke::new(1.5, Color32::from_rgb(120, 255, 160))));
+    }
+}
+
+impl Default for AnalyzerPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,172 @@
+//! Moving and trimming clips by dragging them, with grid, magnetic and zero-crossing snapping
+
+use egui::{Rect, Ui};
+use hallucinator_core::{ClipId, Track};
+
+use super::types::{ArrangeAction, ArrangeContext, ClipDrag, ClipDragMode};
+use super::ArrangePanel;
+use crate::panels::grid::magnet;
+
+/// Width of the grab area at each clip edge that trims instead of moving
+const CLIP_EDGE_WIDTH: f32 = 6.0;
+/// Distance in pixels within which a dragged edge is pulled onto a magnetic target
+const MAGNET_PIXELS: f32 = 8.0;
+/// Shortest a trimmed clip gets, in beats
+const MIN_CLIP_BEATS: f64 = 1.0 / 16.0;
+/// Zero crossings are searched within this many seconds of a trimmed edge
+const ZERO_CROSSING_SECS: f64 = 0.01;
+
+/// Positions (in samples) the magnetic snap pulls dragged clip edges to, besides other clips
+pub(super) struct SnapMarkers {
+    pub position_samples: u64,
+    pub loop_region: (u64, u64),
+}
+
+impl ArrangePanel {
+    /// Show a resize cursor over clip edges and start a drag when the clip is grabbed
+    pub(super) fn begin_clip_drag(
+        &mut self,
+        ui: &Ui,
+        response: &egui::Response,
+        clip_rect: Rect,
+        ctx: &ArrangeContext,
+        (track_idx, clip_id): (usize, ClipId),
+        (start_sample, end_sample): (u64, u64),
+        trim_start: bool,
+    ) {
+        let mode_at = |x: f32| {
+            // Narrow clips are only moved, so they can still be grabbed
+            if clip_rect.width() < CLIP_EDGE_WIDTH * 3.0 {
+                ClipDragMode::Move
+            } else if x >= clip_rect.right() - CLIP_EDGE_WIDTH {
+                ClipDragMode::TrimEnd
+            } else if trim_start && x <= clip_rect.left() + CLIP_EDGE_WIDTH {
+                ClipDragMode::TrimStart
+            } else {
+                ClipDragMode::Move
+            }
+        };
+
+        if let Some(pos) = response.hover_pos().filter(|_| self.clip_drag.is_none())
+            && mode_at(pos.x) != ClipDragMode::Move
+        {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::ResizeHorizontal);
+        }
+
+        if !response.drag_started() {
+            return;
+        }
+        let Some(origin) = ui.input(|i| i.pointer.press_origin()) else { return };
+        self.clip_drag = Some(ClipDrag {
+            track_idx,
+            clip_id,
+            mode: mode_at(origin.x),
+            grab_beat: (ctx.start_beat + (origin.x - ctx.rect.left()) / self.pixels_per_beat) as f64,
+            start_sample,
+            end_sample,
+        });
+    }
+
+    /// Move or trim the dragged clip to follow the pointer, snapped to a magnetic target when
+    /// one is close, else to the grid; trimmed audio edges then go to a zero crossing
+    pub(super) fn handle_clip_drag(
+        &mut self,
+        ui: &Ui,
+        ctx: &ArrangeContext,
+        tracks: &[Track],
+        markers: &SnapMarkers,
+        action: &mut ArrangeAction,
+    ) {
+        let Some(drag) = self.clip_drag else { return };
+        if !ui.input(|i| i.pointer.any_down()) {
+            self.clip_drag = None;
+            return;
+        }
+        let Some(track) = tracks.get(drag.track_idx) else {
+            self.clip_drag = None;
+            return;
+        };
+        let audio_clip = track.clips.iter().find(|c| c.id == drag.clip_id);
+        let current = audio_clip.map(|c| (c.start_sample, c.end_sample()))
+            .or_else(|| track.midi_clips.iter().find(|c| c.id == drag.clip_id).map(|c| (c.start_sample, c.end_sample())));
+        let Some(current) = current else {
+            self.clip_drag = None;
+            return;
+        };
+        ui.ctx().set_cursor_icon(match drag.mode {
+            ClipDragMode::Move => egui::CursorIcon::Grabbing,
+            ClipDragMode::TrimStart | ClipDragMode::TrimEnd => egui::CursorIcon::ResizeHorizontal,
+        });
+        let Some(pos) = ui.input(|i| i.pointer.interact_pos()) else { return };
+
+        let spb = ctx.samples_per_beat;
+        let delta = (ctx.start_beat + (pos.x - ctx.rect.left()) / self.pixels_per_beat) as f64 - drag.grab_beat;
+        let (start, end) = (drag.start_sample as f64 / spb, drag.end_sample as f64 / spb);
+
+        // Other clips' edges, the loop points and the playhead
+        let targets: Vec<f64> = if ctx.grid.magnetic {
+            tracks.iter()
+                .flat_map(|t| {
+                    let audio = t.clips.iter().map(|c| (c.id, c.start_sample, c.end_sample()));
+                    audio.chain(t.midi_clips.iter().map(|c| (c.id, c.start_sample, c.end_sample())))
+                })
+                .filter(|&(id, _, _)| id != drag.clip_id)
+                .flat_map(|(_, start, end)| [start, end])
+                .chain([markers.loop_region.0, markers.loop_region.1, markers.position_samples])
+                .map(|s| s as f64 / spb)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let threshold = (MAGNET_PIXELS / self.pixels_per_beat) as f64;
+        let grid_snap = |original: f64| {
+            if ctx.grid.snap {
+                ctx.grid.snap_drag(original, delta, ctx.grid_step as f64)
+            } else {
+                original + delta
+            }
+        };
+        let snap_edge = |original: f64| magnet(original + delta, &targets, threshold).unwrap_or_else(|| grid_snap(original));
+        let to_samples = |beat: f64| (beat.max(0.0) * spb).round() as u64;
+        // Trimmed audio edges settle on a zero crossing near where they were snapped
+        let settle = |sample: u64| match audio_clip.filter(|_| ctx.grid.zero_crossing) {
+            Some(clip) => {
+                let radius = (clip.sample_rate as f64 * ZERO_CROSSING_SECS) as u64;
+                clip.zero_crossing_near(sample, radius).unwrap_or(sample)
+            }
+            None => sample,
+        };
+
+        let (new_start, new_end) = match drag.mode {
+            ClipDragMode::Move => {
+                // Either edge can land on a magnet; otherwise the start snaps to the grid
+                let length = end - start;
+                let start = magnet(start + delta, &targets, threshold)
+                    .or_else(|| magnet(end + delta, &targets, threshold).map(|end| end - length))
+                    .unwrap_or_else(|| grid_snap(start))
+                    .max(0.0);
+                let start = to_samples(start);
+                (start, start + (drag.end_sample - drag.start_sample))
+            }
+            ClipDragMode::TrimStart => {
+                let start = settle(to_samples(snap_edge(start).min(end - MIN_CLIP_BEATS)));
+                (start, drag.end_sample)
+            }
+            ClipDragMode::TrimEnd => {
+                let end = settle(to_samples(snap_edge(end).max(start + MIN_CLIP_BEATS)));
+                (drag.start_sample, end)
+            }
+        };
+
+        if (new_start, new_end) == current {
+            return;
+        }
+        let (track_idx, clip_id) = (drag.track_idx, drag.clip_id);
+        *action = match drag.mode {
+            ClipDragMode::Move => ArrangeAction::MoveClip { track_idx, clip_id, start_sample: new_start },
+            ClipDragMode::TrimStart | ClipDragMode::TrimEnd => {
+                ArrangeAction::TrimClip { track_idx, clip_id, start_sample: new_start, end_sample: new_end }
+            }
+        };
+    }
+}
@@ -0,0 +1,112 @@
+//! Crossfade regions where audio clips overlap: drag an edge to change the length, drag up
+//! or down inside to bend the curve
+
+use egui::{Color32, Rect, Sense, Stroke, Ui};
+use hallucinator_core::Track;
+
+use super::types::{ArrangeAction, ArrangeContext, CrossfadeDrag};
+use super::ArrangePanel;
+
+/// Width of the grab area at each edge of a crossfade that changes its length
+const CROSSFADE_EDGE_WIDTH: f32 = 5.0;
+/// Points along each drawn fade curve
+const CURVE_POINTS: usize = 24;
+
+impl ArrangePanel {
+    /// Draw each track's crossfades over its clips and turn drags on them into edits
+    pub(super) fn draw_crossfades(
+        &mut self,
+        painter: &egui::Painter,
+        ui: &mut Ui,
+        ctx: &ArrangeContext,
+        tracks: &[Track],
+        action: &mut ArrangeAction,
+    ) {
+        let pixels_per_beat = self.pixels_per_beat;
+        let sample_x = |sample: u64| {
+            ctx.rect.left() + (sample as f64 / ctx.samples_per_beat - ctx.start_beat as f64) as f32 * pixels_per_beat
+        };
+
+        for (track_idx, (track, &row)) in tracks.iter().zip(&ctx.rows).enumerate() {
+            if row.bottom() < ctx.track_area_top || row.top() > ctx.rect.bottom() {
+                continue;
+            }
+            for fade in &track.crossfades {
+                let rect = Rect::from_x_y_ranges(
+                    sample_x(fade.start)..=sample_x(fade.end()).max(sample_x(fade.start) + 2.0),
+                    row.top() + 4.0..=row.bottom() - 4.0,
+                );
+                if rect.right() < ctx.rect.left() || rect.left() > ctx.rect.right() {
+                    continue;
+                }
+
+                let id = ui.id().with(("crossfade", track_idx, fade.out_clip, fade.in_clip));
+                let response = ui
+                    .interact(rect.intersect(ctx.track_area()), id, Sense::drag())
+                    .on_hover_text("Drag an edge to change the crossfade length, up or down to bend it");
+                let edge_at = |x: f32| {
+                    if x >= rect.right() - CROSSFADE_EDGE_WIDTH {
+                        Some(1.0)
+                    } else if x <= rect.left() + CROSSFADE_EDGE_WIDTH {
+                        Some(-1.0)
+                    } else {
+                        None
+                    }
+                };
+                if let Some(pos) = response.hover_pos() {
+                    ui.ctx().set_cursor_icon(match edge_at(pos.x) {
+                        Some(_) => egui::CursorIcon::ResizeHorizontal,
+                        None => egui::CursorIcon::ResizeVertical,
+                    });
+                }
+                if response.drag_started()
+                    && let Some(origin) = ui.input(|i| i.pointer.press_origin())
+                {
+                    self.crossfade_drag = Some(CrossfadeDrag { edge: edge_at(origin.x), length: fade.length, curve: fade.curve });
+                }
+
+                if response.dragged()
+                    && let Some(drag) = self.crossfade_drag
+                    && let (Some(origin), Some(pos)) = ui.input(|i| (i.pointer.press_origin(), i.pointer.interact_pos()))
+                {
+                    let (length, curve) = match drag.edge {
+                        // The fade stays centred in the overlap, so both edges move
+                        Some(side) => {
+                            let delta = (side * (pos.x - origin.x) * 2.0 / pixels_per_beat) as f64 * ctx.samples_per_beat;
+                            ((drag.length as f64 + delta).max(1.0) as u64, drag.curve)
+                        }
+                        None => (drag.length, (drag.curve + (origin.y - pos.y) / (rect.height() / 2.0)).clamp(-1.0, 1.0)),
+                    };
+                    if (length, curve) != (fade.length, fade.curve) {
+                        *action = ArrangeAction::EditCrossfade {
+                            track_idx,
+                            out_clip: fade.out_clip,
+                            in_clip: fade.in_clip,
+                            length,
+                            curve,
+                        };
+                    }
+                }
+                if response.drag_stopped() {
+                    self.crossfade_drag = None;
+                }
+
+                // Shaded region with the fade-out and fade-in gain curves across it
+                painter.rect_filled(rect, 2.0, Color32::from_rgba_unmultiplied(0, 0, 0, 70));
+                let gain_line = |gain: fn((f32, f32)) -> f32| {
+                    (0..=CURVE_POINTS)
+                        .map(|i| {
+                            let position = i as f32 / CURVE_POINTS as f32;
+                            egui::pos2(rect.left() + position * rect.width(), rect.bottom() - gain(fade.gains_at(position)) * rect.height())
+                        })
+                        .collect::<Vec<_>>()
+                };
+                let highlight = response.hovered() || response.dragged();
+                let stroke = |color: Color32| Stroke::new(if highlight { 2.0 } else { 1.5 }, color);
+                painter.add(egui::Shape::line(gain_line(|(out, _)| out), stroke(Color32::from_rgb(230, 170, 90))));
+                painter.add(egui::Shape::line(gain_line(|(_, inn)| inn), stroke(Color32::from_rgb(240, 230, 120))));
+                painter.rect_stroke(rect, 2.0, Stroke::new(1.0, Color32::from_gray(180)), egui::StrokeKind::Inside);
+            }
+        }
+    }
+}
@@ -1,66 +1,72 @@
+use std::collections::HashMap;
+
 use egui::{Color32, Rect, Sense, Stroke, Ui, Vec2};
-use hallucinator_core::ClipId;
+use hallucinator_core::{ClipId, TrackKind, PEAK_BLOCK};
 
 use super::types::{ArrangeAction, ArrangeContext};
 use super::ArrangePanel;
+use crate::panels::keymap::Command;
+use crate::panels::status_bar;
+use crate::panels::theme::{shade, with_alpha, Palette};
 use crate::panels::timeline::RecordingPreview;
 
+/// Ticks per beat in the position readout, as in MIDI clips
+const READOUT_PPQ: u64 = 480;
+
+/// Clips narrower than this in points are drawn without their name
+const MIN_NAMED_CLIP_WIDTH: f32 = 16.0;
+
+/// Drum sounds offered for an audio clip's hits, by the note that plays them on 808 Drums
+const DRUM_HIT_NOTES: [(&str, u8); 3] = [("Kick (C1)", 36), ("Snare (D1)", 38), ("Closed Hat (F#1)", 42)];
+
 impl ArrangePanel {
     pub(super) fn draw_track_backgrounds(
         &self,
         painter: &egui::Painter,
         ctx: &ArrangeContext,
-        tracks: &[hallucinator_core::Track],
         selected_track_idx: Option<usize>,
     ) {
-        for (track_idx, _track) in tracks.iter().enumerate() {
-            let track_y = ctx.track_area_top + (track_idx as f32 * self.track_height) - self.vertical_scroll;
-
-            if track_y + self.track_height < ctx.track_area_top || track_y > ctx.rect.bottom() {
+        let palette = Palette::get(painter.ctx());
+        for (track_idx, row) in ctx.rows.iter().enumerate() {
+            if row.bottom() < ctx.track_area_top || row.top() > ctx.rect.bottom() {
                 continue;
             }
 
-            let track_rect = Rect::from_min_size(
-                egui::pos2(ctx.rect.left(), track_y),
-                Vec2::new(ctx.rect.width(), self.track_height),
-            );
-
             let is_selected = selected_track_idx == Some(track_idx);
             let bg_color = match (is_selected, track_idx % 2 == 0) {
-                (true, true) => Color32::from_gray(28),
-                (true, false) => Color32::from_gray(32),
-                (false, true) => Color32::from_gray(42),
-                (false, false) => Color32::from_gray(48),
+                (true, true) => palette.lane_selected,
+                (true, false) => palette.lane_selected_alt,
+                (false, true) => palette.lane,
+                (false, false) => palette.lane_alt,
             };
-            painter.rect_filled(track_rect, 0.0, bg_color);
+            painter.rect_filled(*row, 0.0, bg_color);
 
             painter.line_segment(
-                [
-                    egui::pos2(ctx.rect.left(), track_y + self.track_height),
-                    egui::pos2(ctx.rect.right(), track_y + self.track_height),
-                ],
-                Stroke::new(1.0, Color32::from_gray(25)),
+                [row.left_bottom(), row.right_bottom()],
+                Stroke::new(1.0, palette.separator),
             );
         }
 
         // Fill remaining area below tracks
-        let tracks_bottom = ctx.track_area_top + (tracks.len() as f32 * self.track_height);
+        let tracks_bottom = ctx.rows.last().map_or(ctx.track_area_top, |row| row.bottom());
         if tracks_bottom < ctx.rect.bottom() {
             let empty_rect = Rect::from_min_max(
                 egui::pos2(ctx.rect.left(), tracks_bottom),
                 ctx.rect.max,
             );
-            painter.rect_filled(empty_rect, 0.0, Color32::from_gray(50));
+            painter.rect_filled(empty_rect, 0.0, palette.empty);
         }
     }
 
-    pub(super) fn draw_grid(&self, painter: &egui::Painter, ctx: &ArrangeContext) {
+    pub(super) fn draw_grid(&mut self, painter: &egui::Painter, ctx: &ArrangeContext) {
         if ctx.pixels_per_grid < 8.0 {
             return;
         }
 
-        let mut pos = (ctx.start_beat / ctx.grid_step).floor() * ctx.grid_step;
-        while pos <= ctx.end_beat {
+        let palette = Palette::get(painter.ctx());
+        let lines = ctx.grid.lines(ctx.start_beat as f64, ctx.end_beat as f64, ctx.grid_step as f64);
+        let segments = lines.into_iter().map(|pos| {
+            let pos = pos as f32;
             let x = ctx.rect.left() + ((pos - ctx.start_beat) * self.pixels_per_beat);
 
             let is_bar = (pos.round() as u32) % (ctx.time_sig_num as u32) == 0
@@ -68,27 +74,25 @@ impl ArrangePanel {
             let is_beat = (pos - pos.round()).abs() < 0.001;
 
             let stroke = if is_bar {
-                Stroke::new(1.0, Color32::from_gray(80))
+                Stroke::new(1.0, palette.grid_bar)
             } else if is_beat {
-                Stroke::new(0.5, Color32::from_gray(60))
+                Stroke::new(0.5, palette.grid_beat)
             } else {
-                Stroke::new(0.5, Color32::from_gray(45))
+                Stroke::new(0.5, palette.grid_subdivision)
             };
 
-            painter.line_segment(
-                [egui::pos2(x, ctx.track_area_top), egui::pos2(x, ctx.rect.bottom())],
-                stroke,
-            );
-
-            pos += ctx.grid_step;
-        }
+            ([egui::pos2(x, ctx.track_area_top), egui::pos2(x, ctx.rect.bottom())], stroke)
+        }).collect();
+        self.layers.grid.paint(painter, segments);
     }
 
-    pub(super) fn draw_ruler(&self, painter: &egui::Painter, ctx: &ArrangeContext) {
-        painter.rect_filled(ctx.ruler_rect, 0.0, Color32::from_gray(45));
+    pub(super) fn draw_ruler(&mut self, painter: &egui::Painter, ctx: &ArrangeContext) {
+        let palette = Palette::get(painter.ctx());
+        painter.rect_filled(ctx.ruler_rect, 0.0, palette.ruler);
 
-        if ctx.pixels_per_grid >= 8.0 {
-            let mut pos = (ctx.start_beat / ctx.grid_step).floor() * ctx.grid_step;
+        let mut ticks = Vec::new();
+        if ctx.pixels_per_ruler >= 8.0 {
+            let mut pos = (ctx.start_beat / ctx.ruler_step).floor() * ctx.ruler_step;
             while pos <= ctx.end_beat {
                 let x = ctx.rect.left() + ((pos - ctx.start_beat) * self.pixels_per_beat);
 
@@ -97,19 +101,15 @@ impl ArrangePanel {
                 let is_beat = (pos - pos.round()).abs() < 0.001;
 
                 let tick_height = if is_bar { 10.0 } else if is_beat { 6.0 } else { 3.0 };
-                let tick_color = if is_bar || is_beat {
-                    Color32::from_gray(120)
-                } else {
-                    Color32::from_gray(80)
-                };
+                let tick_color = if is_bar || is_beat { palette.text_dim } else { palette.grid_bar };
 
-                painter.line_segment(
+                ticks.push((
                     [
                         egui::pos2(x, ctx.ruler_rect.bottom() - tick_height),
                         egui::pos2(x, ctx.ruler_rect.bottom()),
                     ],
                     Stroke::new(1.0, tick_color),
-                );
+                ));
 
                 if is_bar {
                     let bar = (pos.round() as u32) / (ctx.time_sig_num as u32) + 1;
@@ -118,13 +118,14 @@ impl ArrangePanel {
                         egui::Align2::LEFT_TOP,
                         format!("{}", bar),
                         egui::FontId::proportional(12.0),
-                        Color32::WHITE,
+                        palette.text,
                     );
                 }
 
-                pos += ctx.grid_step;
+                pos += ctx.ruler_step;
             }
         }
+        self.layers.ruler_ticks.paint(painter, ticks);
 
         // Ruler bottom border
         painter.line_segment(
@@ -132,37 +133,48 @@ impl ArrangePanel {
                 egui::pos2(ctx.rect.left(), ctx.ruler_rect.bottom()),
                 egui::pos2(ctx.rect.right(), ctx.ruler_rect.bottom()),
             ],
-            Stroke::new(1.0, Color32::from_gray(60)),
+            Stroke::new(1.0, palette.grid_beat),
         );
     }
 
     pub(super) fn draw_clips(
-        &self,
+        &mut self,
         painter: &egui::Painter,
         ui: &mut Ui,
         ctx: &ArrangeContext,
         tracks: &[hallucinator_core::Track],
         selected_clip: Option<(usize, ClipId)>,
         recording_preview: &Option<RecordingPreview>,
+        loading_clips: &HashMap<ClipId, f32>,
     ) -> ArrangeAction {
         let mut action = ArrangeAction::None;
 
-        for (track_idx, track) in tracks.iter().enumerate() {
-            let track_y = ctx.track_area_top + (track_idx as f32 * self.track_height) - self.vertical_scroll;
-
-            if track_y + self.track_height < ctx.track_area_top || track_y > ctx.rect.bottom() {
+        // Shift-clicks extend the clip selection from the selected clip, or the span already selected
+        let anchor = self.clip_range.or_else(|| {
+            let (track_idx, clip_id) = selected_clip?;
+            let track = tracks.get(track_idx)?;
+            let span = track.get_clip(clip_id).map(|c| (c.start_sample, c.end_sample()))
+                .or_else(|| track.get_midi_clip(clip_id).map(|c| (c.start_sample, c.end_sample())))?;
+            Some((track_idx, span.0, span.1))
+        });
+
+        for (track_idx, (track, &row)) in tracks.iter().zip(&ctx.rows).enumerate() {
+            if row.bottom() < ctx.track_area_top || row.top() > ctx.rect.bottom() {
                 continue;
             }
 
             for clip in &track.clips {
-                let clip_action = self.draw_audio_clip(painter, ui, clip, track_idx, track_y, ctx, selected_clip);
+                let loading = loading_clips.get(&clip.id).copied();
+                let clip_action = self.draw_audio_clip(painter, ui, clip, loading, track_idx, row, ctx, selected_clip);
+                let clip_action = self.extend_clip_range(ui, clip_action, (clip.start_sample, clip.end_sample()), anchor);
                 if !matches!(clip_action, ArrangeAction::None) {
                     action = clip_action;
                 }
             }
 
             for clip in &track.midi_clips {
-                let clip_action = self.draw_midi_clip(painter, ui, clip, track_idx, track_y, ctx, selected_clip);
+                let clip_action = self.draw_midi_clip(painter, ui, clip, track_idx, row, ctx, selected_clip);
+                let clip_action = self.extend_clip_range(ui, clip_action, (clip.start_sample, clip.end_sample()), anchor);
                 if !matches!(clip_action, ArrangeAction::None) {
                     action = clip_action;
                 }
@@ -170,18 +182,53 @@ impl ArrangePanel {
 
             if track.armed {
                 if let Some(preview) = recording_preview {
-                    self.draw_recording_preview(painter, preview, track_y, ctx);
+                    self.draw_recording_preview(painter, preview, row, ctx);
                 }
             }
         }
 
+        if let Some((track_idx, start_sample, end_sample)) = self.clip_range
+            && let Some(track) = tracks.get(track_idx)
+            && Command::Consolidate.consume(ui.ctx())
+        {
+            action = ArrangeAction::ConsolidateClips { track_idx, start_sample, end_sample, midi: track.kind == TrackKind::Midi };
+            self.clip_range = None;
+        }
+
+        action
+    }
+
+    /// Shift-clicking a clip on the same track as the selection selects every clip between
+    /// them; a plain click leaves just the clicked one selected
+    fn extend_clip_range(
+        &mut self,
+        ui: &Ui,
+        action: ArrangeAction,
+        (start_sample, end_sample): (u64, u64),
+        anchor: Option<(usize, u64, u64)>,
+    ) -> ArrangeAction {
+        let ArrangeAction::SelectClip { track_idx, .. } = action else {
+            return action;
+        };
+        if ui.input(|i| i.modifiers.shift)
+            && let Some((anchor_track, anchor_start, anchor_end)) = anchor
+            && anchor_track == track_idx
+        {
+            let range = (track_idx, anchor_start.min(start_sample), anchor_end.max(end_sample));
+            // Shift-clicking inside the selection keeps it
+            if (range.1, range.2) != (anchor_start, anchor_end) || self.clip_range.is_some() {
+                self.clip_range = Some(range);
+                return ArrangeAction::None;
+            }
+        }
+        self.clip_range = None;
         action
     }
 
-    /// Shared clip rendering: background, border, name, click handling.
+    /// Shared clip rendering: background, border, name, click and drag handling.
     /// Returns (clip_rect if visible, action from click).
     fn draw_clip_base(
-        &self,
+        &mut self,
         painter: &egui::Painter,
         ui: &mut Ui,
         clip_id: ClipId,
@@ -189,12 +236,14 @@ impl ArrangePanel {
         clip_start_sample: u64,
         clip_end_sample: u64,
         track_idx: usize,
-        track_y: f32,
+        row: Rect,
         ctx: &ArrangeContext,
         selected_clip: Option<(usize, ClipId)>,
         fill: Color32,
         fill_selected: Color32,
         border: Color32,
+        bounceable: bool,
+        trim_start: bool,
     ) -> (Option<Rect>, ArrangeAction) {
         let clip_start_beat = clip_start_sample as f64 / ctx.samples_per_beat;
         let clip_end_beat = clip_end_sample as f64 / ctx.samples_per_beat;
@@ -207,77 +256,170 @@ impl ArrangePanel {
         let clip_width = (clip_end_beat - clip_start_beat) as f32 * self.pixels_per_beat;
 
         let clip_rect = Rect::from_min_size(
-            egui::pos2(clip_x, track_y + 4.0),
-            Vec2::new(clip_width, self.track_height - 8.0),
+            egui::pos2(clip_x, row.top() + 4.0),
+            Vec2::new(clip_width, row.height() - 8.0),
         );
 
-        let is_selected = selected_clip == Some((track_idx, clip_id));
+        let range = self.clip_range
+            .filter(|&(range_track, start, end)| range_track == track_idx && clip_start_sample >= start && clip_end_sample <= end);
+        let is_selected = selected_clip == Some((track_idx, clip_id)) || range.is_some();
         let color = if is_selected { fill_selected } else { fill };
         let border_width = if is_selected { 2.0 } else { 1.0 };
 
         painter.rect_filled(clip_rect, 4.0, color);
         painter.rect_stroke(clip_rect, 4.0, Stroke::new(border_width, border), egui::StrokeKind::Outside);
 
-        painter.text(
-            egui::pos2(clip_rect.left() + 4.0, clip_rect.top() + 2.0),
-            egui::Align2::LEFT_TOP,
-            clip_name,
-            egui::FontId::proportional(10.0),
-            Color32::WHITE,
-        );
+        // Too narrow to read, and laying out hundreds of names costs more than the clips
+        if clip_rect.width() >= MIN_NAMED_CLIP_WIDTH {
+            painter.text(
+                egui::pos2(clip_rect.left() + 4.0, clip_rect.top() + 2.0),
+                egui::Align2::LEFT_TOP,
+                clip_name,
+                egui::FontId::proportional(10.0),
+                Color32::WHITE,
+            );
+        }
 
         let mut action = ArrangeAction::None;
-        let clip_response = ui.allocate_rect(clip_rect, Sense::click());
+        let clip_response = ui.allocate_rect(clip_rect.intersect(ctx.track_area()), Sense::click_and_drag());
         if clip_response.double_clicked() {
             action = ArrangeAction::OpenClipEditor { track_idx, clip_id };
-        } else if clip_response.clicked() {
+        } else if clip_response.clicked() || clip_response.drag_started() {
             action = ArrangeAction::SelectClip { track_idx, clip_id };
         }
+        if clip_response.hovered() {
+            let beats_per_bar = ctx.time_sig_num.max(1) as f64;
+            let bar = (clip_start_beat / beats_per_bar).floor();
+            let beat = clip_start_beat - bar * beats_per_bar;
+            status_bar::set_hovered(ui.ctx(), format!(
+                "{} · {:.2} bars · starts at {}.{}",
+                clip_name,
+                (clip_end_beat - clip_start_beat) / beats_per_bar,
+                bar as u64 + 1,
+                beat.floor() as u64 + 1,
+            ));
+            let trim = if trim_start { "an edge to trim it" } else { "its end to trim it" };
+            status_bar::set_hint(
+                ui.ctx(),
+                format!("Drag to move, {} · double-click to edit · shift-click selects a range · right-click for more", trim),
+            );
+        }
+        // Drag the body to move the clip, an edge to trim it
+        self.begin_clip_drag(
+            ui, &clip_response, clip_rect, ctx,
+            (track_idx, clip_id), (clip_start_sample, clip_end_sample),
+            trim_start,
+        );
+        clip_response.context_menu(|ui| {
+            if let Some((_, start_sample, end_sample)) = range {
+                let shortcut = Command::Consolidate.shortcut_text(ui.ctx());
+                let consolidate = ui.add(egui::Button::new("Consolidate").shortcut_text(shortcut))
+                    .on_hover_text("Glue the selected clips into one: audio is rendered, MIDI notes are merged");
+                if consolidate.clicked() {
+                    action = ArrangeAction::ConsolidateClips { track_idx, start_sample, end_sample, midi: bounceable };
+                    self.clip_range = None;
+                    ui.close_menu();
+                }
+                ui.separator();
+            }
+            if bounceable && ui.button("Bounce to Audio").clicked() {
+                action = ArrangeAction::BounceClip { track_idx, clip_id };
+                ui.close_menu();
+            }
+            let extract = ui.button("Extract Groove")
+                .on_hover_text("Add the clip's timing and velocity per 16th to the groove pool");
+            if extract.clicked() {
+                action = ArrangeAction::ExtractGroove { track_idx, clip_id };
+                ui.close_menu();
+            }
+            if !bounceable {
+                ui.menu_button("Extract Drum Hits", |ui| {
+                    for (name, pitch) in DRUM_HIT_NOTES {
+                        if ui.button(name).clicked() {
+                            action = ArrangeAction::ExtractDrumHits { track_idx, clip_id, pitch };
+                            ui.close_menu();
+                        }
+                    }
+                });
+            }
+        });
 
         (Some(clip_rect), action)
     }
 
     fn draw_audio_clip(
-        &self,
+        &mut self,
         painter: &egui::Painter,
         ui: &mut Ui,
         clip: &hallucinator_core::AudioClip,
+        loading: Option<f32>,
         track_idx: usize,
-        track_y: f32,
+        row: Rect,
         ctx: &ArrangeContext,
         selected_clip: Option<(usize, ClipId)>,
     ) -> ArrangeAction {
+        let color = Palette::get(painter.ctx()).audio_clip;
         let (clip_rect, action) = self.draw_clip_base(
             painter, ui, clip.id, &clip.name,
             clip.start_sample, clip.end_sample(),
-            track_idx, track_y, ctx, selected_clip,
-            Color32::from_rgb(60, 100, 140),
-            Color32::from_rgb(80, 130, 180),
-            Color32::from_rgb(100, 150, 200),
+            track_idx, row, ctx, selected_clip,
+            shade(color, -0.25),
+            color,
+            shade(color, 0.2),
+            false,
+            true,
         );
         if let Some(r) = clip_rect {
-            self.draw_waveform(painter, r, &clip.samples, clip.channels as usize);
+            match loading {
+                Some(progress) => Self::draw_loading_progress(painter, r, progress),
+                None => self.draw_clip_waveform(painter, r, clip),
+            }
         }
         action
     }
 
+    /// A placeholder clip's progress decoding its file, as a bar filling across it
+    fn draw_loading_progress(painter: &egui::Painter, rect: Rect, progress: f32) {
+        let waveform_rect = Self::waveform_rect(rect);
+        if waveform_rect.width() < 4.0 {
+            return;
+        }
+        let bar = Rect::from_min_size(
+            egui::pos2(waveform_rect.left() + 2.0, waveform_rect.center().y - 1.5),
+            Vec2::new((waveform_rect.width() - 4.0) * progress.clamp(0.0, 1.0), 3.0),
+        );
+        painter.rect_filled(bar, 1.0, Color32::from_rgba_unmultiplied(150, 200, 255, 160));
+        if waveform_rect.width() >= MIN_NAMED_CLIP_WIDTH * 4.0 {
+            painter.text(
+                egui::pos2(waveform_rect.left() + 4.0, bar.top() - 2.0),
+                egui::Align2::LEFT_BOTTOM,
+                format!("Loading… {:.0}%", progress * 100.0),
+                egui::FontId::proportional(10.0),
+                Color32::from_white_alpha(180),
+            );
+        }
+    }
+
     fn draw_midi_clip(
-        &self,
+        &mut self,
         painter: &egui::Painter,
         ui: &mut Ui,
         clip: &hallucinator_core::MidiClip,
         track_idx: usize,
-        track_y: f32,
+        row: Rect,
         ctx: &ArrangeContext,
         selected_clip: Option<(usize, ClipId)>,
     ) -> ArrangeAction {
+        let color = Palette::get(painter.ctx()).midi_clip;
         let (clip_rect, action) = self.draw_clip_base(
             painter, ui, clip.id, &clip.name,
             clip.start_sample, clip.end_sample(),
-            track_idx, track_y, ctx, selected_clip,
-            Color32::from_rgb(60, 120, 60),
-            Color32::from_rgb(80, 160, 80),
-            Color32::from_rgb(100, 180, 100),
+            track_idx, row, ctx, selected_clip,
+            shade(color, -0.25),
+            color,
+            shade(color, 0.2),
+            true,
+            false,
         );
         if let Some(r) = clip_rect {
             self.draw_note_preview(painter, r, clip);
@@ -289,7 +431,7 @@ impl ArrangePanel {
         &self,
         painter: &egui::Painter,
         preview: &RecordingPreview,
-        track_y: f32,
+        row: Rect,
         ctx: &ArrangeContext,
     ) {
         if preview.samples.is_empty() {
@@ -297,18 +439,19 @@ impl ArrangePanel {
         }
 
         let clip_start_beat = preview.start_sample as f64 / ctx.samples_per_beat;
-        let clip_duration_beats = preview.samples.len() as f64 / ctx.samples_per_beat;
+        let clip_duration_beats = preview.length_samples as f64 / ctx.samples_per_beat;
 
         let clip_x = ctx.rect.left() + ((clip_start_beat as f32 - ctx.start_beat) * self.pixels_per_beat);
         let clip_width = (clip_duration_beats as f32 * self.pixels_per_beat).max(2.0);
 
         let clip_rect = Rect::from_min_size(
-            egui::pos2(clip_x, track_y + 4.0),
-            Vec2::new(clip_width, self.track_height - 8.0),
+            egui::pos2(clip_x, row.top() + 4.0),
+            Vec2::new(clip_width, row.height() - 8.0),
         );
 
-        painter.rect_filled(clip_rect, 4.0, Color32::from_rgb(140, 60, 60));
-        painter.rect_stroke(clip_rect, 4.0, Stroke::new(1.0, Color32::from_rgb(200, 80, 80)), egui::StrokeKind::Outside);
+        let color = Palette::get(painter.ctx()).recording;
+        painter.rect_filled(clip_rect, 4.0, shade(color, -0.3));
+        painter.rect_stroke(clip_rect, 4.0, Stroke::new(1.0, color), egui::StrokeKind::Outside);
 
         painter.text(
             egui::pos2(clip_rect.left() + 4.0, clip_rect.top() + 2.0),
@@ -339,10 +482,10 @@ impl ArrangePanel {
             egui::pos2(loop_x_start, ctx.ruler_rect.top()),
             egui::pos2(loop_x_end, ctx.ruler_rect.bottom()),
         );
-        painter.rect_filled(loop_rect, 0.0, Color32::from_rgba_unmultiplied(100, 150, 200, 60));
+        let bracket_color = Palette::get(painter.ctx()).loop_region;
+        painter.rect_filled(loop_rect, 0.0, with_alpha(bracket_color, 60));
 
         // Vertical bracket lines
-        let bracket_color = Color32::from_rgb(100, 150, 200);
         painter.line_segment(
             [egui::pos2(loop_x_start, ctx.rect.top()), egui::pos2(loop_x_start, ctx.rect.bottom())],
             Stroke::new(1.0, bracket_color),
@@ -353,7 +496,7 @@ impl ArrangePanel {
         );
 
         // Edge handles (small rectangles at top of each bracket)
-        let handle_color = Color32::from_rgb(130, 180, 230);
+        let handle_color = shade(bracket_color, 0.2);
         let handle_width = 4.0;
         let handle_height = ctx.ruler_rect.height();
 
@@ -385,7 +528,7 @@ impl ArrangePanel {
             return;
         }
 
-        let color = Color32::from_rgb(255, 100, 100);
+        let color = Palette::get(painter.ctx()).playhead;
         painter.line_segment(
             [egui::pos2(playhead_x, ctx.rect.top()), egui::pos2(playhead_x, ctx.rect.bottom())],
             Stroke::new(2.0, color),
@@ -399,6 +542,49 @@ impl ArrangePanel {
         painter.add(egui::Shape::convex_polygon(triangle, color, Stroke::NONE));
     }
 
+    /// Playhead and pointer positions as bar:beat:tick in the right corner of the ruler; the
+    /// pointer position is snapped like a dropped sample when snapping is on
+    pub(super) fn draw_position_readout(
+        &self,
+        ui: &Ui,
+        painter: &egui::Painter,
+        ctx: &ArrangeContext,
+        position_samples: u64,
+    ) {
+        let readout_rect = Rect::from_min_max(
+            egui::pos2(ctx.ruler_rect.right() - 96.0, ctx.ruler_rect.top()),
+            egui::pos2(ctx.ruler_rect.right(), ctx.ruler_rect.bottom() - 1.0),
+        );
+        let palette = Palette::get(painter.ctx());
+        painter.rect_filled(readout_rect, 0.0, palette.panel);
+
+        let font = egui::FontId::monospace(9.0);
+        let playhead_beat = position_samples as f64 / ctx.samples_per_beat;
+        painter.text(
+            readout_rect.left_top() + Vec2::new(4.0, 1.0),
+            egui::Align2::LEFT_TOP,
+            format!("Play  {}", bar_beat_tick(playhead_beat, ctx.time_sig_num)),
+            font.clone(),
+            shade(palette.playhead, 0.2),
+        );
+
+        let pointer_beat = ui.input(|i| i.pointer.hover_pos())
+            .filter(|pos| ctx.rect.contains(*pos))
+            .map(|pos| {
+                let beat = (ctx.start_beat + (pos.x - ctx.rect.left()) / self.pixels_per_beat).max(0.0) as f64;
+                if ctx.grid.snap { ctx.grid.snap_floor(beat, ctx.grid_step as f64) } else { beat }
+            });
+        if let Some(beat) = pointer_beat {
+            painter.text(
+                readout_rect.left_bottom() + Vec2::new(4.0, -1.0),
+                egui::Align2::LEFT_BOTTOM,
+                format!("Mouse {}", bar_beat_tick(beat, ctx.time_sig_num)),
+                font,
+                palette.text,
+            );
+        }
+    }
+
     pub(super) fn draw_loop_selection_overlay(
         &self,
         painter: &egui::Painter,
@@ -412,8 +598,26 @@ impl ArrangePanel {
             egui::pos2(sel_x_start, ctx.ruler_rect.top()),
             egui::pos2(sel_x_end, ctx.rect.bottom()),
         );
-        painter.rect_filled(sel_rect, 0.0, Color32::from_rgba_unmultiplied(100, 180, 255, 40));
-        painter.rect_stroke(sel_rect, 0.0, Stroke::new(1.0, Color32::from_rgb(100, 180, 255)), egui::StrokeKind::Inside);
+        let selection = Palette::get(painter.ctx()).selection;
+        painter.rect_filled(sel_rect, 0.0, with_alpha(selection, 40));
+        painter.rect_stroke(sel_rect, 0.0, Stroke::new(1.0, selection), egui::StrokeKind::Inside);
+    }
+
+    /// An audio clip's waveform, from its peaks once a pixel spans at least a peak block,
+    /// else from its samples
+    fn draw_clip_waveform(&self, painter: &egui::Painter, rect: Rect, clip: &hallucinator_core::AudioClip) {
+        let waveform_rect = Self::waveform_rect(rect);
+        let frames_per_pixel = clip.length_samples as usize / (waveform_rect.width() as usize).max(1);
+        match &clip.peaks {
+            Some(peaks) if frames_per_pixel >= PEAK_BLOCK => {
+                let offset = clip.source_offset as usize;
+                self.draw_waveform_bars(painter, waveform_rect, |px| {
+                    let start = offset + px * frames_per_pixel;
+                    peaks.range(start, start + frames_per_pixel).map(|(min, max)| max.max(-min))
+                });
+            }
+            _ => self.draw_waveform(painter, rect, clip.audible_samples(), clip.channels as usize),
+        }
     }
 
     fn draw_waveform(&self, painter: &egui::Painter, rect: Rect, samples: &[f32], channels: usize) {
@@ -421,15 +625,8 @@ impl ArrangePanel {
             return;
         }
 
-        let waveform_rect = Rect::from_min_max(
-            egui::pos2(rect.left(), rect.top() + 16.0),
-            rect.max,
-        );
-
+        let waveform_rect = Self::waveform_rect(rect);
         let width = waveform_rect.width() as usize;
-        let center_y = waveform_rect.center().y;
-        let amplitude = waveform_rect.height() / 2.0 - 2.0;
-
         let total_frames = samples.len() / channels.max(1);
         let samples_per_pixel = total_frames / width.max(1);
 
@@ -437,28 +634,51 @@ impl ArrangePanel {
             return;
         }
 
-        for px in 0..width {
+        self.draw_waveform_bars(painter, waveform_rect, |px| {
             let start = px * samples_per_pixel * channels;
             let end = ((px + 1) * samples_per_pixel * channels).min(samples.len());
+            (start < samples.len()).then(|| {
+                samples[start..end]
+                    .chunks(channels)
+                    .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+                    .map(|s| s.abs())
+                    .fold(0.0f32, f32::max)
+            })
+        });
+    }
 
-            if start >= samples.len() {
-                break;
-            }
-
-            let max_val = samples[start..end]
-                .chunks(channels)
-                .map(|frame| frame.iter().sum::<f32>() / channels as f32)
-                .map(|s| s.abs())
-                .fold(0.0f32, f32::max);
+    /// Below the clip's name
+    fn waveform_rect(clip_rect: Rect) -> Rect {
+        Rect::from_min_max(egui::pos2(clip_rect.left(), clip_rect.top() + 16.0), clip_rect.max)
+    }
 
-            let x = waveform_rect.left() + px as f32;
+    /// A bar per pixel column of `rect`, `level(px)` high either side of the centre, stopping
+    /// at the first None. Columns scrolled out of view are skipped, and the rest go to the
+    /// painter as one mesh rather than a shape each
+    fn draw_waveform_bars(&self, painter: &egui::Painter, rect: Rect, level: impl Fn(usize) -> Option<f32>) {
+        if rect.width() < 4.0 {
+            return;
+        }
+        let width = rect.width() as usize;
+        let center_y = rect.center().y;
+        let amplitude = rect.height() / 2.0 - 2.0;
+        let visible = painter.clip_rect();
+        let first = (visible.left() - rect.left()).max(0.0) as usize;
+        let last = ((visible.right() - rect.left()).max(0.0).ceil() as usize).min(width);
+
+        let color = Color32::from_rgba_unmultiplied(150, 200, 255, 120);
+        let mut mesh = egui::Mesh::default();
+        mesh.reserve_vertices(last.saturating_sub(first) * 4);
+        mesh.reserve_triangles(last.saturating_sub(first) * 2);
+        for px in first..last {
+            let Some(max_val) = level(px) else { break };
+            let x = rect.left() + px as f32;
             let h = max_val * amplitude;
 
-            painter.rect_filled(
-                Rect::from_center_size(egui::pos2(x, center_y), Vec2::new(1.0, h * 2.0)),
-                0.0,
-                Color32::from_rgba_unmultiplied(150, 200, 255, 120),
-            );
+            mesh.add_colored_rect(Rect::from_center_size(egui::pos2(x, center_y), Vec2::new(1.0, h * 2.0)), color);
+        }
+        if !mesh.is_empty() {
+            painter.add(egui::Shape::mesh(mesh));
         }
     }
 
@@ -477,19 +697,30 @@ impl ArrangePanel {
         let pitch_range = (max_pitch - min_pitch).max(12) as f32;
         let clip_duration_ticks = clip.length_samples as f32;
 
+        // One mesh for every note in view; at this size the notes' rounded corners don't show
+        let visible = painter.clip_rect();
+        let color = Color32::from_rgba_unmultiplied(200, 255, 200, 180);
+        let mut mesh = egui::Mesh::default();
         for note in &clip.notes {
             let x = preview_rect.left() + (note.start_tick as f32 / clip_duration_ticks) * preview_rect.width();
             let w = (note.duration_ticks as f32 / clip_duration_ticks) * preview_rect.width();
             let y = preview_rect.bottom() - ((note.pitch - min_pitch) as f32 / pitch_range) * preview_rect.height();
 
-            painter.rect_filled(
-                Rect::from_min_size(
-                    egui::pos2(x, y - 2.0),
-                    Vec2::new(w.max(2.0), 3.0),
-                ),
-                1.0,
-                Color32::from_rgba_unmultiplied(200, 255, 200, 180),
-            );
+            let note_rect = Rect::from_min_size(egui::pos2(x, y - 2.0), Vec2::new(w.max(2.0), 3.0));
+            if note_rect.intersects(visible) {
+                mesh.add_colored_rect(note_rect, color);
+            }
+        }
+        if !mesh.is_empty() {
+            painter.add(egui::Shape::mesh(mesh));
         }
     }
 }
+
+/// Position as 1-based bar and beat plus ticks into the beat
+fn bar_beat_tick(beat: f64, beats_per_bar: u8) -> String {
+    let ticks = (beat.max(0.0) * READOUT_PPQ as f64).round() as u64;
+    let beats = ticks / READOUT_PPQ;
+    let beats_per_bar = beats_per_bar.max(1) as u64;
+    format!("{}:{}:{:03}", beats / beats_per_bar + 1, beats % beats_per_bar + 1, ticks % READOUT_PPQ)
+}
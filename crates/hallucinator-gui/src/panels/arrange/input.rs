@@ -1,7 +1,12 @@
-use egui::{Rect, Ui};
+use std::path::PathBuf;
 
-use super::types::{ArrangeAction, ArrangeContext, LoopEdge};
-use super::ArrangePanel;
+use egui::{Rect, Stroke, Ui};
+
+use super::types::{ArrangeAction, ArrangeContext, LoopEdge, ZoomTargets};
+use super::{ArrangePanel, MAX_PIXELS_PER_BEAT, MIN_PIXELS_PER_BEAT};
+use crate::panels::theme::Palette;
+use crate::panels::track_layout::TrackLayout;
+use crate::panels::zoom::{fit_range, ZoomCommand};
 
 const EDGE_HIT_WIDTH: f32 = 6.0;
 
@@ -52,6 +57,54 @@ impl ArrangePanel {
         }
     }
 
+    /// Track the drop target under the pointer, highlight it while a sample is dragged from
+    /// the browser (or files from outside the app) and place a browser sample on release
+    pub(super) fn handle_sample_drop(
+        &mut self,
+        ui: &Ui,
+        painter: &egui::Painter,
+        ctx: &ArrangeContext,
+        action: &mut ArrangeAction,
+    ) {
+        // Without a pointer position (e.g. during an OS drag) keep the last target
+        let Some(pos) = ui.input(|i| i.pointer.hover_pos()) else { return };
+        self.drop_target = None;
+        if pos.y < ctx.track_area_top || !ctx.rect.contains(pos) {
+            return;
+        }
+
+        // Below the last track the drop creates a new one in the next row
+        let track_idx = ctx.rows.iter().position(|row| pos.y < row.bottom());
+        let row_rect = match track_idx {
+            Some(idx) => ctx.rows[idx],
+            None => {
+                let top = ctx.rows.last().map_or(ctx.track_area_top, |row| row.bottom());
+                Rect::from_min_size(egui::pos2(ctx.rect.left(), top), egui::vec2(ctx.rect.width(), ctx.new_row_height))
+            }
+        };
+
+        let mut beat = (ctx.start_beat + (pos.x - ctx.rect.left()) / self.pixels_per_beat).max(0.0);
+        if ctx.grid.snap {
+            beat = ctx.grid.snap_floor(beat as f64, ctx.grid_step as f64) as f32;
+        }
+        let start_sample = (beat as f64 * ctx.samples_per_beat) as u64;
+        self.drop_target = Some((track_idx, start_sample));
+
+        let path = egui::DragAndDrop::payload::<PathBuf>(ui.ctx());
+        if path.is_none() && ui.input(|i| i.raw.hovered_files.is_empty()) {
+            return;
+        }
+        let x = ctx.rect.left() + (beat - ctx.start_beat) * self.pixels_per_beat;
+
+        let color = Palette::get(ui.ctx()).selection;
+        painter.rect_stroke(row_rect, 2.0, Stroke::new(1.5, color), egui::StrokeKind::Inside);
+        painter.line_segment([egui::pos2(x, row_rect.top()), egui::pos2(x, row_rect.bottom())], Stroke::new(2.0, color));
+
+        if let Some(path) = path.filter(|_| ui.input(|i| i.pointer.any_released())) {
+            *action = ArrangeAction::DropSample { track_idx, path: (*path).clone(), start_sample };
+        }
+    }
+
     pub(super) fn handle_click_to_seek(
         &self,
         response: &egui::Response,
@@ -64,16 +117,19 @@ impl ArrangePanel {
         let Some(pos) = response.interact_pointer_pos() else { return };
 
         let mut click_beat = ctx.start_beat + (pos.x - ctx.rect.left()) / self.pixels_per_beat;
-        if self.snap_to_grid {
-            click_beat = (click_beat / ctx.grid_step).round() * ctx.grid_step;
+        if ctx.grid.snap {
+            click_beat = ctx.grid.snap_round(click_beat as f64, ctx.grid_step as f64) as f32;
         }
         *action = ArrangeAction::Seek((click_beat as f64 * ctx.samples_per_beat) as u64);
     }
 
+    /// Right-click menu; returns a zoom command picked from it
     pub(super) fn handle_context_menu(
         response: &egui::Response,
         action: &mut ArrangeAction,
-    ) {
+        zoom_targets: &ZoomTargets,
+    ) -> Option<ZoomCommand> {
+        let mut zoom = None;
         response.context_menu(|ui| {
             if ui.button("Add Audio Track").clicked() {
                 *action = ArrangeAction::AddAudioTrack;
@@ -83,7 +139,49 @@ impl ArrangePanel {
                 *action = ArrangeAction::AddMidiTrack;
                 ui.close_menu();
             }
+            ui.separator();
+            for cmd in ZoomCommand::ALL {
+                if cmd.menu_button(ui, zoom_targets.available(cmd)) {
+                    zoom = Some(cmd);
+                }
+            }
         });
+        zoom
+    }
+
+    /// Step the zoom, or fit a range into the view; fitting the selected clip also
+    /// scrolls its track into view
+    pub(super) fn apply_zoom(
+        &mut self,
+        cmd: ZoomCommand,
+        targets: &ZoomTargets,
+        ctx: &ArrangeContext,
+        layout: &mut TrackLayout,
+    ) {
+        if let Some(factor) = cmd.step_factor() {
+            self.pixels_per_beat = (self.pixels_per_beat * factor).clamp(MIN_PIXELS_PER_BEAT, MAX_PIXELS_PER_BEAT);
+            return;
+        }
+        let range = match cmd {
+            ZoomCommand::FitAll => targets.song,
+            ZoomCommand::FitLoop => targets.loop_region,
+            _ => targets.selection.map(|(start, end, _)| (start, end)),
+        };
+        let Some((start, end)) = range else { return };
+        let (pixels_per_beat, scroll) = fit_range(start, end, ctx.rect.width(), MIN_PIXELS_PER_BEAT, MAX_PIXELS_PER_BEAT);
+        self.pixels_per_beat = pixels_per_beat;
+        self.scroll_offset_beats = scroll as f32;
+
+        if cmd == ZoomCommand::FitSelection
+            && let Some(row) = targets.selection.and_then(|(_, _, track_idx)| ctx.rows.get(track_idx))
+        {
+            let area = ctx.track_area();
+            if row.top() < area.top() {
+                layout.scroll -= area.top() - row.top();
+            } else if row.bottom() > area.bottom() {
+                layout.scroll += (row.bottom() - area.bottom()).min(row.top() - area.top());
+            }
+        }
     }
 
     /// Check if pointer is near a loop edge; returns which edge
@@ -167,7 +265,9 @@ impl ArrangePanel {
         true
     }
 
-    pub(super) fn handle_scroll_zoom(&mut self, ui: &Ui, rect: Rect) {
+    /// Mouse wheel over the arrange view: Ctrl/Cmd zooms horizontally, Alt zooms the track
+    /// heights, Shift scrolls horizontally and the plain wheel scrolls the tracks
+    pub(super) fn handle_scroll_zoom(&mut self, ui: &Ui, rect: Rect, layout: &mut TrackLayout) {
         let pointer_in_rect = ui.ctx().input(|i| {
             i.pointer.hover_pos().map(|p| rect.contains(p)).unwrap_or(false)
         });
@@ -190,9 +290,13 @@ impl ArrangePanel {
         if scroll_y.abs() > 0.1 {
             if modifiers.ctrl || modifiers.command {
                 let zoom_factor = 1.0 + scroll_y * 0.02;
-                self.pixels_per_beat = (self.pixels_per_beat * zoom_factor).clamp(10.0, 200.0);
-            } else {
+                self.pixels_per_beat = (self.pixels_per_beat * zoom_factor).clamp(MIN_PIXELS_PER_BEAT, MAX_PIXELS_PER_BEAT);
+            } else if modifiers.alt {
+                layout.set_zoom(layout.zoom() * (1.0 + scroll_y * 0.02));
+            } else if modifiers.shift {
                 self.scroll_offset_beats = (self.scroll_offset_beats - scroll_y / self.pixels_per_beat).max(0.0);
+            } else {
+                layout.scroll -= scroll_y;
             }
         }
 
@@ -0,0 +1,52 @@
+//! Static layers of the arrange view - grid lines and ruler ticks - tessellated once and
+//! reused until the view scrolls, zooms or changes colour. egui can't draw into a
+//! texture, so a layer is kept as a ready-made mesh, the closest it has to one.
+
+use std::sync::Arc;
+
+use egui::epaint::{Mesh, Tessellator};
+use egui::{Painter, Pos2, Shape, Stroke};
+
+/// A line of a layer
+pub(super) type Segment = ([Pos2; 2], Stroke);
+
+#[derive(Default)]
+pub(super) struct CachedLayer {
+    /// What the mesh was built from
+    segments: Vec<Segment>,
+    pixels_per_point: f32,
+    mesh: Arc<Mesh>,
+}
+
+impl CachedLayer {
+    /// Paint `segments`, tessellating them only if they differ from last time
+    pub fn paint(&mut self, painter: &Painter, segments: Vec<Segment>) {
+        let ctx = painter.ctx();
+        let pixels_per_point = ctx.pixels_per_point();
+        if segments != self.segments || pixels_per_point != self.pixels_per_point {
+            let mut tessellator = Tessellator::new(
+                pixels_per_point,
+                ctx.tessellation_options(|options| *options),
+                ctx.fonts(|fonts| fonts.font_image_size()),
+                Vec::new(),
+            );
+            let mut mesh = Mesh::default();
+            for &(points, stroke) in &segments {
+                tessellator.tessellate_shape(Shape::line_segment(points, stroke), &mut mesh);
+            }
+            self.mesh = Arc::new(mesh);
+            self.segments = segments;
+            self.pixels_per_point = pixels_per_point;
+        }
+        if !self.mesh.is_empty() {
+            painter.add(Shape::Mesh(self.mesh.clone()));
+        }
+    }
+}
+
+/// The arrange view's cached layers
+#[derive(Default)]
+pub(super) struct Layers {
+    pub grid: CachedLayer,
+    pub ruler_ticks: CachedLayer,
+}
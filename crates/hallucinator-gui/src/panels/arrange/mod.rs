@@ -1,12 +1,23 @@
 //! Arrange panel - timeline grid with clips in bars:beats
 
+mod clip_drag;
+mod crossfades;
 mod drawing;
 mod input;
+mod layers;
+mod overview;
+mod scrollbar;
 mod types;
 
+pub use overview::OVERVIEW_HEIGHT;
 pub use types::ArrangeAction;
-use types::{ArrangeContext, LoopEdge};
+use clip_drag::SnapMarkers;
+use layers::Layers;
+use overview::OverviewMarkers;
+use scrollbar::SCROLLBAR_HEIGHT;
+use types::{content_end_sample, ArrangeContext, ClipDrag, CrossfadeDrag, LoopEdge, ZoomTargets};
 
+use std::collections::HashMap;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
@@ -15,21 +26,42 @@ use egui::{Rect, Sense, Ui, Vec2};
 use hallucinator_core::ClipId;
 use hallucinator_services::{AudioEngine, EngineState};
 
+use super::grid::GridSettings;
 use super::timeline::RecordingPreview;
+use super::track_layout::TrackLayout;
+use super::zoom::ZoomCommand;
+
+/// Horizontal zoom limits
+const MIN_PIXELS_PER_BEAT: f32 = 10.0;
+const MAX_PIXELS_PER_BEAT: f32 = 200.0;
 
 /// Arrange panel state
 pub struct ArrangePanel {
     pub pixels_per_beat: f32,
     pub scroll_offset_beats: f32,
-    pub track_height: f32,
-    pub vertical_scroll: f32,
-    pub snap_to_grid: bool,
+    /// Grid note value, feel, snap and swing
+    pub grid: GridSettings,
     /// Loop selection drag state (start_beat when dragging)
     loop_drag_start: Option<f32>,
     /// Current loop selection being drawn (start_beat, end_beat)
     loop_selection: Option<(f32, f32)>,
     /// Which loop edge is being resized, plus the opposite edge's beat position
     loop_edge_drag: Option<(LoopEdge, f32)>,
+    /// Track (None = below the last one) and snapped start sample under the pointer,
+    /// where a dropped file is placed
+    drop_target: Option<(Option<usize>, u64)>,
+    /// Overview drag state (beats from the view start to the grab point, strip span in beats)
+    overview_drag: Option<(f32, f32)>,
+    /// Scrollbar drag state (pixels from the thumb's left edge to the grab point, span in beats)
+    scrollbar_drag: Option<(f32, f32)>,
+    /// Clip being moved or trimmed
+    clip_drag: Option<ClipDrag>,
+    /// Crossfade whose length or curve is being dragged
+    crossfade_drag: Option<CrossfadeDrag>,
+    /// Clips shift-clicked into a selection: track and the span in samples they lie in
+    clip_range: Option<(usize, u64, u64)>,
+    /// Grid and ruler ticks, kept tessellated between frames
+    layers: Layers,
 }
 
 impl Default for ArrangePanel {
@@ -43,15 +75,25 @@ impl ArrangePanel {
         Self {
             pixels_per_beat: 40.0,
             scroll_offset_beats: 0.0,
-            track_height: 80.0,
-            vertical_scroll: 0.0,
-            snap_to_grid: true,
+            grid: GridSettings::auto(),
             loop_drag_start: None,
             loop_selection: None,
             loop_edge_drag: None,
+            drop_target: None,
+            overview_drag: None,
+            scrollbar_drag: None,
+            clip_drag: None,
+            crossfade_drag: None,
+            clip_range: None,
+            layers: Layers::default(),
         }
     }
 
+    /// Where a file dropped from outside the app lands (as of the last frame)
+    pub fn drop_target(&self) -> Option<(Option<usize>, u64)> {
+        self.drop_target
+    }
+
     pub fn ui(
         &mut self,
         ui: &mut Ui,
@@ -60,13 +102,21 @@ impl ArrangePanel {
         selected_track_idx: Option<usize>,
         selected_clip: Option<(usize, ClipId)>,
         recording_preview: Option<RecordingPreview>,
+        loading_clips: &HashMap<ClipId, f32>,
         _clipboard: &DawClipboard,
+        layout: &mut TrackLayout,
     ) -> ArrangeAction {
         let mut action = ArrangeAction::None;
 
         let available_rect = ui.available_rect_before_wrap();
         let (response, painter) = ui.allocate_painter(available_rect.size(), Sense::click_and_drag());
-        let rect = response.rect;
+        // Overview strip on top, then the ruler and tracks, and the scrollbar at the bottom
+        let overview_rect = Rect::from_min_size(response.rect.min, Vec2::new(response.rect.width(), OVERVIEW_HEIGHT));
+        let scrollbar_rect = Rect::from_min_max(
+            egui::pos2(response.rect.left(), response.rect.bottom() - SCROLLBAR_HEIGHT),
+            response.rect.max,
+        );
+        let rect = Rect::from_min_max(overview_rect.left_bottom(), scrollbar_rect.right_top());
 
         let Ok(timeline) = state.timeline.lock() else {
             return action;
@@ -93,7 +143,13 @@ impl ArrangePanel {
         } else {
             0.0
         };
-        let grid_step = if subdivision > 0.0 { 1.0 / subdivision } else { time_sig_num as f32 };
+        let ruler_step = if subdivision > 0.0 { 1.0 / subdivision } else { time_sig_num as f32 };
+        // Auto follows the ruler; a fixed grid value overrides it
+        let grid_step = self.grid.step(ruler_step as f64) as f32;
+
+        // Rows scroll under the ruler, in step with the track headers
+        let track_area = Rect::from_min_max(egui::pos2(rect.left(), rect.top() + ruler_height), rect.max);
+        layout.clamp_scroll(&timeline.tracks, track_area.height());
 
         let ctx = ArrangeContext {
             rect,
@@ -102,20 +158,29 @@ impl ArrangePanel {
             start_beat,
             end_beat: start_beat + beats_visible,
             samples_per_beat,
+            ruler_step,
+            pixels_per_ruler: self.pixels_per_beat * ruler_step,
             grid_step,
             pixels_per_grid: self.pixels_per_beat * grid_step,
+            grid: self.grid,
             time_sig_num,
+            rows: layout.rows(track_area, &timeline.tracks),
+            new_row_height: layout.new_row_height(),
         };
 
-        // Drawing layers (order matters)
-        self.draw_track_backgrounds(&painter, &ctx, &timeline.tracks, selected_track_idx);
+        // Drawing layers (order matters); rows scrolled up are cut off at the ruler
+        let track_painter = painter.with_clip_rect(track_area);
+        self.draw_track_backgrounds(&track_painter, &ctx, selected_track_idx);
         self.draw_grid(&painter, &ctx);
         self.draw_ruler(&painter, &ctx);
 
-        let clip_action = self.draw_clips(&painter, ui, &ctx, &timeline.tracks, selected_clip, &recording_preview);
+        let clip_action = self.draw_clips(
+            &track_painter, ui, &ctx, &timeline.tracks, selected_clip, &recording_preview, loading_clips,
+        );
         if !matches!(clip_action, ArrangeAction::None) {
             action = clip_action;
         }
+        self.draw_crossfades(&track_painter, ui, &ctx, &timeline.tracks, &mut action);
 
         let loop_enabled = timeline.transport.loop_enabled;
         let loop_start = timeline.transport.loop_start;
@@ -125,13 +190,34 @@ impl ArrangePanel {
             self.draw_loop_region(&painter, &ctx, loop_start, loop_end);
         }
 
-        drop(timeline);
+        // The overview and scrollbar span the song plus a bar, or up to the view's end when
+        // scrolled past it
+        let content_end = content_end_sample(&timeline.tracks).into_iter()
+            .chain(loop_enabled.then_some(loop_end))
+            .max()
+            .map_or(0.0, |end| end as f64 / samples_per_beat) as f32;
+        let span_beats = (content_end + time_sig_num as f32).max(ctx.end_beat);
 
-        // Playhead (after dropping lock — uses atomic)
         let position_samples = state.position.load(Ordering::SeqCst);
+        let markers = OverviewMarkers {
+            span_beats,
+            position_samples,
+            loop_region: loop_enabled.then_some((loop_start, loop_end)),
+        };
+        self.draw_overview(ui, &painter, overview_rect, &ctx, &timeline.tracks, markers);
+        let snap_markers = SnapMarkers { position_samples, loop_region: (loop_start, loop_end) };
+        self.handle_clip_drag(ui, &ctx, &timeline.tracks, &snap_markers, &mut action);
+        let zoom_targets = ZoomTargets::new(&timeline.tracks, (loop_start, loop_end), selected_clip, samples_per_beat);
+
+        drop(timeline);
+
         self.draw_playhead(&painter, &ctx, position_samples);
+        self.draw_position_readout(ui, &painter, &ctx, position_samples);
+        self.draw_scrollbar(ui, &painter, scrollbar_rect, &ctx, span_beats);
 
         // Input handling
+        self.handle_sample_drop(ui, &painter, &ctx, &mut action);
+
         let ctrl_held = ui.input(|i| i.modifiers.ctrl || i.modifiers.command);
 
         // Loop edge resize takes priority over new loop selection
@@ -150,8 +236,12 @@ impl ArrangePanel {
         }
         self.draw_loop_selection_overlay(&painter, &ctx);
         self.handle_click_to_seek(&response, &ctx, &mut action);
-        Self::handle_context_menu(&response, &mut action);
-        self.handle_scroll_zoom(ui, rect);
+        // Zoom shortcuts the piano roll under the pointer didn't take apply here
+        let menu_zoom = Self::handle_context_menu(&response, &mut action, &zoom_targets);
+        if let Some(cmd) = menu_zoom.or_else(|| ZoomCommand::consume(ui)) {
+            self.apply_zoom(cmd, &zoom_targets, &ctx, layout);
+        }
+        self.handle_scroll_zoom(ui, rect, layout);
 
         action
     }
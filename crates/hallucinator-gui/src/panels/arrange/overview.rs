@@ -0,0 +1,110 @@
+//! Overview strip above the ruler: the whole arrangement in miniature with the visible range
+
+use egui::{Rect, Sense, Stroke, Ui};
+use hallucinator_core::Track;
+
+use super::types::ArrangeContext;
+use super::ArrangePanel;
+use crate::panels::theme::{with_alpha, Palette};
+
+/// Height of the overview strip
+pub const OVERVIEW_HEIGHT: f32 = 18.0;
+
+/// What the overview shows besides the clips
+pub(super) struct OverviewMarkers {
+    /// Beats across the strip
+    pub span_beats: f32,
+    pub position_samples: u64,
+    /// Loop region in samples, when looping
+    pub loop_region: Option<(u64, u64)>,
+}
+
+impl ArrangePanel {
+    /// Draw every track's clips across the strip with a box around the visible range;
+    /// clicking or dragging it scrolls the arrange view there
+    pub(super) fn draw_overview(
+        &mut self,
+        ui: &Ui,
+        painter: &egui::Painter,
+        rect: Rect,
+        ctx: &ArrangeContext,
+        tracks: &[Track],
+        markers: OverviewMarkers,
+    ) {
+        let beats_visible = ctx.end_beat - ctx.start_beat;
+        let to_beats = |start: u64, end: u64| (start as f64 / ctx.samples_per_beat, end as f64 / ctx.samples_per_beat);
+
+        // The strip keeps the span it had when a drag started
+        let total_beats = self.overview_drag.map_or(markers.span_beats, |(_, total)| total);
+        let pixels_per_beat = rect.width() / total_beats;
+        let beat_x = |beat: f64| rect.left() + beat as f32 * pixels_per_beat;
+
+        let palette = Palette::get(ui.ctx());
+        painter.rect_filled(rect, 0.0, palette.panel);
+
+        if let Some((start, end)) = markers.loop_region {
+            let (start, end) = to_beats(start, end);
+            let loop_rect = Rect::from_x_y_ranges(beat_x(start)..=beat_x(end), rect.y_range());
+            painter.rect_filled(loop_rect, 0.0, with_alpha(palette.loop_region, 40));
+        }
+
+        // One lane per track, at least a pixel high
+        let lane_height = ((rect.height() - 4.0) / tracks.len().max(1) as f32).max(1.0);
+        for (idx, track) in tracks.iter().enumerate() {
+            let top = rect.top() + 2.0 + idx as f32 * lane_height;
+            if top >= rect.bottom() - 2.0 {
+                break;
+            }
+            let lane = |(start, end): (f64, f64)| {
+                Rect::from_x_y_ranges(beat_x(start)..=beat_x(end).max(beat_x(start) + 1.0), top..=top + (lane_height - 1.0).max(1.0))
+            };
+            for clip in &track.clips {
+                painter.rect_filled(lane(to_beats(clip.start_sample, clip.end_sample())), 0.0, palette.audio_clip);
+            }
+            for clip in &track.midi_clips {
+                painter.rect_filled(lane(to_beats(clip.start_sample, clip.end_sample())), 0.0, palette.midi_clip);
+            }
+        }
+
+        let playhead_x = beat_x(markers.position_samples as f64 / ctx.samples_per_beat);
+        if rect.x_range().contains(playhead_x) {
+            painter.line_segment(
+                [egui::pos2(playhead_x, rect.top()), egui::pos2(playhead_x, rect.bottom())],
+                Stroke::new(1.0, palette.playhead),
+            );
+        }
+
+        let viewport = Rect::from_x_y_ranges(
+            beat_x(ctx.start_beat as f64)..=beat_x(ctx.end_beat as f64).min(rect.right()),
+            rect.y_range(),
+        );
+        painter.rect_filled(viewport, 2.0, with_alpha(palette.text, 20));
+        painter.rect_stroke(viewport, 2.0, Stroke::new(1.0, palette.text), egui::StrokeKind::Inside);
+
+        // Grab the box where it was clicked; clicking outside it centres the view on the pointer
+        let response = ui.interact(rect, ui.id().with("arrange_overview"), Sense::click_and_drag());
+        let pointer_beat = |pos: egui::Pos2| (pos.x - rect.left()) / pixels_per_beat;
+        if response.drag_started()
+            && let Some(pos) = response.interact_pointer_pos()
+        {
+            let grab = if viewport.contains(pos) { pointer_beat(pos) - ctx.start_beat } else { beats_visible / 2.0 };
+            self.overview_drag = Some((grab, total_beats));
+        }
+        if let Some(pos) = response.interact_pointer_pos() {
+            if let Some((grab, _)) = self.overview_drag.filter(|_| response.dragged()) {
+                self.scroll_offset_beats = (pointer_beat(pos) - grab).max(0.0);
+            } else if response.clicked() {
+                self.scroll_offset_beats = (pointer_beat(pos) - beats_visible / 2.0).max(0.0);
+            }
+        }
+        if response.drag_stopped() {
+            self.overview_drag = None;
+        }
+
+        if self.overview_drag.is_some() {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+        } else if response.hovered() {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::Grab);
+        }
+    }
+}
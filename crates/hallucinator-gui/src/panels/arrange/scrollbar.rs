@@ -0,0 +1,70 @@
+//! Horizontal scrollbar under the arrange grid
+
+use egui::{Rect, Sense, Ui};
+
+use super::types::ArrangeContext;
+use super::ArrangePanel;
+use crate::panels::theme::Palette;
+
+/// Height of the scrollbar
+pub const SCROLLBAR_HEIGHT: f32 = 12.0;
+/// Narrowest the thumb gets, so it stays grabbable when zoomed far in
+const MIN_THUMB_WIDTH: f32 = 16.0;
+
+impl ArrangePanel {
+    /// Thumb over the visible range of `span_beats`: drag it to scroll, click either side
+    /// of it to page there
+    pub(super) fn draw_scrollbar(
+        &mut self,
+        ui: &Ui,
+        painter: &egui::Painter,
+        rect: Rect,
+        ctx: &ArrangeContext,
+        span_beats: f32,
+    ) {
+        let beats_visible = ctx.end_beat - ctx.start_beat;
+        // The span holds still while the thumb is dragged
+        let total_beats = self.scrollbar_drag.map_or(span_beats, |(_, total)| total);
+        let scrollable = (total_beats - beats_visible).max(0.0);
+
+        // The thumb's minimum width takes up some of its travel
+        let thumb_width = (rect.width() * beats_visible / total_beats).max(MIN_THUMB_WIDTH);
+        let travel = (rect.width() - thumb_width).max(1.0);
+        let fraction = if scrollable > 0.0 { (ctx.start_beat / scrollable).min(1.0) } else { 0.0 };
+        let thumb = Rect::from_min_size(
+            egui::pos2(rect.left() + fraction * travel, rect.top()),
+            egui::vec2(thumb_width, rect.height()),
+        )
+        .shrink2(egui::vec2(0.0, 2.0));
+
+        let response = ui.interact(rect, ui.id().with("arrange_scrollbar"), Sense::click_and_drag());
+        if response.drag_started()
+            && let Some(pos) = response.interact_pointer_pos()
+        {
+            let grab = if thumb.contains(pos) { pos.x - thumb.left() } else { thumb_width / 2.0 };
+            self.scrollbar_drag = Some((grab, total_beats));
+        }
+        if let Some(pos) = response.interact_pointer_pos() {
+            if let Some((grab, _)) = self.scrollbar_drag.filter(|_| response.dragged()) {
+                self.scroll_offset_beats = ((pos.x - grab - rect.left()) / travel).clamp(0.0, 1.0) * scrollable;
+            } else if response.clicked() && !thumb.contains(pos) {
+                let page = if pos.x < thumb.left() { -beats_visible } else { beats_visible };
+                self.scroll_offset_beats = (self.scroll_offset_beats + page).max(0.0);
+            }
+        }
+        if response.drag_stopped() {
+            self.scrollbar_drag = None;
+        }
+
+        let palette = Palette::get(ui.ctx());
+        painter.rect_filled(rect, 0.0, palette.panel);
+        let thumb_color = if self.scrollbar_drag.is_some() {
+            palette.text
+        } else if response.hovered() {
+            palette.text_dim
+        } else {
+            palette.grid_bar
+        };
+        painter.rect_filled(thumb, 4.0, thumb_color);
+    }
+}
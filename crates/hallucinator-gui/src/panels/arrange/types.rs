@@ -1,5 +1,10 @@
+use std::path::PathBuf;
+
 use egui::Rect;
-use hallucinator_core::ClipId;
+use hallucinator_core::{ClipId, Track};
+
+use crate::panels::grid::GridSettings;
+use crate::panels::zoom::ZoomCommand;
 
 /// Which edge of the loop region is being dragged
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -8,6 +13,37 @@ pub enum LoopEdge {
     End,
 }
 
+/// What dragging a clip does, by where it was grabbed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClipDragMode {
+    Move,
+    /// Audio clips only: MIDI clips move when grabbed at their start
+    TrimStart,
+    TrimEnd,
+}
+
+/// Clip being dragged in the arrange view
+#[derive(Clone, Copy, Debug)]
+pub(super) struct ClipDrag {
+    pub track_idx: usize,
+    pub clip_id: ClipId,
+    pub mode: ClipDragMode,
+    /// Beat under the pointer where the drag started
+    pub grab_beat: f64,
+    /// Clip start and end in samples when the drag started
+    pub start_sample: u64,
+    pub end_sample: u64,
+}
+
+/// Crossfade being edited in the arrange view, with its values when the drag started
+#[derive(Clone, Copy, Debug)]
+pub(super) struct CrossfadeDrag {
+    /// Edge grabbed to change the length (-1.0 left, 1.0 right), None to bend the curve
+    pub edge: Option<f32>,
+    pub length: u64,
+    pub curve: f32,
+}
+
 /// Action returned from arrange panel
 #[derive(Clone)]
 pub enum ArrangeAction {
@@ -15,10 +51,27 @@ pub enum ArrangeAction {
     SelectClip { track_idx: usize, clip_id: ClipId },
     OpenClipEditor { track_idx: usize, clip_id: ClipId },
     DeleteClip { track_idx: usize, clip_id: ClipId },
+    /// Render a MIDI clip through its track's instrument and effects to an audio clip
+    BounceClip { track_idx: usize, clip_id: ClipId },
+    /// Glue the audio (rendered) or MIDI (merged) clips within a span of a track into one
+    ConsolidateClips { track_idx: usize, start_sample: u64, end_sample: u64, midi: bool },
+    /// Add a clip's timing and velocity feel to the groove pool
+    ExtractGroove { track_idx: usize, clip_id: ClipId },
+    /// Turn an audio clip's hits into notes of `pitch` on a drum track
+    ExtractDrumHits { track_idx: usize, clip_id: ClipId, pitch: u8 },
     Seek(u64),
     AddAudioTrack,
     AddMidiTrack,
     SetLoopRegion { start_sample: u64, end_sample: u64 },
+    /// Clip dragged to a new start on its track
+    MoveClip { track_idx: usize, clip_id: ClipId, start_sample: u64 },
+    /// Clip edge dragged: audio clips keep their audio in place, MIDI clips only change length
+    TrimClip { track_idx: usize, clip_id: ClipId, start_sample: u64, end_sample: u64 },
+    /// Crossfade between two overlapping audio clips resized or bent
+    EditCrossfade { track_idx: usize, out_clip: ClipId, in_clip: ClipId, length: u64, curve: f32 },
+    /// Sample file dropped from the browser: place it as an audio clip on the track
+    /// (None = below the last track, on a new audio track)
+    DropSample { track_idx: Option<usize>, path: PathBuf, start_sample: u64 },
 }
 
 /// Shared layout/timing context for a single arrange panel frame.
@@ -30,7 +83,73 @@ pub(super) struct ArrangeContext {
     pub start_beat: f32,
     pub end_beat: f32,
     pub samples_per_beat: f64,
+    /// Ruler tick spacing, from the zoom level
+    pub ruler_step: f32,
+    pub pixels_per_ruler: f32,
+    /// Grid line and snap spacing, from the grid menu
     pub grid_step: f32,
     pub pixels_per_grid: f32,
+    pub grid: GridSettings,
     pub time_sig_num: u8,
+    /// Screen rect of each track's row, scrolled and sized by the track layout
+    pub rows: Vec<Rect>,
+    /// Height of the row a new track would get
+    pub new_row_height: f32,
+}
+
+impl ArrangeContext {
+    /// Area below the ruler the track rows scroll in
+    pub fn track_area(&self) -> Rect {
+        Rect::from_min_max(egui::pos2(self.rect.left(), self.track_area_top), self.rect.max)
+    }
+}
+
+/// End of the last audio or MIDI clip, in samples
+pub(super) fn content_end_sample(tracks: &[Track]) -> Option<u64> {
+    tracks.iter()
+        .flat_map(|t| t.clips.iter().map(|c| c.end_sample()).chain(t.midi_clips.iter().map(|c| c.end_sample())))
+        .max()
+}
+
+/// Beat ranges the zoom commands fit into the view, gathered while the timeline is locked
+pub(super) struct ZoomTargets {
+    /// Song start to the end of the last clip
+    pub song: Option<(f64, f64)>,
+    pub loop_region: Option<(f64, f64)>,
+    /// Selected clip's range and its track index
+    pub selection: Option<(f64, f64, usize)>,
+}
+
+impl ZoomTargets {
+    pub fn new(
+        tracks: &[Track],
+        loop_region: (u64, u64),
+        selected_clip: Option<(usize, ClipId)>,
+        samples_per_beat: f64,
+    ) -> Self {
+        let beats = |start: u64, end: u64| (start as f64 / samples_per_beat, end as f64 / samples_per_beat);
+        let selection = selected_clip.and_then(|(track_idx, clip_id)| {
+            let track = tracks.get(track_idx)?;
+            let (start, end) = track.clips.iter()
+                .find(|c| c.id == clip_id)
+                .map(|c| beats(c.start_sample, c.end_sample()))
+                .or_else(|| track.midi_clips.iter().find(|c| c.id == clip_id).map(|c| beats(c.start_sample, c.end_sample())))?;
+            Some((start, end, track_idx))
+        });
+        Self {
+            song: content_end_sample(tracks).map(|end| beats(0, end)),
+            loop_region: (loop_region.1 > loop_region.0).then(|| beats(loop_region.0, loop_region.1)),
+            selection,
+        }
+    }
+
+    /// Whether the command has a range to fit (step zoom always applies)
+    pub fn available(&self, cmd: ZoomCommand) -> bool {
+        match cmd {
+            ZoomCommand::ZoomIn | ZoomCommand::ZoomOut => true,
+            ZoomCommand::FitAll => self.song.is_some(),
+            ZoomCommand::FitLoop => self.loop_region.is_some(),
+            ZoomCommand::FitSelection => self.selection.is_some(),
+        }
+    }
 }
@@ -0,0 +1,99 @@
+//! Audio settings panel - the cue (headphone) bus output and sources, and the metronome level,
+//! shown in the Audio tab of the preferences
+
+use egui::Ui;
+use hallucinator_services::{AudioOutputService, CueOutput, CueSettings};
+
+/// Action returned from the audio settings panel
+pub enum AudioSettingsAction {
+    None,
+    SetCue(CueSettings),
+    SetMetronomeVolume(f32),
+}
+
+pub struct AudioSettingsPanel {
+    /// Output devices offered for the cue, listed when the panel is first shown
+    devices: Option<Vec<String>>,
+}
+
+impl AudioSettingsPanel {
+    pub fn new() -> Self {
+        Self { devices: None }
+    }
+
+    /// `output_channels` are the main device's, whose pairs past Out 1/2 can carry the cue
+    pub fn ui(&mut self, ui: &mut Ui, cue: &CueSettings, metronome_volume: f32, output_channels: u16) -> AudioSettingsAction {
+        let mut action = AudioSettingsAction::None;
+        let devices = self.devices.get_or_insert_with(|| {
+            AudioOutputService::list_devices()
+                .inspect_err(|e| tracing::warn!("Failed to list output devices: {}", e))
+                .unwrap_or_default()
+        });
+
+        let mut rescan = false;
+        ui.heading("Cue bus");
+        ui.horizontal(|ui| {
+            ui.label("Output");
+            egui::ComboBox::from_id_salt("cue_output")
+                .selected_text(cue.output.label())
+                .width(220.0)
+                .show_ui(ui, |ui| {
+                    let pairs = (2..output_channels.saturating_sub(1)).step_by(2).map(CueOutput::Channels);
+                    let choices = std::iter::once(CueOutput::Main)
+                        .chain(pairs)
+                        .chain(devices.iter().cloned().map(CueOutput::Device));
+                    for output in choices {
+                        let label = match &output {
+                            CueOutput::Device(name) => format!("Device: {name}"),
+                            other => other.label(),
+                        };
+                        if ui.selectable_label(cue.output == output, label).clicked() {
+                            action = AudioSettingsAction::SetCue(CueSettings { output, ..cue.clone() });
+                        }
+                    }
+                });
+            rescan = ui.small_button("⟳").on_hover_text("List output devices again").clicked();
+        });
+        if rescan {
+            self.devices = None;
+        }
+
+        let separate = cue.output != CueOutput::Main;
+        ui.add_enabled_ui(separate, |ui| {
+            ui.label("On the cue bus instead of the main mix:");
+            let mut settings = cue.clone();
+            let mut changed = ui.checkbox(&mut settings.preview, "Browser audition").changed();
+            changed |= ui.checkbox(&mut settings.metronome, "Metronome").changed();
+            changed |= ui.checkbox(&mut settings.solo, "Soloed tracks (pre-listen)")
+                .on_hover_text("Solo copies tracks to the cue bus and leaves the main mix untouched")
+                .changed();
+            ui.horizontal(|ui| {
+                ui.label("Cue level");
+                changed |= ui.add(egui::Slider::new(&mut settings.volume, 0.0..=2.0)).changed();
+            });
+            if changed {
+                action = AudioSettingsAction::SetCue(settings);
+            }
+        })
+        .response
+        .on_disabled_hover_text("Pick a cue output to route sources to it");
+
+        ui.separator();
+        ui.heading("Metronome");
+        ui.horizontal(|ui| {
+            ui.label("Level");
+            let mut volume = metronome_volume;
+            if ui.add(egui::Slider::new(&mut volume, 0.0..=1.0)).changed() {
+                action = AudioSettingsAction::SetMetronomeVolume(volume);
+            }
+        });
+
+        action
+    }
+}
+
+impl Default for AudioSettingsPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
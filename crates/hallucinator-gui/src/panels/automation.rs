@@ -0,0 +1,315 @@
+//! Automation panel - breakpoint lanes for the selected track's device parameters
+//!
+//! Click empty space to add a point (snapped to 1/16 notes, Shift for free placement),
+//! drag points to move them and right-click one for its curve shape or to delete it.
+//! Exponential and S-curve segments have a tension handle that drags up and down.
+
+use egui::{Color32, Pos2, ScrollArea, Sense, Stroke, Ui, Vec2};
+use hallucinator_core::{Automation, AutomationLane, AutomationPoint, CurveShape};
+
+use super::MappableDevice;
+
+const LANE_HEIGHT: f32 = 160.0;
+const POINT_RADIUS: f32 = 4.0;
+/// Grid points snap to, in beats (1/16 notes)
+const SNAP_BEATS: f64 = 0.25;
+/// Beats shown past the last point
+const TRAILING_BEATS: f64 = 8.0;
+/// Line segments drawn per curved segment
+const CURVE_STEPS: usize = 24;
+
+/// Action returned from the automation panel
+pub enum AutomationAction {
+    None,
+    AddLane(AutomationLane),
+    RemoveLane(usize),
+    SetEnabled(usize, bool),
+    AddPoint(usize, AutomationPoint),
+    MovePoint { lane: usize, index: usize, beat: f64, value: f32 },
+    RemovePoint(usize, usize),
+    SetShape(usize, usize, CurveShape),
+    SetTension(usize, usize, f32),
+}
+
+/// What a drag in the lane is moving
+#[derive(Clone, Copy)]
+enum Drag {
+    Point(usize),
+    /// Tension of the segment after this point, with the tension and pointer y at the start
+    Tension { index: usize, start_tension: f32, start_y: f32 },
+}
+
+/// Automation lane editor (operates on the selected track)
+pub struct AutomationPanel {
+    selected_lane: usize,
+    drag: Option<Drag>,
+}
+
+impl AutomationPanel {
+    pub fn new() -> Self {
+        Self { selected_lane: 0, drag: None }
+    }
+
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        track_name: Option<&str>,
+        automation: Option<&Automation>,
+        devices: &[MappableDevice],
+        playhead_beat: f64,
+    ) -> AutomationAction {
+        let mut action = AutomationAction::None;
+
+        let (Some(track_name), Some(automation)) = (track_name, automation) else {
+            ui.centered_and_justified(|ui| {
+                ui.label("Select a track to edit automation");
+            });
+            return action;
+        };
+        self.selected_lane = self.selected_lane.min(automation.lanes.len().saturating_sub(1));
+
+        ui.horizontal(|ui| {
+            ui.strong(track_name);
+            ui.separator();
+            let lane_label = |lane: &AutomationLane| {
+                let device = devices
+                    .iter()
+                    .find(|d| d.target == lane.target)
+                    .map(|d| d.name.as_str())
+                    .unwrap_or("(missing)");
+                format!("{} · {}", device, lane.param)
+            };
+            let selected_text = automation.lanes.get(self.selected_lane).map(lane_label).unwrap_or_default();
+            egui::ComboBox::from_id_salt("automation_lane")
+                .selected_text(selected_text)
+                .width(200.0)
+                .show_ui(ui, |ui| {
+                    for (idx, lane) in automation.lanes.iter().enumerate() {
+                        ui.selectable_value(&mut self.selected_lane, idx, lane_label(lane));
+                    }
+                });
+            ui.menu_button("+ Lane", |ui| {
+                if devices.is_empty() {
+                    ui.label("No devices on this track");
+                }
+                for device in devices {
+                    ui.menu_button(&device.name, |ui| {
+                        ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                            for param in &device.params {
+                                if ui.button(&param.name).clicked() {
+                                    let lane = AutomationLane::new(device.target, &param.name, param.min, param.max);
+                                    // Selects the new lane, or the existing one for this parameter
+                                    self.selected_lane = automation.lanes.iter()
+                                        .position(|l| l.target == lane.target && l.param == lane.param)
+                                        .unwrap_or(automation.lanes.len());
+                                    action = AutomationAction::AddLane(lane);
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+                }
+            });
+            if let Some(lane) = automation.lanes.get(self.selected_lane) {
+                let mut enabled = lane.enabled;
+                if ui.checkbox(&mut enabled, "Read").on_hover_text("Play this lane's automation").changed() {
+                    action = AutomationAction::SetEnabled(self.selected_lane, enabled);
+                }
+                if ui.small_button("×").on_hover_text("Remove lane").clicked() {
+                    action = AutomationAction::RemoveLane(self.selected_lane);
+                }
+            }
+        });
+        ui.separator();
+
+        let Some(lane) = automation.lanes.get(self.selected_lane) else {
+            ui.label("Add a lane for one of the track's device parameters");
+            return action;
+        };
+        let lane_action = self.lane_ui(ui, self.selected_lane, lane, playhead_beat);
+        if !matches!(lane_action, AutomationAction::None) {
+            action = lane_action;
+        }
+        action
+    }
+
+    /// The lane's curve and its point and tension handles
+    fn lane_ui(&mut self, ui: &mut Ui, lane_idx: usize, lane: &AutomationLane, playhead_beat: f64) -> AutomationAction {
+        let mut action = AutomationAction::None;
+
+        let last_beat = lane.points.last().map_or(0.0, |p| p.beat);
+        let span = ((last_beat + TRAILING_BEATS).max(16.0) / 4.0).ceil() * 4.0;
+        let (response, painter) = ui.allocate_painter(Vec2::new(ui.available_width(), LANE_HEIGHT), Sense::click_and_drag());
+        let rect = response.rect.shrink2(Vec2::new(POINT_RADIUS, POINT_RADIUS));
+
+        let to_screen = |beat: f64, value: f32| {
+            Pos2::new(
+                rect.left() + (beat / span) as f32 * rect.width(),
+                rect.bottom() - value * rect.height(),
+            )
+        };
+        let from_screen = |pos: Pos2| {
+            let beat = ((pos.x - rect.left()) / rect.width()) as f64 * span;
+            let value = (rect.bottom() - pos.y) / rect.height();
+            (beat.max(0.0), value.clamp(0.0, 1.0))
+        };
+
+        // Background, bar lines and playhead
+        painter.rect_filled(response.rect, 2.0, Color32::from_gray(28));
+        for bar in 0..=(span / 4.0) as usize {
+            let x = to_screen(bar as f64 * 4.0, 0.0).x;
+            painter.line_segment([Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())], Stroke::new(1.0, Color32::from_gray(50)));
+        }
+        let playhead_x = to_screen(playhead_beat, 0.0).x;
+        if rect.x_range().contains(playhead_x) {
+            painter.line_segment(
+                [Pos2::new(playhead_x, rect.top()), Pos2::new(playhead_x, rect.bottom())],
+                Stroke::new(1.0, Color32::from_rgb(255, 200, 80)),
+            );
+        }
+        let curve_color = if lane.enabled { Color32::from_rgb(120, 200, 255) } else { Color32::from_gray(110) };
+
+        if lane.points.is_empty() {
+            painter.text(rect.center(), egui::Align2::CENTER_CENTER, "Click to add points", egui::FontId::proportional(12.0), Color32::GRAY);
+        } else {
+            // Curve, sampled across each segment, flat before the first and after the last point
+            let mut line = vec![to_screen(0.0, lane.points[0].value)];
+            for pair in lane.points.windows(2) {
+                let (from, to) = (pair[0], pair[1]);
+                line.push(to_screen(from.beat, from.value));
+                if from.shape == CurveShape::Hold {
+                    line.push(to_screen(to.beat, from.value));
+                } else if from.shape != CurveShape::Linear {
+                    for step in 1..CURVE_STEPS {
+                        let t = step as f32 / CURVE_STEPS as f32;
+                        let beat = from.beat + (to.beat - from.beat) * t as f64;
+                        line.push(to_screen(beat, from.value + (to.value - from.value) * from.shape.apply(from.tension, t)));
+                    }
+                }
+            }
+            let last = lane.points[lane.points.len() - 1];
+            line.push(to_screen(last.beat, last.value));
+            line.push(to_screen(span, last.value));
+            painter.add(egui::Shape::line(line, Stroke::new(1.5, curve_color)));
+        }
+
+        // Handles: each point, and a tension handle on segments whose shape bends
+        let pointer = response.interact_pointer_pos().or(response.hover_pos());
+        let near = |pos: Pos2| pointer.is_some_and(|p| p.distance(pos) <= POINT_RADIUS * 2.0);
+        let mut hovered_point = None;
+        let mut hovered_tension = None;
+        for (idx, point) in lane.points.iter().enumerate() {
+            let pos = to_screen(point.beat, point.value);
+            if near(pos) {
+                hovered_point = Some(idx);
+            }
+            let fill = if hovered_point == Some(idx) { Color32::WHITE } else { curve_color };
+            painter.circle_filled(pos, POINT_RADIUS, fill);
+
+            if let Some(handle) = tension_handle(lane, idx).map(|(beat, value)| to_screen(beat, value)) {
+                if near(handle) && hovered_point.is_none() {
+                    hovered_tension = Some(idx);
+                }
+                painter.circle_stroke(handle, POINT_RADIUS - 1.0, Stroke::new(1.5, curve_color));
+            }
+        }
+
+        if response.drag_started() {
+            self.drag = match (hovered_point, hovered_tension, pointer) {
+                (Some(idx), _, _) => Some(Drag::Point(idx)),
+                (None, Some(index), Some(pos)) => {
+                    Some(Drag::Tension { index, start_tension: lane.points[index].tension, start_y: pos.y })
+                }
+                _ => None,
+            };
+        }
+        if response.dragged()
+            && let (Some(drag), Some(pos)) = (self.drag, pointer)
+        {
+            match drag {
+                Drag::Point(index) => {
+                    let (beat, value) = from_screen(pos);
+                    let beat = snap(beat, ui.input(|i| i.modifiers.shift));
+                    action = AutomationAction::MovePoint { lane: lane_idx, index, beat, value };
+                }
+                Drag::Tension { index, start_tension, start_y } => {
+                    // Dragging up raises the middle of the segment, whichever way it runs
+                    let rising = lane.points.get(index + 1).is_some_and(|next| next.value >= lane.points[index].value);
+                    let delta = (start_y - pos.y) / rect.height() * 2.0;
+                    let tension = if rising { start_tension - delta } else { start_tension + delta };
+                    action = AutomationAction::SetTension(lane_idx, index, tension);
+                }
+            }
+        }
+        if response.drag_stopped() {
+            self.drag = None;
+        }
+
+        if response.clicked()
+            && hovered_point.is_none()
+            && hovered_tension.is_none()
+            && let Some(pos) = pointer
+        {
+            let (beat, value) = from_screen(pos);
+            let beat = snap(beat, ui.input(|i| i.modifiers.shift));
+            action = AutomationAction::AddPoint(lane_idx, AutomationPoint::new(beat, value));
+        }
+
+        // Right-click a point for its shape; the menu keeps the point it opened on
+        let menu_id = ui.id().with("automation_point_menu");
+        if response.secondary_clicked() {
+            ui.data_mut(|d| d.insert_temp(menu_id, hovered_point));
+        }
+        let menu_point: Option<usize> = ui.data(|d| d.get_temp(menu_id)).flatten();
+        if let Some(index) = menu_point.filter(|&i| i < lane.points.len()) {
+            response.context_menu(|ui| {
+                let point = lane.points[index];
+                ui.label(format!("{:.2} at beat {:.2}", lane.denormalize(point.value), point.beat));
+                ui.separator();
+                for shape in CurveShape::ALL {
+                    if ui.radio(point.shape == shape, shape.name()).clicked() {
+                        action = AutomationAction::SetShape(lane_idx, index, shape);
+                        ui.close_menu();
+                    }
+                }
+                ui.separator();
+                if ui.button("Delete point").clicked() {
+                    action = AutomationAction::RemovePoint(lane_idx, index);
+                    ui.close_menu();
+                }
+            });
+        }
+
+        if let Some(pos) = pointer.filter(|p| rect.contains(*p)) {
+            let (beat, value) = from_screen(pos);
+            response.on_hover_text_at_pointer(format!("beat {:.2}: {:.2}", beat, lane.denormalize(value)));
+        }
+
+        action
+    }
+}
+
+/// Where the tension handle of the segment after point `index` sits (beat, normalized value):
+/// the middle of an exponential segment, a quarter of the way along an S-curve (whose
+/// middle never moves)
+fn tension_handle(lane: &AutomationLane, index: usize) -> Option<(f64, f32)> {
+    let from = lane.points.get(index)?;
+    let to = lane.points.get(index + 1)?;
+    let t = match from.shape {
+        CurveShape::Exponential => 0.5,
+        CurveShape::SCurve => 0.25,
+        _ => return None,
+    };
+    let beat = from.beat + (to.beat - from.beat) * t as f64;
+    Some((beat, from.value + (to.value - from.value) * from.shape.apply(from.tension, t)))
+}
+
+fn snap(beat: f64, free: bool) -> f64 {
+    if free { beat } else { (beat / SNAP_BEATS).round() * SNAP_BEATS }
+}
+
+impl Default for AutomationPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
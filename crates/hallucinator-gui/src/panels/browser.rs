@@ -1,9 +1,25 @@
 //! Browser panel - left sidebar with plugin/sound categories and Places
 
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use egui::{CollapsingHeader, ScrollArea, Ui};
-use hallucinator_services::Vst3PluginInfo;
+use egui::{Align, CollapsingHeader, Layout, RichText, ScrollArea, Sense, Stroke, Ui, Vec2};
+use hallucinator_services::{is_audio_file, PreviewSettings, SampleInfo, SampleInfoLoader, Vst3PluginInfo, VST_PRESET_EXTENSION};
+
+use super::sample_library::{SampleLibrary, SampleQuery, MAX_RATING};
+
+/// Most files listed under search results
+const MAX_SEARCH_RESULTS: usize = 500;
+
+/// Library search results for the filter text, run again when the text or the library
+/// changes rather than every frame
+struct SearchResults {
+    text: String,
+    revision: u64,
+    /// Up to one more than `MAX_SEARCH_RESULTS`, to tell whether the list was cut short
+    paths: Vec<PathBuf>,
+}
 
 // ── Native instrument info ──────────────────────────────────────────
 
@@ -14,11 +30,16 @@ pub struct NativeInstrumentInfo {
     pub name: &'static str,
 }
 
-/// Available native instruments
+/// Available native drum instruments
 pub const NATIVE_DRUMS: &[NativeInstrumentInfo] = &[
     NativeInstrumentInfo { id: "drum808", name: "808 Drums" },
 ];
 
+/// Available native melodic/utility instruments
+pub const NATIVE_INSTRUMENTS: &[NativeInstrumentInfo] = &[
+    NativeInstrumentInfo { id: "signal_generator", name: "Signal Generator" },
+];
+
 // ── Sample library Places ───────────────────────────────────────────
 
 /// A file or folder entry in the sample library tree.
@@ -45,6 +66,22 @@ impl Place {
         let entries = scan_dir(&root);
         Some(Self { root, name, entries })
     }
+
+    /// Every sample file in the place, for the library's search index
+    fn files(&self) -> Vec<PathBuf> {
+        fn collect(entries: &[LibEntry], out: &mut Vec<PathBuf>) {
+            for entry in entries {
+                if entry.is_dir {
+                    collect(&entry.children, out);
+                } else {
+                    out.push(entry.path.clone());
+                }
+            }
+        }
+        let mut files = Vec::new();
+        collect(&self.entries, &mut files);
+        files
+    }
 }
 
 fn scan_dir(dir: &std::path::Path) -> Vec<LibEntry> {
@@ -63,15 +100,14 @@ fn scan_dir(dir: &std::path::Path) -> Vec<LibEntry> {
 
         if path.is_dir() {
             let children = scan_dir(&path);
-            // Only include dirs that contain at least one WAV (directly or nested)
-            if has_any_wav(&children) {
+            // Only include dirs that contain at least one audio file (directly or nested)
+            if has_any_audio(&children) {
                 dirs.push(LibEntry { name, path, children, is_dir: true });
             }
             continue;
         }
 
-        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
-        if ext == "wav" {
+        if is_audio_file(&path) {
             files.push(LibEntry { name, path, children: Vec::new(), is_dir: false });
         }
     }
@@ -82,8 +118,69 @@ fn scan_dir(dir: &std::path::Path) -> Vec<LibEntry> {
     dirs
 }
 
-fn has_any_wav(entries: &[LibEntry]) -> bool {
-    entries.iter().any(|e| !e.is_dir || has_any_wav(&e.children))
+/// Waveform thumbnails and metadata of sample files, analysed in the background
+/// the first time their row is shown
+#[derive(Default)]
+struct SampleInfos {
+    loader: Option<SampleInfoLoader>,
+    /// Requested files; None until analysed, or when the file can't be read
+    known: HashMap<PathBuf, Option<SampleInfo>>,
+    pending: usize,
+}
+
+impl SampleInfos {
+    fn poll(&mut self) {
+        let Some(loader) = &self.loader else { return };
+        for (path, info) in loader.poll() {
+            self.pending = self.pending.saturating_sub(1);
+            self.known.insert(path, info);
+        }
+    }
+
+    /// Info of a file, requesting it on first sight
+    fn get(&mut self, path: &Path) -> Option<&SampleInfo> {
+        if !self.known.contains_key(path) {
+            if let Some(loader) = &self.loader {
+                loader.request(path.to_path_buf());
+                self.pending += 1;
+            }
+            self.known.insert(path.to_path_buf(), None);
+        }
+        self.known.get(path)?.as_ref()
+    }
+}
+
+/// Thumbnail and metadata columns of a sample row, laid out right to left
+fn sample_info_columns(ui: &mut Ui, info: Option<&SampleInfo>) {
+    let row_h = ui.spacing().interact_size.y;
+    let column = |ui: &mut Ui, width: f32, text: String| {
+        ui.add_sized([width, row_h], egui::Label::new(RichText::new(text).small().weak()));
+    };
+    let Some(info) = info else {
+        column(ui, 150.0, String::new());
+        return;
+    };
+    column(ui, 26.0, info.bpm.map_or_else(String::new, |bpm| format!("{:.0}", bpm)));
+    column(ui, 22.0, format!("{}ch", info.channels));
+    column(ui, 34.0, format!("{:.1}k", info.sample_rate as f32 / 1000.0));
+    column(ui, 34.0, format!("{:.1}s", info.duration_secs));
+
+    let (rect, _) = ui.allocate_exact_size(Vec2::new(48.0, row_h - 4.0), Sense::hover());
+    let painter = ui.painter_at(rect);
+    let color = ui.visuals().weak_text_color();
+    let step = rect.width() / info.peaks.len().max(1) as f32;
+    for (i, &peak) in info.peaks.iter().enumerate() {
+        let x = rect.left() + (i as f32 + 0.5) * step;
+        let half = (peak as f32 / 255.0 * rect.height() * 0.5).max(0.5);
+        painter.line_segment(
+            [egui::pos2(x, rect.center().y - half), egui::pos2(x, rect.center().y + half)],
+            Stroke::new(step.max(1.0) * 0.8, color),
+        );
+    }
+}
+
+fn has_any_audio(entries: &[LibEntry]) -> bool {
+    entries.iter().any(|e| !e.is_dir || has_any_audio(&e.children))
 }
 
 fn has_matching_descendant(entry: &LibEntry, filter: &str) -> bool {
@@ -95,13 +192,77 @@ fn has_matching_descendant(entry: &LibEntry, filter: &str) -> bool {
     })
 }
 
-fn render_tree(
-    ui: &mut Ui,
-    entries: &[LibEntry],
-    filter: &str,
-    selected_id: &mut Option<egui::Id>,
-    action: &mut BrowserAction,
-) {
+/// What a sample row can change: selection, analysis requests, library edits and the action
+struct SampleRows<'a> {
+    selected_id: &'a mut Option<egui::Id>,
+    infos: &'a mut SampleInfos,
+    library: &'a mut SampleLibrary,
+    /// Text of the "add tag" field in a row's context menu
+    tag_input: &'a mut String,
+    action: &'a mut BrowserAction,
+}
+
+/// One sample file: preview button, name (clickable, draggable), stars, thumbnail and metadata,
+/// with copy, rating and tags in its context menu
+fn sample_row(ui: &mut Ui, rows: &mut SampleRows, name: &str, path: &Path) {
+    let item_id = egui::Id::new(path);
+    let rating = rows.library.rating(path);
+
+    ui.horizontal(|ui| {
+        if ui.small_button("▶").on_hover_text("Preview").clicked() {
+            *rows.action = BrowserAction::PreviewSample(path.to_path_buf());
+        }
+
+        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+            sample_info_columns(ui, rows.infos.get(path));
+
+            let label = if rating > 0 { format!("{} {}", "★".repeat(rating as usize), name) } else { name.to_string() };
+            let mut resp = browser_item(ui, &label, *rows.selected_id == Some(item_id), true);
+            if let Some(entry) = rows.library.entry(path).filter(|e| !e.tags.is_empty()) {
+                resp = resp.on_hover_text(entry.tags.iter().map(|t| format!("#{t}")).collect::<Vec<_>>().join(" "));
+            }
+            if resp.clicked() {
+                *rows.selected_id = Some(item_id);
+                *rows.action = BrowserAction::SelectFile(path.to_path_buf());
+            }
+            if resp.dragged() {
+                egui::DragAndDrop::set_payload(ui.ctx(), path.to_path_buf());
+            }
+            resp.context_menu(|ui| {
+                if ui.button("Copy").clicked() {
+                    *rows.selected_id = Some(item_id);
+                    *rows.action = BrowserAction::SelectFile(path.to_path_buf());
+                    ui.close_menu();
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    for stars in 1..=MAX_RATING {
+                        let star = if stars <= rating { "★" } else { "☆" };
+                        if ui.small_button(star).on_hover_text(format!("Rate {stars}")).clicked() {
+                            // Clicking the current rating again clears it
+                            rows.library.set_rating(path, if stars == rating { 0 } else { stars });
+                        }
+                    }
+                });
+                let tags = rows.library.entry(path).map(|e| e.tags.clone()).unwrap_or_default();
+                ui.horizontal_wrapped(|ui| {
+                    for tag in &tags {
+                        if ui.small_button(format!("#{tag} ✕")).on_hover_text("Remove tag").clicked() {
+                            rows.library.remove_tag(path, tag);
+                        }
+                    }
+                });
+                let field = ui.add(egui::TextEdit::singleline(rows.tag_input).hint_text("add tag").desired_width(100.0));
+                if field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    rows.library.add_tag(path, rows.tag_input);
+                    rows.tag_input.clear();
+                }
+            });
+        });
+    });
+}
+
+fn render_tree(ui: &mut Ui, entries: &[LibEntry], filter: &str, rows: &mut SampleRows) {
     for entry in entries {
         if entry.is_dir {
             if !filter.is_empty() && !has_matching_descendant(entry, filter) {
@@ -111,39 +272,57 @@ fn render_tree(
                 .id_salt(&entry.path)
                 .default_open(false)
                 .show(ui, |ui| {
-                    render_tree(ui, &entry.children, filter, selected_id, action);
+                    render_tree(ui, &entry.children, filter, rows);
                 });
             continue;
         }
         if !filter.is_empty() && !entry.name.to_lowercase().contains(filter) {
             continue;
         }
-        let item_id = egui::Id::new(&entry.path);
+        sample_row(ui, rows, &entry.name, &entry.path);
+    }
+}
 
-        ui.horizontal(|ui| {
-            // Preview button
-            if ui.small_button("▶").on_hover_text("Preview").clicked() {
-                *action = BrowserAction::PreviewSample(entry.path.clone());
-            }
+// ── VST3 preset files ───────────────────────────────────────────────
 
-            // File name (clickable, draggable)
-            let resp = browser_item(ui, &entry.name, *selected_id == Some(item_id), true);
-            if resp.clicked() {
-                *selected_id = Some(item_id);
-                *action = BrowserAction::SelectFile(entry.path.clone());
-            }
-            if resp.dragged() {
-                egui::DragAndDrop::set_payload(ui.ctx(), entry.path.clone());
+/// `.vstpreset` files in one folder (by convention, one plugin)
+struct PresetFolder {
+    name: String,
+    presets: Vec<(String, PathBuf)>,
+}
+
+/// Find `.vstpreset` files under the preset roots, grouped by containing folder
+fn scan_vst_presets(roots: &[PathBuf]) -> Vec<PresetFolder> {
+    fn collect(dir: &Path, out: &mut Vec<PathBuf>) {
+        let Ok(read) = std::fs::read_dir(dir) else { return };
+        for entry in read.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                collect(&path, out);
+            } else if path.extension().is_some_and(|e| e.eq_ignore_ascii_case(VST_PRESET_EXTENSION)) {
+                out.push(path);
             }
-            resp.context_menu(|ui| {
-                if ui.button("Copy").clicked() {
-                    *selected_id = Some(item_id);
-                    *action = BrowserAction::SelectFile(entry.path.clone());
-                    ui.close_menu();
-                }
-            });
-        });
+        }
     }
+
+    let mut files = Vec::new();
+    for root in roots {
+        collect(root, &mut files);
+    }
+
+    let mut folders: BTreeMap<String, Vec<(String, PathBuf)>> = BTreeMap::new();
+    for path in files {
+        let folder = path.parent().and_then(|p| p.file_name()).and_then(|n| n.to_str()).unwrap_or("").to_string();
+        let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("").to_string();
+        folders.entry(folder).or_default().push((name, path));
+    }
+    folders
+        .into_iter()
+        .map(|(name, mut presets)| {
+            presets.sort_by_key(|(n, _)| n.to_lowercase());
+            PresetFolder { name, presets }
+        })
+        .collect()
 }
 
 /// Render a single browser row with selection highlight, hover, and optional drag support.
@@ -186,8 +365,13 @@ pub enum BrowserAction {
     LoadNativeInstrument(NativeInstrumentInfo),
     SelectFile(PathBuf),
     PreviewSample(PathBuf),
+    StopPreview,
+    /// Audition volume, transpose or tempo sync changed
+    SetPreview(PreviewSettings),
     AddPlace(PathBuf),
     RemovePlace(usize),
+    /// Apply a `.vstpreset` file to its plugin
+    LoadVstPreset(PathBuf),
 }
 
 // ── BrowserPanel ────────────────────────────────────────────────────
@@ -197,6 +381,18 @@ pub struct BrowserPanel {
     filter_text: String,
     places: Vec<Place>,
     selected_id: Option<egui::Id>,
+    preset_roots: Vec<PathBuf>,
+    /// Scanned `.vstpreset` files (None until first shown or after a refresh)
+    vst_presets: Option<Vec<PresetFolder>>,
+    /// Audition a sample as soon as it is clicked
+    auto_preview: bool,
+    preview: PreviewSettings,
+    sample_infos: SampleInfos,
+    /// Tags, ratings and saved searches
+    library: SampleLibrary,
+    /// Text of the "add tag" field in a sample's context menu
+    tag_input: String,
+    search_results: Option<SearchResults>,
 }
 
 impl BrowserPanel {
@@ -205,12 +401,57 @@ impl BrowserPanel {
             filter_text: String::new(),
             places: Vec::new(),
             selected_id: None,
+            preset_roots: Vec::new(),
+            vst_presets: None,
+            auto_preview: true,
+            preview: PreviewSettings::default(),
+            sample_infos: SampleInfos::default(),
+            library: SampleLibrary::default(),
+            tag_input: String::new(),
+            search_results: None,
         }
     }
 
+    /// Start the background thread that draws waveform thumbnails and reads metadata
+    /// for sample rows, caching them in `cache_dir`
+    pub fn start_sample_info(&mut self, cache_dir: PathBuf) {
+        match SampleInfoLoader::spawn(Some(cache_dir)) {
+            Ok(loader) => self.sample_infos.loader = Some(loader),
+            Err(e) => tracing::warn!("Sample info thread failed to start: {}", e),
+        }
+    }
+
+    /// Open the sample library database at `path`, which every edit is saved to, and index
+    /// the files of the current places for searching
+    pub fn load_library(&mut self, path: PathBuf) {
+        self.library = SampleLibrary::load(path);
+        self.index_places();
+    }
+
+    fn index_places(&mut self) {
+        self.library.index_places(self.places.iter().map(|p| (p.root.as_path(), p.files())));
+    }
+
+    /// Whether clicking a sample should also audition it
+    pub fn auto_preview(&self) -> bool {
+        self.auto_preview
+    }
+
+    /// Set the folders searched for `.vstpreset` files.
+    pub fn set_preset_roots(&mut self, roots: Vec<PathBuf>) {
+        self.preset_roots = roots;
+        self.vst_presets = None;
+    }
+
+    /// Rescan preset files next time the list is shown (e.g. after saving one).
+    pub fn refresh_presets(&mut self) {
+        self.vst_presets = None;
+    }
+
     /// Initialize places from a list of folder paths.
     pub fn set_places(&mut self, paths: Vec<PathBuf>) {
         self.places = paths.into_iter().filter_map(Place::scan).collect();
+        self.index_places();
     }
 
     /// Add a single folder as a new place. Returns false if invalid or duplicate.
@@ -221,6 +462,7 @@ impl BrowserPanel {
         let Some(place) = Place::scan(path) else {
             return false;
         };
+        self.library.index_place(&place.root, &place.files());
         self.places.push(place);
         true
     }
@@ -228,7 +470,8 @@ impl BrowserPanel {
     /// Remove a place by index.
     pub fn remove_place(&mut self, index: usize) {
         if index < self.places.len() {
-            self.places.remove(index);
+            let place = self.places.remove(index);
+            self.library.forget_place(&place.root);
         }
     }
 
@@ -240,6 +483,11 @@ impl BrowserPanel {
     pub fn ui(&mut self, ui: &mut Ui, plugins: &[Vst3PluginInfo]) -> BrowserAction {
         let mut action = BrowserAction::None;
 
+        self.sample_infos.poll();
+        if self.sample_infos.pending > 0 {
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+        }
+
         ui.heading("Browser");
         ui.separator();
 
@@ -249,6 +497,26 @@ impl BrowserPanel {
             ui.text_edit_singleline(&mut self.filter_text);
         });
 
+        // Audition controls
+        ui.horizontal(|ui| {
+            if ui.small_button("■").on_hover_text("Stop preview").clicked() {
+                action = BrowserAction::StopPreview;
+            }
+            ui.checkbox(&mut self.auto_preview, "Auto").on_hover_text("Play samples when clicked");
+            let mut changed = ui.add(egui::Slider::new(&mut self.preview.volume, 0.0..=1.0).show_value(false))
+                .on_hover_text("Preview volume")
+                .changed();
+            changed |= ui.add(egui::DragValue::new(&mut self.preview.pitch).range(-24.0..=24.0).speed(0.1).suffix(" st"))
+                .on_hover_text("Transpose the preview")
+                .changed();
+            changed |= ui.checkbox(&mut self.preview.sync_tempo, "Sync")
+                .on_hover_text("Play loops at the project tempo")
+                .changed();
+            if changed {
+                action = BrowserAction::SetPreview(self.preview);
+            }
+        });
+
         ui.separator();
 
         let filter_lower = self.filter_text.to_lowercase();
@@ -262,6 +530,7 @@ impl BrowserPanel {
             CollapsingHeader::new("🎹 Instruments")
                 .default_open(true)
                 .show(ui, |ui| {
+                    action = self.show_native_list(ui, NATIVE_INSTRUMENTS, &action);
                     action = self.show_plugin_list(ui, plugins, true, &action);
                 });
 
@@ -276,19 +545,110 @@ impl BrowserPanel {
             CollapsingHeader::new("🥁 Drums")
                 .default_open(true)
                 .show(ui, |ui| {
-                    for inst in NATIVE_DRUMS {
-                        let name_lower = inst.name.to_lowercase();
-                        if !filter_lower.is_empty() && !name_lower.contains(&filter_lower) {
-                            continue;
+                    action = self.show_native_list(ui, NATIVE_DRUMS, &action);
+                });
+
+            // VST3 preset files, applied to the matching loaded plugin
+            CollapsingHeader::new("🎚 VST Presets")
+                .default_open(false)
+                .show(ui, |ui| {
+                    action = self.show_preset_list(ui, &filter_lower, &action);
+                });
+
+            // ── Library ─────────────────────────────────────────
+            ui.add_space(8.0);
+            ui.strong("Library");
+            ui.separator();
+
+            let query = SampleQuery::parse(&self.filter_text);
+            let stale = self.search_results.as_ref()
+                .is_none_or(|r| r.text != self.filter_text || r.revision != self.library.revision());
+            if stale {
+                self.search_results = Some(SearchResults {
+                    text: self.filter_text.clone(),
+                    revision: self.library.revision(),
+                    paths: if query.is_empty() { Vec::new() } else { self.library.search(&query, MAX_SEARCH_RESULTS + 1) },
+                });
+            }
+            let results = self.search_results.as_ref().map_or(&[][..], |r| &r.paths[..]);
+
+            let mut rows = SampleRows {
+                selected_id: &mut self.selected_id,
+                infos: &mut self.sample_infos,
+                library: &mut self.library,
+                tag_input: &mut self.tag_input,
+                action: &mut action,
+            };
+
+            if !query.is_empty() {
+                let count = if results.len() > MAX_SEARCH_RESULTS {
+                    format!("{MAX_SEARCH_RESULTS}+")
+                } else {
+                    results.len().to_string()
+                };
+                CollapsingHeader::new(format!("🔎 Search results ({count})"))
+                    .id_salt("library_search")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        if ui.small_button("Save search").on_hover_text("Keep this search under Saved searches").clicked() {
+                            let text = self.filter_text.trim();
+                            rows.library.save_search(text, text);
                         }
-                        let item_id = egui::Id::new(("native", inst.id));
-                        let resp = browser_item(ui, inst.name, self.selected_id == Some(item_id), false);
-                        if resp.clicked() {
-                            self.selected_id = Some(item_id);
+                        for path in results.iter().take(MAX_SEARCH_RESULTS) {
+                            let name = path.file_name().map_or_else(|| path.to_string_lossy(), |n| n.to_string_lossy());
+                            sample_row(ui, &mut rows, &name, path);
                         }
-                        if resp.double_clicked() {
-                            action = BrowserAction::LoadNativeInstrument(inst.clone());
+                        if results.len() > MAX_SEARCH_RESULTS {
+                            ui.weak("More files match; narrow the search to see them");
                         }
+                    });
+            }
+
+            CollapsingHeader::new("⭐ Favorites")
+                .id_salt("library_favorites")
+                .default_open(false)
+                .show(ui, |ui| {
+                    let favorites = rows.library.favorites();
+                    if favorites.is_empty() {
+                        ui.weak("Right-click a sample to rate it");
+                    }
+                    for (path, _) in &favorites {
+                        let name = path.file_name().map_or_else(|| path.to_string_lossy(), |n| n.to_string_lossy());
+                        sample_row(ui, &mut rows, &name, path);
+                    }
+                });
+
+            CollapsingHeader::new("Saved searches")
+                .id_salt("library_searches")
+                .default_open(false)
+                .show(ui, |ui| {
+                    let mut remove: Option<usize> = None;
+                    for (idx, search) in rows.library.saved_searches.iter().enumerate() {
+                        let resp = browser_item(ui, &search.name, false, false).on_hover_text(&search.query);
+                        if resp.clicked() {
+                            self.filter_text = search.query.clone();
+                        }
+                        resp.context_menu(|ui| {
+                            if ui.button("Delete").clicked() {
+                                remove = Some(idx);
+                                ui.close_menu();
+                            }
+                        });
+                    }
+                    if let Some(idx) = remove {
+                        rows.library.remove_search(idx);
+                    }
+
+                    let tags = rows.library.all_tags();
+                    if !tags.is_empty() {
+                        ui.add_space(4.0);
+                        ui.horizontal_wrapped(|ui| {
+                            for tag in tags {
+                                if ui.small_button(format!("#{tag}")).clicked() {
+                                    self.filter_text = format!("#{tag}");
+                                }
+                            }
+                        });
                     }
                 });
 
@@ -304,7 +664,7 @@ impl BrowserPanel {
                     .id_salt(&place.root)
                     .default_open(false)
                     .show(ui, |ui| {
-                        render_tree(ui, &place.entries, &filter_lower, &mut self.selected_id, &mut action);
+                        render_tree(ui, &place.entries, &filter_lower, &mut rows);
                     });
 
                 resp.header_response.context_menu(|ui| {
@@ -329,6 +689,74 @@ impl BrowserPanel {
         action
     }
 
+    fn show_native_list(
+        &mut self,
+        ui: &mut Ui,
+        instruments: &[NativeInstrumentInfo],
+        current_action: &BrowserAction,
+    ) -> BrowserAction {
+        let mut action = current_action.clone();
+        let filter_lower = self.filter_text.to_lowercase();
+
+        for inst in instruments {
+            let name_lower = inst.name.to_lowercase();
+            if !filter_lower.is_empty() && !name_lower.contains(&filter_lower) {
+                continue;
+            }
+            let item_id = egui::Id::new(("native", inst.id));
+            let resp = browser_item(ui, inst.name, self.selected_id == Some(item_id), false);
+            if resp.clicked() {
+                self.selected_id = Some(item_id);
+            }
+            if resp.double_clicked() {
+                action = BrowserAction::LoadNativeInstrument(inst.clone());
+            }
+        }
+
+        action
+    }
+
+    fn show_preset_list(&mut self, ui: &mut Ui, filter_lower: &str, current_action: &BrowserAction) -> BrowserAction {
+        let mut action = current_action.clone();
+
+        if ui.small_button("⟳ Rescan").clicked() {
+            self.vst_presets = None;
+        }
+        let folders = self.vst_presets.get_or_insert_with(|| scan_vst_presets(&self.preset_roots));
+        if folders.is_empty() {
+            ui.label("No .vstpreset files found");
+        }
+
+        for folder in folders.iter() {
+            let visible: Vec<_> = folder
+                .presets
+                .iter()
+                .filter(|(name, _)| filter_lower.is_empty() || name.to_lowercase().contains(filter_lower))
+                .collect();
+            if visible.is_empty() {
+                continue;
+            }
+            CollapsingHeader::new(&folder.name)
+                .id_salt(("vst_presets", &folder.name))
+                .default_open(!filter_lower.is_empty())
+                .show(ui, |ui| {
+                    for (name, path) in visible {
+                        let item_id = egui::Id::new(path);
+                        let resp = browser_item(ui, name, self.selected_id == Some(item_id), false)
+                            .on_hover_text("Double-click to apply to the matching plugin");
+                        if resp.clicked() {
+                            self.selected_id = Some(item_id);
+                        }
+                        if resp.double_clicked() {
+                            action = BrowserAction::LoadVstPreset(path.clone());
+                        }
+                    }
+                });
+        }
+
+        action
+    }
+
     fn show_plugin_list(
         &mut self,
         ui: &mut Ui,
@@ -340,16 +768,24 @@ impl BrowserPanel {
         let filter_lower = self.filter_text.to_lowercase();
 
         for plugin in plugins {
-            let name_lower = plugin.name.to_lowercase();
-            if !filter_lower.is_empty() && !name_lower.contains(&filter_lower) {
+            let fits = if is_instrument { plugin.is_instrument() } else { plugin.is_effect() };
+            if !fits || !plugin.matches(&filter_lower) {
                 continue;
             }
 
             let item_id = egui::Id::new(("plugin", &plugin.name));
-            let resp = browser_item(ui, &plugin.name, self.selected_id == Some(item_id), false);
+            // Effects can be dragged onto a track's device rack
+            let mut resp = browser_item(ui, &plugin.name, self.selected_id == Some(item_id), !is_instrument);
+            if !plugin.vendor.is_empty() {
+                let category = plugin.category().map(|c| format!(" · {}", c)).unwrap_or_default();
+                resp = resp.on_hover_text(format!("{}{}", plugin.vendor, category));
+            }
             if resp.clicked() {
                 self.selected_id = Some(item_id);
             }
+            if resp.dragged() && !is_instrument {
+                egui::DragAndDrop::set_payload(ui.ctx(), plugin.clone());
+            }
             if resp.double_clicked() {
                 action = if is_instrument {
                     BrowserAction::LoadInstrument(plugin.clone())
@@ -0,0 +1,204 @@
+//! Chord track panel - the arrangement's chord per region, followed by MIDI effects
+//!
+//! Click an empty bar to add a chord there, drag a chord to move it or its right edge to
+//! resize it (both snap to beats), and right-click one to change its root and quality.
+
+use egui::{Color32, Pos2, Rect, ScrollArea, Sense, Stroke, Ui, Vec2};
+use hallucinator_core::{Chord, ChordQuality, ChordRegion, ChordTrack};
+
+const LANE_HEIGHT: f32 = 48.0;
+const PIXELS_PER_BEAT: f32 = 24.0;
+/// Bars shown past the last chord
+const TRAILING_BARS: usize = 4;
+/// Distance from a chord's right edge that grabs the edge instead of the chord
+const EDGE_GRAB: f32 = 6.0;
+
+const ROOT_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+/// Action returned from the chord track panel
+pub enum ChordTrackAction {
+    None,
+    Add(ChordRegion),
+    Remove(usize),
+    SetChord(usize, Chord),
+    SetSpan { index: usize, start_beat: f64, length_beats: f64 },
+}
+
+/// Chord being dragged, drawn where it will land until the drag ends
+#[derive(Clone, Copy)]
+struct Drag {
+    index: usize,
+    /// Beats from the chord's start to the pointer when moving; None when resizing
+    grab: Option<f64>,
+    start_beat: f64,
+    length_beats: f64,
+}
+
+/// Chord lane editor
+pub struct ChordTrackPanel {
+    drag: Option<Drag>,
+}
+
+impl ChordTrackPanel {
+    pub fn new() -> Self {
+        Self { drag: None }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, track: &ChordTrack, beats_per_bar: u8, playhead_beat: f64) -> ChordTrackAction {
+        let mut action = ChordTrackAction::None;
+        let bar = beats_per_bar.max(1) as f64;
+
+        ui.label("MIDI effects with chord following on (Harmonizer, Arpeggiator, Chord) play these chords");
+        let last_end = track.regions.last().map_or(0.0, |r| r.end_beat());
+        let bars = ((last_end.max(playhead_beat) / bar).ceil() as usize + TRAILING_BARS).max(16);
+        let span = bars as f64 * bar;
+
+        ScrollArea::horizontal().show(ui, |ui| {
+            let size = Vec2::new(span as f32 * PIXELS_PER_BEAT, LANE_HEIGHT);
+            let (response, painter) = ui.allocate_painter(size, Sense::click_and_drag());
+            let rect = response.rect;
+            let x_of = |beat: f64| rect.left() + beat as f32 * PIXELS_PER_BEAT;
+            let beat_of = |x: f32| ((x - rect.left()) / PIXELS_PER_BEAT) as f64;
+
+            // Background, beat and bar lines with bar numbers, playhead
+            painter.rect_filled(rect, 2.0, Color32::from_gray(28));
+            for beat in 0..=span as usize {
+                let x = x_of(beat as f64);
+                let is_bar = beat as f64 % bar == 0.0;
+                let color = if is_bar { Color32::from_gray(70) } else { Color32::from_gray(40) };
+                painter.line_segment([Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())], Stroke::new(1.0, color));
+                if is_bar {
+                    painter.text(
+                        Pos2::new(x + 2.0, rect.top() + 1.0),
+                        egui::Align2::LEFT_TOP,
+                        format!("{}", (beat as f64 / bar) as usize + 1),
+                        egui::FontId::proportional(9.0),
+                        Color32::from_gray(110),
+                    );
+                }
+            }
+
+            // Chords, with the dragged one where it will land
+            let region_rect = |start: f64, length: f64| {
+                Rect::from_min_max(
+                    Pos2::new(x_of(start) + 1.0, rect.top() + 12.0),
+                    Pos2::new(x_of(start + length) - 1.0, rect.bottom() - 2.0),
+                )
+            };
+            let pointer = response.interact_pointer_pos().or(response.hover_pos());
+            let mut hovered = None;
+            for (idx, region) in track.regions.iter().enumerate() {
+                let (start, length) = match self.drag.filter(|d| d.index == idx) {
+                    Some(drag) => (drag.start_beat, drag.length_beats),
+                    None => (region.start_beat, region.length_beats),
+                };
+                let r = region_rect(start, length);
+                if pointer.is_some_and(|p| r.contains(p)) {
+                    hovered = Some(idx);
+                }
+                painter.rect_filled(r, 3.0, Color32::from_rgb(110, 80, 150));
+                painter.rect_stroke(r, 3.0, Stroke::new(1.0, Color32::from_rgb(170, 130, 220)), egui::StrokeKind::Inside);
+                painter.with_clip_rect(r).text(
+                    Pos2::new(r.left() + 4.0, r.center().y),
+                    egui::Align2::LEFT_CENTER,
+                    region.chord.name(),
+                    egui::FontId::proportional(13.0),
+                    Color32::WHITE,
+                );
+            }
+
+            let playhead_x = x_of(playhead_beat);
+            painter.line_segment(
+                [Pos2::new(playhead_x, rect.top()), Pos2::new(playhead_x, rect.bottom())],
+                Stroke::new(1.0, Color32::from_rgb(255, 200, 80)),
+            );
+
+            if response.drag_started()
+                && let (Some(index), Some(pos)) = (hovered, pointer)
+            {
+                let region = track.regions[index];
+                let on_edge = x_of(region.end_beat()) - pos.x <= EDGE_GRAB;
+                self.drag = Some(Drag {
+                    index,
+                    grab: (!on_edge).then(|| beat_of(pos.x) - region.start_beat),
+                    start_beat: region.start_beat,
+                    length_beats: region.length_beats,
+                });
+            }
+            if response.dragged()
+                && let (Some(drag), Some(pos)) = (&mut self.drag, pointer)
+            {
+                let beat = beat_of(pos.x);
+                match drag.grab {
+                    Some(grab) => drag.start_beat = (beat - grab).round().max(0.0),
+                    None => drag.length_beats = (beat - drag.start_beat).round().max(1.0),
+                }
+            }
+            if response.drag_stopped()
+                && let Some(drag) = self.drag.take()
+            {
+                action = ChordTrackAction::SetSpan {
+                    index: drag.index,
+                    start_beat: drag.start_beat,
+                    length_beats: drag.length_beats,
+                };
+            }
+
+            // Click empty space: a chord up to the end of the bar (or the next chord),
+            // continuing the chord before it
+            if response.clicked()
+                && hovered.is_none()
+                && let Some(pos) = pointer
+            {
+                let beat = beat_of(pos.x);
+                let previous = track.regions.iter().rev().find(|r| r.end_beat() <= beat);
+                let start_beat = ((beat / bar).floor() * bar).max(previous.map_or(0.0, |r| r.end_beat()));
+                let chord = previous.map_or(Chord::new(0, ChordQuality::Major), |r| r.chord);
+                let next_start = track.regions.iter().map(|r| r.start_beat).find(|&s| s > start_beat).unwrap_or(f64::MAX);
+                let length_beats = bar.min(next_start - start_beat);
+                action = ChordTrackAction::Add(ChordRegion { start_beat, length_beats, chord });
+            }
+
+            // Right-click a chord for its root and quality; the menu keeps the chord it opened on
+            let menu_id = ui.id().with("chord_region_menu");
+            if response.secondary_clicked() {
+                ui.data_mut(|d| d.insert_temp(menu_id, hovered));
+            }
+            let menu_region: Option<usize> = ui.data(|d| d.get_temp(menu_id)).flatten();
+            if let Some(index) = menu_region.filter(|&i| i < track.regions.len()) {
+                response.context_menu(|ui| {
+                    let chord = track.regions[index].chord;
+                    ui.menu_button(format!("Root: {}", ROOT_NAMES[chord.root as usize % 12]), |ui| {
+                        for (root, name) in ROOT_NAMES.iter().enumerate() {
+                            if ui.radio(chord.root as usize == root, *name).clicked() {
+                                action = ChordTrackAction::SetChord(index, Chord::new(root as u8, chord.quality));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    ui.menu_button(format!("Quality: {}", chord.quality.name()), |ui| {
+                        for quality in ChordQuality::ALL {
+                            if ui.radio(chord.quality == quality, quality.name()).clicked() {
+                                action = ChordTrackAction::SetChord(index, Chord::new(chord.root, quality));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    ui.separator();
+                    if ui.button("Delete chord").clicked() {
+                        action = ChordTrackAction::Remove(index);
+                        ui.close_menu();
+                    }
+                });
+            }
+        });
+
+        action
+    }
+}
+
+impl Default for ChordTrackPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
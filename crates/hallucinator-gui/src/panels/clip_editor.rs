@@ -1,10 +1,38 @@
-//! Clip editor panel - combines piano roll (MIDI) and waveform editor (audio)
+//! Clip editor panel - combines piano roll (MIDI) and waveform or spectral editor (audio)
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
 
 use crate::clipboard::DawClipboard;
-use egui::{Color32, Rect, Sense, Stroke, Ui, Vec2};
-use hallucinator_core::{AudioClip, MidiClip};
+use egui::{Color32, ColorImage, Rect, Sense, Stroke, TextureHandle, TextureOptions, Ui, Vec2};
+use hallucinator_core::{AudioClip, Groove, MidiClip, PEAK_BLOCK};
+use hallucinator_services::spectrogram::{frequency_at, frequency_position, SPECTROGRAM_BANDS};
+use hallucinator_services::{Spectrogram, SpectrogramLoader};
 
 use super::piano_roll::{PianoRollAction, PianoRollPanel};
+use super::status_bar;
+
+/// Levels mapped onto the spectral view's colours; quieter is black
+const SPECTRAL_RANGE_DB: f32 = 100.0;
+
+/// How the audio editor draws the clip
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum AudioView {
+    Waveform,
+    /// Spectrogram: time across, log frequency up, level as colour
+    Spectral,
+}
+
+/// A finished spectrogram uploaded for drawing
+struct SpectralTexture {
+    /// `clip_key` of the audio it shows
+    key: u64,
+    texture: TextureHandle,
+    columns: usize,
+    hop: usize,
+    sample_rate: u32,
+}
 
 /// Clip editor panel state
 pub struct ClipEditorPanel {
@@ -12,6 +40,12 @@ pub struct ClipEditorPanel {
     // Audio editor state
     audio_zoom: f32,
     audio_scroll: f32,
+    audio_view: AudioView,
+    /// Computes spectrograms off the GUI thread; None if the thread couldn't start
+    spectrogram_loader: Option<SpectrogramLoader>,
+    /// Key of the audio last sent for analysis
+    spectrogram_requested: Option<u64>,
+    spectral_texture: Option<SpectralTexture>,
 }
 
 impl ClipEditorPanel {
@@ -20,9 +54,34 @@ impl ClipEditorPanel {
             piano_roll: PianoRollPanel::new(),
             audio_zoom: 1.0,
             audio_scroll: 0.0,
+            audio_view: AudioView::Waveform,
+            spectrogram_loader: SpectrogramLoader::spawn()
+                .inspect_err(|e| tracing::warn!("Spectral view unavailable: {}", e))
+                .ok(),
+            spectrogram_requested: None,
+            spectral_texture: None,
         }
     }
 
+    /// Intervals the selected track's harmonizer adds, shown as chord tones in the piano roll
+    pub fn set_harmony(&mut self, intervals: Vec<i8>) {
+        self.piano_roll.set_harmony(intervals);
+    }
+
+    /// The project's groove pool, for the piano roll's groove commands
+    pub fn set_grooves(&mut self, grooves: Vec<Groove>) {
+        self.piano_roll.set_grooves(grooves);
+    }
+
+    pub fn step_input_active(&self) -> bool {
+        self.piano_roll.step_input_active()
+    }
+
+    /// Pass a played note to the piano roll's step input (velocity 0 is a release)
+    pub fn queue_step_note(&mut self, pitch: u8, velocity: u8) {
+        self.piano_roll.queue_step_note(pitch, velocity);
+    }
+
     /// Render UI for MIDI clip (piano roll)
     pub fn ui_midi(
         &mut self,
@@ -53,6 +112,13 @@ impl ClipEditorPanel {
                 self.audio_zoom = (self.audio_zoom * 1.25).min(10.0);
             }
             ui.label(format!("{:.1}x", self.audio_zoom));
+            ui.separator();
+
+            ui.selectable_value(&mut self.audio_view, AudioView::Waveform, "Waveform");
+            if self.spectrogram_loader.is_some() {
+                ui.selectable_value(&mut self.audio_view, AudioView::Spectral, "Spectral")
+                    .on_hover_text("Frequency content over time, for spotting hum, clicks and bleed");
+            }
         });
 
         ui.separator();
@@ -111,15 +177,27 @@ impl ClipEditorPanel {
             t += marker_interval;
         }
 
-        // Draw waveform
-        self.draw_waveform(&painter, waveform_rect, clip, start_time, visible_duration, sample_rate);
+        match self.audio_view {
+            AudioView::Waveform => {
+                self.draw_waveform(&painter, waveform_rect, clip, start_time, visible_duration, sample_rate);
 
-        // Draw center line
-        let center_y = waveform_rect.center().y;
-        painter.line_segment(
-            [egui::pos2(waveform_rect.left(), center_y), egui::pos2(waveform_rect.right(), center_y)],
-            Stroke::new(0.5, Color32::from_gray(60)),
-        );
+                // Draw center line
+                let center_y = waveform_rect.center().y;
+                painter.line_segment(
+                    [egui::pos2(waveform_rect.left(), center_y), egui::pos2(waveform_rect.right(), center_y)],
+                    Stroke::new(0.5, Color32::from_gray(60)),
+                );
+            }
+            AudioView::Spectral => {
+                self.draw_spectrogram(&painter, waveform_rect, clip, start_time, visible_duration, sample_rate);
+                if let Some(pos) = response.hover_pos().filter(|pos| waveform_rect.contains(*pos)) {
+                    let time = start_time + (pos.x - waveform_rect.left()) / waveform_rect.width() * visible_duration;
+                    let height = (waveform_rect.bottom() - pos.y) / waveform_rect.height();
+                    let freq = frequency_at(height, clip.sample_rate);
+                    status_bar::set_hint(ui.ctx(), format!("{:.0} Hz at {:.2}s", freq, time));
+                }
+            }
+        }
 
         // Handle scroll
         if response.hovered() {
@@ -140,12 +218,14 @@ impl ClipEditorPanel {
         visible_duration: f32,
         sample_rate: u32,
     ) {
-        if clip.samples.is_empty() || rect.width() < 4.0 {
+        // Only the part of the source left after trimming
+        let samples = clip.audible_samples();
+        if samples.is_empty() || rect.width() < 4.0 {
             return;
         }
 
         let channels = clip.channels as usize;
-        let total_frames = clip.samples.len() / channels.max(1);
+        let total_frames = samples.len() / channels.max(1);
 
         let start_sample = (start_time * sample_rate as f32) as usize;
         let visible_samples = (visible_duration * sample_rate as f32) as usize;
@@ -160,6 +240,9 @@ impl ClipEditorPanel {
         if samples_per_pixel == 0 {
             return;
         }
+        // Zoomed out far enough, the peaks are read instead of every sample
+        let peaks = clip.peaks.as_ref().filter(|_| samples_per_pixel >= PEAK_BLOCK);
+        let source_offset = clip.source_offset as usize;
 
         for px in 0..width {
             let frame_start = start_sample + px * samples_per_pixel;
@@ -169,21 +252,25 @@ impl ClipEditorPanel {
                 break;
             }
 
-            let sample_start = frame_start * channels;
-            let sample_end = (frame_end * channels).min(clip.samples.len());
-
-            if sample_start >= clip.samples.len() {
-                break;
-            }
-
             // Find min/max in this range
             let mut min_val = 0.0f32;
             let mut max_val = 0.0f32;
 
-            for chunk in clip.samples[sample_start..sample_end].chunks(channels) {
-                let mono: f32 = chunk.iter().sum::<f32>() / channels as f32;
-                min_val = min_val.min(mono);
-                max_val = max_val.max(mono);
+            if let Some(peaks) = peaks {
+                let Some((min, max)) = peaks.range(source_offset + frame_start, source_offset + frame_end) else { break };
+                min_val = min_val.min(min);
+                max_val = max_val.max(max);
+            } else {
+                let sample_start = frame_start * channels;
+                let sample_end = (frame_end * channels).min(samples.len());
+                if sample_start >= samples.len() {
+                    break;
+                }
+                for chunk in samples[sample_start..sample_end].chunks(channels) {
+                    let mono: f32 = chunk.iter().sum::<f32>() / channels as f32;
+                    min_val = min_val.min(mono);
+                    max_val = max_val.max(mono);
+                }
             }
 
             let x = rect.left() + px as f32;
@@ -197,6 +284,76 @@ impl ClipEditorPanel {
         }
     }
 
+    /// Draw the clip's spectrogram, asking the loader for it first if the clip's audio changed
+    fn draw_spectrogram(
+        &mut self,
+        painter: &egui::Painter,
+        rect: Rect,
+        clip: &AudioClip,
+        start_time: f32,
+        visible_duration: f32,
+        sample_rate: u32,
+    ) {
+        let Some(loader) = &self.spectrogram_loader else { return };
+        let key = clip_key(clip);
+        for (done, spectrogram) in loader.poll() {
+            if Some(done) == self.spectrogram_requested {
+                self.spectral_texture = Some(spectral_texture(painter.ctx(), done, &spectrogram));
+            }
+        }
+
+        let texture = self.spectral_texture.as_ref().filter(|texture| texture.key == key);
+        let Some(texture) = texture else {
+            if self.spectrogram_requested != Some(key) {
+                loader.request(key, clip.audible_samples().to_vec(), clip.channels, clip.sample_rate);
+                self.spectrogram_requested = Some(key);
+            }
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "Analysing…",
+                egui::FontId::proportional(12.0),
+                Color32::from_gray(150),
+            );
+            painter.ctx().request_repaint_after(Duration::from_millis(100));
+            return;
+        };
+
+        // Column c is centred on frame c * hop, i.e. in the middle of texel c
+        let start_frame = start_time * sample_rate as f32;
+        let visible_frames = visible_duration * sample_rate as f32;
+        let total_frames = (texture.columns * texture.hop) as f32;
+        let end_frame = (start_frame + visible_frames).min(total_frames);
+        if end_frame <= start_frame {
+            return;
+        }
+        let frame_to_u = |frame: f32| (frame / texture.hop as f32 + 0.5) / texture.columns as f32;
+        let right = rect.left() + (end_frame - start_frame) / visible_frames * rect.width();
+        let image_rect = Rect::from_min_max(rect.min, egui::pos2(right.min(rect.right()), rect.bottom()));
+        let uv = Rect::from_min_max(egui::pos2(frame_to_u(start_frame), 0.0), egui::pos2(frame_to_u(end_frame), 1.0));
+        painter.image(texture.texture.id(), image_rect, uv, Color32::WHITE);
+
+        // Frequency guides
+        for (freq, label) in [(100.0, "100 Hz"), (1000.0, "1 kHz"), (10000.0, "10 kHz")] {
+            let position = frequency_position(freq, texture.sample_rate);
+            if !(0.0..=1.0).contains(&position) {
+                continue;
+            }
+            let y = rect.bottom() - position * rect.height();
+            painter.line_segment(
+                [egui::pos2(rect.left(), y), egui::pos2(rect.right(), y)],
+                Stroke::new(0.5, Color32::from_white_alpha(40)),
+            );
+            painter.text(
+                egui::pos2(rect.left() + 2.0, y - 2.0),
+                egui::Align2::LEFT_BOTTOM,
+                label,
+                egui::FontId::proportional(9.0),
+                Color32::from_gray(170),
+            );
+        }
+    }
+
     fn calculate_marker_interval(seconds_per_pixel: f32) -> f32 {
         let min_pixel_spacing = 80.0;
         let min_interval = seconds_per_pixel * min_pixel_spacing;
@@ -214,6 +371,48 @@ impl ClipEditorPanel {
     }
 }
 
+/// Identifies the audio a spectrogram was made from, so a trimmed or replaced clip is analysed again
+fn clip_key(clip: &AudioClip) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (clip.id, clip.source_offset, clip.length_samples, clip.samples.len()).hash(&mut hasher);
+    (clip.channels, clip.sample_rate).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Upload a spectrogram as a texture: one texel per column and band, highest band at the top
+fn spectral_texture(ctx: &egui::Context, key: u64, spectrogram: &Spectrogram) -> SpectralTexture {
+    let width = spectrogram.columns.max(1);
+    let mut image = ColorImage::new([width, SPECTROGRAM_BANDS], Color32::BLACK);
+    for column in 0..spectrogram.columns {
+        for band in 0..SPECTROGRAM_BANDS {
+            let row = SPECTROGRAM_BANDS - 1 - band;
+            let t = 1.0 + spectrogram.level(column, band) / SPECTRAL_RANGE_DB;
+            image.pixels[row * width + column] = heat(t);
+        }
+    }
+    SpectralTexture {
+        key,
+        texture: ctx.load_texture("spectrogram", image, TextureOptions::LINEAR),
+        columns: width,
+        hop: spectrogram.hop.max(1),
+        sample_rate: spectrogram.sample_rate,
+    }
+}
+
+/// Colour for a level from 0 (silent, black) through purple, red and orange to 1 (full scale, pale yellow)
+fn heat(t: f32) -> Color32 {
+    const STOPS: [Color32; 5] = [
+        Color32::from_rgb(0, 0, 4),
+        Color32::from_rgb(70, 12, 110),
+        Color32::from_rgb(180, 40, 80),
+        Color32::from_rgb(245, 125, 20),
+        Color32::from_rgb(252, 250, 165),
+    ];
+    let scaled = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let idx = (scaled as usize).min(STOPS.len() - 2);
+    STOPS[idx].lerp_to_gamma(STOPS[idx + 1], scaled - idx as f32)
+}
+
 impl Default for ClipEditorPanel {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,166 @@
+//! Control surface panel - pick a controller profile and edit its bindings
+
+use std::net::{Ipv4Addr, SocketAddr};
+
+use egui::{Color32, ScrollArea, Ui, Vec2};
+use hallucinator_core::{SurfaceAction, SurfaceProfile, SURFACE_STRIPS};
+use hallucinator_services::OscServer;
+
+/// Action returned from the control surface panel
+pub enum ControlSurfaceAction {
+    None,
+    SelectProfile(String),
+    /// Append a binding and learn its control
+    AddBinding,
+    RemoveBinding(usize),
+    SetAction(usize, SurfaceAction),
+    SetRelative(usize, bool),
+    /// Bind the next incoming control to this binding
+    Learn(usize),
+    CancelLearn,
+    SaveProfile(String),
+    /// Start the OSC server at an address, or stop it (None)
+    SetOscServer(Option<SocketAddr>),
+}
+
+/// Control surface mapping editor
+pub struct ControlSurfacePanel {
+    save_name: String,
+    osc_port: u16,
+    /// Listen on every interface rather than localhost only
+    osc_network: bool,
+}
+
+impl ControlSurfacePanel {
+    pub fn new() -> Self {
+        Self {
+            save_name: String::new(),
+            osc_port: OscServer::DEFAULT_PORT,
+            osc_network: false,
+        }
+    }
+
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        profile: &SurfaceProfile,
+        profiles: &[String],
+        bank: usize,
+        learning: Option<usize>,
+        midi_available: bool,
+        osc_addr: Option<SocketAddr>,
+    ) -> ControlSurfaceAction {
+        let mut action = ControlSurfaceAction::None;
+
+        ui.horizontal(|ui| {
+            let mut osc_on = osc_addr.is_some();
+            if ui.checkbox(&mut osc_on, "OSC server").changed() {
+                let ip = if self.osc_network { Ipv4Addr::UNSPECIFIED } else { Ipv4Addr::LOCALHOST };
+                action = ControlSurfaceAction::SetOscServer(osc_on.then_some(SocketAddr::from((ip, self.osc_port))));
+            }
+            match osc_addr {
+                Some(addr) => {
+                    self.osc_port = addr.port();
+                    self.osc_network = !addr.ip().is_loopback();
+                    ui.label(format!("listening on UDP {}", addr));
+                }
+                None => {
+                    ui.label("port");
+                    ui.add(egui::DragValue::new(&mut self.osc_port).range(1024..=65535));
+                    ui.checkbox(&mut self.osc_network, "Allow from network")
+                        .on_hover_text("Accept messages from other machines, not just this one");
+                }
+            }
+        });
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("surface_profile")
+                .selected_text(&profile.name)
+                .width(140.0)
+                .show_ui(ui, |ui| {
+                    for name in profiles {
+                        if ui.selectable_label(*name == profile.name, name).clicked() {
+                            action = ControlSurfaceAction::SelectProfile(name.clone());
+                        }
+                    }
+                });
+
+            ui.add(egui::TextEdit::singleline(&mut self.save_name).hint_text("profile name").desired_width(110.0));
+            let can_save = !self.save_name.trim().is_empty();
+            if ui.add_enabled(can_save, egui::Button::new("Save")).clicked() {
+                action = ControlSurfaceAction::SaveProfile(self.save_name.trim().to_string());
+            }
+
+            ui.separator();
+            ui.label(format!("Strips: tracks {}-{}", bank + 1, bank + SURFACE_STRIPS));
+        });
+
+        if !midi_available {
+            ui.colored_label(Color32::from_rgb(220, 160, 50), "No MIDI input device (connect one and restart)");
+        }
+        ui.separator();
+
+        ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("surface_bindings")
+                .striped(true)
+                .spacing(Vec2::new(8.0, 4.0))
+                .show(ui, |ui| {
+                    ui.strong("Control");
+                    ui.strong("Action");
+                    ui.strong("Relative");
+                    ui.label("");
+                    ui.end_row();
+
+                    for (idx, binding) in profile.bindings.iter().enumerate() {
+                        if learning == Some(idx) {
+                            let waiting = egui::Button::new(
+                                egui::RichText::new("move a control...").color(Color32::from_rgb(255, 180, 60)),
+                            );
+                            if ui.add(waiting).on_hover_text("Click to cancel").clicked() {
+                                action = ControlSurfaceAction::CancelLearn;
+                            }
+                        } else if ui.button(binding.control.label()).on_hover_text("Click, then move a control").clicked() {
+                            action = ControlSurfaceAction::Learn(idx);
+                        }
+
+                        egui::ComboBox::from_id_salt(("surface_action", idx))
+                            .selected_text(binding.action.label())
+                            .width(130.0)
+                            .show_ui(ui, |ui| {
+                                ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                                    for choice in SurfaceAction::choices() {
+                                        if ui.selectable_label(choice == binding.action, choice.label()).clicked() {
+                                            action = ControlSurfaceAction::SetAction(idx, choice);
+                                        }
+                                    }
+                                });
+                            });
+
+                        let mut relative = binding.relative;
+                        let checkbox = ui.add_enabled(binding.action.is_continuous(), egui::Checkbox::without_text(&mut relative));
+                        if checkbox.changed() {
+                            action = ControlSurfaceAction::SetRelative(idx, relative);
+                        }
+
+                        if ui.small_button("×").clicked() {
+                            action = ControlSurfaceAction::RemoveBinding(idx);
+                        }
+                        ui.end_row();
+                    }
+                });
+
+            if ui.button("+ Binding").clicked() {
+                action = ControlSurfaceAction::AddBinding;
+            }
+        });
+
+        action
+    }
+}
+
+impl Default for ControlSurfacePanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,8 +1,12 @@
 //! Device rack panel - horizontal signal chain for selected track
 
+use std::path::PathBuf;
+
 use egui::{Color32, Rect, ScrollArea, Sense, Stroke, Ui, Vec2};
+use hallucinator_core::{DeviceTarget, MacroBank, MacroCurve, MacroMapping, MACRO_COUNT};
+use hallucinator_services::{EffectParam, Oversampling, Vst3PluginInfo, NATIVE_EFFECTS};
 
-/// Info about a device in the chain
+/// Info about a device in the chain (effects use their chain index as `id`)
 #[derive(Clone)]
 pub struct DeviceInfo {
     pub id: u64,
@@ -10,6 +14,18 @@ pub struct DeviceInfo {
     pub is_instrument: bool,
     pub is_bypassed: bool,
     pub has_ui: bool,
+    pub oversampling: Oversampling,
+    /// Dry/wet mix of an effect insert (0-1)
+    pub mix: f32,
+    /// Instrument still loading in the background, shown but not usable yet
+    pub loading: bool,
+}
+
+/// A device on the selected track whose parameters can be mapped to macros or modulators
+pub struct MappableDevice {
+    pub target: DeviceTarget,
+    pub name: String,
+    pub params: Vec<EffectParam>,
 }
 
 /// Action returned from device rack
@@ -18,28 +34,65 @@ pub enum DeviceRackAction {
     None,
     OpenPluginWindow(u64),
     ToggleBypass(u64),
+    /// Remove the effect at this chain index (its `DeviceInfo::id`)
     RemoveDevice(u64),
-    AddEffect,
+    /// Move the effect at the first chain index to the second
+    MoveEffect(usize, usize),
+    /// Insert a native effect (by `NATIVE_EFFECTS` id) at the end of the track chain
+    AddEffect(&'static str),
+    /// Insert a VST3 effect at the end of the track chain
+    AddVst3Effect(Vst3PluginInfo),
+    /// Play a sample file from a new Sampler instrument on the track
+    LoadSampler(PathBuf),
+    /// Open the parameter window of the effect at this chain index
+    OpenEffectWindow(usize),
+    /// Toggle bypass of the effect at this chain index
+    ToggleEffectBypass(usize),
+    /// Oversample the effect at this chain index
+    SetEffectOversampling(usize, Oversampling),
+    /// Set the dry/wet mix (0-1) of the effect at this chain index
+    SetEffectMix(usize, f32),
+    /// Move a macro knob (index, 0-1 position)
+    SetMacro(usize, f32),
+    RenameMacro(usize, String),
+    /// Map a parameter to a macro knob
+    MapMacro(usize, MacroMapping),
+    /// Replace a macro's mapping (macro index, mapping index)
+    UpdateMacroMapping(usize, usize, MacroMapping),
+    RemoveMacroMapping(usize, usize),
 }
 
 /// Device rack panel state
 pub struct DeviceRackPanel {
-    selected_device_id: Option<u64>,
+    selected_device: Option<(bool, u64)>,  // (is_instrument, id)
+    /// Chain index of the effect being dragged to a new slot
+    dragging_effect: Option<usize>,
 }
 
 impl DeviceRackPanel {
     pub fn new() -> Self {
         Self {
-            selected_device_id: None,
+            selected_device: None,
+            dragging_effect: None,
         }
     }
 
+    /// Device selected in the rack (clicked), if any
+    pub fn selected_target(&self) -> Option<DeviceTarget> {
+        self.selected_device.map(|(is_instrument, id)| {
+            if is_instrument { DeviceTarget::Instrument } else { DeviceTarget::Effect(id as usize) }
+        })
+    }
+
     pub fn ui(
         &mut self,
         ui: &mut Ui,
         track_name: Option<&str>,
         instrument: Option<DeviceInfo>,
         effects: &[DeviceInfo],
+        macros: Option<&MacroBank>,
+        macro_sources: &[MappableDevice],
+        plugins: &[Vst3PluginInfo],
     ) -> DeviceRackAction {
         let mut action = DeviceRackAction::None;
 
@@ -61,15 +114,48 @@ impl DeviceRackPanel {
             return action;
         }
 
+        // VST3 effects and samples dragged from the browser drop anywhere on the rack
+        let rack_rect = ui.available_rect_before_wrap();
+        let dragged_plugin = egui::DragAndDrop::payload::<Vst3PluginInfo>(ui.ctx());
+        let dragged_sample = egui::DragAndDrop::payload::<PathBuf>(ui.ctx());
+        let pointer_in_rack = ui.input(|i| i.pointer.hover_pos()).is_some_and(|p| rack_rect.contains(p));
+        if pointer_in_rack && (dragged_plugin.is_some() || dragged_sample.is_some()) {
+            ui.painter().rect_stroke(
+                rack_rect,
+                4.0,
+                Stroke::new(1.5, Color32::from_rgb(80, 120, 180)),
+                egui::StrokeKind::Inside,
+            );
+            if ui.input(|i| i.pointer.any_released()) {
+                if let Some(plugin) = &dragged_plugin {
+                    action = DeviceRackAction::AddVst3Effect((**plugin).clone());
+                } else if let Some(path) = &dragged_sample {
+                    action = DeviceRackAction::LoadSampler((**path).clone());
+                }
+            }
+        }
+
         ScrollArea::horizontal().show(ui, |ui| {
             ui.horizontal(|ui| {
                 ui.spacing_mut().item_spacing.x = 8.0;
 
+                if let Some(macros) = macros {
+                    let macro_action = self.draw_macro_strip(ui, macros, macro_sources);
+                    if !matches!(macro_action, DeviceRackAction::None) {
+                        action = macro_action;
+                    }
+                    ui.separator();
+                }
+
                 // Draw instrument slot (if MIDI track)
                 if let Some(inst) = &instrument {
-                    let device_action = self.draw_device(ui, inst, true);
-                    if !matches!(device_action, DeviceRackAction::None) {
-                        action = device_action;
+                    if inst.loading {
+                        draw_loading_device(ui, inst);
+                    } else {
+                        let (device_action, _) = self.draw_device(ui, inst, true);
+                        if !matches!(device_action, DeviceRackAction::None) {
+                            action = device_action;
+                        }
                     }
 
                     // Arrow connector
@@ -77,42 +163,120 @@ impl DeviceRackPanel {
                 }
 
                 // Draw effect chain
+                let mut effect_rects = Vec::with_capacity(effects.len());
                 for (idx, effect) in effects.iter().enumerate() {
-                    let device_action = self.draw_device(ui, effect, false);
+                    let (device_action, rect) = self.draw_device(ui, effect, false);
                     if !matches!(device_action, DeviceRackAction::None) {
                         action = device_action;
                     }
+                    effect_rects.push(rect);
 
                     if idx < effects.len() - 1 {
                         ui.label("→");
                     }
                 }
 
+                if let Some(from) = self.dragging_effect {
+                    let move_action = self.draw_effect_drop(ui, from, &effect_rects);
+                    if !matches!(move_action, DeviceRackAction::None) {
+                        action = move_action;
+                    }
+                }
+
                 // Add effect button
                 ui.add_space(8.0);
-                let add_btn = ui.button("+ Add Effect");
-                if add_btn.clicked() {
-                    action = DeviceRackAction::AddEffect;
-                }
+                ui.menu_button("+ Add Effect", |ui| {
+                    for &(id, name) in NATIVE_EFFECTS {
+                        if ui.button(name).clicked() {
+                            action = DeviceRackAction::AddEffect(id);
+                            ui.close_menu();
+                        }
+                    }
+                    ui.separator();
+                    ui.menu_button("VST3", |ui| {
+                        if plugins.is_empty() {
+                            ui.label("No plugins scanned");
+                        }
+                        ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                            for plugin in plugins {
+                                if ui.button(&plugin.name).clicked() {
+                                    action = DeviceRackAction::AddVst3Effect(plugin.clone());
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+                });
             });
         });
 
         action
     }
 
-    fn draw_device(&mut self, ui: &mut Ui, device: &DeviceInfo, is_instrument: bool) -> DeviceRackAction {
+    /// Compact 2x4 grid of macro knobs; right-click a knob to map parameters
+    fn draw_macro_strip(&mut self, ui: &mut Ui, macros: &MacroBank, sources: &[MappableDevice]) -> DeviceRackAction {
+        let mut action = DeviceRackAction::None;
+
+        ui.vertical(|ui| {
+            ui.spacing_mut().item_spacing = Vec2::new(4.0, 2.0);
+            for row in 0..2 {
+                ui.horizontal(|ui| {
+                    for index in (row * MACRO_COUNT / 2)..((row + 1) * MACRO_COUNT / 2) {
+                        let knob_action = draw_macro_knob(ui, macros, index, sources);
+                        if !matches!(knob_action, DeviceRackAction::None) {
+                            action = knob_action;
+                        }
+                    }
+                });
+            }
+        });
+
+        action
+    }
+
+    /// Insertion marker for an effect being dragged; dropping it moves the effect there
+    fn draw_effect_drop(&mut self, ui: &mut Ui, from: usize, effect_rects: &[Rect]) -> DeviceRackAction {
+        let Some(pointer) = ui.input(|i| i.pointer.interact_pos()) else {
+            return DeviceRackAction::None;
+        };
+        ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+
+        // Slot between devices the pointer is over (0 = before the first effect)
+        let slot = effect_rects.iter().filter(|r| r.center().x < pointer.x).count();
+        let to = if slot > from { slot - 1 } else { slot };
+        if let (Some(first), Some(last)) = (effect_rects.first(), effect_rects.last()) {
+            let x = match slot {
+                0 => first.left() - 4.0,
+                n => effect_rects[n - 1].right() + 4.0,
+            };
+            ui.painter().line_segment(
+                [egui::pos2(x, first.top()), egui::pos2(x, last.bottom())],
+                Stroke::new(2.0, Color32::from_rgb(120, 200, 255)),
+            );
+        }
+
+        if !ui.input(|i| i.pointer.any_down()) {
+            self.dragging_effect = None;
+            if to != from && to < effect_rects.len() {
+                self.selected_device = Some((false, to as u64));
+                return DeviceRackAction::MoveEffect(from, to);
+            }
+        }
+        DeviceRackAction::None
+    }
+
+    fn draw_device(&mut self, ui: &mut Ui, device: &DeviceInfo, is_instrument: bool) -> (DeviceRackAction, Rect) {
         let mut action = DeviceRackAction::None;
 
         let device_width = 100.0;
         let device_height = 80.0;
 
-        let (response, painter) = ui.allocate_painter(
-            Vec2::new(device_width, device_height),
-            Sense::click(),
-        );
+        // Effects drag to reorder the chain
+        let sense = if is_instrument { Sense::click() } else { Sense::click_and_drag() };
+        let (response, painter) = ui.allocate_painter(Vec2::new(device_width, device_height), sense);
         let rect = response.rect;
 
-        let is_selected = self.selected_device_id == Some(device.id);
+        let is_selected = self.selected_device == Some((is_instrument, device.id));
 
         // Background
         let bg_color = if device.is_bypassed {
@@ -153,17 +317,10 @@ impl DeviceRackPanel {
             );
         }
 
-        // Device name (truncate if needed)
-        let name_display = if device.name.len() > 12 {
-            format!("{}...", &device.name[..10])
-        } else {
-            device.name.clone()
-        };
-
         painter.text(
             egui::pos2(rect.center().x, rect.top() + 24.0),
             egui::Align2::CENTER_TOP,
-            &name_display,
+            short_name(&device.name),
             egui::FontId::proportional(10.0),
             if device.is_bypassed {
                 Color32::from_gray(120)
@@ -183,6 +340,40 @@ impl DeviceRackPanel {
             );
         }
 
+        if device.oversampling != Oversampling::Off {
+            painter.text(
+                egui::pos2(rect.center().x, rect.bottom() - 12.0),
+                egui::Align2::CENTER_CENTER,
+                device.oversampling.label(),
+                egui::FontId::proportional(9.0),
+                Color32::from_rgb(120, 200, 255),
+            );
+        }
+
+        // Dry/wet mix bar (effects only): drag to set, double-click for fully wet
+        if !is_instrument {
+            let mix_rect = Rect::from_min_size(
+                egui::pos2(rect.left() + 8.0, rect.top() + 38.0),
+                Vec2::new(rect.width() - 16.0, 5.0),
+            );
+            painter.rect_filled(mix_rect, 2.0, Color32::from_gray(45));
+            let mut filled = mix_rect;
+            filled.set_width(mix_rect.width() * device.mix);
+            painter.rect_filled(filled, 2.0, Color32::from_rgb(120, 200, 255));
+
+            let mix_response = ui
+                .allocate_rect(mix_rect.expand2(Vec2::new(0.0, 3.0)), Sense::click_and_drag())
+                .on_hover_text(format!("Mix {:.0}%", device.mix * 100.0));
+            if mix_response.double_clicked() {
+                action = DeviceRackAction::SetEffectMix(device.id as usize, 1.0);
+            } else if (mix_response.dragged() || mix_response.clicked())
+                && let Some(pos) = mix_response.interact_pointer_pos()
+            {
+                let mix = ((pos.x - mix_rect.left()) / mix_rect.width()).clamp(0.0, 1.0);
+                action = DeviceRackAction::SetEffectMix(device.id as usize, mix);
+            }
+        }
+
         // Bypass button at bottom-left
         let bypass_rect = Rect::from_min_size(
             egui::pos2(rect.left() + 4.0, rect.bottom() - 20.0),
@@ -204,7 +395,11 @@ impl DeviceRackPanel {
 
         let bypass_response = ui.allocate_rect(bypass_rect, Sense::click());
         if bypass_response.clicked() {
-            action = DeviceRackAction::ToggleBypass(device.id);
+            action = if is_instrument {
+                DeviceRackAction::ToggleBypass(device.id)
+            } else {
+                DeviceRackAction::ToggleEffectBypass(device.id as usize)
+            };
         }
 
         // Remove button at bottom-right (only for effects)
@@ -225,19 +420,181 @@ impl DeviceRackPanel {
             let remove_response = ui.allocate_rect(remove_rect, Sense::click());
             if remove_response.clicked() {
                 action = DeviceRackAction::RemoveDevice(device.id);
+                self.selected_device = None;
             }
         }
 
         // Handle main click/double-click
         if response.clicked() {
-            self.selected_device_id = Some(device.id);
+            self.selected_device = Some((is_instrument, device.id));
+        }
+        if !is_instrument && response.drag_started() {
+            self.dragging_effect = Some(device.id as usize);
+        }
+        if !is_instrument {
+            response.context_menu(|ui| {
+                ui.label("Oversampling");
+                for mode in Oversampling::ALL {
+                    if ui.radio(device.oversampling == mode, mode.label()).clicked() {
+                        action = DeviceRackAction::SetEffectOversampling(device.id as usize, mode);
+                        ui.close_menu();
+                    }
+                }
+            });
         }
         if response.double_clicked() {
-            action = DeviceRackAction::OpenPluginWindow(device.id);
+            action = if is_instrument {
+                DeviceRackAction::OpenPluginWindow(device.id)
+            } else {
+                DeviceRackAction::OpenEffectWindow(device.id as usize)
+            };
         }
 
-        action
+        (action, rect)
+    }
+}
+
+/// Device name cut to fit a device box
+fn short_name(name: &str) -> String {
+    if name.len() > 12 {
+        format!("{}...", &name[..10])
+    } else {
+        name.to_string()
+    }
+}
+
+/// Placeholder for an instrument still loading: its name under a spinner
+fn draw_loading_device(ui: &mut Ui, device: &DeviceInfo) {
+    let (rect, response) = ui.allocate_exact_size(Vec2::new(100.0, 80.0), Sense::hover());
+    let painter = ui.painter();
+    painter.rect_filled(rect, 4.0, Color32::from_gray(40));
+    painter.rect_stroke(rect, 4.0, Stroke::new(1.5, Color32::from_gray(80)), egui::StrokeKind::Outside);
+    painter.text(
+        egui::pos2(rect.center().x, rect.top() + 24.0),
+        egui::Align2::CENTER_TOP,
+        short_name(&device.name),
+        egui::FontId::proportional(10.0),
+        Color32::from_gray(160),
+    );
+    let spinner_rect = Rect::from_center_size(egui::pos2(rect.center().x, rect.bottom() - 22.0), Vec2::splat(16.0));
+    ui.put(spinner_rect, egui::Spinner::new().size(16.0));
+    response.on_hover_text(format!("Loading {}…", device.name));
+}
+
+fn draw_macro_knob(ui: &mut Ui, macros: &MacroBank, index: usize, sources: &[MappableDevice]) -> DeviceRackAction {
+    let mut action = DeviceRackAction::None;
+    let knob = &macros.knobs[index];
+    let label = macros.label(index);
+
+    let (rect, response) = ui.allocate_exact_size(Vec2::new(40.0, 36.0), Sense::click_and_drag());
+    let painter = ui.painter();
+    let center = egui::pos2(rect.center().x, rect.top() + 12.0);
+    let radius = 10.0;
+
+    let mapped = !knob.mappings.is_empty();
+    let arc_color = if mapped { Color32::from_rgb(120, 200, 255) } else { Color32::from_gray(110) };
+    painter.circle_filled(center, radius, Color32::from_gray(35));
+
+    // 270° sweep from bottom-left (min) to bottom-right (max)
+    let start = std::f32::consts::PI * 0.75;
+    let sweep = std::f32::consts::PI * 1.5;
+    let points: Vec<egui::Pos2> = (0..=24)
+        .map(|i| {
+            let angle = start + sweep * knob.value * i as f32 / 24.0;
+            center + Vec2::angled(angle) * radius
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, Stroke::new(2.5, arc_color)));
+    let pointer = start + sweep * knob.value;
+    painter.line_segment([center, center + Vec2::angled(pointer) * (radius - 2.0)], Stroke::new(1.5, Color32::WHITE));
+
+    let short_label: String = label.chars().take(7).collect();
+    painter.text(
+        egui::pos2(rect.center().x, rect.bottom() - 1.0),
+        egui::Align2::CENTER_BOTTOM,
+        short_label,
+        egui::FontId::proportional(8.0),
+        Color32::from_gray(190),
+    );
+
+    if response.dragged() {
+        let delta = -response.drag_delta().y * 0.005;
+        action = DeviceRackAction::SetMacro(index, (knob.value + delta).clamp(0.0, 1.0));
     }
+    let response = response.on_hover_text(format!("{}: {:.0}% ({} mapped)", label, knob.value * 100.0, knob.mappings.len()));
+
+    response.context_menu(|ui| {
+        let mut name = knob.name.clone();
+        ui.horizontal(|ui| {
+            ui.label("Name");
+            if ui.add(egui::TextEdit::singleline(&mut name).hint_text(label.as_str()).desired_width(100.0)).changed() {
+                action = DeviceRackAction::RenameMacro(index, name.clone());
+            }
+        });
+        ui.separator();
+
+        ui.menu_button("Map parameter", |ui| {
+            if sources.is_empty() {
+                ui.label("No devices on this track");
+            }
+            for source in sources {
+                ui.menu_button(&source.name, |ui| {
+                    ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        for param in &source.params {
+                            if ui.button(&param.name).clicked() {
+                                action = DeviceRackAction::MapMacro(
+                                    index,
+                                    MacroMapping::new(source.target, &param.name, param.min, param.max),
+                                );
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+            }
+        });
+
+        for (mapping_idx, mapping) in knob.mappings.iter().enumerate() {
+            ui.separator();
+            let device = sources
+                .iter()
+                .find(|s| s.target == mapping.target)
+                .map(|s| s.name.as_str())
+                .unwrap_or("(missing)");
+            ui.horizontal(|ui| {
+                ui.label(format!("{} · {}", device, mapping.param));
+                if ui.small_button("×").clicked() {
+                    action = DeviceRackAction::RemoveMacroMapping(index, mapping_idx);
+                }
+            });
+
+            let mut edited = mapping.clone();
+            ui.horizontal(|ui| {
+                let range = sources
+                    .iter()
+                    .find(|s| s.target == mapping.target)
+                    .and_then(|s| s.params.iter().find(|p| p.name == mapping.param))
+                    .map(|p| p.min..=p.max)
+                    .unwrap_or(f32::MIN..=f32::MAX);
+                let speed = ((range.end() - range.start()) / 200.0).clamp(0.001, 100.0);
+                ui.add(egui::DragValue::new(&mut edited.min).range(range.clone()).speed(speed).prefix("min "));
+                ui.add(egui::DragValue::new(&mut edited.max).range(range).speed(speed).prefix("max "));
+                egui::ComboBox::from_id_salt(("macro_curve", index, mapping_idx))
+                    .selected_text(edited.curve.name())
+                    .width(70.0)
+                    .show_ui(ui, |ui| {
+                        for curve in MacroCurve::ALL {
+                            ui.selectable_value(&mut edited.curve, curve, curve.name());
+                        }
+                    });
+            });
+            if edited != *mapping {
+                action = DeviceRackAction::UpdateMacroMapping(index, mapping_idx, edited);
+            }
+        }
+    });
+
+    action
 }
 
 impl Default for DeviceRackPanel {
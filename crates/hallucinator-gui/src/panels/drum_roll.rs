@@ -1,5 +1,7 @@
 //! Drum roll panel for 808-style drum sequencing
 
+use super::generate::GenerateSettings;
+use super::grid::GridSettings;
 use crate::clipboard::DawClipboard;
 use egui::{Color32, Key, Pos2, Rect, Sense, Stroke, StrokeKind, Ui, Vec2};
 use hallucinator_core::{ClipId, MidiClip, MidiNote};
@@ -50,6 +52,55 @@ pub enum DrumRollAction {
     },
 }
 
+/// Per-step value edited in the lane under the grid
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum StepParam {
+    Velocity,
+    /// Trigger probability, 0-100%
+    Probability,
+    /// Microtiming offset, up to half a grid step early or late
+    Timing,
+}
+
+impl StepParam {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Velocity => "Velocity",
+            Self::Probability => "Chance",
+            Self::Timing => "Timing",
+        }
+    }
+
+    /// A note's value scaled to the lane height (0 = bottom, 1 = top; timing is centred)
+    fn get(&self, note: &MidiNote, max_offset: i32) -> f32 {
+        match self {
+            Self::Velocity => note.velocity as f32 / 127.0,
+            Self::Probability => note.probability as f32 / 100.0,
+            Self::Timing => 0.5 + note.timing_offset as f32 / (2.0 * max_offset.max(1) as f32),
+        }
+    }
+
+    fn set(&self, note: &mut MidiNote, t: f32, max_offset: i32) {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Velocity => note.velocity = (t * 127.0).round().max(1.0) as u8,
+            Self::Probability => note.probability = (t * 100.0).round() as u8,
+            Self::Timing => note.timing_offset = ((t - 0.5) * 2.0 * max_offset as f32).round() as i32,
+        }
+    }
+
+    fn label(&self, note: &MidiNote) -> String {
+        match self {
+            Self::Velocity => note.velocity.to_string(),
+            Self::Probability => format!("{}%", note.probability),
+            Self::Timing => format!("{:+}", note.timing_offset),
+        }
+    }
+}
+
+/// Height of the step value lane under the grid
+const STEP_LANE_HEIGHT: f32 = 64.0;
+
 /// Drum roll step sequencer panel
 pub struct DrumRollPanel {
     /// Pixels per beat horizontally
@@ -62,14 +113,23 @@ pub struct DrumRollPanel {
     scroll_x: f64,
     /// Currently editing clip
     editing_clip: Option<ClipId>,
-    /// Grid subdivision (0.25 = 16th notes)
+    /// Grid note value, feel, snap and swing
+    pub grid: GridSettings,
+    /// Grid step in beats for this frame (0.25 = 16th notes)
     grid_subdivision: f64,
-    /// Snap to grid enabled
-    pub snap_to_grid: bool,
     /// Loop selection state (start beat when Ctrl+dragging)
     loop_drag_start: Option<f64>,
     /// Keys currently pressed (for drum triggering)
     keys_pressed: std::collections::HashSet<Key>,
+    /// Beat range of the last Ctrl+drag, filled by Generate
+    selection: Option<(f64, f64)>,
+    /// Lane the generator writes to
+    generate_lane: usize,
+    generate: GenerateSettings,
+    /// Value shown and edited in the step lane
+    step_param: StepParam,
+    /// Drum lane whose steps the step lane edits (click a lane label to pick)
+    edit_lane: usize,
 }
 
 impl Default for DrumRollPanel {
@@ -86,10 +146,15 @@ impl DrumRollPanel {
             label_width: 60.0,
             scroll_x: 0.0,
             editing_clip: None,
-            grid_subdivision: 0.25, // 16th notes
-            snap_to_grid: true,
+            grid: GridSettings::fixed(0.25), // 16th notes
+            grid_subdivision: 0.25,
             loop_drag_start: None,
             keys_pressed: std::collections::HashSet::new(),
+            selection: None,
+            generate_lane: 0,
+            generate: GenerateSettings::default(),
+            step_param: StepParam::Velocity,
+            edit_lane: 0,
         }
     }
 
@@ -109,10 +174,12 @@ impl DrumRollPanel {
         if self.editing_clip != Some(clip.id) {
             self.editing_clip = Some(clip.id);
             self.scroll_x = 0.0;
+            self.selection = None;
         }
 
         let samples_per_beat = sample_rate as f64 * 60.0 / bpm;
         let clip_length_beats = clip.length_samples as f64 / samples_per_beat;
+        self.grid_subdivision = self.grid.step(GridSettings::zoom_step(self.pixels_per_beat));
 
         // Toolbar
         ui.horizontal(|ui| {
@@ -122,18 +189,40 @@ impl DrumRollPanel {
                 };
             }
             ui.separator();
-            ui.label("Grid:");
-            if ui.selectable_label(self.grid_subdivision == 0.25, "1/16").clicked() {
-                self.grid_subdivision = 0.25;
-            }
-            if ui.selectable_label(self.grid_subdivision == 0.5, "1/8").clicked() {
-                self.grid_subdivision = 0.5;
-            }
-            if ui.selectable_label(self.grid_subdivision == 1.0, "1/4").clicked() {
-                self.grid_subdivision = 1.0;
+            ui.menu_button(format!("Grid: {}", self.grid.label()), |ui| self.grid.menu_ui(ui));
+            ui.separator();
+            ui.menu_button("Generate...", |ui| {
+                egui::ComboBox::from_label("Lane")
+                    .selected_text(DRUM_LANES[self.generate_lane].name)
+                    .show_ui(ui, |ui| {
+                        for (i, lane) in DRUM_LANES.iter().enumerate() {
+                            ui.selectable_value(&mut self.generate_lane, i, lane.name);
+                        }
+                    });
+                if self.generate.ui(ui) {
+                    let (start, end) = self.selection.unwrap_or((0.0, clip_length_beats));
+                    let pitch = DRUM_LANES[self.generate_lane].pitch;
+                    self.generate.fill(clip, pitch, start, end, self.grid_subdivision);
+                    action = DrumRollAction::ClipModified;
+                    ui.close_menu();
+                }
+            });
+            match self.selection {
+                Some((start, end)) => {
+                    ui.label(format!("Sel: {:.1}-{:.1} bars", start / 4.0, end / 4.0));
+                    if ui.small_button("×").clicked() {
+                        self.selection = None;
+                    }
+                }
+                None => {
+                    ui.label("Sel: whole clip");
+                }
             }
             ui.separator();
-            ui.checkbox(&mut self.snap_to_grid, "Snap");
+            ui.label("Lane:");
+            for param in [StepParam::Velocity, StepParam::Probability, StepParam::Timing] {
+                ui.selectable_value(&mut self.step_param, param, param.name());
+            }
         });
 
         ui.separator();
@@ -144,7 +233,7 @@ impl DrumRollPanel {
         let grid_height = self.lane_height * DRUM_LANES.len() as f32;
 
         let (response, painter) = ui.allocate_painter(
-            Vec2::new(available.x, grid_height.max(available.y)),
+            Vec2::new(available.x, (grid_height + STEP_LANE_HEIGHT).max(available.y)),
             Sense::click_and_drag(),
         );
 
@@ -188,8 +277,9 @@ impl DrumRollPanel {
                 bg_color,
             );
 
-            // Label background
-            painter.rect_filled(label_rect, 0.0, lane.color.gamma_multiply(0.3));
+            // Label background (brighter for the lane the step lane edits)
+            let label_alpha = if i == self.edit_lane { 0.6 } else { 0.3 };
+            painter.rect_filled(label_rect, 0.0, lane.color.gamma_multiply(label_alpha));
 
             // Label text
             painter.text(
@@ -214,8 +304,7 @@ impl DrumRollPanel {
         let end_beat = start_beat + visible_beats;
 
         // Beat lines
-        let mut beat = (start_beat / self.grid_subdivision).floor() * self.grid_subdivision;
-        while beat <= end_beat && beat <= clip_length_beats {
+        for beat in self.grid.lines(start_beat, end_beat.min(clip_length_beats), self.grid_subdivision) {
             let x = grid_rect.min.x + ((beat - start_beat) * self.pixels_per_beat as f64) as f32;
             let is_bar = (beat % 4.0).abs() < 0.001;
             let is_beat = (beat % 1.0).abs() < 0.001;
@@ -229,7 +318,6 @@ impl DrumRollPanel {
             };
 
             painter.vline(x, grid_rect.min.y..=grid_rect.max.y, Stroke::new(1.0, color));
-            beat += self.grid_subdivision;
         }
 
         // Draw existing notes
@@ -259,8 +347,32 @@ impl DrumRollPanel {
             let color = DRUM_LANES[lane_idx].color.gamma_multiply(alpha);
             painter.rect_filled(cell_rect, 4.0, color);
             painter.rect_stroke(cell_rect, 4.0, Stroke::new(1.0, Color32::WHITE.gamma_multiply(0.5)), StrokeKind::Inside);
+
+            // Chance below 100% is written on the cell
+            if note.probability < 100 && cell_width > 18.0 {
+                painter.text(
+                    cell_rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    format!("{}%", note.probability),
+                    egui::FontId::proportional(9.0),
+                    Color32::BLACK,
+                );
+            }
+
+            // Microtiming: a tick where the step actually sounds
+            if note.timing_offset != 0 {
+                let play_beat = note.play_tick() as f64 / clip.ppq as f64;
+                let play_x = grid_rect.min.x + ((play_beat - start_beat) * self.pixels_per_beat as f64) as f32;
+                painter.vline(play_x, cell_rect.y_range(), Stroke::new(2.0, Color32::WHITE));
+            }
         }
 
+        let step_lane_rect = Rect::from_min_size(
+            Pos2::new(grid_rect.min.x, grid_rect.max.y),
+            Vec2::new(grid_width, STEP_LANE_HEIGHT),
+        );
+        self.draw_step_lane(&painter, rect, step_lane_rect, clip, start_beat);
+
         // Draw playhead
         let playback_beat = if playback_position >= clip_start_sample {
             (playback_position - clip_start_sample) as f64 / samples_per_beat
@@ -275,6 +387,27 @@ impl DrumRollPanel {
 
         // Handle Ctrl+drag for loop selection
         let ctrl_held = ui.input(|i| i.modifiers.ctrl);
+
+        // Clicking a lane label picks the lane the step lane edits
+        if response.clicked()
+            && let Some(pos) = response.interact_pointer_pos()
+            && pos.x < grid_rect.min.x
+            && pos.y >= grid_rect.min.y
+            && pos.y < grid_rect.max.y
+        {
+            self.edit_lane = (((pos.y - grid_rect.min.y) / self.lane_height) as usize).min(DRUM_LANES.len() - 1);
+        }
+
+        // Click or drag in the step lane sets the value of the steps under the pointer
+        let lane_press = ui.input(|i| i.pointer.press_origin()).is_some_and(|p| step_lane_rect.contains(p));
+        if lane_press
+            && !ctrl_held
+            && (response.clicked() || response.dragged())
+            && let Some(pos) = response.interact_pointer_pos()
+            && self.edit_steps_at(pos, step_lane_rect, clip, start_beat)
+        {
+            action = DrumRollAction::ClipModified;
+        }
         if ctrl_held && response.drag_started() {
             if let Some(pos) = response.interact_pointer_pos() {
                 if grid_rect.contains(pos) {
@@ -324,6 +457,7 @@ impl DrumRollPanel {
                     };
 
                     if (loop_end - loop_start).abs() > 0.1 {
+                        self.selection = Some((loop_start, loop_end));
                         let start_sample = clip_start_sample + (loop_start * samples_per_beat) as u64;
                         let end_sample = clip_start_sample + (loop_end * samples_per_beat) as u64;
                         action = DrumRollAction::SetLoopRegion { start_sample, end_sample };
@@ -359,8 +493,8 @@ impl DrumRollPanel {
                     let lane_idx = (rel_y / self.lane_height) as usize;
 
                     if lane_idx < DRUM_LANES.len() {
-                        let snapped_beat = if self.snap_to_grid {
-                            (beat / self.grid_subdivision).floor() * self.grid_subdivision
+                        let snapped_beat = if self.grid.snap {
+                            self.grid.snap_floor(beat, self.grid_subdivision)
                         } else {
                             beat
                         };
@@ -393,4 +527,70 @@ impl DrumRollPanel {
 
         action
     }
+    /// Largest microtiming offset in ticks: half a grid step
+    fn max_timing_offset(&self, ppq: u16) -> i32 {
+        (self.grid_subdivision * ppq as f64 / 2.0) as i32
+    }
+
+    /// Set the step value of the edited lane's notes under `pos`. Returns true if any changed.
+    fn edit_steps_at(&self, pos: Pos2, lane_rect: Rect, clip: &mut MidiClip, start_beat: f64) -> bool {
+        let beat = start_beat + (pos.x - lane_rect.min.x) as f64 / self.pixels_per_beat as f64;
+        let t = (lane_rect.max.y - pos.y) / lane_rect.height();
+        let pitch = DRUM_LANES[self.edit_lane].pitch;
+        let max_offset = self.max_timing_offset(clip.ppq);
+        let ppq = clip.ppq as f64;
+        let mut changed = false;
+        for note in clip.notes.iter_mut().filter(|n| n.pitch == pitch) {
+            let note_beat = note.start_tick as f64 / ppq;
+            if beat >= note_beat && beat < note_beat + self.grid_subdivision {
+                self.step_param.set(note, t, max_offset);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Bars for the edited lane's steps: velocity and chance rise from the bottom,
+    /// timing from the centre line (up is late)
+    fn draw_step_lane(&self, painter: &egui::Painter, rect: Rect, lane_rect: Rect, clip: &MidiClip, start_beat: f64) {
+        let lane = &DRUM_LANES[self.edit_lane];
+        painter.rect_filled(lane_rect, 0.0, Color32::from_gray(25));
+        painter.hline(rect.min.x..=rect.max.x, lane_rect.min.y, Stroke::new(1.0, Color32::from_gray(70)));
+
+        let label_rect = Rect::from_min_max(Pos2::new(rect.min.x, lane_rect.min.y), Pos2::new(lane_rect.min.x, lane_rect.max.y));
+        painter.text(
+            label_rect.center(),
+            egui::Align2::CENTER_CENTER,
+            format!("{}\n{}", lane.name, self.step_param.name()),
+            egui::FontId::proportional(10.0),
+            Color32::GRAY,
+        );
+
+        let base = if self.step_param == StepParam::Timing { lane_rect.center().y } else { lane_rect.max.y };
+        if self.step_param == StepParam::Timing {
+            painter.hline(lane_rect.x_range(), base, Stroke::new(1.0, Color32::from_gray(60)));
+        }
+
+        let max_offset = self.max_timing_offset(clip.ppq);
+        let bar_width = ((self.grid_subdivision * self.pixels_per_beat as f64) as f32 * 0.6).max(2.0);
+        for note in clip.notes.iter().filter(|n| n.pitch == lane.pitch) {
+            let note_beat = note.start_tick as f64 / clip.ppq as f64;
+            let x = lane_rect.min.x + ((note_beat - start_beat) * self.pixels_per_beat as f64) as f32;
+            if x < lane_rect.min.x || x > lane_rect.max.x {
+                continue;
+            }
+            let top = lane_rect.max.y - self.step_param.get(note, max_offset) * lane_rect.height();
+            let bar = Rect::from_two_pos(Pos2::new(x, base), Pos2::new((x + bar_width).min(lane_rect.max.x), top));
+            painter.rect_filled(bar, 1.0, lane.color);
+            if bar_width > 18.0 {
+                painter.text(
+                    Pos2::new(x + 1.0, lane_rect.min.y + 1.0),
+                    egui::Align2::LEFT_TOP,
+                    self.step_param.label(note),
+                    egui::FontId::proportional(9.0),
+                    Color32::GRAY,
+                );
+            }
+        }
+    }
 }
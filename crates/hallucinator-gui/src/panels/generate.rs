@@ -0,0 +1,103 @@
+//! "Generate..." menu shared by the drum roll and piano roll - fills a beat
+//! range with a Euclidean or probability rhythm written as ordinary notes
+
+use egui::{DragValue, Slider, Ui};
+use hallucinator_core::{euclidean_rhythm, probability_rhythm, MidiClip, MidiNote};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum GenerateMode {
+    /// K pulses spread as evenly as possible over N steps, repeated across the range
+    Euclidean,
+    /// Every step hits with a chance set by the density
+    Probability,
+}
+
+/// Generator settings, kept between uses so a pattern can be tweaked and regenerated
+pub(super) struct GenerateSettings {
+    mode: GenerateMode,
+    steps: u8,
+    pulses: u8,
+    rotation: u8,
+    /// Chance of a hit per step, in percent
+    density: f32,
+    seed: u64,
+    velocity: u8,
+}
+
+impl Default for GenerateSettings {
+    fn default() -> Self {
+        Self {
+            mode: GenerateMode::Euclidean,
+            steps: 16,
+            pulses: 5,
+            rotation: 0,
+            density: 50.0,
+            seed: 1,
+            velocity: 100,
+        }
+    }
+}
+
+impl GenerateSettings {
+    /// Draw the generator controls; returns true when Generate is clicked
+    pub(super) fn ui(&mut self, ui: &mut Ui) -> bool {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.mode, GenerateMode::Euclidean, "Euclidean");
+            ui.selectable_value(&mut self.mode, GenerateMode::Probability, "Probability");
+        });
+        match self.mode {
+            GenerateMode::Euclidean => {
+                ui.add(Slider::new(&mut self.steps, 1..=32).text("Steps"));
+                self.pulses = self.pulses.min(self.steps);
+                ui.add(Slider::new(&mut self.pulses, 0..=self.steps).text("Pulses"));
+                ui.add(Slider::new(&mut self.rotation, 0..=self.steps - 1).text("Rotation"));
+            }
+            GenerateMode::Probability => {
+                ui.add(Slider::new(&mut self.density, 0.0..=100.0).suffix("%").text("Density"));
+                ui.horizontal(|ui| {
+                    ui.label("Seed");
+                    ui.add(DragValue::new(&mut self.seed));
+                    if ui.button("🎲").clicked() {
+                        self.seed = self.seed.wrapping_mul(6364136223846793005).wrapping_add(1) >> 16;
+                    }
+                });
+            }
+        }
+        ui.add(Slider::new(&mut self.velocity, 1..=127).text("Velocity"));
+        ui.button("Generate").clicked()
+    }
+
+    /// Hit pattern covering `len` steps
+    fn pattern(&self, len: usize) -> Vec<bool> {
+        match self.mode {
+            GenerateMode::Euclidean => {
+                let cycle = euclidean_rhythm(self.steps, self.pulses, self.rotation);
+                cycle.iter().copied().cycle().take(len).collect()
+            }
+            GenerateMode::Probability => probability_rhythm(len, self.density / 100.0, self.seed),
+        }
+    }
+
+    /// Replace the notes of `pitch` between `start_beat` and `end_beat` with the
+    /// pattern, one step per `step_beats`
+    pub(super) fn fill(&self, clip: &mut MidiClip, pitch: u8, start_beat: f64, end_beat: f64, step_beats: f64) {
+        let ppq = clip.ppq as f64;
+        let start_tick = (start_beat * ppq) as u64;
+        let end_tick = (end_beat * ppq) as u64;
+        let step_ticks = (step_beats * ppq) as u64;
+        if step_ticks == 0 || end_tick <= start_tick {
+            return;
+        }
+
+        clip.notes
+            .retain(|n| n.pitch != pitch || n.start_tick < start_tick || n.start_tick >= end_tick);
+
+        let len = ((end_tick - start_tick) / step_ticks) as usize;
+        for (step, hit) in self.pattern(len).into_iter().enumerate() {
+            if hit {
+                let tick = start_tick + step as u64 * step_ticks;
+                clip.add_note(MidiNote::new(pitch, self.velocity, tick, step_ticks));
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! Snap grid shared by the piano roll, drum roll and arrange view: a note value
+//! (or one that follows the zoom), straight/triplet/dotted feel, snap on/off and swing,
+//! plus how dragged items snap (to grid lines or by whole steps, to other clips' edges and
+//! markers, and audio trims to zero crossings)
+
+use egui::{Slider, Ui};
+
+/// Note values offered in the grid menu: (label, length in beats)
+const NOTE_VALUES: [(&str, f64); 4] = [("1/4", 1.0), ("1/8", 0.5), ("1/16", 0.25), ("1/32", 0.125)];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GridFeel {
+    Straight,
+    /// Three steps in the time of two
+    Triplet,
+    /// One and a half times the note value
+    Dotted,
+}
+
+/// How a dragged item's position snaps
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapMode {
+    /// Onto grid lines
+    Absolute,
+    /// By whole grid steps, keeping the item's offset from the grid
+    Relative,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GridSettings {
+    /// Follow the zoom level instead of a fixed note value
+    pub auto: bool,
+    /// Note value in beats when not following the zoom (1.0 = quarter)
+    pub value: f64,
+    pub feel: GridFeel,
+    pub snap: bool,
+    /// Share of each pair of steps taken by the first, in percent:
+    /// 50 is straight, about 66 a triplet shuffle
+    pub swing: f32,
+    pub mode: SnapMode,
+    /// Pull dragged clip edges onto other clips' edges, the loop points and the playhead
+    pub magnetic: bool,
+    /// Move trimmed audio clip edges to the nearest zero crossing
+    pub zero_crossing: bool,
+}
+
+impl GridSettings {
+    /// Grid that follows the zoom level
+    pub fn auto() -> Self {
+        Self {
+            auto: true,
+            value: 0.25,
+            feel: GridFeel::Straight,
+            snap: true,
+            swing: 50.0,
+            mode: SnapMode::Absolute,
+            magnetic: false,
+            zero_crossing: false,
+        }
+    }
+
+    /// Grid fixed to a note value in beats
+    pub fn fixed(value: f64) -> Self {
+        Self { auto: false, value, ..Self::auto() }
+    }
+
+    /// Zoom-derived step for the note editors
+    pub fn zoom_step(pixels_per_beat: f32) -> f64 {
+        if pixels_per_beat >= 160.0 {
+            0.125
+        } else if pixels_per_beat >= 80.0 {
+            0.25
+        } else if pixels_per_beat >= 40.0 {
+            0.5
+        } else {
+            1.0
+        }
+    }
+
+    /// Step length in beats; `zoom_step` is used while following the zoom
+    pub fn step(&self, zoom_step: f64) -> f64 {
+        let base = if self.auto { zoom_step } else { self.value };
+        match self.feel {
+            GridFeel::Straight => base,
+            GridFeel::Triplet => base * 2.0 / 3.0,
+            GridFeel::Dotted => base * 1.5,
+        }
+    }
+
+    /// Start of the step pair holding `beat`, and where its swung second step starts
+    fn pair(&self, beat: f64, step: f64) -> (f64, f64) {
+        let pair = 2.0 * step;
+        let start = (beat / pair).floor() * pair;
+        (start, start + pair * self.swing as f64 / 100.0)
+    }
+
+    /// Last grid line at or before `beat`
+    pub fn snap_floor(&self, beat: f64, step: f64) -> f64 {
+        let (start, mid) = self.pair(beat, step);
+        if beat >= mid { mid } else { start }
+    }
+
+    /// Grid line nearest `beat`
+    pub fn snap_round(&self, beat: f64, step: f64) -> f64 {
+        let (start, mid) = self.pair(beat, step);
+        let end = start + 2.0 * step;
+        [start, mid, end]
+            .into_iter()
+            .min_by(|a, b| (a - beat).abs().total_cmp(&(b - beat).abs()))
+            .unwrap_or(start)
+    }
+
+    /// Where an item at `original` dragged by `delta` beats lands: on the nearest grid line,
+    /// or moved by a whole number of steps in relative mode
+    pub fn snap_drag(&self, original: f64, delta: f64, step: f64) -> f64 {
+        match self.mode {
+            SnapMode::Absolute => self.snap_round(original + delta, step),
+            SnapMode::Relative => original + (delta / step).round() * step,
+        }
+    }
+
+    /// Grid line positions between `from` and `to`
+    pub fn lines(&self, from: f64, to: f64, step: f64) -> Vec<f64> {
+        let mut lines = Vec::new();
+        let (mut start, _) = self.pair(from.max(0.0), step);
+        while start <= to {
+            let mid = start + 2.0 * step * self.swing as f64 / 100.0;
+            lines.extend([start, mid].into_iter().filter(|&b| b >= from && b <= to));
+            start += 2.0 * step;
+        }
+        lines
+    }
+
+    /// Short label for the menu button, e.g. "1/16T" or "Auto"
+    pub fn label(&self) -> String {
+        if !self.snap {
+            return "Off".to_string();
+        }
+        let value = if self.auto {
+            "Auto"
+        } else {
+            NOTE_VALUES.iter().find(|(_, v)| *v == self.value).map_or("?", |(name, _)| *name)
+        };
+        let feel = match self.feel {
+            GridFeel::Straight => "",
+            GridFeel::Triplet => "T",
+            GridFeel::Dotted => ".",
+        };
+        let swing = if self.swing != 50.0 { format!(" {:.0}%", self.swing) } else { String::new() };
+        format!("{}{}{}", value, feel, swing)
+    }
+
+    /// Grid menu contents
+    pub fn menu_ui(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.snap, "Snap");
+        ui.separator();
+        ui.selectable_value(&mut self.auto, true, "Auto (follow zoom)");
+        for (name, value) in NOTE_VALUES {
+            if ui.selectable_label(!self.auto && self.value == value, name).clicked() {
+                self.auto = false;
+                self.value = value;
+            }
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.feel, GridFeel::Straight, "Straight");
+            ui.selectable_value(&mut self.feel, GridFeel::Triplet, "Triplet");
+            ui.selectable_value(&mut self.feel, GridFeel::Dotted, "Dotted");
+        });
+        ui.add(Slider::new(&mut self.swing, 50.0..=75.0).suffix("%").text("Swing"));
+        ui.separator();
+        ui.radio_value(&mut self.mode, SnapMode::Absolute, "Absolute")
+            .on_hover_text("Dragged items land on grid lines");
+        ui.radio_value(&mut self.mode, SnapMode::Relative, "Relative")
+            .on_hover_text("Dragged items move by whole grid steps, keeping their offset from the grid");
+        ui.checkbox(&mut self.magnetic, "Magnetic clip edges")
+            .on_hover_text("Clip edges dragged near another clip's edge, a loop point or the playhead snap to it");
+        ui.checkbox(&mut self.zero_crossing, "Zero-crossing trims")
+            .on_hover_text("Trimmed audio clip edges move to the nearest zero crossing");
+    }
+}
+
+/// Target within `threshold` beats of `beat` and nearest it, for magnetic snapping
+pub fn magnet(beat: f64, targets: &[f64], threshold: f64) -> Option<f64> {
+    targets.iter()
+        .copied()
+        .filter(|t| (t - beat).abs() <= threshold)
+        .min_by(|a, b| (a - beat).abs().total_cmp(&(b - beat).abs()))
+}
@@ -1,19 +1,34 @@
 //! Drawing methods for the keyboard sequencer panel.
 
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 
 use egui::{Color32, Key, Rect, Sense, Stroke, Ui, Vec2};
+use hallucinator_core::StepLocks;
 
 use crate::clipboard::DawClipboard;
+use crate::panels::theme::{shade, Palette};
 
 use super::KeyboardSequencerPanel;
 use super::types::{
     DragRowSample, DragStep, GridInteraction, KeyboardSequencerAction, SequencerRow,
-    DRUM_KEY_LABELS, LABEL_BRIGHT, LABEL_DIM, PAD_ACTIVE, PAD_ACTIVE_STEP,
-    PAD_BG, PAD_BLACK, PAD_BORDER, PAD_CURRENT, PAD_PRESSED,
+    DRUM_KEY_LABELS, LOCK_DOT, pad_active_step, pad_pressed, pad_selected,
     truncate_label,
 };
 
+/// Checkbox locking one parameter, with its value while locked. Returns true on change.
+fn lock_ui(ui: &mut Ui, value: &mut Option<f32>, name: &str, initial: f32, range: RangeInclusive<f32>, suffix: &str) -> bool {
+    let mut locked = value.is_some();
+    let mut changed = ui.checkbox(&mut locked, name).changed();
+    if changed {
+        *value = locked.then_some(initial);
+    }
+    if let Some(v) = value {
+        changed |= ui.add(egui::DragValue::new(v).range(range).speed(0.05).suffix(suffix)).changed();
+    }
+    changed
+}
+
 impl KeyboardSequencerPanel {
     pub(super) fn draw_drum_row(
         &mut self,
@@ -25,6 +40,7 @@ impl KeyboardSequencerPanel {
         let mut actions = Vec::new();
         let mut interactions = Vec::new();
         let l = self.layout();
+        let palette = Palette::get(ui.ctx());
         let layer = self.sel.active_drum_layer;
         let sample_btn_w = l.label_w * 1.8;
 
@@ -34,7 +50,7 @@ impl KeyboardSequencerPanel {
             ui.add_space(2.0);
             // DR label matching row number width
             let (_, label_painter) = ui.allocate_painter(Vec2::new(l.label_w * 0.6, l.size), Sense::hover());
-            let label_color = if is_active_row { LABEL_BRIGHT } else { LABEL_DIM };
+            let label_color = if is_active_row { palette.text } else { palette.text_dim };
             label_painter.text(
                 label_painter.clip_rect().center(),
                 egui::Align2::CENTER_CENTER,
@@ -93,7 +109,7 @@ impl KeyboardSequencerPanel {
                         actions.push(KeyboardSequencerAction::CopyDrumStep { step: i, layer });
                         ui.close_menu();
                     }
-                    if clipboard.content().is_some() && ui.button("Paste").clicked() {
+                    if (clipboard.has_file() || clipboard.has_sample()) && ui.button("Paste").clicked() {
                         actions.extend(self.paste_from_clipboard(clipboard, i, layer));
                         ui.close_menu();
                     }
@@ -107,15 +123,15 @@ impl KeyboardSequencerPanel {
 
                 // --- Visual ---
                 let bg = if any_drop_hover {
-                    Color32::from_rgb(80, 120, 180)
+                    shade(palette.selection, -0.3)
                 } else if is_triggered {
-                    PAD_PRESSED
+                    pad_pressed(&palette)
                 } else {
                     match (active, is_current) {
-                        (true, true) => PAD_ACTIVE_STEP,
-                        (true, false) => PAD_ACTIVE,
-                        (false, true) => PAD_CURRENT,
-                        (false, false) => PAD_BG,
+                        (true, true) => pad_active_step(&palette),
+                        (true, false) => palette.active,
+                        (false, true) => palette.pad_current,
+                        (false, false) => palette.pad,
                     }
                 };
 
@@ -125,7 +141,12 @@ impl KeyboardSequencerPanel {
                 self.draw_pad(&painter, rect, bg, &label, active || is_triggered);
 
                 if is_selected {
-                    painter.rect_stroke(rect, l.radius, Stroke::new(2.0, Color32::from_rgb(130, 170, 220)), egui::StrokeKind::Outside);
+                    painter.rect_stroke(rect, l.radius, Stroke::new(2.0, palette.selection), egui::StrokeKind::Outside);
+                }
+
+                // Corner dot marks a step with parameter locks
+                if !self.drum_steps[i].locks.is_empty() {
+                    painter.circle_filled(rect.right_top() + Vec2::new(-5.0, 5.0), 2.5, LOCK_DOT);
                 }
 
                 ui.add_space(l.spacing);
@@ -135,6 +156,30 @@ impl KeyboardSequencerPanel {
         (actions, interactions)
     }
 
+    /// Parameter locks of the selected drum step: tick a parameter to lock it on that step
+    pub(super) fn draw_step_locks(&mut self, ui: &mut Ui) -> Vec<KeyboardSequencerAction> {
+        let is_drum_row = matches!(self.sel.active_row, SequencerRow::Drum | SequencerRow::DrumLayer(_));
+        let Some(step) = self.sel.selected_step.filter(|&s| is_drum_row && s < self.drum_steps.len()) else {
+            return Vec::new();
+        };
+
+        let locks = &mut self.drum_steps[step].locks;
+        let palette = Palette::get(ui.ctx());
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.colored_label(palette.text_dim, format!("Step {} locks", step + 1));
+            changed |= lock_ui(ui, &mut locks.pitch, "Pitch", 0.0, -24.0..=24.0, " st");
+            changed |= lock_ui(ui, &mut locks.decay, "Decay", 0.3, 0.02..=4.0, " s");
+            changed |= lock_ui(ui, &mut locks.level, "Level", 1.0, 0.0..=1.0, "");
+            if !locks.is_empty() && ui.small_button("Clear").clicked() {
+                *locks = StepLocks::default();
+                changed = true;
+            }
+        });
+
+        if changed { vec![KeyboardSequencerAction::SetStepLocks(step)] } else { Vec::new() }
+    }
+
     pub(super) fn draw_expanded_grid(
         &mut self,
         ui: &mut Ui,
@@ -144,6 +189,7 @@ impl KeyboardSequencerPanel {
         let mut actions = Vec::new();
         let mut interactions = Vec::new();
         let l = self.layout();
+        let palette = Palette::get(ui.ctx());
         let step_count = self.drum_steps.len();
         let cell_size = l.size * 0.8;
         let sample_btn_w = l.label_w * 1.8;
@@ -221,29 +267,44 @@ impl KeyboardSequencerPanel {
                         actions.push(KeyboardSequencerAction::ClearRowSample { row });
                         ui.close_menu();
                     }
+                    ui.separator();
+                    ui.menu_button(format!("Steps: {}", self.row_length(row)), |ui| {
+                        if ui.selectable_label(self.row_lengths[row] == 0, "Pattern length").clicked() {
+                            self.row_lengths[row] = 0;
+                            actions.push(KeyboardSequencerAction::SetRowLength { row });
+                            ui.close_menu();
+                        }
+                        for len in 1..=step_count as u8 {
+                            if ui.selectable_label(self.row_lengths[row] == len, len.to_string()).clicked() {
+                                self.row_lengths[row] = len;
+                                actions.push(KeyboardSequencerAction::SetRowLength { row });
+                                ui.close_menu();
+                            }
+                        }
+                    });
                 });
 
                 // Visual for sample button - enabled rows are brighter, multi-selected have highlight
                 let sample_bg = if drop_hover {
-                    Color32::from_rgb(80, 120, 180)
+                    shade(palette.selection, -0.3)
                 } else if row_is_selected {
-                    Color32::from_rgb(70, 90, 110)  // Multi-selected: blue tint
+                    pad_selected(&palette)  // Multi-selected: blue tint
                 } else if row_has_sample && row_enabled {
-                    Color32::from_rgb(60, 70, 50)  // Enabled: greenish tint
+                    palette.pad.lerp_to_gamma(palette.active, 0.15)  // Enabled: tinted
                 } else if is_active_row {
-                    Color32::from_rgb(50, 50, 55)
+                    palette.pad_border
                 } else {
-                    Color32::from_rgb(35, 35, 40)
+                    palette.pad_black
                 };
                 sample_painter.rect_filled(sample_rect, l.radius * 0.5, sample_bg);
-                sample_painter.rect_stroke(sample_rect, l.radius * 0.5, Stroke::new(0.5, PAD_BORDER), egui::StrokeKind::Outside);
+                sample_painter.rect_stroke(sample_rect, l.radius * 0.5, Stroke::new(0.5, palette.pad_border), egui::StrokeKind::Outside);
 
                 // Selection highlight (active row or multi-selected)
                 if is_active_row || row_is_selected {
                     let highlight_color = if row_is_selected {
-                        Color32::from_rgb(100, 140, 180)  // Multi-select: lighter blue
+                        shade(palette.selection, -0.25)  // Multi-select: lighter blue
                     } else {
-                        Color32::from_rgb(130, 170, 220)  // Active: standard blue
+                        palette.selection  // Active: standard blue
                     };
                     sample_painter.rect_stroke(sample_rect, l.radius * 0.5, Stroke::new(1.5, highlight_color), egui::StrokeKind::Outside);
                 }
@@ -252,9 +313,9 @@ impl KeyboardSequencerPanel {
                     .map_or_else(|| "---".to_string(), |n| truncate_label(n, 6));
                 // Muted rows show dimmed text
                 let sample_text_color = match (row_has_sample, row_enabled) {
-                    (true, true) => LABEL_BRIGHT,
-                    (true, false) => Color32::from_gray(80),  // Muted: dim
-                    _ => LABEL_DIM,
+                    (true, true) => palette.text,
+                    (true, false) => palette.text_dim.lerp_to_gamma(palette.pad, 0.5),  // Muted: dim
+                    _ => palette.text_dim,
                 };
                 sample_painter.text(
                     sample_rect.center(),
@@ -269,15 +330,18 @@ impl KeyboardSequencerPanel {
                 // --- Row number label ---
                 ui.allocate_ui(Vec2::new(l.label_w * 0.6, cell_size), |ui| {
                     ui.centered_and_justified(|ui| {
-                        let label_color = if is_active_row { LABEL_BRIGHT } else { LABEL_DIM };
+                        let label_color = if is_active_row { palette.text } else { palette.text_dim };
                         ui.colored_label(label_color, format!("{}", row + 1));
                     });
                 });
 
                 // --- Step cells (just timing toggles, no individual samples) ---
+                // Steps past a shortened row's length are skipped by its counter and drawn dark
+                let row_length = self.row_length(row);
                 for step in 0..step_count {
                     let step_active = self.drum_steps[step].layers[row].active;
-                    let is_current = is_playing && self.current_step == step;
+                    let in_row = step < row_length;
+                    let is_current = is_playing && self.row_steps[row] == step;
                     let is_triggered = is_active_row && (self.triggered_steps & (1 << step)) != 0;
                     let cell_key = (row, step);
                     let cell_is_multi_selected = self.sel.selected_cells.contains(&cell_key);
@@ -295,28 +359,30 @@ impl KeyboardSequencerPanel {
 
                     // Visual
                     let bg = if cell_is_multi_selected {
-                        Color32::from_rgb(70, 90, 110)  // Multi-selected: blue tint
+                        pad_selected(&palette)  // Multi-selected: blue tint
                     } else if is_triggered {
-                        PAD_PRESSED
+                        pad_pressed(&palette)
+                    } else if !in_row {
+                        palette.pad_black
                     } else {
                         match (step_active, is_current) {
-                            (true, true) => PAD_ACTIVE_STEP,
-                            (true, false) => PAD_ACTIVE,
-                            (false, true) => PAD_CURRENT,
-                            (false, false) => PAD_BG,
+                            (true, true) => pad_active_step(&palette),
+                            (true, false) => palette.active,
+                            (false, true) => palette.pad_current,
+                            (false, false) => palette.pad,
                         }
                     };
 
                     painter.rect_filled(rect, l.radius * 0.5, bg);
-                    painter.rect_stroke(rect, l.radius * 0.5, Stroke::new(0.5, PAD_BORDER), egui::StrokeKind::Outside);
+                    painter.rect_stroke(rect, l.radius * 0.5, Stroke::new(0.5, palette.pad_border), egui::StrokeKind::Outside);
 
                     // Selection highlight (single selection or multi-selected)
                     let is_selected = is_active_row && self.sel.selected_step == Some(step);
                     if is_selected || cell_is_multi_selected {
                         let highlight_color = if cell_is_multi_selected {
-                            Color32::from_rgb(100, 140, 180)
+                            shade(palette.selection, -0.25)
                         } else {
-                            Color32::from_rgb(130, 170, 220)
+                            palette.selection
                         };
                         painter.rect_stroke(rect, l.radius * 0.5, Stroke::new(2.0, highlight_color), egui::StrokeKind::Outside);
                     }
@@ -326,10 +392,10 @@ impl KeyboardSequencerPanel {
                     if let Some(name) = &self.row_samples[row] {
                         let text_color = if is_triggered {
                             Color32::from_rgb(40, 35, 20)
-                        } else if step_active {
-                            LABEL_BRIGHT
+                        } else if step_active && in_row {
+                            palette.text
                         } else {
-                            Color32::from_gray(60)
+                            palette.text_dim.lerp_to_gamma(palette.pad, 0.5)
                         };
                         painter.text(
                             rect.center(),
@@ -352,6 +418,7 @@ impl KeyboardSequencerPanel {
     pub(super) fn draw_melodic_row(&mut self, ui: &mut Ui, label: &str, keys: &[Key], _base_pitch: u8, is_playing: bool, is_active_row: bool, row: SequencerRow) -> Vec<GridInteraction> {
         let mut interactions = Vec::new();
         let l = self.layout();
+        let palette = Palette::get(ui.ctx());
         let sample_btn_w = l.label_w * 1.8;
         ui.horizontal(|ui| {
             // Empty spacer matching sample button width
@@ -359,7 +426,7 @@ impl KeyboardSequencerPanel {
             ui.add_space(2.0);
             // Label matching row number width (use painter for consistent font with DR)
             let (_, label_painter) = ui.allocate_painter(Vec2::new(l.label_w * 0.6, l.size), Sense::hover());
-            let label_color = if is_active_row { LABEL_BRIGHT } else { LABEL_DIM };
+            let label_color = if is_active_row { palette.text } else { palette.text_dim };
             label_painter.text(
                 label_painter.clip_rect().center(),
                 egui::Align2::CENTER_CENTER,
@@ -383,29 +450,29 @@ impl KeyboardSequencerPanel {
                 }
 
                 let bg = if is_pressed {
-                    PAD_PRESSED
+                    pad_pressed(&palette)
                 } else if is_current {
-                    PAD_CURRENT
+                    palette.pad_current
                 } else if is_black {
-                    PAD_BLACK
+                    palette.pad_black
                 } else {
-                    PAD_BG
+                    palette.pad
                 };
 
                 let text_color = if is_pressed || is_current {
-                    LABEL_BRIGHT
+                    palette.text
                 } else if is_black {
-                    Color32::from_gray(90)
+                    palette.text_dim.lerp_to_gamma(palette.pad, 0.3)
                 } else {
-                    LABEL_DIM
+                    palette.text_dim
                 };
 
                 painter.rect_filled(rect, l.radius, bg);
-                painter.rect_stroke(rect, l.radius, Stroke::new(1.0, PAD_BORDER), egui::StrokeKind::Outside);
+                painter.rect_stroke(rect, l.radius, Stroke::new(1.0, palette.pad_border), egui::StrokeKind::Outside);
 
                 // Selection highlight
                 if is_selected {
-                    painter.rect_stroke(rect, l.radius, Stroke::new(2.0, Color32::from_rgb(130, 170, 220)), egui::StrokeKind::Outside);
+                    painter.rect_stroke(rect, l.radius, Stroke::new(2.0, palette.selection), egui::StrokeKind::Outside);
                 }
 
                 painter.text(
@@ -425,15 +492,16 @@ impl KeyboardSequencerPanel {
     /// Draw a single Factory Rat-style pad
     pub(super) fn draw_pad(&self, painter: &egui::Painter, rect: Rect, bg: Color32, label: &str, lit: bool) {
         let l = self.layout();
+        let palette = Palette::get(painter.ctx());
         painter.rect_filled(rect, l.radius, bg);
-        painter.rect_stroke(rect, l.radius, Stroke::new(1.0, PAD_BORDER), egui::StrokeKind::Outside);
+        painter.rect_stroke(rect, l.radius, Stroke::new(1.0, palette.pad_border), egui::StrokeKind::Outside);
 
         if lit {
             let glow = rect.shrink(l.glow_inset);
             painter.rect_filled(glow, l.radius - 2.0, Color32::from_rgba_premultiplied(255, 240, 160, 40));
         }
 
-        let text_color = if lit { Color32::from_rgb(40, 35, 20) } else { LABEL_DIM };
+        let text_color = if lit { Color32::from_rgb(40, 35, 20) } else { palette.text_dim };
         painter.text(
             rect.center(),
             egui::Align2::CENTER_CENTER,
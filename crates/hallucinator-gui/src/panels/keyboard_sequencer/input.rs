@@ -7,10 +7,12 @@ use egui::{Key, Ui};
 use hallucinator_services::EngineState;
 
 use crate::clipboard::{ClipboardContent, DawClipboard};
+use crate::panels::grid::GridSettings;
+use crate::panels::keymap::Command;
 
 use super::KeyboardSequencerPanel;
 use super::types::{
-    GridInteraction, KeyboardSequencerAction, RepeatRate, SequencerRow,
+    GridInteraction, KeyboardSequencerAction, RepeatRamp, RepeatRate, SequencerRow,
     DRUM_KEYS, OCTAVE_3_KEYS, OCTAVE_4_KEYS, OCTAVE_5_KEYS,
 };
 
@@ -70,6 +72,7 @@ impl KeyboardSequencerPanel {
 
         // Get current beat for note repeat timing
         let current_beat = self.get_current_beat(engine_state);
+        let recording = self.repeat_record && engine_state.playing.load(Ordering::Relaxed);
 
         // Determine active row for triggering
         let active_row = match self.sel.active_row {
@@ -86,6 +89,7 @@ impl KeyboardSequencerPanel {
             if !is_down && was_down {
                 self.triggered_steps &= !(1 << i);
                 self.last_repeat_beat.remove(&i);
+                self.repeat_hits.remove(&i);
             }
 
             // Guard: key not pressed or not on a drum row
@@ -102,18 +106,31 @@ impl KeyboardSequencerPanel {
                 }
                 if self.row_samples[row].is_some() {
                     actions.push(KeyboardSequencerAction::PlayRowSample { row, velocity: self.base_velocity });
+                    if recording {
+                        actions.push(KeyboardSequencerAction::RecordDrumHit { row, velocity: self.base_velocity, beat: current_beat });
+                    }
                 }
                 continue;
             }
 
-            // Held: note repeat (guard: repeat off or interval not elapsed)
+            // Held: note repeat on the rate's swung grid (guard: repeat off or next line not reached;
+            // a line behind the last one by a full interval means the transport looped)
             let Some(interval) = self.repeat_rate.beats() else { continue };
+            let grid = GridSettings { swing: self.repeat_swing[self.repeat_rate as usize], ..GridSettings::fixed(interval) };
+            let line = grid.snap_floor(current_beat, interval);
             let last = self.last_repeat_beat.get(&i).copied().unwrap_or(0.0);
-            if current_beat < last + interval { continue; }
+            if line <= last + 1e-9 && last - line < interval { continue; }
 
-            self.last_repeat_beat.insert(i, current_beat);
+            self.last_repeat_beat.insert(i, line);
+            let hits = self.repeat_hits.entry(i).or_insert(0);
+            *hits += 1;
+            let progress = (*hits as f64 * interval / RepeatRamp::BEATS).min(1.0) as f32;
+            let velocity = ((self.base_velocity as f32 * self.repeat_ramp.scale(progress)).round() as u8).clamp(1, 127);
             if self.row_samples[row].is_some() {
-                actions.push(KeyboardSequencerAction::PlayRowSample { row, velocity: self.base_velocity });
+                actions.push(KeyboardSequencerAction::PlayRowSample { row, velocity });
+                if recording {
+                    actions.push(KeyboardSequencerAction::RecordDrumHit { row, velocity, beat: line });
+                }
             }
         }
 
@@ -172,13 +189,13 @@ impl KeyboardSequencerPanel {
         });
         let paste_event = ui.input(|i| i.events.iter().any(|e| matches!(e, egui::Event::Paste(_))));
         let paste = paste_event || (ctrl && v_pressed);
-        let paste_row = active_row.filter(|_| paste && clipboard.content().is_some());
+        let paste_row = active_row.filter(|_| paste && (clipboard.has_file() || clipboard.has_sample()));
         if let Some(row) = paste_row {
             actions.push(KeyboardSequencerAction::PasteRowSample { row });
         }
 
-        // Delete/Backspace: clear row sample
-        let delete = ui.input(|i| i.key_pressed(Key::Delete) || i.key_pressed(Key::Backspace));
+        // Delete: clear row sample
+        let delete = Command::Delete.pressed(ui.ctx());
         let delete_row = active_row.filter(|&r| delete && self.row_samples[r].is_some());
         if let Some(row) = delete_row {
             self.row_samples[row] = None;
@@ -207,6 +224,7 @@ impl KeyboardSequencerPanel {
                     data: Arc::clone(data),
                 }]
             }
+            ClipboardContent::MidiNotes { .. } => Vec::new(),
         }
     }
 
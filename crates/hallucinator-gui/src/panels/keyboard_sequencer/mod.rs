@@ -2,25 +2,27 @@
 
 mod drawing;
 mod input;
+mod patterns;
 mod types;
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
 
 use egui::{Key, Ui};
 use hallucinator_core::ScaleMode;
-use hallucinator_services::EngineState;
+use hallucinator_services::{DrumPattern, DrumPatternStep, EngineState};
 
 pub use types::{DrumStep, KeyboardSequencerAction};
 use types::{
-    SelectionState, SequencerRow, RepeatRate, PadLayout, FLOATING, DOCKED,
+    SelectionState, SequencerRow, RepeatRamp, RepeatRate, PadLayout, FLOATING, DOCKED,
     NOTE_NAMES, IS_BLACK_KEY, ALL_SCALES, ALL_REPEAT_RATES,
-    PANEL_BG, LABEL_BRIGHT, LABEL_DIM,
     OCTAVE_3_KEYS, OCTAVE_4_KEYS, OCTAVE_5_KEYS,
 };
 
 use crate::clipboard::DawClipboard;
+use crate::panels::theme::Palette;
 
 /// QWERTY keyboard sequencer panel
 pub struct KeyboardSequencerPanel {
@@ -45,12 +47,32 @@ pub struct KeyboardSequencerPanel {
     pub(super) repeat_rate: RepeatRate,
     /// Last repeat trigger time per step (in beats)
     pub(super) last_repeat_beat: HashMap<usize, f64>,
+    /// Repeats fired per held step since it was pressed
+    pub(super) repeat_hits: HashMap<usize, u32>,
+    /// Velocity ramp over a held repeat
+    pub(super) repeat_ramp: RepeatRamp,
+    /// Swing of each repeat rate in percent (indexed by `RepeatRate as usize`)
+    pub(super) repeat_swing: [f32; 7],
+    /// Record pad hits and their repeats into the track's clip during playback
+    pub(super) repeat_record: bool,
     /// Sample assigned to each row (one sample per row, shared across all steps)
     pub(super) row_samples: [Option<String>; 12],
+    /// File each row's sample was loaded from, so saved patterns can reload it
+    pub(super) row_paths: [Option<PathBuf>; 12],
     /// Whether each row is enabled (unmuted) - true = plays, false = muted
     pub(super) row_enabled: [bool; 12],
+    /// Steps each row cycles through (0 = all drum steps)
+    pub(super) row_lengths: [u8; 12],
+    /// Current step of each row, read from the audio thread
+    pub(super) row_steps: [usize; 12],
     /// Pending Tab press from app level (Some(true) = shift+tab, Some(false) = tab, None = no tab)
     pub(super) pending_tab: Option<bool>,
+    /// Name typed for saving the pattern to a file
+    pub(super) pattern_name: String,
+    /// Saved pattern file names
+    pub(super) pattern_files: Vec<String>,
+    /// Pattern bank slots of the selected track holding a drum pattern (bit N = slot N)
+    pub(super) bank_filled: u16,
 }
 
 impl Default for KeyboardSequencerPanel {
@@ -77,9 +99,19 @@ impl KeyboardSequencerPanel {
             snap_to_arrange: false,
             repeat_rate: RepeatRate::default(),
             last_repeat_beat: HashMap::new(),
+            repeat_hits: HashMap::new(),
+            repeat_ramp: RepeatRamp::default(),
+            repeat_swing: [50.0; 7],
+            repeat_record: false,
             row_samples: std::array::from_fn(|_| None),
+            row_paths: std::array::from_fn(|_| None),
             row_enabled: [true; 12],  // All rows enabled by default
+            row_lengths: [0; 12],
+            row_steps: [0; 12],
             pending_tab: None,
+            pattern_name: String::new(),
+            pattern_files: Vec::new(),
+            bank_filled: 0,
         }
     }
 
@@ -134,10 +166,11 @@ impl KeyboardSequencerPanel {
         }
     }
 
-    /// Set sample for an entire row (layer)
-    pub fn set_row_sample(&mut self, row: usize, name: String) {
+    /// Set sample for an entire row (layer); `path` is the file it came from, if any
+    pub fn set_row_sample(&mut self, row: usize, name: String, path: Option<PathBuf>) {
         if row < 12 {
             self.row_samples[row] = Some(name);
+            self.row_paths[row] = path;
         }
     }
 
@@ -145,6 +178,20 @@ impl KeyboardSequencerPanel {
     pub fn clear_row_sample(&mut self, row: usize) {
         if row < 12 {
             self.row_samples[row] = None;
+            self.row_paths[row] = None;
+        }
+    }
+
+    /// File a row's sample was loaded from
+    pub fn row_sample_path(&self, row: usize) -> Option<PathBuf> {
+        self.row_paths.get(row).cloned().flatten()
+    }
+
+    /// Steps `row` cycles through before repeating
+    pub(super) fn row_length(&self, row: usize) -> usize {
+        match self.row_lengths[row] as usize {
+            0 => self.drum_step_count,
+            len => len.min(self.drum_step_count),
         }
     }
 
@@ -158,21 +205,29 @@ impl KeyboardSequencerPanel {
         self.pending_tab = Some(shift);
     }
 
-    /// Sync panel's drum pattern to engine state for sample-accurate playback
+    /// Sync panel's drum pattern to engine state for sample-accurate playback; the engine
+    /// gets a fresh copy of the whole pattern, so the audio thread never waits on the GUI
     pub fn sync_pattern_to_engine(&self, engine_state: &Arc<EngineState>, instrument_id: Option<u64>) {
-        let Ok(mut pattern) = engine_state.drum_pattern.lock() else { return };
-        pattern.step_count = self.drum_step_count;
-        pattern.loop_bars = self.drum_loop_bars;
-        pattern.snap_to_arrange = self.snap_to_arrange;
-        pattern.instrument_id = instrument_id;
-        // Convert row_enabled array to bitmask
-        pattern.row_enabled = self.row_enabled.iter().enumerate()
-            .filter(|(_, enabled)| **enabled)
-            .fold(0u16, |mask, (i, _)| mask | (1 << i));
+        let mut pattern = DrumPattern {
+            step_count: self.drum_step_count,
+            loop_bars: self.drum_loop_bars,
+            snap_to_arrange: self.snap_to_arrange,
+            instrument_id,
+            row_lengths: self.row_lengths,
+            // Convert row_enabled array to bitmask
+            row_enabled: self.row_enabled.iter().enumerate()
+                .filter(|(_, enabled)| **enabled)
+                .fold(0u16, |mask, (i, _)| mask | (1 << i)),
+            ..DrumPattern::default()
+        };
         for (i, step) in self.drum_steps.iter().enumerate().take(12) {
-            pattern.steps[i].active = step.active;
-            pattern.steps[i].active_layers = step.active_layer_mask();
+            pattern.steps[i] = DrumPatternStep {
+                active: step.active,
+                active_layers: step.active_layer_mask(),
+                locks: step.locks,
+            };
         }
+        engine_state.set_drum_pattern(pattern);
     }
 
     pub fn ui(
@@ -188,14 +243,20 @@ impl KeyboardSequencerPanel {
         let dsc = self.drum_step_count;  // for drum rows (independent)
 
         // Read current step from audio thread (sample-accurate timing)
-        self.current_step = engine_state.drum_current_step.load(Ordering::Relaxed) % dsc;
+        let tick = engine_state.drum_step_tick.load(Ordering::Relaxed);
+        self.current_step = if tick == u64::MAX { 0 } else { (tick % dsc as u64) as usize };
+        for (row, step) in engine_state.drum_row_steps.iter().enumerate() {
+            self.row_steps[row] = step.load(Ordering::Relaxed);
+        }
 
-        // Dark panel background
+        // Panel background
         let panel_rect = ui.available_rect_before_wrap();
-        ui.painter().rect_filled(panel_rect, 0.0, PANEL_BG);
+        ui.painter().rect_filled(panel_rect, 0.0, Palette::get(ui.ctx()).panel);
 
         // Compact toolbar
         self.draw_toolbar(ui, track_name);
+        actions.extend(self.draw_pattern_bar(ui));
+        actions.extend(self.draw_step_locks(ui));
 
         // Resize drum steps based on drum_step_count (independent from scale)
         self.drum_steps.resize(dsc, DrumStep::default());
@@ -242,10 +303,11 @@ impl KeyboardSequencerPanel {
     }
 
     fn draw_toolbar(&mut self, ui: &mut Ui, track_name: Option<&str>) {
+        let palette = Palette::get(ui.ctx());
         ui.horizontal(|ui| {
-            ui.colored_label(LABEL_BRIGHT, "FACTORY RAT");
+            ui.colored_label(palette.text, "FACTORY RAT");
             if let Some(name) = track_name {
-                ui.colored_label(LABEL_DIM, name);
+                ui.colored_label(palette.text_dim, name);
             }
             let float_label = if self.is_floating { "Dock" } else { "Float" };
             if ui.button(float_label).clicked() {
@@ -271,13 +333,13 @@ impl KeyboardSequencerPanel {
                     });
             }
             ui.separator();
-            ui.colored_label(LABEL_DIM, "Vel");
+            ui.colored_label(palette.text_dim, "Vel");
             let mut vel = self.base_velocity as f32;
             ui.add(egui::Slider::new(&mut vel, 1.0..=127.0).fixed_decimals(0));
             self.base_velocity = vel as u8;
 
             ui.separator();
-            ui.colored_label(LABEL_DIM, "Lyr");
+            ui.colored_label(palette.text_dim, "Lyr");
             egui::ComboBox::from_id_salt("dr_layer")
                 .selected_text(format!("{}", self.sel.active_drum_layer + 1))
                 .width(32.0)
@@ -296,7 +358,7 @@ impl KeyboardSequencerPanel {
             }
 
             ui.separator();
-            ui.colored_label(LABEL_DIM, "Steps");
+            ui.colored_label(palette.text_dim, "Steps");
             egui::ComboBox::from_id_salt("drum_steps")
                 .selected_text(format!("{}", self.drum_step_count))
                 .width(36.0)
@@ -306,7 +368,7 @@ impl KeyboardSequencerPanel {
                     }
                 });
 
-            ui.colored_label(LABEL_DIM, "Bars");
+            ui.colored_label(palette.text_dim, "Bars");
             egui::ComboBox::from_id_salt("loop_bars")
                 .selected_text(format!("{}", self.drum_loop_bars))
                 .width(32.0)
@@ -322,7 +384,7 @@ impl KeyboardSequencerPanel {
             }
 
             ui.separator();
-            ui.colored_label(LABEL_DIM, "Rpt");
+            ui.colored_label(palette.text_dim, "Rpt");
             egui::ComboBox::from_id_salt("repeat_rate")
                 .selected_text(self.repeat_rate.name())
                 .width(40.0)
@@ -331,6 +393,25 @@ impl KeyboardSequencerPanel {
                         ui.selectable_value(&mut self.repeat_rate, rate, rate.name());
                     }
                 });
+            egui::ComboBox::from_id_salt("repeat_ramp")
+                .selected_text(self.repeat_ramp.name())
+                .width(64.0)
+                .show_ui(ui, |ui| {
+                    for ramp in [RepeatRamp::Off, RepeatRamp::Up, RepeatRamp::Down] {
+                        ui.selectable_value(&mut self.repeat_ramp, ramp, ramp.name());
+                    }
+                });
+            let swing = &mut self.repeat_swing[self.repeat_rate as usize];
+            ui.add_enabled(
+                self.repeat_rate != RepeatRate::Off,
+                egui::DragValue::new(swing).range(50.0..=75.0).speed(0.5).prefix("Sw ").suffix("%"),
+            ).on_hover_text("Swing of this repeat rate");
+            if ui.selectable_label(self.repeat_record, "Rec")
+                .on_hover_text("Record pad hits and note repeats into the track's clip while playing")
+                .clicked()
+            {
+                self.repeat_record = !self.repeat_record;
+            }
         });
     }
 }
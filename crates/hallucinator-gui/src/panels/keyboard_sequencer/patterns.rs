@@ -0,0 +1,121 @@
+//! Drum pattern snapshots for pattern files and the track's pattern bank
+
+use egui::{RichText, Ui};
+use hallucinator_core::{DrumPatternData, DrumRowData, DrumStepData};
+
+use crate::panels::theme::Palette;
+
+use super::KeyboardSequencerPanel;
+use super::types::{DrumStep, KeyboardSequencerAction};
+
+impl KeyboardSequencerPanel {
+    /// Names of the saved pattern files listed in the Load menu
+    pub fn set_pattern_files(&mut self, names: Vec<String>) {
+        self.pattern_files = names;
+    }
+
+    /// Which bank slots of the selected track hold a drum pattern (bit N = slot N)
+    pub fn set_bank_filled(&mut self, mask: u16) {
+        self.bank_filled = mask;
+    }
+
+    /// Snapshot of the drum pattern: steps, row samples, step count and bars
+    pub fn pattern_data(&self) -> DrumPatternData {
+        DrumPatternData {
+            step_count: self.drum_step_count,
+            loop_bars: self.drum_loop_bars,
+            steps: self.drum_steps.iter()
+                .map(|step| DrumStepData { active: step.active, rows: step.active_layer_mask(), locks: step.locks })
+                .collect(),
+            rows: (0..12)
+                .map(|row| DrumRowData {
+                    sample_name: self.row_samples[row].clone(),
+                    sample_path: self.row_paths[row].clone(),
+                    enabled: self.row_enabled[row],
+                    length: self.row_lengths[row],
+                })
+                .collect(),
+        }
+    }
+
+    /// Replace the drum pattern with a snapshot. The kit's samples are reloaded by the app.
+    pub fn apply_pattern_data(&mut self, data: &DrumPatternData) {
+        self.drum_step_count = if [4, 6, 8, 12].contains(&data.step_count) { data.step_count } else { 8 };
+        self.drum_loop_bars = if [1, 2, 4].contains(&data.loop_bars) { data.loop_bars } else { 1 };
+        self.drum_steps = (0..self.drum_step_count)
+            .map(|i| {
+                let mut step = DrumStep::default();
+                if let Some(saved) = data.steps.get(i) {
+                    step.active = saved.active;
+                    step.locks = saved.locks;
+                    for (layer_idx, layer) in step.layers.iter_mut().enumerate() {
+                        layer.active = saved.rows & (1 << layer_idx) != 0;
+                    }
+                }
+                step
+            })
+            .collect();
+        for row in 0..12 {
+            let saved = data.rows.get(row).cloned().unwrap_or_default();
+            self.row_samples[row] = saved.sample_name;
+            self.row_paths[row] = saved.sample_path;
+            self.row_enabled[row] = saved.enabled;
+            self.row_lengths[row] = saved.length;
+        }
+        self.sel.selected_step = None;
+    }
+
+    /// Pattern file load/save and the 16 pattern bank slots
+    pub(super) fn draw_pattern_bar(&mut self, ui: &mut Ui) -> Vec<KeyboardSequencerAction> {
+        let mut actions = Vec::new();
+        let palette = Palette::get(ui.ctx());
+        ui.horizontal(|ui| {
+            ui.colored_label(palette.text_dim, "Pattern");
+            egui::ComboBox::from_id_salt("drum_pattern_files")
+                .selected_text("Load")
+                .width(60.0)
+                .show_ui(ui, |ui| {
+                    if self.pattern_files.is_empty() {
+                        ui.label("No patterns saved");
+                    }
+                    for name in &self.pattern_files {
+                        if ui.selectable_label(false, name).clicked() {
+                            actions.push(KeyboardSequencerAction::LoadPatternFile(name.clone()));
+                            self.pattern_name = name.clone();
+                        }
+                    }
+                });
+            ui.add(egui::TextEdit::singleline(&mut self.pattern_name).hint_text("name").desired_width(80.0));
+            if ui.add_enabled(!self.pattern_name.trim().is_empty(), egui::Button::new("Save")).clicked() {
+                actions.push(KeyboardSequencerAction::SavePatternFile(self.pattern_name.trim().to_string()));
+            }
+
+            ui.separator();
+            ui.colored_label(palette.text_dim, "Bank");
+            for slot in 0..16 {
+                let filled = self.bank_filled & (1 << slot) != 0;
+                let color = if filled { palette.active } else { palette.text_dim };
+                let response = ui.small_button(RichText::new(format!("{}", slot + 1)).color(color))
+                    .on_hover_text("Click to recall, Shift+click to store");
+                if response.clicked() {
+                    if ui.input(|i| i.modifiers.shift) {
+                        actions.push(KeyboardSequencerAction::StorePatternSlot(slot));
+                    } else if filled {
+                        actions.push(KeyboardSequencerAction::RecallPatternSlot(slot));
+                    }
+                }
+                response.context_menu(|ui| {
+                    if ui.button("Store pattern here").clicked() {
+                        actions.push(KeyboardSequencerAction::StorePatternSlot(slot));
+                        ui.close_menu();
+                    }
+                    if filled && ui.button("Clear slot").clicked() {
+                        actions.push(KeyboardSequencerAction::ClearPatternSlot(slot));
+                        ui.close_menu();
+                    }
+                });
+            }
+        });
+        actions
+    }
+}
@@ -1,11 +1,13 @@
-//! Types, constants, and color palette for the keyboard sequencer panel.
+//! Types, constants, and pad colors for the keyboard sequencer panel.
 
 use std::collections::HashSet;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use egui::{Color32, Key};
-use hallucinator_core::ScaleMode;
+use hallucinator_core::{ScaleMode, StepLocks};
+
+use crate::panels::theme::{shade, Palette};
 
 // -- Key mappings --
 
@@ -52,18 +54,24 @@ pub(super) const ALL_SCALES: [ScaleMode; 12] = [
     ScaleMode::MelodicMinor, ScaleMode::Pentatonic, ScaleMode::Blues,
 ];
 
-// -- Factory Rat color palette --
+// -- Factory Rat colors (the rest come from the theme palette) --
+
+pub(super) const LOCK_DOT: Color32 = Color32::from_rgb(230, 110, 60);
+
+/// Lit pad under the playing step
+pub(super) fn pad_active_step(palette: &Palette) -> Color32 {
+    shade(palette.active, 0.4)
+}
+
+/// Pad whose key is held down
+pub(super) fn pad_pressed(palette: &Palette) -> Color32 {
+    shade(palette.selection, 0.35)
+}
 
-pub(super) const PAD_BG: Color32 = Color32::from_rgb(38, 38, 42);
-pub(super) const PAD_BORDER: Color32 = Color32::from_rgb(55, 55, 60);
-pub(super) const PAD_ACTIVE: Color32 = Color32::from_rgb(220, 195, 90);
-pub(super) const PAD_ACTIVE_STEP: Color32 = Color32::from_rgb(255, 235, 130);
-pub(super) const PAD_CURRENT: Color32 = Color32::from_rgb(70, 68, 50);
-pub(super) const PAD_PRESSED: Color32 = Color32::from_rgb(140, 200, 240);
-pub(super) const PAD_BLACK: Color32 = Color32::from_rgb(28, 28, 32);
-pub(super) const PANEL_BG: Color32 = Color32::from_rgb(22, 22, 26);
-pub(super) const LABEL_DIM: Color32 = Color32::from_rgb(120, 120, 130);
-pub(super) const LABEL_BRIGHT: Color32 = Color32::from_rgb(210, 210, 215);
+/// Unlit pad in a multi-selection
+pub(super) fn pad_selected(palette: &Palette) -> Color32 {
+    palette.pad.lerp_to_gamma(palette.selection, 0.35)
+}
 
 // -- Layout --
 
@@ -111,6 +119,8 @@ impl Default for DrumLayer {
 pub struct DrumStep {
     pub active: bool,
     pub layers: [DrumLayer; 12],
+    /// Kit pitch/decay/level replaced on this step only
+    pub locks: StepLocks,
 }
 
 impl Default for DrumStep {
@@ -118,6 +128,7 @@ impl Default for DrumStep {
         Self {
             active: false,
             layers: std::array::from_fn(|_| DrumLayer::default()),
+            locks: StepLocks::default(),
         }
     }
 }
@@ -139,6 +150,8 @@ impl DrumStep {
 #[derive(Clone)]
 pub enum KeyboardSequencerAction {
     ToggleDrumStep(usize),
+    /// A drum step's parameter locks were edited
+    SetStepLocks(usize),
     PlayNote { pitch: u8, velocity: u8 },
     StopNote { pitch: u8 },
     LoadStepSample { step: usize, layer: usize, path: PathBuf },
@@ -162,6 +175,19 @@ pub enum KeyboardSequencerAction {
     MoveRowSample { from_row: usize, to_row: usize },
     /// Toggle row enabled/muted state
     ToggleRowEnabled { row: usize },
+    /// Set how many steps a row cycles through (already updated in the panel)
+    SetRowLength { row: usize },
+    /// Write a pad hit into the track's clip at `beat` (note repeat recording)
+    RecordDrumHit { row: usize, velocity: u8, beat: f64 },
+    /// Save the drum pattern to a named pattern file
+    SavePatternFile(String),
+    /// Replace the drum pattern with a saved pattern file
+    LoadPatternFile(String),
+    /// Store the drum pattern in a slot of the track's pattern bank
+    StorePatternSlot(usize),
+    /// Replace the drum pattern with a pattern bank slot
+    RecallPatternSlot(usize),
+    ClearPatternSlot(usize),
 }
 
 /// UI interaction detected during grid drawing, processed separately for SoC
@@ -301,6 +327,39 @@ impl RepeatRate {
     }
 }
 
+/// Velocity ramp applied to note repeats while a pad is held
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RepeatRamp {
+    #[default]
+    Off,
+    /// Crescendo from a quarter of the pad velocity
+    Up,
+    /// Fade down to a quarter of the pad velocity
+    Down,
+}
+
+impl RepeatRamp {
+    /// Bar (in beats) the ramp takes to reach its end velocity
+    pub const BEATS: f64 = 4.0;
+
+    /// Velocity scale after holding for `progress` (0-1) of the ramp
+    pub fn scale(self, progress: f32) -> f32 {
+        match self {
+            Self::Off => 1.0,
+            Self::Up => 0.25 + 0.75 * progress,
+            Self::Down => 1.0 - 0.75 * progress,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Off => "Flat",
+            Self::Up => "Ramp up",
+            Self::Down => "Ramp down",
+        }
+    }
+}
+
 /// All repeat rates for UI selector
 pub const ALL_REPEAT_RATES: [RepeatRate; 7] = [
     RepeatRate::Off,
@@ -0,0 +1,298 @@
+//! Keymap: every command with a keyboard shortcut, bound by a preset (this app's own, or
+//! ones after Live, FL Studio and Reaper) plus the user's rebindings from config.toml.
+//! The app installs the active keymap in the egui context each frame, so any panel can
+//! look a command's shortcut up without it being passed down.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use egui::{Context, Key, KeyboardShortcut, Modifiers};
+use serde::{Deserialize, Serialize};
+
+/// Something the keyboard can do
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Command {
+    PlayPause,
+    /// Selected clip in the arrange view, selected notes or steps in the editors
+    Delete,
+    SequencerNext,
+    SequencerPrevious,
+    ZoomIn,
+    ZoomOut,
+    FitAll,
+    FitLoop,
+    FitSelection,
+    Consolidate,
+    OctaveDown,
+    OctaveUp,
+    RunScript,
+}
+
+impl Command {
+    pub const ALL: [Self; 13] = [
+        Self::PlayPause,
+        Self::Delete,
+        Self::SequencerNext,
+        Self::SequencerPrevious,
+        Self::ZoomIn,
+        Self::ZoomOut,
+        Self::FitAll,
+        Self::FitLoop,
+        Self::FitSelection,
+        Self::Consolidate,
+        Self::OctaveDown,
+        Self::OctaveUp,
+        Self::RunScript,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::PlayPause => "Play / Stop",
+            Self::Delete => "Delete",
+            Self::SequencerNext => "Sequencer: Next",
+            Self::SequencerPrevious => "Sequencer: Previous",
+            Self::ZoomIn => "Zoom In",
+            Self::ZoomOut => "Zoom Out",
+            Self::FitAll => "Fit All",
+            Self::FitLoop => "Fit Loop",
+            Self::FitSelection => "Fit Selection",
+            Self::Consolidate => "Consolidate Clips",
+            Self::OctaveDown => "Keyboard Octave Down",
+            Self::OctaveUp => "Keyboard Octave Up",
+            Self::RunScript => "Run Script",
+        }
+    }
+
+    /// Name the command is saved under in config.toml
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PlayPause => "play_pause",
+            Self::Delete => "delete",
+            Self::SequencerNext => "sequencer_next",
+            Self::SequencerPrevious => "sequencer_previous",
+            Self::ZoomIn => "zoom_in",
+            Self::ZoomOut => "zoom_out",
+            Self::FitAll => "fit_all",
+            Self::FitLoop => "fit_loop",
+            Self::FitSelection => "fit_selection",
+            Self::Consolidate => "consolidate",
+            Self::OctaveDown => "octave_down",
+            Self::OctaveUp => "octave_up",
+            Self::RunScript => "run_script",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|cmd| cmd.name() == name)
+    }
+
+    /// Whether one of the command's shortcuts was pressed this frame, leaving the key for
+    /// other views that act on it too (e.g. Delete in both the arrange view and an editor)
+    pub fn pressed(self, ctx: &Context) -> bool {
+        let keymap = Keymap::get(ctx);
+        ctx.input(|i| {
+            keymap.bindings(self).iter().any(|shortcut| {
+                i.modifiers.matches_logically(shortcut.modifiers) && i.key_pressed(shortcut.logical_key)
+            })
+        })
+    }
+
+    /// Take a pressed shortcut of the command out of the input; true when there was one
+    pub fn consume(self, ctx: &Context) -> bool {
+        Self::consume_any(ctx, &[self]).is_some()
+    }
+
+    /// Take the first pressed shortcut of `commands` out of the input. Shortcuts with more
+    /// modifiers are tried first, so Ctrl+Shift+F isn't taken for Ctrl+F
+    pub fn consume_any(ctx: &Context, commands: &[Self]) -> Option<Self> {
+        let keymap = Keymap::get(ctx);
+        let mut shortcuts: Vec<(Self, KeyboardShortcut)> = commands
+            .iter()
+            .flat_map(|&cmd| keymap.bindings(cmd).iter().map(move |&shortcut| (cmd, shortcut)))
+            .collect();
+        shortcuts.sort_by_key(|(_, shortcut)| std::cmp::Reverse(modifier_count(shortcut.modifiers)));
+        ctx.input_mut(|i| shortcuts.into_iter().find(|(_, shortcut)| i.consume_shortcut(shortcut)).map(|(cmd, _)| cmd))
+    }
+
+    /// The command's first shortcut as shown in menus and tooltips, empty when unbound
+    pub fn shortcut_text(self, ctx: &Context) -> String {
+        Keymap::get(ctx).bindings(self).first().map(|shortcut| ctx.format_shortcut(shortcut)).unwrap_or_default()
+    }
+}
+
+fn modifier_count(modifiers: Modifiers) -> u8 {
+    (modifiers.command || modifiers.ctrl) as u8 + modifiers.shift as u8 + modifiers.alt as u8
+}
+
+/// Set of default bindings
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeymapPreset {
+    #[default]
+    Hallucinator,
+    Live,
+    FlStudio,
+    Reaper,
+}
+
+impl KeymapPreset {
+    pub const ALL: [Self; 4] = [Self::Hallucinator, Self::Live, Self::FlStudio, Self::Reaper];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Hallucinator => "Hallucinator",
+            Self::Live => "Live",
+            Self::FlStudio => "FL Studio",
+            Self::Reaper => "Reaper",
+        }
+    }
+
+    /// Shortcuts of a command in this preset. Ctrl +/- stay with egui's interface zoom in
+    /// every preset, and plain letters with the keyboard piano and sequencer pads
+    pub fn bindings(self, command: Command) -> Vec<KeyboardShortcut> {
+        let key = |key| KeyboardShortcut::new(Modifiers::NONE, key);
+        let ctrl = |key| KeyboardShortcut::new(Modifiers::COMMAND, key);
+        let ctrl_shift = |key| KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, key);
+        let ctrl_alt = |key| KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::ALT, key);
+        let shift = |key| KeyboardShortcut::new(Modifiers::SHIFT, key);
+
+        match (self, command) {
+            (_, Command::PlayPause) => vec![key(Key::Space)],
+            (Self::Hallucinator | Self::Live, Command::Delete) => vec![key(Key::Delete), key(Key::Backspace)],
+            (_, Command::Delete) => vec![key(Key::Delete)],
+            (_, Command::SequencerNext) => vec![key(Key::Tab)],
+            (_, Command::SequencerPrevious) => vec![shift(Key::Tab)],
+            (Self::Reaper, Command::ZoomIn) => vec![ctrl(Key::ArrowUp)],
+            (Self::Reaper, Command::ZoomOut) => vec![ctrl(Key::ArrowDown)],
+            (_, Command::ZoomIn) => vec![ctrl(Key::CloseBracket)],
+            (_, Command::ZoomOut) => vec![ctrl(Key::OpenBracket)],
+            (Self::Reaper, Command::FitAll) => vec![ctrl(Key::PageDown)],
+            (_, Command::FitAll) => vec![ctrl_shift(Key::F)],
+            (_, Command::FitLoop) => vec![ctrl(Key::L)],
+            (Self::Reaper, Command::FitSelection) => vec![ctrl(Key::PageUp)],
+            (_, Command::FitSelection) => vec![ctrl(Key::F)],
+            (Self::FlStudio, Command::Consolidate) => vec![ctrl_alt(Key::C)],
+            (Self::Reaper, Command::Consolidate) => vec![ctrl(Key::G)],
+            (_, Command::Consolidate) => vec![ctrl(Key::J)],
+            (_, Command::OctaveDown) => vec![key(Key::Minus)],
+            (_, Command::OctaveUp) => vec![key(Key::Equals)],
+            (_, Command::RunScript) => vec![ctrl(Key::Enter)],
+        }
+    }
+}
+
+/// Shortcuts of every command: a preset's, with the user's rebindings over it.
+/// The default one has nothing bound
+#[derive(Clone, Debug, Default)]
+pub struct Keymap {
+    pub preset: KeymapPreset,
+    /// Commands bound differently from the preset (an empty list leaves one unbound)
+    rebound: HashMap<Command, Vec<KeyboardShortcut>>,
+    /// Every command's shortcuts, from the two above
+    bindings: HashMap<Command, Vec<KeyboardShortcut>>,
+}
+
+impl Keymap {
+    /// Keymap from config.toml: a preset and rebindings by command name, as written by
+    /// `rebound_by_name`; unknown commands and shortcuts are skipped
+    pub fn from_config(preset: KeymapPreset, rebound: &BTreeMap<String, Vec<String>>) -> Self {
+        let rebound = rebound
+            .iter()
+            .filter_map(|(name, shortcuts)| {
+                let shortcuts = shortcuts.iter().filter_map(|s| parse_shortcut(s)).collect();
+                Some((Command::from_name(name)?, shortcuts))
+            })
+            .collect();
+        let mut keymap = Self { preset, rebound, bindings: HashMap::new() };
+        keymap.update_bindings();
+        keymap
+    }
+
+    /// Rebindings by command name, for config.toml
+    pub fn rebound_by_name(&self) -> BTreeMap<String, Vec<String>> {
+        self.rebound
+            .iter()
+            .map(|(cmd, shortcuts)| (cmd.name().to_string(), shortcuts.iter().map(shortcut_to_string).collect()))
+            .collect()
+    }
+
+    pub fn bindings(&self, command: Command) -> &[KeyboardShortcut] {
+        self.bindings.get(&command).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn is_rebound(&self, command: Command) -> bool {
+        self.rebound.contains_key(&command)
+    }
+
+    /// Start over from a preset's bindings
+    pub fn set_preset(&mut self, preset: KeymapPreset) {
+        self.preset = preset;
+        self.rebound.clear();
+        self.update_bindings();
+    }
+
+    /// Bind a command to `shortcuts` (none to unbind it), taking them off any other command
+    pub fn rebind(&mut self, command: Command, shortcuts: Vec<KeyboardShortcut>) {
+        for other in Command::ALL.into_iter().filter(|&other| other != command) {
+            let current = self.bindings(other);
+            if current.iter().any(|s| shortcuts.contains(s)) {
+                let kept = current.iter().copied().filter(|s| !shortcuts.contains(s)).collect();
+                self.rebound.insert(other, kept);
+            }
+        }
+        self.rebound.insert(command, shortcuts);
+        self.update_bindings();
+    }
+
+    /// Back to the preset's shortcuts for a command
+    pub fn reset(&mut self, command: Command) {
+        self.rebound.remove(&command);
+        self.update_bindings();
+    }
+
+    fn update_bindings(&mut self) {
+        self.bindings = Command::ALL
+            .into_iter()
+            .map(|cmd| (cmd, self.rebound.get(&cmd).cloned().unwrap_or_else(|| self.preset.bindings(cmd))))
+            .collect();
+    }
+
+    /// Make this the keymap panels look shortcuts up in
+    pub fn install(self: &Arc<Self>, ctx: &Context) {
+        ctx.data_mut(|d| d.insert_temp(keymap_id(), self.clone()));
+    }
+
+    /// The installed keymap, or the default one before any is installed
+    pub fn get(ctx: &Context) -> Arc<Self> {
+        ctx.data(|d| d.get_temp(keymap_id())).unwrap_or_else(|| Arc::new(Self::from_config(KeymapPreset::default(), &BTreeMap::new())))
+    }
+}
+
+fn keymap_id() -> egui::Id {
+    egui::Id::new("keymap")
+}
+
+/// Shortcut as saved in config.toml, e.g. "Ctrl+Shift+F" (Ctrl is Cmd on macOS)
+pub fn shortcut_to_string(shortcut: &KeyboardShortcut) -> String {
+    let Modifiers { alt, shift, command, ctrl, mac_cmd } = shortcut.modifiers;
+    [(command || ctrl || mac_cmd, "Ctrl"), (shift, "Shift"), (alt, "Alt")]
+        .into_iter()
+        .filter_map(|(held, name)| held.then_some(name))
+        .chain([shortcut.logical_key.name()])
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+/// Parse a shortcut written by `shortcut_to_string`
+pub fn parse_shortcut(text: &str) -> Option<KeyboardShortcut> {
+    let (modifier_names, key) = text.rsplit_once('+').map_or(("", text), |(mods, key)| (mods, key));
+    let mut modifiers = Modifiers::NONE;
+    for name in modifier_names.split('+').filter(|name| !name.is_empty()) {
+        modifiers |= match name {
+            "Ctrl" | "Cmd" => Modifiers::COMMAND,
+            "Shift" => Modifiers::SHIFT,
+            "Alt" => Modifiers::ALT,
+            _ => return None,
+        };
+    }
+    Some(KeyboardShortcut::new(modifiers, Key::from_name(key)?))
+}
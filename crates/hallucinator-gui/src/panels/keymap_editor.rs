@@ -0,0 +1,121 @@
+//! Keyboard shortcuts window: pick a preset and rebind commands by pressing the new keys
+
+use egui::{Event, Key, KeyboardShortcut, Modifiers, Ui};
+
+use super::keymap::{Command, Keymap, KeymapPreset};
+
+/// Action returned from the keyboard shortcuts window
+pub enum KeymapEditorAction {
+    None,
+    SetPreset(KeymapPreset),
+    /// Bind a command to the shortcut pressed for it, or unbind it (None)
+    Rebind(Command, Option<KeyboardShortcut>),
+    Reset(Command),
+}
+
+pub struct KeymapEditorPanel {
+    /// Command waiting for its new shortcut to be pressed
+    capturing: Option<Command>,
+}
+
+impl KeymapEditorPanel {
+    pub fn new() -> Self {
+        Self { capturing: None }
+    }
+
+    /// Whether key presses go to the window rather than the commands bound to them
+    pub fn is_capturing(&self) -> bool {
+        self.capturing.is_some()
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, keymap: &Keymap) -> KeymapEditorAction {
+        let mut action = KeymapEditorAction::None;
+
+        if let Some(command) = self.capturing {
+            match capture_shortcut(ui) {
+                Some(Some(shortcut)) => {
+                    action = KeymapEditorAction::Rebind(command, Some(shortcut));
+                    self.capturing = None;
+                }
+                // Escape cancels
+                Some(None) => self.capturing = None,
+                None => {}
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Preset");
+            egui::ComboBox::from_id_salt("keymap_preset")
+                .selected_text(keymap.preset.label())
+                .show_ui(ui, |ui| {
+                    for preset in KeymapPreset::ALL {
+                        if ui.selectable_label(keymap.preset == preset, preset.label()).clicked() {
+                            action = KeymapEditorAction::SetPreset(preset);
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Start over from a preset's shortcuts");
+        });
+        ui.separator();
+
+        egui::Grid::new("keymap_grid").num_columns(4).striped(true).show(ui, |ui| {
+            for command in Command::ALL {
+                ui.label(command.label());
+
+                let text = if self.capturing == Some(command) {
+                    "Press keys… (Esc cancels)".to_string()
+                } else {
+                    let bound: Vec<String> = keymap.bindings(command).iter().map(|s| ui.ctx().format_shortcut(s)).collect();
+                    if bound.is_empty() { "—".to_string() } else { bound.join(", ") }
+                };
+                let button = ui.add(egui::Button::new(text).min_size(egui::vec2(160.0, 0.0)))
+                    .on_hover_text("Click, then press the new shortcut");
+                if button.clicked() {
+                    self.capturing = Some(command);
+                }
+
+                if ui.small_button("✖").on_hover_text("Unbind").clicked() {
+                    action = KeymapEditorAction::Rebind(command, None);
+                }
+                let reset = ui.add_enabled(keymap.is_rebound(command), egui::Button::new("Reset").small())
+                    .on_hover_text("Back to the preset's shortcut");
+                if reset.clicked() {
+                    action = KeymapEditorAction::Reset(command);
+                }
+                ui.end_row();
+            }
+        });
+
+        ui.separator();
+        ui.weak("A shortcut taken from another command leaves that one without it");
+        action
+    }
+}
+
+/// Take the next key press out of the input: Some(Some(shortcut)) for a key with its
+/// modifiers, Some(None) for Escape, None while nothing was pressed
+fn capture_shortcut(ui: &Ui) -> Option<Option<KeyboardShortcut>> {
+    ui.input_mut(|i| {
+        let pressed = i.events.iter().find_map(|event| match event {
+            Event::Key { key, pressed: true, modifiers, .. } => Some((*key, *modifiers)),
+            _ => None,
+        })?;
+        i.events.retain(|event| !matches!(event, Event::Key { .. } | Event::Text(_)));
+        let (key, held) = pressed;
+        if key == Key::Escape {
+            return Some(None);
+        }
+        let mut modifiers = Modifiers::NONE;
+        if held.command || held.ctrl {
+            modifiers |= Modifiers::COMMAND;
+        }
+        if held.shift {
+            modifiers |= Modifiers::SHIFT;
+        }
+        if held.alt {
+            modifiers |= Modifiers::ALT;
+        }
+        Some(Some(KeyboardShortcut::new(modifiers, key)))
+    })
+}
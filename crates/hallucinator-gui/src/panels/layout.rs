@@ -0,0 +1,210 @@
+//! Panel layout kept across sessions - which panels and windows are open or torn off, how
+//! big the docked panels are and where the tool windows sit - and the workspaces that each keep one
+
+use std::collections::BTreeMap;
+
+use egui::containers::panel::PanelState;
+use egui::{Context, Id, Ui, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// Panel ids whose sizes are kept in the layout
+const BROWSER_PANEL: &str = "browser";
+const SEQUENCER_PANEL: &str = "factory_rat_panel";
+const CLIP_EDITOR_PANEL: &str = "clip_editor_panel";
+
+/// A named layout, switched between from View → Workspace
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Workspace {
+    #[default]
+    Tracking,
+    Mixing,
+    Editing,
+}
+
+impl Workspace {
+    pub const ALL: [Self; 3] = [Self::Tracking, Self::Mixing, Self::Editing];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Tracking => "Tracking",
+            Self::Mixing => "Mixing",
+            Self::Editing => "Editing",
+        }
+    }
+}
+
+/// Where a tool window was left: its top-left corner and, for resizable windows, its content size
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub pos: [f32; 2],
+    pub size: Option<[f32; 2]>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Layout {
+    pub show_sequencer: bool,
+    /// Keyboard sequencer in its own window rather than docked in the sequencer panel
+    pub sequencer_floating: bool,
+    /// Clip editor in a native window of its own rather than docked at the bottom
+    pub clip_editor_detached: bool,
+    pub show_analyzer: bool,
+    pub show_modulation: bool,
+    pub show_automation: bool,
+    pub show_control_surface: bool,
+    pub show_script_console: bool,
+    pub show_snapshots: bool,
+    pub show_chord_track: bool,
+    pub browser_width: f32,
+    pub sequencer_height: f32,
+    pub clip_editor_height: f32,
+    /// Tool windows by title
+    pub windows: BTreeMap<String, WindowGeometry>,
+    /// Sizes and window positions are forced on the next frame, after a workspace switch
+    #[serde(skip)]
+    restore: bool,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            show_sequencer: true,
+            sequencer_floating: false,
+            clip_editor_detached: false,
+            show_analyzer: false,
+            show_modulation: false,
+            show_automation: false,
+            show_control_surface: false,
+            show_script_console: false,
+            show_snapshots: false,
+            show_chord_track: false,
+            browser_width: 180.0,
+            sequencer_height: 160.0,
+            clip_editor_height: 200.0,
+            windows: BTreeMap::new(),
+            restore: false,
+        }
+    }
+}
+
+impl Layout {
+    /// The layout a workspace starts from
+    pub fn preset(workspace: Workspace) -> Self {
+        match workspace {
+            Workspace::Tracking => Self::default(),
+            Workspace::Mixing => Self {
+                show_sequencer: false,
+                show_analyzer: true,
+                show_automation: true,
+                show_snapshots: true,
+                browser_width: 150.0,
+                ..Self::default()
+            },
+            Workspace::Editing => Self {
+                show_sequencer: false,
+                show_chord_track: true,
+                clip_editor_height: 340.0,
+                ..Self::default()
+            },
+        }
+    }
+
+    /// Force the panel sizes and window positions onto the next frame, rather than
+    /// keeping the ones egui remembers from this session
+    pub fn restore(&mut self, ctx: &Context) {
+        ctx.data_mut(|d| {
+            for panel in [BROWSER_PANEL, SEQUENCER_PANEL, CLIP_EDITOR_PANEL] {
+                d.remove::<PanelState>(Id::new(panel));
+            }
+        });
+        self.restore = true;
+    }
+
+    /// Call once all panels and windows are drawn: picks up the panel sizes and ends a restore
+    pub fn end_frame(&mut self, ctx: &Context) {
+        let size = |panel: &str| PanelState::load(ctx, Id::new(panel)).map(|state| state.size());
+        if let Some(size) = size(BROWSER_PANEL) {
+            self.browser_width = size.x;
+        }
+        if let Some(size) = size(SEQUENCER_PANEL) {
+            self.sequencer_height = size.y;
+        }
+        if let Some(size) = size(CLIP_EDITOR_PANEL) {
+            self.clip_editor_height = size.y;
+        }
+        self.restore = false;
+    }
+
+    /// Show a tool window where it was left, and remember where it is now. Windows with a
+    /// `default_size` are resizable; without one they fit their contents
+    pub fn show_window<R>(
+        &mut self,
+        ctx: &Context,
+        title: &str,
+        open: &mut bool,
+        default_size: Option<[f32; 2]>,
+        add_contents: impl FnOnce(&mut Ui) -> R,
+    ) -> Option<R> {
+        let mut window = egui::Window::new(title).open(open).resizable(default_size.is_some());
+        if let Some(size) = default_size {
+            window = window.default_size(size);
+        }
+        if let Some(geometry) = self.windows.get(title) {
+            let size = geometry.size.filter(|_| default_size.is_some());
+            window = match (self.restore, size) {
+                (true, Some(size)) => window.current_pos(geometry.pos).fixed_size(size),
+                (true, None) => window.current_pos(geometry.pos),
+                (false, Some(size)) => window.default_pos(geometry.pos).default_size(size),
+                (false, None) => window.default_pos(geometry.pos),
+            };
+        }
+
+        let mut content_size: Option<Vec2> = None;
+        let response = window.show(ctx, |ui| {
+            // A resizable window's contents start out as big as the size dragged to
+            content_size = Some(ui.max_rect().size());
+            add_contents(ui)
+        })?;
+
+        let previous = self.windows.get(title).and_then(|geometry| geometry.size);
+        self.windows.insert(title.to_string(), WindowGeometry {
+            pos: response.response.rect.min.into(),
+            size: default_size.and(content_size.map(Into::into).or(previous)),
+        });
+        response.inner
+    }
+}
+
+/// The workspace in use and the layout each one was left in
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workspaces {
+    pub current: Workspace,
+    /// By workspace label; a workspace not listed starts from its preset
+    pub layouts: BTreeMap<String, Layout>,
+}
+
+impl Workspaces {
+    /// Layout of the current workspace
+    pub fn layout(&self) -> Layout {
+        self.layouts.get(self.current.label()).cloned().unwrap_or_else(|| Layout::preset(self.current))
+    }
+
+    /// Keep `layout` as the current workspace's
+    pub fn store(&mut self, layout: &Layout) {
+        self.layouts.insert(self.current.label().to_string(), layout.clone());
+    }
+
+    /// Keep `layout` for the current workspace, then switch to `workspace` and return its layout
+    pub fn switch(&mut self, layout: &Layout, workspace: Workspace) -> Layout {
+        self.store(layout);
+        self.current = workspace;
+        self.layout()
+    }
+
+    /// Forget how the current workspace was left and return its preset
+    pub fn reset(&mut self) -> Layout {
+        self.layouts.remove(self.current.label());
+        Layout::preset(self.current)
+    }
+}
@@ -1,7 +1,7 @@
 //! MIDI FX rack panel - chain of MIDI effects per track (Factory Rat-style)
 
 use egui::{Color32, ComboBox, Rect, Sense, Slider, Stroke, Ui, Vec2};
-use hallucinator_core::{MidiEffect, MidiFxChain, MidiFxParam};
+use hallucinator_core::{moved_index, ArpeggiatorFx, MidiEffect, MidiFxChain, MidiFxParam};
 
 /// Action returned from MIDI FX rack
 #[derive(Clone)]
@@ -12,6 +12,12 @@ pub enum MidiFxRackAction {
     ToggleBypass(usize),
     MoveEffect { from: usize, to: usize },
     SetParam { effect_idx: usize, param_name: String, value: f32 },
+    /// Turn one step of an arpeggiator's pattern on or off
+    SetArpStep { effect_idx: usize, step: usize, on: bool },
+    /// Replace the chain with a saved preset
+    LoadPreset(String),
+    /// Save the chain as a preset under this name
+    SavePreset(String),
 }
 
 /// Types of MIDI effects available to add
@@ -25,6 +31,11 @@ pub enum MidiEffectType {
     Echo,
     Arpeggiator,
     Harmonizer,
+    Chord,
+    NoteFilter,
+    ScaleForce,
+    Strum,
+    Ratchet,
 }
 
 impl MidiEffectType {
@@ -38,6 +49,11 @@ impl MidiEffectType {
             MidiEffectType::Echo,
             MidiEffectType::Arpeggiator,
             MidiEffectType::Harmonizer,
+            MidiEffectType::Chord,
+            MidiEffectType::NoteFilter,
+            MidiEffectType::ScaleForce,
+            MidiEffectType::Strum,
+            MidiEffectType::Ratchet,
         ]
     }
 
@@ -51,9 +67,19 @@ impl MidiEffectType {
             Self::Echo => "Echo",
             Self::Arpeggiator => "Arpeggiator",
             Self::Harmonizer => "Harmonizer",
+            Self::Chord => "Chord",
+            Self::NoteFilter => "Note Filter",
+            Self::ScaleForce => "Scale Force",
+            Self::Strum => "Strum",
+            Self::Ratchet => "Ratchet",
         }
     }
 
+    /// Effect type with this display name (as `MidiEffect::name` reports it)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().iter().copied().find(|t| t.name() == name)
+    }
+
     pub fn create_effect(&self) -> MidiEffect {
         match self {
             Self::Transpose => MidiEffect::Transpose(Default::default()),
@@ -64,6 +90,11 @@ impl MidiEffectType {
             Self::Echo => MidiEffect::Echo(Default::default()),
             Self::Arpeggiator => MidiEffect::Arpeggiator(Default::default()),
             Self::Harmonizer => MidiEffect::Harmonizer(Default::default()),
+            Self::Chord => MidiEffect::Chord(Default::default()),
+            Self::NoteFilter => MidiEffect::NoteFilter(Default::default()),
+            Self::ScaleForce => MidiEffect::ScaleForce(Default::default()),
+            Self::Strum => MidiEffect::Strum(Default::default()),
+            Self::Ratchet => MidiEffect::Ratchet(Default::default()),
         }
     }
 }
@@ -73,6 +104,7 @@ pub struct MidiFxRackPanel {
     expanded_effect: Option<usize>,
     add_effect_type: MidiEffectType,
     drag_source: Option<usize>,
+    preset_name: String,
 }
 
 impl MidiFxRackPanel {
@@ -81,6 +113,7 @@ impl MidiFxRackPanel {
             expanded_effect: None,
             add_effect_type: MidiEffectType::Transpose,
             drag_source: None,
+            preset_name: String::new(),
         }
     }
 
@@ -89,6 +122,7 @@ impl MidiFxRackPanel {
         ui: &mut Ui,
         track_name: Option<&str>,
         chain: Option<&MidiFxChain>,
+        presets: &[String],
     ) -> MidiFxRackAction {
         let mut action = MidiFxRackAction::None;
 
@@ -110,11 +144,20 @@ impl MidiFxRackPanel {
         };
 
         // Effect slots (vertical list, compact)
+        let mut slot_rects = Vec::with_capacity(chain.effects.len());
         for (idx, effect) in chain.effects.iter().enumerate() {
-            let effect_action = self.draw_effect_slot(ui, idx, effect);
+            let (effect_action, rect) = self.draw_effect_slot(ui, idx, effect);
             if !matches!(effect_action, MidiFxRackAction::None) {
                 action = effect_action;
             }
+            slot_rects.push(rect);
+        }
+
+        if let Some(from) = self.drag_source {
+            let move_action = self.draw_drop_target(ui, from, &slot_rects);
+            if !matches!(move_action, MidiFxRackAction::None) {
+                action = move_action;
+            }
         }
 
         // Add effect section (compact)
@@ -139,10 +182,62 @@ impl MidiFxRackPanel {
             ui.small("No FX");
         }
 
+        // Chain presets
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            ComboBox::from_id_salt("midi_fx_presets")
+                .selected_text("Presets")
+                .width(80.0)
+                .show_ui(ui, |ui| {
+                    if presets.is_empty() {
+                        ui.label("No presets saved");
+                    }
+                    for name in presets {
+                        if ui.selectable_label(false, name).clicked() {
+                            action = MidiFxRackAction::LoadPreset(name.clone());
+                            self.preset_name = name.clone();
+                        }
+                    }
+                });
+            ui.add(egui::TextEdit::singleline(&mut self.preset_name).hint_text("name").desired_width(80.0));
+            let can_save = !self.preset_name.trim().is_empty() && !chain.effects.is_empty();
+            if ui.add_enabled(can_save, egui::Button::new("Save")).clicked() {
+                action = MidiFxRackAction::SavePreset(self.preset_name.trim().to_string());
+            }
+        });
+
         action
     }
 
-    fn draw_effect_slot(&mut self, ui: &mut Ui, idx: usize, effect: &MidiEffect) -> MidiFxRackAction {
+    /// Insertion line for a dragged effect; releasing the pointer moves the effect there
+    fn draw_drop_target(&mut self, ui: &mut Ui, from: usize, slot_rects: &[Rect]) -> MidiFxRackAction {
+        let Some(pointer) = ui.input(|i| i.pointer.interact_pos()) else {
+            return MidiFxRackAction::None;
+        };
+        ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+
+        // Slot between effects the pointer is over (0 = before the first)
+        let slot = slot_rects.iter().filter(|r| r.center().y < pointer.y).count();
+        let to = if slot > from { slot - 1 } else { slot };
+        if let Some(last) = slot_rects.last() {
+            let y = slot_rects.get(slot).map_or(last.bottom() + 2.0, |r| r.top() - 2.0);
+            ui.painter().line_segment(
+                [egui::pos2(last.left(), y), egui::pos2(last.right(), y)],
+                Stroke::new(2.0, Color32::from_rgb(120, 200, 255)),
+            );
+        }
+
+        if !ui.input(|i| i.pointer.any_down()) {
+            self.drag_source = None;
+            if to != from && to < slot_rects.len() {
+                self.expanded_effect = self.expanded_effect.map(|i| moved_index(i, from, to));
+                return MidiFxRackAction::MoveEffect { from, to };
+            }
+        }
+        MidiFxRackAction::None
+    }
+
+    fn draw_effect_slot(&mut self, ui: &mut Ui, idx: usize, effect: &MidiEffect) -> (MidiFxRackAction, Rect) {
         let mut action = MidiFxRackAction::None;
         let is_expanded = self.expanded_effect == Some(idx);
         let is_bypassed = effect.is_bypassed();
@@ -240,19 +335,11 @@ impl MidiFxRackPanel {
             self.expanded_effect = if is_expanded { None } else { Some(idx) };
         }
 
-        // Drag and drop
+        // Drag to reorder; the drop is handled once all slots are laid out
         if response.drag_started() {
             self.drag_source = Some(idx);
         }
 
-        if response.hovered() && ui.input(|i| i.pointer.any_released()) {
-            if let Some(from) = self.drag_source.take() {
-                if from != idx {
-                    action = MidiFxRackAction::MoveEffect { from, to: idx };
-                }
-            }
-        }
-
         // Parameter panel (when expanded)
         if is_expanded {
             ui.add_space(4.0);
@@ -261,10 +348,16 @@ impl MidiFxRackPanel {
             if !matches!(param_action, MidiFxRackAction::None) {
                 action = param_action;
             }
+            if let MidiEffect::Arpeggiator(arp) = effect {
+                let pattern_action = self.draw_arp_pattern(ui, idx, arp);
+                if !matches!(pattern_action, MidiFxRackAction::None) {
+                    action = pattern_action;
+                }
+            }
             ui.add_space(4.0);
         }
 
-        action
+        (action, rect)
     }
 
     fn draw_params(&mut self, ui: &mut Ui, effect_idx: usize, params: &[MidiFxParam]) -> MidiFxRackAction {
@@ -294,6 +387,37 @@ impl MidiFxRackPanel {
 
         action
     }
+
+    /// Step toggles for the arpeggiator's rest pattern
+    fn draw_arp_pattern(&mut self, ui: &mut Ui, effect_idx: usize, arp: &ArpeggiatorFx) -> MidiFxRackAction {
+        let mut action = MidiFxRackAction::None;
+
+        ui.horizontal(|ui| {
+            ui.add_space(16.0);
+            ui.label(format!("Rate: {}", arp.division_label()));
+        });
+        ui.horizontal(|ui| {
+            ui.add_space(16.0);
+            ui.spacing_mut().item_spacing.x = 2.0;
+            for step in 0..arp.pattern_len() {
+                let on = arp.pattern_step(step);
+                let (rect, response) = ui.allocate_exact_size(Vec2::new(14.0, 14.0), Sense::click());
+                let fill = if on {
+                    Color32::from_rgb(100, 150, 220)
+                } else if step % 4 == 0 {
+                    Color32::from_gray(55)
+                } else {
+                    Color32::from_gray(40)
+                };
+                ui.painter().rect_filled(rect, 2.0, fill);
+                if response.clicked() {
+                    action = MidiFxRackAction::SetArpStep { effect_idx, step, on: !on };
+                }
+            }
+        });
+
+        action
+    }
 }
 
 impl Default for MidiFxRackPanel {
@@ -1,29 +1,65 @@
 //! UI panels
 
+mod analyzer;
 mod arrange;
+mod audio_settings;
+mod automation;
 mod browser;
+mod chord_track;
 mod clip_editor;
+mod control_surface;
 mod device_rack;
 mod drum_roll;
+mod generate;
+mod grid;
 mod keyboard_sequencer;
+mod keymap;
+mod keymap_editor;
+mod layout;
 mod midi_fx_rack;
+mod modulation;
+mod notifications;
 mod piano_roll;
 mod plugins;
+mod preferences;
+mod sample_library;
+mod script_console;
+mod snapshots;
 mod song_view;
+mod status_bar;
+mod theme;
 mod timeline;
 mod track_headers;
+mod track_layout;
 mod transport;
+mod zoom;
 
+pub use analyzer::AnalyzerPanel;
 pub use arrange::{ArrangeAction, ArrangePanel};
+pub use audio_settings::AudioSettingsAction;
+pub use automation::{AutomationAction, AutomationPanel};
 pub use browser::{BrowserAction, BrowserPanel};
+pub use chord_track::{ChordTrackAction, ChordTrackPanel};
 pub use clip_editor::ClipEditorPanel;
-pub use device_rack::{DeviceInfo, DeviceRackAction, DeviceRackPanel};
+pub use control_surface::{ControlSurfaceAction, ControlSurfacePanel};
+pub use device_rack::{DeviceInfo, DeviceRackAction, DeviceRackPanel, MappableDevice};
 pub use drum_roll::{DrumRollAction, DrumRollPanel};
 pub use keyboard_sequencer::{KeyboardSequencerAction, KeyboardSequencerPanel};
-pub use midi_fx_rack::{MidiFxRackAction, MidiFxRackPanel};
+pub use keymap::{Command, Keymap, KeymapPreset};
+pub use keymap_editor::{KeymapEditorAction, KeymapEditorPanel};
+pub use layout::{Layout, Workspaces};
+pub use midi_fx_rack::{MidiEffectType, MidiFxRackAction, MidiFxRackPanel};
+pub use modulation::{ModulationAction, ModulationPanel};
+pub use notifications::NotificationCenter;
 pub use piano_roll::PianoRollAction;
 pub use plugins::{PluginAction, PluginBrowserPanel};
+pub use preferences::{Preferences, PreferencesAction, PreferencesPanel};
+pub use script_console::{ScriptConsoleAction, ScriptConsolePanel};
+pub use snapshots::{SnapshotAction, SnapshotPanel};
 pub use song_view::{SongViewAction, SongViewPanel};
+pub use status_bar::{EngineStatus, StatusBarPanel};
+pub use theme::{Accents, Theme};
 pub use timeline::RecordingPreview;
 pub use track_headers::{TrackHeaderAction, TrackHeadersPanel};
+pub use track_layout::TrackLayout;
 pub use transport::{TransportAction, TransportPanel};
@@ -0,0 +1,207 @@
+//! Modulation panel - LFO/envelope sources against parameter destinations
+
+use egui::{Color32, ScrollArea, Sense, Ui, Vec2};
+use hallucinator_core::{LfoShape, ModDestination, ModSource, ModulationMatrix, LFO_RATES};
+
+use super::MappableDevice;
+
+/// Action returned from the modulation panel
+pub enum ModulationAction {
+    None,
+    AddSource(ModSource),
+    RemoveSource(usize),
+    UpdateSource(usize, ModSource),
+    AddDestination(ModDestination),
+    RemoveDestination(usize),
+    /// Set the unmodulated value of a destination
+    SetCenter(usize, f32),
+    SetDepth { dest: usize, source: usize, depth: f32 },
+}
+
+/// Modulation matrix panel (operates on the selected track)
+pub struct ModulationPanel;
+
+impl ModulationPanel {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        track_name: Option<&str>,
+        matrix: Option<&ModulationMatrix>,
+        devices: &[MappableDevice],
+    ) -> ModulationAction {
+        let mut action = ModulationAction::None;
+
+        let (Some(track_name), Some(matrix)) = (track_name, matrix) else {
+            ui.centered_and_justified(|ui| {
+                ui.label("Select a track to edit modulation");
+            });
+            return action;
+        };
+
+        ui.horizontal(|ui| {
+            ui.strong(track_name);
+            ui.separator();
+            if ui.button("+ LFO").clicked() {
+                action = ModulationAction::AddSource(ModSource::lfo());
+            }
+            if ui.button("+ Envelope").clicked() {
+                action = ModulationAction::AddSource(ModSource::envelope());
+            }
+            ui.menu_button("+ Destination", |ui| {
+                if devices.is_empty() {
+                    ui.label("No devices on this track");
+                }
+                for device in devices {
+                    ui.menu_button(&device.name, |ui| {
+                        ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                            for param in &device.params {
+                                if ui.button(&param.name).clicked() {
+                                    action = ModulationAction::AddDestination(ModDestination::new(
+                                        device.target,
+                                        &param.name,
+                                        param.value,
+                                        param.min,
+                                        param.max,
+                                    ));
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+                }
+            });
+        });
+        ui.separator();
+
+        if matrix.sources.is_empty() && matrix.destinations.is_empty() {
+            ui.label("Add a source and a destination, then set depths in the grid");
+            return action;
+        }
+
+        ScrollArea::both().show(ui, |ui| {
+            egui::Grid::new("modulation_matrix")
+                .striped(true)
+                .spacing(Vec2::new(8.0, 4.0))
+                .show(ui, |ui| {
+                    ui.label("");
+                    ui.label("Center");
+                    for (idx, source) in matrix.sources.iter().enumerate() {
+                        let source_action = source_header(ui, idx, source, matrix.source_value(idx));
+                        if !matches!(source_action, ModulationAction::None) {
+                            action = source_action;
+                        }
+                    }
+                    ui.end_row();
+
+                    for (dest_idx, dest) in matrix.destinations.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("×").clicked() {
+                                action = ModulationAction::RemoveDestination(dest_idx);
+                            }
+                            let device = devices
+                                .iter()
+                                .find(|d| d.target == dest.target)
+                                .map(|d| d.name.as_str())
+                                .unwrap_or("(missing)");
+                            ui.label(format!("{} · {}", device, dest.param));
+                        });
+
+                        let mut center = dest.center;
+                        let speed = ((dest.max - dest.min) / 200.0).clamp(0.001, 100.0);
+                        let lo = dest.min.min(dest.max);
+                        let hi = dest.max.max(dest.min);
+                        if ui.add(egui::DragValue::new(&mut center).range(lo..=hi).speed(speed)).changed() {
+                            action = ModulationAction::SetCenter(dest_idx, center);
+                        }
+
+                        for source_idx in 0..matrix.sources.len() {
+                            let mut depth = dest.depths.get(source_idx).copied().unwrap_or(0.0) * 100.0;
+                            let drag = egui::DragValue::new(&mut depth)
+                                .range(-100.0..=100.0)
+                                .speed(0.5)
+                                .suffix("%");
+                            if ui.add(drag).changed() {
+                                action = ModulationAction::SetDepth { dest: dest_idx, source: source_idx, depth: depth / 100.0 };
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+
+        action
+    }
+}
+
+/// Column header for a source: name, live value and settings menu
+fn source_header(ui: &mut Ui, idx: usize, source: &ModSource, value: f32) -> ModulationAction {
+    let mut action = ModulationAction::None;
+
+    ui.vertical(|ui| {
+        ui.horizontal(|ui| {
+            ui.menu_button(format!("{} {}", source.name(), idx + 1), |ui| {
+                let mut edited = source.clone();
+                match &mut edited {
+                    ModSource::Lfo { shape, rate_beats } => {
+                        // Plain selectables rather than combo boxes: nested popups close the menu
+                        ui.label("Shape");
+                        ui.horizontal_wrapped(|ui| {
+                            for s in LfoShape::ALL {
+                                ui.selectable_value(shape, s, s.name());
+                            }
+                        });
+                        ui.label("Rate");
+                        ui.horizontal_wrapped(|ui| {
+                            for &(label, beats) in LFO_RATES {
+                                ui.selectable_value(rate_beats, beats, label);
+                            }
+                        });
+                    }
+                    ModSource::Envelope { attack_ms, release_ms, gain } => {
+                        ui.add(egui::Slider::new(attack_ms, 0.1..=500.0).logarithmic(true).text("attack ms"));
+                        ui.add(egui::Slider::new(release_ms, 1.0..=2000.0).logarithmic(true).text("release ms"));
+                        ui.add(egui::Slider::new(gain, 0.1..=10.0).logarithmic(true).text("gain"));
+                    }
+                }
+                if edited != *source {
+                    action = ModulationAction::UpdateSource(idx, edited);
+                }
+                ui.separator();
+                if ui.button("Remove").clicked() {
+                    action = ModulationAction::RemoveSource(idx);
+                    ui.close_menu();
+                }
+            });
+        });
+
+        // Live value: bipolar sources fill from the center
+        let (rect, _) = ui.allocate_exact_size(Vec2::new(60.0, 4.0), Sense::hover());
+        let painter = ui.painter();
+        painter.rect_filled(rect, 1.0, Color32::from_gray(40));
+        let bipolar = matches!(source, ModSource::Lfo { .. });
+        let (from, to): (f32, f32) = if bipolar {
+            (0.5, 0.5 + value.clamp(-1.0, 1.0) * 0.5)
+        } else {
+            (0.0, value.clamp(0.0, 1.0))
+        };
+        let x0 = rect.left() + rect.width() * from.min(to);
+        let x1 = rect.left() + rect.width() * from.max(to);
+        painter.rect_filled(
+            egui::Rect::from_min_max(egui::pos2(x0, rect.top()), egui::pos2(x1, rect.bottom())),
+            1.0,
+            Color32::from_rgb(120, 200, 255),
+        );
+    });
+
+    action
+}
+
+impl Default for ModulationPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
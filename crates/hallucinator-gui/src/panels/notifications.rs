@@ -0,0 +1,184 @@
+//! Toasts for failures posted through `hallucinator_services::notifications`, in the
+//! bottom-right corner for a few seconds, and a log window with the details of each
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use egui::{Align, Align2, CollapsingHeader, Color32, Context, Id, Label, Order, RichText, ScrollArea, Sense, Ui};
+use hallucinator_services::{notifications, Notification, Severity};
+
+/// How long a toast stays up
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+/// Toasts stacked at once; older ones are only in the log
+const MAX_TOASTS: usize = 4;
+/// Notifications kept in the log
+const HISTORY_LEN: usize = 200;
+/// Keeps toasts clear of the status bar
+const TOAST_OFFSET: [f32; 2] = [-8.0, -28.0];
+
+struct Entry {
+    id: u64,
+    notification: Notification,
+    /// Times it was posted in a row
+    count: u32,
+    /// When it was last posted
+    at: Instant,
+    /// Toast closed before it ran out
+    dismissed: bool,
+}
+
+impl Entry {
+    fn toast_visible(&self) -> bool {
+        !self.dismissed && self.at.elapsed() < TOAST_DURATION
+    }
+
+    fn title(&self) -> String {
+        let icon = match self.notification.severity {
+            Severity::Info => "ℹ",
+            Severity::Warning => "⚠",
+            Severity::Error => "⛔",
+        };
+        if self.count > 1 {
+            format!("{} {} (×{})", icon, self.notification.title, self.count)
+        } else {
+            format!("{} {}", icon, self.notification.title)
+        }
+    }
+
+    fn color(&self, ui: &Ui) -> Color32 {
+        match self.notification.severity {
+            Severity::Info => ui.visuals().text_color(),
+            Severity::Warning => ui.visuals().warn_fg_color,
+            Severity::Error => ui.visuals().error_fg_color,
+        }
+    }
+}
+
+pub struct NotificationCenter {
+    /// Newest last
+    history: VecDeque<Entry>,
+    next_id: u64,
+    /// Log window, opened from View or by clicking a toast
+    pub show_log: bool,
+    /// Entry to expand and scroll to when the log opens
+    reveal: Option<u64>,
+}
+
+impl NotificationCenter {
+    pub fn new() -> Self {
+        Self { history: VecDeque::new(), next_id: 0, show_log: false, reveal: None }
+    }
+
+    /// Take in what was posted since the last frame. The same notification posted again
+    /// while its toast is up counts up on that toast instead of stacking another
+    pub fn poll(&mut self) {
+        for notification in notifications::drain() {
+            if let Some(last) = self.history.back_mut()
+                && last.notification == notification
+                && last.toast_visible()
+            {
+                last.count += 1;
+                last.at = Instant::now();
+                continue;
+            }
+            self.history.push_back(Entry { id: self.next_id, notification, count: 1, at: Instant::now(), dismissed: false });
+            self.next_id += 1;
+            if self.history.len() > HISTORY_LEN {
+                self.history.pop_front();
+            }
+        }
+    }
+
+    /// Draw the toasts and, if open, the log window
+    pub fn ui(&mut self, ctx: &Context) {
+        self.toasts_ui(ctx);
+
+        let mut open = self.show_log;
+        egui::Window::new("Notifications")
+            .open(&mut open)
+            .default_size([460.0, 320.0])
+            .show(ctx, |ui| self.log_ui(ui));
+        self.show_log = open;
+    }
+
+    fn toasts_ui(&mut self, ctx: &Context) {
+        let visible: Vec<usize> = (0..self.history.len())
+            .rev()
+            .filter(|&i| self.history[i].toast_visible())
+            .take(MAX_TOASTS)
+            .collect();
+        let Some(expires) = visible.iter().map(|&i| TOAST_DURATION.saturating_sub(self.history[i].at.elapsed())).min()
+        else {
+            return;
+        };
+        ctx.request_repaint_after(expires);
+
+        egui::Area::new(Id::new("notification_toasts"))
+            .anchor(Align2::RIGHT_BOTTOM, TOAST_OFFSET)
+            .order(Order::Foreground)
+            .show(ctx, |ui| {
+                ui.set_max_width(320.0);
+                // Oldest at the top, newest by the corner
+                for &i in visible.iter().rev() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            let entry = &self.history[i];
+                            let title = RichText::new(entry.title()).color(entry.color(ui));
+                            let clicked = ui
+                                .add(Label::new(title).truncate().sense(Sense::click()))
+                                .on_hover_cursor(egui::CursorIcon::PointingHand)
+                                .on_hover_text("Show details")
+                                .clicked();
+                            if clicked {
+                                self.show_log = true;
+                                self.reveal = Some(entry.id);
+                            }
+                            if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                                self.history[i].dismissed = true;
+                            }
+                        });
+                    });
+                }
+            });
+    }
+
+    fn log_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label(format!("{} notifications", self.history.len()));
+            if ui.add_enabled(!self.history.is_empty(), egui::Button::new("Clear")).clicked() {
+                self.history.clear();
+            }
+        });
+        ui.separator();
+
+        let reveal = self.reveal.take();
+        ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+            if self.history.is_empty() {
+                ui.weak("Nothing has gone wrong yet");
+            }
+            for entry in self.history.iter().rev() {
+                let revealed = reveal == Some(entry.id);
+                let header = CollapsingHeader::new(RichText::new(entry.title()).color(entry.color(ui)))
+                    .id_salt(entry.id)
+                    .open(revealed.then_some(true))
+                    .show(ui, |ui| {
+                        ui.weak(format!("{} s ago", entry.at.elapsed().as_secs()));
+                        let detail = &entry.notification.detail;
+                        ui.add(Label::new(RichText::new(detail).monospace()).selectable(true));
+                        if ui.small_button("Copy").clicked() {
+                            ui.ctx().copy_text(format!("{}\n{}", entry.notification.title, detail));
+                        }
+                    });
+                if revealed {
+                    header.header_response.scroll_to_me(Some(Align::Center));
+                }
+            }
+        });
+    }
+}
+
+impl Default for NotificationCenter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,300 @@
+//! Lane under the note grid: note velocities, or CC curves, pitch bend and aftertouch
+
+use egui::{Color32, ComboBox, DragValue, Pos2, Rect, Sense, Shape, Stroke, Ui, Vec2};
+use hallucinator_core::{MidiClip, MidiControl, MidiControlEvent};
+
+use super::types::{LaneDrag, LaneTool};
+use super::PianoRollPanel;
+
+/// Height of the lane's toolbar row
+pub(super) const LANE_HEADER_HEIGHT: f32 = 24.0;
+/// Height of the lane itself
+pub(super) const LANE_HEIGHT: f32 = 80.0;
+
+/// Resolution of drawn curves with snap off
+const UNSNAPPED_POINTS_PER_BEAT: f64 = 32.0;
+
+/// Controllers offered in the lane menu
+const LANE_CONTROLS: [(&str, MidiControl); 9] = [
+    ("Mod Wheel", MidiControl::Cc(1)),
+    ("Breath", MidiControl::Cc(2)),
+    ("Volume", MidiControl::Cc(7)),
+    ("Pan", MidiControl::Cc(10)),
+    ("Expression", MidiControl::Cc(11)),
+    ("Sustain", MidiControl::Cc(64)),
+    ("Pitch Bend", MidiControl::PitchBend),
+    ("Aftertouch", MidiControl::ChannelPressure),
+    ("Poly Aftertouch", MidiControl::PolyPressure(60)),
+];
+
+fn control_label(control: MidiControl) -> String {
+    match control {
+        MidiControl::Cc(cc) => match LANE_CONTROLS.iter().find(|(_, c)| *c == control) {
+            Some((name, _)) => format!("{} (CC {})", name, cc),
+            None => format!("CC {}", cc),
+        },
+        MidiControl::PitchBend => "Pitch Bend".to_string(),
+        MidiControl::ChannelPressure => "Aftertouch".to_string(),
+        MidiControl::PolyPressure(pitch) => format!("Poly Aftertouch {}", pitch),
+    }
+}
+
+/// Replace a controller's changes between two points with a straight ramp, one
+/// change per `step` ticks (repeated values are left out)
+fn write_ramp(clip: &mut MidiClip, control: MidiControl, from: (u64, u16), to: (u64, u16), step: u64) {
+    let ((t0, v0), (t1, v1)) = if from.0 <= to.0 { (from, to) } else { (to, from) };
+    clip.controls.retain(|c| c.control != control || c.tick < t0 || c.tick > t1);
+
+    let span = (t1 - t0) as f64;
+    let mut tick = t0;
+    let mut last = None;
+    loop {
+        let value = if span == 0.0 {
+            v1
+        } else {
+            (v0 as f64 + (v1 as f64 - v0 as f64) * (tick - t0) as f64 / span).round() as u16
+        };
+        if last != Some(value) {
+            clip.add_control(MidiControlEvent::new(control, tick, value));
+            last = Some(value);
+        }
+        if tick >= t1 {
+            break;
+        }
+        tick = (tick + step.max(1)).min(t1);
+    }
+}
+
+impl PianoRollPanel {
+    /// Draw the lane toolbar and lane; returns true if the clip was edited
+    pub(super) fn draw_control_lane(&mut self, ui: &mut Ui, clip: &mut MidiClip, key_width: f32, clip_length_beats: f64) -> bool {
+        let mut modified = false;
+
+        ui.horizontal(|ui| {
+            ui.set_height(LANE_HEADER_HEIGHT);
+            let selected = if self.velocity_lane { "Velocity".to_string() } else { control_label(self.lane_control) };
+            ComboBox::from_id_salt("piano_roll_control_lane")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.velocity_lane, true, "Velocity");
+                    ui.separator();
+                    for (name, control) in LANE_CONTROLS {
+                        let current = !self.velocity_lane && self.lane_control == control;
+                        if ui.selectable_label(current, name).clicked() {
+                            self.lane_control = control;
+                            self.velocity_lane = false;
+                        }
+                    }
+                    // Controllers the clip already uses, beyond the usual ones
+                    let mut used: Vec<MidiControl> = Vec::new();
+                    for event in &clip.controls {
+                        if !used.contains(&event.control) && !LANE_CONTROLS.iter().any(|(_, c)| *c == event.control) {
+                            used.push(event.control);
+                        }
+                    }
+                    if !used.is_empty() {
+                        ui.separator();
+                        for control in used {
+                            let current = !self.velocity_lane && self.lane_control == control;
+                            if ui.selectable_label(current, control_label(control)).clicked() {
+                                self.lane_control = control;
+                                self.velocity_lane = false;
+                            }
+                        }
+                    }
+                });
+            if !self.velocity_lane {
+                match &mut self.lane_control {
+                    MidiControl::Cc(cc) => {
+                        ui.label("CC");
+                        ui.add(DragValue::new(cc).range(0..=127));
+                    }
+                    MidiControl::PolyPressure(pitch) => {
+                        ui.label("Key");
+                        ui.add(DragValue::new(pitch).range(0..=127));
+                    }
+                    MidiControl::PitchBend | MidiControl::ChannelPressure => {}
+                }
+            }
+            ui.separator();
+            ui.selectable_value(&mut self.lane_tool, LaneTool::Freehand, "✏ Draw");
+            ui.selectable_value(&mut self.lane_tool, LaneTool::Line, "╱ Line");
+            ui.separator();
+            if self.velocity_lane {
+                modified |= self.velocity_tools_ui(ui, clip);
+            } else if ui
+                .button("Clear")
+                .on_hover_text("Remove this controller's changes in the loop selection, or the whole clip")
+                .clicked()
+            {
+                let ppq = clip.ppq as f64;
+                let (start, end) = match self.loop_selection {
+                    Some(ref sel) => ((sel.start_beat * ppq) as u64, (sel.end_beat * ppq) as u64),
+                    None => (0, u64::MAX),
+                };
+                let control = self.lane_control;
+                clip.controls.retain(|c| c.control != control || c.tick < start || c.tick >= end);
+                modified = true;
+            }
+        });
+
+        let (response, painter) = ui.allocate_painter(Vec2::new(ui.available_width(), LANE_HEIGHT), Sense::click_and_drag());
+        let rect = response.rect;
+        let lane_rect = Rect::from_min_max(Pos2::new(rect.left() + key_width, rect.top()), rect.max);
+
+        painter.rect_filled(rect, 0.0, Color32::from_gray(40));
+        painter.rect_filled(lane_rect, 0.0, Color32::from_gray(25));
+        self.draw_lane_grid(&painter, lane_rect);
+        if self.velocity_lane {
+            self.draw_velocity_bars(&painter, lane_rect, clip);
+            modified |= self.handle_velocity_input(&response, lane_rect, clip);
+        } else {
+            self.draw_lane_values(&painter, lane_rect, clip);
+            modified |= self.handle_lane_input(ui, &response, lane_rect, clip, clip_length_beats);
+        }
+
+        // Line preview and value readout while drawing
+        if let Some(drag) = self.lane_drag {
+            let ppq = clip.ppq as f64;
+            if self.lane_tool == LaneTool::Line {
+                let from = Pos2::new(self.lane_x(drag.start.0, ppq, lane_rect), self.lane_y(drag.start.1, lane_rect));
+                let to = Pos2::new(self.lane_x(drag.last.0, ppq, lane_rect), self.lane_y(drag.last.1, lane_rect));
+                painter.line_segment([from, to], Stroke::new(1.5, Color32::from_rgb(255, 200, 100)));
+            }
+            painter.text(
+                Pos2::new(rect.left() + 4.0, rect.bottom() - 4.0),
+                egui::Align2::LEFT_BOTTOM,
+                drag.last.1.to_string(),
+                egui::FontId::proportional(10.0),
+                Color32::from_rgb(255, 200, 100),
+            );
+        }
+
+        modified
+    }
+
+    pub(super) fn lane_x(&self, tick: u64, ppq: f64, rect: Rect) -> f32 {
+        rect.left() + ((tick as f64 / ppq - self.scroll_x) * self.pixels_per_beat as f64) as f32
+    }
+
+    pub(super) fn lane_y(&self, value: u16, rect: Rect) -> f32 {
+        let max = if self.velocity_lane { 127 } else { self.lane_control.max_value() };
+        rect.bottom() - value.min(max) as f32 / max as f32 * rect.height()
+    }
+
+    /// Ticks between drawn points: the grid with snap on, a fine step without
+    fn lane_step(&self, ppq: f64) -> u64 {
+        let step_beats = if self.grid.snap { self.grid_subdivision } else { 1.0 / UNSNAPPED_POINTS_PER_BEAT };
+        ((step_beats * ppq) as u64).max(1)
+    }
+
+    /// Tick (on the drawing step) and value under a lane position
+    fn lane_point(&self, pos: Pos2, rect: Rect, ppq: f64, clip_length_beats: f64) -> (u64, u16) {
+        let beat = (self.scroll_x + (pos.x - rect.left()) as f64 / self.pixels_per_beat as f64).clamp(0.0, clip_length_beats);
+        let step = self.lane_step(ppq);
+        let tick = (beat * ppq) as u64 / step * step;
+        let level = ((rect.bottom() - pos.y) / rect.height()).clamp(0.0, 1.0);
+        let value = (level * self.lane_control.max_value() as f32).round() as u16;
+        (tick, value)
+    }
+
+    fn draw_lane_grid(&self, painter: &egui::Painter, rect: Rect) {
+        let beats_visible = rect.width() as f64 / self.pixels_per_beat as f64;
+        let mut beat = self.scroll_x.floor();
+        while beat <= self.scroll_x + beats_visible {
+            let x = rect.left() + ((beat - self.scroll_x) * self.pixels_per_beat as f64) as f32;
+            if x >= rect.left() {
+                let gray = if (beat as i64) % 4 == 0 { 90 } else { 50 };
+                painter.vline(x, rect.y_range(), Stroke::new(0.5, Color32::from_gray(gray)));
+            }
+            beat += 1.0;
+        }
+
+        // Pitch bend rests in the middle
+        if !self.velocity_lane && self.lane_control == MidiControl::PitchBend {
+            let y = self.lane_y(MidiControl::PitchBend.default_value(), rect);
+            painter.hline(rect.x_range(), y, Stroke::new(0.5, Color32::from_gray(80)));
+        }
+    }
+
+    /// Step curve of the selected controller, with a dot at each change
+    fn draw_lane_values(&self, painter: &egui::Painter, rect: Rect, clip: &MidiClip) {
+        let ppq = clip.ppq as f64;
+        let control = self.lane_control;
+        let color = Color32::from_rgb(100, 200, 140);
+
+        let mut y = self.lane_y(control.default_value(), rect);
+        let mut points = vec![Pos2::new(rect.left(), y)];
+        let mut dots = Vec::new();
+        for event in clip.controls.iter().filter(|c| c.control == control) {
+            let x = self.lane_x(event.tick, ppq, rect);
+            if x > rect.right() {
+                break;
+            }
+            let x = x.max(rect.left());
+            points.push(Pos2::new(x, y));
+            y = self.lane_y(event.value, rect);
+            points.push(Pos2::new(x, y));
+            if x > rect.left() {
+                dots.push(Pos2::new(x, y));
+            }
+        }
+        points.push(Pos2::new(rect.right(), y));
+
+        painter.add(Shape::line(points, Stroke::new(1.5, color)));
+        for dot in dots {
+            painter.circle_filled(dot, 2.5, color);
+        }
+    }
+
+    /// Draw with the primary button, erase with the secondary; returns true if the clip changed
+    fn handle_lane_input(
+        &mut self,
+        ui: &Ui,
+        response: &egui::Response,
+        rect: Rect,
+        clip: &mut MidiClip,
+        clip_length_beats: f64,
+    ) -> bool {
+        let ppq = clip.ppq as f64;
+        let control = self.lane_control;
+        let step = self.lane_step(ppq);
+
+        if !response.is_pointer_button_down_on() {
+            // Release finishes a line
+            let Some(drag) = self.lane_drag.take() else { return false };
+            if self.lane_tool == LaneTool::Line {
+                write_ramp(clip, control, drag.start, drag.last, step);
+                return true;
+            }
+            return false;
+        }
+
+        let Some(pos) = response.interact_pointer_pos() else { return false };
+        let point = self.lane_point(pos, rect, ppq, clip_length_beats);
+
+        if ui.input(|i| i.pointer.secondary_down()) {
+            let (tick, _) = point;
+            let before = clip.controls.len();
+            clip.controls.retain(|c| c.control != control || c.tick < tick || c.tick >= tick + step);
+            return clip.controls.len() != before;
+        }
+
+        let last = match &mut self.lane_drag {
+            Some(drag) if drag.last == point => return false,
+            Some(drag) => std::mem::replace(&mut drag.last, point),
+            None => {
+                self.lane_drag = Some(LaneDrag { start: point, last: point });
+                point
+            }
+        };
+
+        match self.lane_tool {
+            LaneTool::Freehand => {
+                write_ramp(clip, control, last, point, step);
+                true
+            }
+            LaneTool::Line => false,
+        }
+    }
+}
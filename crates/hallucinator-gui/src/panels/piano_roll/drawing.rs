@@ -2,12 +2,12 @@ use egui::{Color32, Pos2, Rect, Stroke, StrokeKind, Vec2};
 use hallucinator_core::MidiClip;
 
 use super::PianoRollPanel;
+use crate::panels::theme::{shade, with_alpha, Palette};
 
 impl PianoRollPanel {
     pub(super) fn draw_piano_keys(&self, painter: &egui::Painter, rect: Rect) {
-        let pitch_max = (self.visible_pitch_min + self.visible_pitches).min(127);
-
-        for pitch in self.visible_pitch_min..pitch_max {
+        let palette = Palette::get(painter.ctx());
+        for pitch in self.visible_rows() {
             let y = self.pitch_to_y(pitch, rect);
             let key_rect = Rect::from_min_size(
                 Pos2::new(rect.left(), y),
@@ -18,20 +18,20 @@ impl PianoRollPanel {
             let is_active = self.active_pitches.contains(&pitch);
 
             let color = if is_active {
-                Color32::from_rgb(255, 140, 0)
+                palette.active
             } else if is_black {
-                Color32::from_gray(30)
+                palette.key_black
             } else {
-                Color32::from_gray(60)
+                palette.key_white
             };
 
             painter.rect_filled(key_rect, 0.0, color);
-            painter.rect_stroke(key_rect, 0.0, Stroke::new(0.5, Color32::from_gray(20)), StrokeKind::Inside);
+            painter.rect_stroke(key_rect, 0.0, Stroke::new(0.5, palette.separator), StrokeKind::Inside);
 
             // Label C notes
             if pitch % 12 == 0 {
                 let octave = (pitch as i32 / 12) - 1;
-                let text_color = if is_active { Color32::BLACK } else { Color32::WHITE };
+                let text_color = if is_active { Color32::BLACK } else { palette.text };
                 painter.text(
                     Pos2::new(rect.left() + 2.0, y + 2.0),
                     egui::Align2::LEFT_TOP,
@@ -47,46 +47,45 @@ impl PianoRollPanel {
         let start_beat = self.scroll_x;
         let end_beat = start_beat + beats_visible;
 
+        let palette = Palette::get(painter.ctx());
         let grid_step = self.grid_subdivision;
         let pixels_per_grid = self.pixels_per_beat as f64 * grid_step;
 
         // Only draw if grid lines have minimum spacing
         if pixels_per_grid >= 8.0 {
-            let mut pos = (start_beat / grid_step).floor() * grid_step;
-            while pos <= end_beat {
+            for pos in self.grid.lines(start_beat, end_beat, grid_step) {
                 let x = rect.left() + ((pos - start_beat) * self.pixels_per_beat as f64) as f32;
 
                 let is_bar = (pos.round() as i32) % 4 == 0 && (pos - pos.round()).abs() < 0.001;
                 let is_beat = (pos - pos.round()).abs() < 0.001;
 
                 let stroke = if is_bar {
-                    Stroke::new(1.0, Color32::from_gray(90))
+                    Stroke::new(1.0, palette.grid_bar)
                 } else if is_beat {
-                    Stroke::new(0.5, Color32::from_gray(65))
+                    Stroke::new(0.5, palette.grid_beat)
                 } else {
-                    Stroke::new(0.5, Color32::from_gray(45))
+                    Stroke::new(0.5, palette.grid_subdivision)
                 };
 
                 painter.line_segment([Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())], stroke);
-                pos += grid_step;
             }
         }
 
         // Draw horizontal lines (pitches)
-        let pitch_max = (self.visible_pitch_min + self.visible_pitches).min(127);
-        for pitch in self.visible_pitch_min..pitch_max {
+        for pitch in self.visible_rows() {
             let y = self.pitch_to_y(pitch, rect) + self.key_height;
             let is_c = pitch % 12 == 0;
             let stroke = if is_c {
-                Stroke::new(1.0, Color32::from_gray(60))
+                Stroke::new(1.0, palette.grid_beat)
             } else {
-                Stroke::new(0.5, Color32::from_gray(40))
+                Stroke::new(0.5, palette.grid_subdivision)
             };
             painter.line_segment([Pos2::new(rect.left(), y), Pos2::new(rect.right(), y)], stroke);
         }
     }
 
     pub(super) fn draw_notes(&self, painter: &egui::Painter, rect: Rect, clip: &MidiClip) {
+        let palette = Palette::get(painter.ctx());
         for (idx, note) in clip.notes.iter().enumerate() {
             let start_beat = note.start_tick as f64 / clip.ppq as f64;
             let duration_beats = note.duration_ticks as f64 / clip.ppq as f64;
@@ -94,33 +93,17 @@ impl PianoRollPanel {
             if start_beat + duration_beats < self.scroll_x {
                 continue;
             }
-            if note.pitch < self.visible_pitch_min || note.pitch >= self.visible_pitch_min + self.visible_pitches {
-                continue;
-            }
-
-            let x = rect.left() + ((start_beat - self.scroll_x) * self.pixels_per_beat as f64) as f32;
-            let y = self.pitch_to_y(note.pitch, rect);
-            let width = (duration_beats * self.pixels_per_beat as f64) as f32;
-
-            let note_rect = Rect::from_min_size(
-                Pos2::new(x, y),
-                Vec2::new(width.max(4.0), self.key_height - 1.0),
-            );
-
+            let Some(note_rect) = self.note_screen_rect(note, clip.ppq as f64, rect) else { continue };
             let visible_rect = note_rect.intersect(rect);
             if visible_rect.width() <= 0.0 {
                 continue;
             }
 
             let is_selected = self.selected_notes.contains(&idx);
-            let color = if is_selected {
-                Color32::from_rgb(100, 200, 255)
-            } else {
-                Color32::from_rgb(80, 160, 220)
-            };
+            let color = if is_selected { shade(palette.note, 0.2) } else { palette.note };
 
             painter.rect_filled(visible_rect, 2.0, color);
-            painter.rect_stroke(visible_rect, 2.0, Stroke::new(1.0, Color32::from_rgb(40, 80, 120)), StrokeKind::Inside);
+            painter.rect_stroke(visible_rect, 2.0, Stroke::new(1.0, shade(palette.note, -0.5)), StrokeKind::Inside);
         }
     }
 
@@ -146,7 +129,7 @@ impl PianoRollPanel {
             return;
         }
 
-        let playhead_color = Color32::from_rgb(255, 100, 100);
+        let playhead_color = Palette::get(painter.ctx()).playhead;
 
         painter.line_segment(
             [Pos2::new(playhead_x, grid_rect.top()), Pos2::new(playhead_x, grid_rect.bottom())],
@@ -176,10 +159,11 @@ impl PianoRollPanel {
             Pos2::new(start_x.max(grid_rect.left()), grid_rect.top()),
             Pos2::new(end_x.min(grid_rect.right()), grid_rect.bottom()),
         );
-        painter.rect_filled(sel_rect, 0.0, Color32::from_rgba_unmultiplied(100, 150, 255, 30));
-        painter.rect_stroke(sel_rect, 0.0, Stroke::new(2.0, Color32::from_rgb(100, 150, 255)), StrokeKind::Inside);
+        let loop_color = Palette::get(painter.ctx()).loop_region;
+        painter.rect_filled(sel_rect, 0.0, with_alpha(loop_color, 30));
+        painter.rect_stroke(sel_rect, 0.0, Stroke::new(2.0, loop_color), StrokeKind::Inside);
 
-        let handle_color = Color32::from_rgb(80, 130, 220);
+        let handle_color = shade(loop_color, -0.15);
 
         // Start handle
         if start_x >= grid_rect.left() {
@@ -209,4 +193,13 @@ impl PianoRollPanel {
             );
         }
     }
+
+    /// Rubber band drawn while dragging a selection
+    pub(super) fn draw_marquee(&self, painter: &egui::Painter, grid_rect: Rect) {
+        let Some((start, end)) = self.marquee else { return };
+        let band = Rect::from_two_pos(start, end).intersect(grid_rect);
+        let selection = Palette::get(painter.ctx()).selection;
+        painter.rect_filled(band, 0.0, with_alpha(selection, 25));
+        painter.rect_stroke(band, 0.0, Stroke::new(1.0, selection), StrokeKind::Inside);
+    }
 }
@@ -1,5 +1,5 @@
 use egui::{Pos2, Rect};
-use hallucinator_core::MidiClip;
+use hallucinator_core::{MidiClip, MidiNote};
 
 use super::types::DragMode;
 use super::PianoRollPanel;
@@ -67,9 +67,34 @@ impl PianoRollPanel {
         ]
     }
 
+    /// Index of the bottom row in view when the grid is folded to `rows`
+    pub(super) fn fold_base(&self, rows: &[u8]) -> usize {
+        rows.partition_point(|&p| p < self.visible_pitch_min)
+    }
+
+    /// Row of `pitch` counted up from the bottom of the view; None when folded away
+    pub(super) fn pitch_row(&self, pitch: u8) -> Option<i32> {
+        match &self.fold_rows {
+            None => Some(pitch as i32 - self.visible_pitch_min as i32),
+            Some(rows) => {
+                let idx = rows.binary_search(&pitch).ok()?;
+                Some(idx as i32 - self.fold_base(rows) as i32)
+            }
+        }
+    }
+
+    /// Pitches of the rows in view, bottom to top
+    pub(super) fn visible_rows(&self) -> Vec<u8> {
+        match &self.fold_rows {
+            None => (self.visible_pitch_min..(self.visible_pitch_min + self.visible_pitches).min(127)).collect(),
+            Some(rows) => rows[self.fold_base(rows)..].iter().take(self.visible_pitches as usize).copied().collect(),
+        }
+    }
+
     pub(super) fn pitch_to_y(&self, pitch: u8, rect: Rect) -> f32 {
-        let relative_pitch = (pitch.saturating_sub(self.visible_pitch_min)) as f32;
-        let inverted = self.visible_pitches as f32 - 1.0 - relative_pitch;
+        // Pitches folded out of the grid land below it
+        let row = self.pitch_row(pitch).unwrap_or(-1);
+        let inverted = self.visible_pitches as f32 - 1.0 - row as f32;
         rect.top() + inverted * self.key_height
     }
 
@@ -77,10 +102,43 @@ impl PianoRollPanel {
         let beat = self.scroll_x + (pos.x - rect.left()) as f64 / self.pixels_per_beat as f64;
         let row = ((pos.y - rect.top()) / self.key_height).floor();
         let inverted_pitch = ((self.visible_pitches - 1) as f32 - row).max(0.0) as u8;
-        let pitch = self.visible_pitch_min.saturating_add(inverted_pitch).min(127);
+        let pitch = match &self.fold_rows {
+            None => self.visible_pitch_min.saturating_add(inverted_pitch).min(127),
+            Some(rows) => {
+                let idx = (self.fold_base(rows) + inverted_pitch as usize).min(rows.len().saturating_sub(1));
+                rows.get(idx).copied().unwrap_or(self.visible_pitch_min)
+            }
+        };
         (beat, pitch)
     }
 
+    /// Grid line at or before `beat` (swing and snap groove included), or `beat` itself with snap off
+    pub(super) fn snap_beat_floor(&self, beat: f64) -> f64 {
+        if self.grid.snap { self.groove_shift(self.grid.snap_floor(beat, self.grid_subdivision)) } else { beat }
+    }
+
+    /// Grid line moved by the groove picked in the note commands, when snapping follows it
+    pub(super) fn groove_shift(&self, beat: f64) -> f64 {
+        let groove = self.groove.filter(|_| self.groove_snap).and_then(|idx| self.grooves.get(idx));
+        groove.map_or(beat, |g| g.shift(beat))
+    }
+
+    /// Screen rectangle of a note in the grid (may lie outside it); None when its row is out of view
+    pub(super) fn note_screen_rect(&self, note: &MidiNote, ppq: f64, rect: Rect) -> Option<Rect> {
+        let row = self.pitch_row(note.pitch)?;
+        if row < 0 || row >= self.visible_pitches as i32 {
+            return None;
+        }
+        let start_beat = note.start_tick as f64 / ppq;
+        let duration_beats = note.duration_ticks as f64 / ppq;
+        let x = rect.left() + ((start_beat - self.scroll_x) * self.pixels_per_beat as f64) as f32;
+        let width = (duration_beats * self.pixels_per_beat as f64) as f32;
+        Some(Rect::from_min_size(
+            Pos2::new(x, self.pitch_to_y(note.pitch, rect)),
+            egui::Vec2::new(width.max(4.0), self.key_height - 1.0),
+        ))
+    }
+
     pub(super) fn find_note_at(
         &self,
         clip: &MidiClip,
@@ -2,7 +2,9 @@ use egui::{Pos2, Rect, Ui};
 use hallucinator_core::{MidiClip, MidiNote};
 
 use super::types::{DragMode, LoopDragMode, LoopSelection, NoteDragState, PianoRollAction};
-use super::PianoRollPanel;
+use super::{PianoRollPanel, MAX_PIXELS_PER_BEAT, MIN_PIXELS_PER_BEAT};
+use crate::panels::keymap::Command;
+use crate::panels::zoom::{fit_range, ZoomCommand};
 
 impl PianoRollPanel {
     /// Delete all selected notes, returning their pitches for note-off messages.
@@ -62,11 +64,7 @@ impl PianoRollPanel {
         }
 
         // Create new note snapped to grid
-        let quantized_beat = if self.snap_to_grid {
-            (beat / self.grid_subdivision).floor() * self.grid_subdivision
-        } else {
-            beat
-        };
+        let quantized_beat = self.snap_beat_floor(beat);
         let start_tick = (quantized_beat * clip.ppq as f64) as u64;
         let duration_ticks = (self.grid_subdivision * clip.ppq as f64) as u64;
 
@@ -75,10 +73,9 @@ impl PianoRollPanel {
         (true, PianoRollAction::None)
     }
 
-    /// Handle delete/backspace key.
+    /// Handle the delete key.
     pub(super) fn handle_delete_key(&mut self, ui: &Ui, clip: &mut MidiClip) -> (bool, PianoRollAction) {
-        let delete_pressed = ui.input(|i| i.key_pressed(egui::Key::Delete) || i.key_pressed(egui::Key::Backspace));
-        if !delete_pressed {
+        if !Command::Delete.pressed(ui.ctx()) {
             return (false, PianoRollAction::None);
         }
 
@@ -90,12 +87,17 @@ impl PianoRollPanel {
 
     /// Handle keyboard piano input. Returns action for note on/off.
     pub(super) fn handle_keyboard_piano(&mut self, ui: &Ui) -> PianoRollAction {
+        // Ctrl/Cmd chords are editing shortcuts (copy, cut, paste), not notes
+        if ui.input(|i| i.modifiers.ctrl || i.modifiers.command) {
+            return PianoRollAction::None;
+        }
+
         // Octave shift keys
-        if ui.input(|i| i.key_pressed(egui::Key::Minus)) {
+        if Command::OctaveDown.pressed(ui.ctx()) {
             self.keyboard_octave = (self.keyboard_octave - 1).max(-2);
             self.active_pitches.clear();
         }
-        if ui.input(|i| i.key_pressed(egui::Key::Equals)) {
+        if Command::OctaveUp.pressed(ui.ctx()) {
             self.keyboard_octave = (self.keyboard_octave + 1).min(2);
             self.active_pitches.clear();
         }
@@ -111,12 +113,14 @@ impl PianoRollPanel {
                 self.pressed_keys.insert(key);
                 if let Some(pitch) = self.key_to_pitch(key) {
                     self.active_pitches.insert(pitch);
+                    self.queue_step_note(pitch, 100);
                     note_on = Some(pitch);
                 }
             } else if !is_pressed && was_pressed {
                 self.pressed_keys.remove(&key);
                 if let Some(pitch) = self.key_to_pitch(key) {
                     self.active_pitches.remove(&pitch);
+                    self.queue_step_note(pitch, 0);
                     note_off = Some(pitch);
                 }
             }
@@ -132,6 +136,30 @@ impl PianoRollPanel {
     }
 
     /// Handle scroll and zoom via mouse wheel.
+    /// Beat range a fit command shows: the whole clip, the loop selection or the selected notes
+    pub(super) fn zoom_range(&self, cmd: ZoomCommand, clip: &MidiClip, clip_length_beats: f64) -> Option<(f64, f64)> {
+        match cmd {
+            ZoomCommand::ZoomIn | ZoomCommand::ZoomOut => None,
+            ZoomCommand::FitAll => (clip_length_beats > 0.0).then_some((0.0, clip_length_beats)),
+            ZoomCommand::FitLoop => self.loop_selection.as_ref().map(|sel| (sel.start_beat, sel.end_beat)),
+            ZoomCommand::FitSelection => {
+                let selected = || self.selected_notes.iter().filter_map(|&i| clip.notes.get(i));
+                let start = selected().map(|n| n.start_tick).min()?;
+                let end = selected().map(|n| n.end_tick()).max()?;
+                Some((start as f64 / clip.ppq as f64, end as f64 / clip.ppq as f64))
+            }
+        }
+    }
+
+    /// Step the zoom, or fit a range across the note grid
+    pub(super) fn apply_zoom(&mut self, cmd: ZoomCommand, clip: &MidiClip, grid_width: f32, clip_length_beats: f64) {
+        if let Some(factor) = cmd.step_factor() {
+            self.pixels_per_beat = (self.pixels_per_beat * factor).clamp(MIN_PIXELS_PER_BEAT, MAX_PIXELS_PER_BEAT);
+        } else if let Some((start, end)) = self.zoom_range(cmd, clip, clip_length_beats) {
+            (self.pixels_per_beat, self.scroll_x) = fit_range(start, end, grid_width, MIN_PIXELS_PER_BEAT, MAX_PIXELS_PER_BEAT);
+        }
+    }
+
     pub(super) fn handle_scroll_zoom(&mut self, ui: &Ui, response: &egui::Response) {
         if !response.hovered() {
             return;
@@ -144,7 +172,7 @@ impl PianoRollPanel {
         if modifiers.ctrl || modifiers.command {
             if scroll_delta.y.abs() > 0.1 {
                 let zoom_factor = 1.0 + scroll_delta.y * 0.008;
-                self.pixels_per_beat = (self.pixels_per_beat * zoom_factor).clamp(20.0, 200.0);
+                self.pixels_per_beat = (self.pixels_per_beat * zoom_factor).clamp(MIN_PIXELS_PER_BEAT, MAX_PIXELS_PER_BEAT);
             }
             return;
         }
@@ -152,15 +180,25 @@ impl PianoRollPanel {
         if scroll_delta.x.abs() > 0.0 || scroll_delta.y.abs() > 0.0 {
             self.scroll_x = (self.scroll_x - scroll_delta.x as f64 / self.pixels_per_beat as f64).max(0.0);
             let pitch_scroll = (scroll_delta.y / self.key_height) as i32;
-            self.visible_pitch_min = (self.visible_pitch_min as i32 + pitch_scroll)
-                .clamp(0, 127 - self.visible_pitches as i32) as u8;
+            self.visible_pitch_min = match &self.fold_rows {
+                // Folded grids scroll by row, not by semitone
+                Some(rows) => {
+                    let max_base = rows.len().saturating_sub(self.visible_pitches as usize) as i32;
+                    let base = (self.fold_base(rows) as i32 + pitch_scroll).clamp(0, max_base) as usize;
+                    rows.get(base).copied().unwrap_or(self.visible_pitch_min)
+                }
+                None => (self.visible_pitch_min as i32 + pitch_scroll)
+                    .clamp(0, 127 - self.visible_pitches as i32) as u8,
+            };
         }
     }
 
-    /// Handle drag end — emit loop region if selection was made.
+    /// Handle drag end — emit loop region if selection was made, or finish a rubber band.
     pub(super) fn handle_drag_end(
         &mut self,
         response: &egui::Response,
+        grid_rect: Rect,
+        clip: &MidiClip,
         clip_start_sample: u64,
         samples_per_beat: f64,
     ) -> PianoRollAction {
@@ -168,6 +206,10 @@ impl PianoRollPanel {
             return PianoRollAction::None;
         }
 
+        if let Some((start, end)) = self.marquee.take() {
+            self.select_in_rect(Rect::from_two_pos(start, end), grid_rect, clip);
+        }
+
         let mut action = PianoRollAction::None;
 
         if self.loop_select_drag.is_some() {
@@ -260,19 +302,24 @@ impl PianoRollPanel {
         });
     }
 
-    /// Show context menu for loop selection. Returns action if "Set Loop Region" clicked.
-    pub(super) fn handle_loop_context_menu(
+    /// Right-click menu: note commands, plus loop entries when there is a loop selection.
+    /// Returns (modified, action).
+    pub(super) fn handle_context_menu(
         &mut self,
         response: &egui::Response,
+        clip: &mut MidiClip,
+        bpm: f64,
         clip_start_sample: u64,
         samples_per_beat: f64,
-    ) -> PianoRollAction {
-        if self.loop_selection.is_none() {
-            return PianoRollAction::None;
-        }
-
+    ) -> (bool, PianoRollAction) {
+        let mut modified = false;
         let mut action = PianoRollAction::None;
         response.context_menu(|ui| {
+            modified |= self.note_tools_menu(ui, clip, bpm);
+            if self.loop_selection.is_none() {
+                return;
+            }
+            ui.separator();
             if ui.button("Set Loop Region").clicked() {
                 if let Some(ref selection) = self.loop_selection {
                     let start_sample = clip_start_sample + (selection.start_beat * samples_per_beat) as u64;
@@ -286,17 +333,18 @@ impl PianoRollPanel {
                 ui.close_menu();
             }
         });
-        action
+        (modified, action)
     }
 
-    /// Handle drag start — either note drag or loop selection.
-    pub(super) fn handle_drag_start(&mut self, response: &egui::Response, grid_rect: Rect, clip: &MidiClip, ctrl_held: bool) {
+    /// Handle drag start — note drag, loop selection (Ctrl) or rubber band selection
+    /// on empty space (Shift adds to the current selection).
+    pub(super) fn handle_drag_start(&mut self, response: &egui::Response, grid_rect: Rect, clip: &MidiClip, modifiers: egui::Modifiers) {
         let Some(pos) = response.interact_pointer_pos() else { return };
         if !grid_rect.contains(pos) { return };
 
         let (beat, pitch) = self.pos_to_beat_pitch(pos, grid_rect);
 
-        if ctrl_held {
+        if modifiers.ctrl || modifiers.command {
             let snapped_beat = (beat / 0.25).floor() * 0.25;
             self.loop_select_drag = Some(snapped_beat);
             self.loop_selection = Some(LoopSelection {
@@ -306,7 +354,23 @@ impl PianoRollPanel {
             return;
         }
 
-        let Some((note_idx, drag_mode)) = self.find_note_drag_target(clip, beat, pitch, grid_rect) else { return };
+        let Some((note_idx, drag_mode)) = self.find_note_drag_target(clip, beat, pitch, grid_rect) else {
+            if !modifiers.shift {
+                self.selected_notes.clear();
+            }
+            self.marquee = Some((pos, pos));
+            return;
+        };
+
+        // Moving a selected note carries the rest of the selection along
+        if !matches!(drag_mode, DragMode::Move) || !self.selected_notes.contains(&note_idx) {
+            self.selected_notes.clear();
+            self.selected_notes.insert(note_idx);
+        }
+        let group = self.selected_notes.iter()
+            .filter(|&&idx| idx != note_idx)
+            .filter_map(|&idx| clip.notes.get(idx).map(|n| (idx, n.start_tick, n.pitch)))
+            .collect();
 
         let note = &clip.notes[note_idx];
         self.note_drag = Some(NoteDragState {
@@ -317,9 +381,8 @@ impl PianoRollPanel {
             original_pitch: note.pitch,
             drag_start_beat: beat,
             drag_start_pitch: pitch,
+            group,
         });
-        self.selected_notes.clear();
-        self.selected_notes.insert(note_idx);
     }
 
     /// Handle drag continue — update note position or loop selection.
@@ -330,6 +393,11 @@ impl PianoRollPanel {
             return self.update_note_drag(pos, grid_rect, clip, &drag_state);
         }
 
+        if let Some((_, end)) = &mut self.marquee {
+            *end = pos;
+            return false;
+        }
+
         let Some(start_beat) = self.loop_select_drag else { return false };
         let (beat, _) = self.pos_to_beat_pitch(pos, grid_rect);
         let snapped_beat = (beat / 0.25).floor() * 0.25;
@@ -353,22 +421,36 @@ impl PianoRollPanel {
         let beat_delta = beat - drag_state.drag_start_beat;
         let pitch_delta = pitch as i32 - drag_state.drag_start_pitch as i32;
 
+        let ppq = clip.ppq as f64;
         let Some(note) = clip.notes.get_mut(drag_state.note_idx) else { return false };
 
-        let snap_factor = if self.snap_to_grid { self.grid_subdivision } else { 0.001 };
+        let snap_factor = if self.grid.snap { self.grid_subdivision } else { 0.001 };
 
         match drag_state.mode {
             DragMode::Move => {
-                let new_start_beat = (drag_state.original_start_tick as f64 / clip.ppq as f64) + beat_delta;
-                let snapped_beat = (new_start_beat / snap_factor).round() * snap_factor;
-                note.start_tick = (snapped_beat.max(0.0) * clip.ppq as f64) as u64;
+                let original_start_beat = drag_state.original_start_tick as f64 / ppq;
+                let snapped_beat = if self.grid.snap {
+                    self.grid.snap_drag(original_start_beat, beat_delta, self.grid_subdivision)
+                } else {
+                    original_start_beat + beat_delta
+                };
+                note.start_tick = (snapped_beat.max(0.0) * ppq) as u64;
                 note.pitch = (drag_state.original_pitch as i32 + pitch_delta).clamp(0, 127) as u8;
+
+                let tick_delta = note.start_tick as i64 - drag_state.original_start_tick as i64;
+                let pitch_delta = note.pitch as i32 - drag_state.original_pitch as i32;
+                for &(idx, start_tick, pitch) in &drag_state.group {
+                    if let Some(other) = clip.notes.get_mut(idx) {
+                        other.start_tick = (start_tick as i64 + tick_delta).max(0) as u64;
+                        other.pitch = (pitch as i32 + pitch_delta).clamp(0, 127) as u8;
+                    }
+                }
             }
             DragMode::ResizeEnd => {
-                let new_duration_beats = (drag_state.original_duration_ticks as f64 / clip.ppq as f64) + beat_delta;
+                let new_duration_beats = (drag_state.original_duration_ticks as f64 / ppq) + beat_delta;
                 let snapped_duration = (new_duration_beats / snap_factor).round() * snap_factor;
                 let min_duration = self.grid_subdivision;
-                note.duration_ticks = (snapped_duration.max(min_duration) * clip.ppq as f64) as u64;
+                note.duration_ticks = (snapped_duration.max(min_duration) * ppq) as u64;
             }
         }
         true
@@ -1,18 +1,34 @@
 //! Piano roll panel for MIDI editing
 
+mod control_lane;
 mod drawing;
 mod geometry;
 mod input;
+mod note_tools;
+mod scale;
+mod selection;
+mod step_input;
 mod types;
+mod velocity_lane;
 
 pub use types::PianoRollAction;
-use types::{LoopDragMode, LoopSelection, NoteDragState};
+use control_lane::{LANE_HEADER_HEIGHT, LANE_HEIGHT};
+use types::{LaneDrag, LaneTool, LoopDragMode, LoopSelection, NoteDragState};
 
 use std::collections::HashSet;
 
+use super::generate::GenerateSettings;
+use super::grid::GridSettings;
+use super::status_bar;
+use super::theme::Palette;
+use super::zoom::ZoomCommand;
 use crate::clipboard::DawClipboard;
-use egui::{Color32, Pos2, Rect, Sense, Ui, Vec2};
-use hallucinator_core::MidiClip;
+use egui::{Pos2, Rect, Sense, Ui, Vec2};
+use hallucinator_core::{Groove, MidiClip, MidiControl, ScaleMode};
+
+/// Horizontal zoom limits
+const MIN_PIXELS_PER_BEAT: f32 = 20.0;
+const MAX_PIXELS_PER_BEAT: f32 = 200.0;
 
 /// Piano roll editor panel
 pub struct PianoRollPanel {
@@ -26,13 +42,15 @@ pub struct PianoRollPanel {
     selected_notes: HashSet<usize>,
     /// Note drag state
     note_drag: Option<NoteDragState>,
+    /// Rubber band selection in progress: (drag start, pointer) in screen space
+    marquee: Option<(Pos2, Pos2)>,
     /// Lowest visible pitch
     visible_pitch_min: u8,
     /// Number of visible pitches
     visible_pitches: u8,
-    /// Snap to grid enabled
-    pub snap_to_grid: bool,
-    /// Grid subdivision (1.0 = quarter, 0.5 = 8th, 0.25 = 16th, etc.)
+    /// Grid note value, feel, snap and swing
+    pub grid: GridSettings,
+    /// Grid step in beats for this frame (1.0 = quarter, 0.5 = 8th, 0.25 = 16th, etc.)
     grid_subdivision: f64,
     /// Loop selection (beat range)
     loop_selection: Option<LoopSelection>,
@@ -46,6 +64,52 @@ pub struct PianoRollPanel {
     keyboard_octave: i8,
     /// Currently active MIDI pitches (for visual feedback on piano keys)
     active_pitches: HashSet<u8>,
+    /// Lane under the note grid shows note velocities instead of a controller
+    velocity_lane: bool,
+    /// Controller shown in the lane under the note grid
+    lane_control: MidiControl,
+    /// Drawing tool in the lane under the note grid
+    lane_tool: LaneTool,
+    /// Lane drag in progress
+    lane_drag: Option<LaneDrag>,
+    /// Velocity tool amounts: scale (%), compress toward the average (%), randomize (±)
+    velocity_scale: f32,
+    velocity_compress: f32,
+    velocity_random: u8,
+    /// Note command amounts: quantize strength (%) and whether lengths are quantized too,
+    /// humanize timing (± ms) and velocity (±), fixed length (beats)
+    quantize_strength: f32,
+    quantize_lengths: bool,
+    humanize_ms: f32,
+    humanize_velocity: u8,
+    fixed_length: f64,
+    /// Project groove pool, the groove picked from it, how strongly it is applied (%),
+    /// and whether snapping follows it; a groove removed from the menu waits here for the app
+    grooves: Vec<Groove>,
+    groove: Option<usize>,
+    groove_amount: f32,
+    groove_snap: bool,
+    groove_removed: Option<usize>,
+    /// Scale shaded on the grid and its root (0 = C)
+    scale: ScaleMode,
+    scale_root: u8,
+    /// Show only scale rows (plus pitches in use)
+    fold_to_scale: bool,
+    /// Pitches shown as rows while folded, ascending; rebuilt every frame
+    fold_rows: Option<Vec<u8>>,
+    /// Intervals the track's harmonizer adds to each note, drawn as chord tones
+    harmony: Vec<i8>,
+    /// Step input: played notes are written at the cursor (in ticks), which then advances
+    step_input: bool,
+    step_cursor: u64,
+    /// Played notes (pitch, velocity; 0 = release) waiting to be written by step input
+    step_events: Vec<(u8, u8)>,
+    /// Step input keys still down, and whether the cursor moves on once they are released
+    step_held: HashSet<u8>,
+    step_advance: bool,
+    /// Pitch the generator writes to
+    generate_pitch: u8,
+    generate: GenerateSettings,
 }
 
 impl Default for PianoRollPanel {
@@ -62,9 +126,10 @@ impl PianoRollPanel {
             scroll_x: 0.0,
             selected_notes: HashSet::new(),
             note_drag: None,
+            marquee: None,
             visible_pitch_min: 36, // C2
             visible_pitches: 48,   // 4 octaves
-            snap_to_grid: true,
+            grid: GridSettings::auto(),
             grid_subdivision: 0.25, // 16th notes default
             loop_selection: None,
             loop_drag: None,
@@ -72,9 +137,51 @@ impl PianoRollPanel {
             pressed_keys: HashSet::new(),
             keyboard_octave: 0,
             active_pitches: HashSet::new(),
+            velocity_lane: true,
+            lane_control: MidiControl::Cc(1),
+            lane_tool: LaneTool::Freehand,
+            lane_drag: None,
+            velocity_scale: 110.0,
+            velocity_compress: 50.0,
+            velocity_random: 10,
+            quantize_strength: 100.0,
+            quantize_lengths: false,
+            humanize_ms: 10.0,
+            humanize_velocity: 8,
+            fixed_length: 0.25,
+            grooves: Vec::new(),
+            groove: None,
+            groove_amount: 100.0,
+            groove_snap: false,
+            groove_removed: None,
+            scale: ScaleMode::Chromatic,
+            scale_root: 0,
+            fold_to_scale: false,
+            fold_rows: None,
+            harmony: Vec::new(),
+            step_input: false,
+            step_cursor: 0,
+            step_events: Vec::new(),
+            step_held: HashSet::new(),
+            step_advance: false,
+            generate_pitch: 60,
+            generate: GenerateSettings::default(),
         }
     }
 
+    /// Set the intervals the track's harmonizer adds, drawn as chord tones on each note
+    pub fn set_harmony(&mut self, intervals: Vec<i8>) {
+        self.harmony = intervals;
+    }
+
+    /// Set the project's groove pool, offered in the note commands
+    pub fn set_grooves(&mut self, grooves: Vec<Groove>) {
+        if self.groove.is_some_and(|idx| idx >= grooves.len()) {
+            self.groove = None;
+        }
+        self.grooves = grooves;
+    }
+
     /// Render the piano roll UI
     /// Returns action to be handled by app
     /// - clip_start_sample: where the clip starts in the timeline
@@ -87,36 +194,53 @@ impl PianoRollPanel {
         sample_rate: u32,
         clip_start_sample: u64,
         playback_position: u64,
-        _clipboard: &DawClipboard,
+        clipboard: &DawClipboard,
     ) -> PianoRollAction {
         let mut action = PianoRollAction::None;
         let mut modified = false;
 
-        // Calculate grid subdivision based on zoom level
-        self.grid_subdivision = if self.pixels_per_beat >= 160.0 {
-            0.125
-        } else if self.pixels_per_beat >= 80.0 {
-            0.25
-        } else if self.pixels_per_beat >= 40.0 {
-            0.5
+        // Grid step from the grid menu, or from the zoom level on Auto
+        self.grid_subdivision = self.grid.step(GridSettings::zoom_step(self.pixels_per_beat));
+
+        let samples_per_beat = sample_rate as f64 * 60.0 / bpm;
+        let clip_length_ticks = (clip.length_samples as f64 / samples_per_beat * clip.ppq as f64) as u64;
+        // Step input starts at the playhead when it is inside the clip, else the loop selection
+        let step_start_tick = if playback_position >= clip_start_sample
+            && playback_position < clip_start_sample + clip.length_samples
+        {
+            ((playback_position - clip_start_sample) as f64 / samples_per_beat * clip.ppq as f64) as u64
         } else {
-            1.0
+            self.loop_selection.as_ref().map_or(0, |sel| (sel.start_beat * clip.ppq as f64) as u64)
         };
 
+        let clip_length_beats = clip.length_samples as f64 / samples_per_beat;
+        // Zoom from the toolbar or a shortcut, applied once the grid width is known
+        let mut zoom = None;
+
         // Toolbar
         ui.horizontal(|ui| {
             ui.label("Piano Roll");
             ui.separator();
 
             if ui.button("-").clicked() {
-                self.pixels_per_beat = (self.pixels_per_beat * 0.8).max(20.0);
+                zoom = Some(ZoomCommand::ZoomOut);
             }
             if ui.button("+").clicked() {
-                self.pixels_per_beat = (self.pixels_per_beat * 1.25).min(200.0);
+                zoom = Some(ZoomCommand::ZoomIn);
             }
             ui.label(format!("{:.0} px/beat", self.pixels_per_beat));
+            ui.menu_button("Fit", |ui| {
+                for cmd in ZoomCommand::ALL {
+                    let available = cmd.step_factor().is_some() || self.zoom_range(cmd, clip, clip_length_beats).is_some();
+                    if cmd.menu_button(ui, available) {
+                        zoom = Some(cmd);
+                    }
+                }
+            });
+            ui.separator();
+            ui.menu_button(format!("Grid: {}", self.grid.label()), |ui| self.grid.menu_ui(ui));
             ui.separator();
-            ui.checkbox(&mut self.snap_to_grid, "Snap");
+            self.scale_toolbar_ui(ui);
             ui.separator();
 
             if ui.button("Oct-").clicked() {
@@ -133,6 +257,8 @@ impl PianoRollPanel {
                 self.active_pitches.clear();
             }
             ui.separator();
+            self.step_input_toolbar_ui(ui, clip.ppq, step_start_tick);
+            ui.separator();
 
             if ui.button("Delete Selected").clicked() {
                 if let Some(a) = self.delete_selected_notes(clip) {
@@ -141,6 +267,32 @@ impl PianoRollPanel {
                 }
             }
 
+            ui.menu_button("Notes...", |ui| {
+                modified |= self.note_tools_menu(ui, clip, bpm);
+            });
+            if let Some(idx) = self.groove_removed.take() {
+                action = PianoRollAction::RemoveGroove(idx);
+            }
+
+            ui.separator();
+            ui.menu_button("Generate...", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Pitch");
+                    ui.add(egui::DragValue::new(&mut self.generate_pitch).range(0..=127));
+                });
+                if self.generate.ui(ui) {
+                    let (start, end) = match self.loop_selection {
+                        Some(ref sel) => (sel.start_beat, sel.end_beat),
+                        None => (0.0, clip.length_samples as f64 / samples_per_beat),
+                    };
+                    self.generate.fill(clip, self.generate_pitch, start, end, self.grid_subdivision);
+                    // Note indices shift when notes are replaced
+                    self.selected_notes.clear();
+                    modified = true;
+                    ui.close_menu();
+                }
+            });
+
             if let Some(ref sel) = self.loop_selection {
                 ui.separator();
                 ui.label(format!("Loop: {:.1}-{:.1} bars", sel.start_beat / 4.0, sel.end_beat / 4.0));
@@ -151,9 +303,12 @@ impl PianoRollPanel {
         });
 
         ui.separator();
+        self.update_fold_rows(clip);
 
-        // Layout
+        // Layout: note grid on top, velocity/controller lane underneath
         let available = ui.available_rect_before_wrap();
+        let notes_height = (available.height() - LANE_HEADER_HEIGHT - LANE_HEIGHT).max(self.key_height * 4.0);
+        let available = Rect::from_min_size(available.min, Vec2::new(available.width(), notes_height));
         let piano_width = 40.0;
         let grid_rect = Rect::from_min_size(
             Pos2::new(available.left() + piano_width, available.top()),
@@ -170,31 +325,50 @@ impl PianoRollPanel {
         if response.clicked() || response.drag_started() || response.hovered() {
             ui.memory_mut(|mem| mem.request_focus(piano_roll_id));
         }
+        if response.hovered() {
+            status_bar::set_hint(
+                ui.ctx(),
+                "Click to add or remove a note · drag a note to move it, its end to resize · drag empty space to select \
+                 (Shift adds, Ctrl sets the loop) · right-click for more",
+            );
+        }
         let has_focus = ui.memory(|mem| mem.has_focus(piano_roll_id));
 
+        // Zoom shortcuts go to the view under the pointer
+        if ui.rect_contains_pointer(response.rect) {
+            zoom = zoom.or_else(|| ZoomCommand::consume(ui));
+        }
+        if let Some(cmd) = zoom {
+            self.apply_zoom(cmd, clip, grid_rect.width(), clip_length_beats);
+            self.grid_subdivision = self.grid.step(GridSettings::zoom_step(self.pixels_per_beat));
+        }
+
         // Background
-        painter.rect_filled(grid_rect, 0.0, Color32::from_gray(25));
-        painter.rect_filled(piano_rect, 0.0, Color32::from_gray(40));
+        let palette = Palette::get(ui.ctx());
+        painter.rect_filled(grid_rect, 0.0, palette.panel);
+        painter.rect_filled(piano_rect, 0.0, palette.key_black);
 
         // Drawing
         let beats_visible = grid_rect.width() as f64 / self.pixels_per_beat as f64;
         self.draw_piano_keys(&painter, piano_rect);
+        self.draw_scale_rows(&painter, grid_rect);
         self.draw_grid(&painter, grid_rect, beats_visible);
+        self.draw_chord_tones(&painter, grid_rect, clip);
         self.draw_notes(&painter, grid_rect, clip);
+        self.draw_step_cursor(&painter, grid_rect, clip.ppq);
 
-        let samples_per_beat = sample_rate as f64 * 60.0 / bpm;
         self.draw_playhead(&painter, grid_rect, clip_start_sample, clip.length_samples, playback_position, samples_per_beat);
 
         // Input handling
         if response.drag_started() {
-            let ctrl_held = ui.input(|i| i.modifiers.ctrl || i.modifiers.command);
-            self.handle_drag_start(&response, grid_rect, clip, ctrl_held);
+            let modifiers = ui.input(|i| i.modifiers);
+            self.handle_drag_start(&response, grid_rect, clip, modifiers);
         }
         if response.dragged() {
             modified |= self.handle_drag_continue(&response, grid_rect, clip);
         }
 
-        let drag_end_action = self.handle_drag_end(&response, clip_start_sample, samples_per_beat);
+        let drag_end_action = self.handle_drag_end(&response, grid_rect, clip, clip_start_sample, samples_per_beat);
         if !matches!(drag_end_action, PianoRollAction::None) {
             action = drag_end_action;
         }
@@ -218,21 +392,40 @@ impl PianoRollPanel {
             if !matches!(kb_action, PianoRollAction::None) {
                 action = kb_action;
             }
+
+            let playhead_beat = (playback_position >= clip_start_sample
+                && playback_position < clip_start_sample + clip.length_samples)
+                .then(|| (playback_position - clip_start_sample) as f64 / samples_per_beat);
+            let paste_beat = self.paste_beat(response.hover_pos(), grid_rect, playhead_beat);
+            let (clip_modified, clip_action) = self.handle_clipboard_keys(ui, clip, clipboard, paste_beat);
+            modified |= clip_modified;
+            if !matches!(clip_action, PianoRollAction::None) {
+                action = clip_action;
+            }
+
+            self.handle_step_keys(ui, clip.ppq, clip_length_ticks);
+            modified |= self.handle_nudge_keys(ui, clip);
         }
+        modified |= self.apply_step_events(clip, clip_length_ticks);
 
         // Loop handling
         self.handle_loop_drag(&response, grid_rect);
         self.handle_loop_right_click(&response, grid_rect);
 
-        let ctx_action = self.handle_loop_context_menu(&response, clip_start_sample, samples_per_beat);
+        let (ctx_modified, ctx_action) = self.handle_context_menu(&response, clip, bpm, clip_start_sample, samples_per_beat);
+        modified |= ctx_modified;
         if !matches!(ctx_action, PianoRollAction::None) {
             action = ctx_action;
         }
 
         self.draw_loop_selection(&painter, grid_rect);
+        self.draw_marquee(&painter, grid_rect);
         self.handle_scroll_zoom(ui, &response);
 
-        if modified {
+        modified |= self.draw_control_lane(ui, clip, piano_width, clip_length_beats);
+
+        // A cut edits the clip and still has to reach the clipboard
+        if modified && !matches!(action, PianoRollAction::CopyNotes { .. }) {
             return PianoRollAction::ClipModified;
         }
         action
@@ -0,0 +1,168 @@
+//! Note commands on the selection: quantize, humanize, groove, legato and fixed length
+
+use egui::{DragValue, Ui};
+use hallucinator_core::MidiClip;
+
+use super::PianoRollPanel;
+
+/// Move `value` toward the nearest multiple of `grid` by `strength` (0..=1)
+fn snap_toward(value: u64, grid: u64, strength: f64) -> u64 {
+    let nearest = ((value as f64 / grid as f64).round() as u64) * grid;
+    pull_toward(value, nearest, strength)
+}
+
+fn pull_toward(value: u64, target: u64, strength: f64) -> u64 {
+    (value as f64 + (target as f64 - value as f64) * strength).round() as u64
+}
+
+impl PianoRollPanel {
+    /// Notes edit commands apply to: the selection, or every note when nothing is selected
+    pub(super) fn edit_targets(&self, clip: &MidiClip) -> Vec<usize> {
+        if self.selected_notes.is_empty() {
+            return (0..clip.notes.len()).collect();
+        }
+        self.selected_notes.iter().copied().filter(|&i| i < clip.notes.len()).collect()
+    }
+
+    /// Pull starts (and optionally lengths) toward the grid by the quantize strength;
+    /// starts follow the grid swing and the snap groove
+    fn quantize(&self, clip: &mut MidiClip) {
+        let ppq = clip.ppq as f64;
+        let grid = ((self.grid_subdivision * ppq) as u64).max(1);
+        let strength = self.quantize_strength as f64 / 100.0;
+        for i in self.edit_targets(clip) {
+            let note = &mut clip.notes[i];
+            let target = self.groove_shift(self.grid.snap_round(note.start_tick as f64 / ppq, self.grid_subdivision));
+            note.start_tick = pull_toward(note.start_tick, (target * ppq).round() as u64, strength);
+            if self.quantize_lengths {
+                note.duration_ticks = snap_toward(note.duration_ticks, grid, strength).max(grid);
+            }
+        }
+    }
+
+    /// Randomly offset starts by up to the humanize time and velocities by up to the humanize amount
+    fn humanize(&self, clip: &mut MidiClip, bpm: f64) {
+        let max_ticks = (self.humanize_ms as f64 / 60_000.0 * bpm * clip.ppq as f64) as i64;
+        let max_velocity = self.humanize_velocity as i32;
+        for i in self.edit_targets(clip) {
+            let note = &mut clip.notes[i];
+            note.start_tick = (note.start_tick as i64 + fastrand::i64(-max_ticks..=max_ticks)).max(0) as u64;
+            note.velocity = (note.velocity as i32 + fastrand::i32(-max_velocity..=max_velocity)).clamp(1, 127) as u8;
+        }
+    }
+
+    /// Groove section of the note commands: pick from the pool, apply, snap to it or remove it;
+    /// returns true if the clip was edited
+    fn groove_ui(&mut self, ui: &mut Ui, clip: &mut MidiClip) -> bool {
+        let mut modified = false;
+        ui.horizontal(|ui| {
+            let picked = self.groove.and_then(|idx| self.grooves.get(idx));
+            ui.menu_button(picked.map_or("Groove: none", |g| g.name.as_str()), |ui| {
+                if self.grooves.is_empty() {
+                    ui.label("Right-click a MIDI or audio clip → Extract Groove");
+                }
+                for (idx, groove) in self.grooves.iter().enumerate() {
+                    let label = format!("{} ({} beats)", groove.name, groove.length_beats());
+                    if ui.selectable_label(self.groove == Some(idx), label).clicked() {
+                        self.groove = Some(idx);
+                        ui.close_menu();
+                    }
+                }
+            });
+            ui.add(DragValue::new(&mut self.groove_amount).range(0.0..=100.0).suffix("%"));
+            let picked = self.groove.and_then(|idx| self.grooves.get(idx));
+            let apply = ui.add_enabled(picked.is_some(), egui::Button::new("Apply Groove"))
+                .on_hover_text("Pull starts onto the grooved 16ths and add its accents, by this much");
+            if apply.clicked()
+                && let Some(groove) = picked
+            {
+                let targets = self.edit_targets(clip);
+                groove.apply(clip, &targets, self.groove_amount / 100.0);
+                modified = true;
+                ui.close_menu();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add_enabled(self.groove.is_some(), egui::Checkbox::new(&mut self.groove_snap, "Snap to groove"))
+                .on_hover_text("Grid snapping and Quantize follow the groove's timing");
+            if ui.add_enabled(self.groove.is_some(), egui::Button::new("Remove from pool")).clicked() {
+                self.groove_removed = self.groove.take();
+                ui.close_menu();
+            }
+        });
+        modified
+    }
+
+    /// Extend each note up to the next later start among the targets; the last ones keep their length
+    fn legato(&self, clip: &mut MidiClip) {
+        let targets = self.edit_targets(clip);
+        let mut starts: Vec<u64> = targets.iter().map(|&i| clip.notes[i].start_tick).collect();
+        starts.sort_unstable();
+        starts.dedup();
+        for i in targets {
+            let note = &mut clip.notes[i];
+            let next = starts.partition_point(|&s| s <= note.start_tick);
+            if let Some(&next_start) = starts.get(next) {
+                note.duration_ticks = next_start - note.start_tick;
+            }
+        }
+    }
+
+    fn set_fixed_length(&self, clip: &mut MidiClip) {
+        let length = ((self.fixed_length * clip.ppq as f64) as u64).max(1);
+        for i in self.edit_targets(clip) {
+            clip.notes[i].duration_ticks = length;
+        }
+    }
+
+    /// Quantize, humanize, legato and fixed-length commands, shared by the toolbar
+    /// menu and the grid's right-click menu; returns true if the clip was edited
+    pub(super) fn note_tools_menu(&mut self, ui: &mut Ui, clip: &mut MidiClip, bpm: f64) -> bool {
+        let mut modified = false;
+        ui.label(if self.selected_notes.is_empty() { "All notes" } else { "Selected notes" });
+
+        ui.horizontal(|ui| {
+            ui.add(DragValue::new(&mut self.quantize_strength).range(0.0..=100.0).suffix("%"));
+            ui.checkbox(&mut self.quantize_lengths, "Lengths");
+            if ui.button("Quantize").on_hover_text("Move starts toward the grid by this much").clicked() {
+                self.quantize(clip);
+                modified = true;
+                ui.close_menu();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(DragValue::new(&mut self.humanize_ms).range(0.0..=100.0).prefix("±").suffix(" ms"));
+            ui.add(DragValue::new(&mut self.humanize_velocity).range(0..=64).prefix("vel ±"));
+            if ui.button("Humanize").clicked() {
+                self.humanize(clip, bpm);
+                modified = true;
+                ui.close_menu();
+            }
+        });
+
+        modified |= self.groove_ui(ui, clip);
+
+        ui.horizontal(|ui| {
+            ui.add(
+                DragValue::new(&mut self.fixed_length)
+                    .speed(0.0625)
+                    .range(1.0 / 32.0..=16.0)
+                    .suffix(" beats"),
+            );
+            if ui.button("Fixed Length").clicked() {
+                self.set_fixed_length(clip);
+                modified = true;
+                ui.close_menu();
+            }
+        });
+
+        if ui.button("Legato").on_hover_text("Extend notes to the start of the next note").clicked() {
+            self.legato(clip);
+            modified = true;
+            ui.close_menu();
+        }
+
+        modified
+    }
+}
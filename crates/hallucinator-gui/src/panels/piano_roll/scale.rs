@@ -0,0 +1,122 @@
+//! Scale highlighting, folding the grid to the scale, and harmonizer chord tones
+
+use egui::{Color32, ComboBox, Pos2, Rect, Stroke, StrokeKind, Ui};
+use hallucinator_core::{MidiClip, MidiNote, ScaleMode};
+
+use super::PianoRollPanel;
+use crate::panels::theme::{shade, Palette};
+
+const SCALES: [ScaleMode; 12] = [
+    ScaleMode::Chromatic, ScaleMode::Major, ScaleMode::Minor,
+    ScaleMode::Dorian, ScaleMode::Phrygian, ScaleMode::Lydian,
+    ScaleMode::Mixolydian, ScaleMode::Locrian, ScaleMode::HarmonicMinor,
+    ScaleMode::MelodicMinor, ScaleMode::Pentatonic, ScaleMode::Blues,
+];
+
+const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+/// Chord tone names by semitones above the played note
+const INTERVAL_NAMES: [&str; 12] = ["R", "b2", "2", "b3", "3", "4", "b5", "5", "b6", "6", "b7", "7"];
+
+fn interval_label(interval: i8) -> String {
+    let name = INTERVAL_NAMES[interval.rem_euclid(12) as usize];
+    if interval < 0 { format!("{}↓", name) } else { name.to_string() }
+}
+
+impl PianoRollPanel {
+    /// Scale, root and fold controls for the toolbar
+    pub(super) fn scale_toolbar_ui(&mut self, ui: &mut Ui) {
+        ComboBox::from_id_salt("piano_roll_scale")
+            .selected_text(self.scale.name())
+            .width(90.0)
+            .show_ui(ui, |ui| {
+                for mode in SCALES {
+                    ui.selectable_value(&mut self.scale, mode, mode.name());
+                }
+            });
+        if self.scale == ScaleMode::Chromatic {
+            return;
+        }
+        ComboBox::from_id_salt("piano_roll_scale_root")
+            .selected_text(NOTE_NAMES[self.scale_root as usize % 12])
+            .width(40.0)
+            .show_ui(ui, |ui| {
+                for (i, name) in NOTE_NAMES.iter().enumerate() {
+                    ui.selectable_value(&mut self.scale_root, i as u8, *name);
+                }
+            });
+        ui.checkbox(&mut self.fold_to_scale, "Fold")
+            .on_hover_text("Show only scale notes (and pitches the clip already uses)");
+    }
+
+    pub(super) fn in_scale(&self, pitch: u8) -> bool {
+        let relative = (pitch + 12 - self.scale_root % 12) % 12;
+        self.scale.intervals().contains(&relative)
+    }
+
+    /// Rows of the folded grid: scale pitches plus any pitch the clip uses, ascending
+    pub(super) fn update_fold_rows(&mut self, clip: &MidiClip) {
+        if !self.fold_to_scale || self.scale == ScaleMode::Chromatic {
+            self.fold_rows = None;
+            return;
+        }
+        let mut used = [false; 128];
+        for note in &clip.notes {
+            used[note.pitch.min(127) as usize] = true;
+        }
+        self.fold_rows = Some((0..128u8).filter(|&p| used[p as usize] || self.in_scale(p)).collect());
+    }
+
+    /// Darken rows outside the scale and lift the root rows
+    pub(super) fn draw_scale_rows(&self, painter: &egui::Painter, rect: Rect) {
+        if self.scale == ScaleMode::Chromatic {
+            return;
+        }
+        let background = Palette::get(painter.ctx()).panel;
+        for pitch in self.visible_rows() {
+            let color = if !self.in_scale(pitch) {
+                shade(background, -0.3)
+            } else if pitch % 12 == self.scale_root % 12 {
+                background.lerp_to_gamma(Color32::GRAY, 0.15)
+            } else {
+                continue;
+            };
+            let y = self.pitch_to_y(pitch, rect);
+            let row = Rect::from_min_max(Pos2::new(rect.left(), y), Pos2::new(rect.right(), y + self.key_height));
+            painter.rect_filled(row.intersect(rect), 0.0, color);
+        }
+    }
+
+    /// Outline the notes the track's harmonizer adds to each note, labelled by chord tone
+    pub(super) fn draw_chord_tones(&self, painter: &egui::Painter, rect: Rect, clip: &MidiClip) {
+        if self.harmony.is_empty() {
+            return;
+        }
+        let ppq = clip.ppq as f64;
+        let color = Color32::from_rgb(220, 170, 90);
+        for note in &clip.notes {
+            for &interval in &self.harmony {
+                let pitch = note.pitch as i32 + interval as i32;
+                if interval == 0 || !(0..=127).contains(&pitch) {
+                    continue;
+                }
+                let tone = MidiNote { pitch: pitch as u8, ..*note };
+                let Some(tone_rect) = self.note_screen_rect(&tone, ppq, rect) else { continue };
+                let visible = tone_rect.intersect(rect);
+                if visible.width() <= 0.0 {
+                    continue;
+                }
+                painter.rect_stroke(visible, 2.0, Stroke::new(1.0, color), StrokeKind::Inside);
+                if visible.width() > 14.0 {
+                    painter.text(
+                        Pos2::new(visible.left() + 2.0, visible.center().y),
+                        egui::Align2::LEFT_CENTER,
+                        interval_label(interval),
+                        egui::FontId::proportional(9.0),
+                        color,
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Multi-note selection: rubber band, copy/cut/paste through the DAW clipboard
+//! and nudging with the arrow keys
+
+use egui::{Key, Modifiers, Pos2, Rect, Ui};
+use hallucinator_core::{MidiClip, MidiNote};
+
+use super::types::PianoRollAction;
+use super::PianoRollPanel;
+use crate::clipboard::{ClipboardContent, DawClipboard};
+
+impl PianoRollPanel {
+    /// Add every note touching `band` (screen space) to the selection
+    pub(super) fn select_in_rect(&mut self, band: Rect, grid_rect: Rect, clip: &MidiClip) {
+        let ppq = clip.ppq as f64;
+        for (idx, note) in clip.notes.iter().enumerate() {
+            if let Some(note_rect) = self.note_screen_rect(note, ppq, grid_rect)
+                && note_rect.intersects(band)
+            {
+                self.selected_notes.insert(idx);
+            }
+        }
+    }
+
+    /// Selected notes as a clipboard action, shifted so the earliest starts at tick 0
+    fn copy_selection(&self, clip: &MidiClip) -> Option<PianoRollAction> {
+        let mut indices: Vec<usize> = self.selected_notes.iter().copied().filter(|&i| i < clip.notes.len()).collect();
+        indices.sort_unstable();
+        let mut notes: Vec<MidiNote> = indices.iter().map(|&i| clip.notes[i]).collect();
+        let first = notes.iter().map(|n| n.start_tick).min()?;
+        for note in &mut notes {
+            note.start_tick -= first;
+        }
+        Some(PianoRollAction::CopyNotes { ppq: clip.ppq, notes })
+    }
+
+    /// Insert clipboard notes starting at `beat`; the pasted notes become the selection
+    fn paste_notes(&mut self, clip: &mut MidiClip, ppq: u16, notes: &[MidiNote], beat: f64) {
+        let scale = clip.ppq as f64 / ppq.max(1) as f64;
+        let offset = (beat.max(0.0) * clip.ppq as f64) as u64;
+        let pasted = notes.iter().map(|n| MidiNote {
+            start_tick: offset + (n.start_tick as f64 * scale) as u64,
+            duration_ticks: ((n.duration_ticks as f64 * scale) as u64).max(1),
+            ..*n
+        });
+
+        // Merge in start order, remembering which notes are new
+        let mut merged: Vec<(MidiNote, bool)> = clip.notes.drain(..).map(|n| (n, false)).collect();
+        merged.extend(pasted.map(|n| (n, true)));
+        merged.sort_by_key(|(n, _)| n.start_tick);
+
+        self.selected_notes.clear();
+        for (idx, (note, new)) in merged.into_iter().enumerate() {
+            if new {
+                self.selected_notes.insert(idx);
+            }
+            clip.notes.push(note);
+        }
+    }
+
+    /// Where a paste lands: the grid position under the mouse, else the playhead
+    /// if it is inside the clip, else the clip start
+    pub(super) fn paste_beat(&self, hover: Option<Pos2>, grid_rect: Rect, playhead_beat: Option<f64>) -> f64 {
+        let Some(pos) = hover.filter(|p| grid_rect.contains(*p)) else {
+            return playhead_beat.unwrap_or(0.0);
+        };
+        let (beat, _) = self.pos_to_beat_pitch(pos, grid_rect);
+        self.snap_beat_floor(beat)
+    }
+
+    /// Ctrl+C / Ctrl+X / Ctrl+V. Returns (modified, action).
+    pub(super) fn handle_clipboard_keys(
+        &mut self,
+        ui: &Ui,
+        clip: &mut MidiClip,
+        clipboard: &DawClipboard,
+        paste_beat: f64,
+    ) -> (bool, PianoRollAction) {
+        let (copy, cut, paste) = ui.input(|i| {
+            let ctrl = i.modifiers.ctrl || i.modifiers.command;
+            (
+                (ctrl && i.key_pressed(Key::C)) || i.events.iter().any(|e| matches!(e, egui::Event::Copy)),
+                (ctrl && i.key_pressed(Key::X)) || i.events.iter().any(|e| matches!(e, egui::Event::Cut)),
+                (ctrl && i.key_pressed(Key::V)) || i.events.iter().any(|e| matches!(e, egui::Event::Paste(_))),
+            )
+        });
+
+        if copy || cut {
+            let Some(action) = self.copy_selection(clip) else { return (false, PianoRollAction::None) };
+            if cut {
+                self.delete_selected_notes(clip);
+            }
+            return (cut, action);
+        }
+
+        if paste
+            && let Some(ClipboardContent::MidiNotes { ppq, notes }) = clipboard.content()
+            && !notes.is_empty()
+        {
+            self.paste_notes(clip, *ppq, notes, paste_beat);
+            return (true, PianoRollAction::None);
+        }
+        (false, PianoRollAction::None)
+    }
+
+    /// Arrow keys move the selection: Left/Right by one grid step, Up/Down by a
+    /// semitone, Shift+Up/Down by an octave. Returns true if notes moved.
+    pub(super) fn handle_nudge_keys(&mut self, ui: &Ui, clip: &mut MidiClip) -> bool {
+        if self.selected_notes.is_empty() {
+            return false;
+        }
+
+        let step = ((self.grid_subdivision * clip.ppq as f64) as i64).max(1);
+        let (ticks, semitones) = ui.input_mut(|i| {
+            let mut ticks = 0;
+            let mut semitones = 0;
+            // Shifted keys first: a plain pattern also matches with Shift held
+            if i.consume_key(Modifiers::SHIFT, Key::ArrowUp) {
+                semitones += 12;
+            }
+            if i.consume_key(Modifiers::SHIFT, Key::ArrowDown) {
+                semitones -= 12;
+            }
+            if i.consume_key(Modifiers::NONE, Key::ArrowUp) {
+                semitones += 1;
+            }
+            if i.consume_key(Modifiers::NONE, Key::ArrowDown) {
+                semitones -= 1;
+            }
+            if i.consume_key(Modifiers::NONE, Key::ArrowLeft) {
+                ticks -= step;
+            }
+            if i.consume_key(Modifiers::NONE, Key::ArrowRight) {
+                ticks += step;
+            }
+            (ticks, semitones)
+        });
+        if ticks == 0 && semitones == 0 {
+            return false;
+        }
+
+        let targets: Vec<usize> = self.selected_notes.iter().copied().filter(|&i| i < clip.notes.len()).collect();
+        let Some(earliest) = targets.iter().map(|&i| clip.notes[i].start_tick).min() else { return false };
+        let lowest = targets.iter().map(|&i| clip.notes[i].pitch).min().unwrap_or(0);
+        let highest = targets.iter().map(|&i| clip.notes[i].pitch).max().unwrap_or(127);
+
+        // The selection moves as a block, stopping at the clip start and the keyboard ends
+        let ticks = ticks.max(-(earliest as i64));
+        let semitones = if (lowest as i32) + semitones < 0 || highest as i32 + semitones > 127 {
+            0
+        } else {
+            semitones
+        };
+        if ticks == 0 && semitones == 0 {
+            return false;
+        }
+
+        for i in targets {
+            let note = &mut clip.notes[i];
+            note.start_tick = (note.start_tick as i64 + ticks) as u64;
+            note.pitch = (note.pitch as i32 + semitones) as u8;
+        }
+        true
+    }
+}
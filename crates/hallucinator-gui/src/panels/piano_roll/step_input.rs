@@ -0,0 +1,114 @@
+//! Step input: notes played on the QWERTY keys or a MIDI keyboard are written at
+//! the edit cursor, one grid step long, and the cursor moves on by a step
+
+use egui::{Color32, Key, Modifiers, Pos2, Rect, Stroke, Ui};
+use hallucinator_core::{MidiClip, MidiNote};
+
+use super::PianoRollPanel;
+
+impl PianoRollPanel {
+    pub fn step_input_active(&self) -> bool {
+        self.step_input
+    }
+
+    /// Queue a played note for step input (velocity 0 is a release)
+    pub fn queue_step_note(&mut self, pitch: u8, velocity: u8) {
+        if self.step_input {
+            self.step_events.push((pitch, velocity));
+        }
+    }
+
+    fn step_ticks(&self, ppq: u16) -> u64 {
+        ((self.grid_subdivision * ppq as f64) as u64).max(1)
+    }
+
+    /// Toolbar toggle; switching on puts the cursor at `start_tick`
+    pub(super) fn step_input_toolbar_ui(&mut self, ui: &mut Ui, ppq: u16, start_tick: u64) {
+        let toggle = ui
+            .selectable_label(self.step_input, "Step")
+            .on_hover_text("Step input: played notes are written at the cursor, which then advances one grid step.\nHold keys together for chords; Left/Right move the cursor (Right enters a rest).");
+        if toggle.clicked() {
+            self.step_input = !self.step_input;
+            self.step_cursor = start_tick;
+            self.step_events.clear();
+            self.step_held.clear();
+            self.step_advance = false;
+        }
+        if self.step_input {
+            let beat = self.step_cursor as f64 / ppq as f64;
+            ui.label(format!("@ {}.{:.2}", (beat / 4.0).floor() as u32 + 1, beat % 4.0 + 1.0));
+        }
+    }
+
+    /// Left/Right move the cursor by a grid step while step input is on
+    pub(super) fn handle_step_keys(&mut self, ui: &Ui, ppq: u16, length_ticks: u64) {
+        if !self.step_input {
+            return;
+        }
+        let step = self.step_ticks(ppq);
+        let (back, forward) = ui.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::NONE, Key::ArrowLeft),
+                i.consume_key(Modifiers::NONE, Key::ArrowRight),
+            )
+        });
+        if back {
+            self.step_cursor = self.step_cursor.saturating_sub(step);
+        }
+        if forward {
+            self.advance_step_cursor(ppq, length_ticks);
+        }
+    }
+
+    /// Move on one grid step, wrapping to the clip start at its end
+    fn advance_step_cursor(&mut self, ppq: u16, length_ticks: u64) {
+        self.step_cursor += self.step_ticks(ppq);
+        if self.step_cursor >= length_ticks {
+            self.step_cursor = 0;
+        }
+    }
+
+    /// Write queued notes at the cursor. Notes held together land on the same
+    /// step; the cursor advances once they are all released. Returns true if the clip changed.
+    pub(super) fn apply_step_events(&mut self, clip: &mut MidiClip, length_ticks: u64) -> bool {
+        let mut modified = false;
+        let step = self.step_ticks(clip.ppq);
+        for (pitch, velocity) in std::mem::take(&mut self.step_events) {
+            if velocity > 0 {
+                let cursor = self.step_cursor;
+                // Re-entering a step replaces that pitch rather than stacking a duplicate
+                clip.notes.retain(|n| n.pitch != pitch || n.start_tick != cursor);
+                clip.add_note(MidiNote::new(pitch, velocity, cursor, step));
+                self.step_held.insert(pitch);
+                self.step_advance = true;
+                modified = true;
+            } else {
+                self.step_held.remove(&pitch);
+                if self.step_held.is_empty() && self.step_advance {
+                    self.step_advance = false;
+                    self.advance_step_cursor(clip.ppq, length_ticks);
+                }
+            }
+        }
+        if modified {
+            // Note indices shift as notes are inserted
+            self.selected_notes.clear();
+        }
+        modified
+    }
+
+    pub(super) fn draw_step_cursor(&self, painter: &egui::Painter, grid_rect: Rect, ppq: u16) {
+        if !self.step_input {
+            return;
+        }
+        let beat = self.step_cursor as f64 / ppq as f64;
+        let x = grid_rect.left() + ((beat - self.scroll_x) * self.pixels_per_beat as f64) as f32;
+        if x < grid_rect.left() || x > grid_rect.right() {
+            return;
+        }
+        let width = (self.grid_subdivision * self.pixels_per_beat as f64) as f32;
+        let column = Rect::from_min_max(Pos2::new(x, grid_rect.top()), Pos2::new((x + width).min(grid_rect.right()), grid_rect.bottom()));
+        painter.rect_filled(column, 0.0, Color32::from_rgba_unmultiplied(120, 220, 120, 20));
+        painter.vline(x, grid_rect.y_range(), Stroke::new(2.0, Color32::from_rgb(120, 220, 120)));
+    }
+}
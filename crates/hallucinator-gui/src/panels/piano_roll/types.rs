@@ -1,3 +1,5 @@
+use hallucinator_core::MidiNote;
+
 /// Actions returned from piano roll
 #[derive(Clone, Debug)]
 pub enum PianoRollAction {
@@ -28,6 +30,13 @@ pub enum PianoRollAction {
         start_tick: u64,
         duration_ticks: u64,
     },
+    /// Remove a groove from the project's groove pool
+    RemoveGroove(usize),
+    /// Put notes on the DAW clipboard (ticks relative to the earliest note)
+    CopyNotes {
+        ppq: u16,
+        notes: Vec<MidiNote>,
+    },
 }
 
 /// State for dragging a note
@@ -47,6 +56,8 @@ pub(super) struct NoteDragState {
     pub original_pitch: u8,
     pub drag_start_beat: f64,
     pub drag_start_pitch: u8,
+    /// Other selected notes moving with this one: (index, start tick, pitch) at drag start
+    pub group: Vec<(usize, u64, u8)>,
 }
 
 /// Loop selection state
@@ -63,3 +74,19 @@ pub(super) enum LoopDragMode {
     End,
     Move,
 }
+
+/// Drawing tool in the lane under the note grid
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum LaneTool {
+    /// Follow the pointer
+    Freehand,
+    /// Straight ramp from where the drag started
+    Line,
+}
+
+/// Lane drag state: where it started and where the pointer was last frame
+#[derive(Clone, Copy)]
+pub(super) struct LaneDrag {
+    pub start: (u64, u16),
+    pub last: (u64, u16),
+}
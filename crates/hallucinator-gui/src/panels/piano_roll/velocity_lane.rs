@@ -0,0 +1,140 @@
+//! Velocity editing in the lane under the note grid
+
+use egui::{DragValue, Pos2, Rect, Stroke, Ui};
+use hallucinator_core::MidiClip;
+
+use super::types::{LaneDrag, LaneTool};
+use super::PianoRollPanel;
+use crate::panels::theme::{shade, Palette};
+
+/// Bars this close to the pointer's path (in pixels) are painted
+const PAINT_REACH: f32 = 4.0;
+
+/// Velocity at `tick` on a line between two (tick, velocity) points
+fn interpolate(from: (u64, u16), to: (u64, u16), tick: u64) -> u8 {
+    let ((t0, v0), (t1, v1)) = if from.0 <= to.0 { (from, to) } else { (to, from) };
+    let value = if t1 == t0 {
+        v1 as f64
+    } else {
+        v0 as f64 + (v1 as f64 - v0 as f64) * (tick.clamp(t0, t1) - t0) as f64 / (t1 - t0) as f64
+    };
+    value.round().clamp(1.0, 127.0) as u8
+}
+
+impl PianoRollPanel {
+    /// Scale, compress and randomize menu; returns true if the clip was edited
+    pub(super) fn velocity_tools_ui(&mut self, ui: &mut Ui, clip: &mut MidiClip) -> bool {
+        let mut modified = false;
+
+        ui.menu_button("Velocity...", |ui| {
+            ui.label(if self.selected_notes.is_empty() { "All notes" } else { "Selected notes" });
+            let targets = self.edit_targets(clip);
+
+            ui.horizontal(|ui| {
+                ui.add(DragValue::new(&mut self.velocity_scale).range(10.0..=200.0).suffix("%"));
+                if ui.button("Scale").clicked() {
+                    for &i in &targets {
+                        let note = &mut clip.notes[i];
+                        note.velocity = (note.velocity as f32 * self.velocity_scale / 100.0).round().clamp(1.0, 127.0) as u8;
+                    }
+                    modified = true;
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.add(DragValue::new(&mut self.velocity_compress).range(0.0..=100.0).suffix("%"));
+                if ui.button("Compress").on_hover_text("Pull velocities toward their average").clicked() && !targets.is_empty() {
+                    let mean = targets.iter().map(|&i| clip.notes[i].velocity as f32).sum::<f32>() / targets.len() as f32;
+                    let keep = 1.0 - self.velocity_compress / 100.0;
+                    for &i in &targets {
+                        let note = &mut clip.notes[i];
+                        note.velocity = (mean + (note.velocity as f32 - mean) * keep).round().clamp(1.0, 127.0) as u8;
+                    }
+                    modified = true;
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.add(DragValue::new(&mut self.velocity_random).range(0..=64).prefix("±"));
+                if ui.button("Randomize").clicked() {
+                    let range = self.velocity_random as i32;
+                    for &i in &targets {
+                        let note = &mut clip.notes[i];
+                        note.velocity = (note.velocity as i32 + fastrand::i32(-range..=range)).clamp(1, 127) as u8;
+                    }
+                    modified = true;
+                }
+            });
+        });
+
+        modified
+    }
+
+    /// One bar per note, from the bottom of the lane up to its velocity
+    pub(super) fn draw_velocity_bars(&self, painter: &egui::Painter, rect: Rect, clip: &MidiClip) {
+        let ppq = clip.ppq as f64;
+        let note_color = Palette::get(painter.ctx()).note;
+        for (idx, note) in clip.notes.iter().enumerate() {
+            let x = self.lane_x(note.start_tick, ppq, rect);
+            if x < rect.left() || x > rect.right() {
+                continue;
+            }
+            let top = self.lane_y(note.velocity as u16, rect);
+            let color = if self.selected_notes.contains(&idx) { shade(note_color, 0.2) } else { note_color };
+            painter.line_segment([Pos2::new(x, rect.bottom()), Pos2::new(x, top)], Stroke::new(2.0, color));
+            painter.circle_filled(Pos2::new(x, top), 3.0, color);
+        }
+    }
+
+    /// Paint velocities with the pointer, or ramp them along a line; returns true if the clip changed
+    pub(super) fn handle_velocity_input(&mut self, response: &egui::Response, rect: Rect, clip: &mut MidiClip) -> bool {
+        let ppq = clip.ppq as f64;
+
+        if !response.is_pointer_button_down_on() {
+            // Release applies a line as a crescendo or decrescendo
+            let Some(drag) = self.lane_drag.take() else { return false };
+            if self.lane_tool != LaneTool::Line {
+                return false;
+            }
+            let (t0, t1) = (drag.start.0.min(drag.last.0), drag.start.0.max(drag.last.0));
+            for i in self.edit_targets(clip) {
+                let note = &mut clip.notes[i];
+                if (t0..=t1).contains(&note.start_tick) {
+                    note.velocity = interpolate(drag.start, drag.last, note.start_tick);
+                }
+            }
+            return true;
+        }
+
+        let Some(pos) = response.interact_pointer_pos() else { return false };
+        let beat = (self.scroll_x + (pos.x - rect.left()) as f64 / self.pixels_per_beat as f64).max(0.0);
+        let level = ((rect.bottom() - pos.y) / rect.height()).clamp(0.0, 1.0);
+        let point = ((beat * ppq) as u64, (1.0 + level * 126.0).round() as u16);
+
+        let last = match &mut self.lane_drag {
+            Some(drag) if drag.last == point => return false,
+            Some(drag) => std::mem::replace(&mut drag.last, point),
+            None => {
+                self.lane_drag = Some(LaneDrag { start: point, last: point });
+                point
+            }
+        };
+        if self.lane_tool != LaneTool::Freehand {
+            return false;
+        }
+
+        // Paint every bar the pointer passed since the last frame
+        let (x0, x1) = (self.lane_x(last.0, ppq, rect), self.lane_x(point.0, ppq, rect));
+        let (left, right) = (x0.min(x1) - PAINT_REACH, x0.max(x1) + PAINT_REACH);
+        let mut modified = false;
+        for i in self.edit_targets(clip) {
+            let note = &mut clip.notes[i];
+            let x = self.lane_x(note.start_tick, ppq, rect);
+            if x >= left && x <= right {
+                note.velocity = interpolate(last, point, note.start_tick);
+                modified = true;
+            }
+        }
+        modified
+    }
+}
@@ -1,18 +1,61 @@
 //! Plugin browser panel for VST3 plugins
 
+use std::collections::BTreeSet;
 use std::path::PathBuf;
 
+use std::time::Duration;
+
 use egui::{Color32, ScrollArea, Ui};
-use hallucinator_services::{Vst3PluginInfo, Vst3Scanner};
+use hallucinator_services::{default_vst3_dirs, ScanEvent, ScanJob, Vst3PluginInfo, Vst3Scanner, NATIVE_EFFECTS, PROJECT_EXTENSION};
 use tracing::info;
 
+use super::grid::GridSettings;
+use super::layout::{Layout, Workspace};
+
 /// Action returned from plugin panel
 pub enum PluginAction {
     None,
+    /// Open the project file picked
+    OpenProject(PathBuf),
+    /// Save the project to the file picked
+    SaveProject(PathBuf),
+    /// Open the last autosave
+    RecoverAutosave,
     LoadPlugin(Vst3PluginInfo),
     CreateMidiTrack(Vst3PluginInfo),
     AddAudioTrack,
     AddMidiTrack,
+    /// Append a native effect (by `NATIVE_EFFECTS` id) to the master chain
+    AddMasterEffect(&'static str),
+    /// Open the parameter window of the master effect at this index
+    OpenMasterEffect(usize),
+    /// Star or unstar a plugin (by unique ID)
+    ToggleFavorite(String),
+    /// Keep the current layout for its workspace and switch to another
+    SwitchWorkspace(Workspace),
+    /// Put the current workspace back to its preset layout
+    ResetWorkspace,
+}
+
+/// Recently used plugins kept, newest first
+const RECENT_LIMIT: usize = 8;
+/// Recently used plugins listed above the full list
+const RECENT_SHOWN: usize = 5;
+
+/// Plugin list tab of the Plugins menu
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PluginTab {
+    Instruments,
+    Effects,
+}
+
+impl PluginTab {
+    fn includes(self, plugin: &Vst3PluginInfo) -> bool {
+        match self {
+            Self::Instruments => plugin.is_instrument(),
+            Self::Effects => plugin.is_effect(),
+        }
+    }
 }
 
 /// Plugin browser panel
@@ -22,10 +65,27 @@ pub struct PluginBrowserPanel {
     scan_error: Option<String>,
     custom_path: String,
     use_custom_path: bool,
+    /// Scan results cache, so launches only load new or changed bundles
+    cache_path: Option<PathBuf>,
+    /// Background scan in progress
+    scan_job: Option<ScanJob>,
+    /// Bundle being loaded by the running scan: (index, total, name)
+    scan_progress: Option<(usize, usize, String)>,
+    /// Load new instruments in a separate host process (crash isolation, no native editor)
+    sandbox_instruments: bool,
+    tab: PluginTab,
+    search: String,
+    vendor_filter: Option<String>,
+    category_filter: Option<String>,
+    favorites_only: bool,
+    /// Starred plugins, by unique ID
+    favorites: BTreeSet<String>,
+    /// Recently loaded plugins, by unique ID, newest first
+    recent: Vec<String>,
 }
 
 impl PluginBrowserPanel {
-    pub fn new() -> Self {
+    pub fn new(cache_path: Option<PathBuf>) -> Self {
         let default_path = dirs::home_dir()
             .map(|h| h.join(".vst3"))
             .unwrap_or_else(|| PathBuf::from("~/.vst3"));
@@ -36,39 +96,119 @@ impl PluginBrowserPanel {
             scan_error: None,
             custom_path: default_path.display().to_string(),
             use_custom_path: false,
+            cache_path,
+            scan_job: None,
+            scan_progress: None,
+            sandbox_instruments: false,
+            tab: PluginTab::Instruments,
+            search: String::new(),
+            vendor_filter: None,
+            category_filter: None,
+            favorites_only: false,
+            favorites: BTreeSet::new(),
+            recent: Vec::new(),
         };
 
-        // Auto-scan on init
-        panel.scan();
+        // Auto-scan on init (in the background, from the cache where possible)
+        panel.scan(false);
         panel
     }
 
-    /// Initialize scanner and scan for plugins
-    pub fn scan(&mut self) {
-        match Vst3Scanner::new() {
-            Ok(mut scanner) => {
-                let result = if self.use_custom_path {
-                    scanner.scan_path(&PathBuf::from(&self.custom_path))
-                } else {
-                    scanner.scan()
-                };
+    /// Start a background scan; `rescan` ignores the cache and reloads every bundle
+    pub fn scan(&mut self, rescan: bool) {
+        if self.scan_job.is_some() {
+            return;
+        }
+        if self.scanner.is_none() {
+            match Vst3Scanner::new() {
+                Ok(scanner) => self.scanner = Some(scanner),
+                Err(e) => {
+                    self.scan_error = Some(format!("{}", e));
+                    return;
+                }
+            }
+        }
 
-                match result {
-                    Ok(plugins) => {
-                        self.plugins = plugins.to_vec();
-                        self.scan_error = None;
+        let dirs = if self.use_custom_path {
+            vec![PathBuf::from(&self.custom_path)]
+        } else {
+            default_vst3_dirs()
+        };
+        match Vst3Scanner::spawn_scan(dirs, self.cache_path.clone(), rescan) {
+            Ok(job) => self.scan_job = Some(job),
+            Err(e) => self.scan_error = Some(format!("{}", e)),
+        }
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        self.scan_job.is_some()
+    }
+
+    /// Pick up progress and results of a running scan
+    fn poll_scan(&mut self, ctx: &egui::Context) {
+        let Some(job) = &mut self.scan_job else { return };
+        for event in job.poll() {
+            match event {
+                ScanEvent::Progress { done, total, bundle } => {
+                    self.scan_progress = Some((done, total, bundle));
+                }
+                ScanEvent::Finished(result) => {
+                    self.scan_job = None;
+                    self.scan_progress = None;
+                    match result {
+                        Ok(mut plugins) => {
+                            plugins.sort_by_key(|p| p.name.to_lowercase());
+                            self.plugins = plugins;
+                            self.scan_error = None;
+                        }
+                        Err(e) => {
+                            self.scan_error = Some(format!("{}", e));
+                            self.plugins.clear();
+                        }
                     }
-                    Err(e) => {
-                        self.scan_error = Some(format!("{}", e));
-                        self.plugins.clear();
+                    if let Some(scanner) = &mut self.scanner {
+                        scanner.set_plugins(self.plugins.clone());
                     }
+                    return;
                 }
-                self.scanner = Some(scanner);
-            }
-            Err(e) => {
-                self.scan_error = Some(format!("{}", e));
             }
         }
+        ctx.request_repaint_after(Duration::from_millis(100));
+    }
+
+    /// Whether new instruments should run in a sandbox host process
+    pub fn sandbox_instruments(&self) -> bool {
+        self.sandbox_instruments
+    }
+
+    /// Restore favorites and recently used plugins (unique IDs, e.g. from the config)
+    pub fn set_library(&mut self, favorites: Vec<String>, recent: Vec<String>) {
+        self.favorites = favorites.into_iter().collect();
+        self.recent = recent;
+        self.recent.truncate(RECENT_LIMIT);
+    }
+
+    /// Starred plugins, by unique ID (for config persistence)
+    pub fn favorites(&self) -> Vec<String> {
+        self.favorites.iter().cloned().collect()
+    }
+
+    /// Recently loaded plugins, by unique ID, newest first (for config persistence)
+    pub fn recent(&self) -> &[String] {
+        &self.recent
+    }
+
+    pub fn toggle_favorite(&mut self, unique_id: &str) {
+        if !self.favorites.remove(unique_id) {
+            self.favorites.insert(unique_id.to_string());
+        }
+    }
+
+    /// Move a plugin to the front of the recently used list
+    pub fn note_used(&mut self, unique_id: &str) {
+        self.recent.retain(|id| id != unique_id);
+        self.recent.insert(0, unique_id.to_string());
+        self.recent.truncate(RECENT_LIMIT);
     }
 
     /// Get the scanner for loading plugins
@@ -77,11 +217,40 @@ impl PluginBrowserPanel {
     }
 
     /// Render as a menu bar
-    pub fn menu_ui(&mut self, ui: &mut Ui, snap_to_grid: &mut bool) -> PluginAction {
+    pub fn menu_ui(
+        &mut self,
+        ui: &mut Ui,
+        arrange_grid: &mut GridSettings,
+        layout: &mut Layout,
+        workspace: Workspace,
+        show_preferences: &mut bool,
+        show_keymap_editor: &mut bool,
+        show_notifications: &mut bool,
+        master_effects: &[String],
+    ) -> PluginAction {
         let mut action = PluginAction::None;
+        self.poll_scan(ui.ctx());
 
         egui::menu::bar(ui, |ui| {
             ui.menu_button("File", |ui| {
+                let dialog = || rfd::FileDialog::new().add_filter("Project", &[PROJECT_EXTENSION]);
+                if ui.button("Open Project...").clicked() {
+                    if let Some(path) = dialog().pick_file() {
+                        action = PluginAction::OpenProject(path);
+                    }
+                    ui.close_menu();
+                }
+                if ui.button("Save Project As...").clicked() {
+                    if let Some(path) = dialog().set_file_name(format!("Untitled.{PROJECT_EXTENSION}")).save_file() {
+                        action = PluginAction::SaveProject(path);
+                    }
+                    ui.close_menu();
+                }
+                if ui.button("Recover Autosave").clicked() {
+                    action = PluginAction::RecoverAutosave;
+                    ui.close_menu();
+                }
+                ui.separator();
                 if ui.button("Exit").clicked() {
                     std::process::exit(0);
                 }
@@ -98,8 +267,62 @@ impl PluginBrowserPanel {
                 }
             });
 
-            ui.menu_button("Grid", |ui| {
-                ui.checkbox(snap_to_grid, "Snap to Grid");
+            ui.menu_button("Master", |ui| {
+                if master_effects.is_empty() {
+                    ui.label("No master effects");
+                }
+                for (idx, name) in master_effects.iter().enumerate() {
+                    if ui.button(format!("🎛 {}", name)).clicked() {
+                        action = PluginAction::OpenMasterEffect(idx);
+                        ui.close_menu();
+                    }
+                }
+
+                ui.separator();
+                ui.menu_button("Add Effect", |ui| {
+                    for &(id, name) in NATIVE_EFFECTS {
+                        if ui.button(name).clicked() {
+                            action = PluginAction::AddMasterEffect(id);
+                            ui.close_menu();
+                        }
+                    }
+                });
+            });
+
+            ui.menu_button("Grid", |ui| arrange_grid.menu_ui(ui));
+
+            ui.menu_button("View", |ui| {
+                ui.menu_button("Workspace", |ui| {
+                    for candidate in Workspace::ALL {
+                        if ui.radio(workspace == candidate, candidate.label()).clicked() {
+                            if workspace != candidate {
+                                action = PluginAction::SwitchWorkspace(candidate);
+                            }
+                            ui.close_menu();
+                        }
+                    }
+                    ui.separator();
+                    if ui.button("Reset Layout")
+                        .on_hover_text(format!("Put the {} workspace back to how it starts out", workspace.label()))
+                        .clicked()
+                    {
+                        action = PluginAction::ResetWorkspace;
+                        ui.close_menu();
+                    }
+                });
+                ui.separator();
+                ui.checkbox(&mut layout.show_sequencer, "Sequencer");
+                ui.checkbox(&mut layout.show_analyzer, "Analyzer");
+                ui.checkbox(&mut layout.show_modulation, "Modulation");
+                ui.checkbox(&mut layout.show_automation, "Automation");
+                ui.checkbox(&mut layout.show_control_surface, "Control Surface");
+                ui.checkbox(&mut layout.show_script_console, "Script Console");
+                ui.checkbox(&mut layout.show_snapshots, "Snapshots");
+                ui.checkbox(&mut layout.show_chord_track, "Chord Track");
+                ui.separator();
+                ui.checkbox(show_preferences, "Preferences");
+                ui.checkbox(show_keymap_editor, "Keyboard Shortcuts");
+                ui.checkbox(show_notifications, "Notifications");
             });
 
             ui.menu_button("Plugins", |ui| {
@@ -116,8 +339,26 @@ impl PluginBrowserPanel {
 
                 ui.separator();
 
-                if ui.button("Scan for Plugins").clicked() {
-                    self.scan();
+                let idle = !self.is_scanning();
+                if ui.add_enabled(idle, egui::Button::new("Scan for Plugins"))
+                    .on_hover_text("Load new and changed plugins; unchanged ones come from the cache")
+                    .clicked()
+                {
+                    self.scan(false);
+                }
+                if ui.add_enabled(idle, egui::Button::new("Rescan"))
+                    .on_hover_text("Ignore the cache and load every plugin again")
+                    .clicked()
+                {
+                    self.scan(true);
+                }
+
+                if cfg!(target_os = "linux") {
+                    ui.checkbox(&mut self.sandbox_instruments, "Run instruments in sandbox")
+                        .on_hover_text(
+                            "Load new instruments in a separate process, so a crashing plugin is \
+                             restarted instead of taking the session down (no native editor)",
+                        );
                 }
 
                 ui.separator();
@@ -127,35 +368,13 @@ impl PluginBrowserPanel {
                     ui.separator();
                 }
 
-                if self.plugins.is_empty() {
+                if self.plugins.is_empty() && self.is_scanning() {
+                    ui.label("Scanning for plugins…");
+                } else if self.plugins.is_empty() {
                     ui.label("No plugins found");
                     ui.label("Default paths: /usr/lib/vst3/, ~/.vst3/");
                 } else {
-                    ui.label(format!("{} plugins available:", self.plugins.len()));
-                    ui.separator();
-
-                    ui.label("Effects (add to master chain):");
-                    ScrollArea::vertical().id_salt("effects_scroll").max_height(150.0).show(ui, |ui| {
-                        for plugin in &self.plugins {
-                            if ui.button(format!("🎛 {}", plugin.name)).clicked() {
-                                info!("Plugin clicked as effect: {}", plugin.name);
-                                action = PluginAction::LoadPlugin(plugin.clone());
-                                ui.close_menu();
-                            }
-                        }
-                    });
-
-                    ui.separator();
-                    ui.label("Instruments (create MIDI track):");
-                    ScrollArea::vertical().id_salt("instruments_scroll").max_height(150.0).show(ui, |ui| {
-                        for plugin in &self.plugins {
-                            if ui.button(format!("🎹 {}", plugin.name)).clicked() {
-                                info!("Plugin clicked as instrument: {}", plugin.name);
-                                action = PluginAction::CreateMidiTrack(plugin.clone());
-                                ui.close_menu();
-                            }
-                        }
-                    });
+                    action = self.plugin_list_ui(ui);
                 }
             });
 
@@ -164,14 +383,160 @@ impl PluginBrowserPanel {
                     ui.close_menu();
                 }
             });
+
+            if self.is_scanning() {
+                ui.separator();
+                ui.spinner();
+                match &self.scan_progress {
+                    Some((done, total, bundle)) => {
+                        ui.weak(format!("Scanning {} ({}/{})", bundle, done + 1, total))
+                    }
+                    None => ui.weak("Scanning plugins…"),
+                };
+            }
         });
 
         action
     }
 }
 
+impl PluginBrowserPanel {
+    /// Searchable, filterable plugin list of the Plugins menu
+    fn plugin_list_ui(&mut self, ui: &mut Ui) -> PluginAction {
+        let mut action = PluginAction::None;
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.tab, PluginTab::Instruments, "🎹 Instruments");
+            ui.selectable_value(&mut self.tab, PluginTab::Effects, "🎛 Effects");
+        });
+        ui.horizontal(|ui| {
+            ui.label("🔍");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.search)
+                    .hint_text("Name, vendor or category")
+                    .desired_width(220.0),
+            );
+        });
+
+        // Filter choices come from the plugins in the current tab
+        let tab = self.tab;
+        let mut vendors = BTreeSet::new();
+        let mut categories = BTreeSet::new();
+        for plugin in self.plugins.iter().filter(|p| tab.includes(p)) {
+            if !plugin.vendor.is_empty() {
+                vendors.insert(plugin.vendor.clone());
+            }
+            categories.extend(plugin.category().map(str::to_string));
+        }
+
+        ui.horizontal(|ui| {
+            filter_combo(ui, "plugin_vendor_filter", "All vendors", &mut self.vendor_filter, &vendors);
+            filter_combo(ui, "plugin_category_filter", "All categories", &mut self.category_filter, &categories);
+            ui.toggle_value(&mut self.favorites_only, "★ Favorites");
+        });
+        ui.weak(match tab {
+            PluginTab::Instruments => "Click to create a MIDI track",
+            PluginTab::Effects => "Click to add to the master chain",
+        });
+        ui.separator();
+
+        let query = self.search.to_lowercase();
+        let browsing = query.is_empty() && !self.favorites_only;
+        let in_tab: Vec<&Vst3PluginInfo> = self.plugins.iter().filter(|p| tab.includes(p)).collect();
+        let total = in_tab.len();
+
+        if browsing {
+            let recent: Vec<&Vst3PluginInfo> = self
+                .recent
+                .iter()
+                .filter_map(|id| in_tab.iter().find(|p| p.info.unique_id == *id).copied())
+                .take(RECENT_SHOWN)
+                .collect();
+            if !recent.is_empty() {
+                ui.weak("Recently used");
+                for plugin in recent {
+                    let favorite = self.favorites.contains(&plugin.info.unique_id);
+                    if let Some(a) = plugin_row(ui, plugin, favorite, tab) {
+                        action = a;
+                    }
+                }
+                ui.separator();
+            }
+        }
+
+        let visible: Vec<&Vst3PluginInfo> = in_tab
+            .into_iter()
+            .filter(|p| p.matches(&query))
+            .filter(|p| self.vendor_filter.as_ref().is_none_or(|v| p.vendor == *v))
+            .filter(|p| self.category_filter.as_deref().is_none_or(|c| p.category() == Some(c)))
+            .filter(|p| !self.favorites_only || self.favorites.contains(&p.info.unique_id))
+            .collect();
+        ui.weak(format!("{} of {} plugins", visible.len(), total));
+
+        ScrollArea::vertical().id_salt("plugin_list_scroll").max_height(300.0).show(ui, |ui| {
+            for plugin in visible {
+                let favorite = self.favorites.contains(&plugin.info.unique_id);
+                if let Some(a) = plugin_row(ui, plugin, favorite, tab) {
+                    action = a;
+                }
+            }
+        });
+
+        action
+    }
+}
+
+/// A vendor or category filter; `None` shows everything
+fn filter_combo(ui: &mut Ui, id: &str, all_label: &str, selected: &mut Option<String>, options: &BTreeSet<String>) {
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(selected.as_deref().unwrap_or(all_label))
+        .width(120.0)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(selected, None, all_label);
+            for option in options {
+                ui.selectable_value(selected, Some(option.clone()), option);
+            }
+        });
+}
+
+/// Star toggle, plugin button and a vendor/category/layout line
+fn plugin_row(ui: &mut Ui, plugin: &Vst3PluginInfo, favorite: bool, tab: PluginTab) -> Option<PluginAction> {
+    let mut action = None;
+    ui.horizontal(|ui| {
+        let star = if favorite { "★" } else { "☆" };
+        let hover = if favorite { "Remove from favorites" } else { "Add to favorites" };
+        if ui.small_button(star).on_hover_text(hover).clicked() {
+            action = Some(PluginAction::ToggleFavorite(plugin.info.unique_id.clone()));
+        }
+
+        let icon = match tab {
+            PluginTab::Instruments => "🎹",
+            PluginTab::Effects => "🎛",
+        };
+        if ui.button(format!("{} {}", icon, plugin.name)).clicked() {
+            info!(plugin = %plugin.name, "Plugin chosen from the Plugins menu");
+            action = Some(match tab {
+                PluginTab::Instruments => PluginAction::CreateMidiTrack(plugin.clone()),
+                PluginTab::Effects => PluginAction::LoadPlugin(plugin.clone()),
+            });
+            ui.close_menu();
+        }
+
+        let io = plugin.io().map(|io| io.to_string());
+        let details: Vec<&str> = [Some(plugin.vendor.as_str()), plugin.category(), io.as_deref()]
+            .into_iter()
+            .flatten()
+            .filter(|s| !s.is_empty())
+            .collect();
+        if !details.is_empty() {
+            ui.weak(details.join(" · "));
+        }
+    });
+    action
+}
+
 impl Default for PluginBrowserPanel {
     fn default() -> Self {
-        Self::new()
+        Self::new(None)
     }
 }
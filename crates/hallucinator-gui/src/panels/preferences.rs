@@ -0,0 +1,237 @@
+//! Preferences window - audio output, MIDI inputs, project folders and autosave, and the theme with its colours
+
+use egui::Ui;
+use hallucinator_services::{AudioOutputService, CueSettings, MidiInputService, MidiPort, OutputSettings};
+
+use super::audio_settings::{AudioSettingsAction, AudioSettingsPanel};
+use super::theme::{Accents, Theme};
+
+/// Settings edited in the preferences window
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Preferences {
+    pub output: OutputSettings,
+    /// MIDI inputs left closed, by port name
+    pub midi_inputs_off: Vec<String>,
+    /// Folder autosaves are written to (empty = the default)
+    pub projects_folder: String,
+    /// Folder takes recorded to disk are written to (empty = the default)
+    pub recordings_folder: String,
+    /// Minutes between autosaves (0 = off)
+    pub autosave_minutes: u32,
+    pub theme: Theme,
+    pub accents: Accents,
+}
+
+/// Action returned from the preferences window
+pub enum PreferencesAction {
+    None,
+    /// Cue bus or metronome change from the Audio tab
+    Audio(AudioSettingsAction),
+    /// Changed preferences, applied straight away
+    Update(Preferences),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PreferencesTab {
+    Audio,
+    Midi,
+    Project,
+    Interface,
+}
+
+pub struct PreferencesPanel {
+    tab: PreferencesTab,
+    audio_settings: AudioSettingsPanel,
+    /// Output devices offered for the main output, listed when the tab is first shown
+    devices: Option<Vec<String>>,
+    /// MIDI inputs, listed when the tab is first shown
+    midi_ports: Option<Vec<MidiPort>>,
+}
+
+impl PreferencesPanel {
+    pub fn new() -> Self {
+        Self { tab: PreferencesTab::Audio, audio_settings: AudioSettingsPanel::new(), devices: None, midi_ports: None }
+    }
+
+    /// `cue`, `metronome_volume` and `output_channels` are passed on to the Audio tab's cue bus settings
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        preferences: &Preferences,
+        cue: &CueSettings,
+        metronome_volume: f32,
+        output_channels: u16,
+    ) -> PreferencesAction {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.tab, PreferencesTab::Audio, "Audio");
+            ui.selectable_value(&mut self.tab, PreferencesTab::Midi, "MIDI");
+            ui.selectable_value(&mut self.tab, PreferencesTab::Project, "Project");
+            ui.selectable_value(&mut self.tab, PreferencesTab::Interface, "Interface");
+        });
+        ui.separator();
+
+        let mut edited = preferences.clone();
+        match self.tab {
+            PreferencesTab::Audio => {
+                self.output_ui(ui, &mut edited.output);
+                ui.separator();
+                let action = self.audio_settings.ui(ui, cue, metronome_volume, output_channels);
+                if !matches!(action, AudioSettingsAction::None) {
+                    return PreferencesAction::Audio(action);
+                }
+            }
+            PreferencesTab::Midi => self.midi_ui(ui, &mut edited.midi_inputs_off),
+            PreferencesTab::Project => project_ui(ui, &mut edited),
+            PreferencesTab::Interface => interface_ui(ui, &mut edited),
+        }
+
+        if edited == *preferences { PreferencesAction::None } else { PreferencesAction::Update(edited) }
+    }
+
+    fn output_ui(&mut self, ui: &mut Ui, output: &mut OutputSettings) {
+        let devices = self.devices.get_or_insert_with(|| {
+            AudioOutputService::list_devices()
+                .inspect_err(|e| tracing::warn!("Failed to list output devices: {}", e))
+                .unwrap_or_default()
+        });
+
+        let mut rescan = false;
+        ui.heading("Output");
+        egui::Grid::new("preferences_output").num_columns(2).show(ui, |ui| {
+            ui.label("Device");
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("output_device")
+                    .selected_text(output.device.as_deref().unwrap_or("System default"))
+                    .width(220.0)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut output.device, None, "System default");
+                        for name in devices.iter() {
+                            ui.selectable_value(&mut output.device, Some(name.clone()), name);
+                        }
+                    });
+                rescan = ui.small_button("⟳").on_hover_text("List output devices again").clicked();
+            });
+            ui.end_row();
+
+            ui.label("Buffer size");
+            let label = |size: Option<u32>| size.map_or("Device default".to_string(), |frames| format!("{frames} samples"));
+            egui::ComboBox::from_id_salt("output_buffer_size")
+                .selected_text(label(output.buffer_size))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut output.buffer_size, None, label(None));
+                    for frames in OutputSettings::BUFFER_SIZES {
+                        ui.selectable_value(&mut output.buffer_size, Some(frames), label(Some(frames)));
+                    }
+                })
+                .response
+                .on_hover_text("Smaller buffers lower the latency but need more CPU headroom");
+            ui.end_row();
+        });
+        if rescan {
+            self.devices = None;
+        }
+    }
+
+    fn midi_ui(&mut self, ui: &mut Ui, inputs_off: &mut Vec<String>) {
+        let ports = self.midi_ports.get_or_insert_with(|| {
+            MidiInputService::list_ports()
+                .inspect_err(|e| tracing::warn!("Failed to list MIDI inputs: {}", e))
+                .unwrap_or_default()
+        });
+
+        let mut rescan = false;
+        ui.horizontal(|ui| {
+            ui.heading("MIDI inputs");
+            rescan = ui.small_button("⟳").on_hover_text("List MIDI inputs again").clicked();
+        });
+        if ports.is_empty() {
+            ui.weak("No MIDI inputs found");
+        }
+        for port in ports.iter() {
+            let mut enabled = !inputs_off.contains(&port.name);
+            if ui.checkbox(&mut enabled, &port.name).on_hover_text(&port.id).changed() {
+                if enabled {
+                    inputs_off.retain(|name| *name != port.name);
+                } else {
+                    inputs_off.push(port.name.clone());
+                }
+            }
+        }
+        if rescan {
+            self.midi_ports = None;
+        }
+    }
+}
+
+impl Default for PreferencesPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn project_ui(ui: &mut Ui, preferences: &mut Preferences) {
+    ui.heading("Folders");
+    egui::Grid::new("preferences_folders").num_columns(2).show(ui, |ui| {
+        ui.label("Projects");
+        folder_ui(ui, &mut preferences.projects_folder);
+        ui.end_row();
+        ui.label("Recordings");
+        folder_ui(ui, &mut preferences.recordings_folder);
+        ui.end_row();
+    });
+
+    ui.separator();
+    ui.heading("Autosave");
+    ui.horizontal(|ui| {
+        ui.label("Every");
+        ui.add(egui::DragValue::new(&mut preferences.autosave_minutes).range(0..=120).suffix(" min"))
+            .on_hover_text("0 turns autosave off");
+        if preferences.autosave_minutes == 0 {
+            ui.weak("(off)");
+        }
+    });
+    ui.weak("The project is saved to autosave.json in the projects folder");
+}
+
+fn interface_ui(ui: &mut Ui, preferences: &mut Preferences) {
+    ui.horizontal(|ui| {
+        ui.label("Theme");
+        egui::ComboBox::from_id_salt("preferences_theme")
+            .selected_text(preferences.theme.label())
+            .show_ui(ui, |ui| {
+                for theme in Theme::ALL {
+                    ui.selectable_value(&mut preferences.theme, theme, theme.label());
+                }
+            });
+    });
+
+    ui.separator();
+    ui.heading("Colours");
+    egui::Grid::new("preferences_accents").num_columns(2).show(ui, |ui| {
+        for (label, color) in preferences.accents.entries_mut() {
+            ui.label(label);
+            egui::color_picker::color_edit_button_srgb(ui, color);
+            ui.end_row();
+        }
+    });
+    let is_default = preferences.accents == Accents::default();
+    if ui.add_enabled(!is_default, egui::Button::new("Reset colours")).clicked() {
+        preferences.accents = Accents::default();
+    }
+    ui.weak("Colours are used by the arrange view, piano roll and sequencer in both themes");
+}
+
+/// A folder with Browse and Default buttons (empty = the default folder)
+fn folder_ui(ui: &mut Ui, folder: &mut String) {
+    ui.horizontal(|ui| {
+        ui.label(if folder.is_empty() { "Default" } else { folder.as_str() });
+        if ui.small_button("Browse…").clicked()
+            && let Some(path) = rfd::FileDialog::new().pick_folder()
+        {
+            *folder = path.display().to_string();
+        }
+        if ui.add_enabled(!folder.is_empty(), egui::Button::new("Default").small()).clicked() {
+            folder.clear();
+        }
+    });
+}
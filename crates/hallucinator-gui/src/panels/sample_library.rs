@@ -0,0 +1,309 @@
+//! Sample library database: user tags, star ratings and saved searches for files in
+//! the browser's Places, kept in SQLite next to a full-text (trigram) index of every
+//! sample file under the Places. Searches in a small query syntax (`SampleQuery`) run
+//! against that index, so they don't walk the folders.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
+
+/// Highest star rating
+pub const MAX_RATING: u8 = 5;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS ratings (path TEXT PRIMARY KEY, rating INTEGER NOT NULL);
+    CREATE TABLE IF NOT EXISTS tags (path TEXT NOT NULL, tag TEXT NOT NULL, PRIMARY KEY (path, tag));
+    CREATE TABLE IF NOT EXISTS saved_searches (name TEXT PRIMARY KEY, query TEXT NOT NULL);
+    CREATE VIRTUAL TABLE IF NOT EXISTS files USING fts5(path, place UNINDEXED, tokenize = 'trigram');
+";
+
+/// Tags and rating of one sample file
+#[derive(Clone, Debug, Default)]
+pub struct SampleEntry {
+    pub tags: Vec<String>,
+    /// Stars, 0 (unrated) to `MAX_RATING`; rated samples are favorites
+    pub rating: u8,
+}
+
+impl SampleEntry {
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.rating == 0
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+}
+
+#[derive(Default)]
+pub struct SampleLibrary {
+    /// Open database (None = nothing is kept or indexed)
+    db: Option<Connection>,
+    /// Entries keyed by the sample's full path, read at load and written through to the
+    /// database, so rows can look up their stars and tags every frame
+    samples: BTreeMap<String, SampleEntry>,
+    /// Saved searches, oldest first
+    pub saved_searches: Vec<SavedSearch>,
+    /// Bumped on every write, so cached search results know when they're stale
+    revision: u64,
+}
+
+impl SampleLibrary {
+    /// Open the library database at `path`, creating it if needed; when it can't be opened
+    /// the library is empty and edits aren't kept
+    pub fn load(path: PathBuf) -> Self {
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        match Self::open(&path) {
+            Ok(library) => library,
+            Err(e) => {
+                tracing::warn!("Sample library {} unavailable: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    fn open(path: &Path) -> rusqlite::Result<Self> {
+        let db = Connection::open(path)?;
+        db.execute_batch(SCHEMA)?;
+
+        let mut samples: BTreeMap<String, SampleEntry> = BTreeMap::new();
+        let mut ratings = db.prepare("SELECT path, rating FROM ratings")?;
+        for row in ratings.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, u8>(1)?)))? {
+            let (path, rating) = row?;
+            samples.entry(path).or_default().rating = rating.min(MAX_RATING);
+        }
+        let mut tags = db.prepare("SELECT path, tag FROM tags ORDER BY path, tag")?;
+        for row in tags.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))? {
+            let (path, tag) = row?;
+            samples.entry(path).or_default().tags.push(tag);
+        }
+        let mut searches = db.prepare("SELECT name, query FROM saved_searches ORDER BY rowid")?;
+        let saved_searches = searches
+            .query_map([], |row| Ok(SavedSearch { name: row.get(0)?, query: row.get(1)? }))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        drop((ratings, tags, searches));
+
+        Ok(Self { db: Some(db), samples, saved_searches, revision: 0 })
+    }
+
+    /// Run a write against the database, logging a failure (the edit then lasts until exit)
+    fn write(&mut self, f: impl FnOnce(&Connection) -> rusqlite::Result<()>) {
+        self.revision += 1;
+        let Some(db) = &self.db else { return };
+        let written = db.unchecked_transaction().and_then(|tx| {
+            f(&tx)?;
+            tx.commit()
+        });
+        if let Err(e) = written {
+            tracing::warn!("Failed to save sample library: {}", e);
+        }
+    }
+
+    /// Replace the indexed files of every Place, each given by its root and sample files
+    pub fn index_places<'a>(&mut self, places: impl IntoIterator<Item = (&'a Path, Vec<PathBuf>)>) {
+        let places: Vec<_> = places.into_iter().collect();
+        self.write(|db| {
+            db.execute("DELETE FROM files", [])?;
+            for (root, files) in &places {
+                insert_files(db, root, files)?;
+            }
+            Ok(())
+        });
+    }
+
+    /// Replace the indexed files of one Place
+    pub fn index_place(&mut self, root: &Path, files: &[PathBuf]) {
+        self.write(|db| {
+            db.execute("DELETE FROM files WHERE place = ?1", [root.to_string_lossy()])?;
+            insert_files(db, root, files)
+        });
+    }
+
+    /// Drop a removed Place's files from the index (their tags and ratings stay)
+    pub fn forget_place(&mut self, root: &Path) {
+        self.write(|db| {
+            db.execute("DELETE FROM files WHERE place = ?1", [root.to_string_lossy()])?;
+            Ok(())
+        });
+    }
+
+    /// Changes with every edit to the library or its index
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn entry(&self, path: &Path) -> Option<&SampleEntry> {
+        self.samples.get(path.to_string_lossy().as_ref())
+    }
+
+    pub fn rating(&self, path: &Path) -> u8 {
+        self.entry(path).map_or(0, |e| e.rating)
+    }
+
+    /// Change a sample's entry, dropping it once it has no tags or rating, and save
+    fn update(&mut self, path: &Path, f: impl FnOnce(&mut SampleEntry)) {
+        let key = path.to_string_lossy().into_owned();
+        let entry = self.samples.entry(key.clone()).or_default();
+        f(entry);
+        let entry = entry.clone();
+        if entry.is_empty() {
+            self.samples.remove(&key);
+        }
+        self.write(|db| {
+            db.execute("DELETE FROM ratings WHERE path = ?1", [&key])?;
+            db.execute("DELETE FROM tags WHERE path = ?1", [&key])?;
+            if entry.rating > 0 {
+                db.execute("INSERT INTO ratings (path, rating) VALUES (?1, ?2)", params![key, entry.rating])?;
+            }
+            for tag in &entry.tags {
+                db.execute("INSERT INTO tags (path, tag) VALUES (?1, ?2)", params![key, tag])?;
+            }
+            Ok(())
+        });
+    }
+
+    pub fn set_rating(&mut self, path: &Path, rating: u8) {
+        self.update(path, |e| e.rating = rating.min(MAX_RATING));
+    }
+
+    /// Add a tag (lower-cased, without a leading '#')
+    pub fn add_tag(&mut self, path: &Path, tag: &str) {
+        let tag = tag.trim().trim_start_matches('#').to_lowercase();
+        if tag.is_empty() {
+            return;
+        }
+        self.update(path, |e| {
+            if !e.tags.contains(&tag) {
+                e.tags.push(tag);
+                e.tags.sort();
+            }
+        });
+    }
+
+    pub fn remove_tag(&mut self, path: &Path, tag: &str) {
+        self.update(path, |e| e.tags.retain(|t| t != tag));
+    }
+
+    /// Every tag in use, sorted
+    pub fn all_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self.samples.values().flat_map(|e| e.tags.iter().cloned()).collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// Rated samples, best first
+    pub fn favorites(&self) -> Vec<(PathBuf, u8)> {
+        let mut favorites: Vec<(PathBuf, u8)> = self.samples.iter()
+            .filter(|(_, e)| e.rating > 0)
+            .map(|(path, e)| (PathBuf::from(path), e.rating))
+            .collect();
+        favorites.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        favorites
+    }
+
+    pub fn save_search(&mut self, name: &str, query: &str) {
+        self.saved_searches.retain(|s| s.name != name);
+        self.saved_searches.push(SavedSearch { name: name.to_string(), query: query.to_string() });
+        self.write(|db| {
+            db.execute("DELETE FROM saved_searches WHERE name = ?1", [name])?;
+            db.execute("INSERT INTO saved_searches (name, query) VALUES (?1, ?2)", [name, query])?;
+            Ok(())
+        });
+    }
+
+    pub fn remove_search(&mut self, index: usize) {
+        if index < self.saved_searches.len() {
+            let search = self.saved_searches.remove(index);
+            self.write(|db| {
+                db.execute("DELETE FROM saved_searches WHERE name = ?1", [&search.name])?;
+                Ok(())
+            });
+        }
+    }
+
+    /// Up to `limit` indexed sample files that match `query`, by path
+    pub fn search(&self, query: &SampleQuery, limit: usize) -> Vec<PathBuf> {
+        let Some(db) = &self.db else { return Vec::new() };
+        let mut sql = String::from(
+            "SELECT files.path FROM files LEFT JOIN ratings ON ratings.path = files.path \
+             WHERE COALESCE(ratings.rating, 0) >= ?",
+        );
+        let mut args = vec![Value::Integer(query.min_rating.into())];
+
+        // The trigram index finds words of three or more characters; shorter ones are
+        // looked for in each path
+        let (indexed, short): (Vec<&String>, Vec<&String>) = query.words.iter().partition(|w| w.chars().count() >= 3);
+        if !indexed.is_empty() {
+            sql.push_str(" AND files MATCH ?");
+            let phrases: Vec<String> = indexed.iter().map(|w| format!("\"{}\"", w.replace('"', "\"\""))).collect();
+            args.push(Value::Text(phrases.join(" ")));
+        }
+        for word in short {
+            sql.push_str(" AND instr(lower(files.path), ?) > 0");
+            args.push(Value::Text(word.clone()));
+        }
+        for tag in &query.tags {
+            sql.push_str(" AND EXISTS (SELECT 1 FROM tags WHERE tags.path = files.path AND tags.tag = ?)");
+            args.push(Value::Text(tag.clone()));
+        }
+        sql.push_str(" ORDER BY files.path LIMIT ?");
+        args.push(Value::Integer(limit.try_into().unwrap_or(i64::MAX)));
+
+        let found = db.prepare(&sql).and_then(|mut stmt| {
+            stmt.query_map(params_from_iter(args), |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()
+        });
+        match found {
+            Ok(paths) => paths.into_iter().map(PathBuf::from).collect(),
+            Err(e) => {
+                tracing::warn!("Sample search failed: {}", e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+fn insert_files(db: &Connection, root: &Path, files: &[PathBuf]) -> rusqlite::Result<()> {
+    let root = root.to_string_lossy();
+    let mut insert = db.prepare_cached("INSERT INTO files (path, place) VALUES (?1, ?2)")?;
+    for file in files {
+        insert.execute([file.to_string_lossy(), root.clone()])?;
+    }
+    Ok(())
+}
+
+/// A parsed search: plain words match anywhere in the file's path (folders and name),
+/// `#tag` requires a tag and `rating:N` at least N stars; every term must match
+#[derive(Debug, Default)]
+pub struct SampleQuery {
+    words: Vec<String>,
+    tags: Vec<String>,
+    min_rating: u8,
+}
+
+impl SampleQuery {
+    pub fn parse(query: &str) -> Self {
+        let mut parsed = Self::default();
+        for term in query.split_whitespace().map(str::to_lowercase) {
+            if let Some(tag) = term.strip_prefix('#').filter(|t| !t.is_empty()) {
+                parsed.tags.push(tag.to_string());
+            } else if let Some(rating) = term.strip_prefix("rating:").and_then(|n| n.parse::<u8>().ok()) {
+                parsed.min_rating = rating.min(MAX_RATING);
+            } else {
+                parsed.words.push(term);
+            }
+        }
+        parsed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty() && self.tags.is_empty() && self.min_rating == 0
+    }
+}
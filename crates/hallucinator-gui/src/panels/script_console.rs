@@ -0,0 +1,94 @@
+//! Script console panel - edit and run Rhai scripts over the arrangement's MIDI clips
+
+use egui::{Color32, ScrollArea, Ui};
+
+use super::keymap::Command;
+
+/// Lines of output kept in the console
+const MAX_LOG_LINES: usize = 500;
+
+const EXAMPLE: &str = "\
+// Transpose the selected clip up an octave
+let t = selected_track();
+let c = selected_clip();
+if c >= 0 {
+    transpose(t, c, 12);
+}";
+
+/// Action returned from the script console
+pub enum ScriptConsoleAction {
+    None,
+    Run(String),
+}
+
+/// Script editor with its output log
+pub struct ScriptConsolePanel {
+    source: String,
+    log: Vec<(String, bool)>,
+}
+
+impl ScriptConsolePanel {
+    pub fn new() -> Self {
+        Self {
+            source: EXAMPLE.to_string(),
+            log: Vec::new(),
+        }
+    }
+
+    /// Append output lines (`error` lines are drawn in red)
+    pub fn push_log(&mut self, line: impl Into<String>, error: bool) {
+        self.log.push((line.into(), error));
+        let excess = self.log.len().saturating_sub(MAX_LOG_LINES);
+        self.log.drain(..excess);
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) -> ScriptConsoleAction {
+        let mut action = ScriptConsoleAction::None;
+
+        ui.horizontal(|ui| {
+            let run = ui.button("▶ Run").on_hover_text(format!("Run the script ({})", Command::RunScript.shortcut_text(ui.ctx())));
+            if run.clicked() {
+                action = ScriptConsoleAction::Run(self.source.clone());
+            }
+            if ui.button("Clear output").clicked() {
+                self.log.clear();
+            }
+            ui.label("Edits are applied only if the script finishes without an error");
+        });
+
+        // Taken before the editor sees it, so Ctrl+Enter doesn't also insert a newline
+        let editor_id = ui.id().with("script_source");
+        if ui.memory(|m| m.has_focus(editor_id))
+            && Command::RunScript.consume(ui.ctx())
+        {
+            action = ScriptConsoleAction::Run(self.source.clone());
+        }
+        ui.add(
+            egui::TextEdit::multiline(&mut self.source)
+                .id(editor_id)
+                .code_editor()
+                .desired_rows(12)
+                .desired_width(f32::INFINITY),
+        );
+
+        ui.separator();
+        ScrollArea::vertical()
+            .id_salt("script_log")
+            .stick_to_bottom(true)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for (line, error) in &self.log {
+                    let color = if *error { Color32::from_rgb(230, 90, 80) } else { ui.visuals().text_color() };
+                    ui.label(egui::RichText::new(line).monospace().color(color));
+                }
+            });
+
+        action
+    }
+}
+
+impl Default for ScriptConsolePanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
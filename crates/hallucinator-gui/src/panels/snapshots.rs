@@ -0,0 +1,107 @@
+//! Snapshots panel - store and recall mixer/device states, or morph between two
+
+use egui::{Color32, Ui};
+use hallucinator_core::{SnapshotBank, SNAPSHOT_SLOTS};
+
+/// Action returned from the snapshots panel
+pub enum SnapshotAction {
+    None,
+    Store(usize),
+    Recall(usize),
+    Clear(usize),
+    Morph { from: usize, to: usize, bars: u32 },
+    StopMorph,
+}
+
+/// A/B/C snapshot slots with a morph between two of them
+pub struct SnapshotPanel {
+    morph_from: usize,
+    morph_to: usize,
+    morph_bars: u32,
+}
+
+impl SnapshotPanel {
+    pub fn new() -> Self {
+        Self {
+            morph_from: 0,
+            morph_to: 1,
+            morph_bars: 4,
+        }
+    }
+
+    /// `morph_progress` is Some (0-1) while a morph is running
+    pub fn ui(&mut self, ui: &mut Ui, bank: &SnapshotBank, morph_progress: Option<f32>) -> SnapshotAction {
+        let mut action = SnapshotAction::None;
+
+        ui.horizontal(|ui| {
+            for slot in 0..SNAPSHOT_SLOTS {
+                let name = SnapshotBank::slot_name(slot);
+                let stored = bank.slots[slot].is_some();
+                ui.vertical(|ui| {
+                    let recall = egui::Button::new(egui::RichText::new(name.to_string()).size(20.0).strong())
+                        .min_size(egui::vec2(56.0, 40.0))
+                        .fill(if stored { Color32::from_rgb(60, 90, 130) } else { ui.visuals().faint_bg_color });
+                    let response = ui.add_enabled(stored, recall)
+                        .on_hover_text(format!("Recall snapshot {name}"))
+                        .on_disabled_hover_text(format!("Snapshot {name} is empty"));
+                    if response.clicked() {
+                        action = SnapshotAction::Recall(slot);
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.small_button("Store").on_hover_text("Capture track volumes, pans and device parameters").clicked() {
+                            action = SnapshotAction::Store(slot);
+                        }
+                        if ui.add_enabled(stored, egui::Button::new("×").small()).on_hover_text("Clear").clicked() {
+                            action = SnapshotAction::Clear(slot);
+                        }
+                    });
+                });
+            }
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Morph");
+            slot_combo(ui, "morph_from", &mut self.morph_from);
+            ui.label("→");
+            slot_combo(ui, "morph_to", &mut self.morph_to);
+            ui.label("over");
+            ui.add(egui::DragValue::new(&mut self.morph_bars).range(1..=64).suffix(" bars"));
+
+            if let Some(progress) = morph_progress {
+                if ui.button("■ Stop").clicked() {
+                    action = SnapshotAction::StopMorph;
+                }
+                ui.add(egui::ProgressBar::new(progress).desired_width(100.0));
+            } else {
+                let ready = self.morph_from != self.morph_to
+                    && bank.slots[self.morph_from].is_some()
+                    && bank.slots[self.morph_to].is_some();
+                let start = ui.add_enabled(ready, egui::Button::new("▶ Start"))
+                    .on_disabled_hover_text("Store two different snapshots to morph between");
+                if start.clicked() {
+                    action = SnapshotAction::Morph { from: self.morph_from, to: self.morph_to, bars: self.morph_bars };
+                }
+            }
+        });
+
+        action
+    }
+}
+
+fn slot_combo(ui: &mut Ui, id: &str, slot: &mut usize) {
+    egui::ComboBox::from_id_salt(id)
+        .width(36.0)
+        .selected_text(SnapshotBank::slot_name(*slot).to_string())
+        .show_ui(ui, |ui| {
+            for s in 0..SNAPSHOT_SLOTS {
+                ui.selectable_value(slot, s, SnapshotBank::slot_name(s).to_string());
+            }
+        });
+}
+
+impl Default for SnapshotPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
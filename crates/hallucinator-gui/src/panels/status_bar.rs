@@ -0,0 +1,108 @@
+//! Status bar along the bottom of the window: a hint for what's under the pointer, the
+//! hovered clip, files still loading, the last warning or error, and the engine's sample
+//! rate and buffer
+
+use std::time::Instant;
+
+use egui::{Align, Context, Id, Label, Layout, ProgressBar, RichText, Ui};
+use tracing::Level;
+
+use crate::status_log;
+
+/// What the status bar shows of the audio engine
+pub struct EngineStatus {
+    pub sample_rate: u32,
+    /// Frames per buffer, None for the device's default
+    pub buffer_size: Option<u32>,
+}
+
+pub struct StatusBarPanel {
+    /// Messages logged before this were dismissed
+    dismissed: Option<Instant>,
+}
+
+impl StatusBarPanel {
+    pub fn new() -> Self {
+        Self { dismissed: None }
+    }
+
+    /// Draw after the other panels, so the hints they set this frame are shown. `loading`
+    /// names the audio files being decoded with how far along each is (0-1)
+    pub fn ui(&mut self, ui: &mut Ui, engine: &EngineStatus, loading: &[(String, f32)]) {
+        let ctx = ui.ctx().clone();
+        let hovered = ctx.data_mut(|d| d.remove_temp::<String>(hovered_id()));
+        let hint = ctx.data_mut(|d| d.remove_temp::<String>(hint_id()));
+        let message = status_log::last().filter(|m| self.dismissed.is_none_or(|dismissed| m.at > dismissed));
+
+        ui.horizontal_centered(|ui| {
+            if let Some(hovered) = hovered {
+                ui.label(hovered);
+                ui.separator();
+            }
+            if let Some(hint) = hint {
+                ui.weak(hint);
+            }
+
+            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                ui.weak(engine_text(engine));
+                if let Some((name, progress)) = loading.first() {
+                    ui.separator();
+                    let text = match loading.len() {
+                        1 => format!("Loading {}", name),
+                        n => format!("Loading {} (+{} more)", name, n - 1),
+                    };
+                    ui.add(ProgressBar::new(*progress).desired_width(160.0).text(text))
+                        .on_hover_text(loading.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join("\n"));
+                }
+                let Some(message) = message else { return };
+                ui.separator();
+                if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                    self.dismissed = Some(message.at);
+                }
+                let (icon, color) = if message.level == Level::ERROR {
+                    ("⛔", ui.visuals().error_fg_color)
+                } else {
+                    ("⚠", ui.visuals().warn_fg_color)
+                };
+                let ago = message.at.elapsed().as_secs();
+                ui.add(Label::new(RichText::new(format!("{} {}", icon, message.text)).color(color)).truncate())
+                    .on_hover_text(format!("{}\n\n{} s ago; the log has the details", message.text, ago));
+            });
+        });
+    }
+}
+
+impl Default for StatusBarPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hint for the control under the pointer, shown in the status bar this frame
+pub fn set_hint(ctx: &Context, hint: impl Into<String>) {
+    ctx.data_mut(|d| d.insert_temp(hint_id(), hint.into()));
+}
+
+/// Name, length and position of the clip under the pointer, shown in the status bar this frame
+pub fn set_hovered(ctx: &Context, hovered: impl Into<String>) {
+    ctx.data_mut(|d| d.insert_temp(hovered_id(), hovered.into()));
+}
+
+fn hint_id() -> Id {
+    Id::new("status_hint")
+}
+
+fn hovered_id() -> Id {
+    Id::new("status_hovered")
+}
+
+fn engine_text(engine: &EngineStatus) -> String {
+    let rate = format!("{:.1} kHz", engine.sample_rate as f32 / 1000.0);
+    match engine.buffer_size {
+        Some(frames) => {
+            let ms = frames as f32 * 1000.0 / engine.sample_rate.max(1) as f32;
+            format!("{} · {} samples ({:.1} ms)", rate, frames, ms)
+        }
+        None => format!("{} · default buffer", rate),
+    }
+}
@@ -0,0 +1,227 @@
+//! Colour theme of the whole window, and the palette the editors (arrange, piano roll,
+//! sequencer) paint with: the theme's base colours plus accents the user can edit
+
+use egui::{Color32, Context, Visuals};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub const ALL: [Self; 2] = [Self::Dark, Self::Light];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Dark => "Dark",
+            Self::Light => "Light",
+        }
+    }
+
+    /// Switch egui's visuals and install the palette the editors look up
+    pub fn apply(self, ctx: &Context, accents: &Accents) {
+        let palette = Palette::new(self, accents);
+        let mut visuals = match self {
+            Self::Dark => Visuals::dark(),
+            Self::Light => Visuals::light(),
+        };
+        visuals.selection.bg_fill = visuals.panel_fill.lerp_to_gamma(palette.selection, 0.6);
+        ctx.set_visuals(visuals);
+        ctx.data_mut(|d| d.insert_temp(palette_id(), palette));
+    }
+}
+
+/// Accent colours, the same whichever theme is picked
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Accents {
+    /// Selections, marquees and the outline of selected pads
+    pub selection: [u8; 3],
+    pub playhead: [u8; 3],
+    pub loop_region: [u8; 3],
+    pub audio_clip: [u8; 3],
+    pub midi_clip: [u8; 3],
+    /// Piano roll notes
+    pub note: [u8; 3],
+    /// Lit sequencer steps and sounding piano keys
+    pub active: [u8; 3],
+    pub recording: [u8; 3],
+}
+
+impl Default for Accents {
+    fn default() -> Self {
+        Self {
+            selection: [100, 180, 255],
+            playhead: [255, 100, 100],
+            loop_region: [100, 150, 200],
+            audio_clip: [80, 130, 180],
+            midi_clip: [80, 160, 80],
+            note: [80, 160, 220],
+            active: [220, 195, 90],
+            recording: [200, 80, 80],
+        }
+    }
+}
+
+impl Accents {
+    /// Every accent with its label, for editing
+    pub fn entries_mut(&mut self) -> [(&'static str, &mut [u8; 3]); 8] {
+        [
+            ("Selection", &mut self.selection),
+            ("Playhead", &mut self.playhead),
+            ("Loop", &mut self.loop_region),
+            ("Audio clips", &mut self.audio_clip),
+            ("MIDI clips", &mut self.midi_clip),
+            ("Notes", &mut self.note),
+            ("Active steps", &mut self.active),
+            ("Recording", &mut self.recording),
+        ]
+    }
+}
+
+/// Colours the editors paint with
+#[derive(Clone, Copy, Debug)]
+pub struct Palette {
+    /// Behind the sequencer and the position readout
+    pub panel: Color32,
+    /// Arrange track rows, alternating
+    pub lane: Color32,
+    pub lane_alt: Color32,
+    /// Row of the selected track, alternating like `lane`
+    pub lane_selected: Color32,
+    pub lane_selected_alt: Color32,
+    /// Below the last track
+    pub empty: Color32,
+    pub ruler: Color32,
+    /// Lines between rows and around piano keys
+    pub separator: Color32,
+    pub grid_bar: Color32,
+    pub grid_beat: Color32,
+    pub grid_subdivision: Color32,
+    pub text: Color32,
+    pub text_dim: Color32,
+    pub key_white: Color32,
+    pub key_black: Color32,
+    /// Sequencer pads: unlit, on an accidental, under the playing step, and their outline
+    pub pad: Color32,
+    pub pad_black: Color32,
+    pub pad_current: Color32,
+    pub pad_border: Color32,
+
+    pub selection: Color32,
+    pub playhead: Color32,
+    pub loop_region: Color32,
+    pub audio_clip: Color32,
+    pub midi_clip: Color32,
+    pub note: Color32,
+    pub active: Color32,
+    pub recording: Color32,
+}
+
+impl Palette {
+    pub fn new(theme: Theme, accents: &Accents) -> Self {
+        let rgb = |[r, g, b]: [u8; 3]| Color32::from_rgb(r, g, b);
+        Self {
+            selection: rgb(accents.selection),
+            playhead: rgb(accents.playhead),
+            loop_region: rgb(accents.loop_region),
+            audio_clip: rgb(accents.audio_clip),
+            midi_clip: rgb(accents.midi_clip),
+            note: rgb(accents.note),
+            active: rgb(accents.active),
+            recording: rgb(accents.recording),
+            ..match theme {
+                Theme::Dark => DARK,
+                Theme::Light => LIGHT,
+            }
+        }
+    }
+
+    /// The installed palette, or the dark one before any is installed
+    pub fn get(ctx: &Context) -> Self {
+        ctx.data(|d| d.get_temp(palette_id())).unwrap_or_else(|| Self::new(Theme::Dark, &Accents::default()))
+    }
+}
+
+/// Base colours of the dark theme; `Palette::new` fills in the accents
+const DARK: Palette = Palette {
+    panel: Color32::from_rgb(22, 22, 26),
+    lane: Color32::from_gray(42),
+    lane_alt: Color32::from_gray(48),
+    lane_selected: Color32::from_gray(28),
+    lane_selected_alt: Color32::from_gray(32),
+    empty: Color32::from_gray(50),
+    ruler: Color32::from_gray(45),
+    separator: Color32::from_gray(25),
+    grid_bar: Color32::from_gray(80),
+    grid_beat: Color32::from_gray(60),
+    grid_subdivision: Color32::from_gray(45),
+    text: Color32::from_rgb(210, 210, 215),
+    text_dim: Color32::from_rgb(120, 120, 130),
+    key_white: Color32::from_gray(60),
+    key_black: Color32::from_gray(30),
+    pad: Color32::from_rgb(38, 38, 42),
+    pad_black: Color32::from_rgb(28, 28, 32),
+    pad_current: Color32::from_rgb(70, 68, 50),
+    pad_border: Color32::from_rgb(55, 55, 60),
+    selection: Color32::PLACEHOLDER,
+    playhead: Color32::PLACEHOLDER,
+    loop_region: Color32::PLACEHOLDER,
+    audio_clip: Color32::PLACEHOLDER,
+    midi_clip: Color32::PLACEHOLDER,
+    note: Color32::PLACEHOLDER,
+    active: Color32::PLACEHOLDER,
+    recording: Color32::PLACEHOLDER,
+};
+
+/// Base colours of the light theme; `Palette::new` fills in the accents
+const LIGHT: Palette = Palette {
+    panel: Color32::from_rgb(236, 236, 240),
+    lane: Color32::from_gray(228),
+    lane_alt: Color32::from_gray(220),
+    lane_selected: Color32::from_gray(204),
+    lane_selected_alt: Color32::from_gray(198),
+    empty: Color32::from_gray(212),
+    ruler: Color32::from_gray(218),
+    separator: Color32::from_gray(175),
+    grid_bar: Color32::from_gray(140),
+    grid_beat: Color32::from_gray(175),
+    grid_subdivision: Color32::from_gray(200),
+    text: Color32::from_rgb(30, 30, 35),
+    text_dim: Color32::from_rgb(105, 105, 115),
+    key_white: Color32::from_gray(248),
+    key_black: Color32::from_gray(70),
+    pad: Color32::from_rgb(214, 214, 220),
+    pad_black: Color32::from_rgb(192, 192, 198),
+    pad_current: Color32::from_rgb(236, 228, 186),
+    pad_border: Color32::from_rgb(168, 168, 174),
+    selection: Color32::PLACEHOLDER,
+    playhead: Color32::PLACEHOLDER,
+    loop_region: Color32::PLACEHOLDER,
+    audio_clip: Color32::PLACEHOLDER,
+    midi_clip: Color32::PLACEHOLDER,
+    note: Color32::PLACEHOLDER,
+    active: Color32::PLACEHOLDER,
+    recording: Color32::PLACEHOLDER,
+};
+
+fn palette_id() -> egui::Id {
+    egui::Id::new("palette")
+}
+
+/// `color` lightened towards white (`amount` > 0) or darkened towards black (< 0)
+pub fn shade(color: Color32, amount: f32) -> Color32 {
+    if amount >= 0.0 {
+        color.lerp_to_gamma(Color32::WHITE, amount)
+    } else {
+        color.lerp_to_gamma(Color32::BLACK, -amount)
+    }
+}
+
+/// `color` with its alpha replaced
+pub fn with_alpha(color: Color32, alpha: u8) -> Color32 {
+    Color32::from_rgba_unmultiplied(color.r(), color.g(), color.b(), alpha)
+}
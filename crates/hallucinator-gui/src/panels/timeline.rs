@@ -4,4 +4,6 @@
 pub struct RecordingPreview {
     pub samples: Vec<f32>,
     pub start_sample: u64,
+    /// Length of the take so far (a take recorded to disk previews as peaks)
+    pub length_samples: u64,
 }
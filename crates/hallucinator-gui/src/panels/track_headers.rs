@@ -1,7 +1,10 @@
 //! Track headers panel - track controls column (left of arrange view)
 
 use egui::{Color32, Rect, Sense, Stroke, Ui, Vec2};
-use hallucinator_core::Track;
+use hallucinator_core::{PanLaw, PanMode, Track, TrackInput, TrackKind, TrackMonitor, TrackOutput};
+
+use super::arrange::OVERVIEW_HEIGHT;
+use super::track_layout::TrackLayout;
 
 /// Action returned from track headers
 pub enum TrackHeaderAction {
@@ -12,23 +15,57 @@ pub enum TrackHeaderAction {
     ToggleArm(usize),
     SetVolume(usize, f32),
     SetPan(usize, f32),
+    /// Balance the track's channels or place them in the field with its width
+    SetPanMode(usize, PanMode),
+    /// Stereo width of a track in stereo pan mode
+    SetWidth(usize, f32),
+    /// Pan law of every track in the project
+    SetPanLaw(PanLaw),
+    /// Move the track at the first index to the second
+    MoveTrack(usize, usize),
     DeleteTrack(usize),
+    /// Copy the track with its own instrument, effects and MIDI FX (and its clips when set)
+    DuplicateTrack(usize, bool),
     AddAudioTrack,
     AddMidiTrack,
     RenameTrack(usize, String),
+    /// Choose the interface channels an audio track records from, or a track it plays
+    SetInput(usize, TrackInput),
+    /// Send the track to master, into another track, or to a hardware output pair
+    SetOutput(usize, TrackOutput),
+    /// Set when live input plays through an audio track's effect chain
+    SetMonitor(usize, TrackMonitor),
+    /// Give each auxiliary output of the track's instrument its own audio track
+    RouteInstrumentOutputs(usize),
+    /// Render the track's instrument and effects to audio and idle them
+    Freeze(usize),
+    /// Go back to the live instrument and effects
+    Unfreeze(usize),
+    /// Render the loop region through the track's instrument and effects to an audio clip
+    BounceLoop(usize),
 }
 
 /// Track headers panel state
 pub struct TrackHeadersPanel {
-    pub track_height: f32,
     pub ruler_height: f32,
+    /// Channels of the audio input device, for the input selector
+    pub input_channels: u16,
+    /// Channels of the audio output device, for the output selector
+    pub output_channels: u16,
+    /// The project's pan law, offered in the pan menu
+    pub pan_law: PanLaw,
+    /// Index of the track being dragged to a new position
+    dragging_track: Option<usize>,
 }
 
 impl TrackHeadersPanel {
     pub fn new() -> Self {
         Self {
-            track_height: 80.0,
-            ruler_height: 24.0, // Match arrange panel ruler
+            ruler_height: OVERVIEW_HEIGHT + 24.0, // Match arrange panel overview strip and ruler
+            input_channels: 2,
+            output_channels: 2,
+            pan_law: PanLaw::default(),
+            dragging_track: None,
         }
     }
 
@@ -37,70 +74,151 @@ impl TrackHeadersPanel {
         ui: &mut Ui,
         tracks: &[Track],
         selected_track_idx: Option<usize>,
+        layout: &mut TrackLayout,
     ) -> TrackHeaderAction {
         let mut action = TrackHeaderAction::None;
 
         // Force vertical layout
         ui.vertical(|ui| {
-            // Add spacer to match arrange panel ruler height
-            ui.add_space(self.ruler_height);
+            // Spacer matching the arrange panel ruler, holding the vertical zoom buttons
+            let (ruler_rect, _) = ui.allocate_exact_size(Vec2::new(ui.available_width(), self.ruler_height), Sense::hover());
+            Self::draw_zoom_buttons(ui, ruler_rect, layout);
+
+            // Headers scroll with the arrange rows; clicks on the area below them add tracks
+            let (column, response) = ui.allocate_exact_size(ui.available_size(), Sense::click());
+            ui.painter().rect_filled(column, 0.0, Color32::from_gray(35));
+            layout.clamp_scroll(tracks, column.height());
+
+            let mut rows_ui = ui.new_child(egui::UiBuilder::new().max_rect(column));
+            rows_ui.set_clip_rect(column.intersect(ui.clip_rect()));
 
             // Add each track header
-            for (idx, track) in tracks.iter().enumerate() {
+            let header_rects = layout.rows(column, tracks);
+            for (idx, (track, &row)) in tracks.iter().zip(&header_rects).enumerate() {
+                if !row.intersects(column) {
+                    continue;
+                }
                 let header_action = self.draw_track_header(
-                    ui,
+                    &mut rows_ui,
                     idx,
                     track,
+                    tracks,
                     selected_track_idx == Some(idx),
+                    row,
                 );
 
                 if !matches!(header_action, TrackHeaderAction::None) {
                     action = header_action;
                 }
-            }
-
-            // Fill remaining space and handle right-click for adding tracks
-            let remaining = ui.available_size();
-            if remaining.y > 0.0 {
-                let (rect, response) = ui.allocate_exact_size(remaining, Sense::click());
 
-                // Draw empty area background
-                ui.painter().rect_filled(rect, 0.0, Color32::from_gray(35));
+                // Drag the bottom edge to resize the track's row
+                let grip = Rect::from_x_y_ranges(row.x_range(), row.bottom() - 3.0..=row.bottom() + 1.0);
+                let grip_response = rows_ui.interact(grip, rows_ui.id().with(("resize", track.id)), Sense::drag());
+                if grip_response.hovered() || grip_response.dragged() {
+                    ui.ctx().set_cursor_icon(egui::CursorIcon::ResizeVertical);
+                }
+                if grip_response.dragged() {
+                    layout.resize(track.id, row.height() + grip_response.drag_delta().y);
+                }
+            }
 
-                // Right-click on empty area to add track
-                if response.secondary_clicked() {
-                    action = TrackHeaderAction::AddAudioTrack; // Default action
+            if let Some(from) = self.dragging_track {
+                let move_action = self.draw_drop_target(&mut rows_ui, from, &header_rects);
+                if !matches!(move_action, TrackHeaderAction::None) {
+                    action = move_action;
                 }
+            }
 
-                response.context_menu(|ui| {
-                    if ui.button("Add Audio Track").clicked() {
-                        action = TrackHeaderAction::AddAudioTrack;
-                        ui.close_menu();
-                    }
-                    if ui.button("Add MIDI Track").clicked() {
-                        action = TrackHeaderAction::AddMidiTrack;
-                        ui.close_menu();
+            // Wheel scrolls the tracks, Alt+wheel zooms their heights (as over the arrange view)
+            if ui.rect_contains_pointer(column) {
+                let (scroll_y, alt) = ui.input(|i| (i.smooth_scroll_delta.y + i.raw_scroll_delta.y, i.modifiers.alt));
+                if scroll_y.abs() > 0.1 {
+                    if alt {
+                        layout.set_zoom(layout.zoom() * (1.0 + scroll_y * 0.02));
+                    } else {
+                        layout.scroll -= scroll_y;
                     }
-                });
+                }
+            }
+
+            // Right-click on empty area to add track
+            if response.secondary_clicked() {
+                action = TrackHeaderAction::AddAudioTrack; // Default action
             }
+
+            response.context_menu(|ui| {
+                if ui.button("Add Audio Track").clicked() {
+                    action = TrackHeaderAction::AddAudioTrack;
+                    ui.close_menu();
+                }
+                if ui.button("Add MIDI Track").clicked() {
+                    action = TrackHeaderAction::AddMidiTrack;
+                    ui.close_menu();
+                }
+            });
         });
 
         action
     }
 
+    /// Vertical zoom out/in buttons at the right of the ruler spacer, level with the ruler
+    fn draw_zoom_buttons(ui: &mut Ui, ruler_rect: Rect, layout: &mut TrackLayout) {
+        let size = Vec2::splat(16.0);
+        let zoom_in = Rect::from_min_size(egui::pos2(ruler_rect.right() - 20.0, ruler_rect.bottom() - 20.0), size);
+        let zoom_out = zoom_in.translate(Vec2::new(-18.0, 0.0));
+        for (rect, label, factor) in [(zoom_out, "−", 0.8), (zoom_in, "+", 1.25)] {
+            let response = ui
+                .interact(rect, ui.id().with(("zoom_vertical", label)), Sense::click())
+                .on_hover_text("Track height zoom (Alt+wheel)");
+            let color = if response.hovered() { Color32::from_gray(90) } else { Color32::from_gray(70) };
+            ui.painter().rect_filled(rect, 2.0, color);
+            ui.painter().text(rect.center(), egui::Align2::CENTER_CENTER, label, egui::FontId::proportional(11.0), Color32::WHITE);
+            if response.clicked() {
+                layout.set_zoom(layout.zoom() * factor);
+            }
+        }
+    }
+
+    /// Insertion line for a dragged track; releasing the pointer moves the track there
+    fn draw_drop_target(&mut self, ui: &mut Ui, from: usize, header_rects: &[Rect]) -> TrackHeaderAction {
+        let Some(pointer) = ui.input(|i| i.pointer.interact_pos()) else {
+            return TrackHeaderAction::None;
+        };
+        ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+
+        // Gap between headers the pointer is over (0 = above the first)
+        let slot = header_rects.iter().filter(|r| r.center().y < pointer.y).count();
+        let to = if slot > from { slot - 1 } else { slot };
+        if let Some(last) = header_rects.last() {
+            let y = header_rects.get(slot).map_or(last.bottom(), |r| r.top());
+            ui.painter().line_segment(
+                [egui::pos2(last.left(), y), egui::pos2(last.right(), y)],
+                Stroke::new(2.0, Color32::from_rgb(120, 200, 255)),
+            );
+        }
+
+        if !ui.input(|i| i.pointer.any_down()) {
+            self.dragging_track = None;
+            if to != from && to < header_rects.len() {
+                return TrackHeaderAction::MoveTrack(from, to);
+            }
+        }
+        TrackHeaderAction::None
+    }
+
     fn draw_track_header(
         &mut self,
         ui: &mut Ui,
         idx: usize,
         track: &Track,
+        tracks: &[Track],
         is_selected: bool,
+        rect: Rect,
     ) -> TrackHeaderAction {
         let mut action = TrackHeaderAction::None;
 
-        let width = ui.available_width();
-        let header_size = Vec2::new(width, self.track_height);
-
-        let (rect, response) = ui.allocate_exact_size(header_size, Sense::click());
+        let width = rect.width();
+        let response = ui.interact(rect, ui.id().with(("header", track.id)), Sense::click_and_drag());
         let painter = ui.painter();
 
         // Background
@@ -122,12 +240,50 @@ impl TrackHeadersPanel {
             action = TrackHeaderAction::SelectTrack(idx);
         }
 
+        // Drag the header background to reorder; the drop is handled once all headers are laid out
+        if response.drag_started() {
+            self.dragging_track = Some(idx);
+        }
+
         // Right-click context menu
         response.context_menu(|ui| {
             if ui.button("Delete Track").clicked() {
                 action = TrackHeaderAction::DeleteTrack(idx);
                 ui.close_menu();
             }
+            if ui.button("Duplicate Track").clicked() {
+                action = TrackHeaderAction::DuplicateTrack(idx, false);
+                ui.close_menu();
+            }
+            if ui.button("Duplicate Track with Clips").clicked() {
+                action = TrackHeaderAction::DuplicateTrack(idx, true);
+                ui.close_menu();
+            }
+            if ui.add_enabled(idx > 0, egui::Button::new("Move Up")).clicked() {
+                action = TrackHeaderAction::MoveTrack(idx, idx - 1);
+                ui.close_menu();
+            }
+            if ui.add_enabled(idx + 1 < tracks.len(), egui::Button::new("Move Down")).clicked() {
+                action = TrackHeaderAction::MoveTrack(idx, idx + 1);
+                ui.close_menu();
+            }
+            if track.instrument_id.is_some() && ui.button("Route Outputs to Tracks").clicked() {
+                action = TrackHeaderAction::RouteInstrumentOutputs(idx);
+                ui.close_menu();
+            }
+            if track.is_frozen() {
+                if ui.button("Unfreeze Track").clicked() {
+                    action = TrackHeaderAction::Unfreeze(idx);
+                    ui.close_menu();
+                }
+            } else if (track.instrument_id.is_some() || track.kind == TrackKind::Audio) && ui.button("Freeze Track").clicked() {
+                action = TrackHeaderAction::Freeze(idx);
+                ui.close_menu();
+            }
+            if track.instrument_id.is_some() && ui.button("Bounce Loop to Audio").clicked() {
+                action = TrackHeaderAction::BounceLoop(idx);
+                ui.close_menu();
+            }
             ui.separator();
             if ui.button("Add Audio Track").clicked() {
                 action = TrackHeaderAction::AddAudioTrack;
@@ -148,6 +304,15 @@ impl TrackHeadersPanel {
             egui::FontId::proportional(11.0),
             Color32::WHITE,
         );
+        if track.is_frozen() {
+            painter.text(
+                egui::pos2(rect.right() - 4.0, name_y),
+                egui::Align2::RIGHT_TOP,
+                "❄ Frozen",
+                egui::FontId::proportional(10.0),
+                Color32::from_rgb(140, 200, 255),
+            );
+        }
 
         // Control buttons row
         let btn_y = rect.top() + 22.0;
@@ -216,6 +381,81 @@ impl TrackHeadersPanel {
         if arm_response.clicked() {
             action = TrackHeaderAction::ToggleArm(idx);
         }
+        btn_x += btn_size + btn_spacing;
+
+        // Monitor mode and input selector (audio tracks)
+        if track.kind == TrackKind::Audio {
+            let monitor_rect = Rect::from_min_size(egui::pos2(btn_x, btn_y), Vec2::new(26.0, btn_size));
+            let monitor_color = match track.monitor {
+                TrackMonitor::Off => Color32::from_gray(70),
+                TrackMonitor::Auto => Color32::from_rgb(60, 100, 140),
+                TrackMonitor::On => Color32::from_rgb(60, 150, 200),
+            };
+            painter.rect_filled(monitor_rect, 2.0, monitor_color);
+            painter.text(
+                monitor_rect.center(),
+                egui::Align2::CENTER_CENTER,
+                track.monitor.label(),
+                egui::FontId::proportional(8.0),
+                Color32::WHITE,
+            );
+            let monitor_response = ui
+                .interact(monitor_rect, ui.id().with(("monitor", idx)), Sense::click())
+                .on_hover_text("Input monitoring through the track's effects: Auto (while armed, when stopped or recording), In (always) or Off");
+            if monitor_response.clicked() {
+                action = TrackHeaderAction::SetMonitor(idx, track.monitor.next());
+            }
+            btn_x += 26.0 + btn_spacing;
+
+            // Click for a menu of channels and stereo pairs
+            let input_rect = Rect::from_min_size(egui::pos2(btn_x, btn_y), Vec2::new(44.0, btn_size));
+            let from_track = matches!(track.input, TrackInput::Track(_));
+            let available = from_track || track.input.fits(self.input_channels);
+            painter.rect_filled(input_rect, 2.0, Color32::from_gray(60));
+            painter.with_clip_rect(input_rect).text(
+                input_rect.center(),
+                egui::Align2::CENTER_CENTER,
+                input_label(track.input, tracks),
+                egui::FontId::proportional(9.0),
+                if available { Color32::WHITE } else { Color32::from_rgb(220, 120, 80) },
+            );
+            let input_response = ui
+                .interact(input_rect, ui.id().with(("input", idx)), Sense::click())
+                .on_hover_text(match (from_track, available) {
+                    (true, _) => "Plays the track's output while monitoring; takes record the mono mix",
+                    (false, true) => "Recording input",
+                    (false, false) => "Input not on this device; records the mono mix",
+                });
+            let popup_id = ui.id().with(("input_menu", idx));
+            if input_response.clicked() {
+                ui.memory_mut(|m| m.toggle_popup(popup_id));
+            }
+            egui::popup_below_widget(ui, popup_id, &input_response, egui::PopupCloseBehavior::CloseOnClick, |ui| {
+                ui.set_min_width(90.0);
+                for input in TrackInput::choices(self.input_channels) {
+                    let label = match input {
+                        TrackInput::MonoMix => "Mono mix".to_string(),
+                        TrackInput::Stereo(_) => format!("{} (stereo)", input.label()),
+                        TrackInput::Mono(_) | TrackInput::Track(_) => input.label(),
+                    };
+                    if ui.selectable_label(track.input == input, label).clicked() {
+                        action = TrackHeaderAction::SetInput(idx, input);
+                    }
+                }
+                ui.separator();
+                for other in tracks.iter().filter(|t| t.id != track.id) {
+                    let input = TrackInput::Track(other.id);
+                    if ui.selectable_label(track.input == input, format!("From {}", other.name)).clicked() {
+                        action = TrackHeaderAction::SetInput(idx, input);
+                    }
+                }
+            });
+        }
+
+        // Short rows keep the name and buttons, dropping the controls that don't fit
+        if rect.height() < 56.0 {
+            return action;
+        }
 
         // Volume slider (horizontal at bottom)
         let vol_y = rect.top() + 44.0;
@@ -238,6 +478,10 @@ impl TrackHeadersPanel {
             }
         }
 
+        if rect.height() < 68.0 {
+            return action;
+        }
+
         // Pan slider (horizontal below volume)
         let pan_y = rect.top() + 58.0;
         let pan_rect = Rect::from_min_size(
@@ -248,6 +492,14 @@ impl TrackHeadersPanel {
 
         let pan_center = pan_rect.center().x;
         let pan_pos = pan_center + (track.pan * (vol_width / 2.0 - 4.0));
+        if track.pan_mode == PanMode::Stereo {
+            // Where the two sides of the signal sit
+            let spread = track.width.clamp(0.0, 1.0);
+            let side = |p: f32| pan_center + (p.clamp(-1.0, 1.0) * (vol_width / 2.0 - 4.0));
+            let span = Rect::from_x_y_ranges(side(track.pan - spread)..=side(track.pan + spread), pan_rect.y_range())
+                .shrink2(Vec2::new(0.0, 2.0));
+            painter.rect_filled(span, 1.0, Color32::from_rgb(60, 60, 100));
+        }
         painter.rect_filled(
             Rect::from_center_size(egui::pos2(pan_pos, pan_rect.center().y), Vec2::new(6.0, 6.0)),
             2.0,
@@ -261,11 +513,88 @@ impl TrackHeadersPanel {
                 action = TrackHeaderAction::SetPan(idx, pan);
             }
         }
+        pan_response.context_menu(|ui| {
+            for mode in [PanMode::Balance, PanMode::Stereo] {
+                if ui.radio(track.pan_mode == mode, mode.label()).clicked() {
+                    action = TrackHeaderAction::SetPanMode(idx, mode);
+                }
+            }
+            ui.add_enabled_ui(track.pan_mode == PanMode::Stereo, |ui| {
+                let mut width = track.width;
+                if ui.add(egui::Slider::new(&mut width, 0.0..=1.0).text("Width")).changed() {
+                    action = TrackHeaderAction::SetWidth(idx, width);
+                }
+            });
+            ui.separator();
+            ui.label("Pan law (all tracks)");
+            for law in PanLaw::ALL {
+                if ui.radio(self.pan_law == law, law.label()).clicked() {
+                    action = TrackHeaderAction::SetPanLaw(law);
+                    ui.close_menu();
+                }
+            }
+        });
+
+        if rect.height() < 80.0 {
+            return action;
+        }
+
+        // Output selector (below pan): master, another track, or a hardware output pair
+        let output_rect = Rect::from_min_size(egui::pos2(rect.left() + 4.0, rect.top() + 68.0), Vec2::new(vol_width, 10.0));
+        painter.with_clip_rect(output_rect).text(
+            output_rect.left_center(),
+            egui::Align2::LEFT_CENTER,
+            format!("→ {}", output_label(track.output, tracks)),
+            egui::FontId::proportional(8.0),
+            Color32::from_gray(170),
+        );
+        let output_response = ui
+            .interact(output_rect, ui.id().with(("output", idx)), Sense::click())
+            .on_hover_text("Where the track goes after its effects");
+        let popup_id = ui.id().with(("output_menu", idx));
+        if output_response.clicked() {
+            ui.memory_mut(|m| m.toggle_popup(popup_id));
+        }
+        egui::popup_below_widget(ui, popup_id, &output_response, egui::PopupCloseBehavior::CloseOnClick, |ui| {
+            ui.set_min_width(90.0);
+            if ui.selectable_label(track.output == TrackOutput::Master, "Master").clicked() {
+                action = TrackHeaderAction::SetOutput(idx, TrackOutput::Master);
+            }
+            ui.separator();
+            for other in tracks.iter().filter(|t| t.id != track.id) {
+                let output = TrackOutput::Track(other.id);
+                if ui.selectable_label(track.output == output, other.name.as_str()).clicked() {
+                    action = TrackHeaderAction::SetOutput(idx, output);
+                }
+            }
+            ui.separator();
+            for output in TrackOutput::hardware_choices(self.output_channels) {
+                if ui.selectable_label(track.output == output, output.label()).clicked() {
+                    action = TrackHeaderAction::SetOutput(idx, output);
+                }
+            }
+        });
 
         action
     }
 }
 
+/// Input name for the header, with the source track's name for track inputs
+fn input_label(input: TrackInput, tracks: &[Track]) -> String {
+    match input {
+        TrackInput::Track(id) => tracks.iter().find(|t| t.id == id).map_or(input.label(), |t| t.name.clone()),
+        _ => input.label(),
+    }
+}
+
+/// Output name for the header, with the destination track's name for track outputs
+fn output_label(output: TrackOutput, tracks: &[Track]) -> String {
+    match output {
+        TrackOutput::Track(id) => tracks.iter().find(|t| t.id == id).map_or(output.label(), |t| t.name.clone()),
+        _ => output.label(),
+    }
+}
+
 impl Default for TrackHeadersPanel {
     fn default() -> Self {
         Self::new()
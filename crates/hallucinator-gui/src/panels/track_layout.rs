@@ -0,0 +1,81 @@
+//! Vertical layout of the track rows, shared by the track headers column and the arrange view
+
+use std::collections::HashMap;
+
+use egui::{Rect, Vec2};
+use hallucinator_core::{Track, TrackId};
+
+/// Row height of a track that hasn't been resized, before vertical zoom
+const DEFAULT_HEIGHT: f32 = 80.0;
+/// Row height limits, after vertical zoom
+const MIN_HEIGHT: f32 = 40.0;
+const MAX_HEIGHT: f32 = 320.0;
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 3.0;
+
+/// Track row heights, vertical zoom and scroll
+pub struct TrackLayout {
+    /// Heights of tracks resized by their grip, before vertical zoom
+    heights: HashMap<TrackId, f32>,
+    /// Vertical zoom applied to every row
+    zoom: f32,
+    /// Pixels the rows are scrolled up by
+    pub scroll: f32,
+}
+
+impl TrackLayout {
+    pub fn new() -> Self {
+        Self { heights: HashMap::new(), zoom: 1.0, scroll: 0.0 }
+    }
+
+    /// On-screen height of a track's row
+    pub fn height(&self, id: TrackId) -> f32 {
+        (self.heights.get(&id).copied().unwrap_or(DEFAULT_HEIGHT) * self.zoom).clamp(MIN_HEIGHT, MAX_HEIGHT)
+    }
+
+    /// Height of a row for a track not yet added (e.g. where a dropped sample creates one)
+    pub fn new_row_height(&self) -> f32 {
+        (DEFAULT_HEIGHT * self.zoom).clamp(MIN_HEIGHT, MAX_HEIGHT)
+    }
+
+    /// Set a track's on-screen row height
+    pub fn resize(&mut self, id: TrackId, height: f32) {
+        self.heights.insert(id, height.clamp(MIN_HEIGHT, MAX_HEIGHT) / self.zoom);
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Scale every row, keeping the row at the top of the view in place
+    pub fn set_zoom(&mut self, zoom: f32) {
+        let zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.scroll *= zoom / self.zoom;
+        self.zoom = zoom;
+    }
+
+    /// Screen rect of each track's row, the first starting at the top of `area` when not scrolled
+    pub fn rows(&self, area: Rect, tracks: &[Track]) -> Vec<Rect> {
+        let mut top = area.top() - self.scroll;
+        tracks.iter()
+            .map(|track| {
+                let height = self.height(track.id);
+                let row = Rect::from_min_size(egui::pos2(area.left(), top), Vec2::new(area.width(), height));
+                top += height;
+                row
+            })
+            .collect()
+    }
+
+    /// Keep the scroll between the first row at the top and the last at the bottom of a view
+    pub fn clamp_scroll(&mut self, tracks: &[Track], view_height: f32) {
+        let content: f32 = tracks.iter().map(|t| self.height(t.id)).sum();
+        self.scroll = self.scroll.clamp(0.0, (content - view_height).max(0.0));
+    }
+}
+
+impl Default for TrackLayout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -4,25 +4,39 @@ use std::sync::Arc;
 use std::sync::atomic::Ordering;
 
 use egui::{Ui, RichText, Color32, Rect, Stroke, Vec2, Sense};
-use hallucinator_services::{AudioEngine, EngineState, InputMonitor, MeterState};
+use hallucinator_services::loudness::SILENCE_LUFS;
+use hallucinator_services::{AudioEffect, AudioEngine, ClockSync, EngineState, InputMonitor, LoudnessState, MeterState, MidiClockSender};
 
 /// Actions that can be triggered from transport
 pub enum TransportAction {
     None,
     StartRecording,
     StopRecording,
+    SetClockSync(ClockSync),
+    /// Stream takes to WAV files (saved to the config)
+    SetRecordToDisk(bool),
+    /// Samples trimmed from takes to make up for round-trip latency (saved to the config)
+    SetLatencyOffset(u32),
+    /// Measure the round-trip latency with a pulse through a loopback cable
+    CalibrateLatency,
 }
 
 pub struct TransportPanel {
     bpm_text: String,
     display_peak: f32,
+    /// Record takes straight to disk instead of into memory
+    pub record_to_disk: bool,
+    /// Recording latency compensation in samples
+    pub latency_offset: u32,
 }
 
 impl TransportPanel {
-    pub fn new() -> Self {
+    pub fn new(record_to_disk: bool, latency_offset: u32) -> Self {
         Self {
             bpm_text: "120.0".to_string(),
             display_peak: 0.0,
+            record_to_disk,
+            latency_offset,
         }
     }
 
@@ -33,6 +47,7 @@ impl TransportPanel {
         state: &Arc<EngineState>,
         monitor: &mut InputMonitor,
         meter_state: &Arc<MeterState>,
+        clock_sync: &ClockSync,
     ) -> TransportAction {
         let mut action = TransportAction::None;
 
@@ -79,7 +94,36 @@ impl TransportPanel {
                     TransportAction::StartRecording
                 };
             }
-            rec_btn.on_hover_text(if is_recording { "Stop recording" } else { "Start recording" });
+            rec_btn.context_menu(|ui| {
+                let toggle = ui.add_enabled(!is_recording, egui::Checkbox::new(&mut self.record_to_disk, "Record to disk"));
+                if toggle.on_hover_text("Stream takes to Broadcast WAV files as they are recorded").changed() {
+                    action = TransportAction::SetRecordToDisk(self.record_to_disk);
+                }
+
+                ui.separator();
+                let ms = self.latency_offset as f64 * 1000.0 / monitor.sample_rate() as f64;
+                ui.horizontal(|ui| {
+                    ui.label("Latency compensation");
+                    let drag = egui::DragValue::new(&mut self.latency_offset).range(0..=48_000).suffix(" smp");
+                    if ui.add(drag).on_hover_text(format!("{ms:.1} ms trimmed from the start of each take")).changed() {
+                        action = TransportAction::SetLatencyOffset(self.latency_offset);
+                    }
+                });
+                let calibrate = ui.add_enabled(!is_recording, egui::Button::new("Calibrate (loopback)"));
+                if calibrate
+                    .on_hover_text("Cable an output to the armed track's input, then click: a pulse is played and timed on its way back")
+                    .clicked()
+                {
+                    action = TransportAction::CalibrateLatency;
+                    ui.close_menu();
+                }
+            });
+            let disk = if self.record_to_disk { " to disk" } else { "" };
+            rec_btn.on_hover_text(if is_recording {
+                "Stop recording".to_string()
+            } else {
+                format!("Start recording{disk} (right-click for options)")
+            });
 
             ui.separator();
 
@@ -97,6 +141,19 @@ impl TransportPanel {
                 ui.label(RichText::new("REC").color(Color32::RED).strong());
             }
 
+            // Metronome toggle
+            let click_on = engine.metronome_enabled();
+            let click_color = if click_on {
+                Color32::from_rgb(100, 200, 100)
+            } else {
+                Color32::from_gray(150)
+            };
+            let click_btn = ui.button(RichText::new("Click").color(click_color));
+            if click_btn.clicked() {
+                engine.set_metronome(!click_on);
+            }
+            click_btn.on_hover_text("Toggle metronome (level and cue routing in View → Preferences)");
+
             ui.separator();
 
             // Monitor controls
@@ -127,14 +184,45 @@ impl TransportPanel {
             }
             pass_btn.on_hover_text("Toggle monitor pass-through");
 
+            // Input gate toggle (right-click for settings)
+            let gate_on = monitor.with_gate(|g| !g.is_bypassed()).unwrap_or(false);
+            let gate_color = if gate_on {
+                Color32::from_rgb(100, 200, 100)
+            } else {
+                Color32::from_gray(150)
+            };
+            let gate_btn = ui.button(RichText::new("Gate").color(gate_color));
+            if gate_btn.clicked() {
+                monitor.with_gate(|g| g.set_bypass(gate_on));
+            }
+            gate_btn.context_menu(|ui| {
+                let params = monitor.with_gate(|g| g.get_params()).unwrap_or_default();
+                for param in params {
+                    let mut value = param.value;
+                    let slider = egui::Slider::new(&mut value, param.min..=param.max)
+                        .text(format!("{} {}", param.name, param.unit));
+                    if ui.add(slider).changed() {
+                        monitor.with_gate(|g| g.set_param(&param.name, value));
+                    }
+                }
+            });
+            gate_btn.on_hover_text("Toggle input noise gate (right-click for settings)");
+
             // VU Meter
             self.draw_meter(ui, meter_state, is_monitoring);
 
             ui.separator();
 
-            // BPM
+            // Master loudness and correlation
+            Self::draw_loudness(ui, &state.loudness);
+
+            ui.separator();
+
+            // BPM (read-only while following external clock)
             ui.label("BPM:");
-            let response = ui.add(
+            let following = *clock_sync == ClockSync::Receive;
+            let response = ui.add_enabled(
+                !following,
                 egui::TextEdit::singleline(&mut self.bpm_text)
                     .desired_width(50.0)
             );
@@ -146,6 +234,16 @@ impl TransportPanel {
                     });
                 }
             }
+            // Reflect tempo changes made elsewhere (clock, OSC) while not editing
+            if !response.has_focus()
+                && let Some(bpm) = engine.with_timeline(|t| t.transport.bpm)
+            {
+                self.bpm_text = format!("{:.1}", bpm);
+            }
+
+            if let Some(sync) = Self::clock_sync_menu(ui, clock_sync) {
+                action = TransportAction::SetClockSync(sync);
+            }
 
             // Time signature
             let (num, denom) = engine.with_timeline(|t| {
@@ -173,6 +271,48 @@ impl TransportPanel {
         action
     }
 
+    /// MIDI clock sync menu: internal, send to an output port, or follow input
+    fn clock_sync_menu(ui: &mut Ui, current: &ClockSync) -> Option<ClockSync> {
+        let mut selected = None;
+        let label = match current {
+            ClockSync::Internal => "Int",
+            ClockSync::Send(_) => "Clock Out",
+            ClockSync::Receive => "Ext",
+        };
+        ui.menu_button(label, |ui| {
+            if ui.selectable_label(*current == ClockSync::Internal, "Internal").clicked() {
+                selected = Some(ClockSync::Internal);
+                ui.close_menu();
+            }
+            if ui.selectable_label(*current == ClockSync::Receive, "Follow MIDI clock").clicked() {
+                selected = Some(ClockSync::Receive);
+                ui.close_menu();
+            }
+            ui.separator();
+            ui.label("Send MIDI clock to");
+            match MidiClockSender::list_ports() {
+                Ok(ports) if !ports.is_empty() => {
+                    for port in ports {
+                        let active = matches!(current, ClockSync::Send(p) if p.id == port.id);
+                        if ui.selectable_label(active, &port.name).clicked() {
+                            selected = Some(ClockSync::Send(port));
+                            ui.close_menu();
+                        }
+                    }
+                }
+                Ok(_) => {
+                    ui.weak("No MIDI outputs");
+                }
+                Err(e) => {
+                    ui.weak(e.to_string());
+                }
+            }
+        })
+        .response
+        .on_hover_text("Tempo sync");
+        selected
+    }
+
     fn draw_meter(&mut self, ui: &mut Ui, meter_state: &Arc<MeterState>, is_monitoring: bool) {
         let peak = meter_state.peak();
         let clipped = meter_state.is_clipped();
@@ -258,6 +398,48 @@ impl TransportPanel {
         ui.label(format!("{:+.0}", peak_db));
     }
 
+    fn draw_loudness(ui: &mut Ui, loudness: &LoudnessState) {
+        let format_lufs = |lufs: f32| {
+            if lufs <= SILENCE_LUFS { "-inf".to_string() } else { format!("{:.1}", lufs) }
+        };
+
+        let readout = ui.add(
+            egui::Label::new(RichText::new(format!(
+                "M {} S {} I {} LUFS",
+                format_lufs(loudness.momentary()),
+                format_lufs(loudness.short_term()),
+                format_lufs(loudness.integrated()),
+            )).monospace())
+            .sense(Sense::click()),
+        );
+        if readout.clicked() {
+            loudness.reset();
+        }
+        readout.on_hover_text("Master loudness (EBU R128): momentary, short-term, integrated. Click to reset integrated.");
+
+        // Correlation: -1 (left) .. +1 (right), red when out of phase
+        let correlation = loudness.correlation().clamp(-1.0, 1.0);
+        let (rect, response) = ui.allocate_exact_size(Vec2::new(60.0, 14.0), Sense::hover());
+        let painter = ui.painter();
+        painter.rect_filled(rect, 2.0, Color32::from_gray(25));
+        painter.line_segment(
+            [egui::pos2(rect.center().x, rect.top()), egui::pos2(rect.center().x, rect.bottom())],
+            Stroke::new(1.0, Color32::from_gray(60)),
+        );
+        let x = rect.center().x + correlation * rect.width() * 0.5;
+        let color = if correlation < 0.0 {
+            Color32::from_rgb(220, 60, 60)
+        } else {
+            Color32::from_rgb(50, 200, 50)
+        };
+        painter.rect_filled(
+            Rect::from_center_size(egui::pos2(x, rect.center().y), Vec2::new(3.0, rect.height())),
+            1.0,
+            color,
+        );
+        response.on_hover_text(format!("Phase correlation {:+.2}", correlation));
+    }
+
     fn linear_to_db(linear: f32) -> f32 {
         if linear < 0.00001 {
             return -60.0;
@@ -0,0 +1,78 @@
+//! Horizontal zoom commands shared by the arrange view and the piano roll: step zoom and
+//! fitting the whole song (or clip), the loop or the selection into the view
+
+use egui::Ui;
+
+use super::keymap::Command;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ZoomCommand {
+    ZoomIn,
+    ZoomOut,
+    /// Everything: the song in the arrange view, the clip in the piano roll
+    FitAll,
+    FitLoop,
+    FitSelection,
+}
+
+impl ZoomCommand {
+    pub const ALL: [Self; 5] = [Self::ZoomIn, Self::ZoomOut, Self::FitAll, Self::FitLoop, Self::FitSelection];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::ZoomIn => "Zoom In",
+            Self::ZoomOut => "Zoom Out",
+            Self::FitAll => "Fit All",
+            Self::FitLoop => "Fit Loop",
+            Self::FitSelection => "Fit Selection",
+        }
+    }
+
+    /// Keymap command the zoom command's shortcut is bound to
+    pub fn command(self) -> Command {
+        match self {
+            Self::ZoomIn => Command::ZoomIn,
+            Self::ZoomOut => Command::ZoomOut,
+            Self::FitAll => Command::FitAll,
+            Self::FitLoop => Command::FitLoop,
+            Self::FitSelection => Command::FitSelection,
+        }
+    }
+
+    /// Take a pressed zoom shortcut out of the input so no other view acts on it
+    pub fn consume(ui: &Ui) -> Option<Self> {
+        let command = Command::consume_any(ui.ctx(), &Self::ALL.map(Self::command))?;
+        Self::ALL.into_iter().find(|cmd| cmd.command() == command)
+    }
+
+    /// Button for the command with its shortcut; true when clicked
+    pub fn menu_button(self, ui: &mut Ui, enabled: bool) -> bool {
+        let shortcut = self.command().shortcut_text(ui.ctx());
+        let clicked = ui
+            .add_enabled(enabled, egui::Button::new(self.label()).shortcut_text(shortcut))
+            .clicked();
+        if clicked {
+            ui.close_menu();
+        }
+        clicked
+    }
+
+    /// Zoom factor of a step zoom command
+    pub fn step_factor(self) -> Option<f32> {
+        match self {
+            Self::ZoomIn => Some(1.25),
+            Self::ZoomOut => Some(0.8),
+            _ => None,
+        }
+    }
+}
+
+/// Pixels per beat and scroll offset (in beats) that show `start..end` beats across
+/// `width` pixels with a small margin, centred when the zoom limit leaves room
+pub fn fit_range(start: f64, end: f64, width: f32, min_pixels_per_beat: f32, max_pixels_per_beat: f32) -> (f32, f64) {
+    let span = (end - start).max(1e-3);
+    let pixels_per_beat = (width / (span as f32 * 1.05)).clamp(min_pixels_per_beat, max_pixels_per_beat);
+    let beats_visible = (width / pixels_per_beat) as f64;
+    let scroll = (start - (beats_visible - span) / 2.0).max(0.0);
+    (pixels_per_beat, scroll)
+}
@@ -0,0 +1,78 @@
+//! Headless render: `hallucinator --render <project> --out <mix.wav>` opens a saved project
+//! without a window or audio device and writes its mix to a 32-bit float WAV file. Devices
+//! that can't be loaded fail the render unless `--allow-missing` is given.
+
+use std::path::Path;
+
+use hallucinator_services::wav_writer::WavWriter;
+use hallucinator_services::{AudioEngine, Project, Vst3Scanner, PROJECT_EXTENSION};
+
+pub const RENDER_ARG: &str = "--render";
+const OUT_ARG: &str = "--out";
+const ALLOW_MISSING_ARG: &str = "--allow-missing";
+
+/// Seconds rendered past the end of the song, for releases and effect tails
+const RENDER_TAIL_SECS: f64 = 2.0;
+
+/// Render the project named in `args` (the arguments after `RENDER_ARG`) and return the
+/// process exit code
+pub fn run(args: &[String]) -> i32 {
+    let mut project_path = None;
+    let mut out_path = None;
+    let mut allow_missing = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            OUT_ARG if out_path.is_none() => out_path = args.next(),
+            ALLOW_MISSING_ARG => allow_missing = true,
+            path if project_path.is_none() && !path.starts_with("--") => project_path = Some(path),
+            _ => return usage(),
+        }
+    }
+    let (Some(project_path), Some(out_path)) = (project_path, out_path) else { return usage() };
+    match render(Path::new(project_path), Path::new(out_path), allow_missing) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Failed to render {}: {}", project_path, e);
+            1
+        }
+    }
+}
+
+fn usage() -> i32 {
+    eprintln!("usage: hallucinator {} <project.{}> {} <mix.wav> [{}]", RENDER_ARG, PROJECT_EXTENSION, OUT_ARG, ALLOW_MISSING_ARG);
+    2
+}
+
+fn render(project_path: &Path, out_path: &Path, allow_missing: bool) -> Result<(), String> {
+    let project = Project::load(project_path).map_err(|e| e.to_string())?;
+    let sample_rate = project.timeline.transport.sample_rate;
+
+    // VST3 devices are found by a scan of the default plugin folders
+    let scanner = Vst3Scanner::new().map(|mut scanner| {
+        if let Err(e) = scanner.scan() {
+            tracing::warn!("VST3 scan found no plugins: {}", e);
+        }
+        scanner
+    });
+    let devices = project.load_devices(scanner.as_ref().ok(), sample_rate as f32);
+    if !devices.missing.is_empty() {
+        let missing = devices.missing.join(", ");
+        if !allow_missing {
+            return Err(format!("devices couldn't be loaded: {} (pass {} to render without them)", missing, ALLOW_MISSING_ARG));
+        }
+        tracing::warn!("Rendering without devices that couldn't be loaded: {}", missing);
+    }
+
+    let engine = AudioEngine::new(sample_rate);
+    engine.state().open_project(project.timeline, devices);
+    let tail = (RENDER_TAIL_SECS * sample_rate as f64) as u64;
+    let samples = engine.render_song(tail).map_err(|e| e.to_string())?;
+
+    let description = project_path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut writer = WavWriter::create(out_path, sample_rate, 2, 0, &description).map_err(|e| e.to_string())?;
+    writer.write(&samples).map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())?;
+    tracing::info!("Rendered {} to {}", project_path.display(), out_path.display());
+    Ok(())
+}
@@ -0,0 +1,55 @@
+//! Last warning or error logged anywhere in the app, kept for the status bar so failures
+//! don't only show up in the terminal
+
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+static LAST: Mutex<Option<LoggedMessage>> = Mutex::new(None);
+
+/// A warning or error and when it was logged
+#[derive(Clone, Debug)]
+pub struct LoggedMessage {
+    pub level: Level,
+    pub text: String,
+    pub at: Instant,
+}
+
+/// The last warning or error logged, if any
+pub fn last() -> Option<LoggedMessage> {
+    LAST.lock().ok()?.clone()
+}
+
+/// Tracing layer keeping the last warning or error for `last`
+pub struct StatusLog;
+
+impl<S: Subscriber> Layer<S> for StatusLog {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let level = *event.metadata().level();
+        // More verbose levels compare greater
+        if level > Level::WARN {
+            return;
+        }
+        let mut message = MessageVisitor::default();
+        event.record(&mut message);
+        if let Ok(mut last) = LAST.lock() {
+            *last = Some(LoggedMessage { level, text: message.0, at: Instant::now() });
+        }
+    }
+}
+
+/// Picks the formatted message out of an event's fields
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{:?}", value);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Passive analysis effect: spectrum and oscilloscope taps for the GUI
+
+use std::fmt;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use realfft::num_complex::Complex;
+use realfft::{RealFftPlanner, RealToComplex};
+
+use super::{AudioEffect, EffectParam};
+
+/// FFT length used for the spectrum (bins = FFT_SIZE / 2)
+pub const FFT_SIZE: usize = 2048;
+/// Scope history length in samples (power of two)
+pub const SCOPE_SIZE: usize = 4096;
+
+/// Floor for published spectrum magnitudes
+const MIN_DB: f32 = -120.0;
+
+/// Lock-free analysis data shared between the audio thread and the GUI
+///
+/// The scope is a single-producer ring of atomic samples; readers copy the
+/// most recent window without blocking the writer. Spectrum bins are
+/// overwritten in place once per hop, so a reader may see a frame mid-update,
+/// which is harmless for display.
+pub struct AnalyzerTap {
+    scope: Box<[AtomicU32]>,
+    scope_write: AtomicUsize,
+    spectrum: Box<[AtomicU32]>,
+    spectrum_frame: AtomicU64,
+    sample_rate: AtomicU32,
+}
+
+impl AnalyzerTap {
+    fn new(sample_rate: f32) -> Self {
+        Self {
+            scope: (0..SCOPE_SIZE).map(|_| AtomicU32::new(0)).collect(),
+            scope_write: AtomicUsize::new(0),
+            spectrum: (0..FFT_SIZE / 2).map(|_| AtomicU32::new(MIN_DB.to_bits())).collect(),
+            spectrum_frame: AtomicU64::new(0),
+            sample_rate: AtomicU32::new(sample_rate.to_bits()),
+        }
+    }
+
+    fn push_scope(&self, sample: f32) {
+        let pos = self.scope_write.load(Ordering::Relaxed);
+        self.scope[pos & (SCOPE_SIZE - 1)].store(sample.to_bits(), Ordering::Relaxed);
+        self.scope_write.store(pos.wrapping_add(1), Ordering::Release);
+    }
+
+    /// Copy the most recent `out.len()` scope samples (oldest first)
+    pub fn read_scope(&self, out: &mut [f32]) {
+        let len = out.len().min(SCOPE_SIZE);
+        let end = self.scope_write.load(Ordering::Acquire);
+        let start = end.wrapping_sub(len);
+        for (i, sample) in out.iter_mut().take(len).enumerate() {
+            let idx = start.wrapping_add(i) & (SCOPE_SIZE - 1);
+            *sample = f32::from_bits(self.scope[idx].load(Ordering::Relaxed));
+        }
+    }
+
+    /// Copy the latest spectrum magnitudes in dB (`FFT_SIZE / 2` bins)
+    pub fn read_spectrum(&self, out: &mut [f32]) {
+        for (bin, value) in out.iter_mut().zip(self.spectrum.iter()) {
+            *bin = f32::from_bits(value.load(Ordering::Relaxed));
+        }
+    }
+
+    /// Incremented each time a new spectrum frame is published
+    pub fn spectrum_frame(&self) -> u64 {
+        self.spectrum_frame.load(Ordering::Acquire)
+    }
+
+    /// Sample rate of the analysed signal (for bin-to-frequency mapping)
+    pub fn sample_rate(&self) -> f32 {
+        f32::from_bits(self.sample_rate.load(Ordering::Relaxed))
+    }
+}
+
+impl fmt::Debug for AnalyzerTap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnalyzerTap")
+            .field("spectrum_frame", &self.spectrum_frame())
+            .field("sample_rate", &self.sample_rate())
+            .finish()
+    }
+}
+
+/// Pass-through effect that publishes spectrum and scope data to an `AnalyzerTap`
+pub struct AnalyzerEffect {
+    tap: Arc<AnalyzerTap>,
+    fft: Arc<dyn RealToComplex<f32>>,
+    window: Vec<f32>,
+    history: Vec<f32>,
+    history_pos: usize,
+    hop_counter: usize,
+    input: Vec<f32>,
+    output: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    bypassed: bool,
+}
+
+impl AnalyzerEffect {
+    pub fn new(sample_rate: f32) -> Self {
+        let fft = RealFftPlanner::<f32>::new().plan_fft_forward(FFT_SIZE);
+        let window = (0..FFT_SIZE)
+            .map(|i| {
+                let phase = std::f32::consts::TAU * i as f32 / FFT_SIZE as f32;
+                0.5 - 0.5 * phase.cos()
+            })
+            .collect();
+        Self {
+            tap: Arc::new(AnalyzerTap::new(sample_rate)),
+            input: fft.make_input_vec(),
+            output: fft.make_output_vec(),
+            scratch: fft.make_scratch_vec(),
+            fft,
+            window,
+            history: vec![0.0; FFT_SIZE],
+            history_pos: 0,
+            hop_counter: 0,
+            bypassed: false,
+        }
+    }
+
+    /// Shared analysis data for the GUI
+    pub fn tap(&self) -> Arc<AnalyzerTap> {
+        Arc::clone(&self.tap)
+    }
+
+    fn push(&mut self, sample: f32) {
+        self.tap.push_scope(sample);
+        self.history[self.history_pos] = sample;
+        self.history_pos = (self.history_pos + 1) % FFT_SIZE;
+
+        self.hop_counter += 1;
+        if self.hop_counter >= FFT_SIZE / 2 {
+            self.hop_counter = 0;
+            self.publish_spectrum();
+        }
+    }
+
+    fn publish_spectrum(&mut self) {
+        // Unroll the history ring (oldest first) into the windowed FFT input
+        for (i, value) in self.input.iter_mut().enumerate() {
+            let idx = (self.history_pos + i) % FFT_SIZE;
+            *value = self.history[idx] * self.window[i];
+        }
+        if self.fft.process_with_scratch(&mut self.input, &mut self.output, &mut self.scratch).is_err() {
+            return;
+        }
+
+        // Hann window coherent gain is 0.5, so a full-scale sine reads 0 dB
+        let norm = 4.0 / FFT_SIZE as f32;
+        for (bin, value) in self.tap.spectrum.iter().zip(self.output.iter()) {
+            let db = (20.0 * (value.norm() * norm).max(1e-9).log10()).max(MIN_DB);
+            bin.store(db.to_bits(), Ordering::Relaxed);
+        }
+        self.tap.spectrum_frame.fetch_add(1, Ordering::Release);
+    }
+}
+
+impl fmt::Debug for AnalyzerEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnalyzerEffect")
+            .field("tap", &self.tap)
+            .field("bypassed", &self.bypassed)
+            .finish()
+    }
+}
+
+impl AudioEffect for AnalyzerEffect {
+    fn name(&self) -> &str { "Analyzer" }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        for &sample in samples.iter() {
+            self.push(sample);
+        }
+    }
+
+    fn process_stereo(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (&l, &r) in left.iter().zip(right.iter()) {
+            self.push((l + r) * 0.5);
+        }
+    }
+
+    fn set_param(&mut self, _name: &str, _value: f32) {}
+
+    fn get_params(&self) -> Vec<EffectParam> {
+        Vec::new()
+    }
+
+    fn set_bypass(&mut self, bypass: bool) { self.bypassed = bypass; }
+    fn is_bypassed(&self) -> bool { self.bypassed }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.tap.sample_rate.store(sample_rate.to_bits(), Ordering::Relaxed);
+    }
+
+    fn analyzer_tap(&self) -> Option<Arc<AnalyzerTap>> {
+        Some(self.tap())
+    }
+}
@@ -0,0 +1,84 @@
+//! Click-free dry/wet blending for effect inserts and instrument bypass
+
+/// Length of a bypass or mix change, in samples (about 10 ms at 48 kHz)
+const RAMP_SAMPLES: f32 = 480.0;
+
+/// How much of a device's processed signal is heard, ramped toward its target
+/// so that bypassing or moving the mix never steps the output
+#[derive(Debug, Clone)]
+pub struct DryWet {
+    /// Wet amount while active (0-1)
+    mix: f32,
+    bypassed: bool,
+    /// Current wet gain
+    wet: f32,
+}
+
+impl Default for DryWet {
+    fn default() -> Self {
+        Self { mix: 1.0, bypassed: false, wet: 1.0 }
+    }
+}
+
+impl DryWet {
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+
+    pub fn is_bypassed(&self) -> bool {
+        self.bypassed
+    }
+
+    pub fn set_bypassed(&mut self, bypassed: bool) {
+        self.bypassed = bypassed;
+    }
+
+    /// Bypassed and fully faded out, so the device needn't run
+    pub fn is_silent(&self) -> bool {
+        self.bypassed && self.wet <= 0.0
+    }
+
+    /// Fully wet with no ramp pending, so the processed signal passes as is
+    pub fn is_wet(&self) -> bool {
+        self.wet >= 1.0 && self.target() >= 1.0
+    }
+
+    fn target(&self) -> f32 {
+        if self.bypassed { 0.0 } else { self.mix }
+    }
+
+    /// Blend processed channels with their dry copies in place, all channels on the same ramp
+    pub fn blend(&mut self, dry: &[&[f32]], wet: &mut [&mut [f32]]) {
+        let frames = wet.iter().map(|c| c.len()).chain(dry.iter().map(|c| c.len())).min().unwrap_or(0);
+        self.ramp(frames, |i, gain| {
+            for (d, w) in dry.iter().zip(wet.iter_mut()) {
+                w[i] = d[i] + (w[i] - d[i]) * gain;
+            }
+        });
+    }
+
+    /// Fade processed channels against silence (an instrument has no dry signal)
+    pub fn fade(&mut self, wet: &mut [&mut [f32]]) {
+        let frames = wet.iter().map(|c| c.len()).min().unwrap_or(0);
+        self.ramp(frames, |i, gain| {
+            for channel in wet.iter_mut() {
+                channel[i] *= gain;
+            }
+        });
+    }
+
+    fn ramp(&mut self, frames: usize, mut apply: impl FnMut(usize, f32)) {
+        let target = self.target();
+        let step = 1.0 / RAMP_SAMPLES;
+        let mut gain = self.wet;
+        for i in 0..frames {
+            gain = if gain < target { (gain + step).min(target) } else { (gain - step).max(target) };
+            apply(i, gain);
+        }
+        self.wet = gain;
+    }
+}
@@ -0,0 +1,168 @@
+//! Hardware insert: the signal goes out an interface output pair and comes back in from an
+//! input pair, so outboard gear (a compressor, a synth's filter) sits in an effect chain
+//!
+//! The effect only swaps buffers with its `InsertPort`; the engine plays the send on the
+//! output pair and fills in the return from the raw interface input each block. The return
+//! lags by the round trip, which the effect reports as its latency so the other tracks are
+//! held back to match. Offline renders (freeze, bounce) have no hardware, so there the
+//! signal passes through untouched.
+
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use fundsp::hacker::db_amp;
+
+use super::{AudioEffect, EffectParam};
+
+/// Highest output/input pair offered (pair 8 = channels 15/16)
+const MAX_PAIR: f32 = 8.0;
+/// Longest round trip accepted, in samples (one second at 48 kHz)
+const MAX_LATENCY: f32 = 48_000.0;
+
+/// Buffers the engine and a hardware insert swap each block
+#[derive(Default)]
+pub struct InsertPort {
+    /// First interface output channel (0-based) the send plays on
+    send_channel: AtomicUsize,
+    /// First interface input channel (0-based) the return is read from
+    return_channel: AtomicUsize,
+    send: Mutex<Option<(Vec<f32>, Vec<f32>)>>,
+    /// Return for the coming block, taken by the insert (None = not running live)
+    ret: Mutex<Option<(Vec<f32>, Vec<f32>)>>,
+}
+
+impl InsertPort {
+    pub fn send_channel(&self) -> usize {
+        self.send_channel.load(Ordering::Relaxed)
+    }
+
+    /// Fill the return from a block of raw interleaved interface input (None = no input
+    /// running, so the return is silent)
+    pub fn set_return(&self, input: Option<(&[f32], usize)>, num_frames: usize) {
+        let mut left = vec![0.0; num_frames];
+        let mut right = vec![0.0; num_frames];
+        if let Some((samples, channels)) = input.filter(|(_, channels)| *channels > 0) {
+            let first = self.return_channel.load(Ordering::Relaxed);
+            // A pair the device lacks falls back to its first channels (both sides on mono)
+            let (l_ch, r_ch) = if first + 1 < channels { (first, first + 1) } else { (0, channels.min(2) - 1) };
+            for (i, frame) in samples.chunks(channels).take(num_frames).enumerate() {
+                left[i] = frame[l_ch];
+                right[i] = frame[r_ch];
+            }
+        }
+        if let Ok(mut ret) = self.ret.lock() {
+            *ret = Some((left, right));
+        }
+    }
+
+    /// The block the insert sent out since the last call
+    pub fn take_send(&self) -> Option<(Vec<f32>, Vec<f32>)> {
+        self.send.lock().ok()?.take()
+    }
+}
+
+impl fmt::Debug for InsertPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InsertPort")
+            .field("send_channel", &self.send_channel())
+            .field("return_channel", &self.return_channel.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+/// Insert that routes the signal through outboard gear on the audio interface
+#[derive(Debug)]
+pub struct HardwareInsertEffect {
+    /// Output pair (1 = Out 1/2)
+    send_pair: f32,
+    /// Input pair (1 = In 1/2)
+    return_pair: f32,
+    /// Round trip from the send to the return, in samples
+    latency: f32,
+    return_gain_db: f32,
+    port: Arc<InsertPort>,
+    bypassed: bool,
+}
+
+impl HardwareInsertEffect {
+    /// `latency` is the measured round trip of the interface, in samples
+    pub fn new(latency: u32) -> Self {
+        let mut effect = Self {
+            send_pair: 1.0,
+            return_pair: 1.0,
+            latency: (latency as f32).min(MAX_LATENCY),
+            return_gain_db: 0.0,
+            port: Arc::new(InsertPort::default()),
+            bypassed: false,
+        };
+        effect.sync_port();
+        effect
+    }
+
+    fn sync_port(&mut self) {
+        self.port.send_channel.store((self.send_pair as usize - 1) * 2, Ordering::Relaxed);
+        self.port.return_channel.store((self.return_pair as usize - 1) * 2, Ordering::Relaxed);
+    }
+}
+
+impl AudioEffect for HardwareInsertEffect {
+    fn name(&self) -> &str { "Hardware Insert" }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        let mut right = samples.to_vec();
+        self.process_stereo(samples, &mut right);
+        for (s, r) in samples.iter_mut().zip(&right) {
+            *s = (*s + r) * 0.5;
+        }
+    }
+
+    fn process_stereo(&mut self, left: &mut [f32], right: &mut [f32]) {
+        if let Ok(mut send) = self.port.send.lock() {
+            *send = Some((left.to_vec(), right.to_vec()));
+        }
+        let Some((ret_left, ret_right)) = self.port.ret.lock().ok().and_then(|mut ret| ret.take()) else {
+            return;
+        };
+        let gain = db_amp(self.return_gain_db) as f32;
+        for (i, (l, r)) in left.iter_mut().zip(right.iter_mut()).enumerate() {
+            *l = ret_left.get(i).copied().unwrap_or(0.0) * gain;
+            *r = ret_right.get(i).copied().unwrap_or(0.0) * gain;
+        }
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) {
+        match name {
+            "send pair" => self.send_pair = value.round().clamp(1.0, MAX_PAIR),
+            "return pair" => self.return_pair = value.round().clamp(1.0, MAX_PAIR),
+            "latency" => self.latency = value.round().clamp(0.0, MAX_LATENCY),
+            "return gain" => self.return_gain_db = value,
+            _ => return,
+        }
+        self.sync_port();
+    }
+
+    fn get_params(&self) -> Vec<EffectParam> {
+        vec![
+            EffectParam::new("send pair", self.send_pair, 1.0, MAX_PAIR, "").with_default(1.0).with_steps(MAX_PAIR as u32 - 1),
+            EffectParam::new("return pair", self.return_pair, 1.0, MAX_PAIR, "").with_default(1.0).with_steps(MAX_PAIR as u32 - 1),
+            EffectParam::new("latency", self.latency, 0.0, MAX_LATENCY, "smp").with_default(0.0),
+            EffectParam::new("return gain", self.return_gain_db, -24.0, 24.0, "dB").with_default(0.0),
+        ]
+    }
+
+    fn set_bypass(&mut self, bypass: bool) { self.bypassed = bypass; }
+    fn is_bypassed(&self) -> bool { self.bypassed }
+
+    fn latency_samples(&self) -> usize {
+        self.latency as usize
+    }
+
+    fn max_latency_samples(&self) -> usize {
+        MAX_LATENCY as usize
+    }
+
+    fn hardware_insert(&self) -> Option<Arc<InsertPort>> {
+        Some(self.port.clone())
+    }
+}
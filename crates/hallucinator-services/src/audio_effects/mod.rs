@@ -1,17 +1,30 @@
 //! Audio effects chain and built-in effects
 
+pub mod analyzer;
+mod dry_wet;
+mod hardware_insert;
 mod native;
 pub mod native_instruments;
+mod oversampling;
 pub mod vst3;
 
 pub use native::{
-    CompressorEffect, DelayEffect, GainEffect, HighPassEffect, LowPassEffect, ReverbEffect,
+    create_native_effect, CompressorEffect, DelayEffect, GainEffect, GainReductionMeter,
+    GateEffect, HighPassEffect, LimiterEffect, LowPassEffect, ReverbEffect, UtilityEffect,
+    DELAY_DIVISIONS, NATIVE_EFFECTS,
 };
-pub use native_instruments::{Drum808, SampleKit, Sampler};
+pub use analyzer::{AnalyzerEffect, AnalyzerTap};
+pub use dry_wet::DryWet;
+pub use hardware_insert::{HardwareInsertEffect, InsertPort};
+pub use oversampling::{Oversampler, Oversampling};
+pub use native_instruments::{Drum808, SampleKit, Sampler, SignalGenerator, GENERATOR_WAVEFORMS};
 pub use vst3::{
-    NativeWindowHandle, PluginGuiManager, PluginGuiWindow, Vst3Effect, Vst3Error,
-    Vst3GuiError, Vst3Instrument, Vst3PluginInfo, Vst3Scanner,
+    default_vst3_dirs, GuiBackend, InstrumentLoadJob, NativeWindowHandle, OutputBus, ParamGesture, PluginGuiManager,
+    PluginGuiWindow, PluginIo, PluginStateReader, ScanEvent, ScanJob, Vst3Effect, Vst3Error, Vst3GuiError,
+    Vst3InstanceHandle, Vst3Instrument, Vst3PluginInfo, Vst3Scanner, VstPreset, VST_PRESET_EXTENSION,
 };
+#[cfg(target_os = "linux")]
+pub use vst3::{run_plugin_host, PLUGIN_HOST_ARG};
 
 // Re-export drum MIDI constants for UI
 pub use native_instruments::drum808::{
@@ -20,6 +33,9 @@ pub use native_instruments::drum808::{
 };
 
 use std::fmt::Debug;
+use std::sync::Arc;
+
+use hallucinator_core::{MidiControl, PluginState};
 
 /// Audio effect that can process samples in-place
 pub trait AudioEffect: Send + Debug {
@@ -31,6 +47,29 @@ pub trait AudioEffect: Send + Debug {
     fn is_bypassed(&self) -> bool;
     /// Update sample rate for effects that depend on it
     fn set_sample_rate(&mut self, _sample_rate: f32) {}
+    /// Update tempo for effects that sync to the transport
+    fn set_tempo(&mut self, _bpm: f64) {}
+    /// Process planar stereo samples in-place (defaults to a mono sum through `process`)
+    fn process_stereo(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            *l = (*l + *r) * 0.5;
+        }
+        self.process(left);
+        right.copy_from_slice(left);
+    }
+    /// Shared gain reduction telemetry for dynamics effects
+    fn gain_reduction_meter(&self) -> Option<Arc<GainReductionMeter>> { None }
+    /// Shared spectrum/scope data for analysis effects
+    fn analyzer_tap(&self) -> Option<Arc<AnalyzerTap>> { None }
+    /// Samples the effect delays its output by (the engine holds other tracks back to match)
+    fn latency_samples(&self) -> usize { 0 }
+    /// Most `latency_samples` can reach at the current sample rate, e.g. at the top of a
+    /// latency parameter's range (the insert's dry delay line is sized for it)
+    fn max_latency_samples(&self) -> usize { self.latency_samples() }
+    /// Interface send/return buffers for hardware inserts
+    fn hardware_insert(&self) -> Option<Arc<InsertPort>> { None }
+    /// Plugin a VST3 effect was loaded from
+    fn vst3_plugin_info(&self) -> Option<&Vst3PluginInfo> { None }
 }
 
 /// Audio instrument that generates sound from MIDI input
@@ -41,6 +80,9 @@ pub trait AudioInstrument: Send {
     fn queue_note_on(&mut self, pitch: u8, velocity: u8, channel: u8, sample_offset: u32);
     /// Queue a note-off event at the given sample offset
     fn queue_note_off(&mut self, pitch: u8, velocity: u8, channel: u8, sample_offset: u32);
+    /// Queue a controller, pitch bend or aftertouch change at the given sample offset
+    /// (ignored by instruments that don't respond to it)
+    fn queue_control(&mut self, _control: MidiControl, _value: u16, _channel: u8, _sample_offset: u32) {}
     /// Turn off all notes immediately
     fn all_notes_off(&mut self);
     /// Process and return stereo output buffers
@@ -63,6 +105,7 @@ pub enum Instrument {
     Drum808(Drum808),
     Sampler(Sampler),
     SampleKit(SampleKit),
+    SignalGenerator(SignalGenerator),
 }
 
 /// Macro to delegate method calls to all Instrument variants
@@ -75,6 +118,7 @@ macro_rules! delegate_instrument {
                 Self::Drum808(d) => d.$method($($arg),*),
                 Self::Sampler(s) => s.$method($($arg),*),
                 Self::SampleKit(k) => k.$method($($arg),*),
+                Self::SignalGenerator(g) => g.$method($($arg),*),
             }
         }
     };
@@ -86,6 +130,7 @@ macro_rules! delegate_instrument {
                 Self::Drum808(d) => d.$method($($arg),*),
                 Self::Sampler(s) => s.$method($($arg),*),
                 Self::SampleKit(k) => k.$method($($arg),*),
+                Self::SignalGenerator(g) => g.$method($($arg),*),
             }
         }
     };
@@ -97,6 +142,7 @@ macro_rules! delegate_instrument {
                 Self::Drum808(d) => d.$method($($arg),*),
                 Self::Sampler(s) => s.$method($($arg),*),
                 Self::SampleKit(k) => k.$method($($arg),*),
+                Self::SignalGenerator(g) => g.$method($($arg),*),
             }
         }
     };
@@ -106,6 +152,7 @@ impl Instrument {
     delegate_instrument!(name(&self) -> &str);
     delegate_instrument!(queue_note_on(&mut self, pitch: u8, velocity: u8, channel: u8, sample_offset: u32));
     delegate_instrument!(queue_note_off(&mut self, pitch: u8, velocity: u8, channel: u8, sample_offset: u32));
+    delegate_instrument!(queue_control(&mut self, control: MidiControl, value: u16, channel: u8, sample_offset: u32));
     delegate_instrument!(process(&mut self, num_frames: usize) -> (&[f32], &[f32]));
     delegate_instrument!(set_sample_rate(&mut self, sample_rate: f32));
     delegate_instrument!(get_params(&self) -> &[EffectParam]);
@@ -118,6 +165,7 @@ impl Instrument {
             Self::Drum808(d) => { let _ = sample_offset; d.all_notes_off(); }
             Self::Sampler(s) => { let _ = sample_offset; s.all_notes_off(); }
             Self::SampleKit(k) => { let _ = sample_offset; k.all_notes_off(); }
+            Self::SignalGenerator(g) => { let _ = sample_offset; g.all_notes_off(); }
         }
     }
 
@@ -127,6 +175,7 @@ impl Instrument {
             Self::Drum808(_) => true,
             Self::Sampler(_) => false,
             Self::SampleKit(_) => true,
+            Self::SignalGenerator(_) => false,
         }
     }
 
@@ -134,7 +183,141 @@ impl Instrument {
     pub fn vst3_plugin_info(&self) -> Option<&Vst3PluginInfo> {
         match self {
             Self::Vst3(v) => Some(v.plugin_info()),
-            Self::Drum808(_) | Self::Sampler(_) | Self::SampleKit(_) => None,
+            Self::Drum808(_) | Self::Sampler(_) | Self::SampleKit(_) | Self::SignalGenerator(_) => None,
+        }
+    }
+    /// Handle to the VST3 plugin instance, for opening its native editor (only for VST3 instruments)
+    pub fn vst3_instance_handle(&self) -> Option<Vst3InstanceHandle> {
+        match self {
+            Self::Vst3(v) => v.instance_handle(),
+            Self::Drum808(_) | Self::Sampler(_) | Self::SampleKit(_) | Self::SignalGenerator(_) => None,
+        }
+    }
+
+    /// Output buses of a multi-output VST3 instrument, main bus first (empty otherwise)
+    pub fn output_buses(&self) -> &[OutputBus] {
+        match self {
+            Self::Vst3(v) => v.output_buses(),
+            Self::Drum808(_) | Self::Sampler(_) | Self::SampleKit(_) | Self::SignalGenerator(_) => &[],
+        }
+    }
+
+    /// Render an auxiliary output bus separately (only for VST3 instruments)
+    pub fn set_output_enabled(&mut self, bus: usize, enabled: bool) -> Result<(), Vst3Error> {
+        match self {
+            Self::Vst3(v) => v.set_output_enabled(bus, enabled),
+            Self::Drum808(_) | Self::Sampler(_) | Self::SampleKit(_) | Self::SignalGenerator(_) => {
+                Err(Vst3Error::NoSuchOutput(bus))
+            }
+        }
+    }
+
+    /// Audio of an enabled auxiliary output bus from the last `process` call
+    pub fn output_bus(&self, bus: usize) -> Option<(&[f32], &[f32])> {
+        match self {
+            Self::Vst3(v) => v.output_bus(bus),
+            Self::Drum808(_) | Self::Sampler(_) | Self::SampleKit(_) | Self::SignalGenerator(_) => None,
+        }
+    }
+
+    /// Crash restarts of a sandboxed VST3 instrument (None when not sandboxed)
+    pub fn sandbox_restarts(&self) -> Option<u32> {
+        match self {
+            Self::Vst3(v) => v.sandbox_restarts(),
+            Self::Drum808(_) | Self::Sampler(_) | Self::SampleKit(_) | Self::SignalGenerator(_) => None,
+        }
+    }
+
+    /// A new native instrument with the same samples and parameter values (None for VST3
+    /// instruments, which are loaded again and given the original's plugin state)
+    pub fn duplicate_native(&self, sample_rate: f32) -> Option<Self> {
+        let mut copy = match self {
+            Self::Vst3(_) => return None,
+            Self::Drum808(_) => Self::Drum808(Drum808::new(sample_rate)),
+            Self::Sampler(s) => Self::Sampler(s.share_sample()),
+            Self::SampleKit(k) => {
+                let mut kit = SampleKit::new(sample_rate);
+                for (index, slot) in k.slots().iter().enumerate() {
+                    if let Some(slot) = slot {
+                        kit.set_slot(index, slot.name.clone(), Arc::clone(&slot.data));
+                    }
+                }
+                Self::SampleKit(kit)
+            }
+            Self::SignalGenerator(_) => Self::SignalGenerator(SignalGenerator::new(sample_rate)),
+        };
+        for param in self.get_params() {
+            copy.set_param(&param.name, param.value);
+        }
+        Some(copy)
+    }
+
+    /// Reader of the plugin state for capturing it off the audio lock (only for VST3 instruments)
+    pub fn plugin_state_reader(&self) -> Option<PluginStateReader> {
+        match self {
+            Self::Vst3(v) => v.state_reader(),
+            Self::Drum808(_) | Self::Sampler(_) | Self::SampleKit(_) | Self::SignalGenerator(_) => None,
+        }
+    }
+
+    /// Restore plugin state saved with the project (ignored by native instruments)
+    pub fn restore_plugin_state(&mut self, state: &PluginState) -> Result<(), Vst3Error> {
+        match self {
+            Self::Vst3(v) => v.restore_state(state),
+            Self::Drum808(_) | Self::Sampler(_) | Self::SampleKit(_) | Self::SignalGenerator(_) => Ok(()),
+        }
+    }
+
+    /// Capture a `.vstpreset` (only for VST3 instruments)
+    pub fn save_vst_preset(&self) -> Result<VstPreset, Vst3Error> {
+        match self {
+            Self::Vst3(v) => v.save_preset(),
+            Self::Drum808(_) | Self::Sampler(_) | Self::SampleKit(_) | Self::SignalGenerator(_) => {
+                Err(Vst3Error::StateUnsupported)
+            }
+        }
+    }
+
+    /// Apply a `.vstpreset` (only for VST3 instruments)
+    pub fn load_vst_preset(&mut self, preset: &VstPreset) -> Result<(), Vst3Error> {
+        match self {
+            Self::Vst3(v) => v.load_preset(preset),
+            Self::Drum808(_) | Self::Sampler(_) | Self::SampleKit(_) | Self::SignalGenerator(_) => {
+                Err(Vst3Error::PresetMismatch(preset.class_id.clone()))
+            }
+        }
+    }
+
+    /// Program (factory preset) names (only for VST3 instruments)
+    pub fn programs(&self) -> &[String] {
+        match self {
+            Self::Vst3(v) => v.programs(),
+            Self::Drum808(_) | Self::Sampler(_) | Self::SampleKit(_) | Self::SignalGenerator(_) => &[],
+        }
+    }
+
+    /// Currently selected program (only for VST3 instruments)
+    pub fn current_program(&self) -> Option<usize> {
+        match self {
+            Self::Vst3(v) => v.current_program(),
+            Self::Drum808(_) | Self::Sampler(_) | Self::SampleKit(_) | Self::SignalGenerator(_) => None,
+        }
+    }
+
+    /// Select a program (only for VST3 instruments)
+    pub fn set_program(&mut self, index: usize) -> Result<(), Vst3Error> {
+        match self {
+            Self::Vst3(v) => v.set_program(index),
+            Self::Drum808(_) | Self::Sampler(_) | Self::SampleKit(_) | Self::SignalGenerator(_) => {
+                Err(Vst3Error::NoSuchProgram(index))
+            }
+        }
+    }
+
+    /// Refresh the cached value of a parameter changed in the plugin's native editor
+    pub fn sync_param_from_editor(&mut self, index: usize, value: f64) {
+        if let Self::Vst3(v) = self {
+            v.sync_param_from_editor(index, value);
         }
     }
 }
@@ -146,9 +329,14 @@ pub struct EffectParam {
     pub min: f32,
     pub max: f32,
     pub unit: String,
+    /// Factory default value (used by reset-to-default)
+    pub default: f32,
+    /// Number of discrete steps across the range (0 = continuous)
+    pub steps: u32,
 }
 
 impl EffectParam {
+    /// Create a param whose default is its current value; use `with_default` to override
     pub fn new(name: &str, value: f32, min: f32, max: f32, unit: &str) -> Self {
         Self {
             name: name.to_string(),
@@ -156,23 +344,162 @@ impl EffectParam {
             min,
             max,
             unit: unit.to_string(),
+            default: value,
+            steps: 0,
         }
     }
+
+    pub fn with_default(mut self, default: f32) -> Self {
+        self.default = default;
+        self
+    }
+
+    pub fn with_steps(mut self, steps: u32) -> Self {
+        self.steps = steps;
+        self
+    }
+}
+
+/// Effect a copied chain slot is rebuilt from
+#[derive(Debug, Clone)]
+pub enum EffectSource {
+    /// Built-in effect, by its `NATIVE_EFFECTS` id
+    Native(&'static str),
+    Vst3(Vst3PluginInfo),
+}
+
+/// An effect slot taken from a chain, to be rebuilt in another one
+#[derive(Debug, Clone)]
+pub struct EffectSlotCopy {
+    pub source: EffectSource,
+    /// Parameter values by name
+    pub params: Vec<(String, f32)>,
+    pub bypass: bool,
+    pub mix: f32,
+    pub oversampling: Oversampling,
 }
 
 /// Chain of audio effects processed in order
 #[derive(Debug, Default)]
 pub struct EffectChain {
     effects: Vec<Box<dyn AudioEffect>>,
+    /// Host-side state of each effect's slot, in step with `effects`
+    inserts: Vec<Insert>,
     bypass_all: bool,
 }
 
+/// Frames of delayed dry input a slot holds without growing (the largest output buffer size)
+const DRY_BLOCK: usize = 2048;
+
+/// Per-slot processing around an effect: oversampling, bypass and dry/wet
+#[derive(Debug)]
+struct Insert {
+    /// None when the effect runs at the base rate
+    oversampler: Option<Oversampler>,
+    mix: DryWet,
+    /// Input held back by the effect's latency, for blending while not fully wet
+    dry: [DryDelay; 2],
+}
+
+/// Delay line lining a slot's dry input up with its latent effect output
+#[derive(Debug)]
+struct DryDelay {
+    line: Vec<f32>,
+    pos: usize,
+    /// The last block's input, delayed
+    out: Vec<f32>,
+}
+
+impl DryDelay {
+    fn new(max_delay: usize) -> Self {
+        Self { line: vec![0.0; max_delay + 1], pos: 0, out: Vec::with_capacity(DRY_BLOCK) }
+    }
+
+    /// Run a block through the line, `delay` frames long (at most what it was sized for)
+    fn push(&mut self, input: &[f32], delay: usize) {
+        let len = self.line.len();
+        let delay = delay.min(len - 1);
+        self.out.clear();
+        for &x in input {
+            self.line[self.pos] = x;
+            self.out.push(self.line[(self.pos + len - delay) % len]);
+            self.pos = (self.pos + 1) % len;
+        }
+    }
+}
+
+impl Insert {
+    /// Slot for `effect`, with its dry delay sized for the effect's longest latency
+    /// (allocates; call from the control thread)
+    fn new(effect: &dyn AudioEffect, oversampler: Option<Oversampler>) -> Self {
+        let mut insert = Self {
+            oversampler,
+            mix: DryWet::default(),
+            dry: [DryDelay::new(0), DryDelay::new(0)],
+        };
+        insert.size_dry(effect);
+        insert
+    }
+
+    /// Resize the dry delay after the effect's rate or oversampling changed (allocates)
+    fn size_dry(&mut self, effect: &dyn AudioEffect) {
+        let max_delay = effect.max_latency_samples() / self.factor();
+        self.dry = [DryDelay::new(max_delay), DryDelay::new(max_delay)];
+    }
+
+    fn factor(&self) -> usize {
+        self.oversampler.as_ref().map_or(1, |os| os.mode().factor() as usize)
+    }
+
+    /// Effect latency in base-rate frames
+    fn latency(&self, effect: &dyn AudioEffect) -> usize {
+        effect.latency_samples() / self.factor()
+    }
+
+    /// Feed the dry delay; it runs whenever the effect is latent, so it holds current
+    /// audio the moment a mix or bypass ramp starts
+    fn keep_dry(&mut self, effect: &dyn AudioEffect, channels: &[&[f32]]) -> bool {
+        let blend = !self.mix.is_wet();
+        let latency = self.latency(effect);
+        if blend || latency > 0 {
+            for (dry, channel) in self.dry.iter_mut().zip(channels) {
+                dry.push(channel, latency);
+            }
+        }
+        blend
+    }
+
+    fn process(&mut self, effect: &mut dyn AudioEffect, samples: &mut [f32]) {
+        let blend = self.keep_dry(effect, &[samples]);
+        match &mut self.oversampler {
+            Some(os) => os.process(effect, samples),
+            None => effect.process(samples),
+        }
+        if blend {
+            self.mix.blend(&[self.dry[0].out.as_slice()], &mut [samples]);
+        }
+    }
+
+    fn process_stereo(&mut self, effect: &mut dyn AudioEffect, left: &mut [f32], right: &mut [f32]) {
+        let blend = self.keep_dry(effect, &[left, right]);
+        match &mut self.oversampler {
+            Some(os) => os.process_stereo(effect, left, right),
+            None => effect.process_stereo(left, right),
+        }
+        if blend {
+            let [dry_left, dry_right] = &self.dry;
+            self.mix.blend(&[dry_left.out.as_slice(), dry_right.out.as_slice()], &mut [left, right]);
+        }
+    }
+}
+
 impl EffectChain {
     pub fn new() -> Self {
         Self::default()
     }
 
     pub fn add(&mut self, effect: Box<dyn AudioEffect>) {
+        self.inserts.push(Insert::new(effect.as_ref(), None));
         self.effects.push(effect);
     }
 
@@ -180,20 +507,139 @@ impl EffectChain {
         if index >= self.effects.len() {
             return None;
         }
+        self.inserts.remove(index);
         Some(self.effects.remove(index))
     }
 
+    /// Move the effect at `from` to position `to`, along with its bypass, mix and oversampling
+    pub fn move_effect(&mut self, from: usize, to: usize) {
+        if from >= self.effects.len() || to >= self.effects.len() || from == to {
+            return;
+        }
+        let effect = self.effects.remove(from);
+        self.effects.insert(to, effect);
+        let insert = self.inserts.remove(from);
+        self.inserts.insert(to, insert);
+    }
+
     pub fn process(&mut self, samples: &mut [f32]) {
         if self.bypass_all {
             return;
         }
-        for effect in &mut self.effects {
-            if !effect.is_bypassed() {
-                effect.process(samples);
+        for (effect, insert) in self.effects.iter_mut().zip(&mut self.inserts) {
+            if !effect.is_bypassed() && !insert.mix.is_silent() {
+                insert.process(effect.as_mut(), samples);
             }
         }
     }
 
+    pub fn process_stereo(&mut self, left: &mut [f32], right: &mut [f32]) {
+        if self.bypass_all {
+            return;
+        }
+        for (effect, insert) in self.effects.iter_mut().zip(&mut self.inserts) {
+            if !effect.is_bypassed() && !insert.mix.is_silent() {
+                insert.process_stereo(effect.as_mut(), left, right);
+            }
+        }
+    }
+
+    /// Whether the effect at `index` is bypassed (it fades out rather than cutting off)
+    pub fn is_bypassed(&self, index: usize) -> bool {
+        self.inserts.get(index).is_some_and(|i| i.mix.is_bypassed())
+            || self.effects.get(index).is_some_and(|e| e.is_bypassed())
+    }
+
+    /// Bypass the effect at `index`, crossfading to and from its dry input
+    pub fn set_bypass(&mut self, index: usize, bypass: bool) {
+        if let Some(effect) = self.effects.get_mut(index) {
+            effect.set_bypass(false);
+        }
+        if let Some(insert) = self.inserts.get_mut(index) {
+            insert.mix.set_bypassed(bypass);
+        }
+    }
+
+    /// Wet amount of the effect at `index` (0-1)
+    pub fn mix(&self, index: usize) -> f32 {
+        self.inserts.get(index).map_or(1.0, |i| i.mix.mix())
+    }
+
+    pub fn set_mix(&mut self, index: usize, mix: f32) {
+        if let Some(insert) = self.inserts.get_mut(index) {
+            insert.mix.set_mix(mix);
+        }
+    }
+
+    /// Oversampling of the effect at `index`
+    pub fn oversampling(&self, index: usize) -> Oversampling {
+        self.inserts
+            .get(index)
+            .and_then(|i| i.oversampler.as_ref())
+            .map_or(Oversampling::Off, Oversampler::mode)
+    }
+
+    /// Run the effect at `index` at a multiple of `sample_rate` (the chain's base rate)
+    pub fn set_oversampling(&mut self, index: usize, mode: Oversampling, sample_rate: f32) {
+        if self.oversampling(index) == mode {
+            return;
+        }
+        let (Some(effect), Some(insert)) = (self.effects.get_mut(index), self.inserts.get_mut(index)) else {
+            return;
+        };
+        effect.set_sample_rate(sample_rate * mode.factor() as f32);
+        insert.oversampler = (mode != Oversampling::Off).then(|| Oversampler::new(mode));
+        insert.size_dry(effect.as_ref());
+    }
+
+    /// Latency of the effects in use, in samples
+    pub fn latency_samples(&self) -> usize {
+        if self.bypass_all {
+            return 0;
+        }
+        (0..self.effects.len())
+            .filter(|&i| !self.is_bypassed(i))
+            .map(|i| self.inserts[i].latency(self.effects[i].as_ref()))
+            .sum()
+    }
+
+    /// Send/return ports of the chain's hardware inserts
+    pub fn hardware_inserts(&self) -> Vec<Arc<InsertPort>> {
+        self.effects.iter().filter_map(|e| e.hardware_insert()).collect()
+    }
+
+    /// Each effect slot with what it takes to rebuild it elsewhere (new instances are loaded
+    /// by the caller, outside the engine's locks)
+    pub fn slot_copies(&self) -> Vec<EffectSlotCopy> {
+        self.effects.iter().enumerate()
+            .filter_map(|(index, effect)| {
+                let source = match effect.vst3_plugin_info() {
+                    Some(info) => EffectSource::Vst3(info.clone()),
+                    None => EffectSource::Native(NATIVE_EFFECTS.iter().find(|(_, name)| *name == effect.name())?.0),
+                };
+                Some(EffectSlotCopy {
+                    source,
+                    params: effect.get_params().into_iter().map(|p| (p.name, p.value)).collect(),
+                    bypass: self.is_bypassed(index),
+                    mix: self.mix(index),
+                    oversampling: self.oversampling(index),
+                })
+            })
+            .collect()
+    }
+
+    /// Add an effect rebuilt from `copy`, with its parameter values and slot settings
+    pub fn add_copy(&mut self, mut effect: Box<dyn AudioEffect>, copy: &EffectSlotCopy, sample_rate: f32) {
+        for (name, value) in &copy.params {
+            effect.set_param(name, *value);
+        }
+        self.add(effect);
+        let index = self.effects.len() - 1;
+        self.set_bypass(index, copy.bypass);
+        self.set_mix(index, copy.mix);
+        self.set_oversampling(index, copy.oversampling, sample_rate);
+    }
+
     pub fn set_bypass_all(&mut self, bypass: bool) {
         self.bypass_all = bypass;
     }
@@ -220,11 +666,20 @@ impl EffectChain {
 
     pub fn clear(&mut self) {
         self.effects.clear();
+        self.inserts.clear();
     }
 
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        for (effect, insert) in self.effects.iter_mut().zip(&mut self.inserts) {
+            effect.set_sample_rate(sample_rate * insert.factor() as f32);
+            // Latency in frames follows the rate
+            insert.size_dry(effect.as_ref());
+        }
+    }
+
+    pub fn set_tempo(&mut self, bpm: f64) {
         for effect in &mut self.effects {
-            effect.set_sample_rate(sample_rate);
+            effect.set_tempo(bpm);
         }
     }
 }
@@ -1,10 +1,15 @@
 //! Native audio effects using fundsp
 
 use std::cmp::Ord;
+use std::collections::VecDeque;
 use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 
 use fundsp::hacker::*;
 
+use super::analyzer::AnalyzerEffect;
+use super::hardware_insert::HardwareInsertEffect;
 use super::{AudioEffect, EffectParam};
 
 
@@ -44,7 +49,114 @@ impl AudioEffect for GainEffect {
     }
 
     fn get_params(&self) -> Vec<EffectParam> {
-        vec![EffectParam::new("gain", self.gain_db, -60.0, 24.0, "dB")]
+        vec![EffectParam::new("gain", self.gain_db, -60.0, 24.0, "dB").with_default(0.0)]
+    }
+
+    fn set_bypass(&mut self, bypass: bool) { self.bypassed = bypass; }
+    fn is_bypassed(&self) -> bool { self.bypassed }
+}
+
+/// Stereo utility: width, mid/side balance, channel swap, mono and polarity flip
+///
+/// Width scales the side signal (0 = mono, 1 = unchanged, 2 = double width).
+/// The mid/side balance trims one of the two components without boosting the other.
+#[derive(Debug)]
+pub struct UtilityEffect {
+    width: f32,
+    ms_balance: f32,
+    swap: bool,
+    mono: bool,
+    invert_left: bool,
+    invert_right: bool,
+    gain_db: f32,
+    gain_linear: f32,
+    bypassed: bool,
+}
+
+impl UtilityEffect {
+    pub fn new() -> Self {
+        Self {
+            width: 1.0,
+            ms_balance: 0.0,
+            swap: false,
+            mono: false,
+            invert_left: false,
+            invert_right: false,
+            gain_db: 0.0,
+            gain_linear: 1.0,
+            bypassed: false,
+        }
+    }
+
+    fn polarity(invert: bool) -> f32 {
+        if invert { -1.0 } else { 1.0 }
+    }
+}
+
+impl Default for UtilityEffect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioEffect for UtilityEffect {
+    fn name(&self) -> &str { "Utility" }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        // Mono path: only gain and polarity are meaningful
+        let gain = self.gain_linear * Self::polarity(self.invert_left);
+        for sample in samples.iter_mut() {
+            *sample *= gain;
+        }
+    }
+
+    fn process_stereo(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let pol_l = Self::polarity(self.invert_left);
+        let pol_r = Self::polarity(self.invert_right);
+        let mid_gain = (1.0 - self.ms_balance).min(1.0);
+        let side_gain = (1.0 + self.ms_balance).min(1.0) * if self.mono { 0.0 } else { self.width };
+
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let (mut in_l, mut in_r) = (*l * pol_l, *r * pol_r);
+            if self.swap {
+                std::mem::swap(&mut in_l, &mut in_r);
+            }
+
+            let mid = (in_l + in_r) * 0.5 * mid_gain;
+            let side = (in_l - in_r) * 0.5 * side_gain;
+
+            *l = (mid + side) * self.gain_linear;
+            *r = (mid - side) * self.gain_linear;
+        }
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) {
+        match name {
+            "width" => self.width = value.clamp(0.0, 2.0),
+            "ms_balance" => self.ms_balance = value.clamp(-1.0, 1.0),
+            "swap" => self.swap = value >= 0.5,
+            "mono" => self.mono = value >= 0.5,
+            "invert_l" => self.invert_left = value >= 0.5,
+            "invert_r" => self.invert_right = value >= 0.5,
+            "gain" => {
+                self.gain_db = value.clamp(-24.0, 24.0);
+                self.gain_linear = db_amp(self.gain_db);
+            }
+            _ => {}
+        }
+    }
+
+    fn get_params(&self) -> Vec<EffectParam> {
+        let flag = |on: bool| if on { 1.0 } else { 0.0 };
+        vec![
+            EffectParam::new("width", self.width, 0.0, 2.0, "").with_default(1.0),
+            EffectParam::new("ms_balance", self.ms_balance, -1.0, 1.0, "").with_default(0.0),
+            EffectParam::new("swap", flag(self.swap), 0.0, 1.0, "").with_default(0.0),
+            EffectParam::new("mono", flag(self.mono), 0.0, 1.0, "").with_default(0.0),
+            EffectParam::new("invert_l", flag(self.invert_left), 0.0, 1.0, "").with_default(0.0),
+            EffectParam::new("invert_r", flag(self.invert_right), 0.0, 1.0, "").with_default(0.0),
+            EffectParam::new("gain", self.gain_db, -24.0, 24.0, "dB").with_default(0.0),
+        ]
     }
 
     fn set_bypass(&mut self, bypass: bool) { self.bypassed = bypass; }
@@ -95,7 +207,7 @@ impl AudioEffect for HighPassEffect {
     }
 
     fn get_params(&self) -> Vec<EffectParam> {
-        vec![EffectParam::new("cutoff", self.cutoff_hz, 20.0, 2000.0, "Hz")]
+        vec![EffectParam::new("cutoff", self.cutoff_hz, 20.0, 2000.0, "Hz").with_default(80.0)]
     }
 
     fn set_bypass(&mut self, bypass: bool) { self.bypassed = bypass; }
@@ -150,7 +262,7 @@ impl AudioEffect for LowPassEffect {
     }
 
     fn get_params(&self) -> Vec<EffectParam> {
-        vec![EffectParam::new("cutoff", self.cutoff_hz, 200.0, 20000.0, "Hz")]
+        vec![EffectParam::new("cutoff", self.cutoff_hz, 200.0, 20000.0, "Hz").with_default(12000.0)]
     }
 
     fn set_bypass(&mut self, bypass: bool) { self.bypassed = bypass; }
@@ -225,9 +337,9 @@ impl AudioEffect for CompressorEffect {
 
     fn get_params(&self) -> Vec<EffectParam> {
         vec![
-            EffectParam::new("threshold", self.threshold_db, -60.0, 0.0, "dB"),
-            EffectParam::new("attack", self.attack_ms, 0.1, 100.0, "ms"),
-            EffectParam::new("release", self.release_ms, 10.0, 1000.0, "ms"),
+            EffectParam::new("threshold", self.threshold_db, -60.0, 0.0, "dB").with_default(-18.0),
+            EffectParam::new("attack", self.attack_ms, 0.1, 100.0, "ms").with_default(10.0),
+            EffectParam::new("release", self.release_ms, 10.0, 1000.0, "ms").with_default(100.0),
         ]
     }
 
@@ -235,12 +347,471 @@ impl AudioEffect for CompressorEffect {
     fn is_bypassed(&self) -> bool { self.bypassed }
 }
 
-/// Simple delay effect with feedback
+/// Gain reduction telemetry shared between an effect and the GUI
+#[derive(Debug, Default)]
+pub struct GainReductionMeter {
+    reduction_raw: AtomicU32,
+}
+
+impl GainReductionMeter {
+    /// Current gain reduction in dB (0 = none, positive = attenuating)
+    pub fn reduction_db(&self) -> f32 {
+        f32::from_bits(self.reduction_raw.load(Ordering::Relaxed))
+    }
+
+    fn set_reduction_db(&self, db: f32) {
+        self.reduction_raw.store(db.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Longest limiter lookahead; its buffers are sized for this up front
+const MAX_LOOKAHEAD_MS: f32 = 10.0;
+
+/// Lookahead brickwall limiter with true-peak detection
+///
+/// Inter-sample peaks are estimated with 4x Catmull-Rom interpolation. The gain
+/// needed to hold peaks under the ceiling is min-held and averaged over the
+/// lookahead window, so reduction ramps in smoothly and is fully applied by the
+/// time the peak leaves the delay line.
+pub struct LimiterEffect {
+    ceiling_db: f32,
+    input_gain_db: f32,
+    lookahead_ms: f32,
+    release_ms: f32,
+    sample_rate: f32,
+    window: usize,
+    delay_left: Vec<f32>,
+    delay_right: Vec<f32>,
+    delay_pos: usize,
+    history_left: [f32; 3],
+    history_right: [f32; 3],
+    min_window: VecDeque<(u64, f32)>,
+    avg_ring: Vec<f32>,
+    avg_sum: f64,
+    avg_pos: usize,
+    sample_index: u64,
+    gain: f32,
+    meter: Arc<GainReductionMeter>,
+    bypassed: bool,
+}
+
+impl LimiterEffect {
+    pub fn new(ceiling_db: f32, sample_rate: f32) -> Self {
+        let mut effect = Self {
+            ceiling_db: ceiling_db.clamp(-12.0, 0.0),
+            input_gain_db: 0.0,
+            lookahead_ms: 5.0,
+            release_ms: 100.0,
+            sample_rate,
+            window: 1,
+            delay_left: Vec::new(),
+            delay_right: Vec::new(),
+            delay_pos: 0,
+            history_left: [0.0; 3],
+            history_right: [0.0; 3],
+            min_window: VecDeque::new(),
+            avg_ring: Vec::new(),
+            avg_sum: 0.0,
+            avg_pos: 0,
+            sample_index: 0,
+            gain: 1.0,
+            meter: Arc::new(GainReductionMeter::default()),
+            bypassed: false,
+        };
+        effect.rebuild();
+        effect
+    }
+
+    /// Size the buffers for the longest lookahead at the current sample rate (allocates;
+    /// call from the control thread)
+    fn rebuild(&mut self) {
+        let max_window = Self::window_for(MAX_LOOKAHEAD_MS, self.sample_rate);
+        // Two extra samples cover the interpolation segment behind the detector
+        let delay_len = max_window + 2;
+        self.delay_left = vec![0.0; delay_len];
+        self.delay_right = vec![0.0; delay_len];
+        self.delay_pos = 0;
+        self.history_left = [0.0; 3];
+        self.history_right = [0.0; 3];
+        self.min_window = VecDeque::with_capacity(max_window + 1);
+        self.avg_ring = vec![1.0; max_window];
+        self.avg_pos = 0;
+        self.gain = 1.0;
+        self.set_window();
+    }
+
+    fn window_for(lookahead_ms: f32, sample_rate: f32) -> usize {
+        Ord::max((lookahead_ms * sample_rate / 1000.0) as usize, 1)
+    }
+
+    /// Follow a lookahead change within the buffers `rebuild` sized: only the delay read
+    /// point and the averaging span move, so it's safe on the audio thread
+    fn set_window(&mut self) {
+        self.window = Ord::min(Self::window_for(self.lookahead_ms, self.sample_rate), self.avg_ring.len());
+        let len = self.avg_ring.len();
+        self.avg_sum = (1..=self.window)
+            .map(|back| self.avg_ring[(self.avg_pos + len - back) % len] as f64)
+            .sum();
+    }
+
+    /// Latency introduced by the lookahead delay, in samples
+    pub fn latency_samples(&self) -> usize {
+        self.window + 1
+    }
+
+    /// Estimate the true peak around the newest sample and update the history
+    fn true_peak(history: &mut [f32; 3], x: f32) -> f32 {
+        let [p0, p1, p2] = *history;
+        let p3 = x;
+        let mut peak = x.abs();
+        for t in [0.25f32, 0.5, 0.75] {
+            let t2 = t * t;
+            let t3 = t2 * t;
+            let y = 0.5 * (2.0 * p1
+                + (p2 - p0) * t
+                + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+                + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3);
+            peak = peak.max(y.abs());
+        }
+        *history = [p1, p2, p3];
+        peak
+    }
+
+    /// Push a detected peak and return the gain to apply to the delayed sample
+    fn tick_gain(&mut self, peak: f32, ceiling: f32, release_coeff: f32) -> f32 {
+        let target = if peak > ceiling { ceiling / peak } else { 1.0 };
+
+        // Sliding minimum over the lookahead window
+        let index = self.sample_index;
+        self.sample_index += 1;
+        while self.min_window.back().is_some_and(|&(_, g)| g >= target) {
+            self.min_window.pop_back();
+        }
+        self.min_window.push_back((index, target));
+        while self.min_window.front().is_some_and(|&(i, _)| i + self.window as u64 <= index) {
+            self.min_window.pop_front();
+        }
+        let held = self.min_window.front().map_or(1.0, |&(_, g)| g);
+
+        // Moving average turns the held steps into ramps of one window length
+        let len = self.avg_ring.len();
+        self.avg_sum += held as f64 - self.avg_ring[(self.avg_pos + len - self.window) % len] as f64;
+        self.avg_ring[self.avg_pos] = held;
+        self.avg_pos = (self.avg_pos + 1) % len;
+        let smoothed = (self.avg_sum / self.window as f64) as f32;
+
+        self.gain = if smoothed < self.gain {
+            smoothed
+        } else {
+            self.gain + release_coeff * (smoothed - self.gain)
+        };
+        self.gain
+    }
+
+    /// Write `x` at `pos` and read the sample from one window and one sample ago
+    fn delay(buffer: &mut [f32], pos: usize, window: usize, x: f32) -> f32 {
+        buffer[pos] = x;
+        buffer[(pos + buffer.len() - window - 1) % buffer.len()]
+    }
+
+    fn release_coeff(&self) -> f32 {
+        let samples = (self.release_ms * self.sample_rate / 1000.0).max(1.0);
+        1.0 - (-1.0 / samples).exp()
+    }
+
+    /// Gain reduction meter shared with the GUI
+    pub fn meter(&self) -> Arc<GainReductionMeter> {
+        Arc::clone(&self.meter)
+    }
+}
+
+impl fmt::Debug for LimiterEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LimiterEffect")
+            .field("ceiling_db", &self.ceiling_db)
+            .field("lookahead_ms", &self.lookahead_ms)
+            .field("release_ms", &self.release_ms)
+            .field("bypassed", &self.bypassed)
+            .finish()
+    }
+}
+
+impl AudioEffect for LimiterEffect {
+    fn name(&self) -> &str { "Limiter" }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        let input_gain: f32 = db_amp(self.input_gain_db);
+        let ceiling: f32 = db_amp(self.ceiling_db);
+        let release_coeff = self.release_coeff();
+        let mut min_gain = 1.0f32;
+
+        for sample in samples.iter_mut() {
+            let x = *sample * input_gain;
+            let peak = Self::true_peak(&mut self.history_left, x);
+            let gain = self.tick_gain(peak, ceiling, release_coeff);
+            let delayed = Self::delay(&mut self.delay_left, self.delay_pos, self.window, x);
+            self.delay_pos = (self.delay_pos + 1) % self.delay_left.len();
+            *sample = (delayed * gain).clamp(-ceiling, ceiling);
+            min_gain = min_gain.min(gain);
+        }
+
+        self.meter.set_reduction_db(-amp_db(min_gain));
+    }
+
+    fn process_stereo(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let input_gain: f32 = db_amp(self.input_gain_db);
+        let ceiling: f32 = db_amp(self.ceiling_db);
+        let release_coeff = self.release_coeff();
+        let mut min_gain = 1.0f32;
+
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let (xl, xr) = (*l * input_gain, *r * input_gain);
+            // Linked detection keeps the stereo image stable under reduction
+            let peak = Self::true_peak(&mut self.history_left, xl)
+                .max(Self::true_peak(&mut self.history_right, xr));
+            let gain = self.tick_gain(peak, ceiling, release_coeff);
+            let delayed_l = Self::delay(&mut self.delay_left, self.delay_pos, self.window, xl);
+            let delayed_r = Self::delay(&mut self.delay_right, self.delay_pos, self.window, xr);
+            self.delay_pos = (self.delay_pos + 1) % self.delay_left.len();
+            *l = (delayed_l * gain).clamp(-ceiling, ceiling);
+            *r = (delayed_r * gain).clamp(-ceiling, ceiling);
+            min_gain = min_gain.min(gain);
+        }
+
+        self.meter.set_reduction_db(-amp_db(min_gain));
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) {
+        match name {
+            "ceiling" => self.ceiling_db = value.clamp(-12.0, 0.0),
+            "gain" => self.input_gain_db = value.clamp(0.0, 24.0),
+            "lookahead" => {
+                self.lookahead_ms = value.clamp(0.5, MAX_LOOKAHEAD_MS);
+                self.set_window();
+            }
+            "release" => self.release_ms = value.clamp(10.0, 1000.0),
+            _ => {}
+        }
+    }
+
+    fn get_params(&self) -> Vec<EffectParam> {
+        vec![
+            EffectParam::new("ceiling", self.ceiling_db, -12.0, 0.0, "dBTP").with_default(-1.0),
+            EffectParam::new("gain", self.input_gain_db, 0.0, 24.0, "dB").with_default(0.0),
+            EffectParam::new("lookahead", self.lookahead_ms, 0.5, MAX_LOOKAHEAD_MS, "ms").with_default(5.0),
+            EffectParam::new("release", self.release_ms, 10.0, 1000.0, "ms").with_default(100.0),
+        ]
+    }
+
+    fn set_bypass(&mut self, bypass: bool) { self.bypassed = bypass; }
+    fn is_bypassed(&self) -> bool { self.bypassed }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.rebuild();
+    }
+
+    fn gain_reduction_meter(&self) -> Option<Arc<GainReductionMeter>> {
+        Some(self.meter())
+    }
+
+    fn latency_samples(&self) -> usize {
+        LimiterEffect::latency_samples(self)
+    }
+
+    fn max_latency_samples(&self) -> usize {
+        Self::window_for(MAX_LOOKAHEAD_MS, self.sample_rate) + 1
+    }
+}
+
+/// Noise gate / downward expander
+///
+/// Below the threshold the signal is attenuated by `ratio` (expander) down to
+/// at most `range` dB; a high ratio behaves as a hard gate. The gate stays open
+/// for `hold` ms after the level drops, then closes over `release` ms.
+pub struct GateEffect {
+    threshold_db: f32,
+    ratio: f32,
+    attack_ms: f32,
+    hold_ms: f32,
+    release_ms: f32,
+    range_db: f32,
+    sample_rate: f32,
+    envelope: f32,
+    gain: f32,
+    hold_counter: usize,
+    bypassed: bool,
+}
+
+impl GateEffect {
+    pub fn new(threshold_db: f32, sample_rate: f32) -> Self {
+        Self {
+            threshold_db: threshold_db.clamp(-80.0, 0.0),
+            ratio: 20.0,
+            attack_ms: 1.0,
+            hold_ms: 50.0,
+            release_ms: 100.0,
+            range_db: -80.0,
+            sample_rate,
+            envelope: 0.0,
+            gain: 1.0,
+            hold_counter: 0,
+            bypassed: false,
+        }
+    }
+
+    fn time_coeff(ms: f32, sample_rate: f32) -> f32 {
+        let samples = (ms * sample_rate / 1000.0).max(1.0);
+        1.0 - (-1.0 / samples).exp()
+    }
+
+    /// Target gain (linear) for a detected level, ignoring hold and smoothing
+    fn closed_gain(&self, level_db: f32) -> f32 {
+        let below = level_db - self.threshold_db;
+        let reduction_db = (below * (self.ratio - 1.0)).max(self.range_db);
+        db_amp(reduction_db)
+    }
+
+    fn tick(&mut self, detector: f32, detector_release: f32) -> f32 {
+        let level = detector.abs();
+        let coeff = if level > self.envelope { 1.0 } else { detector_release };
+        self.envelope += coeff * (level - self.envelope);
+
+        let level_db = amp_db(self.envelope.max(1e-6));
+        let hold_samples = (self.hold_ms * self.sample_rate / 1000.0) as usize;
+
+        let target = if level_db >= self.threshold_db {
+            self.hold_counter = hold_samples;
+            1.0
+        } else if self.hold_counter > 0 {
+            self.hold_counter -= 1;
+            1.0
+        } else {
+            self.closed_gain(level_db)
+        };
+
+        let time = if target > self.gain { self.attack_ms } else { self.release_ms };
+        self.gain += Self::time_coeff(time, self.sample_rate) * (target - self.gain);
+        self.gain
+    }
+}
+
+impl fmt::Debug for GateEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GateEffect")
+            .field("threshold_db", &self.threshold_db)
+            .field("ratio", &self.ratio)
+            .field("range_db", &self.range_db)
+            .field("bypassed", &self.bypassed)
+            .finish()
+    }
+}
+
+impl AudioEffect for GateEffect {
+    fn name(&self) -> &str { "Gate" }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        let detector_release = Self::time_coeff(10.0, self.sample_rate);
+        for sample in samples.iter_mut() {
+            *sample *= self.tick(*sample, detector_release);
+        }
+    }
+
+    fn process_stereo(&mut self, left: &mut [f32], right: &mut [f32]) {
+        // Linked detection so the stereo image doesn't shift while gating
+        let detector_release = Self::time_coeff(10.0, self.sample_rate);
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let gain = self.tick(l.abs().max(r.abs()), detector_release);
+            *l *= gain;
+            *r *= gain;
+        }
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) {
+        match name {
+            "threshold" => self.threshold_db = value.clamp(-80.0, 0.0),
+            "ratio" => self.ratio = value.clamp(1.0, 20.0),
+            "attack" => self.attack_ms = value.clamp(0.1, 100.0),
+            "hold" => self.hold_ms = value.clamp(0.0, 500.0),
+            "release" => self.release_ms = value.clamp(5.0, 2000.0),
+            "range" => self.range_db = value.clamp(-80.0, 0.0),
+            _ => {}
+        }
+    }
+
+    fn get_params(&self) -> Vec<EffectParam> {
+        vec![
+            EffectParam::new("threshold", self.threshold_db, -80.0, 0.0, "dB").with_default(-50.0),
+            EffectParam::new("ratio", self.ratio, 1.0, 20.0, ":1").with_default(20.0),
+            EffectParam::new("attack", self.attack_ms, 0.1, 100.0, "ms").with_default(1.0),
+            EffectParam::new("hold", self.hold_ms, 0.0, 500.0, "ms").with_default(50.0),
+            EffectParam::new("release", self.release_ms, 5.0, 2000.0, "ms").with_default(100.0),
+            EffectParam::new("range", self.range_db, -80.0, 0.0, "dB").with_default(-80.0),
+        ]
+    }
+
+    fn set_bypass(&mut self, bypass: bool) { self.bypassed = bypass; }
+    fn is_bypassed(&self) -> bool { self.bypassed }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+    }
+}
+
+/// Note-value delay times as (label, length in beats)
+pub const DELAY_DIVISIONS: &[(&str, f64)] = &[
+    ("1/1", 4.0),
+    ("1/2", 2.0),
+    ("1/2D", 3.0),
+    ("1/4", 1.0),
+    ("1/4D", 1.5),
+    ("1/4T", 2.0 / 3.0),
+    ("1/8", 0.5),
+    ("1/8D", 0.75),
+    ("1/8T", 1.0 / 3.0),
+    ("1/16", 0.25),
+    ("1/16D", 0.375),
+    ("1/16T", 1.0 / 6.0),
+    ("1/32", 0.125),
+];
+
+/// One-pole low/high-cut filter applied to a delay feedback path
+#[derive(Debug, Clone, Copy, Default)]
+struct FeedbackFilter {
+    lp_state: f32,
+    hp_state: f32,
+}
+
+impl FeedbackFilter {
+    fn tick(&mut self, input: f32, lp_coeff: f32, hp_coeff: f32) -> f32 {
+        self.lp_state += lp_coeff * (input - self.lp_state);
+        self.hp_state += hp_coeff * (self.lp_state - self.hp_state);
+        self.lp_state - self.hp_state
+    }
+}
+
+/// One-pole smoothing coefficient for a cutoff frequency
+fn one_pole_coeff(cutoff_hz: f32, sample_rate: f32) -> f32 {
+    1.0 - (-std::f32::consts::TAU * cutoff_hz / sample_rate).exp()
+}
+
+/// Stereo delay with tempo sync, ping-pong mode and filtered feedback
 pub struct DelayEffect {
     delay_ms: f32,
     feedback: f32,
     mix: f32,
-    buffer: Vec<f32>,
+    /// When true, delay time follows `division` at the current tempo
+    sync: bool,
+    /// Index into `DELAY_DIVISIONS`
+    division: usize,
+    ping_pong: bool,
+    low_cut_hz: f32,
+    high_cut_hz: f32,
+    bpm: f64,
+    buffer_left: Vec<f32>,
+    buffer_right: Vec<f32>,
+    filter_left: FeedbackFilter,
+    filter_right: FeedbackFilter,
     write_pos: usize,
     sample_rate: f32,
     max_delay_ms: f32,
@@ -249,19 +820,52 @@ pub struct DelayEffect {
 
 impl DelayEffect {
     pub fn new(delay_ms: f32, feedback: f32, mix: f32, sample_rate: f32) -> Self {
-        let max_delay_ms = 2000.0;
+        // Long enough for a whole note at 60 BPM
+        let max_delay_ms = 4000.0;
         let max_samples = (max_delay_ms * sample_rate / 1000.0) as usize;
         Self {
             delay_ms: delay_ms.clamp(1.0, max_delay_ms),
             feedback: feedback.clamp(0.0, 1.0),
             mix: mix.clamp(0.0, 1.0),
-            buffer: vec![0.0; max_samples],
+            sync: false,
+            division: 3, // 1/4
+            ping_pong: false,
+            low_cut_hz: 20.0,
+            high_cut_hz: 20000.0,
+            bpm: 120.0,
+            buffer_left: vec![0.0; max_samples],
+            buffer_right: vec![0.0; max_samples],
+            filter_left: FeedbackFilter::default(),
+            filter_right: FeedbackFilter::default(),
             write_pos: 0,
             sample_rate,
             max_delay_ms,
             bypassed: false,
         }
     }
+
+    /// Effective delay time in milliseconds (synced or free)
+    pub fn effective_delay_ms(&self) -> f32 {
+        if !self.sync {
+            return self.delay_ms;
+        }
+        let beats = DELAY_DIVISIONS[self.division].1;
+        let ms = beats * 60_000.0 / self.bpm.max(1.0);
+        (ms as f32).clamp(1.0, self.max_delay_ms)
+    }
+
+    fn delay_samples(&self) -> usize {
+        let samples = (self.effective_delay_ms() * self.sample_rate / 1000.0) as usize;
+        samples.clamp(1, self.buffer_left.len() - 1)
+    }
+
+    fn filter_coeffs(&self) -> (f32, f32) {
+        let nyquist = self.sample_rate * 0.5;
+        (
+            one_pole_coeff(self.high_cut_hz.min(nyquist), self.sample_rate),
+            one_pole_coeff(self.low_cut_hz.min(nyquist), self.sample_rate),
+        )
+    }
 }
 
 impl fmt::Debug for DelayEffect {
@@ -270,6 +874,9 @@ impl fmt::Debug for DelayEffect {
             .field("delay_ms", &self.delay_ms)
             .field("feedback", &self.feedback)
             .field("mix", &self.mix)
+            .field("sync", &self.sync)
+            .field("division", &DELAY_DIVISIONS[self.division].0)
+            .field("ping_pong", &self.ping_pong)
             .field("bypassed", &self.bypassed)
             .finish()
     }
@@ -279,32 +886,79 @@ impl AudioEffect for DelayEffect {
     fn name(&self) -> &str { "Delay" }
 
     fn process(&mut self, samples: &mut [f32]) {
-        let delay_samples = (self.delay_ms * self.sample_rate / 1000.0) as usize;
-        let delay_samples = delay_samples.clamp(1, self.buffer.len() - 1);
+        let delay_samples = self.delay_samples();
+        let (lp_coeff, hp_coeff) = self.filter_coeffs();
+        let len = self.buffer_left.len();
 
         for sample in samples.iter_mut() {
-            let read_pos = (self.write_pos + self.buffer.len() - delay_samples) % self.buffer.len();
-            let delayed = self.buffer[read_pos];
-            self.buffer[self.write_pos] = *sample + delayed * self.feedback;
-            self.write_pos = (self.write_pos + 1) % self.buffer.len();
+            let read_pos = (self.write_pos + len - delay_samples) % len;
+            let delayed = self.buffer_left[read_pos];
+            let fed_back = self.filter_left.tick(delayed, lp_coeff, hp_coeff) * self.feedback;
+            self.buffer_left[self.write_pos] = *sample + fed_back;
+            self.write_pos = (self.write_pos + 1) % len;
             *sample = *sample * (1.0 - self.mix) + delayed * self.mix;
         }
     }
 
+    fn process_stereo(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let delay_samples = self.delay_samples();
+        let (lp_coeff, hp_coeff) = self.filter_coeffs();
+        let len = self.buffer_left.len();
+
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let read_pos = (self.write_pos + len - delay_samples) % len;
+            let delayed_l = self.buffer_left[read_pos];
+            let delayed_r = self.buffer_right[read_pos];
+            let fb_l = self.filter_left.tick(delayed_l, lp_coeff, hp_coeff) * self.feedback;
+            let fb_r = self.filter_right.tick(delayed_r, lp_coeff, hp_coeff) * self.feedback;
+
+            if self.ping_pong {
+                // Mono input enters the left line; each line feeds the opposite side
+                self.buffer_left[self.write_pos] = (*l + *r) * 0.5 + fb_r;
+                self.buffer_right[self.write_pos] = fb_l;
+            } else {
+                self.buffer_left[self.write_pos] = *l + fb_l;
+                self.buffer_right[self.write_pos] = *r + fb_r;
+            }
+            self.write_pos = (self.write_pos + 1) % len;
+
+            *l = *l * (1.0 - self.mix) + delayed_l * self.mix;
+            *r = *r * (1.0 - self.mix) + delayed_r * self.mix;
+        }
+    }
+
     fn set_param(&mut self, name: &str, value: f32) {
         match name {
             "delay" => self.delay_ms = value.clamp(1.0, self.max_delay_ms),
             "feedback" => self.feedback = value.clamp(0.0, 1.0),
             "mix" => self.mix = value.clamp(0.0, 1.0),
+            "sync" => self.sync = value >= 0.5,
+            "division" => {
+                self.division = Ord::min(value.round().max(0.0) as usize, DELAY_DIVISIONS.len() - 1);
+            }
+            "ping_pong" => self.ping_pong = value >= 0.5,
+            "low_cut" => self.low_cut_hz = value.clamp(20.0, 2000.0),
+            "high_cut" => self.high_cut_hz = value.clamp(1000.0, 20000.0),
             _ => {}
         }
     }
 
     fn get_params(&self) -> Vec<EffectParam> {
         vec![
-            EffectParam::new("delay", self.delay_ms, 1.0, self.max_delay_ms, "ms"),
-            EffectParam::new("feedback", self.feedback, 0.0, 1.0, ""),
-            EffectParam::new("mix", self.mix, 0.0, 1.0, ""),
+            EffectParam::new("delay", self.delay_ms, 1.0, self.max_delay_ms, "ms").with_default(375.0),
+            EffectParam::new("feedback", self.feedback, 0.0, 1.0, "").with_default(0.35),
+            EffectParam::new("mix", self.mix, 0.0, 1.0, "").with_default(0.3),
+            EffectParam::new("sync", if self.sync { 1.0 } else { 0.0 }, 0.0, 1.0, "").with_default(0.0),
+            EffectParam::new(
+                "division",
+                self.division as f32,
+                0.0,
+                (DELAY_DIVISIONS.len() - 1) as f32,
+                DELAY_DIVISIONS[self.division].0,
+            ).with_default(3.0),
+            EffectParam::new("ping_pong", if self.ping_pong { 1.0 } else { 0.0 }, 0.0, 1.0, "").with_default(0.0),
+            EffectParam::new("low_cut", self.low_cut_hz, 20.0, 2000.0, "Hz").with_default(20.0),
+            EffectParam::new("high_cut", self.high_cut_hz, 1000.0, 20000.0, "Hz").with_default(20000.0),
         ]
     }
 
@@ -317,9 +971,16 @@ impl AudioEffect for DelayEffect {
         }
         self.sample_rate = sample_rate;
         let max_samples = (self.max_delay_ms * sample_rate / 1000.0) as usize;
-        self.buffer = vec![0.0; max_samples];
+        self.buffer_left = vec![0.0; max_samples];
+        self.buffer_right = vec![0.0; max_samples];
+        self.filter_left = FeedbackFilter::default();
+        self.filter_right = FeedbackFilter::default();
         self.write_pos = 0;
     }
+
+    fn set_tempo(&mut self, bpm: f64) {
+        self.bpm = bpm;
+    }
 }
 
 /// Simple reverb using multiple delay lines (Schroeder)
@@ -416,9 +1077,9 @@ impl AudioEffect for ReverbEffect {
 
     fn get_params(&self) -> Vec<EffectParam> {
         vec![
-            EffectParam::new("room_size", self.room_size, 0.0, 1.0, ""),
-            EffectParam::new("damping", self.damping, 0.0, 1.0, ""),
-            EffectParam::new("mix", self.mix, 0.0, 1.0, ""),
+            EffectParam::new("room_size", self.room_size, 0.0, 1.0, "").with_default(0.5),
+            EffectParam::new("damping", self.damping, 0.0, 1.0, "").with_default(0.5),
+            EffectParam::new("mix", self.mix, 0.0, 1.0, "").with_default(0.25),
         ]
     }
 
@@ -433,3 +1094,37 @@ impl AudioEffect for ReverbEffect {
         self.rebuild_delays();
     }
 }
+
+/// Built-in effects that can be inserted on a track, as (id, display name)
+pub const NATIVE_EFFECTS: &[(&str, &str)] = &[
+    ("utility", "Utility"),
+    ("gain", "Gain"),
+    ("highpass", "High Pass"),
+    ("lowpass", "Low Pass"),
+    ("compressor", "Compressor"),
+    ("limiter", "Limiter"),
+    ("gate", "Gate"),
+    ("delay", "Delay"),
+    ("reverb", "Reverb"),
+    ("analyzer", "Analyzer"),
+    ("hardware_insert", "Hardware Insert"),
+];
+
+/// Create a built-in effect by its `NATIVE_EFFECTS` id with default settings
+pub fn create_native_effect(id: &str, sample_rate: f32) -> Option<Box<dyn AudioEffect>> {
+    let effect: Box<dyn AudioEffect> = match id {
+        "utility" => Box::new(UtilityEffect::new()),
+        "gain" => Box::new(GainEffect::new(0.0)),
+        "highpass" => Box::new(HighPassEffect::new(80.0, sample_rate)),
+        "lowpass" => Box::new(LowPassEffect::new(12000.0, sample_rate)),
+        "compressor" => Box::new(CompressorEffect::new(-18.0, 10.0, 100.0)),
+        "limiter" => Box::new(LimiterEffect::new(-1.0, sample_rate)),
+        "gate" => Box::new(GateEffect::new(-50.0, sample_rate)),
+        "delay" => Box::new(DelayEffect::new(375.0, 0.35, 0.3, sample_rate)),
+        "reverb" => Box::new(ReverbEffect::new(0.5, 0.5, 0.25, sample_rate)),
+        "analyzer" => Box::new(AnalyzerEffect::new(sample_rate)),
+        "hardware_insert" => Box::new(HardwareInsertEffect::new(0)),
+        _ => return None,
+    };
+    Some(effect)
+}
@@ -501,21 +501,22 @@ impl Drum808 {
     }
 
     fn build_param_cache(params: &Drum808Params) -> Vec<EffectParam> {
+        let defaults = Drum808Params::default();
         vec![
-            EffectParam::new("master", params.master, 0.0, 1.0, ""),
-            EffectParam::new("kick_level", params.kick_level, 0.0, 1.0, ""),
-            EffectParam::new("kick_tune", params.kick_tune, 0.0, 1.0, ""),
-            EffectParam::new("kick_decay", params.kick_decay, 0.0, 1.0, ""),
-            EffectParam::new("snare_level", params.snare_level, 0.0, 1.0, ""),
-            EffectParam::new("snare_tune", params.snare_tune, 0.0, 1.0, ""),
-            EffectParam::new("snare_decay", params.snare_decay, 0.0, 1.0, ""),
-            EffectParam::new("snare_tone", params.snare_tone, 0.0, 1.0, ""),
-            EffectParam::new("hat_level", params.hat_level, 0.0, 1.0, ""),
-            EffectParam::new("hat_decay", params.hat_decay, 0.0, 1.0, ""),
-            EffectParam::new("clap_level", params.clap_level, 0.0, 1.0, ""),
-            EffectParam::new("tom_level", params.tom_level, 0.0, 1.0, ""),
-            EffectParam::new("tom_tune", params.tom_tune, 0.0, 1.0, ""),
-            EffectParam::new("tom_decay", params.tom_decay, 0.0, 1.0, ""),
+            EffectParam::new("master", params.master, 0.0, 1.0, "").with_default(defaults.master),
+            EffectParam::new("kick_level", params.kick_level, 0.0, 1.0, "").with_default(defaults.kick_level),
+            EffectParam::new("kick_tune", params.kick_tune, 0.0, 1.0, "").with_default(defaults.kick_tune),
+            EffectParam::new("kick_decay", params.kick_decay, 0.0, 1.0, "").with_default(defaults.kick_decay),
+            EffectParam::new("snare_level", params.snare_level, 0.0, 1.0, "").with_default(defaults.snare_level),
+            EffectParam::new("snare_tune", params.snare_tune, 0.0, 1.0, "").with_default(defaults.snare_tune),
+            EffectParam::new("snare_decay", params.snare_decay, 0.0, 1.0, "").with_default(defaults.snare_decay),
+            EffectParam::new("snare_tone", params.snare_tone, 0.0, 1.0, "").with_default(defaults.snare_tone),
+            EffectParam::new("hat_level", params.hat_level, 0.0, 1.0, "").with_default(defaults.hat_level),
+            EffectParam::new("hat_decay", params.hat_decay, 0.0, 1.0, "").with_default(defaults.hat_decay),
+            EffectParam::new("clap_level", params.clap_level, 0.0, 1.0, "").with_default(defaults.clap_level),
+            EffectParam::new("tom_level", params.tom_level, 0.0, 1.0, "").with_default(defaults.tom_level),
+            EffectParam::new("tom_tune", params.tom_tune, 0.0, 1.0, "").with_default(defaults.tom_tune),
+            EffectParam::new("tom_decay", params.tom_decay, 0.0, 1.0, "").with_default(defaults.tom_decay),
         ]
     }
 
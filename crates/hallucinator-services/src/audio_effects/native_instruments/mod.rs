@@ -3,6 +3,7 @@
 pub mod drum808;
 pub mod sample_kit;
 pub mod sampler;
+pub mod signal_generator;
 
 pub use drum808::{
     Drum808, KICK, RIM_SHOT, SNARE, CLAP, CLOSED_HAT, OPEN_HAT, LOW_TOM,
@@ -10,3 +11,4 @@ pub use drum808::{
 };
 pub use sample_kit::SampleKit;
 pub use sampler::Sampler;
+pub use signal_generator::{SignalGenerator, GENERATOR_WAVEFORMS};
@@ -2,6 +2,8 @@
 
 use std::sync::Arc;
 
+use hallucinator_core::StepLocks;
+
 use crate::audio_effects::{AudioInstrument, EffectParam};
 
 const MAX_SLOTS: usize = 144; // 12 steps × 12 layers
@@ -9,6 +11,25 @@ const MAX_VOICES: usize = 32;
 const MAX_BLOCK_SIZE: usize = 4096;
 /// Base MIDI note for slot 0 (C1, same as 808 kick)
 const BASE_NOTE: u8 = 36;
+/// Decay at or above this many seconds lets samples ring out in full
+const DECAY_HOLD: f32 = 4.0;
+
+/// Kit-wide sound settings, overridable per drum step by `StepLocks`
+#[derive(Clone, Copy, Debug)]
+struct KitParams {
+    master: f32,
+    /// Semitones
+    pitch: f32,
+    /// Seconds to fade to -60 dB
+    decay: f32,
+}
+
+impl Default for KitParams {
+    fn default() -> Self {
+        Self { master: 0.8, pitch: 0.0, decay: DECAY_HOLD }
+    }
+}
+
 
 /// A loaded sample assigned to a kit slot.
 pub struct SampleSlot {
@@ -23,19 +44,31 @@ struct KitVoice {
     position: f64,
     velocity: f32,
     age: usize,
+    /// Playback speed (1.0 = original pitch)
+    rate: f64,
+    /// Decay envelope level and its per-sample multiplier
+    envelope: f32,
+    envelope_coeff: f32,
 }
 
 impl KitVoice {
     fn new() -> Self {
-        Self { active: false, slot: 0, position: 0.0, velocity: 0.0, age: 0 }
+        Self { active: false, slot: 0, position: 0.0, velocity: 0.0, age: 0, rate: 1.0, envelope: 1.0, envelope_coeff: 1.0 }
     }
 
-    fn trigger(&mut self, slot: usize, velocity: u8) {
+    fn trigger(&mut self, slot: usize, velocity: f32, params: &KitParams, sample_rate: f32) {
         self.active = true;
         self.slot = slot;
         self.position = 0.0;
-        self.velocity = velocity as f32 / 127.0;
+        self.velocity = velocity;
         self.age = 0;
+        self.rate = 2f64.powf(params.pitch as f64 / 12.0);
+        self.envelope = 1.0;
+        self.envelope_coeff = if params.decay >= DECAY_HOLD {
+            1.0
+        } else {
+            0.001f32.powf(1.0 / (params.decay.max(0.005) * sample_rate))
+        };
     }
 
     fn tick(&mut self, data: &[f32]) -> f32 {
@@ -56,9 +89,13 @@ impl KitVoice {
         let s1 = data[idx + 1];
         let sample = s0 + frac * (s1 - s0);
 
-        self.position += 1.0; // 1:1 playback, no pitch shift
+        self.position += self.rate;
+        self.envelope *= self.envelope_coeff;
+        if self.envelope < 0.001 {
+            self.active = false;
+        }
 
-        sample * self.velocity
+        sample * self.velocity * self.envelope
     }
 }
 
@@ -66,11 +103,12 @@ impl KitVoice {
 pub struct SampleKit {
     slots: Vec<Option<SampleSlot>>,
     voices: Vec<KitVoice>,
-    pending_events: Vec<(u8, u8, u32)>,
+    /// Queued triggers: (pitch, velocity, sample offset, step locks)
+    pending_events: Vec<(u8, u8, u32, StepLocks)>,
     output_left: Vec<f32>,
     output_right: Vec<f32>,
     sample_rate: f32,
-    master: f32,
+    params: KitParams,
     param_cache: Vec<EffectParam>,
 }
 
@@ -85,6 +123,9 @@ impl std::fmt::Debug for SampleKit {
 }
 
 impl SampleKit {
+    /// MIDI note that triggers row (slot) 0; row N plays at `ROW_BASE_NOTE + N`
+    pub const ROW_BASE_NOTE: u8 = BASE_NOTE;
+
     pub fn new(sample_rate: f32) -> Self {
         let mut slots = Vec::with_capacity(MAX_SLOTS);
         slots.resize_with(MAX_SLOTS, || None);
@@ -96,11 +137,24 @@ impl SampleKit {
             output_left: vec![0.0; MAX_BLOCK_SIZE],
             output_right: vec![0.0; MAX_BLOCK_SIZE],
             sample_rate,
-            master: 0.8,
-            param_cache: vec![EffectParam::new("master", 0.8, 0.0, 1.0, "")],
+            params: KitParams::default(),
+            param_cache: Self::build_param_cache(&KitParams::default()),
         }
     }
 
+    fn build_param_cache(params: &KitParams) -> Vec<EffectParam> {
+        let defaults = KitParams::default();
+        vec![
+            EffectParam::new("master", params.master, 0.0, 1.0, "").with_default(defaults.master),
+            EffectParam::new("pitch", params.pitch, -24.0, 24.0, "st").with_default(defaults.pitch),
+            EffectParam::new("decay", params.decay, 0.02, DECAY_HOLD, "s").with_default(defaults.decay),
+        ]
+    }
+
+    fn update_param_cache(&mut self) {
+        self.param_cache = Self::build_param_cache(&self.params);
+    }
+
     /// Read-only access to all slots.
     pub fn slots(&self) -> &[Option<SampleSlot>] {
         &self.slots
@@ -142,22 +196,27 @@ impl SampleKit {
             if slot >= self.slots.len() || self.slots[slot].is_none() { continue; }
             // Convert slot to pitch for pending_events queue
             let pitch = BASE_NOTE + (slot as u8);
-            self.pending_events.push((pitch, velocity, sample_offset));
+            self.pending_events.push((pitch, velocity, sample_offset, StepLocks::default()));
         }
     }
 
     /// Queue row triggers at specific sample offset (new row-based model: slot = row).
-    pub fn queue_row_triggers(&mut self, active_rows: u16, velocity: u8, sample_offset: u32) {
+    /// `locks` override the kit settings for these voices only.
+    pub fn queue_row_triggers(&mut self, active_rows: u16, velocity: u8, sample_offset: u32, locks: StepLocks) {
         for row in 0..12u16 {
             if active_rows & (1 << row) == 0 { continue; }
             let slot = row as usize;
             if slot >= self.slots.len() || self.slots[slot].is_none() { continue; }
             let pitch = BASE_NOTE + (slot as u8);
-            self.pending_events.push((pitch, velocity, sample_offset));
+            self.pending_events.push((pitch, velocity, sample_offset, locks));
         }
     }
 
     pub fn trigger_slot(&mut self, slot: usize, velocity: u8) {
+        self.trigger_slot_locked(slot, velocity, StepLocks::default());
+    }
+
+    fn trigger_slot_locked(&mut self, slot: usize, velocity: u8, locks: StepLocks) {
         if slot >= self.slots.len() || self.slots[slot].is_none() {
             return;
         }
@@ -173,12 +232,18 @@ impl SampleKit {
                     .unwrap_or(0)
             });
 
-        self.voices[vi].trigger(slot, velocity);
+        let params = KitParams {
+            pitch: locks.pitch.unwrap_or(self.params.pitch),
+            decay: locks.decay.unwrap_or(self.params.decay),
+            ..self.params
+        };
+        let gain = velocity as f32 / 127.0 * locks.level.unwrap_or(1.0);
+        self.voices[vi].trigger(slot, gain, &params, self.sample_rate);
     }
 
-    fn trigger_from_midi(&mut self, pitch: u8, velocity: u8) {
+    fn trigger_from_midi(&mut self, pitch: u8, velocity: u8, locks: StepLocks) {
         let slot = ((pitch.wrapping_sub(BASE_NOTE)) as usize) % MAX_SLOTS;
-        self.trigger_slot(slot, velocity);
+        self.trigger_slot_locked(slot, velocity, locks);
     }
 }
 
@@ -188,7 +253,7 @@ impl AudioInstrument for SampleKit {
     }
 
     fn queue_note_on(&mut self, pitch: u8, velocity: u8, _channel: u8, sample_offset: u32) {
-        self.pending_events.push((pitch, velocity, sample_offset));
+        self.pending_events.push((pitch, velocity, sample_offset, StepLocks::default()));
     }
 
     fn queue_note_off(&mut self, _pitch: u8, _velocity: u8, _channel: u8, _sample_offset: u32) {
@@ -210,12 +275,12 @@ impl AudioInstrument for SampleKit {
         self.pending_events.sort_by_key(|e| e.2);
 
         for frame_idx in 0..frames {
-            while let Some(&(pitch, velocity, offset)) = self.pending_events.first() {
+            while let Some(&(pitch, velocity, offset, locks)) = self.pending_events.first() {
                 if offset as usize > frame_idx {
                     break;
                 }
                 self.pending_events.remove(0);
-                self.trigger_from_midi(pitch, velocity);
+                self.trigger_from_midi(pitch, velocity, locks);
             }
 
             let mut mix = 0.0_f32;
@@ -233,7 +298,7 @@ impl AudioInstrument for SampleKit {
                 mix += voice.tick(slot_data);
             }
 
-            let out = (mix * self.master).clamp(-1.0, 1.0);
+            let out = (mix * self.params.master).clamp(-1.0, 1.0);
             self.output_left[frame_idx] = out;
             self.output_right[frame_idx] = out;
         }
@@ -255,16 +320,18 @@ impl AudioInstrument for SampleKit {
     }
 
     fn set_param(&mut self, name: &str, value: f32) {
-        if name == "master" {
-            self.master = value;
-            self.param_cache = vec![EffectParam::new("master", value, 0.0, 1.0, "")];
+        match name {
+            "master" => self.params.master = value,
+            "pitch" => self.params.pitch = value,
+            "decay" => self.params.decay = value,
+            _ => return,
         }
+        self.update_param_cache();
     }
 
     fn set_param_by_index(&mut self, index: usize, value: f64) {
-        if index == 0 {
-            self.master = value as f32;
-            self.param_cache = vec![EffectParam::new("master", value as f32, 0.0, 1.0, "")];
+        if let Some(name) = ["master", "pitch", "decay"].get(index) {
+            self.set_param(name, value as f32);
         }
     }
 
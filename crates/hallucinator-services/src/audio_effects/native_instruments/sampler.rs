@@ -1,4 +1,4 @@
-//! Sample-based instrument — loads a WAV file and plays it pitched across MIDI keyboard
+//! Sample-based instrument — loads an audio file and plays it pitched across MIDI keyboard
 
 use std::path::Path;
 use std::sync::Arc;
@@ -152,55 +152,46 @@ impl Sampler {
         }
     }
 
-    /// Load a sampler from a WAV file, resampling to the given engine sample rate.
-    pub fn from_wav(path: &Path, engine_sample_rate: f32) -> Result<Self, String> {
-        let reader = hound::WavReader::open(path)
-            .map_err(|e| format!("Failed to open WAV: {e}"))?;
-
-        let spec = reader.spec();
-        let channels = spec.channels as usize;
-
-        let raw_samples: Vec<f32> = match spec.sample_format {
-            hound::SampleFormat::Float => {
-                reader.into_samples::<f32>().filter_map(Result::ok).collect()
-            }
-            hound::SampleFormat::Int => {
-                let max_val = (1_i64 << (spec.bits_per_sample - 1)) as f32;
-                reader.into_samples::<i32>()
-                    .filter_map(Result::ok)
-                    .map(|s| s as f32 / max_val)
-                    .collect()
-            }
-        };
-
-        if raw_samples.is_empty() {
-            return Err("WAV file is empty".into());
-        }
-
-        // Mix to mono if stereo
-        let mono: Vec<f32> = if channels == 1 {
-            raw_samples
-        } else {
-            raw_samples
-                .chunks(channels)
-                .map(|frame| frame.iter().sum::<f32>() / channels as f32)
-                .collect()
-        };
+    /// A new sampler playing the same sample data (shared, not copied)
+    pub fn share_sample(&self) -> Self {
+        let mut sampler = Self::new(self.inst_name.clone(), Vec::new(), self.sample_rate);
+        sampler.sample_data = Arc::clone(&self.sample_data);
+        sampler.base_pitch = self.base_pitch;
+        sampler
+    }
 
-        // Resample if needed
-        let resampled = resample_linear(&mono, spec.sample_rate as f32, engine_sample_rate);
+    /// The mono sample data played, at the engine sample rate
+    pub fn sample_data(&self) -> &Arc<Vec<f32>> {
+        &self.sample_data
+    }
 
+    /// Load a sampler from an audio file (mixed to mono), resampling to the given engine sample rate.
+    pub fn from_file(path: &Path, engine_sample_rate: f32) -> Result<Self, String> {
+        let (mono, sample_rate) = crate::audio_file::read_audio_mono(path)?;
         let name = path.file_stem()
             .and_then(|s| s.to_str())
             .unwrap_or("Sample")
             .to_string();
 
+        Self::from_samples(name, &mono, sample_rate, engine_sample_rate)
+    }
+
+    /// A sampler of mono audio already decoded at `sample_rate`, resampled to the engine sample rate.
+    pub fn from_samples(name: String, mono: &[f32], sample_rate: u32, engine_sample_rate: f32) -> Result<Self, String> {
+        if mono.is_empty() {
+            return Err("audio file is empty".into());
+        }
+
+        // Resample if needed
+        let resampled = resample_linear(mono, sample_rate as f32, engine_sample_rate);
+
         Ok(Self::new(name, resampled, engine_sample_rate))
     }
 
     fn build_param_cache(params: &SamplerParams) -> Vec<EffectParam> {
         vec![
-            EffectParam::new("master", params.master, 0.0, 1.0, ""),
+            EffectParam::new("master", params.master, 0.0, 1.0, "")
+                .with_default(SamplerParams::default().master),
         ]
     }
 
@@ -0,0 +1,267 @@
+//! Test signal generator — sine/square (optionally swept), white and pink noise
+
+use crate::audio_effects::{AudioInstrument, EffectParam};
+
+const MAX_BLOCK_SIZE: usize = 4096;
+
+/// Waveform names indexed by the `waveform` parameter
+pub const GENERATOR_WAVEFORMS: &[&str] = &["Sine", "Square", "White", "Pink"];
+
+/// Parameters for the generator
+#[derive(Debug, Clone)]
+struct GeneratorParams {
+    /// Index into `GENERATOR_WAVEFORMS`
+    waveform: f32,
+    frequency: f32,
+    /// 1 = sweep logarithmically from `frequency` to `sweep_end`
+    sweep: f32,
+    sweep_end: f32,
+    sweep_time: f32,
+    level_db: f32,
+    /// 1 = output continuously, 0 = only while a note is held
+    free_run: f32,
+    /// 1 = tone frequency follows the played note
+    keytrack: f32,
+}
+
+impl Default for GeneratorParams {
+    fn default() -> Self {
+        Self {
+            waveform: 0.0,
+            frequency: 1000.0,
+            sweep: 0.0,
+            sweep_end: 20000.0,
+            sweep_time: 10.0,
+            level_db: -18.0,
+            free_run: 0.0,
+            keytrack: 0.0,
+        }
+    }
+}
+
+/// Paul Kellet's refined pink noise filter state
+#[derive(Default)]
+struct PinkFilter {
+    b: [f32; 7],
+}
+
+impl PinkFilter {
+    fn tick(&mut self, white: f32) -> f32 {
+        let b = &mut self.b;
+        b[0] = 0.99886 * b[0] + white * 0.0555179;
+        b[1] = 0.99332 * b[1] + white * 0.0750759;
+        b[2] = 0.96900 * b[2] + white * 0.153852;
+        b[3] = 0.86650 * b[3] + white * 0.3104856;
+        b[4] = 0.55000 * b[4] + white * 0.5329522;
+        b[5] = -0.7616 * b[5] - white * 0.0168980;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115926;
+        pink * 0.11
+    }
+}
+
+/// Signal generator instrument for calibrating levels and testing routing
+pub struct SignalGenerator {
+    sample_rate: f32,
+    phase: f64,
+    sweep_elapsed: f64,
+    held_note: Option<u8>,
+    pending_events: Vec<(u8, bool, u32)>,
+    rng: fastrand::Rng,
+    pink: PinkFilter,
+    output_left: Vec<f32>,
+    output_right: Vec<f32>,
+    params: GeneratorParams,
+    param_cache: Vec<EffectParam>,
+}
+
+impl std::fmt::Debug for SignalGenerator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignalGenerator")
+            .field("params", &self.params)
+            .field("held_note", &self.held_note)
+            .finish()
+    }
+}
+
+impl SignalGenerator {
+    pub fn new(sample_rate: f32) -> Self {
+        let params = GeneratorParams::default();
+        let param_cache = Self::build_param_cache(&params);
+
+        Self {
+            sample_rate,
+            phase: 0.0,
+            sweep_elapsed: 0.0,
+            held_note: None,
+            pending_events: Vec::with_capacity(64),
+            rng: fastrand::Rng::new(),
+            pink: PinkFilter::default(),
+            output_left: vec![0.0; MAX_BLOCK_SIZE],
+            output_right: vec![0.0; MAX_BLOCK_SIZE],
+            params,
+            param_cache,
+        }
+    }
+
+    fn build_param_cache(params: &GeneratorParams) -> Vec<EffectParam> {
+        let defaults = GeneratorParams::default();
+        let waveform = GENERATOR_WAVEFORMS
+            .get(params.waveform as usize)
+            .copied()
+            .unwrap_or("Sine");
+        vec![
+            EffectParam::new("waveform", params.waveform, 0.0, (GENERATOR_WAVEFORMS.len() - 1) as f32, waveform)
+                .with_default(defaults.waveform),
+            EffectParam::new("frequency", params.frequency, 20.0, 20000.0, "Hz").with_default(defaults.frequency),
+            EffectParam::new("sweep", params.sweep, 0.0, 1.0, "").with_default(defaults.sweep),
+            EffectParam::new("sweep_end", params.sweep_end, 20.0, 20000.0, "Hz").with_default(defaults.sweep_end),
+            EffectParam::new("sweep_time", params.sweep_time, 1.0, 60.0, "s").with_default(defaults.sweep_time),
+            EffectParam::new("level", params.level_db, -60.0, 0.0, "dBFS").with_default(defaults.level_db),
+            EffectParam::new("free_run", params.free_run, 0.0, 1.0, "").with_default(defaults.free_run),
+            EffectParam::new("keytrack", params.keytrack, 0.0, 1.0, "").with_default(defaults.keytrack),
+        ]
+    }
+
+    fn update_param_cache(&mut self) {
+        self.param_cache = Self::build_param_cache(&self.params);
+    }
+
+    fn apply_param(&mut self, name: &str, value: f32) {
+        let p = &mut self.params;
+        match name {
+            "waveform" => p.waveform = value.round().clamp(0.0, (GENERATOR_WAVEFORMS.len() - 1) as f32),
+            "frequency" => p.frequency = value.clamp(20.0, 20000.0),
+            "sweep" => {
+                p.sweep = if value >= 0.5 { 1.0 } else { 0.0 };
+                self.sweep_elapsed = 0.0;
+            }
+            "sweep_end" => p.sweep_end = value.clamp(20.0, 20000.0),
+            "sweep_time" => p.sweep_time = value.clamp(1.0, 60.0),
+            "level" => p.level_db = value.clamp(-60.0, 0.0),
+            "free_run" => p.free_run = if value >= 0.5 { 1.0 } else { 0.0 },
+            "keytrack" => p.keytrack = if value >= 0.5 { 1.0 } else { 0.0 },
+            _ => return,
+        }
+        self.update_param_cache();
+    }
+
+    /// Instantaneous tone frequency, including sweep and key tracking
+    fn current_frequency(&self) -> f64 {
+        if self.params.keytrack >= 0.5 {
+            if let Some(note) = self.held_note {
+                return 440.0 * 2f64.powf((note as f64 - 69.0) / 12.0);
+            }
+        }
+        let start = self.params.frequency as f64;
+        if self.params.sweep < 0.5 {
+            return start;
+        }
+        let t = (self.sweep_elapsed / self.params.sweep_time as f64).min(1.0);
+        start * (self.params.sweep_end as f64 / start).powf(t)
+    }
+
+    fn tick(&mut self) -> f32 {
+        let waveform = self.params.waveform as usize;
+        let out = match waveform {
+            0 | 1 => {
+                let freq = self.current_frequency();
+                let value = if waveform == 0 {
+                    (self.phase * std::f64::consts::TAU).sin() as f32
+                } else if self.phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                };
+                self.phase = (self.phase + freq / self.sample_rate as f64).fract();
+                value
+            }
+            2 => self.rng.f32() * 2.0 - 1.0,
+            _ => {
+                let white = self.rng.f32() * 2.0 - 1.0;
+                self.pink.tick(white)
+            }
+        };
+
+        if self.params.sweep >= 0.5 {
+            self.sweep_elapsed += 1.0 / self.sample_rate as f64;
+            if self.sweep_elapsed >= self.params.sweep_time as f64 {
+                self.sweep_elapsed = 0.0;
+            }
+        }
+
+        out
+    }
+}
+
+impl AudioInstrument for SignalGenerator {
+    fn name(&self) -> &str {
+        "Signal Generator"
+    }
+
+    fn queue_note_on(&mut self, pitch: u8, _velocity: u8, _channel: u8, sample_offset: u32) {
+        self.pending_events.push((pitch, true, sample_offset));
+    }
+
+    fn queue_note_off(&mut self, pitch: u8, _velocity: u8, _channel: u8, sample_offset: u32) {
+        self.pending_events.push((pitch, false, sample_offset));
+    }
+
+    fn all_notes_off(&mut self) {
+        self.held_note = None;
+    }
+
+    fn process(&mut self, num_frames: usize) -> (&[f32], &[f32]) {
+        let frames = num_frames.min(MAX_BLOCK_SIZE);
+        let gain = 10f32.powf(self.params.level_db / 20.0);
+        let free_run = self.params.free_run >= 0.5;
+
+        self.pending_events.sort_by_key(|e| e.2);
+
+        for frame_idx in 0..frames {
+            while let Some(&(pitch, is_on, offset)) = self.pending_events.first() {
+                if offset as usize > frame_idx { break; }
+                self.pending_events.remove(0);
+                if is_on {
+                    // Each new note restarts the sweep from the start frequency
+                    self.held_note = Some(pitch);
+                    self.sweep_elapsed = 0.0;
+                } else if self.held_note == Some(pitch) {
+                    self.held_note = None;
+                }
+            }
+
+            let out = if free_run || self.held_note.is_some() {
+                self.tick() * gain
+            } else {
+                0.0
+            };
+            self.output_left[frame_idx] = out;
+            self.output_right[frame_idx] = out;
+        }
+
+        self.pending_events.retain(|e| e.2 as usize >= frames);
+        for event in &mut self.pending_events {
+            event.2 -= frames as u32;
+        }
+
+        (&self.output_left[..frames], &self.output_right[..frames])
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+    }
+
+    fn get_params(&self) -> &[EffectParam] {
+        &self.param_cache
+    }
+
+    fn set_param(&mut self, name: &str, value: f32) {
+        self.apply_param(name, value);
+    }
+
+    fn set_param_by_index(&mut self, index: usize, value: f64) {
+        let Some(name) = self.param_cache.get(index).map(|p| p.name.clone()) else { return };
+        self.apply_param(&name, value as f32);
+    }
+}
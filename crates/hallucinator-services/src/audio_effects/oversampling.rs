@@ -0,0 +1,201 @@
+//! Oversampling around an effect insert
+//!
+//! Each 2× stage is a linear-phase halfband FIR split into its two polyphase
+//! branches: every other tap of a halfband filter is zero except the center,
+//! so one branch is a plain delay and only the other needs a convolution.
+//! 4× cascades two stages. The effect runs at the raised rate in between,
+//! which keeps the harmonics a nonlinear effect generates from folding back
+//! below Nyquist, at the cost of CPU and a few samples of latency.
+
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+use super::AudioEffect;
+
+/// Halfband filter length (the center tap index must be odd)
+const TAPS: usize = 95;
+const CENTER: usize = TAPS / 2;
+/// Taps in the convolving polyphase branch (the even-indexed ones)
+const BRANCH: usize = CENTER + 1;
+
+/// Oversampling factor of an insert
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Oversampling {
+    #[default]
+    Off,
+    X2,
+    X4,
+}
+
+impl Oversampling {
+    pub const ALL: [Oversampling; 3] = [Self::Off, Self::X2, Self::X4];
+
+    /// Rate multiplier applied to the effect
+    pub fn factor(self) -> u32 {
+        1 << self.stages()
+    }
+
+    fn stages(self) -> usize {
+        match self {
+            Self::Off => 0,
+            Self::X2 => 1,
+            Self::X4 => 2,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::X2 => "2×",
+            Self::X4 => "4×",
+        }
+    }
+}
+
+/// Up/downsampling state for one effect, stereo
+#[derive(Debug)]
+pub struct Oversampler {
+    mode: Oversampling,
+    coeffs: [f32; BRANCH],
+    /// Per stage, per channel filter state
+    stages: Vec<[Halfband; 2]>,
+    /// Per stage, per channel audio at that stage's output rate
+    buffers: Vec<[Vec<f32>; 2]>,
+}
+
+impl Oversampler {
+    pub fn new(mode: Oversampling) -> Self {
+        let stages = mode.stages();
+        Self {
+            mode,
+            coeffs: halfband_coeffs(),
+            stages: (0..stages).map(|_| [Halfband::new(), Halfband::new()]).collect(),
+            buffers: (0..stages).map(|_| [Vec::new(), Vec::new()]).collect(),
+        }
+    }
+
+    pub fn mode(&self) -> Oversampling {
+        self.mode
+    }
+
+    /// Run an effect's mono `process` at the oversampled rate
+    pub fn process(&mut self, effect: &mut dyn AudioEffect, samples: &mut [f32]) {
+        let Some(len) = self.upsample(0, samples) else {
+            effect.process(samples);
+            return;
+        };
+        if let Some([buffer, _]) = self.buffers.last_mut() {
+            effect.process(&mut buffer[..len]);
+        }
+        self.downsample(0, samples);
+    }
+
+    /// Run an effect's `process_stereo` at the oversampled rate
+    pub fn process_stereo(&mut self, effect: &mut dyn AudioEffect, left: &mut [f32], right: &mut [f32]) {
+        let frames = left.len().min(right.len());
+        let (left, right) = (&mut left[..frames], &mut right[..frames]);
+        let Some(len) = self.upsample(0, left) else {
+            effect.process_stereo(left, right);
+            return;
+        };
+        self.upsample(1, right);
+        if let Some([buffer_left, buffer_right]) = self.buffers.last_mut() {
+            effect.process_stereo(&mut buffer_left[..len], &mut buffer_right[..len]);
+        }
+        self.downsample(0, left);
+        self.downsample(1, right);
+    }
+
+    /// Upsample one channel through every stage; returns the oversampled length
+    fn upsample(&mut self, channel: usize, input: &[f32]) -> Option<usize> {
+        if self.stages.is_empty() {
+            return None;
+        }
+        let mut len = input.len();
+        for stage in 0..self.stages.len() {
+            let (lower, upper) = self.buffers.split_at_mut(stage);
+            let output = &mut upper[0][channel];
+            if output.len() < len * 2 {
+                output.resize(len * 2, 0.0);
+            }
+            let source = match lower.last() {
+                Some(previous) => &previous[channel][..len],
+                None => input,
+            };
+            self.stages[stage][channel].upsample(&self.coeffs, source, &mut output[..len * 2]);
+            len *= 2;
+        }
+        Some(len)
+    }
+
+    /// Downsample one channel back through every stage into `output`
+    fn downsample(&mut self, channel: usize, output: &mut [f32]) {
+        for stage in (0..self.stages.len()).rev() {
+            let len = output.len() << stage;
+            let (lower, upper) = self.buffers.split_at_mut(stage);
+            let source = &upper[0][channel][..len * 2];
+            let target = match lower.last_mut() {
+                Some(previous) => &mut previous[channel][..len],
+                None => &mut *output,
+            };
+            self.stages[stage][channel].downsample(&self.coeffs, source, target);
+        }
+    }
+}
+
+/// One channel of a 2× stage: input history for each direction
+#[derive(Debug)]
+struct Halfband {
+    up: [f32; BRANCH],
+    down: [f32; TAPS],
+}
+
+impl Halfband {
+    fn new() -> Self {
+        Self { up: [0.0; BRANCH], down: [0.0; TAPS] }
+    }
+
+    /// Each input sample yields two: the convolving branch, then the delayed input
+    fn upsample(&mut self, coeffs: &[f32; BRANCH], input: &[f32], output: &mut [f32]) {
+        for (&x, pair) in input.iter().zip(output.chunks_exact_mut(2)) {
+            self.up.copy_within(..BRANCH - 1, 1);
+            self.up[0] = x;
+            // Gain of 2 makes up for the zeros stuffed between samples
+            pair[0] = 2.0 * dot(coeffs, &self.up);
+            pair[1] = self.up[CENTER / 2];
+        }
+    }
+
+    /// Filter at the high rate, keeping every second output
+    fn downsample(&mut self, coeffs: &[f32; BRANCH], input: &[f32], output: &mut [f32]) {
+        for (pair, out) in input.chunks_exact(2).zip(output.iter_mut()) {
+            self.down.copy_within(..TAPS - 2, 2);
+            self.down[1] = pair[0];
+            self.down[0] = pair[1];
+            let branch: f32 = coeffs.iter().zip(self.down.iter().step_by(2)).map(|(c, x)| c * x).sum();
+            *out = branch + 0.5 * self.down[CENTER];
+        }
+    }
+}
+
+fn dot(a: &[f32; BRANCH], b: &[f32; BRANCH]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+/// Blackman-windowed sinc halfband (cutoff at a quarter of the high rate),
+/// even-indexed taps only; the odd ones are zero apart from the 0.5 center
+fn halfband_coeffs() -> [f32; BRANCH] {
+    let mut coeffs = [0.0f64; BRANCH];
+    for (p, c) in coeffs.iter_mut().enumerate() {
+        let n = 2 * p;
+        let k = n as f64 - CENTER as f64;
+        let sinc = (PI * k / 2.0).sin() / (PI * k);
+        let phase = 2.0 * PI * n as f64 / (TAPS - 1) as f64;
+        let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+        *c = sinc * window;
+    }
+    // Unity gain at DC: the branch sums to 0.5, the center tap supplies the rest
+    let sum: f64 = coeffs.iter().sum();
+    coeffs.map(|c| (c * 0.5 / sum) as f32)
+}
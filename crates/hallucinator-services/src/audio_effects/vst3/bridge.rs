@@ -0,0 +1,821 @@
+//! Sandboxed plugin host process
+//!
+//! A bridged instrument runs its plugin in a child process (this executable,
+//! relaunched with `PLUGIN_HOST_ARG`), so a plugin that crashes or hangs takes
+//! down only its host. Audio and MIDI go through a shared-memory block: the
+//! audio thread writes the block's events and frame count and bumps `request`,
+//! and the child renders while the engine carries on, answering with
+//! `response`. The audio thread collects that output on its next block, so a
+//! sandboxed instrument plays one block late but the engine never waits on it;
+//! a late, crashed or restarting child yields silence. Everything else
+//! (parameters, state, programs) is JSON lines over the child's stdin/stdout,
+//! with plugin state as base64.
+//!
+//! A watchdog thread notices when the child exits, launches a new one and
+//! restores the last plugin state it captured.
+
+use std::cell::UnsafeCell;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::os::fd::AsFd;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, TryRecvError};
+use hallucinator_core::MidiControl;
+use memmap2::MmapMut;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use super::error::Vst3Error;
+use super::scanner::Vst3PluginInfo;
+use super::shared::InProcess;
+use crate::audio_effects::EffectParam;
+use crate::notifications;
+
+/// First argument that starts this executable as a plugin host (see `run_plugin_host`)
+pub const PLUGIN_HOST_ARG: &str = "--plugin-host";
+
+/// Largest block transferred, the instrument's own maximum
+const MAX_BLOCK_SIZE: usize = 4096;
+/// Events per block; the rest wait for the next block
+const MAX_EVENTS: usize = 512;
+
+const EVENT_NOTE_OFF: u32 = 0;
+const EVENT_NOTE_ON: u32 = 1;
+const EVENT_PARAM: u32 = 2;
+const EVENT_CONTROL: u32 = 3;
+/// Control event index of the first poly pressure key (CC numbers come first)
+const POLY_PRESSURE_INDEX: u32 = 256;
+
+/// How long the child may take to load its plugin
+const LOAD_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a control request may take before the child is considered hung
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(2);
+/// Plugin state allowed per second on top of `CONTROL_TIMEOUT`, so big states
+/// (sample libraries) have time to encode and cross the pipe
+const STATE_BYTES_PER_SEC: f64 = 8.0 * 1024.0 * 1024.0;
+const WATCHDOG_INTERVAL: Duration = Duration::from_millis(200);
+/// How often the watchdog captures the plugin state, to restore after a crash
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(5);
+/// Pause before trying again when a restarted plugin fails to load
+const RELAUNCH_BACKOFF: Duration = Duration::from_secs(2);
+/// Without blocks for this long the child sleeps between polls instead of yielding
+const CHILD_IDLE_AFTER: Duration = Duration::from_millis(20);
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct BridgeEvent {
+    kind: u32,
+    /// Pitch for notes, parameter index for parameter changes, `control_index` for controllers
+    index: u32,
+    channel: u32,
+    sample_offset: u32,
+    /// Velocity (0-127) for notes, normalized value for parameters, raw value for controllers
+    value: f32,
+}
+
+/// Layout of the shared-memory file; all zeroes is a valid initial state
+#[repr(C)]
+struct SharedBlock {
+    /// Bumped by the host when a block is ready to process
+    request: AtomicU32,
+    /// Set to `request` by the child once that block's output is written
+    response: AtomicU32,
+    frames: AtomicU32,
+    event_count: AtomicU32,
+    events: UnsafeCell<[BridgeEvent; MAX_EVENTS]>,
+    output: UnsafeCell<[[f32; MAX_BLOCK_SIZE]; 2]>,
+}
+
+// Safety: the host only writes events while the child is idle (response ==
+// request) and only reads output after the child answered; the child only
+// touches either in between, so the two sides never overlap
+unsafe impl Sync for SharedBlock {}
+
+/// A memory-mapped file holding one `SharedBlock`
+struct SharedMemory {
+    map: MmapMut,
+    path: PathBuf,
+    /// The host that created the file removes it
+    owned: bool,
+}
+
+impl SharedMemory {
+    fn create() -> Result<Self, Vst3Error> {
+        static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+        let shm = Path::new("/dev/shm");
+        let dir = if shm.is_dir() { shm.to_path_buf() } else { std::env::temp_dir() };
+        let path = dir.join(format!(
+            "hallucinator-bridge-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+        file.set_len(size_of::<SharedBlock>() as u64)?;
+        Self::map(&file, path, true)
+    }
+
+    fn open(path: &Path) -> Result<Self, Vst3Error> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        if file.metadata()?.len() < size_of::<SharedBlock>() as u64 {
+            return Err(Vst3Error::LoadError("shared memory file is too small".to_string()));
+        }
+        Self::map(&file, path.to_path_buf(), false)
+    }
+
+    fn map(file: &File, path: PathBuf, owned: bool) -> Result<Self, Vst3Error> {
+        // Safety: the file belongs to one host and its child, which only
+        // access it through `SharedBlock`
+        let map = unsafe { MmapMut::map_mut(file)? };
+        Ok(Self { map, path, owned })
+    }
+
+    fn block(&self) -> &SharedBlock {
+        // Safety: the mapping is page aligned, at least a `SharedBlock` long
+        // and lives as long as `self`
+        unsafe { &*(self.map.as_ptr() as *const SharedBlock) }
+    }
+}
+
+impl Drop for SharedMemory {
+    fn drop(&mut self) {
+        if self.owned {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Control requests, host to child
+#[derive(Debug, Serialize, Deserialize)]
+enum Request {
+    Params,
+    State,
+    SetState(#[serde(with = "base64_bytes")] Vec<u8>),
+    Programs,
+    CurrentProgram,
+    SetProgram(usize),
+    SetSampleRate(f32),
+}
+
+/// Control replies, child to host (one per request, plus `Ready` after loading)
+#[derive(Debug, Serialize, Deserialize)]
+enum Reply {
+    Ready,
+    Done,
+    Params(Vec<ParamSnapshot>),
+    State(#[serde(with = "base64_bytes")] Vec<u8>),
+    Programs(Vec<String>),
+    CurrentProgram(Option<usize>),
+    Error(String),
+}
+
+/// A normalized parameter as the child reports it
+#[derive(Debug, Serialize, Deserialize)]
+struct ParamSnapshot {
+    name: String,
+    value: f32,
+    default: f32,
+    unit: String,
+    steps: u32,
+}
+
+impl From<&EffectParam> for ParamSnapshot {
+    fn from(param: &EffectParam) -> Self {
+        Self {
+            name: param.name.clone(),
+            value: param.value,
+            default: param.default,
+            unit: param.unit.clone(),
+            steps: param.steps,
+        }
+    }
+}
+
+impl From<ParamSnapshot> for EffectParam {
+    fn from(param: ParamSnapshot) -> Self {
+        EffectParam::new(&param.name, param.value, 0.0, 1.0, &param.unit)
+            .with_default(param.default)
+            .with_steps(param.steps)
+    }
+}
+
+/// Plugin state as a base64 string rather than a JSON array of numbers
+mod base64_bytes {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        STANDARD.decode(text).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Time a control request may take, given the plugin state it carries (either way)
+fn control_timeout(state_len: usize) -> Duration {
+    CONTROL_TIMEOUT + Duration::from_secs_f64(state_len as f64 / STATE_BYTES_PER_SEC)
+}
+
+fn write_line(out: &mut impl Write, message: &impl Serialize) -> Result<(), Vst3Error> {
+    let mut line = serde_json::to_vec(message).map_err(|e| Vst3Error::ProcessError(e.to_string()))?;
+    line.push(b'\n');
+    out.write_all(&line)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn unexpected_reply() -> Vst3Error {
+    Vst3Error::ProcessError("unexpected reply from plugin host".to_string())
+}
+
+/// What the host needs to (re)launch a child
+struct Launch {
+    name: String,
+    bundle: PathBuf,
+    unique_id: String,
+    shm_path: PathBuf,
+}
+
+/// A running child process and its control channel
+struct HostProcess {
+    child: Child,
+    stdin: ChildStdin,
+    replies: Receiver<Reply>,
+}
+
+impl HostProcess {
+    /// Start a child and wait until its plugin is loaded
+    fn spawn(launch: &Launch, sample_rate: f32) -> Result<Self, Vst3Error> {
+        let mut child = Command::new(std::env::current_exe()?)
+            .arg(PLUGIN_HOST_ARG)
+            .arg(&launch.shm_path)
+            .arg(&launch.bundle)
+            .arg(&launch.unique_id)
+            .arg(sample_rate.to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            let _ = child.kill();
+            return Err(Vst3Error::LoadError("plugin host has no control channel".to_string()));
+        };
+
+        let (reply_tx, replies) = crossbeam_channel::unbounded();
+        thread::Builder::new()
+            .name("vst3-bridge-replies".to_string())
+            .spawn(move || {
+                for line in BufReader::new(stdout).lines() {
+                    let Ok(line) = line else { break };
+                    match serde_json::from_str::<Reply>(&line) {
+                        Ok(reply) => {
+                            if reply_tx.send(reply).is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => warn!("Ignoring malformed plugin host reply: {}", e),
+                    }
+                }
+            })?;
+
+        let mut process = Self { child, stdin, replies };
+        let result = match process.replies.recv_timeout(LOAD_TIMEOUT) {
+            Ok(Reply::Ready) => return Ok(process),
+            Ok(Reply::Error(e)) => Vst3Error::LoadError(e),
+            Ok(_) => unexpected_reply(),
+            Err(RecvTimeoutError::Timeout) => Vst3Error::LoadError("plugin host did not finish loading".to_string()),
+            Err(RecvTimeoutError::Disconnected) => Vst3Error::LoadError("plugin host exited while loading".to_string()),
+        };
+        process.kill();
+        Err(result)
+    }
+
+    fn call(&mut self, request: &Request, timeout: Duration) -> Result<Reply, Vst3Error> {
+        write_line(&mut self.stdin, request)?;
+        match self.replies.recv_timeout(timeout) {
+            Ok(Reply::Error(e)) => Err(Vst3Error::ProcessError(e)),
+            Ok(reply) => Ok(reply),
+            Err(RecvTimeoutError::Timeout) => {
+                // The watchdog starts a new one
+                self.kill();
+                Err(Vst3Error::ProcessError("plugin host stopped responding".to_string()))
+            }
+            Err(RecvTimeoutError::Disconnected) => Err(Vst3Error::ProcessError("plugin host exited".to_string())),
+        }
+    }
+
+    fn kill(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Host-side state shared with the watchdog thread
+struct Link {
+    launch: Launch,
+    process: Mutex<Option<HostProcess>>,
+    /// Sample rate (f32 bits) for relaunches
+    sample_rate: AtomicU32,
+    /// Plugin state restored after a relaunch
+    last_state: Mutex<Option<Vec<u8>>>,
+    restarts: AtomicU32,
+    shutdown: AtomicBool,
+}
+
+impl Link {
+    fn call(&self, request: &Request) -> Result<Reply, Vst3Error> {
+        // A state reply is expected to be about as big as the last one
+        let state_len = match request {
+            Request::SetState(chunk) => chunk.len(),
+            Request::State => self.last_state.lock().ok().and_then(|state| Some(state.as_ref()?.len())).unwrap_or(0),
+            _ => 0,
+        };
+        let mut process = self
+            .process
+            .lock()
+            .map_err(|_| Vst3Error::ProcessError("plugin host lock poisoned".to_string()))?;
+        let process = process
+            .as_mut()
+            .ok_or_else(|| Vst3Error::ProcessError("plugin host is restarting".to_string()))?;
+        process.call(request, control_timeout(state_len))
+    }
+
+    fn remember_state(&self, state: Vec<u8>) {
+        if let Ok(mut last_state) = self.last_state.lock() {
+            *last_state = Some(state);
+        }
+    }
+
+    fn watch(&self) {
+        let mut last_snapshot = Instant::now();
+        loop {
+            thread::sleep(WATCHDOG_INTERVAL);
+            if self.shutdown.load(Ordering::Relaxed) {
+                break;
+            }
+            if !self.running() {
+                self.relaunch();
+            } else if last_snapshot.elapsed() >= SNAPSHOT_INTERVAL {
+                last_snapshot = Instant::now();
+                if let Ok(Reply::State(state)) = self.call(&Request::State) {
+                    self.remember_state(state);
+                }
+            }
+        }
+    }
+
+    /// Whether the child is alive; an exited child is reaped
+    fn running(&self) -> bool {
+        let Ok(mut slot) = self.process.lock() else { return false };
+        let Some(process) = slot.as_mut() else { return false };
+        match process.child.try_wait() {
+            Ok(None) => return true,
+            Ok(Some(status)) => {
+                let title = format!("{} crashed and is being restarted", self.launch.name);
+                notifications::warning(title, format!("Plugin host exited: {}", status));
+            }
+            Err(e) => {
+                warn!(plugin = %self.launch.name, "Lost track of plugin host: {}", e);
+                process.kill();
+            }
+        }
+        *slot = None;
+        false
+    }
+
+    fn relaunch(&self) {
+        let sample_rate = f32::from_bits(self.sample_rate.load(Ordering::Relaxed));
+        let mut process = match HostProcess::spawn(&self.launch, sample_rate) {
+            Ok(process) => process,
+            Err(e) => {
+                notifications::error(format!("Failed to restart {}", self.launch.name), e);
+                thread::sleep(RELAUNCH_BACKOFF);
+                return;
+            }
+        };
+        if self.shutdown.load(Ordering::Relaxed) {
+            process.kill();
+            return;
+        }
+
+        let state = self.last_state.lock().ok().and_then(|state| state.clone());
+        if let Some(state) = state {
+            let timeout = control_timeout(state.len());
+            if let Err(e) = process.call(&Request::SetState(state), timeout) {
+                warn!(plugin = %self.launch.name, "Failed to restore plugin state after restart: {}", e);
+            }
+        }
+        if let Ok(mut slot) = self.process.lock() {
+            *slot = Some(process);
+        }
+        let restarts = self.restarts.fetch_add(1, Ordering::Relaxed) + 1;
+        info!(plugin = %self.launch.name, restarts, "Plugin host restarted");
+    }
+}
+
+/// Reads a sandboxed plugin's state over its link, from any thread
+#[derive(Clone)]
+pub struct BridgeStateReader(Arc<Link>);
+
+impl BridgeStateReader {
+    pub fn state(&self) -> Result<Vec<u8>, Vst3Error> {
+        match self.0.call(&Request::State)? {
+            Reply::State(state) => {
+                self.0.remember_state(state.clone());
+                Ok(state)
+            }
+            _ => Err(unexpected_reply()),
+        }
+    }
+}
+
+/// Host side of a plugin running in a sandbox process
+pub struct BridgedPlugin {
+    shared: Arc<SharedMemory>,
+    link: Arc<Link>,
+    /// Events for the next block (audio thread)
+    pending: Vec<BridgeEvent>,
+    /// Frames of the block the child was last handed, whose output the next
+    /// `process` call plays (0 when there is none)
+    submitted_frames: usize,
+    parameter_count: usize,
+}
+
+impl BridgedPlugin {
+    pub fn launch(info: &Vst3PluginInfo, sample_rate: f32) -> Result<Self, Vst3Error> {
+        let shared = Arc::new(SharedMemory::create()?);
+        let launch = Launch {
+            name: info.name.clone(),
+            bundle: info.info.path.clone(),
+            unique_id: info.info.unique_id.clone(),
+            shm_path: shared.path.clone(),
+        };
+        let process = HostProcess::spawn(&launch, sample_rate)?;
+        let link = Arc::new(Link {
+            launch,
+            process: Mutex::new(Some(process)),
+            sample_rate: AtomicU32::new(sample_rate.to_bits()),
+            last_state: Mutex::new(None),
+            restarts: AtomicU32::new(0),
+            shutdown: AtomicBool::new(false),
+        });
+
+        let mut plugin = Self {
+            shared,
+            link,
+            pending: Vec::with_capacity(MAX_EVENTS),
+            submitted_frames: 0,
+            parameter_count: 0,
+        };
+        plugin.parameter_count = plugin.params().len();
+
+        // Holds only the link: the shared memory goes away with the instrument
+        let watchdog = plugin.link.clone();
+        thread::Builder::new()
+            .name("vst3-bridge-watchdog".to_string())
+            .spawn(move || watchdog.watch())?;
+
+        info!(plugin = %info.name, "Plugin running in sandbox host");
+        Ok(plugin)
+    }
+
+    /// Times the host process was restarted after a crash
+    pub fn restarts(&self) -> u32 {
+        self.link.restarts.load(Ordering::Relaxed)
+    }
+
+    pub fn params(&self) -> Vec<EffectParam> {
+        match self.link.call(&Request::Params) {
+            Ok(Reply::Params(params)) => params.into_iter().map(EffectParam::from).collect(),
+            Ok(_) => Vec::new(),
+            Err(e) => {
+                warn!("Failed to read parameters from plugin host: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    pub fn parameter_count(&self) -> usize {
+        self.parameter_count
+    }
+
+    pub fn set_parameter(&mut self, index: usize, value: f32) -> Result<(), Vst3Error> {
+        if index >= self.parameter_count {
+            return Err(Vst3Error::ProcessError(format!("no parameter {}", index)));
+        }
+        self.push_event(BridgeEvent {
+            kind: EVENT_PARAM,
+            index: index as u32,
+            value,
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    pub fn queue_note(&mut self, note_on: bool, pitch: u8, velocity: u8, channel: u8, sample_offset: u32) {
+        self.push_event(BridgeEvent {
+            kind: if note_on { EVENT_NOTE_ON } else { EVENT_NOTE_OFF },
+            index: pitch as u32,
+            channel: channel as u32,
+            sample_offset,
+            value: velocity as f32,
+        });
+    }
+
+    pub fn queue_control(&mut self, control: MidiControl, value: u16, channel: u8, sample_offset: u32) {
+        self.push_event(BridgeEvent {
+            kind: EVENT_CONTROL,
+            index: control_index(control),
+            channel: channel as u32,
+            sample_offset,
+            value: value as f32,
+        });
+    }
+
+    /// Events beyond a block's worth are dropped (e.g. while the host restarts)
+    fn push_event(&mut self, event: BridgeEvent) {
+        if self.pending.len() < MAX_EVENTS {
+            self.pending.push(event);
+        }
+    }
+
+    /// Play the output of the block handed to the child last time and hand it
+    /// this one; never waits for the child
+    pub fn process(&mut self, outputs: [&mut [f32]; 2], frames: usize) -> Result<(), Vst3Error> {
+        let [left, right] = outputs;
+        let frames = frames.min(MAX_BLOCK_SIZE).min(left.len()).min(right.len());
+        left[..frames].fill(0.0);
+        right[..frames].fill(0.0);
+        let block = self.shared.block();
+
+        let request = block.request.load(Ordering::Relaxed);
+        if block.response.load(Ordering::Acquire) != request {
+            // Still on an earlier block, or restarting; this block is silent and events wait
+            self.submitted_frames = 0;
+            return Ok(());
+        }
+
+        // Safety: the child wrote the output before answering and is idle until `request` changes
+        let output = unsafe { &*block.output.get() };
+        let ready = self.submitted_frames.min(frames);
+        left[..ready].copy_from_slice(&output[0][..ready]);
+        right[..ready].copy_from_slice(&output[1][..ready]);
+
+        let count = self.pending.len().min(MAX_EVENTS);
+        // Safety: as above
+        let events = unsafe { &mut *block.events.get() };
+        events[..count].copy_from_slice(&self.pending[..count]);
+        self.pending.drain(..count);
+        block.event_count.store(count as u32, Ordering::Relaxed);
+        block.frames.store(frames as u32, Ordering::Relaxed);
+        block.request.store(request.wrapping_add(1), Ordering::Release);
+        self.submitted_frames = frames;
+        Ok(())
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) -> Result<(), Vst3Error> {
+        self.link.sample_rate.store(sample_rate.to_bits(), Ordering::Relaxed);
+        match self.link.call(&Request::SetSampleRate(sample_rate))? {
+            Reply::Done => Ok(()),
+            _ => Err(unexpected_reply()),
+        }
+    }
+
+    pub fn state(&self) -> Result<Vec<u8>, Vst3Error> {
+        self.state_reader().state()
+    }
+
+    pub fn state_reader(&self) -> BridgeStateReader {
+        BridgeStateReader(Arc::clone(&self.link))
+    }
+
+    pub fn set_state(&mut self, chunk: &[u8]) -> Result<(), Vst3Error> {
+        match self.link.call(&Request::SetState(chunk.to_vec()))? {
+            Reply::Done => {
+                self.link.remember_state(chunk.to_vec());
+                Ok(())
+            }
+            _ => Err(unexpected_reply()),
+        }
+    }
+
+    pub fn program_names(&self) -> Vec<String> {
+        match self.link.call(&Request::Programs) {
+            Ok(Reply::Programs(names)) => names,
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn current_program(&self) -> Option<usize> {
+        match self.link.call(&Request::CurrentProgram) {
+            Ok(Reply::CurrentProgram(index)) => index,
+            _ => None,
+        }
+    }
+
+    pub fn set_program(&mut self, index: usize) -> Result<(), Vst3Error> {
+        match self.link.call(&Request::SetProgram(index))? {
+            Reply::Done => Ok(()),
+            _ => Err(unexpected_reply()),
+        }
+    }
+}
+
+impl Drop for BridgedPlugin {
+    fn drop(&mut self) {
+        self.link.shutdown.store(true, Ordering::Relaxed);
+        if let Ok(mut slot) = self.link.process.lock()
+            && let Some(mut process) = slot.take()
+        {
+            process.kill();
+        }
+    }
+}
+
+/// Run this process as the sandbox host of one plugin and return its exit code
+///
+/// `args` are the arguments after `PLUGIN_HOST_ARG`. Stdout carries the
+/// control channel, so it is moved aside first and anything the plugin prints
+/// ends up on stderr.
+pub fn run_plugin_host(args: &[String]) -> i32 {
+    let mut control = match take_stdout() {
+        Ok(control) => control,
+        Err(e) => {
+            error!("Failed to set up plugin host control channel: {}", e);
+            return 1;
+        }
+    };
+    let [shm_path, bundle, unique_id, sample_rate] = args else {
+        error!("Usage: {} <shared memory> <bundle> <class id> <sample rate>", PLUGIN_HOST_ARG);
+        return 2;
+    };
+    let Ok(sample_rate) = sample_rate.parse::<f32>() else {
+        error!("Invalid sample rate: {}", sample_rate);
+        return 2;
+    };
+
+    match serve(&mut control, Path::new(shm_path), bundle, unique_id, sample_rate) {
+        Ok(()) => 0,
+        Err(e) => {
+            error!(bundle = %bundle, "Plugin host failed: {}", e);
+            let _ = write_line(&mut control, &Reply::Error(e.to_string()));
+            1
+        }
+    }
+}
+
+/// Duplicate stdout for the control channel, then point stdout at stderr
+fn take_stdout() -> std::io::Result<File> {
+    let control = File::from(std::io::stdout().as_fd().try_clone_to_owned()?);
+    // Safety: only replaces descriptor 1, which nothing else owns
+    if unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(control)
+}
+
+fn serve(
+    control: &mut File,
+    shm_path: &Path,
+    bundle: &str,
+    unique_id: &str,
+    sample_rate: f32,
+) -> Result<(), Vst3Error> {
+    let shared = SharedMemory::open(shm_path)?;
+    let block = shared.block();
+    let mut plugin = InProcess::open(bundle, unique_id, sample_rate, MAX_BLOCK_SIZE)?;
+
+    // Answer whatever block the host is waiting on (from a crashed
+    // predecessor) with silence, then start serving
+    // Safety: the host doesn't read the output until `response` changes
+    unsafe { (*block.output.get()).iter_mut().for_each(|channel| channel.fill(0.0)) };
+    let mut served = block.request.load(Ordering::Acquire);
+    block.response.store(served, Ordering::Release);
+    write_line(control, &Reply::Ready)?;
+
+    let (request_tx, requests) = crossbeam_channel::unbounded();
+    thread::Builder::new()
+        .name("plugin-host-control".to_string())
+        .spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                let request = serde_json::from_str::<Request>(&line).map_err(|e| e.to_string());
+                if request_tx.send(request).is_err() {
+                    break;
+                }
+            }
+        })?;
+
+    let mut buffers: [Vec<f32>; 4] = std::array::from_fn(|_| vec![0.0; MAX_BLOCK_SIZE]);
+    let mut last_block = Instant::now();
+    loop {
+        let request = block.request.load(Ordering::Acquire);
+        if request != served {
+            process_block(&mut plugin, block, &mut buffers);
+            served = request;
+            block.response.store(request, Ordering::Release);
+            last_block = Instant::now();
+            continue;
+        }
+
+        match requests.try_recv() {
+            Ok(Ok(request)) => write_line(control, &handle_request(&mut plugin, request))?,
+            Ok(Err(e)) => write_line(control, &Reply::Error(format!("malformed request: {}", e)))?,
+            // The host closed the channel: the instrument is gone
+            Err(TryRecvError::Disconnected) => return Ok(()),
+            Err(TryRecvError::Empty) if last_block.elapsed() > CHILD_IDLE_AFTER => {
+                thread::sleep(Duration::from_millis(1));
+            }
+            Err(TryRecvError::Empty) => thread::yield_now(),
+        }
+    }
+}
+
+/// Controller as a bridge event index: CC number, 128 aftertouch, 129 pitch bend, then poly pressure keys
+fn control_index(control: MidiControl) -> u32 {
+    match control {
+        MidiControl::Cc(cc) => cc as u32,
+        MidiControl::ChannelPressure => 128,
+        MidiControl::PitchBend => 129,
+        MidiControl::PolyPressure(pitch) => POLY_PRESSURE_INDEX + pitch as u32,
+    }
+}
+
+fn control_from_index(index: u32) -> Option<MidiControl> {
+    match index {
+        0..=127 => Some(MidiControl::Cc(index as u8)),
+        128 => Some(MidiControl::ChannelPressure),
+        129 => Some(MidiControl::PitchBend),
+        _ => Some(MidiControl::PolyPressure(u8::try_from(index.checked_sub(POLY_PRESSURE_INDEX)?).ok()?)),
+    }
+}
+
+fn process_block(plugin: &mut InProcess, block: &SharedBlock, buffers: &mut [Vec<f32>; 4]) {
+    let frames = (block.frames.load(Ordering::Relaxed) as usize).min(MAX_BLOCK_SIZE);
+    let count = (block.event_count.load(Ordering::Relaxed) as usize).min(MAX_EVENTS);
+
+    // Safety: the host doesn't touch the events until this block is answered
+    let events = unsafe { &*block.events.get() };
+    for event in &events[..count] {
+        match event.kind {
+            EVENT_NOTE_ON | EVENT_NOTE_OFF => plugin.queue_note(
+                event.kind == EVENT_NOTE_ON,
+                event.index as u8,
+                event.value as u8,
+                event.channel as u8,
+                event.sample_offset,
+            ),
+            EVENT_PARAM => {
+                if let Err(e) = plugin.set_parameter(event.index as usize, event.value) {
+                    warn!("Failed to set parameter {}: {}", event.index, e);
+                }
+            }
+            EVENT_CONTROL => {
+                if let Some(control) = control_from_index(event.index) {
+                    plugin.queue_control(control, event.value as u16, event.channel as u8, event.sample_offset);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let [in_left, in_right, out_left, out_right] = buffers;
+    in_left[..frames].fill(0.0);
+    in_right[..frames].fill(0.0);
+    out_left[..frames].fill(0.0);
+    out_right[..frames].fill(0.0);
+    if let Err(e) = plugin.process(
+        [&mut in_left[..frames], &mut in_right[..frames]],
+        [&mut out_left[..frames], &mut out_right[..frames]],
+        frames,
+    ) {
+        warn!("Plugin process error: {}", e);
+    }
+
+    // Safety: the host doesn't read the output until this block is answered
+    let output = unsafe { &mut *block.output.get() };
+    output[0][..frames].copy_from_slice(&out_left[..frames]);
+    output[1][..frames].copy_from_slice(&out_right[..frames]);
+}
+
+fn handle_request(plugin: &mut InProcess, request: Request) -> Reply {
+    let result = match request {
+        Request::Params => Ok(Reply::Params(plugin.params().iter().map(ParamSnapshot::from).collect())),
+        Request::State => plugin.state().map(Reply::State),
+        Request::SetState(chunk) => plugin.set_state(&chunk).map(|()| Reply::Done),
+        Request::Programs => Ok(Reply::Programs(plugin.program_names())),
+        Request::CurrentProgram => Ok(Reply::CurrentProgram(plugin.current_program())),
+        Request::SetProgram(index) => plugin.set_program(index).map(|()| Reply::Done),
+        Request::SetSampleRate(sample_rate) => plugin.set_sample_rate(sample_rate).map(|()| Reply::Done),
+    };
+    result.unwrap_or_else(|e| Reply::Error(e.to_string()))
+}
@@ -14,6 +14,20 @@ pub enum Vst3Error {
     ProcessError(String),
     #[error("No plugins found in scan paths")]
     NoPluginsFound,
+    #[error("Plugin state save/restore is not supported on this platform")]
+    StateUnsupported,
+    #[error("Saved state belongs to plugin {0}")]
+    StateMismatch(String),
+    #[error("Invalid .vstpreset file: {0}")]
+    InvalidPreset(String),
+    #[error("Preset is for another plugin (class {0})")]
+    PresetMismatch(String),
+    #[error("Sandboxed plugins are not supported on this platform")]
+    SandboxUnsupported,
+    #[error("Plugin has no program {0}")]
+    NoSuchProgram(usize),
+    #[error("Plugin has no auxiliary output {0}")]
+    NoSuchOutput(usize),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
@@ -2,10 +2,23 @@
 //!
 //! This module provides native plugin GUI windows for VST3 plugins.
 //! On Linux, it creates X11 windows and embeds the plugin view using XEmbed.
+//! Windows follow the editor's size: resizable editors can be resized from the
+//! window edge or from inside the plugin UI, fixed-size ones are locked.
+//! The view belongs to the same plugin instance the audio engine processes
+//! (see `shared`), so edits and preset loads in the editor are heard directly.
+//!
+//! Keyboard focus follows the window: activating it focuses the editor, and
+//! keys the editor's own window doesn't take are forwarded to the view, so
+//! typing into plugin text fields never reaches the DAW.
+//!
+//! VST3 editors on Linux can only embed into X11 windows. On a Wayland session
+//! the editor windows are opened through XWayland: they are top-level windows
+//! (not children of the host window), so the compositor manages them like any
+//! other X11 client.
 
-use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
-use std::hash::{Hash, Hasher};
+#[cfg(target_os = "linux")]
+use std::collections::HashSet;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
@@ -13,7 +26,12 @@ use thiserror::Error;
 use tracing::info;
 
 #[cfg(target_os = "linux")]
-use rack_vst3_gui::Vst3Gui;
+use rack_vst3_gui::EditGesture;
+
+#[cfg(target_os = "linux")]
+use super::x11_keys::Keymap;
+
+use super::shared::Vst3InstanceHandle;
 
 #[derive(Debug, Error)]
 pub enum Vst3GuiError {
@@ -29,6 +47,32 @@ pub enum Vst3GuiError {
     PluginGui(String),
 }
 
+/// Display server the plugin editor windows are opened on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuiBackend {
+    /// Native X11 session
+    X11,
+    /// Wayland session, editors opened as XWayland windows
+    XWayland,
+}
+
+impl GuiBackend {
+    /// Pick the backend for this session from the environment
+    #[cfg(target_os = "linux")]
+    fn detect() -> Result<Self, Vst3GuiError> {
+        let set = |var: &str| std::env::var_os(var).is_some_and(|v| !v.is_empty());
+        let wayland = set("WAYLAND_DISPLAY")
+            || std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t.eq_ignore_ascii_case("wayland"));
+        match (wayland, set("DISPLAY")) {
+            (false, _) => Ok(Self::X11),
+            (true, true) => Ok(Self::XWayland),
+            (true, false) => Err(Vst3GuiError::X11Connection(
+                "Wayland session without XWayland (DISPLAY is unset); plugin editors need XWayland".to_string(),
+            )),
+        }
+    }
+}
+
 /// Native window handle for plugin GUI embedding
 #[derive(Debug, Clone, Copy)]
 pub struct NativeWindowHandle {
@@ -41,54 +85,76 @@ pub struct NativeWindowHandle {
 unsafe impl Send for NativeWindowHandle {}
 unsafe impl Sync for NativeWindowHandle {}
 
+/// One step of a parameter change made in a plugin editor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamGesture {
+    /// The user grabbed the parameter's control
+    Begin,
+    /// New normalized value; `touched` is whether it came from the user holding the
+    /// control, as opposed to a preset load or the plugin moving the parameter itself
+    Change { value: f64, touched: bool },
+    /// The user let go of the control
+    End,
+}
+
 /// Plugin GUI window state
 pub struct PluginGuiWindow {
     pub plugin_id: u64,
-    pub plugin_path: String,
-    pub plugin_uid: String,
     pub title: String,
     pub width: u32,
     pub height: u32,
     pub visible: bool,
     pub native_handle: Option<NativeWindowHandle>,
-    #[cfg(target_os = "linux")]
-    pub vst3_gui: Option<Vst3Gui>,
+    /// Plugin instance shared with the audio engine
+    pub instance: Vst3InstanceHandle,
     /// Last known parameter values for change detection
     #[cfg(target_os = "linux")]
     last_params: Vec<f64>,
-    /// Last known component state hash for preset change detection
-    #[cfg(target_os = "linux")]
-    last_state_hash: u64,
-    /// Frame counter for state sync debouncing
+    /// Parameters whose control the user is holding (between Begin and End)
     #[cfg(target_os = "linux")]
-    state_check_counter: u32,
+    touched: HashSet<usize>,
 }
 
 /// Manager for plugin GUI windows
 pub struct PluginGuiManager {
     windows: HashMap<u64, PluginGuiWindow>,
+    backend: Option<GuiBackend>,
     #[cfg(target_os = "linux")]
     x11_connection: Option<Arc<x11rb::rust_connection::RustConnection>>,
+    /// Keyboard layout, for forwarding keys to editors
+    #[cfg(target_os = "linux")]
+    keymap: Option<Keymap>,
 }
 
 impl PluginGuiManager {
     pub fn new() -> Self {
         Self {
             windows: HashMap::new(),
+            backend: None,
             #[cfg(target_os = "linux")]
             x11_connection: None,
+            #[cfg(target_os = "linux")]
+            keymap: None,
         }
     }
 
     /// Initialize the window manager (connect to display server)
     #[cfg(target_os = "linux")]
     pub fn initialize(&mut self) -> Result<(), Vst3GuiError> {
+        let backend = GuiBackend::detect()?;
 
-        let (conn, _screen_num) = x11rb::connect(None)
-            .map_err(|e| Vst3GuiError::X11Connection(e.to_string()))?;
+        // On Wayland, DISPLAY points at XWayland (started on demand by most compositors)
+        let (conn, _screen_num) = x11rb::connect(None).map_err(|e| {
+            let hint = if backend == GuiBackend::XWayland { " (is XWayland enabled?)" } else { "" };
+            Vst3GuiError::X11Connection(format!("{e}{hint}"))
+        })?;
 
+        self.keymap = Keymap::load(&conn)
+            .inspect_err(|e| tracing::warn!("Plugin editors won't receive forwarded keys: {}", e))
+            .ok();
         self.x11_connection = Some(Arc::new(conn));
-        info!("X11 connection established for plugin GUIs");
+        self.backend = Some(backend);
+        info!(?backend, "X11 connection established for plugin GUIs");
         Ok(())
     }
 
@@ -98,13 +164,17 @@ impl PluginGuiManager {
         Ok(())
     }
 
+    /// Display server editor windows open on, once initialized
+    pub fn backend(&self) -> Option<GuiBackend> {
+        self.backend
+    }
+
     /// Create a new plugin GUI window with native VST3 view
     #[cfg(target_os = "linux")]
     pub fn create_window(
         &mut self,
         plugin_id: u64,
-        plugin_path: &str,
-        plugin_uid: &str,
+        instance: Vst3InstanceHandle,
         title: &str,
         default_width: u32,
         default_height: u32,
@@ -115,14 +185,17 @@ impl PluginGuiManager {
         };
         use x11rb::wrapper::ConnectionExt as WrapperConnectionExt;
 
-        // First, create the VST3 GUI handle to get the actual plugin view size
-        let vst3_gui = Vst3Gui::new(plugin_path, plugin_uid)
-            .map_err(|e| Vst3GuiError::PluginGui(format!("Failed to create VST3 GUI: {}", e)))?;
+        // The editor view is created on the instrument's own plugin instance
+        let vst3_gui = &instance.plugin;
 
         // Get the preferred size from the plugin
-        let (width, height) = vst3_gui.get_size()
-            .map(|(w, h)| (w as u32, h as u32))
-            .unwrap_or((default_width, default_height));
+        let (width, height) = match vst3_gui.get_size() {
+            Ok((w, h)) => (w as u32, h as u32),
+            Err(rack_vst3_gui::Vst3GuiError::NoView) => {
+                return Err(Vst3GuiError::ViewCreation("plugin has no editor".to_string()));
+            }
+            Err(_) => (default_width, default_height),
+        };
 
         info!(plugin_id, width, height, "Plugin requested size");
 
@@ -143,6 +216,7 @@ impl PluginGuiManager {
                     | EventMask::BUTTON_RELEASE
                     | EventMask::POINTER_MOTION
                     | EventMask::STRUCTURE_NOTIFY
+                    | EventMask::FOCUS_CHANGE
             );
 
         conn.create_window(
@@ -167,6 +241,15 @@ impl PluginGuiManager {
             x11rb::protocol::xproto::AtomEnum::STRING,
             title.as_bytes(),
         ).map_err(|e: x11rb::errors::ConnectionError| Vst3GuiError::WindowCreation(e.to_string()))?;
+        set_window_identity(conn.as_ref(), window_id, title)?;
+
+        // Accept keyboard focus from the window manager
+        let mut wm_hints = x11rb::properties::WmHints::new();
+        wm_hints.input = Some(true);
+        wm_hints.set(conn.as_ref(), window_id)
+            .map_err(|e| Vst3GuiError::WindowCreation(e.to_string()))?;
+
+        set_size_hints(conn.as_ref(), window_id, width, height, vst3_gui.can_resize())?;
 
         conn.flush().map_err(|e: x11rb::errors::ConnectionError| Vst3GuiError::WindowCreation(e.to_string()))?;
 
@@ -186,28 +269,16 @@ impl PluginGuiManager {
         info!(plugin_id, param_count, "GUI instance has parameters");
         let last_params = vst3_gui.get_all_parameters();
 
-        // Get initial state hash for preset change detection
-        let last_state_hash = vst3_gui.get_component_state()
-            .map(|state| {
-                let mut hasher = DefaultHasher::new();
-                state.hash(&mut hasher);
-                hasher.finish()
-            })
-            .unwrap_or(0);
-
         let window = PluginGuiWindow {
             plugin_id,
-            plugin_path: plugin_path.to_string(),
-            plugin_uid: plugin_uid.to_string(),
             title: title.to_string(),
             width,
             height,
             visible: false,
             native_handle: Some(native_handle),
-            vst3_gui: Some(vst3_gui),
+            instance,
             last_params,
-            last_state_hash,
-            state_check_counter: 0,
+            touched: HashSet::new(),
         };
 
         self.windows.insert(plugin_id, window);
@@ -220,21 +291,19 @@ impl PluginGuiManager {
     pub fn create_window(
         &mut self,
         plugin_id: u64,
-        plugin_path: &str,
-        plugin_uid: &str,
+        instance: Vst3InstanceHandle,
         title: &str,
         width: u32,
         height: u32,
     ) -> Result<(), Vst3GuiError> {
         let window = PluginGuiWindow {
             plugin_id,
-            plugin_path: plugin_path.to_string(),
-            plugin_uid: plugin_uid.to_string(),
             title: title.to_string(),
             width,
             height,
             visible: false,
             native_handle: None,
+            instance,
         };
         self.windows.insert(plugin_id, window);
         tracing::warn!("Native plugin GUI not available on this platform");
@@ -308,14 +377,12 @@ impl PluginGuiManager {
         use x11rb::connection::Connection;
         use x11rb::protocol::xproto::ConnectionExt;
 
-        let Some(mut window) = self.windows.remove(&plugin_id) else {
+        let Some(window) = self.windows.remove(&plugin_id) else {
             return Ok(());
         };
 
-        // Detach VST3 plugin view first (will be dropped after detach)
-        if let Some(vst3_gui) = window.vst3_gui.take() {
-            vst3_gui.detach();
-        }
+        // Detach the view first; the plugin instance lives on in the instrument
+        window.instance.plugin.detach();
 
         // Destroy X11 window if both handle and connection exist
         let Some(handle) = window.native_handle else { return Ok(()) };
@@ -346,34 +413,109 @@ impl PluginGuiManager {
 
     /// Process pending window events (call periodically from main thread)
     #[cfg(target_os = "linux")]
-    pub fn process_events(&self) -> Result<(), Vst3GuiError> {
+    pub fn process_events(&mut self) -> Result<(), Vst3GuiError> {
         use x11rb::connection::Connection;
+        use x11rb::protocol::Event;
+        use x11rb::protocol::xproto::NotifyDetail;
 
-        let Some(conn) = &self.x11_connection else {
+        let Some(conn) = self.x11_connection.clone() else {
             return Ok(());
         };
 
-        // Pump event queue - events handled by plugin's embedded view
-        while conn.poll_for_event().ok().flatten().is_some() {}
+        // Input goes to the plugin's embedded view; we follow window resizes and
+        // focus, and forward keys that reach the host window instead of the view
+        while let Some(event) = conn.poll_for_event().ok().flatten() {
+            match event {
+                Event::ConfigureNotify(e) => {
+                    if let Some(window) = self.window_for(e.window) {
+                        window.window_resized(conn.as_ref(), e.width as u32, e.height as u32)?;
+                    }
+                }
+                Event::KeyPress(e) => self.forward_key(&e, true),
+                Event::KeyRelease(e) => self.forward_key(&e, false),
+                // Focus moving between our window and the editor inside it is not a change
+                Event::FocusIn(e) if e.detail != NotifyDetail::INFERIOR => {
+                    if let Some(window) = self.window_for(e.event) {
+                        window.focus_changed(conn.as_ref(), true)?;
+                    }
+                }
+                Event::FocusOut(e) if e.detail != NotifyDetail::INFERIOR => {
+                    if let Some(window) = self.window_for(e.event) {
+                        window.focus_changed(conn.as_ref(), false)?;
+                    }
+                }
+                Event::MappingNotify(_) => {
+                    self.keymap = Keymap::load(conn.as_ref()).ok();
+                }
+                _ => {}
+            }
+        }
+
+        for window in self.windows.values_mut() {
+            // Let editors follow host and processor-side parameter changes
+            window.instance.plugin.idle();
+            // Resizes requested from inside the plugin UI
+            if let Some((width, height)) = window.instance.plugin.take_resize_request() {
+                window.resize_to(conn.as_ref(), width.max(1) as u32, height.max(1) as u32)?;
+            }
+        }
 
+        conn.flush().map_err(|e| Vst3GuiError::WindowCreation(e.to_string()))?;
         Ok(())
     }
 
     #[cfg(not(target_os = "linux"))]
-    pub fn process_events(&self) -> Result<(), Vst3GuiError> {
+    pub fn process_events(&mut self) -> Result<(), Vst3GuiError> {
         Ok(())
     }
 
-    /// Get parameter changes from all visible GUI windows
-    /// Returns: Vec<(plugin_id, param_index, new_value)>
     #[cfg(target_os = "linux")]
-    pub fn get_parameter_changes(&mut self) -> Vec<(u64, usize, f64)> {
+    fn window_for(&mut self, x11_window: u32) -> Option<&mut PluginGuiWindow> {
+        self.windows.values_mut().find(|w| w.x11_window() == Some(x11_window))
+    }
+
+    /// Pass a key that reached the host window (not the editor's own) to the view
+    #[cfg(target_os = "linux")]
+    fn forward_key(&self, event: &x11rb::protocol::xproto::KeyPressEvent, down: bool) {
+        let Some(keymap) = &self.keymap else { return };
+        let Some(window) = self.windows.values().find(|w| w.x11_window() == Some(event.event)) else {
+            return;
+        };
+        let input = keymap.translate(event.detail, event.state);
+        window.instance.plugin.key_event(down, input.character, input.key, input.modifiers);
+    }
+
+    /// Get parameter changes from all visible GUI windows, in order, with the gesture
+    /// boundaries the editors reported around the user's edits
+    /// Returns: Vec<(plugin_id, param_index, gesture)>
+    #[cfg(target_os = "linux")]
+    pub fn get_parameter_changes(&mut self) -> Vec<(u64, usize, ParamGesture)> {
         static POLL_COUNTER: AtomicU32 = AtomicU32::new(0);
 
         let mut changes = Vec::new();
 
         for window in self.windows.values_mut().filter(|w| w.visible) {
-            let Some(ref gui) = window.vst3_gui else { return changes };
+            let gui = &window.instance.plugin;
+
+            // Edits the editor reported, with their gestures
+            let mut reported = HashSet::new();
+            for edit in gui.take_edits() {
+                let gesture = match edit.gesture {
+                    EditGesture::Begin => {
+                        window.touched.insert(edit.index);
+                        ParamGesture::Begin
+                    }
+                    EditGesture::Perform(value) => {
+                        reported.insert(edit.index);
+                        ParamGesture::Change { value, touched: window.touched.contains(&edit.index) }
+                    }
+                    EditGesture::End => {
+                        window.touched.remove(&edit.index);
+                        ParamGesture::End
+                    }
+                };
+                changes.push((window.plugin_id, edit.index, gesture));
+            }
 
             let current_params = gui.get_all_parameters();
 
@@ -387,10 +529,11 @@ impl PluginGuiManager {
                 );
             }
 
-            // Detect changed parameters
+            // Detect parameters changed without an edit (presets, the plugin's own changes)
             let window_changes: Vec<_> = current_params
                 .iter()
                 .enumerate()
+                .filter(|(i, _)| !reported.contains(i))
                 .filter_map(|(i, &new_val)| {
                     let old_val = window.last_params.get(i).copied().unwrap_or(0.0);
                     ((new_val - old_val).abs() > 0.0001).then(|| {
@@ -398,7 +541,7 @@ impl PluginGuiManager {
                             "GUI param change detected: plugin_id={} param[{}] {} -> {}",
                             window.plugin_id, i, old_val, new_val
                         );
-                        (window.plugin_id, i, new_val)
+                        (window.plugin_id, i, ParamGesture::Change { value: new_val, touched: false })
                     })
                 })
                 .collect();
@@ -411,47 +554,160 @@ impl PluginGuiManager {
     }
 
     #[cfg(not(target_os = "linux"))]
-    pub fn get_parameter_changes(&mut self) -> Vec<(u64, usize, f64)> {
+    pub fn get_parameter_changes(&mut self) -> Vec<(u64, usize, ParamGesture)> {
         Vec::new()
     }
 
-    /// Get component state changes (for preset/patch sync)
-    /// Returns: Vec<(plugin_id, state_bytes)>
-    /// Checks every 60 frames (~1 sec at 60fps) to detect preset loads
+    /// Whether the user is holding a parameter's control in the plugin's editor
     #[cfg(target_os = "linux")]
-    pub fn get_state_changes(&mut self) -> Vec<(u64, Vec<u8>)> {
-        self.windows
-            .values_mut()
-            .filter(|w| w.visible && w.vst3_gui.is_some())
-            .filter_map(|window| {
-                window.state_check_counter += 1;
-                if window.state_check_counter < 60 {
-                    return None;
-                }
-                window.state_check_counter = 0;
+    pub fn is_param_touched(&self, plugin_id: u64, param_index: usize) -> bool {
+        self.windows.get(&plugin_id).is_some_and(|w| w.touched.contains(&param_index))
+    }
 
-                let gui = window.vst3_gui.as_ref()?;
-                let current_state = gui.get_component_state().ok()?;
+    #[cfg(not(target_os = "linux"))]
+    pub fn is_param_touched(&self, _plugin_id: u64, _param_index: usize) -> bool {
+        false
+    }
+}
 
-                let mut hasher = DefaultHasher::new();
-                current_state.hash(&mut hasher);
-                let current_hash = hasher.finish();
+#[cfg(target_os = "linux")]
+impl PluginGuiWindow {
+    fn x11_window(&self) -> Option<u32> {
+        self.native_handle.map(|h| h.x11_window)
+    }
 
-                if current_hash == window.last_state_hash {
-                    return None;
-                }
+    /// The window manager (or user) resized the window: fit the editor to it,
+    /// snapping the window to the editor's size constraints
+    fn window_resized(
+        &mut self,
+        conn: &impl x11rb::connection::Connection,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Vst3GuiError> {
+        if (width, height) == (self.width, self.height) {
+            return Ok(());
+        }
+        let plugin = &self.instance.plugin;
+        if !plugin.can_resize() {
+            // Fixed-size editor: put the window back
+            return self.resize_to(conn, self.width, self.height);
+        }
+        match plugin.set_size(width as i32, height as i32) {
+            Ok((w, h)) if (w.max(1) as u32, h.max(1) as u32) != (width, height) => {
+                self.resize_to(conn, w.max(1) as u32, h.max(1) as u32)
+            }
+            Ok(_) => {
+                self.width = width;
+                self.height = height;
+                Ok(())
+            }
+            Err(e) => {
+                tracing::warn!(plugin_id = self.plugin_id, "Plugin rejected resize: {}", e);
+                self.resize_to(conn, self.width, self.height)
+            }
+        }
+    }
+
+    /// The window gained or lost keyboard focus: tell the editor, and on gaining
+    /// it hand X input focus to the editor's own window so its text fields get typing
+    fn focus_changed(
+        &mut self,
+        conn: &impl x11rb::connection::Connection,
+        focused: bool,
+    ) -> Result<(), Vst3GuiError> {
+        use x11rb::protocol::xproto::{ConnectionExt, InputFocus};
+        use x11rb::CURRENT_TIME;
 
-                info!(plugin_id = window.plugin_id, "Component state changed (preset loaded)");
-                window.last_state_hash = current_hash;
-                Some((window.plugin_id, current_state))
-            })
-            .collect()
+        self.instance.plugin.set_focus(focused);
+        let Some(window_id) = self.x11_window() else { return Ok(()) };
+        if !focused || !self.visible {
+            return Ok(());
+        }
+        let editor = conn
+            .query_tree(window_id)
+            .map_err(|e| Vst3GuiError::X11Connection(e.to_string()))?
+            .reply()
+            .map_err(|e| Vst3GuiError::X11Connection(e.to_string()))?
+            .children
+            .first()
+            .copied();
+        if let Some(editor) = editor {
+            // Errors are async and harmless if the editor window went away meanwhile
+            conn.set_input_focus(InputFocus::PARENT, editor, CURRENT_TIME)
+                .map_err(|e| Vst3GuiError::X11Connection(e.to_string()))?;
+        }
+        Ok(())
     }
 
-    #[cfg(not(target_os = "linux"))]
-    pub fn get_state_changes(&mut self) -> Vec<(u64, Vec<u8>)> {
-        Vec::new()
+    /// Resize the X11 window to a size the editor has already taken
+    fn resize_to(
+        &mut self,
+        conn: &impl x11rb::connection::Connection,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Vst3GuiError> {
+        use x11rb::protocol::xproto::{ConfigureWindowAux, ConnectionExt};
+
+        let Some(window_id) = self.x11_window() else { return Ok(()) };
+        self.width = width;
+        self.height = height;
+        set_size_hints(conn, window_id, width, height, self.instance.plugin.can_resize())?;
+        conn.configure_window(window_id, &ConfigureWindowAux::new().width(width).height(height))
+            .map_err(|e| Vst3GuiError::WindowCreation(e.to_string()))?;
+        info!(plugin_id = self.plugin_id, width, height, "Resized plugin GUI window");
+        Ok(())
+    }
+}
+
+/// UTF-8 title and WM_CLASS, which Wayland compositors use to name and group
+/// XWayland windows (WM_NAME alone is Latin-1 and often ignored)
+#[cfg(target_os = "linux")]
+fn set_window_identity(
+    conn: &impl x11rb::connection::Connection,
+    window_id: u32,
+    title: &str,
+) -> Result<(), Vst3GuiError> {
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _, PropMode};
+    use x11rb::wrapper::ConnectionExt as _;
+
+    let err = |e: x11rb::errors::ConnectionError| Vst3GuiError::WindowCreation(e.to_string());
+    let reply_err = |e: x11rb::errors::ReplyError| Vst3GuiError::WindowCreation(e.to_string());
+
+    let net_wm_name = conn.intern_atom(false, b"_NET_WM_NAME").map_err(err)?;
+    let utf8_string = conn.intern_atom(false, b"UTF8_STRING").map_err(err)?;
+    let net_wm_name = net_wm_name.reply().map_err(reply_err)?.atom;
+    let utf8_string = utf8_string.reply().map_err(reply_err)?.atom;
+    conn.change_property8(PropMode::REPLACE, window_id, net_wm_name, utf8_string, title.as_bytes())
+        .map_err(err)?;
+
+    // WM_CLASS is "instance\0class\0"
+    conn.change_property8(PropMode::REPLACE, window_id, AtomEnum::WM_CLASS, AtomEnum::STRING, b"hallucinator-plugin\0Hallucinator\0")
+        .map_err(err)?;
+    Ok(())
+}
+
+/// Tell the window manager whether the window may be resized: fixed-size
+/// editors get equal minimum and maximum sizes
+#[cfg(target_os = "linux")]
+fn set_size_hints(
+    conn: &impl x11rb::connection::Connection,
+    window_id: u32,
+    width: u32,
+    height: u32,
+    resizable: bool,
+) -> Result<(), Vst3GuiError> {
+    use x11rb::properties::WmSizeHints;
+
+    let mut hints = WmSizeHints::new();
+    hints.size = Some((x11rb::properties::WmSizeHintsSpecification::ProgramSpecified, width as i32, height as i32));
+    if !resizable {
+        hints.min_size = Some((width as i32, height as i32));
+        hints.max_size = Some((width as i32, height as i32));
     }
+    hints
+        .set_normal_hints(conn, window_id)
+        .map_err(|e| Vst3GuiError::WindowCreation(e.to_string()))?;
+    Ok(())
 }
 
 impl Default for PluginGuiManager {
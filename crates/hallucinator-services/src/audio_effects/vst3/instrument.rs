@@ -2,17 +2,45 @@
 
 use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::thread::JoinHandle;
 
-use rack::{midi::MidiEvent, Plugin, PluginInstance, PluginScanner, Scanner};
+use crossbeam_channel::Receiver;
+use hallucinator_core::{MidiControl, PluginState};
+use rack::Scanner;
 use tracing::info;
 
 use super::error::Vst3Error;
+use super::preset_file::VstPreset;
 use super::scanner::Vst3PluginInfo;
+use super::shared::{OutputBus, PluginProcessor, StateSource, Vst3InstanceHandle};
 use crate::audio_effects::{AudioInstrument, EffectParam};
+use crate::notifications;
+
+/// Reads an instrument's plugin state without going through the instrument, so the state
+/// can be captured while the audio thread has the instrument locked for playback
+#[derive(Clone)]
+pub struct PluginStateReader {
+    plugin_uid: String,
+    name: String,
+    source: StateSource,
+}
+
+impl PluginStateReader {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn state(&self) -> Result<PluginState, Vst3Error> {
+        Ok(PluginState {
+            plugin_uid: self.plugin_uid.clone(),
+            chunk: self.source.state()?,
+        })
+    }
+}
 
 /// VST3 instrument that generates audio from MIDI input
 pub struct Vst3Instrument {
-    instance: Plugin,
+    instance: PluginProcessor,
     info: Vst3PluginInfo,
     sample_rate: f32,
     // Pre-allocated input buffers (silent, for instruments that require input)
@@ -21,13 +49,20 @@ pub struct Vst3Instrument {
     // Pre-allocated output buffers
     output_left: Vec<f32>,
     output_right: Vec<f32>,
+    // Output buses (main first) and pre-allocated buffers for each auxiliary bus
+    output_buses: Vec<OutputBus>,
+    aux_outputs: Vec<[Vec<f32>; 2]>,
+    // Frames rendered by the last `process` call
+    last_frames: usize,
     max_block_size: usize,
-    // Pending MIDI events for next process call
-    pending_events: Vec<MidiEvent>,
     // Parameter name -> index mapping
     param_map: HashMap<String, usize>,
     // Cached parameter info
     param_cache: Vec<EffectParam>,
+    // Program (factory preset) names, fixed for the plugin's lifetime
+    programs: Vec<String>,
+    // Selected program, cached so reading it never calls into the plugin (or its host process)
+    current_program: Option<usize>,
     // Track active notes (pitches with Note On but no Note Off yet)
     active_notes: HashSet<u8>,
 }
@@ -42,36 +77,68 @@ impl Vst3Instrument {
         info: &Vst3PluginInfo,
         sample_rate: f32,
     ) -> Result<Self, Vst3Error> {
-        let mut instance = scanner
-            .load(&info.info)
-            .map_err(|e| Vst3Error::LoadError(format!("{:?}", e)))?;
+        Self::load(scanner, info, sample_rate, false)
+    }
 
+    /// Create a VST3 instrument whose plugin runs in a separate host process,
+    /// so a crash only silences it until the host is restarted. Sandboxed
+    /// instruments have no native editor.
+    pub fn new_sandboxed(
+        scanner: &Scanner,
+        info: &Vst3PluginInfo,
+        sample_rate: f32,
+    ) -> Result<Self, Vst3Error> {
+        Self::load(scanner, info, sample_rate, true)
+    }
+
+    /// Load an instrument on a background thread, so a plugin that takes seconds to set
+    /// up doesn't hold up the caller; the job hands it over once it's ready
+    pub fn spawn_load(info: Vst3PluginInfo, sample_rate: f32, sandboxed: bool) -> Result<InstrumentLoadJob, Vst3Error> {
+        let (sender, result) = crossbeam_channel::bounded(1);
+        let thread = std::thread::Builder::new()
+            .name("vst3-load".to_string())
+            .spawn(move || {
+                let loaded = Scanner::new()
+                    .map_err(|e| Vst3Error::LoadError(format!("{:?}", e)))
+                    .and_then(|scanner| Self::load(&scanner, &info, sample_rate, sandboxed));
+                let _ = sender.send(loaded);
+            })?;
+        Ok(InstrumentLoadJob { result, thread: Some(thread) })
+    }
+
+    fn load(
+        scanner: &Scanner,
+        info: &Vst3PluginInfo,
+        sample_rate: f32,
+        sandboxed: bool,
+    ) -> Result<Self, Vst3Error> {
         let max_block_size = 4096;
-        instance
-            .initialize(sample_rate as f64, max_block_size)
-            .map_err(|e| Vst3Error::LoadError(format!("{:?}", e)))?;
+        let instance = PluginProcessor::load(scanner, info, sample_rate, max_block_size, sandboxed)?;
 
         // Build parameter map
-        let param_count = instance.parameter_count();
-        let param_data: Vec<_> = (0..param_count)
-            .filter_map(|i| {
-                let pinfo = instance.parameter_info(i).ok()?;
-                let current_value = instance.get_parameter(i).unwrap_or(pinfo.default);
-                Some((i, pinfo, current_value))
-            })
-            .collect();
-
-        let param_map: HashMap<_, _> = param_data
+        let param_cache = instance.params();
+        let param_map: HashMap<_, _> = param_cache
             .iter()
-            .map(|(i, pinfo, _)| (pinfo.name.clone(), *i))
+            .enumerate()
+            .map(|(i, param)| (param.name.clone(), i))
             .collect();
 
-        let param_cache: Vec<_> = param_data
-            .into_iter()
-            .map(|(_, pinfo, value)| EffectParam::new(&pinfo.name, value, pinfo.min, pinfo.max, &pinfo.unit))
+        let programs = instance.program_names();
+        let current_program = instance.current_program();
+        let output_buses = instance.output_buses();
+        let aux_outputs = (1..output_buses.len())
+            .map(|_| [vec![0.0; max_block_size], vec![0.0; max_block_size]])
             .collect();
 
-        info!(name = %info.name, sample_rate, params = param_count, "VST3 instrument loaded");
+        info!(
+            name = %info.name,
+            sample_rate,
+            params = param_cache.len(),
+            programs = programs.len(),
+            outputs = output_buses.len(),
+            sandboxed,
+            "VST3 instrument loaded"
+        );
 
         Ok(Self {
             instance,
@@ -81,10 +148,14 @@ impl Vst3Instrument {
             input_right: vec![0.0; max_block_size],
             output_left: vec![0.0; max_block_size],
             output_right: vec![0.0; max_block_size],
+            output_buses,
+            aux_outputs,
+            last_frames: 0,
             max_block_size,
-            pending_events: Vec::with_capacity(256),
             param_map,
             param_cache,
+            programs,
+            current_program,
             active_notes: HashSet::new(),
         })
     }
@@ -94,6 +165,112 @@ impl Vst3Instrument {
         &self.info
     }
 
+    /// Handle to this instrument's plugin instance, for opening its native editor
+    /// (None when sandboxed)
+    pub fn instance_handle(&self) -> Option<Vst3InstanceHandle> {
+        self.instance.handle(&self.info)
+    }
+
+    /// Times the sandbox host process was restarted after a crash (None when not sandboxed)
+    pub fn sandbox_restarts(&self) -> Option<u32> {
+        self.instance.sandbox_restarts()
+    }
+
+    /// Capture the plugin's full state (patch, samples, settings)
+    pub fn state(&self) -> Result<PluginState, Vst3Error> {
+        Ok(PluginState {
+            plugin_uid: self.info.info.unique_id.clone(),
+            chunk: self.instance.state()?,
+        })
+    }
+
+    /// Reader of the plugin's state for another thread (None where state can't be read)
+    pub fn state_reader(&self) -> Option<PluginStateReader> {
+        Some(PluginStateReader {
+            plugin_uid: self.info.info.unique_id.clone(),
+            name: self.info.name.clone(),
+            source: self.instance.state_source()?,
+        })
+    }
+
+    /// Restore a state captured by `state`, then refresh the cached parameters
+    pub fn restore_state(&mut self, state: &PluginState) -> Result<(), Vst3Error> {
+        if state.plugin_uid != self.info.info.unique_id {
+            return Err(Vst3Error::StateMismatch(state.plugin_uid.clone()));
+        }
+        self.instance.set_state(&state.chunk)?;
+        self.param_cache = self.instance.params();
+        self.current_program = self.instance.current_program();
+        Ok(())
+    }
+
+    /// Capture the plugin's state as a `.vstpreset`
+    pub fn save_preset(&self) -> Result<VstPreset, Vst3Error> {
+        Ok(VstPreset::new(&self.info.info.unique_id, self.instance.state()?))
+    }
+
+    /// Apply a `.vstpreset` saved from this plugin. The component state is
+    /// also handed to the controller, so a stored controller chunk is not needed.
+    pub fn load_preset(&mut self, preset: &VstPreset) -> Result<(), Vst3Error> {
+        if !preset.matches(&self.info.info.unique_id) {
+            return Err(Vst3Error::PresetMismatch(preset.class_id.clone()));
+        }
+        self.instance.set_state(&preset.component)?;
+        self.param_cache = self.instance.params();
+        self.current_program = self.instance.current_program();
+        Ok(())
+    }
+
+    /// Names of the plugin's programs (factory presets)
+    pub fn programs(&self) -> &[String] {
+        &self.programs
+    }
+
+    /// Currently selected program, if the plugin has programs
+    pub fn current_program(&self) -> Option<usize> {
+        self.current_program
+    }
+
+    /// Switch to a program, then refresh the cached parameters
+    pub fn set_program(&mut self, index: usize) -> Result<(), Vst3Error> {
+        if index >= self.programs.len() {
+            return Err(Vst3Error::NoSuchProgram(index));
+        }
+        self.instance.set_program(index)?;
+        self.param_cache = self.instance.params();
+        self.current_program = Some(index);
+        Ok(())
+    }
+
+    /// Output buses, main bus first (empty when the plugin only has a main output
+    /// or runs sandboxed)
+    pub fn output_buses(&self) -> &[OutputBus] {
+        &self.output_buses
+    }
+
+    /// Render an auxiliary output bus separately, or fold it back into the main
+    /// output. Restarts the plugin's processing, so call it from the UI thread.
+    pub fn set_output_enabled(&mut self, bus: usize, enabled: bool) -> Result<(), Vst3Error> {
+        let Some(output) = self.output_buses.get(bus).filter(|_| bus > 0) else {
+            return Err(Vst3Error::NoSuchOutput(bus));
+        };
+        if output.enabled == enabled {
+            return Ok(());
+        }
+        self.instance.enable_output_bus(bus, enabled)?;
+        self.output_buses[bus].enabled = enabled;
+        Ok(())
+    }
+
+    /// Audio of an enabled auxiliary output bus from the last `process` call
+    pub fn output_bus(&self, bus: usize) -> Option<(&[f32], &[f32])> {
+        if !self.output_buses.get(bus)?.enabled {
+            return None;
+        }
+        let [left, right] = self.aux_outputs.get(bus.checked_sub(1)?)?;
+        Some((&left[..self.last_frames], &right[..self.last_frames]))
+    }
+
     /// Get all parameters
     pub fn get_params(&self) -> &[EffectParam] {
         &self.param_cache
@@ -104,7 +281,7 @@ impl Vst3Instrument {
         let Some(&index) = self.param_map.get(name) else { return };
 
         if let Err(e) = self.instance.set_parameter(index, value) {
-            tracing::warn!("Failed to set parameter {}: {:?}", name, e);
+            tracing::warn!("Failed to set parameter {}: {}", name, e);
             return;
         }
 
@@ -124,72 +301,85 @@ impl Vst3Instrument {
         );
 
         if let Err(e) = self.instance.set_parameter(index, normalized) {
-            tracing::warn!("Failed to set parameter index {}: {:?}", index, e);
+            tracing::warn!("Failed to set parameter index {}: {}", index, e);
             return;
         }
 
         tracing::info!("Successfully set parameter {} = {}", index, normalized);
+        self.sync_param_from_editor(index, normalized_value);
+    }
 
-        // Update cache with denormalized value for display
+    /// Update the cached value of a parameter changed in the plugin's own editor
+    ///
+    /// The editor shares this instance, so the processor already has the value;
+    /// only the host-side display needs refreshing.
+    pub fn sync_param_from_editor(&mut self, index: usize, normalized_value: f64) {
         let Some(param) = self.param_cache.get_mut(index) else { return };
-        param.value = param.min + normalized * (param.max - param.min);
+        param.value = param.min + normalized_value as f32 * (param.max - param.min);
     }
 
     /// Queue a note on event
     pub fn queue_note_on(&mut self, pitch: u8, velocity: u8, channel: u8, sample_offset: u32) {
         self.active_notes.insert(pitch);
-        self.pending_events.push(MidiEvent::note_on(pitch, velocity, channel, sample_offset));
+        self.instance.queue_note(true, pitch, velocity, channel, sample_offset);
     }
 
     /// Queue a note off event
     pub fn queue_note_off(&mut self, pitch: u8, velocity: u8, channel: u8, sample_offset: u32) {
         self.active_notes.remove(&pitch);
-        self.pending_events.push(MidiEvent::note_off(pitch, velocity, channel, sample_offset));
+        self.instance.queue_note(false, pitch, velocity, channel, sample_offset);
+    }
+
+    /// Queue a controller, pitch bend or aftertouch change
+    pub fn queue_control(&mut self, control: MidiControl, value: u16, channel: u8, sample_offset: u32) {
+        self.instance.queue_control(control, value, channel, sample_offset);
     }
 
     /// Send note off for all currently active notes (used when loop wraps to stop hanging notes)
     pub fn all_notes_off(&mut self, sample_offset: u32) {
         for pitch in self.active_notes.drain() {
-            self.pending_events.push(MidiEvent::note_off(pitch, 0, 0, sample_offset));
+            self.instance.queue_note(false, pitch, 0, 0, sample_offset);
         }
     }
 
     /// Process pending MIDI events and generate audio
-    /// Returns stereo output buffers (left, right)
+    /// Returns stereo output buffers (left, right); enabled auxiliary buses are
+    /// read afterwards with `output_bus`
     pub fn process(&mut self, num_frames: usize) -> (&[f32], &[f32]) {
         let frames = num_frames.min(self.max_block_size);
-
-        // Send queued MIDI to plugin
-        self.flush_pending_midi();
+        self.last_frames = frames;
 
         // Clear output buffers
         self.output_left[..frames].fill(0.0);
         self.output_right[..frames].fill(0.0);
 
-        // Process with silent input buffers (instruments generate audio from MIDI)
-        let inputs: [&[f32]; 2] = [&self.input_left[..frames], &self.input_right[..frames]];
-        let mut outputs: [&mut [f32]; 2] = [
-            &mut self.output_left[..frames],
-            &mut self.output_right[..frames],
-        ];
-
-        if let Err(e) = self.instance.process(&inputs, &mut outputs, frames) {
-            tracing::warn!("VST3 instrument process error: {:?}", e);
+        // Process queued MIDI with silent input buffers (instruments generate audio from MIDI)
+        self.input_left[..frames].fill(0.0);
+        self.input_right[..frames].fill(0.0);
+        let inputs = [&mut self.input_left[..frames], &mut self.input_right[..frames]];
+        let outputs = [&mut self.output_left[..frames], &mut self.output_right[..frames]];
+
+        // Deliver buses up to the last enabled one; the plugin leaves disabled ones untouched
+        let last_bus = self.output_buses.iter().rposition(|b| b.enabled).unwrap_or(0);
+        let result = if last_bus == 0 {
+            self.instance.process(inputs, outputs, frames)
+        } else {
+            let mut buses = Vec::with_capacity(last_bus + 1);
+            buses.push(outputs);
+            for [left, right] in &mut self.aux_outputs[..last_bus] {
+                left[..frames].fill(0.0);
+                right[..frames].fill(0.0);
+                buses.push([&mut left[..frames], &mut right[..frames]]);
+            }
+            self.instance.process_buses(inputs, &mut buses, frames)
+        };
+        if let Err(e) = result {
+            tracing::warn!("VST3 instrument process error: {}", e);
         }
 
         (&self.output_left[..frames], &self.output_right[..frames])
     }
 
-    fn flush_pending_midi(&mut self) {
-        if self.pending_events.is_empty() {
-            return;
-        }
-        if let Err(e) = self.instance.send_midi(&self.pending_events) {
-            tracing::warn!("Failed to send MIDI: {:?}", e);
-        }
-        self.pending_events.clear();
-    }
-
     /// Set sample rate (reinitializes plugin)
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
         if (sample_rate - self.sample_rate).abs() < 1.0 {
@@ -198,17 +388,39 @@ impl Vst3Instrument {
 
         self.sample_rate = sample_rate;
 
-        if let Err(e) = self.instance.initialize(sample_rate as f64, self.max_block_size) {
-            tracing::error!("Failed to reinitialize VST3 instrument: {:?}", e);
+        if let Err(e) = self.instance.set_sample_rate(sample_rate) {
+            notifications::error("Failed to reinitialize VST3 instrument", e);
         }
     }
 }
 
+/// An instrument loading on a background thread
+pub struct InstrumentLoadJob {
+    result: Receiver<Result<Vst3Instrument, Vst3Error>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl InstrumentLoadJob {
+    /// The instrument or the error once loading is over (also if the loader thread died,
+    /// e.g. the plugin panicked while setting up), None while it's still going
+    pub fn poll(&mut self) -> Option<Result<Vst3Instrument, Vst3Error>> {
+        let stopped = self.thread.as_ref().is_none_or(|t| t.is_finished());
+        if let Ok(result) = self.result.try_recv() {
+            self.thread = None;
+            return Some(result);
+        }
+        if stopped && self.thread.take().is_some() {
+            return Some(Err(Vst3Error::LoadError("instrument loader stopped unexpectedly".to_string())));
+        }
+        None
+    }
+}
+
 impl fmt::Debug for Vst3Instrument {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Vst3Instrument")
             .field("name", &self.info.name)
-            .field("pending_events", &self.pending_events.len())
+            .field("active_notes", &self.active_notes.len())
             .finish()
     }
 }
@@ -226,6 +438,10 @@ impl AudioInstrument for Vst3Instrument {
         Vst3Instrument::queue_note_off(self, pitch, velocity, channel, sample_offset);
     }
 
+    fn queue_control(&mut self, control: MidiControl, value: u16, channel: u8, sample_offset: u32) {
+        Vst3Instrument::queue_control(self, control, value, channel, sample_offset);
+    }
+
     fn all_notes_off(&mut self) {
         Vst3Instrument::all_notes_off(self, 0);
     }
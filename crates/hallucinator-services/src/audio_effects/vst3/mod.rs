@@ -1,13 +1,26 @@
 //! VST3 plugin hosting support using rack crate
 
+#[cfg(target_os = "linux")]
+mod bridge;
 mod error;
 mod gui;
 mod instrument;
+mod module_info;
+mod preset_file;
+mod scan_cache;
 mod scanner;
+mod shared;
 mod wrapper;
+#[cfg(target_os = "linux")]
+mod x11_keys;
 
+#[cfg(target_os = "linux")]
+pub use bridge::{run_plugin_host, PLUGIN_HOST_ARG};
 pub use error::Vst3Error;
-pub use gui::{NativeWindowHandle, PluginGuiManager, PluginGuiWindow, Vst3GuiError};
-pub use instrument::Vst3Instrument;
-pub use scanner::{Vst3PluginInfo, Vst3Scanner};
+pub use gui::{GuiBackend, NativeWindowHandle, ParamGesture, PluginGuiManager, PluginGuiWindow, Vst3GuiError};
+pub use instrument::{InstrumentLoadJob, PluginStateReader, Vst3Instrument};
+pub use preset_file::{VstPreset, VST_PRESET_EXTENSION};
+pub use scan_cache::default_vst3_dirs;
+pub use scanner::{PluginIo, ScanEvent, ScanJob, Vst3PluginInfo, Vst3Scanner};
+pub use shared::{OutputBus, Vst3InstanceHandle};
 pub use wrapper::Vst3Effect;
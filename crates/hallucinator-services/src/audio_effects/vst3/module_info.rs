@@ -0,0 +1,101 @@
+//! Class metadata from a bundle's `Contents/Resources/moduleinfo.json`
+//!
+//! Bundles built with VST3 SDK 3.7.5 or later describe their classes in this
+//! file, including the sub-categories (`Instrument|Synth`, `Fx|Reverb|Stereo`)
+//! the host browser sorts by. Older bundles have none, and fall back to the
+//! plugin type reported by the scan.
+
+use std::path::Path;
+
+use serde::Deserialize;
+use tracing::debug;
+
+use super::preset_file::normalize_class_id;
+
+const MODULE_INFO_PATH: &str = "Contents/Resources/moduleinfo.json";
+const AUDIO_MODULE_CLASS: &str = "Audio Module Class";
+
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct ModuleInfo {
+    #[serde(rename = "Factory Info", default)]
+    factory: FactoryInfo,
+    #[serde(rename = "Classes", default)]
+    classes: Vec<ClassInfo>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FactoryInfo {
+    #[serde(rename = "Vendor", default)]
+    vendor: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClassInfo {
+    #[serde(rename = "CID", default)]
+    cid: String,
+    #[serde(rename = "Category", default)]
+    category: String,
+    #[serde(rename = "Name", default)]
+    name: String,
+    #[serde(rename = "Vendor", default)]
+    vendor: String,
+    #[serde(rename = "Sub Categories", default)]
+    sub_categories: SubCategories,
+}
+
+/// Written as a list by the SDK's module info tool, as a `|`-joined string by some others
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SubCategories {
+    List(Vec<String>),
+    Joined(String),
+}
+
+impl Default for SubCategories {
+    fn default() -> Self {
+        Self::List(Vec::new())
+    }
+}
+
+impl SubCategories {
+    fn to_vec(&self) -> Vec<String> {
+        match self {
+            Self::List(list) => list.clone(),
+            Self::Joined(joined) => joined.split('|').filter(|s| !s.is_empty()).map(str::to_string).collect(),
+        }
+    }
+}
+
+/// Vendor and sub-categories of one plugin class
+pub(super) struct ClassMetadata {
+    pub vendor: String,
+    pub categories: Vec<String>,
+}
+
+impl ModuleInfo {
+    /// Module info of the bundle containing `path`, if it has one
+    pub fn for_plugin(path: &Path) -> Option<Self> {
+        let bundle = path
+            .ancestors()
+            .find(|p| p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("vst3")))?;
+        let data = std::fs::read(bundle.join(MODULE_INFO_PATH)).ok()?;
+        serde_json::from_slice(&data)
+            .inspect_err(|e| debug!(bundle = %bundle.display(), "Unreadable moduleinfo.json: {}", e))
+            .ok()
+    }
+
+    /// Metadata of the audio class with this class ID, or else this name
+    pub fn class(&self, unique_id: &str, name: &str) -> Option<ClassMetadata> {
+        let audio_classes = || self.classes.iter().filter(|c| c.category == AUDIO_MODULE_CLASS);
+        let uid = normalize_class_id(unique_id);
+        let class = audio_classes()
+            .find(|c| !uid.is_empty() && normalize_class_id(&c.cid) == uid)
+            .or_else(|| audio_classes().find(|c| c.name == name))?;
+
+        let vendor = if class.vendor.is_empty() { &self.factory.vendor } else { &class.vendor };
+        Some(ClassMetadata {
+            vendor: vendor.clone(),
+            categories: class.sub_categories.to_vec(),
+        })
+    }
+}
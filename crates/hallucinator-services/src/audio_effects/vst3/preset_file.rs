@@ -0,0 +1,146 @@
+//! Standard `.vstpreset` files
+//!
+//! Layout (little endian): a 48-byte header (`VST3`, version, 32-char class
+//! ID, offset of the chunk list), the chunk data, then the chunk list
+//! (`List`, entry count, and per entry a 4-char ID, offset and size).
+//! `Comp` holds the component state, `Cont` the optional controller state.
+
+use std::path::Path;
+
+use super::error::Vst3Error;
+
+const MAGIC: &[u8; 4] = b"VST3";
+const VERSION: i32 = 1;
+const CLASS_ID_LEN: usize = 32;
+const HEADER_LEN: usize = 4 + 4 + CLASS_ID_LEN + 8;
+const LIST_ID: &[u8; 4] = b"List";
+const COMPONENT_ID: &[u8; 4] = b"Comp";
+const CONTROLLER_ID: &[u8; 4] = b"Cont";
+
+/// File extension, without the dot
+pub const VST_PRESET_EXTENSION: &str = "vstpreset";
+
+/// Contents of a `.vstpreset` file
+#[derive(Debug, Clone)]
+pub struct VstPreset {
+    /// Class ID of the plugin the preset belongs to (32 hex characters)
+    pub class_id: String,
+    /// Component (processor) state
+    pub component: Vec<u8>,
+    /// Controller state, if the file carries one
+    pub controller: Option<Vec<u8>>,
+}
+
+impl VstPreset {
+    pub fn new(plugin_uid: &str, component: Vec<u8>) -> Self {
+        Self {
+            class_id: normalize_class_id(plugin_uid),
+            component,
+            controller: None,
+        }
+    }
+
+    /// Whether the preset was saved from the plugin with this unique ID
+    pub fn matches(&self, plugin_uid: &str) -> bool {
+        normalize_class_id(plugin_uid) == normalize_class_id(&self.class_id)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, Vst3Error> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Vst3Error> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Vst3Error> {
+        let invalid = |msg: &str| Vst3Error::InvalidPreset(msg.to_string());
+        if data.len() < HEADER_LEN || &data[..4] != MAGIC {
+            return Err(invalid("not a VST3 preset"));
+        }
+        let class_id = String::from_utf8_lossy(&data[8..8 + CLASS_ID_LEN]).into_owned();
+        let list_offset = read_offset(data, 8 + CLASS_ID_LEN).ok_or_else(|| invalid("bad chunk list offset"))?;
+
+        if data.get(list_offset..list_offset + 4) != Some(LIST_ID.as_slice()) {
+            return Err(invalid("missing chunk list"));
+        }
+        let count = data
+            .get(list_offset + 4..list_offset + 8)
+            .map(|b| i32::from_le_bytes(b.try_into().unwrap_or_default()))
+            .ok_or_else(|| invalid("truncated chunk list"))?;
+
+        let mut component = None;
+        let mut controller = None;
+        for entry in 0..count.max(0) as usize {
+            let at = list_offset + 8 + entry * 20;
+            let id = data.get(at..at + 4).ok_or_else(|| invalid("truncated chunk list"))?;
+            let (Some(offset), Some(size)) = (read_offset(data, at + 4), read_offset(data, at + 12)) else {
+                return Err(invalid("bad chunk entry"));
+            };
+            let chunk = data
+                .get(offset..offset.saturating_add(size))
+                .ok_or_else(|| invalid("chunk out of bounds"))?;
+            match id {
+                id if id == COMPONENT_ID => component = Some(chunk.to_vec()),
+                id if id == CONTROLLER_ID => controller = Some(chunk.to_vec()),
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            class_id,
+            component: component.ok_or_else(|| invalid("no component state"))?,
+            controller,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut chunks: Vec<(&[u8; 4], &[u8])> = vec![(COMPONENT_ID, &self.component)];
+        if let Some(controller) = &self.controller {
+            chunks.push((CONTROLLER_ID, controller));
+        }
+
+        let mut entries = Vec::with_capacity(chunks.len());
+        let mut data = Vec::new();
+        for (id, chunk) in &chunks {
+            entries.push((*id, (HEADER_LEN + data.len()) as i64, chunk.len() as i64));
+            data.extend_from_slice(chunk);
+        }
+
+        let mut class_id = normalize_class_id(&self.class_id).into_bytes();
+        class_id.resize(CLASS_ID_LEN, b'0');
+
+        let mut out = Vec::with_capacity(HEADER_LEN + data.len() + 8 + entries.len() * 20);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&class_id);
+        out.extend_from_slice(&((HEADER_LEN + data.len()) as i64).to_le_bytes());
+        out.extend_from_slice(&data);
+        out.extend_from_slice(LIST_ID);
+        out.extend_from_slice(&(entries.len() as i32).to_le_bytes());
+        for (id, offset, size) in entries {
+            out.extend_from_slice(id);
+            out.extend_from_slice(&offset.to_le_bytes());
+            out.extend_from_slice(&size.to_le_bytes());
+        }
+        out
+    }
+}
+
+/// Class IDs as 32 uppercase hex digits (plugin UIDs may be dashed or lowercase)
+pub(super) fn normalize_class_id(id: &str) -> String {
+    id.chars()
+        .filter(char::is_ascii_hexdigit)
+        .map(|c| c.to_ascii_uppercase())
+        .take(CLASS_ID_LEN)
+        .collect()
+}
+
+fn read_offset(data: &[u8], at: usize) -> Option<usize> {
+    let bytes: [u8; 8] = data.get(at..at + 8)?.try_into().ok()?;
+    usize::try_from(i64::from_le_bytes(bytes)).ok()
+}
@@ -0,0 +1,189 @@
+//! On-disk cache of VST3 scan results
+//!
+//! Plugins are cached per bundle, keyed by the bundle path and its newest
+//! modification time, so a scan only loads bundles that were added or changed.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use rack::{PluginInfo, PluginType};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::error::Vst3Error;
+use super::scanner::Vst3PluginInfo;
+
+/// Bumped when the cache layout changes; older caches are discarded
+const CACHE_VERSION: u32 = 2;
+
+/// How deep to look inside a bundle for its newest file (`Contents/<arch>/<binary>`)
+const BUNDLE_MTIME_DEPTH: usize = 3;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(super) struct ScanCache {
+    version: u32,
+    bundles: BTreeMap<PathBuf, CachedBundle>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedBundle {
+    /// Newest modification time in the bundle, seconds since the epoch
+    mtime: u64,
+    plugins: Vec<CachedPlugin>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedPlugin {
+    name: String,
+    manufacturer: String,
+    version: u32,
+    plugin_type: String,
+    path: PathBuf,
+    unique_id: String,
+    vendor: String,
+    categories: Vec<String>,
+}
+
+impl ScanCache {
+    /// Read a cache file; a missing, unreadable or outdated cache is empty
+    pub fn load(path: &Path) -> Self {
+        let Ok(data) = std::fs::read(path) else {
+            return Self::default();
+        };
+        match serde_json::from_slice::<Self>(&data) {
+            Ok(cache) if cache.version == CACHE_VERSION => cache,
+            Ok(_) => Self::default(),
+            Err(e) => {
+                warn!(path = %path.display(), "Ignoring unreadable plugin cache: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Vst3Error> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let data = serde_json::to_vec_pretty(self).map_err(|e| Vst3Error::LoadError(e.to_string()))?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    /// Cached plugins of a bundle, if it hasn't changed since it was scanned
+    pub fn get(&self, bundle: &Path, mtime: u64) -> Option<Vec<Vst3PluginInfo>> {
+        let cached = self.bundles.get(bundle).filter(|b| b.mtime == mtime)?;
+        Some(cached.plugins.iter().map(CachedPlugin::to_info).collect())
+    }
+
+    pub fn insert(&mut self, bundle: PathBuf, mtime: u64, plugins: &[Vst3PluginInfo]) {
+        let plugins = plugins.iter().map(CachedPlugin::from_info).collect();
+        self.bundles.insert(bundle, CachedBundle { mtime, plugins });
+    }
+
+    /// Start a fresh cache (stale entries for removed bundles are dropped this way)
+    pub fn fresh() -> Self {
+        Self { version: CACHE_VERSION, bundles: BTreeMap::new() }
+    }
+}
+
+impl CachedPlugin {
+    fn from_info(plugin: &Vst3PluginInfo) -> Self {
+        let info = &plugin.info;
+        Self {
+            name: info.name.clone(),
+            manufacturer: info.manufacturer.clone(),
+            version: info.version,
+            plugin_type: format!("{:?}", info.plugin_type),
+            path: info.path.clone(),
+            unique_id: info.unique_id.clone(),
+            vendor: plugin.vendor.clone(),
+            categories: plugin.categories.clone(),
+        }
+    }
+
+    fn to_info(&self) -> Vst3PluginInfo {
+        let plugin_type = match self.plugin_type.as_str() {
+            "Instrument" => PluginType::Instrument,
+            "Effect" => PluginType::Effect,
+            "Mixer" => PluginType::Mixer,
+            "Analyzer" => PluginType::Analyzer,
+            _ => PluginType::Other,
+        };
+        let info = PluginInfo {
+            name: self.name.clone(),
+            manufacturer: self.manufacturer.clone(),
+            version: self.version,
+            plugin_type,
+            path: self.path.clone(),
+            unique_id: self.unique_id.clone(),
+        };
+        Vst3PluginInfo {
+            name: self.name.clone(),
+            vendor: self.vendor.clone(),
+            categories: self.categories.clone(),
+            info,
+        }
+    }
+}
+
+/// Standard VST3 install locations for this platform
+pub fn default_vst3_dirs() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let mut dirs = Vec::new();
+    if cfg!(target_os = "macos") {
+        dirs.extend(home.map(|h| h.join("Library/Audio/Plug-Ins/VST3")));
+        dirs.push(PathBuf::from("/Library/Audio/Plug-Ins/VST3"));
+    } else if cfg!(target_os = "windows") {
+        let common = std::env::var_os("COMMONPROGRAMFILES").map(PathBuf::from);
+        dirs.push(common.unwrap_or_else(|| PathBuf::from(r"C:\Program Files\Common Files")).join("VST3"));
+    } else {
+        dirs.extend(home.map(|h| h.join(".vst3")));
+        dirs.push(PathBuf::from("/usr/lib/vst3"));
+        dirs.push(PathBuf::from("/usr/local/lib/vst3"));
+    }
+    dirs
+}
+
+/// All `.vst3` bundles under the given directories (vendors often nest them in folders)
+pub(super) fn find_bundles(dirs: &[PathBuf]) -> Vec<PathBuf> {
+    fn walk(dir: &Path, out: &mut Vec<PathBuf>) {
+        let Ok(entries) = std::fs::read_dir(dir) else { return };
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("vst3")) {
+                out.push(path);
+            } else if path.is_dir() {
+                walk(&path, out);
+            }
+        }
+    }
+
+    let mut bundles = Vec::new();
+    for dir in dirs {
+        walk(dir, &mut bundles);
+    }
+    bundles.sort();
+    bundles.dedup();
+    bundles
+}
+
+/// Newest modification time of a bundle and the files inside it
+pub(super) fn bundle_mtime(bundle: &Path) -> u64 {
+    fn newest(path: &Path, depth: usize) -> u64 {
+        let Ok(meta) = std::fs::metadata(path) else { return 0 };
+        let own = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+        if !meta.is_dir() || depth == 0 {
+            return own;
+        }
+        let Ok(entries) = std::fs::read_dir(path) else { return own };
+        entries
+            .flatten()
+            .map(|e| newest(&e.path(), depth - 1))
+            .fold(own, u64::max)
+    }
+    newest(bundle, BUNDLE_MTIME_DEPTH)
+}
@@ -1,11 +1,19 @@
 //! VST3 plugin scanner using rack crate
+//!
+//! `Vst3Scanner::spawn_scan` scans on a background thread and keeps results in
+//! a cache file, so only new or changed bundles are loaded on later launches.
 
-use std::path::Path;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
 
-use rack::{PluginInfo, PluginScanner, Scanner};
+use crossbeam_channel::{Receiver, Sender};
+use rack::{PluginInfo, PluginScanner, PluginType, Scanner};
 use tracing::info;
 
 use super::error::Vst3Error;
+use super::module_info::ModuleInfo;
+use super::scan_cache::{bundle_mtime, find_bundles, ScanCache};
 
 /// Information about a discovered VST3 plugin
 #[derive(Debug, Clone)]
@@ -14,6 +22,95 @@ pub struct Vst3PluginInfo {
     pub info: PluginInfo,
     /// Plugin name
     pub name: String,
+    /// Vendor, from the plugin factory or the bundle's module info
+    pub vendor: String,
+    /// VST3 sub-categories, e.g. `["Fx", "Reverb", "Stereo"]` or `["Instrument", "Synth"]`
+    pub categories: Vec<String>,
+}
+
+/// Channel layout a plugin declares in its sub-categories
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginIo {
+    Mono,
+    Stereo,
+    Surround,
+    Ambisonics,
+}
+
+impl PluginIo {
+    fn from_category(category: &str) -> Option<Self> {
+        match category {
+            "Mono" => Some(Self::Mono),
+            "Stereo" => Some(Self::Stereo),
+            "Surround" => Some(Self::Surround),
+            "Ambisonics" => Some(Self::Ambisonics),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PluginIo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Mono => "Mono",
+            Self::Stereo => "Stereo",
+            Self::Surround => "Surround",
+            Self::Ambisonics => "Ambisonics",
+        })
+    }
+}
+
+impl Vst3PluginInfo {
+    /// A scanned plugin, with vendor and sub-categories from its bundle's module info where available
+    pub(super) fn describe(info: PluginInfo) -> Self {
+        let mut plugin = Self::from(info);
+        let class = ModuleInfo::for_plugin(&plugin.info.path)
+            .and_then(|module| module.class(&plugin.info.unique_id, &plugin.name));
+        if let Some(class) = class {
+            if plugin.vendor.is_empty() {
+                plugin.vendor = class.vendor;
+            }
+            if !class.categories.is_empty() {
+                plugin.categories = class.categories;
+            }
+        }
+        plugin
+    }
+
+    /// Whether the plugin can be played as an instrument (plugins of unknown kind count as both)
+    pub fn is_instrument(&self) -> bool {
+        self.categories.is_empty() || self.has_category("Instrument")
+    }
+
+    /// Whether the plugin can be inserted as an effect (plugins of unknown kind count as both)
+    pub fn is_effect(&self) -> bool {
+        self.categories.is_empty() || self.has_category("Fx") || !self.has_category("Instrument")
+    }
+
+    /// Most specific category for display, e.g. "Reverb" or "Synth"
+    pub fn category(&self) -> Option<&str> {
+        self.categories
+            .iter()
+            .map(String::as_str)
+            .find(|c| !matches!(*c, "Fx" | "Instrument") && PluginIo::from_category(c).is_none())
+    }
+
+    /// Channel layout, if the plugin declares one
+    pub fn io(&self) -> Option<PluginIo> {
+        self.categories.iter().find_map(|c| PluginIo::from_category(c))
+    }
+
+    /// Whether a lowercase search `query` matches the name, vendor or a category
+    pub fn matches(&self, query: &str) -> bool {
+        query.is_empty()
+            || self.name.to_lowercase().contains(query)
+            || self.vendor.to_lowercase().contains(query)
+            || self.categories.iter().any(|c| c.to_lowercase().contains(query))
+    }
+
+    fn has_category(&self, category: &str) -> bool {
+        self.categories.iter().any(|c| c.eq_ignore_ascii_case(category))
+    }
 }
 
 /// Scanner for discovering VST3 plugins on the system
@@ -72,19 +169,33 @@ impl Vst3Scanner {
 
     fn add_plugins(&mut self, plugin_infos: Vec<PluginInfo>) {
         for plugin_info in plugin_infos {
-            let name = plugin_info.name.clone();
-            info!(name = %name, "Found plugin");
-            self.plugins.push(Vst3PluginInfo {
-                info: plugin_info,
-                name,
-            });
+            info!(name = %plugin_info.name, "Found plugin");
+            self.plugins.push(Vst3PluginInfo::describe(plugin_info));
         }
     }
 
+    /// Scan `dirs` on a background thread, reusing cached results for bundles
+    /// that haven't changed; `rescan` ignores the cache and loads every bundle
+    pub fn spawn_scan(dirs: Vec<PathBuf>, cache_path: Option<PathBuf>, rescan: bool) -> Result<ScanJob, Vst3Error> {
+        let (sender, events) = crossbeam_channel::unbounded();
+        let thread = std::thread::Builder::new()
+            .name("vst3-scan".to_string())
+            .spawn(move || {
+                let result = scan_bundles(&dirs, cache_path.as_deref(), rescan, &sender);
+                let _ = sender.send(ScanEvent::Finished(result));
+            })?;
+        Ok(ScanJob { events, thread: Some(thread) })
+    }
+
     pub fn plugins(&self) -> &[Vst3PluginInfo] {
         &self.plugins
     }
 
+    /// Replace the plugin list, e.g. with the result of a background scan
+    pub fn set_plugins(&mut self, plugins: Vec<Vst3PluginInfo>) {
+        self.plugins = plugins;
+    }
+
     pub fn find_by_name(&self, name: &str) -> Option<&Vst3PluginInfo> {
         self.plugins.iter().find(|p| p.name == name)
     }
@@ -95,6 +206,113 @@ impl Vst3Scanner {
     }
 }
 
+/// Plugin info as reported by the factory; categories follow from the plugin type
+impl From<PluginInfo> for Vst3PluginInfo {
+    fn from(info: PluginInfo) -> Self {
+        let categories: &[&str] = match info.plugin_type {
+            PluginType::Instrument => &["Instrument"],
+            PluginType::Effect => &["Fx"],
+            PluginType::Analyzer => &["Fx", "Analyzer"],
+            PluginType::Mixer => &["Fx", "Mixing"],
+            PluginType::Other => &[],
+        };
+        Self {
+            name: info.name.clone(),
+            vendor: info.manufacturer.clone(),
+            categories: categories.iter().map(|c| c.to_string()).collect(),
+            info,
+        }
+    }
+}
+
+/// Progress and outcome of a background scan
+#[derive(Debug)]
+pub enum ScanEvent {
+    /// `bundle` (the `done`-th of `total`) is being loaded; cached bundles are skipped silently
+    Progress { done: usize, total: usize, bundle: String },
+    Finished(Result<Vec<Vst3PluginInfo>, Vst3Error>),
+}
+
+/// A scan running on a background thread
+pub struct ScanJob {
+    events: Receiver<ScanEvent>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ScanJob {
+    /// Events since the last poll; ends with `Finished` once the scan is done
+    /// (also if the scan thread died, e.g. a plugin panicked while loading)
+    pub fn poll(&mut self) -> Vec<ScanEvent> {
+        let stopped = self.thread.as_ref().is_none_or(|t| t.is_finished());
+        let mut events: Vec<_> = self.events.try_iter().collect();
+        if stopped && self.thread.take().is_some() && !events.iter().any(|e| matches!(e, ScanEvent::Finished(_))) {
+            events.push(ScanEvent::Finished(Err(Vst3Error::LoadError("plugin scan stopped unexpectedly".to_string()))));
+        }
+        events
+    }
+}
+
+fn scan_bundles(
+    dirs: &[PathBuf],
+    cache_path: Option<&Path>,
+    rescan: bool,
+    events: &Sender<ScanEvent>,
+) -> Result<Vec<Vst3PluginInfo>, Vst3Error> {
+    let scanner = Scanner::new().map_err(|e| Vst3Error::LoadError(format!("{:?}", e)))?;
+    let previous = match cache_path {
+        Some(path) if !rescan => ScanCache::load(path),
+        _ => ScanCache::fresh(),
+    };
+
+    // Rebuilt from scratch so removed bundles drop out of the cache
+    let mut cache = ScanCache::fresh();
+    let bundles = find_bundles(dirs);
+    let total = bundles.len();
+    let mut plugins = Vec::new();
+    let mut loaded = 0;
+    for (done, bundle) in bundles.into_iter().enumerate() {
+        let mtime = bundle_mtime(&bundle);
+        let infos = match previous.get(&bundle, mtime) {
+            Some(infos) => infos,
+            None => {
+                let name = bundle.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                let _ = events.send(ScanEvent::Progress { done, total, bundle: name });
+                loaded += 1;
+                scan_bundle(&scanner, &bundle).into_iter().map(Vst3PluginInfo::describe).collect()
+            }
+        };
+        cache.insert(bundle, mtime, &infos);
+        plugins.extend(infos);
+    }
+
+    if let Some(path) = cache_path
+        && let Err(e) = cache.save(path)
+    {
+        tracing::warn!(path = %path.display(), "Failed to write plugin cache: {}", e);
+    }
+    info!(count = plugins.len(), bundles = total, loaded, "Plugin scan complete");
+
+    if plugins.is_empty() {
+        return Err(Vst3Error::NoPluginsFound);
+    }
+    Ok(plugins)
+}
+
+/// Plugins in one bundle. rack scans folders for bundles, so fall back to the
+/// bundle's folder (keeping only this bundle's plugins) if the bundle itself yields none.
+fn scan_bundle(scanner: &Scanner, bundle: &Path) -> Vec<PluginInfo> {
+    match scanner.scan_path(bundle) {
+        Ok(infos) if !infos.is_empty() => infos,
+        _ => bundle
+            .parent()
+            .and_then(|dir| scanner.scan_path(dir).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|info| info.path.starts_with(bundle))
+            .collect(),
+    }
+}
+
 impl Default for Vst3Scanner {
     fn default() -> Self {
         Self::new().unwrap_or(Self {
@@ -0,0 +1,570 @@
+//! The plugin instance behind a `Vst3Instrument`
+//!
+//! On Linux instruments are hosted through `rack_vst3_gui`, and the same
+//! instance backs the native editor window: the editor's controller and the
+//! audio processor are connected, so parameter edits and preset loads made in
+//! the plugin UI are what plays. A sandboxed instrument instead runs its
+//! plugin in a separate host process (see `bridge`) and has no editor.
+//! Elsewhere (no native editor yet) processing goes through rack.
+//!
+//! Only in-process Linux instances expose auxiliary output buses; the others
+//! deliver the main bus alone.
+
+#[cfg(target_os = "linux")]
+use std::sync::Arc;
+
+use hallucinator_core::MidiControl;
+#[cfg(target_os = "linux")]
+use rack_vst3_gui::{Vst3Gui, Vst3GuiError, CONTROLLER_AFTERTOUCH, CONTROLLER_PITCH_BEND};
+use rack::Scanner;
+
+use super::error::Vst3Error;
+use super::scanner::Vst3PluginInfo;
+use crate::audio_effects::EffectParam;
+
+#[cfg(target_os = "linux")]
+pub(super) use linux::{InProcess, PluginProcessor, StateSource};
+#[cfg(not(target_os = "linux"))]
+pub(super) use fallback::{PluginProcessor, StateSource};
+
+/// One audio output bus of an instrument plugin (bus 0 is the main output)
+#[derive(Debug, Clone)]
+pub struct OutputBus {
+    pub name: String,
+    pub channels: u32,
+    /// Whether the bus is rendered separately; a disabled auxiliary bus usually
+    /// folds into the main output
+    pub enabled: bool,
+}
+
+/// Handle to an instrument's plugin instance, passed to `PluginGuiManager` to open its editor
+#[derive(Clone)]
+pub struct Vst3InstanceHandle {
+    pub info: Vst3PluginInfo,
+    #[cfg(target_os = "linux")]
+    pub(super) plugin: Arc<Vst3Gui>,
+}
+
+impl Vst3InstanceHandle {
+    /// Format a normalized parameter value as the plugin displays it, e.g. "1.2"
+    /// for a cutoff in kHz (UI thread; None if the plugin can't format it)
+    #[cfg(target_os = "linux")]
+    pub fn param_display(&self, index: usize, value: f32) -> Option<String> {
+        self.plugin.parameter_display(index, value as f64).ok().filter(|s| !s.is_empty())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn param_display(&self, _index: usize, _value: f32) -> Option<String> {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::super::bridge::{BridgeStateReader, BridgedPlugin};
+    use super::*;
+
+    /// Where an instrument's plugin state is read from outside its `PluginProcessor`
+    #[derive(Clone)]
+    pub enum StateSource {
+        /// The instance itself, which its editor already shares across threads
+        InProcess(Arc<Vst3Gui>),
+        Bridged(BridgeStateReader),
+    }
+
+    impl StateSource {
+        pub fn state(&self) -> Result<Vec<u8>, Vst3Error> {
+            match self {
+                Self::InProcess(instance) => instance
+                    .get_component_state()
+                    .map_err(|e| Vst3Error::ProcessError(e.to_string())),
+                Self::Bridged(b) => b.state(),
+            }
+        }
+    }
+
+    /// Processor side of an instrument's plugin instance
+    pub enum PluginProcessor {
+        /// Loaded into this process and shared with its editor
+        InProcess(InProcess),
+        /// Running in a sandboxed host process, without an editor
+        Bridged(BridgedPlugin),
+    }
+
+    impl PluginProcessor {
+        pub fn load(
+            _scanner: &Scanner,
+            info: &Vst3PluginInfo,
+            sample_rate: f32,
+            max_block_size: usize,
+            sandboxed: bool,
+        ) -> Result<Self, Vst3Error> {
+            if sandboxed {
+                return Ok(Self::Bridged(BridgedPlugin::launch(info, sample_rate)?));
+            }
+            let path = info.info.path.to_string_lossy();
+            InProcess::open(&path, &info.info.unique_id, sample_rate, max_block_size).map(Self::InProcess)
+        }
+
+        pub fn params(&self) -> Vec<EffectParam> {
+            match self {
+                Self::InProcess(p) => p.params(),
+                Self::Bridged(b) => b.params(),
+            }
+        }
+
+        pub fn parameter_count(&self) -> usize {
+            match self {
+                Self::InProcess(p) => p.parameter_count(),
+                Self::Bridged(b) => b.parameter_count(),
+            }
+        }
+
+        pub fn set_parameter(&mut self, index: usize, value: f32) -> Result<(), Vst3Error> {
+            match self {
+                Self::InProcess(p) => p.set_parameter(index, value),
+                Self::Bridged(b) => b.set_parameter(index, value),
+            }
+        }
+
+        pub fn queue_note(&mut self, note_on: bool, pitch: u8, velocity: u8, channel: u8, sample_offset: u32) {
+            match self {
+                Self::InProcess(p) => p.queue_note(note_on, pitch, velocity, channel, sample_offset),
+                Self::Bridged(b) => b.queue_note(note_on, pitch, velocity, channel, sample_offset),
+            }
+        }
+
+        pub fn queue_control(&mut self, control: MidiControl, value: u16, channel: u8, sample_offset: u32) {
+            match self {
+                Self::InProcess(p) => p.queue_control(control, value, channel, sample_offset),
+                Self::Bridged(b) => b.queue_control(control, value, channel, sample_offset),
+            }
+        }
+
+        pub fn process(
+            &mut self,
+            inputs: [&mut [f32]; 2],
+            outputs: [&mut [f32]; 2],
+            frames: usize,
+        ) -> Result<(), Vst3Error> {
+            match self {
+                Self::InProcess(p) => p.process(inputs, outputs, frames),
+                // Instruments get silent input, so it isn't transferred
+                Self::Bridged(b) => b.process(outputs, frames),
+            }
+        }
+
+        /// Output buses, main bus first (only the main bus when sandboxed)
+        pub fn output_buses(&self) -> Vec<OutputBus> {
+            match self {
+                Self::InProcess(p) => p.output_buses(),
+                Self::Bridged(_) => Vec::new(),
+            }
+        }
+
+        pub fn enable_output_bus(&mut self, index: usize, enabled: bool) -> Result<(), Vst3Error> {
+            match self {
+                Self::InProcess(p) => p.enable_output_bus(index, enabled),
+                Self::Bridged(_) => Err(Vst3Error::NoSuchOutput(index)),
+            }
+        }
+
+        /// Process, delivering the first `outputs.len()` output buses
+        pub fn process_buses(
+            &mut self,
+            inputs: [&mut [f32]; 2],
+            outputs: &mut [[&mut [f32]; 2]],
+            frames: usize,
+        ) -> Result<(), Vst3Error> {
+            match self {
+                Self::InProcess(p) => p.process_buses(inputs, outputs, frames),
+                Self::Bridged(b) => {
+                    let [left, right] = outputs.first_mut().ok_or(Vst3Error::NoSuchOutput(0))?;
+                    b.process([&mut **left, &mut **right], frames)
+                }
+            }
+        }
+
+        pub fn set_sample_rate(&mut self, sample_rate: f32) -> Result<(), Vst3Error> {
+            match self {
+                Self::InProcess(p) => p.set_sample_rate(sample_rate),
+                Self::Bridged(b) => b.set_sample_rate(sample_rate),
+            }
+        }
+
+        /// Handle for the editor; sandboxed instances have none
+        pub fn handle(&self, info: &Vst3PluginInfo) -> Option<Vst3InstanceHandle> {
+            match self {
+                Self::InProcess(p) => Some(Vst3InstanceHandle {
+                    info: info.clone(),
+                    plugin: p.instance.clone(),
+                }),
+                Self::Bridged(_) => None,
+            }
+        }
+
+        /// Times the sandbox host was restarted after a crash (None when not sandboxed)
+        pub fn sandbox_restarts(&self) -> Option<u32> {
+            match self {
+                Self::InProcess(_) => None,
+                Self::Bridged(b) => Some(b.restarts()),
+            }
+        }
+
+        pub fn state(&self) -> Result<Vec<u8>, Vst3Error> {
+            match self {
+                Self::InProcess(p) => p.state(),
+                Self::Bridged(b) => b.state(),
+            }
+        }
+
+        pub fn set_state(&mut self, chunk: &[u8]) -> Result<(), Vst3Error> {
+            match self {
+                Self::InProcess(p) => p.set_state(chunk),
+                Self::Bridged(b) => b.set_state(chunk),
+            }
+        }
+
+        /// Source of the plugin state that can be read without this processor
+        pub fn state_source(&self) -> Option<StateSource> {
+            Some(match self {
+                Self::InProcess(p) => StateSource::InProcess(p.instance.clone()),
+                Self::Bridged(b) => StateSource::Bridged(b.state_reader()),
+            })
+        }
+
+        pub fn program_names(&self) -> Vec<String> {
+            match self {
+                Self::InProcess(p) => p.program_names(),
+                Self::Bridged(b) => b.program_names(),
+            }
+        }
+
+        pub fn current_program(&self) -> Option<usize> {
+            match self {
+                Self::InProcess(p) => p.current_program(),
+                Self::Bridged(b) => b.current_program(),
+            }
+        }
+
+        pub fn set_program(&mut self, index: usize) -> Result<(), Vst3Error> {
+            match self {
+                Self::InProcess(p) => p.set_program(index),
+                Self::Bridged(b) => b.set_program(index),
+            }
+        }
+    }
+
+    /// Plugin instance in this process (also what a sandbox host process runs)
+    pub struct InProcess {
+        instance: Arc<Vst3Gui>,
+        sample_rate: f32,
+        max_block_size: usize,
+    }
+
+    impl InProcess {
+        pub fn open(path: &str, unique_id: &str, sample_rate: f32, max_block_size: usize) -> Result<Self, Vst3Error> {
+            let instance = Vst3Gui::new(path, unique_id).map_err(|e| Vst3Error::LoadError(e.to_string()))?;
+            instance
+                .setup_processing(sample_rate as f64, max_block_size)
+                .map_err(|e| Vst3Error::LoadError(e.to_string()))?;
+            Ok(Self {
+                instance: Arc::new(instance),
+                sample_rate,
+                max_block_size,
+            })
+        }
+
+        /// Current parameters (normalized 0-1)
+        pub fn params(&self) -> Vec<EffectParam> {
+            (0..self.instance.parameter_count())
+                .filter_map(|i| {
+                    let pinfo = self.instance.parameter_info(i).ok()?;
+                    let value = self.instance.get_parameter(i).unwrap_or(pinfo.default);
+                    Some(
+                        EffectParam::new(&pinfo.name, value as f32, 0.0, 1.0, &pinfo.units)
+                            .with_default(pinfo.default as f32)
+                            .with_steps(pinfo.step_count),
+                    )
+                })
+                .collect()
+        }
+
+        pub fn parameter_count(&self) -> usize {
+            self.instance.parameter_count()
+        }
+
+        pub fn set_parameter(&mut self, index: usize, value: f32) -> Result<(), Vst3Error> {
+            self.instance
+                .set_parameter(index, value as f64)
+                .map_err(|e| Vst3Error::ProcessError(e.to_string()))
+        }
+
+        pub fn queue_note(&mut self, note_on: bool, pitch: u8, velocity: u8, channel: u8, sample_offset: u32) {
+            let velocity = velocity as f32 / 127.0;
+            if let Err(e) = self.instance.queue_note(note_on, channel, pitch, velocity, sample_offset) {
+                tracing::warn!("Failed to queue MIDI note: {}", e);
+            }
+        }
+
+        /// Controllers reach the plugin as changes of the parameters it maps them to
+        pub fn queue_control(&mut self, control: MidiControl, value: u16, channel: u8, sample_offset: u32) {
+            let value = control.normalized(value);
+            let result = match control {
+                MidiControl::Cc(cc) => self.instance.queue_controller(channel, cc, value as f64, sample_offset),
+                MidiControl::ChannelPressure => {
+                    self.instance.queue_controller(channel, CONTROLLER_AFTERTOUCH, value as f64, sample_offset)
+                }
+                MidiControl::PitchBend => {
+                    self.instance.queue_controller(channel, CONTROLLER_PITCH_BEND, value as f64, sample_offset)
+                }
+                MidiControl::PolyPressure(pitch) => self.instance.queue_poly_pressure(channel, pitch, value, sample_offset),
+            };
+            // A controller the plugin doesn't map is simply ignored
+            if let Err(e) = result
+                && !matches!(e, Vst3GuiError::InvalidParam)
+            {
+                tracing::warn!("Failed to queue MIDI controller: {}", e);
+            }
+        }
+
+        pub fn process(
+            &mut self,
+            inputs: [&mut [f32]; 2],
+            outputs: [&mut [f32]; 2],
+            frames: usize,
+        ) -> Result<(), Vst3Error> {
+            self.instance
+                .process(inputs, outputs, frames)
+                .map_err(|e| Vst3Error::ProcessError(e.to_string()))
+        }
+
+        /// Output buses the host can take, main bus first
+        pub fn output_buses(&self) -> Vec<OutputBus> {
+            self.instance
+                .output_buses()
+                .into_iter()
+                .take(rack_vst3_gui::MAX_OUTPUT_BUSES)
+                .enumerate()
+                .map(|(index, bus)| OutputBus {
+                    name: bus.name,
+                    channels: bus.channels,
+                    enabled: index == 0,
+                })
+                .collect()
+        }
+
+        /// Activate or deactivate an auxiliary bus (restarts processing)
+        pub fn enable_output_bus(&mut self, index: usize, enabled: bool) -> Result<(), Vst3Error> {
+            self.instance
+                .enable_output_bus(index, enabled)
+                .map_err(|_| Vst3Error::NoSuchOutput(index))?;
+            self.set_sample_rate(self.sample_rate)
+        }
+
+        pub fn process_buses(
+            &mut self,
+            inputs: [&mut [f32]; 2],
+            outputs: &mut [[&mut [f32]; 2]],
+            frames: usize,
+        ) -> Result<(), Vst3Error> {
+            self.instance
+                .process_buses(inputs, outputs, frames)
+                .map_err(|e| Vst3Error::ProcessError(e.to_string()))
+        }
+
+        pub fn set_sample_rate(&mut self, sample_rate: f32) -> Result<(), Vst3Error> {
+            self.sample_rate = sample_rate;
+            self.instance
+                .setup_processing(sample_rate as f64, self.max_block_size)
+                .map_err(|e| Vst3Error::ProcessError(e.to_string()))
+        }
+
+        pub fn state(&self) -> Result<Vec<u8>, Vst3Error> {
+            self.instance
+                .get_component_state()
+                .map_err(|e| Vst3Error::ProcessError(e.to_string()))
+        }
+
+        pub fn set_state(&mut self, chunk: &[u8]) -> Result<(), Vst3Error> {
+            self.instance
+                .set_component_state(chunk)
+                .map_err(|e| Vst3Error::ProcessError(e.to_string()))
+        }
+
+        pub fn program_names(&self) -> Vec<String> {
+            self.instance.program_names()
+        }
+
+        pub fn current_program(&self) -> Option<usize> {
+            self.instance.current_program()
+        }
+
+        pub fn set_program(&mut self, index: usize) -> Result<(), Vst3Error> {
+            self.instance
+                .set_program(index)
+                .map_err(|_| Vst3Error::NoSuchProgram(index))
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod fallback {
+    use rack::{midi::MidiEvent, Plugin, PluginInstance, PluginScanner};
+
+    use super::*;
+
+    /// Plugin state can't be read through rack, so there is never a source
+    #[derive(Clone)]
+    pub enum StateSource {}
+
+    impl StateSource {
+        pub fn state(&self) -> Result<Vec<u8>, Vst3Error> {
+            match *self {}
+        }
+    }
+
+    /// Plugin instance processed through rack
+    pub struct PluginProcessor {
+        instance: Plugin,
+        max_block_size: usize,
+        pending_events: Vec<MidiEvent>,
+    }
+
+    impl PluginProcessor {
+        pub fn load(
+            scanner: &Scanner,
+            info: &Vst3PluginInfo,
+            sample_rate: f32,
+            max_block_size: usize,
+            sandboxed: bool,
+        ) -> Result<Self, Vst3Error> {
+            if sandboxed {
+                return Err(Vst3Error::SandboxUnsupported);
+            }
+            let mut instance = scanner
+                .load(&info.info)
+                .map_err(|e| Vst3Error::LoadError(format!("{:?}", e)))?;
+            instance
+                .initialize(sample_rate as f64, max_block_size)
+                .map_err(|e| Vst3Error::LoadError(format!("{:?}", e)))?;
+            Ok(Self {
+                instance,
+                max_block_size,
+                pending_events: Vec::with_capacity(256),
+            })
+        }
+
+        pub fn params(&self) -> Vec<EffectParam> {
+            (0..self.instance.parameter_count())
+                .filter_map(|i| {
+                    let pinfo = self.instance.parameter_info(i).ok()?;
+                    let value = self.instance.get_parameter(i).unwrap_or(pinfo.default);
+                    Some(
+                        EffectParam::new(&pinfo.name, value, pinfo.min, pinfo.max, &pinfo.unit)
+                            .with_default(pinfo.default),
+                    )
+                })
+                .collect()
+        }
+
+        pub fn parameter_count(&self) -> usize {
+            self.instance.parameter_count()
+        }
+
+        pub fn set_parameter(&mut self, index: usize, value: f32) -> Result<(), Vst3Error> {
+            self.instance
+                .set_parameter(index, value)
+                .map_err(|e| Vst3Error::ProcessError(format!("{:?}", e)))
+        }
+
+        pub fn queue_note(&mut self, note_on: bool, pitch: u8, velocity: u8, channel: u8, sample_offset: u32) {
+            let event = if note_on {
+                MidiEvent::note_on(pitch, velocity, channel, sample_offset)
+            } else {
+                MidiEvent::note_off(pitch, velocity, channel, sample_offset)
+            };
+            self.pending_events.push(event);
+        }
+
+        /// Controller changes aren't forwarded through rack yet
+        pub fn queue_control(&mut self, _control: MidiControl, _value: u16, _channel: u8, _sample_offset: u32) {}
+
+        pub fn process(
+            &mut self,
+            inputs: [&mut [f32]; 2],
+            outputs: [&mut [f32]; 2],
+            frames: usize,
+        ) -> Result<(), Vst3Error> {
+            if !self.pending_events.is_empty() {
+                if let Err(e) = self.instance.send_midi(&self.pending_events) {
+                    tracing::warn!("Failed to send MIDI: {:?}", e);
+                }
+                self.pending_events.clear();
+            }
+
+            let [in_left, in_right] = inputs;
+            let inputs: [&[f32]; 2] = [in_left, in_right];
+            let mut outputs = outputs;
+            self.instance
+                .process(&inputs, &mut outputs, frames)
+                .map_err(|e| Vst3Error::ProcessError(format!("{:?}", e)))
+        }
+
+        pub fn output_buses(&self) -> Vec<OutputBus> {
+            Vec::new()
+        }
+
+        pub fn enable_output_bus(&mut self, index: usize, _enabled: bool) -> Result<(), Vst3Error> {
+            Err(Vst3Error::NoSuchOutput(index))
+        }
+
+        pub fn process_buses(
+            &mut self,
+            inputs: [&mut [f32]; 2],
+            outputs: &mut [[&mut [f32]; 2]],
+            frames: usize,
+        ) -> Result<(), Vst3Error> {
+            let [left, right] = outputs.first_mut().ok_or(Vst3Error::NoSuchOutput(0))?;
+            self.process(inputs, [&mut **left, &mut **right], frames)
+        }
+
+        pub fn set_sample_rate(&mut self, sample_rate: f32) -> Result<(), Vst3Error> {
+            self.instance
+                .initialize(sample_rate as f64, self.max_block_size)
+                .map_err(|e| Vst3Error::ProcessError(format!("{:?}", e)))
+        }
+
+        pub fn handle(&self, info: &Vst3PluginInfo) -> Option<Vst3InstanceHandle> {
+            Some(Vst3InstanceHandle { info: info.clone() })
+        }
+
+        pub fn sandbox_restarts(&self) -> Option<u32> {
+            None
+        }
+
+        pub fn state(&self) -> Result<Vec<u8>, Vst3Error> {
+            Err(Vst3Error::StateUnsupported)
+        }
+
+        pub fn set_state(&mut self, _chunk: &[u8]) -> Result<(), Vst3Error> {
+            Err(Vst3Error::StateUnsupported)
+        }
+
+        pub fn state_source(&self) -> Option<StateSource> {
+            None
+        }
+
+        pub fn program_names(&self) -> Vec<String> {
+            Vec::new()
+        }
+
+        pub fn current_program(&self) -> Option<usize> {
+            None
+        }
+
+        pub fn set_program(&mut self, index: usize) -> Result<(), Vst3Error> {
+            Err(Vst3Error::NoSuchProgram(index))
+        }
+    }
+}
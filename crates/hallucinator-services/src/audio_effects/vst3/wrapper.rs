@@ -10,6 +10,7 @@ use tracing::info;
 use super::error::Vst3Error;
 use super::scanner::Vst3PluginInfo;
 use crate::audio_effects::{AudioEffect, EffectParam};
+use crate::notifications;
 
 /// Wrapper around a VST3 plugin instance that implements AudioEffect
 pub struct Vst3Effect {
@@ -64,7 +65,7 @@ impl Vst3Effect {
                     pinfo.min,
                     pinfo.max,
                     &pinfo.unit,
-                ));
+                ).with_default(pinfo.default));
             }
         }
 
@@ -171,6 +172,10 @@ impl AudioEffect for Vst3Effect {
         self.bypassed
     }
 
+    fn vst3_plugin_info(&self) -> Option<&Vst3PluginInfo> {
+        Some(&self.info)
+    }
+
     fn set_sample_rate(&mut self, sample_rate: f32) {
         if (sample_rate - self.sample_rate).abs() < 1.0 {
             return;
@@ -183,7 +188,7 @@ impl AudioEffect for Vst3Effect {
             .instance
             .initialize(sample_rate as f64, self.max_block_size)
         {
-            tracing::error!("Failed to reinitialize VST3: {:?}", e);
+            notifications::error("Failed to reinitialize VST3 plugin", format!("{:?}", e));
         }
     }
 }
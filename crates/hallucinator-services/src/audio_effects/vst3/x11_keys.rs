@@ -0,0 +1,142 @@
+//! X11 keyboard translation for forwarding keys to plugin editors
+//!
+//! Keys that reach the host window instead of the editor's own child window
+//! are turned into the character, virtual key and modifiers a VST3 view expects.
+
+use rack_vst3_gui::{KeyModifiers, VirtualKey};
+use x11rb::protocol::xproto::{ConnectionExt as _, KeyButMask, Keycode, Keysym};
+
+use super::gui::Vst3GuiError;
+
+/// A key event, ready for `Vst3Gui::key_event`
+pub(super) struct KeyInput {
+    pub character: Option<char>,
+    pub key: Option<VirtualKey>,
+    pub modifiers: KeyModifiers,
+}
+
+/// Keycode to keysym table of the X server
+pub(super) struct Keymap {
+    min_keycode: Keycode,
+    keysyms_per_keycode: usize,
+    keysyms: Vec<Keysym>,
+}
+
+impl Keymap {
+    pub fn load(conn: &impl x11rb::connection::Connection) -> Result<Self, Vst3GuiError> {
+        let setup = conn.setup();
+        let (min_keycode, max_keycode) = (setup.min_keycode, setup.max_keycode);
+        let reply = conn
+            .get_keyboard_mapping(min_keycode, max_keycode - min_keycode + 1)
+            .map_err(|e| Vst3GuiError::X11Connection(e.to_string()))?
+            .reply()
+            .map_err(|e| Vst3GuiError::X11Connection(e.to_string()))?;
+        Ok(Self {
+            min_keycode,
+            keysyms_per_keycode: reply.keysyms_per_keycode as usize,
+            keysyms: reply.keysyms,
+        })
+    }
+
+    pub fn translate(&self, keycode: Keycode, state: KeyButMask) -> KeyInput {
+        let held = |mask: KeyButMask| u16::from(state) & u16::from(mask) != 0;
+        let shift = held(KeyButMask::SHIFT);
+        let modifiers = KeyModifiers {
+            shift,
+            alt: held(KeyButMask::MOD1),
+            command: held(KeyButMask::MOD4),
+            control: held(KeyButMask::CONTROL),
+        };
+
+        let keysym = self.keysym(keycode, shift, held(KeyButMask::MOD2));
+        let mut character = keysym_to_char(keysym);
+        if held(KeyButMask::LOCK) && !shift {
+            character = character.map(|c| c.to_uppercase().next().unwrap_or(c));
+        }
+
+        KeyInput {
+            character,
+            key: keysym_to_virtual_key(keysym),
+            modifiers,
+        }
+    }
+
+    /// Keysym for the first two levels (plain and shifted); Num Lock flips keypad keys
+    fn keysym(&self, keycode: Keycode, shift: bool, num_lock: bool) -> Keysym {
+        let Some(start) = keycode
+            .checked_sub(self.min_keycode)
+            .map(|offset| offset as usize * self.keysyms_per_keycode)
+        else {
+            return 0;
+        };
+        let syms = self.keysyms.get(start..start + self.keysyms_per_keycode).unwrap_or_default();
+        let plain = syms.first().copied().unwrap_or(0);
+        let shifted = syms.get(1).copied().filter(|&s| s != 0).unwrap_or(plain);
+
+        let use_shifted = if num_lock && is_keypad(shifted) { !shift } else { shift };
+        if use_shifted { shifted } else { plain }
+    }
+}
+
+fn is_keypad(keysym: Keysym) -> bool {
+    (0xff80..=0xffbd).contains(&keysym)
+}
+
+fn keysym_to_char(keysym: Keysym) -> Option<char> {
+    match keysym {
+        // Latin-1 keysyms are their code points
+        0x20..=0x7e | 0xa0..=0xff => char::from_u32(keysym),
+        // Unicode keysyms
+        0x0100_0100..=0x0110_ffff => char::from_u32(keysym - 0x0100_0000),
+        0xffb0..=0xffb9 => char::from_digit(keysym - 0xffb0, 10),
+        0xff80 => Some(' '),
+        0xffaa => Some('*'),
+        0xffab => Some('+'),
+        0xffad => Some('-'),
+        0xffae => Some('.'),
+        0xffaf => Some('/'),
+        0xffbd => Some('='),
+        _ => None,
+    }
+}
+
+fn keysym_to_virtual_key(keysym: Keysym) -> Option<VirtualKey> {
+    Some(match keysym {
+        0xff08 => VirtualKey::Back,
+        0xff09 | 0xfe20 => VirtualKey::Tab,
+        0xff0b => VirtualKey::Clear,
+        0xff0d => VirtualKey::Return,
+        0xff13 => VirtualKey::Pause,
+        0xff14 => VirtualKey::Scroll,
+        0xff1b => VirtualKey::Escape,
+        0x20 | 0xff80 => VirtualKey::Space,
+        0xff50 | 0xff95 => VirtualKey::Home,
+        0xff51 | 0xff96 => VirtualKey::Left,
+        0xff52 | 0xff97 => VirtualKey::Up,
+        0xff53 | 0xff98 => VirtualKey::Right,
+        0xff54 | 0xff99 => VirtualKey::Down,
+        0xff55 | 0xff9a => VirtualKey::PageUp,
+        0xff56 | 0xff9b => VirtualKey::PageDown,
+        0xff57 | 0xff9c => VirtualKey::End,
+        0xff60 => VirtualKey::Select,
+        0xff61 => VirtualKey::Print,
+        0xff63 | 0xff9e => VirtualKey::Insert,
+        0xff67 => VirtualKey::ContextMenu,
+        0xff6a => VirtualKey::Help,
+        0xff7f => VirtualKey::NumLock,
+        0xff8d => VirtualKey::Enter,
+        0xffaa => VirtualKey::Multiply,
+        0xffab => VirtualKey::Add,
+        0xffac => VirtualKey::Separator,
+        0xffad => VirtualKey::Subtract,
+        0xffae => VirtualKey::Decimal,
+        0xffaf => VirtualKey::Divide,
+        0xffb0..=0xffb9 => return VirtualKey::numpad((keysym - 0xffb0) as u8),
+        0xffbe..=0xffc9 => return VirtualKey::function((keysym - 0xffbe + 1) as u8),
+        0xffe1 | 0xffe2 => VirtualKey::Shift,
+        0xffe3 | 0xffe4 => VirtualKey::Control,
+        0xffe9 | 0xffea => VirtualKey::Alt,
+        0xffff | 0xff9f => VirtualKey::Delete,
+        _ => return None,
+    })
+}
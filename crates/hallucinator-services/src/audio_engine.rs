@@ -1,15 +1,50 @@
 //! Audio engine for timeline playback
 
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-use hallucinator_core::{MidiClip, MidiEvent, Timeline, TrackKind};
+use crossbeam_channel::{Receiver, Sender, TrySendError};
+use hallucinator_core::{
+    ClipId, DeviceTarget, EventSchedule, InstrumentOutput, MidiClip, MidiControl, MidiEvent, ParamOwner, PluginState, StepLocks,
+    Timeline, Track, TrackId, TrackInput, TrackKind, TrackOutput,
+};
 use thiserror::Error;
 use tracing::info;
 
-use crate::audio_effects::{EffectChain, Instrument};
-use crate::audio_io::{AudioOutputError, RealtimeOutputStream};
+use crate::audio_effects::{DryWet, EffectChain, EffectParam, InsertPort, Instrument, PluginStateReader};
+use crate::audio_io::{AudioOutputError, OutputSettings, RealtimeOutputStream};
+use crate::cue::{CueFeed, CueOutput, CueSettings};
+use crate::input_monitor::{select_input, LiveInput};
+use crate::latency::PULSE_LEVEL;
+use crate::loudness::{LoudnessMeter, LoudnessState};
+use crate::metronome::Metronome;
+use crate::notifications;
+use crate::preview::{Preview, PreviewSettings};
+use crate::project::ProjectDevices;
+
+/// A clip controller change due in the current block: (controller, value, sample offset)
+type ControlEvent = (MidiControl, u16, u32);
+
+/// Frames per block when rendering a track offline (freeze, bounce)
+const RENDER_BLOCK: usize = 512;
+
+/// Drum pattern updates that can wait for the audio thread; when it falls further behind
+/// the oldest are dropped, as each update replaces the whole pattern
+const DRUM_PATTERN_UPDATES: usize = 4;
+
+/// Replaced instruments the audio thread can hand back before the GUI drops them; while
+/// that many are waiting, further commands stay queued
+const RETIRED_INSTRUMENTS: usize = 8;
+
+/// Live events that can wait for the audio thread (and be scheduled at once); when it
+/// falls further behind, or no output is running, the oldest are dropped
+const LIVE_EVENTS: usize = 256;
+
+/// Live events play this long plus one block after they were sent, which covers the
+/// wait for the next audio callback and jitter in when it runs
+const LIVE_MARGIN_SECS: f64 = 0.002;
 
 #[derive(Debug, Error)]
 pub enum AudioEngineError {
@@ -21,14 +56,178 @@ pub enum AudioEngineError {
     NotRunning,
 }
 
+/// Delay line holding back a track's output to line it up with slower tracks (effect latency
+/// compensation); it grows by inserting silence and shrinks by dropping the oldest audio
+#[derive(Default)]
+struct CompensationDelay {
+    frames: VecDeque<(f32, f32)>,
+}
+
+impl CompensationDelay {
+    /// Delay a block by `delay` frames
+    fn process(&mut self, delay: usize, left: &mut [f32], right: &mut [f32]) {
+        if self.frames.len() < delay {
+            let missing = delay - self.frames.len();
+            for _ in 0..missing {
+                self.frames.push_front((0.0, 0.0));
+            }
+        } else {
+            let excess = self.frames.len() - delay;
+            self.frames.drain(..excess);
+        }
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            self.frames.push_back((*l, *r));
+            (*l, *r) = self.frames.pop_front().unwrap_or_default();
+        }
+    }
+}
+
+/// A stereo block, and whether anything was written to it this block
+#[derive(Default)]
+struct StereoBlock {
+    left: Vec<f32>,
+    right: Vec<f32>,
+    filled: bool,
+}
+
+impl StereoBlock {
+    /// Silence for a block of `frames` frames (allocates only when blocks get longer)
+    fn clear(&mut self, frames: usize) {
+        for channel in [&mut self.left, &mut self.right] {
+            channel.clear();
+            channel.resize(frames, 0.0);
+        }
+        self.filled = false;
+    }
+
+    /// Add a block (silence first, if nothing was added this block)
+    fn add(&mut self, left: &[f32], right: &[f32]) {
+        if !self.filled {
+            self.clear(left.len());
+            self.filled = true;
+        }
+        for i in 0..left.len().min(self.left.len()) {
+            self.left[i] += left[i];
+            self.right[i] += right[i];
+        }
+    }
+}
+
+/// What the track routing is worked out from, for one track
+#[derive(Clone, Copy, PartialEq)]
+struct RoutingKey {
+    id: TrackId,
+    input: TrackInput,
+    output: TrackOutput,
+    instrument_output: Option<InstrumentOutput>,
+    instrument_id: Option<u64>,
+    /// Effect latency (0 while frozen, as its effects are printed)
+    latency: usize,
+}
+
+/// Track render order and latency compensation, worked out again only when the routing
+/// or an effect latency changes
+#[derive(Default)]
+struct TrackRouting {
+    /// Per track index
+    keys: Vec<RoutingKey>,
+    order: Vec<usize>,
+    /// Frames each track's output is held back
+    delays: Vec<usize>,
+    /// Whether another track takes the track's output as its input
+    tapped: Vec<bool>,
+}
+
+impl TrackRouting {
+    /// Follow the timeline's routing; returns whether it changed (which allocates)
+    fn update(&mut self, timeline: &Timeline, track_effects: Option<&HashMap<u64, EffectChain>>) -> bool {
+        let key = |track: &Track| RoutingKey {
+            id: track.id,
+            input: track.input,
+            output: track.output,
+            instrument_output: track.instrument_output,
+            instrument_id: track.instrument_id,
+            latency: match (&track.freeze, track.effect_chain_id) {
+                (None, Some(id)) => track_effects.and_then(|chains| chains.get(&id)).map_or(0, EffectChain::latency_samples),
+                _ => 0,
+            },
+        };
+        if self.keys.len() == timeline.tracks.len() && timeline.tracks.iter().zip(&self.keys).all(|(t, k)| key(t) == *k) {
+            return false;
+        }
+        self.keys = timeline.tracks.iter().map(key).collect();
+        self.order = timeline.render_order();
+        let latencies: Vec<usize> = self.keys.iter().map(|k| k.latency).collect();
+        self.delays = timeline.compensation_delays(&latencies);
+        self.tapped = self.keys.iter()
+            .map(|k| self.keys.iter().any(|other| other.input == TrackInput::Track(k.id)))
+            .collect();
+        true
+    }
+
+    fn index(&self, id: TrackId) -> Option<usize> {
+        self.keys.iter().position(|k| k.id == id)
+    }
+}
+
+/// Working buffers of the track mix, kept from block to block so the audio thread doesn't
+/// allocate them (they only grow with the block length and the track count)
+#[derive(Default)]
+struct MixBuffers {
+    routing: TrackRouting,
+    /// The track being rendered
+    track: StereoBlock,
+    master: StereoBlock,
+    /// Signal routed into each track (by index) ahead of its effects
+    bus_inputs: Vec<StereoBlock>,
+    /// Output of each track another one takes as input
+    track_outputs: Vec<StereoBlock>,
+    /// What goes to each hardware output pair, by its left channel
+    hardware_outputs: Vec<StereoBlock>,
+    /// Instruments already rendered this block, by ID
+    rendered_instruments: Vec<u64>,
+    /// Instruments of frozen tracks, by ID
+    frozen_instruments: Vec<u64>,
+}
+
+impl MixBuffers {
+    /// Ready the buffers for a block of `frames` frames over `tracks` tracks and `channels`
+    /// output channels
+    fn start_block(&mut self, frames: usize, tracks: usize, channels: usize) {
+        self.master.clear(frames);
+        self.master.filled = true;
+        for outputs in [&mut self.bus_inputs, &mut self.track_outputs] {
+            outputs.resize_with(tracks, StereoBlock::default);
+            outputs.iter_mut().for_each(|block| block.filled = false);
+        }
+        if self.hardware_outputs.len() < channels {
+            self.hardware_outputs.resize_with(channels, StereoBlock::default);
+        }
+        self.hardware_outputs.iter_mut().for_each(|block| block.filled = false);
+        self.rendered_instruments.clear();
+        self.frozen_instruments.clear();
+    }
+}
+
+/// Offline copies of a track's instrument and effect chain for `AudioEngine::render_track`
+/// to play through, leaving the live ones to the audio thread
+#[derive(Default)]
+pub struct RenderDevices {
+    pub instrument: Option<Instrument>,
+    pub effects: Option<EffectChain>,
+}
+
 /// A single step in the drum pattern
 #[derive(Clone, Default)]
 pub struct DrumPatternStep {
     pub active: bool,
     pub active_layers: u16,
+    /// Sample kit settings replaced for this step's triggers only
+    pub locks: StepLocks,
 }
 
 /// Drum pattern for sample-accurate sequencer triggering
+#[derive(Clone)]
 pub struct DrumPattern {
     pub steps: [DrumPatternStep; 12],
     pub step_count: usize,
@@ -38,6 +237,19 @@ pub struct DrumPattern {
     pub snap_to_arrange: bool,
     /// Which rows are enabled (unmuted) - bit N = row N enabled
     pub row_enabled: u16,
+    /// Steps each row cycles through before repeating (0 = all `step_count` steps);
+    /// rows shorter than the pattern drift against it for polymetric patterns
+    pub row_lengths: [u8; 12],
+}
+
+impl DrumPattern {
+    /// Number of steps `row` cycles through
+    pub fn row_length(&self, row: usize) -> usize {
+        match self.row_lengths[row] as usize {
+            0 => self.step_count,
+            len => len.min(self.step_count),
+        }
+    }
 }
 
 impl Default for DrumPattern {
@@ -49,16 +261,35 @@ impl Default for DrumPattern {
             instrument_id: None,
             snap_to_arrange: false,
             row_enabled: 0xFFF,  // All 12 rows enabled by default
+            row_lengths: [0; 12],
         }
     }
 }
 
+/// A change handed to the audio thread, which carries it out at the start of its next block
+/// instead of the GUI taking the lock the audio thread renders under
+pub enum EngineCommand {
+    /// Put a loaded instrument in place
+    AddInstrument { id: u64, instrument: Box<Instrument> },
+}
+
+/// A note or pad hit played from the GUI, timed by the engine from when it was sent
+#[derive(Debug, Clone, Copy)]
+pub enum LiveEvent {
+    NoteOn { instrument_id: u64, pitch: u8, velocity: u8 },
+    NoteOff { instrument_id: u64, pitch: u8 },
+    /// A sequencer step's active layers on a sample kit
+    KitStep { instrument_id: u64, step: usize, velocity: u8, active_layers: u16 },
+}
+
 /// Audio engine state shared between UI and audio thread
 pub struct EngineState {
     /// Current playback position in samples
     pub position: AtomicU64,
     /// Playing flag
     pub playing: AtomicBool,
+    /// Transport is recording (armed tracks on auto monitoring play their input)
+    pub recording: AtomicBool,
     /// Timeline data (protected by mutex for clip access)
     pub timeline: Mutex<Timeline>,
     /// Master effect chain
@@ -67,31 +298,404 @@ pub struct EngineState {
     pub instruments: Mutex<HashMap<u64, Instrument>>,
     /// Per-track effect chains keyed by chain ID
     pub track_effects: Mutex<HashMap<u64, EffectChain>>,
-    /// Preview sample data (mono) and playback position
-    pub preview_sample: Mutex<Option<Vec<f32>>>,
-    pub preview_position: AtomicU64,
-    /// Drum pattern for sample-accurate sequencer
-    pub drum_pattern: Mutex<DrumPattern>,
-    /// Current drum step (for GUI display)
-    pub drum_current_step: AtomicUsize,
+    /// Bypass fades of instruments keyed by instrument ID (absent = never bypassed)
+    instrument_bypass: Mutex<HashMap<u64, DryWet>>,
+    /// Latency compensation of track outputs keyed by track (absent = not delayed)
+    output_delays: Mutex<HashMap<TrackId, CompensationDelay>>,
+    /// Track routing and mix buffers (audio thread only, so never waited on)
+    mix: Mutex<MixBuffers>,
+    /// Timeline position of the latency calibration pulse (u64::MAX = not calibrating);
+    /// while set, the output is silent apart from the pulse
+    pub calibration_pulse: AtomicU64,
+    /// Live input from the input monitor, played through monitoring audio tracks
+    pub live_input: Arc<LiveInput>,
+    /// Sample auditioned from the browser, played on the cue bus
+    pub preview: Mutex<Preview>,
+    /// Click on every beat while playing
+    pub metronome: Mutex<Metronome>,
+    /// Where the cue bus plays and which sources go to it
+    pub cue: Mutex<CueSettings>,
+    /// Cue bus audio for a second output device
+    cue_feed: Arc<CueFeed>,
+    /// Drum pattern the sequencer plays (audio thread only, so never waited on)
+    drum_pattern: Mutex<DrumPattern>,
+    /// Whole new drum patterns from the GUI, sent and received ends; the audio thread
+    /// takes up the latest at its next block
+    drum_pattern_updates: Sender<DrumPattern>,
+    drum_pattern_queue: Receiver<DrumPattern>,
+    /// Steps elapsed since the drum pattern started (u64::MAX before the first step)
+    pub drum_step_tick: AtomicU64,
+    /// Current step of each drum row (for GUI display)
+    pub drum_row_steps: [AtomicUsize; 12],
     /// Independent drum position (increments continuously, ignores transport loop)
     pub drum_position: AtomicU64,
+    /// Count of loop wraps and play starts; seeds per-note probability rolls
+    loop_pass: AtomicU64,
+    /// Master output loudness and correlation readings (for GUI display)
+    pub loudness: LoudnessState,
+    /// Channels of the output device, for hardware output routing (0 before the first block)
+    pub output_channels: AtomicU16,
+    /// Audio-thread loudness measurement state
+    loudness_meter: Mutex<LoudnessMeter>,
+    /// Commands for the audio thread, sent and received ends
+    commands: Sender<EngineCommand>,
+    command_queue: Receiver<EngineCommand>,
+    /// Instruments replaced by commands, sent back to be dropped off the audio thread
+    retired: Sender<Box<Instrument>>,
+    retired_queue: Receiver<Box<Instrument>>,
+    /// Live events stamped with when they were sent, sent and received ends
+    live_events: Sender<(Instant, LiveEvent)>,
+    live_queue: Receiver<(Instant, LiveEvent)>,
+    /// Live events waiting for their frame (audio thread only)
+    live_schedule: Mutex<EventSchedule<LiveEvent>>,
 }
 
 impl EngineState {
     pub fn new(sample_rate: u32) -> Self {
+        let (commands, command_queue) = crossbeam_channel::unbounded();
+        let (retired, retired_queue) = crossbeam_channel::bounded(RETIRED_INSTRUMENTS);
+        let (live_events, live_queue) = crossbeam_channel::bounded(LIVE_EVENTS);
+        let (drum_pattern_updates, drum_pattern_queue) = crossbeam_channel::bounded(DRUM_PATTERN_UPDATES);
         Self {
             position: AtomicU64::new(0),
             playing: AtomicBool::new(false),
+            recording: AtomicBool::new(false),
             timeline: Mutex::new(Timeline::new(sample_rate)),
             master_effects: Mutex::new(EffectChain::new()),
             instruments: Mutex::new(HashMap::new()),
             track_effects: Mutex::new(HashMap::new()),
-            preview_sample: Mutex::new(None),
-            preview_position: AtomicU64::new(u64::MAX), // MAX = not playing
+            instrument_bypass: Mutex::new(HashMap::new()),
+            output_delays: Mutex::new(HashMap::new()),
+            mix: Mutex::new(MixBuffers::default()),
+            calibration_pulse: AtomicU64::new(u64::MAX),
+            live_input: Arc::new(LiveInput::default()),
+            preview: Mutex::new(Preview::default()),
+            metronome: Mutex::new(Metronome::default()),
+            cue: Mutex::new(CueSettings::default()),
+            cue_feed: Arc::new(CueFeed::default()),
             drum_pattern: Mutex::new(DrumPattern::default()),
-            drum_current_step: AtomicUsize::new(0),
+            drum_pattern_updates,
+            drum_pattern_queue,
+            drum_step_tick: AtomicU64::new(u64::MAX),
+            drum_row_steps: std::array::from_fn(|_| AtomicUsize::new(0)),
             drum_position: AtomicU64::new(0),
+            loop_pass: AtomicU64::new(0),
+            loudness: LoudnessState::default(),
+            output_channels: AtomicU16::new(0),
+            loudness_meter: Mutex::new(LoudnessMeter::new(sample_rate as f32)),
+            commands,
+            command_queue,
+            retired,
+            retired_queue,
+            live_events,
+            live_queue,
+            live_schedule: Mutex::new(EventSchedule::with_capacity(LIVE_EVENTS)),
+        }
+    }
+
+    /// Queue a command for the audio thread
+    pub fn send_command(&self, command: EngineCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    /// Replace the drum pattern the sequencer plays, from its next block on
+    pub fn set_drum_pattern(&self, mut pattern: DrumPattern) {
+        loop {
+            match self.drum_pattern_updates.try_send(pattern) {
+                Ok(()) | Err(TrySendError::Disconnected(_)) => return,
+                // The oldest waiting update is superseded by this one anyway
+                Err(TrySendError::Full(rejected)) => {
+                    let _ = self.drum_pattern_queue.try_recv();
+                    pattern = rejected;
+                }
+            }
+        }
+    }
+
+    /// Play a live event, timed from now
+    pub fn send_live_event(&self, event: LiveEvent) {
+        let mut timed = (Instant::now(), event);
+        loop {
+            match self.live_events.try_send(timed) {
+                Ok(()) | Err(TrySendError::Disconnected(_)) => return,
+                Err(TrySendError::Full(rejected)) => {
+                    let _ = self.live_queue.try_recv();
+                    timed = rejected;
+                }
+            }
+        }
+    }
+
+    /// Drop the live events waiting to be played, which have gone stale while no
+    /// output was running
+    fn discard_live_events(&self) {
+        self.live_queue.try_iter().for_each(drop);
+        if let Ok(mut schedule) = self.live_schedule.lock() {
+            schedule.clear();
+        }
+    }
+
+    /// Schedule the live events sent since the last block, each one block plus
+    /// `LIVE_MARGIN_SECS` after it was sent, then queue the ones due in this block on their
+    /// instruments
+    fn play_live_events(&self, instruments: &mut HashMap<u64, Instrument>, num_frames: usize, sample_rate: u32) {
+        let Ok(mut schedule) = self.live_schedule.lock() else { return };
+        let now = Instant::now();
+        let lookahead = num_frames as u64 + (LIVE_MARGIN_SECS * sample_rate as f64) as u64;
+        for (sent, event) in self.live_queue.try_iter() {
+            let age = (now.saturating_duration_since(sent).as_secs_f64() * sample_rate as f64) as u64;
+            let frame = schedule.clock() + lookahead.saturating_sub(age);
+            schedule.push(frame, event);
+        }
+
+        schedule.next_block(num_frames, |offset, event| match event {
+            LiveEvent::NoteOn { instrument_id, pitch, velocity } => {
+                if let Some(instrument) = instruments.get_mut(&instrument_id) {
+                    instrument.queue_note_on(pitch, velocity, 0, offset);
+                }
+            }
+            LiveEvent::NoteOff { instrument_id, pitch } => {
+                if let Some(instrument) = instruments.get_mut(&instrument_id) {
+                    instrument.queue_note_off(pitch, 0, 0, offset);
+                }
+            }
+            LiveEvent::KitStep { instrument_id, step, velocity, active_layers } => {
+                if let Some(Instrument::SampleKit(kit)) = instruments.get_mut(&instrument_id) {
+                    kit.queue_step_trigger(step, velocity, active_layers, offset);
+                }
+            }
+        });
+    }
+
+    /// Carry out the queued commands, with the instruments already locked
+    fn apply_commands(&self, instruments: &mut HashMap<u64, Instrument>) {
+        // Only this thread sends on `retired`, so while it has room a replacement fits
+        while !self.retired.is_full() {
+            let Ok(command) = self.command_queue.try_recv() else { break };
+            match command {
+                EngineCommand::AddInstrument { id, mut instrument } => match instruments.get_mut(&id) {
+                    // The instrument replaced goes back in the command's box
+                    Some(current) => {
+                        std::mem::swap(current, &mut *instrument);
+                        let _ = self.retired.try_send(instrument);
+                    }
+                    None => {
+                        instruments.insert(id, *instrument);
+                    }
+                },
+            }
+        }
+    }
+
+    /// Drop the instruments the audio thread replaced
+    fn drop_retired_instruments(&self) {
+        self.retired_queue.try_iter().for_each(drop);
+    }
+
+    /// Replace the loudness meter once the project's sample rate no longer matches it.
+    /// Building one allocates, so it's done here rather than on the audio thread.
+    pub fn update_loudness_meter(&self) {
+        let Some(sample_rate) = self.timeline.lock().ok().map(|t| t.transport.sample_rate as f32) else { return };
+        if self.loudness_meter.lock().is_ok_and(|meter| meter.sample_rate() == sample_rate) {
+            return;
+        }
+        let meter = LoudnessMeter::new(sample_rate);
+        let old = self.loudness_meter.lock().map(|mut current| std::mem::replace(&mut *current, meter));
+        drop(old);
+    }
+
+    /// Play from current position
+    pub fn play(&self) {
+        self.playing.store(true, Ordering::SeqCst);
+        self.loop_pass.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut timeline) = self.timeline.lock() {
+            timeline.transport.play();
+        }
+    }
+
+    /// Pause playback
+    pub fn pause(&self) {
+        self.playing.store(false, Ordering::SeqCst);
+        if let Ok(mut timeline) = self.timeline.lock() {
+            timeline.transport.pause();
+        }
+    }
+
+    /// Stop and reset to beginning
+    pub fn stop_playback(&self) {
+        self.playing.store(false, Ordering::SeqCst);
+        self.position.store(0, Ordering::SeqCst);
+        self.reset_drum_steps();
+        if let Ok(mut timeline) = self.timeline.lock() {
+            timeline.transport.stop();
+        }
+    }
+
+    /// Restart every drum row from its first step
+    fn reset_drum_steps(&self) {
+        self.drum_step_tick.store(u64::MAX, Ordering::SeqCst);
+        for row_step in &self.drum_row_steps {
+            row_step.store(0, Ordering::SeqCst);
+        }
+    }
+
+    /// Seek to position in samples
+    pub fn seek(&self, position_samples: u64) {
+        self.position.store(position_samples, Ordering::SeqCst);
+        // Reset drum step when seeking to start (common case: spacebar stop)
+        if position_samples == 0 {
+            self.reset_drum_steps();
+        }
+        if let Ok(mut timeline) = self.timeline.lock() {
+            timeline.transport.position_samples = position_samples;
+        }
+    }
+
+    /// All parameters of a device
+    pub fn params(&self, owner: ParamOwner) -> Option<Vec<EffectParam>> {
+        match owner {
+            ParamOwner::Instrument(id) => {
+                let instruments = self.instruments.lock().ok()?;
+                Some(instruments.get(&id)?.get_params().to_vec())
+            }
+            ParamOwner::TrackEffect { chain_id, index } => {
+                let chains = self.track_effects.lock().ok()?;
+                Some(chains.get(&chain_id)?.effects().get(index)?.get_params())
+            }
+            ParamOwner::MasterEffect(index) => {
+                let chain = self.master_effects.lock().ok()?;
+                Some(chain.effects().get(index)?.get_params())
+            }
+        }
+    }
+
+    /// Current value of a device parameter
+    pub fn param_value(&self, owner: ParamOwner, name: &str) -> Option<f32> {
+        self.params(owner)?.into_iter().find(|p| p.name == name).map(|p| p.value)
+    }
+
+    /// Set a device parameter wherever it lives
+    pub fn set_param(&self, owner: ParamOwner, name: &str, value: f32) {
+        match owner {
+            ParamOwner::Instrument(id) => {
+                let Ok(mut instruments) = self.instruments.lock() else { return };
+                if let Some(inst) = instruments.get_mut(&id) {
+                    inst.set_param(name, value);
+                }
+            }
+            ParamOwner::TrackEffect { chain_id, index } => {
+                let Ok(mut chains) = self.track_effects.lock() else { return };
+                if let Some(effect) = chains.get_mut(&chain_id).and_then(|c| c.effects_mut().get_mut(index)) {
+                    effect.set_param(name, value);
+                }
+            }
+            ParamOwner::MasterEffect(index) => {
+                let Ok(mut chain) = self.master_effects.lock() else { return };
+                if let Some(effect) = chain.effects_mut().get_mut(index) {
+                    effect.set_param(name, value);
+                }
+            }
+        }
+    }
+
+    /// Store each track's instrument plugin state on the timeline, ready to serialize. The
+    /// states are read through readers taken from the instruments, so the audio thread
+    /// keeps playing them while the plugins write out their state.
+    pub fn capture_plugin_states(&self) {
+        let readers: Vec<(u64, PluginStateReader)> = match self.instruments.lock() {
+            Ok(instruments) => instruments
+                .iter()
+                .filter_map(|(&id, inst)| Some((id, inst.plugin_state_reader()?)))
+                .collect(),
+            Err(_) => return,
+        };
+        let states: HashMap<u64, PluginState> = readers
+            .into_iter()
+            .filter_map(|(id, reader)| match reader.state() {
+                Ok(state) => Some((id, state)),
+                Err(e) => {
+                    tracing::warn!("Failed to capture state of {}: {}", reader.name(), e);
+                    None
+                }
+            })
+            .collect();
+        let Ok(mut timeline) = self.timeline.lock() else { return };
+        for track in &mut timeline.tracks {
+            track.instrument_state = track.instrument_id.and_then(|id| states.get(&id).cloned());
+        }
+    }
+
+    /// Put a loaded project in place of the current one: its timeline, instruments (given
+    /// their saved plugin states before the audio thread has them) and effect chains.
+    /// Playback stops, and the devices replaced are dropped on the calling thread once the
+    /// audio thread has let go of them.
+    pub fn open_project(&self, mut timeline: Timeline, mut devices: ProjectDevices) {
+        Self::restore_plugin_states(&timeline, &mut devices.instruments);
+        self.stop_playback();
+        timeline.transport.stop();
+        let old_instruments = self.instruments.lock().ok().map(|mut i| std::mem::replace(&mut *i, devices.instruments));
+        let old_chains = self.track_effects.lock().ok().map(|mut c| std::mem::replace(&mut *c, devices.effect_chains));
+        let old_master = self.master_effects.lock().ok().map(|mut c| std::mem::replace(&mut *c, devices.master_effects));
+        let old_timeline = self.timeline.lock().ok().map(|mut t| std::mem::replace(&mut *t, timeline));
+        if let Ok(mut fades) = self.instrument_bypass.lock() {
+            fades.clear();
+        }
+        if let Ok(mut delays) = self.output_delays.lock() {
+            delays.clear();
+        }
+        self.sync_instrument_outputs();
+        drop((old_instruments, old_chains, old_master, old_timeline));
+    }
+
+    /// Give newly loaded instruments the plugin states saved on the tracks playing them
+    fn restore_plugin_states(timeline: &Timeline, instruments: &mut HashMap<u64, Instrument>) {
+        for track in &timeline.tracks {
+            let (Some(id), Some(state)) = (track.instrument_id, &track.instrument_state) else { continue };
+            if let Some(inst) = instruments.get_mut(&id)
+                && let Err(e) = inst.restore_plugin_state(state)
+            {
+                tracing::warn!("Failed to restore plugin state for instrument {}: {}", id, e);
+            }
+        }
+    }
+
+    /// Bypass an instrument; its output fades out (or back in) over a few milliseconds
+    pub fn set_instrument_bypass(&self, id: u64, bypass: bool) {
+        if let Ok(mut bypass_fades) = self.instrument_bypass.lock() {
+            bypass_fades.entry(id).or_default().set_bypassed(bypass);
+        }
+    }
+
+    pub fn is_instrument_bypassed(&self, id: u64) -> bool {
+        self.instrument_bypass
+            .lock()
+            .ok()
+            .and_then(|fades| Some(fades.get(&id)?.is_bypassed()))
+            .unwrap_or(false)
+    }
+
+    /// Enable exactly the instrument output buses some track plays; the others
+    /// fold back into their instrument's main output. Call after changing routing.
+    pub fn sync_instrument_outputs(&self) {
+        let routed: Vec<(u64, usize)> = match self.timeline.lock() {
+            Ok(timeline) => timeline
+                .tracks
+                .iter()
+                .filter_map(|t| {
+                    let output = t.instrument_output?;
+                    let source = timeline.tracks.iter().find(|s| s.id == output.track)?;
+                    Some((source.instrument_id?, output.bus))
+                })
+                .collect(),
+            Err(_) => return,
+        };
+        let Ok(mut instruments) = self.instruments.lock() else { return };
+        for (&id, inst) in instruments.iter_mut() {
+            for bus in 1..inst.output_buses().len() {
+                if let Err(e) = inst.set_output_enabled(bus, routed.contains(&(id, bus))) {
+                    tracing::warn!("Failed to switch output {} of instrument {}: {}", bus, id, e);
+                }
+            }
         }
     }
 }
@@ -100,6 +704,10 @@ impl EngineState {
 pub struct AudioEngine {
     state: Arc<EngineState>,
     stream: Option<RealtimeOutputStream>,
+    /// Second output device playing the cue bus
+    cue_stream: Option<RealtimeOutputStream>,
+    /// Device and buffer size `stream` opens with
+    output: OutputSettings,
     sample_rate: u32,
 }
 
@@ -108,6 +716,8 @@ impl AudioEngine {
         Self {
             state: Arc::new(EngineState::new(sample_rate)),
             stream: None,
+            cue_stream: None,
+            output: OutputSettings::default(),
             sample_rate,
         }
     }
@@ -123,21 +733,67 @@ impl AudioEngine {
             return Err(AudioEngineError::AlreadyRunning);
         }
 
+        self.state.discard_live_events();
         let state = self.state.clone();
+        let output = &self.output;
 
-        let stream = RealtimeOutputStream::start(move |buffer, _sample_rate, channels| {
+        let stream = RealtimeOutputStream::start_on(output.device.as_deref(), output.buffer_size, move |buffer, _sample_rate, channels| {
             Self::render_audio(&state, buffer, channels);
         })?;
+        if stream.sample_rate() != self.sample_rate {
+            tracing::warn!(
+                device_rate = stream.sample_rate(), engine_rate = self.sample_rate,
+                "Output device runs at a different sample rate, so playback is off pitch"
+            );
+        }
 
         self.stream = Some(stream);
         info!("Audio engine started");
         Ok(())
     }
 
+    pub fn output_settings(&self) -> OutputSettings {
+        self.output.clone()
+    }
+
+    /// Play through another device or buffer size, reopening the output stream if it is running;
+    /// if the new one fails to open the engine goes back to the default device
+    pub fn set_output_settings(&mut self, settings: OutputSettings) -> Result<(), AudioEngineError> {
+        self.output = settings;
+        let Some(stream) = self.stream.take() else { return Ok(()) };
+        stream.stop();
+        // The old stream has to let go of the device before it can be opened again
+        drop(stream);
+        self.apply_queued_commands();
+
+        let result = self.start();
+        if result.is_err() && self.output != OutputSettings::default() {
+            self.output = OutputSettings::default();
+            if let Err(e) = self.start() {
+                notifications::error("Failed to reopen the default output", e);
+            }
+        }
+        result
+    }
+
+    /// Carry out the commands still queued once the output has stopped, as no audio
+    /// thread is left to
+    fn apply_queued_commands(&self) {
+        let Ok(mut instruments) = self.state.instruments.lock() else { return };
+        loop {
+            self.state.apply_commands(&mut instruments);
+            self.state.drop_retired_instruments();
+            if self.state.command_queue.is_empty() {
+                break;
+            }
+        }
+    }
+
     /// Stop the audio engine
     pub fn stop(&mut self) -> Result<(), AudioEngineError> {
         let stream = self.stream.take().ok_or(AudioEngineError::NotRunning)?;
         stream.stop();
+        self.apply_queued_commands();
         self.state.playing.store(false, Ordering::SeqCst);
         info!("Audio engine stopped");
         Ok(())
@@ -145,40 +801,22 @@ impl AudioEngine {
 
     /// Play from current position
     pub fn play(&self) {
-        self.state.playing.store(true, Ordering::SeqCst);
-        if let Ok(mut timeline) = self.state.timeline.lock() {
-            timeline.transport.play();
-        }
+        self.state.play();
     }
 
     /// Pause playback
     pub fn pause(&self) {
-        self.state.playing.store(false, Ordering::SeqCst);
-        if let Ok(mut timeline) = self.state.timeline.lock() {
-            timeline.transport.pause();
-        }
+        self.state.pause();
     }
 
     /// Stop and reset to beginning
     pub fn stop_playback(&self) {
-        self.state.playing.store(false, Ordering::SeqCst);
-        self.state.position.store(0, Ordering::SeqCst);
-        self.state.drum_current_step.store(0, Ordering::SeqCst);
-        if let Ok(mut timeline) = self.state.timeline.lock() {
-            timeline.transport.stop();
-        }
+        self.state.stop_playback();
     }
 
     /// Seek to position in samples
     pub fn seek(&self, position_samples: u64) {
-        self.state.position.store(position_samples, Ordering::SeqCst);
-        // Reset drum step when seeking to start (common case: spacebar stop)
-        if position_samples == 0 {
-            self.state.drum_current_step.store(0, Ordering::SeqCst);
-        }
-        if let Ok(mut timeline) = self.state.timeline.lock() {
-            timeline.transport.position_samples = position_samples;
-        }
+        self.state.seek(position_samples);
     }
 
     /// Set loop region (start and end in samples)
@@ -220,36 +858,111 @@ impl AudioEngine {
         self.state.playing.load(Ordering::SeqCst)
     }
 
+    /// Channels of the output device (0 until audio starts)
+    pub fn output_channels(&self) -> u16 {
+        self.state.output_channels.load(Ordering::Relaxed)
+    }
+
     /// Get sample rate
     pub fn sample_rate(&self) -> u32 {
         self.sample_rate
     }
 
+    pub fn metronome_enabled(&self) -> bool {
+        self.state.metronome.lock().is_ok_and(|m| m.enabled)
+    }
+
+    pub fn set_metronome(&self, enabled: bool) {
+        if let Ok(mut metronome) = self.state.metronome.lock() {
+            metronome.enabled = enabled;
+        }
+    }
+
+    /// Click level, linear gain
+    pub fn metronome_volume(&self) -> f32 {
+        self.state.metronome.lock().map_or(0.0, |m| m.volume)
+    }
+
+    pub fn set_metronome_volume(&self, volume: f32) {
+        if let Ok(mut metronome) = self.state.metronome.lock() {
+            metronome.volume = volume;
+        }
+    }
+
+    pub fn cue_settings(&self) -> CueSettings {
+        self.state.cue.lock().map(|cue| cue.clone()).unwrap_or_default()
+    }
+
+    /// Route the cue bus, opening the second output device it names; if that fails the cue
+    /// sources fall back to the main mix
+    pub fn set_cue_settings(&mut self, mut settings: CueSettings) -> Result<(), AudioEngineError> {
+        let device_changed = self.cue_settings().output != settings.output;
+        let result = if device_changed { self.open_cue_device(&settings.output) } else { Ok(()) };
+        if result.is_err() {
+            settings.output = CueOutput::Main;
+        }
+        if let Ok(mut cue) = self.state.cue.lock() {
+            *cue = settings;
+        }
+        result
+    }
+
+    fn open_cue_device(&mut self, output: &CueOutput) -> Result<(), AudioEngineError> {
+        if let Some(stream) = self.cue_stream.take() {
+            stream.stop();
+        }
+        self.state.cue_feed.clear();
+        let CueOutput::Device(name) = output else { return Ok(()) };
+
+        let feed = self.state.cue_feed.clone();
+        let stream = RealtimeOutputStream::start_on(Some(name), self.output.buffer_size, move |buffer, _sample_rate, channels| {
+            feed.pull_into(buffer, channels as usize);
+        })?;
+        if stream.sample_rate() != self.sample_rate {
+            tracing::warn!(
+                device = %name, device_rate = stream.sample_rate(), engine_rate = self.sample_rate,
+                "Cue device runs at a different sample rate, so the cue plays off pitch"
+            );
+        }
+        self.cue_stream = Some(stream);
+        info!(device = %name, "Cue output started");
+        Ok(())
+    }
+
     /// Preview a sample file (plays immediately, stops any current preview)
     pub fn preview_sample(&self, path: &std::path::Path) {
-        let (mono, _sample_rate) = match crate::wav_reader::read_wav_mono(path) {
+        let (mono, sample_rate) = match crate::audio_file::read_audio_mono(path) {
             Ok(result) => result,
             Err(e) => {
-                tracing::warn!("Failed to load WAV for preview: {:?} - {}", path, e);
+                tracing::warn!("Failed to load sample for preview: {:?} - {}", path, e);
                 return;
             }
         };
 
-        if let Ok(mut preview) = self.state.preview_sample.lock() {
-            *preview = Some(mono);
+        if let Ok(mut preview) = self.state.preview.lock() {
+            preview.start(mono, sample_rate);
         }
-        self.state.preview_position.store(0, Ordering::SeqCst);
     }
 
     /// Stop any currently playing preview
     pub fn stop_preview(&self) {
-        self.state.preview_position.store(u64::MAX, Ordering::SeqCst);
+        if let Ok(mut preview) = self.state.preview.lock() {
+            preview.stop();
+        }
+    }
+
+    /// Change the audition volume, transpose and tempo sync (applies to a playing preview too)
+    pub fn set_preview_settings(&self, settings: PreviewSettings) {
+        if let Ok(mut preview) = self.state.preview.lock() {
+            preview.settings = settings;
+        }
     }
 
     /// Render audio into output buffer (called from audio thread)
     fn render_audio(state: &EngineState, buffer: &mut [f32], channels: u16) {
         let is_playing = state.playing.load(Ordering::SeqCst);
         let mut pos = state.position.load(Ordering::SeqCst);
+        state.output_channels.store(channels, Ordering::Relaxed);
         let channels = channels as usize;
         let num_frames = buffer.len() / channels;
 
@@ -258,25 +971,45 @@ impl AudioEngine {
             buffer.fill(0.0);
             return;
         };
+        state.apply_commands(&mut instruments);
 
-        // If playing, also lock timeline and queue MIDI events from clips
-        let timeline_data = if is_playing {
-            let Ok(mut timeline) = state.timeline.lock() else {
-                drop(instruments);
-                buffer.fill(0.0);
-                return;
-            };
+        // Timeline is needed even when stopped to route instruments through track chains
+        let Ok(mut timeline) = state.timeline.lock() else {
+            drop(instruments);
+            buffer.fill(0.0);
+            return;
+        };
+
+        let duration = timeline.duration_samples();
+        let loop_enabled = timeline.transport.loop_enabled;
+        let loop_start = timeline.transport.loop_start;
+        let loop_end = timeline.transport.loop_end;
+        let bpm = timeline.transport.bpm;
+        let sample_rate = timeline.transport.sample_rate;
+        let beats_per_bar = timeline.transport.time_sig_num;
+        let pass = state.loop_pass.load(Ordering::Relaxed);
+
+        state.play_live_events(&mut instruments, num_frames, sample_rate);
+
+        let Ok(mut mix) = state.mix.lock() else {
+            drop((instruments, timeline));
+            buffer.fill(0.0);
+            return;
+        };
+        let mix = &mut *mix;
+        mix.start_block(num_frames, timeline.tracks.len(), channels);
+
+        // Instruments of frozen tracks sit idle; their tracks play the rendered clip
+        mix.frozen_instruments.extend(timeline.tracks.iter().filter(|t| t.is_frozen()).filter_map(|t| t.instrument_id));
 
-            let duration = timeline.duration_samples();
-            let loop_enabled = timeline.transport.loop_enabled;
-            let loop_start = timeline.transport.loop_start;
-            let loop_end = timeline.transport.loop_end;
-            let bpm = timeline.transport.bpm;
-            let sample_rate = timeline.transport.sample_rate;
+        // If playing, queue MIDI events from clips
+        if is_playing {
+            // MIDI effects following the chord track take the chord at the block start
+            let chord = timeline.chord_track.chord_at(pos as f64 * bpm / (60.0 * sample_rate as f64));
 
             // Queue MIDI events for each instrument from clips
             // Handle loop wrap: if buffer spans loop_end, collect from both regions
-            for track in timeline.tracks.iter_mut().filter(|t| t.kind == TrackKind::Midi && !t.mute) {
+            for track in timeline.tracks.iter_mut().filter(|t| t.kind == TrackKind::Midi && !t.mute && !t.is_frozen()) {
                 let Some(inst_id) = track.instrument_id else {
                     tracing::trace!("MIDI track '{}' has no instrument", track.name);
                     continue;
@@ -298,6 +1031,7 @@ impl AudioEngine {
 
                 // Collect raw MIDI events from all clips
                 let mut raw_events: Vec<MidiEvent> = Vec::new();
+                let mut control_events: Vec<ControlEvent> = Vec::new();
 
                 for clip in &track.midi_clips {
                     tracing::trace!("MIDI collect: pos={} buffer_end={} loop={}..{} spans={}", pos, buffer_end, loop_start, loop_end, spans_loop);
@@ -305,18 +1039,27 @@ impl AudioEngine {
                     if spans_loop {
                         // Part 1: from pos to loop_end
                         let frames_before_loop = (loop_end - pos) as usize;
-                        Self::collect_midi_events_raw(clip, pos, frames_before_loop, bpm, sample_rate, &mut raw_events, 0);
+                        Self::collect_midi_events_raw(clip, pos, frames_before_loop, bpm, sample_rate, pass, &mut raw_events, 0);
+                        Self::collect_control_events(clip, pos, frames_before_loop, bpm, sample_rate, &mut control_events, 0);
 
                         // Part 2: from loop_start for remaining frames
                         let frames_after_loop = num_frames - frames_before_loop;
-                        Self::collect_midi_events_raw(clip, loop_start, frames_after_loop, bpm, sample_rate, &mut raw_events, frames_before_loop as u32);
+                        Self::collect_midi_events_raw(clip, loop_start, frames_after_loop, bpm, sample_rate, pass + 1, &mut raw_events, frames_before_loop as u32);
+                        Self::chase_controls(clip, loop_start, bpm, sample_rate, &mut control_events, frames_before_loop as u32);
+                        Self::collect_control_events(clip, loop_start, frames_after_loop, bpm, sample_rate, &mut control_events, frames_before_loop as u32);
                     } else {
-                        Self::collect_midi_events_raw(clip, pos, num_frames, bpm, sample_rate, &mut raw_events, 0);
+                        Self::collect_midi_events_raw(clip, pos, num_frames, bpm, sample_rate, pass, &mut raw_events, 0);
+                        Self::collect_control_events(clip, pos, num_frames, bpm, sample_rate, &mut control_events, 0);
                     }
                 }
 
+                // Controllers bypass the MIDI FX chain, which only works on notes
+                for (control, value, offset) in control_events {
+                    instrument.queue_control(control, value, 0, offset);
+                }
+
                 // Process through MIDI FX chain
-                let processed_events = track.midi_fx_chain.process(raw_events, sample_rate as f32, bpm);
+                let processed_events = track.midi_fx_chain.process(raw_events, num_frames, chord, sample_rate as f32, bpm);
 
                 // Queue processed events to instrument
                 for event in processed_events {
@@ -329,40 +1072,51 @@ impl AudioEngine {
             }
 
             // Drum sequencer - sample-accurate step triggering (no allocations)
-            if let Ok(pattern) = state.drum_pattern.lock() {
+            if let Ok(mut pattern) = state.drum_pattern.lock() {
+                if let Some(latest) = state.drum_pattern_queue.try_iter().last() {
+                    *pattern = latest;
+                }
                 let step_count = pattern.step_count;
                 if step_count > 0 {
                     // loop_bars * 4 beats per bar, divided by step_count
                     let total_beats = 4.0 * pattern.loop_bars.max(1) as f64;
                     let samples_per_step = (sample_rate as f64 * 60.0 / bpm) * total_beats / step_count as f64;
-                    let pattern_length_samples = samples_per_step * step_count as f64;
                     let snap = pattern.snap_to_arrange;
-                    let mut current_step = state.drum_current_step.load(Ordering::Relaxed);
+                    let mut last_tick = state.drum_step_tick.load(Ordering::Relaxed);
                     let inst_id = pattern.instrument_id;
 
                     // Free mode uses independent drum_position; Snap mode uses transport pos
                     let drum_base = state.drum_position.load(Ordering::Relaxed);
 
-                    // Stack array for triggers (max 1 per step, 12 steps max)
+                    // Stack array for triggers (one per firing step: up to 12 a tick, two ticks a block)
                     let mut trigger_count = 0usize;
-                    let mut trigger_buf: [(usize, u16, u32); 12] = [(0, 0, 0); 12];
+                    let mut trigger_buf: [(u16, u32, StepLocks); 24] = [(0, 0, StepLocks::default()); 24];
 
                     for frame_idx in 0..num_frames {
                         // Snap: use transport position; Free: use independent drum position
                         let raw_pos = if snap { pos + frame_idx as u64 } else { drum_base + frame_idx as u64 };
-                        let drum_pos = (raw_pos as f64) % pattern_length_samples;
-                        let step = (drum_pos / samples_per_step) as usize % step_count;
-                        if step == current_step { continue; }
-
-                        current_step = step;
-                        let step_data = &pattern.steps[step];
-                        if !step_data.active || step_data.active_layers == 0 { continue; }
-                        if trigger_count < 12 {
-                            trigger_buf[trigger_count] = (step, step_data.active_layers, frame_idx as u32);
-                            trigger_count += 1;
+                        let tick = (raw_pos as f64 / samples_per_step) as u64;
+                        if tick == last_tick { continue; }
+                        last_tick = tick;
+
+                        // Each row runs its own step counter; group the rows firing together by step
+                        let mut step_rows = [0u16; 12];
+                        for (row, row_step) in state.drum_row_steps.iter().enumerate() {
+                            let step = (tick % pattern.row_length(row) as u64) as usize;
+                            row_step.store(step, Ordering::Relaxed);
+                            let step_data = &pattern.steps[step];
+                            if step_data.active && step_data.active_layers & (1 << row) != 0 {
+                                step_rows[step] |= 1 << row;
+                            }
+                        }
+                        for (step, &rows) in step_rows.iter().enumerate() {
+                            if rows != 0 && trigger_count < trigger_buf.len() {
+                                trigger_buf[trigger_count] = (rows, frame_idx as u32, pattern.steps[step].locks);
+                                trigger_count += 1;
+                            }
                         }
                     }
-                    state.drum_current_step.store(current_step, Ordering::Relaxed);
+                    state.drum_step_tick.store(last_tick, Ordering::Relaxed);
 
                     // Capture row_enabled before dropping pattern lock
                     let row_enabled = pattern.row_enabled;
@@ -374,120 +1128,585 @@ impl AudioEngine {
 
                     // Apply triggers outside pattern lock (row-based model: slot = row)
                     // Mask with row_enabled to respect muted rows
-                    if let Some(id) = inst_id {
+                    if let Some(id) = inst_id.filter(|id| !mix.frozen_instruments.contains(id)) {
                         if let Some(Instrument::SampleKit(kit)) = instruments.get_mut(&id) {
                             for i in 0..trigger_count {
-                                let (_step, active_rows, offset) = trigger_buf[i];
+                                let (active_rows, offset, locks) = trigger_buf[i];
                                 let enabled_rows = active_rows & row_enabled;
                                 if enabled_rows != 0 {
-                                    kit.queue_row_triggers(enabled_rows, 100, offset);
+                                    kit.queue_row_triggers(enabled_rows, 100, offset, locks);
                                 }
                             }
                         }
                     }
                 }
             }
-
-            Some((duration, loop_enabled, loop_start, loop_end, timeline))
-        } else {
-            None
-        };
-
-        // Process instruments and collect their output (always, for keyboard preview)
-        let mut instrument_mix = vec![0.0f32; num_frames];
-        for instrument in instruments.values_mut() {
-            let (left, right) = instrument.process(num_frames);
-            for (i, sample) in instrument_mix.iter_mut().enumerate() {
-                let l = left.get(i).copied().unwrap_or(0.0);
-                let r = right.get(i).copied().unwrap_or(0.0);
-                *sample += (l + r) * 0.5; // Mix stereo to mono
-            }
         }
 
-        drop(instruments);
-
-        // Fill buffer based on playback state
-        if let Some((duration, loop_enabled, loop_start, loop_end, timeline)) = timeline_data {
-            // Playing: mix audio tracks with instruments
-            let mut frame_idx = 0;
-            for frame in buffer.chunks_mut(channels) {
+        // Timeline position for each frame (None past the end of the arrangement)
+        let mut frame_positions: Vec<Option<u64>> = Vec::with_capacity(num_frames);
+        if is_playing {
+            for _ in 0..num_frames {
                 if loop_enabled && loop_end > loop_start && pos >= loop_end {
                     pos = loop_start;
+                    state.loop_pass.fetch_add(1, Ordering::Relaxed);
                 }
 
                 let at_end = !loop_enabled && duration > 0 && pos >= duration;
-                let audio_sample = if at_end { 0.0 } else { timeline.sample_at(pos) };
-                let midi_sample = instrument_mix.get(frame_idx).copied().unwrap_or(0.0);
-                frame.fill(audio_sample + midi_sample);
+                frame_positions.push((!at_end).then_some(pos));
                 pos += 1;
-                frame_idx += 1;
 
                 if at_end {
                     state.playing.store(false, Ordering::SeqCst);
                 }
             }
-
-            drop(timeline);
             state.position.store(pos, Ordering::SeqCst);
-        } else {
-            // Not playing: just output instrument mix (for keyboard preview)
-            for (i, frame) in buffer.chunks_mut(channels).enumerate() {
-                let sample = instrument_mix.get(i).copied().unwrap_or(0.0);
-                frame.fill(sample);
+        }
+
+        // Live input for software monitoring, taken every block so it never falls behind
+        let live_input = state.live_input.pull(num_frames);
+        let live_source = state.live_input.source();
+        // Every device input channel, for tracks monitoring other inputs and hardware insert returns
+        let raw_input = state.live_input.pull_raw(num_frames);
+        let recording = state.recording.load(Ordering::Relaxed);
+
+        // Cue bus (interleaved stereo) when it plays apart from the main mix: its sources skip
+        // the main mix, and soloing pre-listens there instead of silencing other tracks
+        let cue = state.cue.lock().ok()
+            .map(|cue| cue.clone())
+            .filter(|cue| match cue.output {
+                CueOutput::Main => false,
+                CueOutput::Channels(first) => first >= 2 && (first as usize) + 1 < channels,
+                CueOutput::Device(_) => true,
+            });
+        let mut cue_bus = vec![0.0f32; if cue.is_some() { num_frames * 2 } else { 0 }];
+        let solo_to_cue = cue.as_ref().is_some_and(|cue| cue.solo);
+
+        // Render each track (instrument + audio clips) through its effect chain
+        let has_solo = timeline.has_solo() && !solo_to_cue;
+        let mut track_effects = state.track_effects.lock().ok();
+        let mut bypass_fades = state.instrument_bypass.lock().ok();
+
+        // LFOs lock to the transport beat while playing and free-run when stopped
+        let block_beat = frame_positions.first().copied().flatten()
+            .map(|p| p as f64 * bpm / (60.0 * sample_rate as f64));
+
+        // Tracks render after the tracks feeding them (instrument outputs, track inputs, buses),
+        // each track's output held back to the slowest path it meets (effect latency compensation)
+        let pan_law = timeline.pan_law;
+        let mut output_delays = state.output_delays.lock().ok();
+        if mix.routing.update(&timeline, track_effects.as_deref())
+            && let Some(output_delays) = output_delays.as_mut()
+        {
+            let routing = &mix.routing;
+            output_delays.retain(|id, _| routing.index(*id).is_some_and(|idx| routing.delays[idx] > 0));
+        }
+        let MixBuffers { routing, track: block, master, bus_inputs, track_outputs, hardware_outputs, rendered_instruments, frozen_instruments } = mix;
+
+        for &idx in &routing.order {
+            let track = &mut timeline.tracks[idx];
+            let delay = routing.delays[idx];
+            block.clear(num_frames);
+            let StereoBlock { left, right, .. } = block;
+            // Signal other tracks routed into this one
+            let bus_input = &bus_inputs[idx];
+
+            // A frozen track's rendered clip replaces its instrument and effects
+            if let Some(freeze) = &track.freeze {
+                if bus_input.filled {
+                    left.copy_from_slice(&bus_input.left);
+                    right.copy_from_slice(&bus_input.right);
+                }
+                if !track.mute {
+                    for (i, frame_pos) in frame_positions.iter().enumerate() {
+                        let Some(p) = frame_pos else { continue };
+                        if let Some((l, r)) = freeze.frame_at(*p) {
+                            left[i] += l;
+                            right[i] += r;
+                        }
+                    }
+                }
+                track.apply_fader(pan_law, left, right);
+                if delay > 0 && let Some(output_delays) = output_delays.as_mut() {
+                    output_delays.entry(track.id).or_default().process(delay, left, right);
+                }
+                if solo_to_cue && track.solo {
+                    Self::add_to_cue(&mut cue_bus, left, right);
+                }
+                Self::route_track_output(idx, track.output, left, right, channels, routing, master, bus_inputs, track_outputs, hardware_outputs);
+                continue;
+            }
+
+            let modulated = !track.modulation.is_idle();
+            if modulated {
+                track.modulation.advance(num_frames, bpm, sample_rate, block_beat);
+            }
+            // Automation is read at the block start while playing; modulation applies on top
+            let automation_beat = block_beat.filter(|_| !track.automation.is_idle());
+
+            if let Some(inst_id) = track.instrument_id.filter(|id| !rendered_instruments.contains(id)) {
+                if let Some(instrument) = instruments.get_mut(&inst_id) {
+                    if let Some(beat) = automation_beat {
+                        for (target, param, value) in track.automation.values(beat) {
+                            if target == DeviceTarget::Instrument {
+                                instrument.set_param(param, value);
+                            }
+                        }
+                    }
+                    if modulated {
+                        for (target, param, value) in track.modulation.values() {
+                            if target == DeviceTarget::Instrument {
+                                instrument.set_param(param, value);
+                            }
+                        }
+                    }
+                    let (l, r) = instrument.process(num_frames);
+                    left[..l.len()].copy_from_slice(l);
+                    right[..r.len()].copy_from_slice(r);
+                    if let Some(fade) = bypass_fades.as_mut().and_then(|f| f.get_mut(&inst_id)) {
+                        fade.fade(&mut [&mut left[..], &mut right[..]]);
+                    }
+                    rendered_instruments.push(inst_id);
+                }
+            }
+
+            if let Some(output) = track.instrument_output
+                && let Some(inst_id) = routing.index(output.track).and_then(|source| routing.keys[source].instrument_id)
+                && rendered_instruments.contains(&inst_id)
+                && let Some((l, r)) = instruments.get(&inst_id).and_then(|inst| inst.output_bus(output.bus))
+            {
+                for (i, (ls, rs)) in l.iter().zip(r.iter()).enumerate() {
+                    left[i] += ls;
+                    right[i] += rs;
+                }
+            }
+
+            if bus_input.filled {
+                for i in 0..num_frames {
+                    left[i] += bus_input.left[i];
+                    right[i] += bus_input.right[i];
+                }
             }
+
+            if track.kind == TrackKind::Audio && (!has_solo || track.solo) {
+                for (i, frame_pos) in frame_positions.iter().enumerate() {
+                    let Some(p) = frame_pos else { continue };
+                    let (l, r) = track.frame_at(*p);
+                    left[i] += l;
+                    right[i] += r;
+                }
+
+                // The record input plays gated; other tracks take their own channels ungated
+                let own_channels;
+                let input = match track.input {
+                    _ if !track.monitors_input(recording, is_playing) => None,
+                    TrackInput::Track(id) => routing.index(id)
+                        .map(|source| &track_outputs[source])
+                        .filter(|source| source.filled)
+                        .map(|source| (source.left.as_slice(), source.right.as_slice())),
+                    input if input == live_source => live_input.as_ref().map(|(l, r)| (l.as_slice(), r.as_slice())),
+                    input => {
+                        own_channels = raw_input.as_ref().map(|(samples, channels)| select_input(samples, *channels, input));
+                        own_channels.as_ref().map(|(l, r)| (l.as_slice(), r.as_deref().unwrap_or(l)))
+                    }
+                };
+                if let Some((input_left, input_right)) = input {
+                    for i in 0..num_frames {
+                        left[i] += input_left[i];
+                        right[i] += input_right[i];
+                    }
+                }
+            }
+
+            if modulated {
+                // Envelope followers see the track signal before its effects
+                track.modulation.follow(left, right, sample_rate);
+            }
+
+            let chain = track.effect_chain_id
+                .and_then(|id| track_effects.as_mut()?.get_mut(&id));
+            if let Some(chain) = chain {
+                if let Some(beat) = automation_beat {
+                    for (target, param, value) in track.automation.values(beat) {
+                        let DeviceTarget::Effect(index) = target else { continue };
+                        if let Some(effect) = chain.effects_mut().get_mut(index) {
+                            effect.set_param(param, value);
+                        }
+                    }
+                }
+                if modulated {
+                    for (target, param, value) in track.modulation.values() {
+                        let DeviceTarget::Effect(index) = target else { continue };
+                        if let Some(effect) = chain.effects_mut().get_mut(index) {
+                            effect.set_param(param, value);
+                        }
+                    }
+                }
+                chain.set_tempo(bpm);
+                let inserts = Self::return_hardware_inserts(chain, raw_input.as_ref(), num_frames);
+                chain.process_stereo(left, right);
+                Self::send_hardware_inserts(&inserts, channels, hardware_outputs);
+            }
+
+            // Volume and pan after the effects, ahead of latency compensation and the track's output
+            track.apply_fader(pan_law, left, right);
+
+            if delay > 0 && let Some(output_delays) = output_delays.as_mut() {
+                output_delays.entry(track.id).or_default().process(delay, left, right);
+            }
+            if solo_to_cue && track.solo {
+                Self::add_to_cue(&mut cue_bus, left, right);
+            }
+            Self::route_track_output(idx, track.output, left, right, channels, routing, master, bus_inputs, track_outputs, hardware_outputs);
+        }
+
+        drop(output_delays);
+        drop(track_effects);
+        drop(bypass_fades);
+        drop(timeline);
+
+        // Instruments not assigned to a track (e.g. keyboard preview) go straight to master
+        for (id, instrument) in instruments.iter_mut() {
+            if rendered_instruments.contains(id) || frozen_instruments.contains(id) {
+                continue;
+            }
+            let (l, r) = instrument.process(num_frames);
+            master.add(l, r);
         }
 
-        // Mix in preview sample if playing
-        Self::mix_preview(state, buffer, channels);
+        drop(instruments);
+
+        // Master on the first pair; the device's other outputs carry only what is routed there
+        for (i, frame) in buffer.chunks_mut(channels).enumerate() {
+            let (l, r) = (master.left[i], master.right[i]);
+            if channels < 2 {
+                frame.fill((l + r) * 0.5);
+                continue;
+            }
+            frame[0] = l;
+            frame[1] = r;
+            frame[2..].fill(0.0);
+        }
 
-        // Apply master effects
+        // Apply master effects (tempo-synced effects follow the transport BPM)
         if let Ok(mut effects) = state.master_effects.lock() {
-            let mut mono: Vec<f32> = buffer
-                .chunks(channels)
-                .map(|frame| frame.iter().sum::<f32>() / channels as f32)
-                .collect();
+            effects.set_tempo(bpm);
+            let inserts = Self::return_hardware_inserts(&effects, raw_input.as_ref(), num_frames);
+            Self::process_master_effects(&mut effects, buffer, channels);
+            Self::send_hardware_inserts(&inserts, channels, hardware_outputs);
+        }
 
-            effects.process(&mut mono);
+        // Latency calibration: nothing but the pulse, at its timeline position
+        let pulse = state.calibration_pulse.load(Ordering::Relaxed);
+        if pulse != u64::MAX {
+            buffer.fill(0.0);
+            hardware_outputs.iter_mut().for_each(|output| output.filled = false);
+            if let Some(i) = frame_positions.iter().position(|p| *p == Some(pulse)) {
+                buffer[i * channels..(i + 1) * channels].fill(PULSE_LEVEL);
+            }
+        }
+
+        // Meter the final master output; after a rate change metering waits for
+        // `update_loudness_meter` to build a meter for the new rate
+        if let Ok(mut meter) = state.loudness_meter.lock()
+            && meter.sample_rate() == sample_rate as f32
+        {
+            meter.process(buffer, channels, &state.loudness);
+        }
 
+        // Tracks routed to hardware outputs and hardware insert sends: after the master chain,
+        // and not metered
+        for (first, output) in hardware_outputs.iter().enumerate().filter(|(_, output)| output.filled) {
             for (i, frame) in buffer.chunks_mut(channels).enumerate() {
-                let sample = mono.get(i).copied().unwrap_or(0.0);
-                frame.fill(sample);
+                frame[first] += output.left[i];
+                frame[first + 1] += output.right[i];
+            }
+        }
+
+        // Metronome and browser audition: after the master chain, and not metered; on the
+        // cue bus when it plays apart and they are routed to it
+        let mut click = vec![0.0f32; num_frames];
+        if let Ok(mut metronome) = state.metronome.lock() {
+            metronome.render(&frame_positions, bpm, sample_rate, beats_per_bar, &mut click);
+        }
+        if cue.as_ref().is_some_and(|cue| cue.metronome) {
+            Self::add_to_cue(&mut cue_bus, &click, &click);
+        } else {
+            for (frame, c) in buffer.chunks_mut(channels).zip(&click) {
+                frame.iter_mut().for_each(|s| *s += c);
+            }
+        }
+        if let Ok(mut preview) = state.preview.lock() {
+            if cue.as_ref().is_some_and(|cue| cue.preview) {
+                preview.mix(&mut cue_bus, 2, sample_rate, bpm);
+            } else {
+                preview.mix(buffer, channels, sample_rate, bpm);
+            }
+        }
+
+        if let Some(cue) = &cue {
+            cue_bus.iter_mut().for_each(|s| *s *= cue.volume);
+            match cue.output {
+                CueOutput::Channels(first) => {
+                    let first = first as usize;
+                    for (frame, pair) in buffer.chunks_mut(channels).zip(cue_bus.chunks_exact(2)) {
+                        frame[first] += pair[0];
+                        frame[first + 1] += pair[1];
+                    }
+                }
+                CueOutput::Device(_) => state.cue_feed.push(&cue_bus),
+                CueOutput::Main => {}
             }
         }
     }
 
-    /// Mix preview sample into buffer (called from audio thread)
-    fn mix_preview(state: &EngineState, buffer: &mut [f32], channels: usize) {
-        let preview_pos = state.preview_position.load(Ordering::SeqCst);
-        if preview_pos == u64::MAX {
-            return;
+    /// Add a stereo block to the interleaved cue bus
+    fn add_to_cue(cue_bus: &mut [f32], left: &[f32], right: &[f32]) {
+        for (frame, (l, r)) in cue_bus.chunks_exact_mut(2).zip(left.iter().zip(right)) {
+            frame[0] += l;
+            frame[1] += r;
         }
+    }
 
-        let Ok(preview) = state.preview_sample.lock() else { return };
-        let Some(samples) = preview.as_ref() else { return };
+    /// Send the processed block of the track at `idx` where its output points: another
+    /// track's bus input, a hardware output pair the device has, or else master. Tracks
+    /// another track takes as input also keep a copy of their block.
+    #[allow(clippy::too_many_arguments)]
+    fn route_track_output(
+        idx: usize,
+        output: TrackOutput,
+        left: &[f32],
+        right: &[f32],
+        channels: usize,
+        routing: &TrackRouting,
+        master: &mut StereoBlock,
+        bus_inputs: &mut [StereoBlock],
+        track_outputs: &mut [StereoBlock],
+        hardware_outputs: &mut [StereoBlock],
+    ) {
+        if routing.tapped[idx] {
+            track_outputs[idx].add(left, right);
+        }
 
-        let mut pos = preview_pos as usize;
-        for frame in buffer.chunks_mut(channels) {
-            if pos >= samples.len() {
-                state.preview_position.store(u64::MAX, Ordering::SeqCst);
-                return;
+        let bus = match output {
+            TrackOutput::Track(dest) => routing.index(dest).filter(|&dest| dest != idx),
+            _ => None,
+        };
+        match (output, bus) {
+            (TrackOutput::Hardware(first), _) if (first as usize) + 1 < channels => {
+                Self::mix_hardware_output(hardware_outputs, first as usize, channels, left, right);
             }
-            let sample = samples[pos];
-            for ch in frame.iter_mut() {
-                *ch += sample;
+            (_, Some(dest)) => bus_inputs[dest].add(left, right),
+            _ => master.add(left, right),
+        }
+    }
+
+    /// Hand a chain's hardware inserts this block's device input to return; returns the inserts
+    fn return_hardware_inserts(chain: &EffectChain, raw_input: Option<&(Vec<f32>, usize)>, num_frames: usize) -> Vec<Arc<InsertPort>> {
+        let inserts = chain.hardware_inserts();
+        for port in &inserts {
+            port.set_return(raw_input.map(|(samples, ch)| (samples.as_slice(), *ch)), num_frames);
+        }
+        inserts
+    }
+
+    /// Play what a chain's hardware inserts sent on their output pairs
+    fn send_hardware_inserts(inserts: &[Arc<InsertPort>], channels: usize, hardware_outputs: &mut [StereoBlock]) {
+        for port in inserts {
+            if let Some((left, right)) = port.take_send() {
+                Self::mix_hardware_output(hardware_outputs, port.send_channel(), channels, &left, &right);
             }
-            pos += 1;
         }
-        state.preview_position.store(pos as u64, Ordering::SeqCst);
     }
 
-    /// Collect MIDI events from a clip into a Vec (for MIDI FX processing)
+    /// Add a stereo block to the hardware output pair starting at channel `first` (dropped
+    /// if the device doesn't have the pair)
+    fn mix_hardware_output(hardware_outputs: &mut [StereoBlock], first: usize, channels: usize, left: &[f32], right: &[f32]) {
+        if first + 1 < channels && let Some(output) = hardware_outputs.get_mut(first) {
+            output.add(left, right);
+        }
+    }
+
+    /// Render `frames` frames of a track from timeline position `start` to interleaved stereo
+    /// (for freezing and bouncing), playing only the clip `only_clip` when given. MIDI tracks
+    /// play through `devices.instrument`; audio tracks play their clips. Both go through
+    /// `devices.effects`, and through the track's fader when `post_fader`. The devices are
+    /// offline copies, so the live ones carry on playing meanwhile.
+    pub fn render_track(
+        &self,
+        track_idx: usize,
+        start: u64,
+        frames: u64,
+        only_clip: Option<ClipId>,
+        devices: RenderDevices,
+        post_fader: bool,
+    ) -> Option<Vec<f32>> {
+        let state = &self.state;
+        let (mut track, chord_track, bpm, sample_rate, pan_law) = {
+            let timeline = state.timeline.lock().ok()?;
+            let track = timeline.tracks.get(track_idx)?.clone();
+            let transport = &timeline.transport;
+            (track, timeline.chord_track.clone(), transport.bpm, transport.sample_rate, timeline.pan_law)
+        };
+        if let Some(id) = only_clip {
+            track.midi_clips.retain(|c| c.id == id);
+            track.clips.retain(|c| c.id == id);
+        }
+        let RenderDevices { mut instrument, effects: mut chain } = devices;
+        if track.kind != TrackKind::Audio && instrument.is_none() {
+            return None;
+        }
+
+        let end = start + frames;
+        let mut rendered = Vec::with_capacity(frames as usize * 2);
+        if let Some(instrument) = instrument.as_mut() {
+            instrument.all_notes_off(0);
+        }
+
+        let mut pos = start;
+        while pos < end {
+            let frames = RENDER_BLOCK.min((end - pos) as usize);
+            let beat = pos as f64 * bpm / (60.0 * sample_rate as f64);
+            let modulated = !track.modulation.is_idle();
+            if modulated {
+                track.modulation.advance(frames, bpm, sample_rate, Some(beat));
+            }
+
+            let mut left = vec![0.0f32; frames];
+            let mut right = vec![0.0f32; frames];
+            if let Some(instrument) = instrument.as_mut() {
+                let mut raw_events: Vec<MidiEvent> = Vec::new();
+                let mut control_events: Vec<ControlEvent> = Vec::new();
+                for clip in &track.midi_clips {
+                    // Starting mid-clip: controllers at their values so far
+                    if pos == start {
+                        Self::chase_controls(clip, start, bpm, sample_rate, &mut control_events, 0);
+                    }
+                    Self::collect_midi_events_raw(clip, pos, frames, bpm, sample_rate, 0, &mut raw_events, 0);
+                    Self::collect_control_events(clip, pos, frames, bpm, sample_rate, &mut control_events, 0);
+                }
+                for (control, value, offset) in control_events {
+                    instrument.queue_control(control, value, 0, offset);
+                }
+                let chord = chord_track.chord_at(beat);
+                for event in track.midi_fx_chain.process(raw_events, frames, chord, sample_rate as f32, bpm) {
+                    if event.is_note_on {
+                        instrument.queue_note_on(event.pitch, event.velocity, event.channel, event.sample_offset.max(1));
+                    } else {
+                        instrument.queue_note_off(event.pitch, event.velocity, event.channel, event.sample_offset);
+                    }
+                }
+
+                for (target, param, value) in track.automation.values(beat) {
+                    if target == DeviceTarget::Instrument {
+                        instrument.set_param(param, value);
+                    }
+                }
+                if modulated {
+                    for (target, param, value) in track.modulation.values() {
+                        if target == DeviceTarget::Instrument {
+                            instrument.set_param(param, value);
+                        }
+                    }
+                }
+
+                let (l, r) = instrument.process(frames);
+                left.iter_mut().zip(l).for_each(|(out, s)| *out = *s);
+                right.iter_mut().zip(r).for_each(|(out, s)| *out = *s);
+            } else {
+                for (i, p) in (pos..pos + frames as u64).enumerate() {
+                    (left[i], right[i]) = track.frame_at(p);
+                }
+            }
+
+            if modulated {
+                track.modulation.follow(&left, &right, sample_rate);
+            }
+            if let Some(chain) = chain.as_mut() {
+                for (target, param, value) in track.automation.values(beat) {
+                    let DeviceTarget::Effect(index) = target else { continue };
+                    if let Some(effect) = chain.effects_mut().get_mut(index) {
+                        effect.set_param(param, value);
+                    }
+                }
+                if modulated {
+                    for (target, param, value) in track.modulation.values() {
+                        let DeviceTarget::Effect(index) = target else { continue };
+                        if let Some(effect) = chain.effects_mut().get_mut(index) {
+                            effect.set_param(param, value);
+                        }
+                    }
+                }
+                chain.set_tempo(bpm);
+                chain.process_stereo(&mut left, &mut right);
+            }
+            if post_fader {
+                track.apply_fader(pan_law, &mut left, &mut right);
+            }
+
+            rendered.extend(left.iter().zip(&right).flat_map(|(l, r)| [*l, *r]));
+            pos += frames as u64;
+        }
+        Some(rendered)
+    }
+
+    /// Render the song from its start to interleaved stereo through the same mix as playback
+    /// (routing, mute and solo, faders, master chain), looping off, then `tail` frames more
+    /// for releases and effect tails. It drives the engine in place of the audio thread, so
+    /// no output may be running.
+    pub fn render_song(&self, tail: u64) -> Result<Vec<f32>, AudioEngineError> {
+        if self.stream.is_some() {
+            return Err(AudioEngineError::AlreadyRunning);
+        }
+        self.apply_queued_commands();
+        let state = &self.state;
+        let (duration, looping) = self.with_timeline(|timeline| {
+            let looping = std::mem::replace(&mut timeline.transport.loop_enabled, false);
+            (timeline.duration_samples(), looping)
+        }).unwrap_or_default();
+
+        let frames = (duration + tail) as usize;
+        let mut rendered = Vec::with_capacity(frames * 2);
+        let mut block = vec![0.0f32; RENDER_BLOCK * 2];
+        state.stop_playback();
+        state.play();
+        while rendered.len() < frames * 2 {
+            let len = (frames * 2 - rendered.len()).min(block.len());
+            Self::render_audio(state, &mut block[..len], 2);
+            rendered.extend_from_slice(&block[..len]);
+        }
+        state.stop_playback();
+        self.with_timeline(|timeline| timeline.transport.loop_enabled = looping);
+        Ok(rendered)
+    }
+
+    /// Run the master chain over an interleaved buffer, in stereo when the device allows
+    fn process_master_effects(effects: &mut EffectChain, buffer: &mut [f32], channels: usize) {
+        if channels < 2 {
+            effects.process(buffer);
+            return;
+        }
+
+        let mut left: Vec<f32> = buffer.chunks(channels).map(|frame| frame[0]).collect();
+        let mut right: Vec<f32> = buffer.chunks(channels).map(|frame| frame[1]).collect();
+
+        effects.process_stereo(&mut left, &mut right);
+
+        for (i, frame) in buffer.chunks_mut(channels).enumerate() {
+            frame[0] = left[i];
+            frame[1] = right[i];
+        }
+    }
+
+    /// Collect MIDI events from a clip into a Vec (for MIDI FX processing).
+    /// Notes are placed by their microtiming and rolled against their probability for `pass`.
     fn collect_midi_events_raw(
         clip: &MidiClip,
         buffer_start: u64,
         buffer_frames: usize,
         bpm: f64,
         sample_rate: u32,
+        pass: u64,
         events: &mut Vec<MidiEvent>,
         base_offset: u32,
     ) {
@@ -500,8 +1719,11 @@ impl AudioEngine {
 
         let samples_per_tick = (sample_rate as f64 * 60.0) / (bpm * clip.ppq as f64);
 
-        for note in &clip.notes {
-            let note_start_sample = clip.start_sample + (note.start_tick as f64 * samples_per_tick) as u64;
+        for (idx, note) in clip.notes.iter().enumerate() {
+            if !note.plays((clip.id.0 << 40) ^ ((idx as u64) << 20) ^ pass) {
+                continue;
+            }
+            let note_start_sample = clip.start_sample + (note.play_tick() as f64 * samples_per_tick) as u64;
             let note_end_sample = note_start_sample + (note.duration_ticks as f64 * samples_per_tick) as u64;
 
             // Note On in this buffer?
@@ -530,6 +1752,61 @@ impl AudioEngine {
         }
     }
 
+    /// Collect a clip's controller changes that fall in a buffer
+    fn collect_control_events(
+        clip: &MidiClip,
+        buffer_start: u64,
+        buffer_frames: usize,
+        bpm: f64,
+        sample_rate: u32,
+        events: &mut Vec<ControlEvent>,
+        base_offset: u32,
+    ) {
+        let buffer_end = buffer_start + buffer_frames as u64;
+        if clip.controls.is_empty() || buffer_end <= clip.start_sample || buffer_start >= clip.end_sample() {
+            return;
+        }
+
+        let samples_per_tick = (sample_rate as f64 * 60.0) / (bpm * clip.ppq as f64);
+        for event in &clip.controls {
+            let sample = clip.start_sample + (event.tick as f64 * samples_per_tick) as u64;
+            if sample >= buffer_end {
+                break;
+            }
+            if sample >= buffer_start {
+                events.push((event.control, event.value, base_offset + (sample - buffer_start) as u32));
+            }
+        }
+    }
+
+    /// After jumping to `position`, resend each of a clip's controllers at its value
+    /// there, so a bend or pedal left over from before the jump doesn't stick
+    fn chase_controls(
+        clip: &MidiClip,
+        position: u64,
+        bpm: f64,
+        sample_rate: u32,
+        events: &mut Vec<ControlEvent>,
+        offset: u32,
+    ) {
+        if clip.controls.is_empty() || position < clip.start_sample || position >= clip.end_sample() {
+            return;
+        }
+
+        let samples_per_tick = (sample_rate as f64 * 60.0) / (bpm * clip.ppq as f64);
+        // Changes exactly at the position are collected with the buffer
+        let tick = ((position - clip.start_sample) as f64 / samples_per_tick).ceil() as u64;
+        let mut chased: Vec<MidiControl> = Vec::new();
+        for event in &clip.controls {
+            if chased.contains(&event.control) {
+                continue;
+            }
+            chased.push(event.control);
+            let value = clip.control_value_at(event.control, tick.saturating_sub(1));
+            events.push((event.control, value, offset));
+        }
+    }
+
     /// Access timeline for modification (use sparingly, locks mutex)
     pub fn with_timeline<F, R>(&self, f: F) -> Option<R>
     where
@@ -546,6 +1823,37 @@ impl AudioEngine {
         self.state.master_effects.lock().ok().map(|mut e| f(&mut e))
     }
 
+    /// Access a track effect chain by ID (`None` if there is no such chain)
+    pub fn with_track_effects<F, R>(&self, chain_id: u64, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut EffectChain) -> R,
+    {
+        let mut chains = self.state.track_effects.lock().ok()?;
+        chains.get_mut(&chain_id).map(f)
+    }
+
+    /// Create an empty track effect chain under `chain_id`, if there isn't one
+    pub fn ensure_track_effects(&self, chain_id: u64) {
+        if let Ok(mut chains) = self.state.track_effects.lock() {
+            chains.entry(chain_id).or_default();
+        }
+    }
+
+    /// Put a track effect chain under `chain_id`, replacing any chain there
+    pub fn set_track_effects(&self, chain_id: u64, chain: EffectChain) {
+        let old = self.state.track_effects.lock().ok().and_then(|mut chains| chains.insert(chain_id, chain));
+        drop(old);
+    }
+
+    /// Whether any effect chain (master or track) has a hardware insert, which needs the
+    /// device input running for its return
+    pub fn has_hardware_inserts(&self) -> bool {
+        let in_master = self.with_master_effects(|chain| !chain.hardware_inserts().is_empty());
+        let in_tracks = self.state.track_effects.lock().ok()
+            .map(|chains| chains.values().any(|chain| !chain.hardware_inserts().is_empty()));
+        in_master.unwrap_or(false) || in_tracks.unwrap_or(false)
+    }
+
     /// Add an instrument with the given ID
     pub fn add_instrument(&self, id: u64, instrument: Instrument) {
         if let Ok(mut instruments) = self.state.instruments.lock() {
@@ -553,8 +1861,31 @@ impl AudioEngine {
         }
     }
 
+    /// Hand a loaded instrument to the audio thread, which puts it in place between blocks;
+    /// while no output is running it goes in straight away
+    pub fn add_instrument_queued(&self, id: u64, instrument: Instrument) {
+        if self.stream.is_some() {
+            self.state.send_command(EngineCommand::AddInstrument { id, instrument: Box::new(instrument) });
+        } else {
+            self.add_instrument(id, instrument);
+        }
+    }
+
+    /// Drop the instruments replaced on the audio thread since the last call
+    pub fn drop_retired_instruments(&self) {
+        self.state.drop_retired_instruments();
+    }
+
+    /// Whether an instrument with the given ID is in place
+    pub fn has_instrument(&self, id: u64) -> bool {
+        self.with_instruments(|instruments| instruments.contains_key(&id)).unwrap_or(false)
+    }
+
     /// Remove an instrument by ID
     pub fn remove_instrument(&self, id: u64) -> Option<Instrument> {
+        if let Ok(mut bypass_fades) = self.state.instrument_bypass.lock() {
+            bypass_fades.remove(&id);
+        }
         self.state.instruments.lock().ok()?.remove(&id)
     }
 
@@ -0,0 +1,118 @@
+//! Audio file decoding for every import path (browser, prelisten, sampler, kits, clips)
+//!
+//! WAV goes through `wav_reader`; FLAC, MP3, Ogg Vorbis and AIFF are decoded with symphonia.
+
+use std::path::Path;
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as DecodeError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::wav_reader::{read_wav_with_progress, to_mono};
+
+/// File extensions (lower case) that `read_audio` can decode
+pub const AUDIO_EXTENSIONS: &[&str] = &["wav", "flac", "mp3", "ogg", "aif", "aiff"];
+
+/// Whether a path has one of the `AUDIO_EXTENSIONS`
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| AUDIO_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// Read an audio file to interleaved f32 samples. Returns (samples, channels, sample_rate).
+pub fn read_audio(path: &Path) -> Result<(Vec<f32>, u16, u32), String> {
+    read_audio_with_progress(path, &mut |_| {})
+}
+
+/// `read_audio`, calling `progress` with the share of the file read so far (0-1) as it goes.
+/// Compressed files that don't state their length report nothing until they're done
+pub fn read_audio_with_progress(
+    path: &Path,
+    progress: &mut dyn FnMut(f32),
+) -> Result<(Vec<f32>, u16, u32), String> {
+    let is_wav = path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("wav"));
+    if is_wav {
+        return read_wav_with_progress(path, progress);
+    }
+    decode(path, progress)
+}
+
+/// Read an audio file and convert to mono. Returns (mono_samples, sample_rate).
+pub fn read_audio_mono(path: &Path) -> Result<(Vec<f32>, u32), String> {
+    let (samples, channels, sample_rate) = read_audio(path)?;
+    Ok((to_mono(&samples, channels as usize), sample_rate))
+}
+
+/// Decode the first audio track of a compressed or AIFF file
+fn decode(path: &Path, progress: &mut dyn FnMut(f32)) -> Result<(Vec<f32>, u16, u32), String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("open: {e}"))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| format!("probe: {e}"))?;
+    let mut format = probed.format;
+    let track = format.tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or("no audio track")?;
+    let track_id = track.id;
+    let mut sample_rate = track.codec_params.sample_rate.unwrap_or(0);
+    let mut channels = track.codec_params.channels.map_or(0, |c| c.count() as u16);
+    let total_frames = track.codec_params.n_frames.filter(|&frames| frames > 0);
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| format!("codec: {e}"))?;
+
+    let mut samples = Vec::new();
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(DecodeError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(DecodeError::ResetRequired) => break,
+            Err(e) => return Err(format!("read: {e}")),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        if let Some(total) = total_frames {
+            progress(((packet.ts() + packet.dur()) as f32 / total as f32).min(1.0));
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt frame is skipped, as players do
+            Err(DecodeError::DecodeError(e)) => {
+                tracing::debug!(path = %path.display(), "Skipping undecodable packet: {}", e);
+                continue;
+            }
+            Err(e) => return Err(format!("decode: {e}")),
+        };
+
+        let spec = *decoded.spec();
+        sample_rate = spec.rate;
+        channels = spec.channels.count() as u16;
+        let needed = decoded.capacity() * spec.channels.count();
+        if buffer.as_ref().is_none_or(|b| b.capacity() < needed) {
+            buffer = Some(SampleBuffer::new(decoded.capacity() as u64, spec));
+        }
+        if let Some(buffer) = buffer.as_mut() {
+            buffer.copy_interleaved_ref(decoded);
+            samples.extend_from_slice(buffer.samples());
+        }
+    }
+
+    if samples.is_empty() || channels == 0 {
+        return Err("no audio decoded".into());
+    }
+    Ok((samples, channels, sample_rate))
+}
@@ -4,10 +4,12 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{Device, FromSample, SampleFormat, StreamConfig};
+use cpal::{Device, FromSample, SampleFormat, StreamConfig, SupportedStreamConfig};
 use crossbeam_channel::Sender;
 use thiserror::Error;
-use tracing::{error, info};
+use tracing::info;
+
+use crate::notifications;
 
 #[derive(Debug, Error)]
 pub enum AudioInputError {
@@ -27,7 +29,10 @@ pub struct InputDevice {
     pub id: String,
     pub name: String,
     pub sample_rate: u32,
+    /// Channels of the device's default config
     pub channels: u16,
+    /// Channels the input stream opens with, so every interface input can be selected
+    pub max_channels: u16,
     pub is_default: bool,
 }
 
@@ -64,12 +69,14 @@ impl AudioInputService {
             .filter_map(|device| {
                 let name = device.name().ok()?;
                 let config = device.default_input_config().ok()?;
+                let max_channels = Self::stream_config(&device).map_or(config.channels(), |c| c.channels());
 
                 Some(InputDevice {
                     id: name.clone(),
                     name: name.clone(),
                     sample_rate: config.sample_rate().0,
                     channels: config.channels(),
+                    max_channels,
                     is_default: default_name.as_ref() == Some(&name),
                 })
             })
@@ -107,31 +114,49 @@ impl AudioInputService {
         Err(AudioInputError::DeviceNotFound(device_id.to_string()))
     }
 
-    /// Get default input device info
+    /// Get default input device info; channels are those an input stream opens with
     pub fn get_default_device_info() -> Result<(String, u32, u16), AudioInputError> {
         let host = cpal::default_host();
         let device = host
             .default_input_device()
             .ok_or(AudioInputError::NoDevices)?;
 
-        let config = device
-            .default_input_config()
-            .map_err(|e| AudioInputError::ConfigError(e.to_string()))?;
+        let config = Self::stream_config(&device)?;
 
         let name = device.name().unwrap_or_default();
         Ok((name, config.sample_rate().0, config.channels()))
     }
 
-    /// Start streaming audio input to a channel
+    /// Config the input stream opens: the device's default sample rate and format with as
+    /// many channels as it supports, since defaults are often stereo on larger interfaces
+    fn stream_config(device: &Device) -> Result<SupportedStreamConfig, AudioInputError> {
+        let default = device
+            .default_input_config()
+            .map_err(|e| AudioInputError::ConfigError(e.to_string()))?;
+        let rate = default.sample_rate();
+
+        let widest = device
+            .supported_input_configs()
+            .into_iter()
+            .flatten()
+            .filter(|c| c.min_sample_rate() <= rate && rate <= c.max_sample_rate())
+            .filter(|c| matches!(c.sample_format(), SampleFormat::F32 | SampleFormat::I16 | SampleFormat::I32))
+            .max_by_key(|c| (c.channels(), c.sample_format() == default.sample_format()));
+
+        Ok(match widest {
+            Some(config) if config.channels() > default.channels() => config.with_sample_rate(rate),
+            _ => default,
+        })
+    }
+
+    /// Start streaming interleaved audio input (every channel of the device) to a channel
     /// Returns (handle, sample_rate, channels)
     pub fn start_stream(
         device_id: &str,
         chunk_tx: Sender<Vec<f32>>,
     ) -> Result<(InputStreamHandle, u32, u16), AudioInputError> {
         let device = Self::get_device(device_id)?;
-        let config = device
-            .default_input_config()
-            .map_err(|e| AudioInputError::ConfigError(e.to_string()))?;
+        let config = Self::stream_config(&device)?;
 
         let sample_rate = config.sample_rate().0;
         let channels = config.channels();
@@ -180,7 +205,7 @@ impl AudioInputService {
                     let samples: Vec<f32> = data.iter().map(|s| f32::from_sample_(*s)).collect();
                     let _ = tx.try_send(samples);
                 },
-                |err| error!("Input stream error: {}", err),
+                |err| notifications::error("Audio input stream error", err),
                 None,
             )
             .map_err(|e| AudioInputError::StreamError(e.to_string()))
@@ -6,15 +6,20 @@ use std::thread;
 use std::time::Duration;
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::StreamConfig;
+use cpal::{SampleFormat, StreamConfig, SupportedStreamConfig};
 use crossbeam_channel::{bounded, Receiver};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tracing::{error, info};
+use tracing::info;
+
+use crate::notifications;
 
 #[derive(Debug, Error)]
 pub enum AudioOutputError {
     #[error("No audio output devices found")]
     NoDevices,
+    #[error("Output device not found: {0}")]
+    DeviceNotFound(String),
     #[error("Failed to get default output config: {0}")]
     ConfigError(String),
     #[error("Failed to build output stream: {0}")]
@@ -23,6 +28,22 @@ pub enum AudioOutputError {
     PlaybackError(String),
 }
 
+/// Device and buffer size the engine's output stream opens with
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputSettings {
+    /// Output device by name (None = the system default)
+    #[serde(default)]
+    pub device: Option<String>,
+    /// Frames per callback (None = the device's own)
+    #[serde(default)]
+    pub buffer_size: Option<u32>,
+}
+
+impl OutputSettings {
+    /// Buffer sizes offered for the output stream
+    pub const BUFFER_SIZES: [u32; 6] = [64, 128, 256, 512, 1024, 2048];
+}
+
 /// Handle for async playback completion
 pub struct PlaybackHandle {
     done_rx: Receiver<Result<(), AudioOutputError>>,
@@ -105,7 +126,7 @@ impl AudioOutputService {
                     let new_pos = (pos + data.len()).min(total_samples);
                     position_clone.store(new_pos, Ordering::SeqCst);
                 },
-                move |err| error!("Playback stream error: {}", err),
+                move |err| notifications::error("Playback stream error", err),
                 None,
             )
             .map_err(|e| AudioOutputError::StreamError(e.to_string()))?;
@@ -163,42 +184,92 @@ impl AudioOutputService {
         Ok(output.into_iter().flatten().collect())
     }
 
-    /// Get default output device info
+    /// Output config with every channel of the device, at its default sample rate: the
+    /// default config of many multi-out interfaces only opens the first pair
+    fn stream_config(device: &cpal::Device) -> Result<SupportedStreamConfig, AudioOutputError> {
+        let default = device
+            .default_output_config()
+            .map_err(|e| AudioOutputError::ConfigError(e.to_string()))?;
+        let rate = default.sample_rate();
+
+        // Streams are built with f32 samples
+        let widest = device
+            .supported_output_configs()
+            .into_iter()
+            .flatten()
+            .filter(|c| c.min_sample_rate() <= rate && rate <= c.max_sample_rate())
+            .filter(|c| c.sample_format() == SampleFormat::F32)
+            .max_by_key(|c| c.channels());
+
+        Ok(match widest {
+            Some(config) if config.channels() > default.channels() => config.with_sample_rate(rate),
+            _ => default,
+        })
+    }
+
+    /// Names of the available output devices
+    pub fn list_devices() -> Result<Vec<String>, AudioOutputError> {
+        let host = cpal::default_host();
+        let devices: Vec<String> = host
+            .output_devices()
+            .map_err(|e| AudioOutputError::ConfigError(e.to_string()))?
+            .filter_map(|device| device.name().ok())
+            .collect();
+        if devices.is_empty() {
+            return Err(AudioOutputError::NoDevices);
+        }
+        Ok(devices)
+    }
+
+    /// Get default output device info; channels are those a realtime stream opens with
     pub fn get_default_device_info() -> Result<(String, u32, u16), AudioOutputError> {
         let host = cpal::default_host();
         let device = host
             .default_output_device()
             .ok_or(AudioOutputError::NoDevices)?;
 
-        let config = device
-            .default_output_config()
-            .map_err(|e| AudioOutputError::ConfigError(e.to_string()))?;
+        let config = Self::stream_config(&device)?;
 
         let name = device.name().unwrap_or_default();
         Ok((name, config.sample_rate().0, config.channels()))
     }
 }
 
-/// Real-time audio output stream for engine playback
+/// Real-time audio output stream for engine playback, open on every channel of the device
 pub struct RealtimeOutputStream {
     stop_flag: Arc<AtomicBool>,
+    sample_rate: u32,
     _stream: cpal::Stream,
 }
 
 impl RealtimeOutputStream {
-    /// Start a real-time output stream that pulls samples from a callback
+    /// Start a real-time output stream on the default device that pulls samples from a callback
     pub fn start<F>(sample_callback: F) -> Result<Self, AudioOutputError>
+    where
+        F: FnMut(&mut [f32], u32, u16) + Send + 'static,
+    {
+        Self::start_on(None, None, sample_callback)
+    }
+
+    /// Start a real-time output stream on the named device (None = default), asking for
+    /// `buffer_size` frames per callback (None = the device's own)
+    pub fn start_on<F>(device_name: Option<&str>, buffer_size: Option<u32>, sample_callback: F) -> Result<Self, AudioOutputError>
     where
         F: FnMut(&mut [f32], u32, u16) + Send + 'static,
     {
         let host = cpal::default_host();
-        let device = host
-            .default_output_device()
-            .ok_or(AudioOutputError::NoDevices)?;
+        let device = match device_name {
+            Some(name) => host
+                .output_devices()
+                .map_err(|e| AudioOutputError::ConfigError(e.to_string()))?
+                .find(|d| d.name().is_ok_and(|n| n == name))
+                .ok_or_else(|| AudioOutputError::DeviceNotFound(name.to_string()))?,
+            None => host
+                .default_output_device()
+                .ok_or(AudioOutputError::NoDevices)?,
+        };
 
-        let supported_config = device
-            .default_output_config()
-            .map_err(|e| AudioOutputError::ConfigError(e.to_string()))?;
+        let supported_config = AudioOutputService::stream_config(&device)?;
 
         let sample_rate = supported_config.sample_rate().0;
         let channels = supported_config.channels();
@@ -206,7 +277,10 @@ impl RealtimeOutputStream {
         let stop_flag = Arc::new(AtomicBool::new(false));
         let stop_clone = stop_flag.clone();
 
-        let config: StreamConfig = supported_config.into();
+        let mut config: StreamConfig = supported_config.into();
+        if let Some(frames) = buffer_size {
+            config.buffer_size = cpal::BufferSize::Fixed(frames);
+        }
         let callback = Arc::new(Mutex::new(sample_callback));
 
         let stream = device
@@ -223,16 +297,20 @@ impl RealtimeOutputStream {
                     };
                     cb(data, sample_rate, channels);
                 },
-                move |err| error!("Output stream error: {}", err),
+                move |err| notifications::error("Audio output stream error", err),
                 None,
             )
             .map_err(|e| AudioOutputError::StreamError(e.to_string()))?;
 
         stream.play().map_err(|e| AudioOutputError::StreamError(e.to_string()))?;
 
-        info!(sample_rate, channels, "Started realtime output stream");
+        info!(sample_rate, channels, ?buffer_size, device = device_name.unwrap_or("default"), "Started realtime output stream");
+
+        Ok(Self { stop_flag, sample_rate, _stream: stream })
+    }
 
-        Ok(Self { stop_flag, _stream: stream })
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
     }
 
     pub fn stop(&self) {
@@ -0,0 +1,94 @@
+//! Audio files decoded off the GUI thread
+//!
+//! `AudioFileLoader` reads files on a background thread in the order they were asked
+//! for, reporting how far through each it is, so a long file dropped on the arrange view
+//! or loaded into a sampler doesn't freeze the window while it decodes.
+
+use std::path::{Path, PathBuf};
+
+use crossbeam_channel::{Receiver, Sender};
+use hallucinator_core::WaveformPeaks;
+
+use crate::audio_file::read_audio_with_progress;
+use crate::peak_file;
+
+/// Smallest change in progress worth reporting
+const PROGRESS_STEP: f32 = 0.01;
+
+/// A decoded file
+#[derive(Debug)]
+pub struct DecodedAudio {
+    /// Interleaved
+    pub samples: Vec<f32>,
+    pub channels: u16,
+    pub sample_rate: u32,
+    /// Built or read from the peak cache when the request asked for them
+    pub peaks: Option<WaveformPeaks>,
+}
+
+/// What the loader has to say about a request
+#[derive(Debug)]
+pub enum LoadEvent {
+    /// Share of the file decoded so far, 0-1
+    Progress { id: u64, fraction: f32 },
+    Loaded { id: u64, result: Result<DecodedAudio, String> },
+}
+
+struct Job {
+    id: u64,
+    path: PathBuf,
+    peak_cache: Option<PathBuf>,
+}
+
+/// Background thread decoding audio files
+pub struct AudioFileLoader {
+    requests: Sender<Job>,
+    events: Receiver<LoadEvent>,
+    next_id: u64,
+}
+
+impl AudioFileLoader {
+    /// Start the loader. The thread exits when the loader is dropped.
+    pub fn spawn() -> std::io::Result<Self> {
+        let (requests, incoming) = crossbeam_channel::unbounded::<Job>();
+        let (sender, events) = crossbeam_channel::unbounded();
+        std::thread::Builder::new()
+            .name("audio-loader".to_string())
+            .spawn(move || {
+                for job in incoming {
+                    let result = load(&job, &sender);
+                    if sender.send(LoadEvent::Loaded { id: job.id, result }).is_err() {
+                        break;
+                    }
+                }
+            })?;
+        Ok(Self { requests, events, next_id: 0 })
+    }
+
+    /// Queue a file for decoding, with its waveform peaks when given a peak cache folder.
+    /// Returns the id its events come back under
+    pub fn request(&mut self, path: &Path, peak_cache: Option<PathBuf>) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        let _ = self.requests.send(Job { id, path: path.to_path_buf(), peak_cache });
+        id
+    }
+
+    /// Events since the last poll, oldest first
+    pub fn poll(&self) -> Vec<LoadEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+fn load(job: &Job, events: &Sender<LoadEvent>) -> Result<DecodedAudio, String> {
+    let mut reported = 0.0;
+    let (samples, channels, sample_rate) = read_audio_with_progress(&job.path, &mut |fraction| {
+        if fraction - reported >= PROGRESS_STEP {
+            reported = fraction;
+            let _ = events.send(LoadEvent::Progress { id: job.id, fraction });
+        }
+    })?;
+    let peaks = job.peak_cache.as_deref()
+        .map(|cache_dir| peak_file::load_or_build(cache_dir, &job.path, &samples, channels));
+    Ok(DecodedAudio { samples, channels, sample_rate, peaks })
+}
@@ -0,0 +1,94 @@
+//! Cue (headphone) bus: the browser audition, metronome and soloed tracks heard apart from
+//! the main mix, on another output pair of the interface or on a second output device
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+/// Frames the cue device may fall behind the engine by before the oldest are dropped
+const CUE_SLACK: usize = 2048;
+
+/// Where the cue bus plays
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CueOutput {
+    /// No separate cue bus: cue sources play in the main mix
+    #[default]
+    Main,
+    /// An output pair of the main device, by its first channel (0-based)
+    Channels(u16),
+    /// A second output device, by name
+    Device(String),
+}
+
+impl CueOutput {
+    pub fn label(&self) -> String {
+        match self {
+            Self::Main => "Main mix".to_string(),
+            Self::Channels(first) => format!("Out {}/{}", first + 1, first + 2),
+            Self::Device(name) => name.clone(),
+        }
+    }
+}
+
+/// Where the cue bus plays and which sources go to it instead of the main mix
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CueSettings {
+    pub output: CueOutput,
+    /// Browser audition
+    pub preview: bool,
+    pub metronome: bool,
+    /// Soloed tracks are pre-listened on the cue bus and the main mix is left as it is
+    pub solo: bool,
+    /// Cue level, linear gain
+    pub volume: f32,
+}
+
+impl Default for CueSettings {
+    fn default() -> Self {
+        Self { output: CueOutput::Main, preview: true, metronome: true, solo: false, volume: 1.0 }
+    }
+}
+
+/// Cue bus audio handed from the engine to the second output device
+#[derive(Default)]
+pub struct CueFeed {
+    frames: Mutex<VecDeque<(f32, f32)>>,
+}
+
+impl CueFeed {
+    /// Queue a block of interleaved stereo cue audio
+    pub(crate) fn push(&self, stereo: &[f32]) {
+        let Ok(mut frames) = self.frames.lock() else { return };
+        frames.extend(stereo.chunks_exact(2).map(|frame| (frame[0], frame[1])));
+        let excess = frames.len().saturating_sub(4 * CUE_SLACK);
+        frames.drain(..excess);
+    }
+
+    pub(crate) fn clear(&self) {
+        if let Ok(mut frames) = self.frames.lock() {
+            frames.clear();
+        }
+    }
+
+    /// Fill an interleaved device buffer (silence where the engine is late); a mono device
+    /// gets the sum, channels past the first pair stay silent
+    pub(crate) fn pull_into(&self, buffer: &mut [f32], channels: usize) {
+        buffer.fill(0.0);
+        let Ok(mut frames) = self.frames.lock() else { return };
+        let num_frames = buffer.len() / channels.max(1);
+        let excess = frames.len().saturating_sub(num_frames + CUE_SLACK);
+        frames.drain(..excess);
+
+        let available = num_frames.min(frames.len());
+        for (frame, (l, r)) in buffer.chunks_mut(channels.max(1)).zip(frames.drain(..available)) {
+            if channels < 2 {
+                frame[0] = (l + r) * 0.5;
+                continue;
+            }
+            frame[0] = l;
+            frame[1] = r;
+        }
+    }
+}
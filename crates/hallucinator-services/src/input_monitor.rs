@@ -1,16 +1,28 @@
 //! Input monitoring with VU metering and recording
 
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 
 use crossbeam_channel::{bounded, Receiver, Sender};
+use hallucinator_core::TrackInput;
 use thiserror::Error;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::audio_input::{AudioInputError, AudioInputService, InputStreamHandle};
 use crate::audio_io::{AudioOutputError, RealtimeOutputStream};
-use crate::audio_effects::EffectChain;
+use crate::audio_effects::{AudioEffect, EffectChain, GateEffect};
+use crate::wav_reader::{read_wav, to_mono};
+use crate::wav_writer::WavWriter;
+
+/// Frames of input the engine may fall behind by before the oldest are dropped, which
+/// bounds the monitoring latency to about one engine block plus this
+const LIVE_INPUT_SLACK: usize = 512;
+
+/// Frames per peak kept for the live preview of a take recorded to disk
+const DISK_PREVIEW_STEP: usize = 256;
 
 #[derive(Debug, Error)]
 pub enum MonitorError {
@@ -24,6 +36,8 @@ pub enum MonitorError {
     NotRunning,
     #[error("Not recording")]
     NotRecording,
+    #[error("Recording file error: {0}")]
+    File(#[from] std::io::Error),
 }
 
 /// Shared metering state (lock-free reads from UI)
@@ -77,11 +91,116 @@ impl Default for MeterState {
     }
 }
 
+/// Gated live input (the selected record input) handed to the audio engine, which plays
+/// it through monitoring tracks' effect chains, along with every interface channel
+/// ungated for tracks monitoring other inputs and hardware insert returns
+#[derive(Default)]
+pub struct LiveInput {
+    frames: Mutex<VecDeque<(f32, f32)>>,
+    /// Record input the gated frames are taken from
+    source: Mutex<TrackInput>,
+    /// Interleaved device input and its channel count
+    raw: Mutex<(VecDeque<f32>, usize)>,
+}
+
+impl LiveInput {
+    /// Queue input frames from `source`; a mono input (`right` None) goes to both sides
+    fn push(&self, source: TrackInput, left: &[f32], right: Option<&[f32]>) {
+        if let Ok(mut slot) = self.source.lock() {
+            *slot = source;
+        }
+        let Ok(mut frames) = self.frames.lock() else { return };
+        match right {
+            Some(right) => frames.extend(left.iter().copied().zip(right.iter().copied())),
+            None => frames.extend(left.iter().map(|&s| (s, s))),
+        }
+        // Nothing is pulling (engine stopped): keep only recent input
+        let max = 8 * LIVE_INPUT_SLACK;
+        if frames.len() > max {
+            let excess = frames.len() - max;
+            frames.drain(..excess);
+        }
+    }
+
+    /// Queue a block of interleaved device input
+    fn push_raw(&self, samples: &[f32], channels: usize) {
+        let Ok(mut raw) = self.raw.lock() else { return };
+        let (queue, queued_channels) = &mut *raw;
+        if *queued_channels != channels {
+            queue.clear();
+            *queued_channels = channels;
+        }
+        queue.extend(samples);
+        let max = 8 * LIVE_INPUT_SLACK * channels;
+        if queue.len() > max {
+            let excess = queue.len() - max;
+            queue.drain(..excess);
+        }
+    }
+
+    fn clear(&self) {
+        if let Ok(mut frames) = self.frames.lock() {
+            frames.clear();
+        }
+        if let Ok(mut raw) = self.raw.lock() {
+            raw.0.clear();
+        }
+    }
+
+    /// Record input the gated frames are taken from
+    pub fn source(&self) -> TrackInput {
+        self.source.lock().map_or(TrackInput::MonoMix, |source| *source)
+    }
+
+    /// Next `num_frames` frames of input as (left, right), zero-padded if the input is late.
+    /// None while no input is arriving.
+    pub fn pull(&self, num_frames: usize) -> Option<(Vec<f32>, Vec<f32>)> {
+        let mut frames = self.frames.lock().ok()?;
+        if frames.is_empty() {
+            return None;
+        }
+        let excess = frames.len().saturating_sub(num_frames + LIVE_INPUT_SLACK);
+        frames.drain(..excess);
+
+        let mut left = vec![0.0; num_frames];
+        let mut right = vec![0.0; num_frames];
+        let available = num_frames.min(frames.len());
+        for (i, (l, r)) in frames.drain(..available).enumerate() {
+            left[i] = l;
+            right[i] = r;
+        }
+        Some((left, right))
+    }
+
+    /// Next `num_frames` frames of interleaved device input and its channel count,
+    /// zero-padded if the input is late. None while no input is arriving.
+    pub fn pull_raw(&self, num_frames: usize) -> Option<(Vec<f32>, usize)> {
+        let mut raw = self.raw.lock().ok()?;
+        let (queue, channels) = &mut *raw;
+        let channels = *channels;
+        if queue.is_empty() || channels == 0 {
+            return None;
+        }
+        let excess = queue.len().saturating_sub((num_frames + LIVE_INPUT_SLACK) * channels);
+        queue.drain(..excess);
+
+        let mut samples = vec![0.0; num_frames * channels];
+        let available = samples.len().min(queue.len());
+        for (out, s) in samples.iter_mut().zip(queue.drain(..available)) {
+            *out = s;
+        }
+        Some((samples, channels))
+    }
+}
+
 /// Recorded audio data
 pub struct RecordedAudio {
     pub samples: Vec<f32>,
     pub sample_rate: u32,
+    /// 1, or 2 for a take from a stereo input pair (samples interleaved)
     pub channels: u16,
+    /// WAV file the take was streamed to (direct-to-disk recording)
+    pub file: Option<PathBuf>,
 }
 
 /// Input monitor with pass-through, metering, and recording
@@ -91,9 +210,20 @@ pub struct InputMonitor {
     output_stream: Option<RealtimeOutputStream>,
     monitor_enabled: Arc<AtomicBool>,
     recording: Arc<AtomicBool>,
+    /// The take in memory (interleaved), or when recording to disk one peak per `DISK_PREVIEW_STEP` frames
     record_buffer: Arc<Mutex<Vec<f32>>>,
+    /// Open file of a direct-to-disk take
+    disk_writer: Arc<Mutex<Option<WavWriter>>>,
+    /// Frames recorded in the current take
+    recorded_len: Arc<AtomicU64>,
+    /// Input channels the current take records (and the meter and monitor follow)
+    record_input: Arc<Mutex<TrackInput>>,
+    /// Engine feed for monitoring through track effect chains
+    live_input: Option<Arc<LiveInput>>,
     sample_rate: u32,
     channels: u16,
+    /// Input gate applied before recording and the monitor effect chain
+    gate: Arc<Mutex<GateEffect>>,
     effects: Arc<Mutex<EffectChain>>,
 }
 
@@ -106,12 +236,24 @@ impl InputMonitor {
             monitor_enabled: Arc::new(AtomicBool::new(false)),
             recording: Arc::new(AtomicBool::new(false)),
             record_buffer: Arc::new(Mutex::new(Vec::new())),
+            disk_writer: Arc::new(Mutex::new(None)),
+            recorded_len: Arc::new(AtomicU64::new(0)),
+            record_input: Arc::new(Mutex::new(TrackInput::MonoMix)),
+            live_input: None,
             sample_rate: 44100,
             channels: 2,
+            gate: Arc::new(Mutex::new(Self::default_gate())),
             effects: Arc::new(Mutex::new(EffectChain::new())),
         }
     }
 
+    /// Input gate starts bypassed so raw input is recorded until enabled
+    fn default_gate() -> GateEffect {
+        let mut gate = GateEffect::new(-50.0, 44100.0);
+        gate.set_bypass(true);
+        gate
+    }
+
     pub fn meter_state(&self) -> Arc<MeterState> {
         self.meter_state.clone()
     }
@@ -137,10 +279,41 @@ impl InputMonitor {
         self.sample_rate
     }
 
+    /// Channels of the running input stream (all the device has)
     pub fn channels(&self) -> u16 {
         self.channels
     }
 
+    /// Input channels recorded, metered and monitored
+    pub fn record_input(&self) -> TrackInput {
+        self.record_input.lock().map_or(TrackInput::MonoMix, |input| *input)
+    }
+
+    /// Select the input channels to record; one the running device lacks falls back to the mono mix
+    pub fn set_record_input(&self, input: TrackInput) {
+        let input = if input.fits(self.channels) {
+            input
+        } else {
+            warn!(input = %input.label(), channels = self.channels, "Input not on device, recording the mono mix");
+            TrackInput::MonoMix
+        };
+        if let Ok(mut slot) = self.record_input.lock() {
+            *slot = input;
+        }
+    }
+
+    /// Send the gated input to the audio engine (takes effect when the monitor next starts)
+    pub fn set_live_input(&mut self, live_input: Arc<LiveInput>) {
+        self.live_input = Some(live_input);
+    }
+
+    pub fn with_gate<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut GateEffect) -> R,
+    {
+        self.gate.lock().ok().map(|mut g| f(&mut g))
+    }
+
     pub fn with_effects<F, R>(&self, f: F) -> Option<R>
     where
         F: FnOnce(&mut EffectChain) -> R,
@@ -148,19 +321,51 @@ impl InputMonitor {
         self.effects.lock().ok().map(|mut e| f(&mut e))
     }
 
-    /// Start recording (monitor must be running)
-    pub fn start_recording(&self) -> Result<(), MonitorError> {
+    /// Start recording `input` (monitor must be running)
+    pub fn start_recording(&self, input: TrackInput) -> Result<(), MonitorError> {
         if !self.is_running() {
             return Err(MonitorError::NotRunning);
         }
+        self.finish_disk_take();
+        self.set_record_input(input);
+        self.begin_take();
+        Ok(())
+    }
 
+    fn begin_take(&self) {
         // Clear buffer
         if let Ok(mut buf) = self.record_buffer.lock() {
             buf.clear();
         }
+        self.recorded_len.store(0, Ordering::SeqCst);
 
         self.recording.store(true, Ordering::SeqCst);
         info!("Recording started");
+    }
+
+    /// Close the file of a take to disk that was never stopped (monitor stopped mid-take)
+    fn finish_disk_take(&self) {
+        let writer = self.disk_writer.lock().ok().and_then(|mut slot| slot.take());
+        if let Some(Err(e)) = writer.map(WavWriter::finish) {
+            warn!("Failed to finish recording file: {}", e);
+        }
+    }
+
+    /// Start recording straight to a Broadcast WAV file at `path`, so a long take neither
+    /// fills memory nor is lost on a crash. `time_reference` is the take's timeline start in samples.
+    pub fn start_recording_to_file(&self, path: &Path, time_reference: u64, input: TrackInput) -> Result<(), MonitorError> {
+        if !self.is_running() {
+            return Err(MonitorError::NotRunning);
+        }
+        self.finish_disk_take();
+        self.set_record_input(input);
+        let channels = self.record_input().channels();
+        let writer = WavWriter::create(path, self.sample_rate, channels, time_reference, "hallucinator input recording")?;
+        if let Ok(mut slot) = self.disk_writer.lock() {
+            *slot = Some(writer);
+        }
+        info!(path = %path.display(), "Recording to disk");
+        self.begin_take();
         Ok(())
     }
 
@@ -172,34 +377,57 @@ impl InputMonitor {
 
         self.recording.store(false, Ordering::SeqCst);
 
-        let samples = self.record_buffer
+        let mut samples = self.record_buffer
             .lock()
             .map(|mut buf| std::mem::take(&mut *buf))
             .unwrap_or_default();
 
+        // A take on disk is read back in full for its clip
+        let writer = self.disk_writer.lock().ok().and_then(|mut slot| slot.take());
+        let file = match writer.map(WavWriter::finish) {
+            Some(Ok(path)) => match read_wav(&path) {
+                Ok((data, _, _)) => {
+                    samples = data;
+                    Some(path)
+                }
+                Err(e) => {
+                    warn!(path = %path.display(), "Failed to read back recording: {}", e);
+                    samples.clear();
+                    Some(path)
+                }
+            },
+            Some(Err(e)) => {
+                warn!("Failed to finish recording file: {}", e);
+                samples.clear();
+                None
+            }
+            None => None,
+        };
+
         info!(samples = samples.len(), "Recording stopped");
 
         Ok(RecordedAudio {
             samples,
             sample_rate: self.sample_rate,
-            channels: 1, // We record mono
+            channels: self.record_input().channels(),
+            file,
         })
     }
 
-    /// Get current recording buffer for live preview (doesn't stop recording)
+    /// Get current recording buffer for live preview (doesn't stop recording), mixed to mono.
+    /// A take recorded to disk previews as peaks, so compare against `recording_length`.
     pub fn get_recording_preview(&self) -> Option<Vec<f32>> {
         if !self.is_recording() {
             return None;
         }
-        self.record_buffer.lock().ok().map(|buf| buf.to_vec())
+        let to_disk = self.disk_writer.lock().is_ok_and(|slot| slot.is_some());
+        let channels = if to_disk { 1 } else { self.record_input().channels() };
+        self.record_buffer.lock().ok().map(|buf| to_mono(&buf, channels as usize))
     }
 
-    /// Get current recording length in samples
+    /// Get current recording length in frames
     pub fn recording_length(&self) -> usize {
-        self.record_buffer
-            .lock()
-            .map(|buf| buf.len())
-            .unwrap_or(0)
+        self.recorded_len.load(Ordering::Relaxed) as usize
     }
 
     /// Start input monitoring
@@ -216,6 +444,9 @@ impl InputMonitor {
         self.channels = channels;
         self.input_handle = Some(input_handle);
 
+        if let Ok(mut gate) = self.gate.lock() {
+            gate.set_sample_rate(sample_rate as f32);
+        }
         if let Ok(mut fx) = self.effects.lock() {
             fx.set_sample_rate(sample_rate as f32);
         }
@@ -224,6 +455,11 @@ impl InputMonitor {
         let monitor_enabled = self.monitor_enabled.clone();
         let recording = self.recording.clone();
         let record_buffer = self.record_buffer.clone();
+        let disk_writer = self.disk_writer.clone();
+        let recorded_len = self.recorded_len.clone();
+        let record_input = self.record_input.clone();
+        let live_input = self.live_input.clone();
+        let gate = self.gate.clone();
         let effects = self.effects.clone();
         let (out_tx, out_rx) = bounded::<Vec<f32>>(64);
 
@@ -234,7 +470,10 @@ impl InputMonitor {
                 meter_state,
                 monitor_enabled,
                 recording,
-                record_buffer,
+                Recorder { buffer: record_buffer, disk_writer, recorded_len },
+                record_input,
+                live_input,
+                gate,
                 effects,
                 channels as usize,
             );
@@ -246,11 +485,12 @@ impl InputMonitor {
             match out_rx.try_recv() {
                 Ok(samples) => {
                     let mut idx = 0;
+                    // On the first pair, like the engine's master
                     for frame in buffer.chunks_mut(out_channels) {
                         let mono = samples.get(idx).copied().unwrap_or(0.0);
-                        for ch in frame.iter_mut() {
-                            *ch = mono;
-                        }
+                        let (pair, rest) = frame.split_at_mut(frame.len().min(2));
+                        pair.fill(mono);
+                        rest.fill(0.0);
                         idx += 1;
                     }
                 }
@@ -269,6 +509,7 @@ impl InputMonitor {
         if self.is_recording() {
             self.recording.store(false, Ordering::SeqCst);
         }
+        self.finish_disk_take();
 
         let input = self.input_handle.take().ok_or(MonitorError::NotRunning)?;
         input.stop();
@@ -279,6 +520,9 @@ impl InputMonitor {
 
         self.meter_state.set_peak(0.0);
         self.meter_state.set_rms(0.0);
+        if let Some(live) = &self.live_input {
+            live.clear();
+        }
 
         info!("Input monitor stopped");
         Ok(())
@@ -290,7 +534,10 @@ impl InputMonitor {
         meter: Arc<MeterState>,
         monitor_enabled: Arc<AtomicBool>,
         recording: Arc<AtomicBool>,
-        record_buffer: Arc<Mutex<Vec<f32>>>,
+        recorder: Recorder,
+        record_input: Arc<Mutex<TrackInput>>,
+        live_input: Option<Arc<LiveInput>>,
+        gate: Arc<Mutex<GateEffect>>,
         effects: Arc<Mutex<EffectChain>>,
         channels: usize,
     ) {
@@ -298,18 +545,22 @@ impl InputMonitor {
         let peak_decay = 0.95f32;
 
         while let Ok(samples) = rx.recv() {
-            // Convert to mono
-            let mono: Vec<f32> = samples
-                .chunks(channels)
-                .map(|frame| frame.iter().sum::<f32>() / channels as f32)
-                .collect();
+            if let Some(live) = &live_input {
+                live.push_raw(&samples, channels);
+            }
+
+            // Pick out the selected input channel(s)
+            let input = record_input.lock().map_or(TrackInput::MonoMix, |input| *input);
+            let (mut left, mut right) = select_input(&samples, channels, input);
+            let selected = || left.iter().chain(right.iter().flatten());
 
             // Calculate peak
-            let current_peak = mono.iter().map(|s| s.abs()).fold(0.0f32, f32::max);
+            let current_peak = selected().map(|s| s.abs()).fold(0.0f32, f32::max);
             peak_hold = f32::max(current_peak, peak_hold * peak_decay);
 
             // Calculate RMS
-            let rms = (mono.iter().map(|s| s * s).sum::<f32>() / mono.len() as f32).sqrt();
+            let count = left.len() * input.channels() as usize;
+            let rms = (selected().map(|s| s * s).sum::<f32>() / count as f32).sqrt();
 
             // Update meter state
             meter.set_peak(peak_hold);
@@ -319,10 +570,29 @@ impl InputMonitor {
                 meter.set_clipped();
             }
 
+            // Gate after metering so the meter still shows the raw input level
+            // (linked detection on a stereo pair, so the image doesn't shift)
+            if let Ok(mut gate) = gate.lock() {
+                if !gate.is_bypassed() {
+                    match right.as_mut() {
+                        Some(right) => gate.process_stereo(&mut left, right),
+                        None => gate.process(&mut left),
+                    }
+                }
+            }
+
+            if let Some(live) = &live_input {
+                live.push(input, &left, right.as_deref());
+            }
+
             // Record if enabled
             if recording.load(Ordering::SeqCst) {
-                if let Ok(mut buf) = record_buffer.lock() {
-                    buf.extend(&mono);
+                match &right {
+                    Some(right) => {
+                        let interleaved: Vec<f32> = left.iter().zip(right).flat_map(|(l, r)| [*l, *r]).collect();
+                        recorder.record(&interleaved, 2);
+                    }
+                    None => recorder.record(&left, 1),
                 }
             }
 
@@ -331,7 +601,11 @@ impl InputMonitor {
                 continue;
             }
 
-            let mut processed = mono;
+            // Monitored in mono
+            let mut processed = match right {
+                Some(right) => left.iter().zip(&right).map(|(l, r)| (l + r) * 0.5).collect(),
+                None => left,
+            };
 
             if let Ok(mut fx) = effects.lock() {
                 fx.process(&mut processed);
@@ -342,6 +616,57 @@ impl InputMonitor {
     }
 }
 
+/// Where the processing thread puts a take
+struct Recorder {
+    buffer: Arc<Mutex<Vec<f32>>>,
+    disk_writer: Arc<Mutex<Option<WavWriter>>>,
+    recorded_len: Arc<AtomicU64>,
+}
+
+impl Recorder {
+    /// Append interleaved samples of `channels` channels
+    fn record(&self, samples: &[f32], channels: usize) {
+        let Ok(mut buf) = self.buffer.lock() else { return };
+        let frames = samples.len() / channels;
+        let recorded = self.recorded_len.fetch_add(frames as u64, Ordering::Relaxed) as usize;
+
+        let Ok(mut slot) = self.disk_writer.lock() else { return };
+        let Some(writer) = slot.as_mut() else {
+            buf.extend(samples);
+            return;
+        };
+        if let Err(e) = writer.write(samples) {
+            warn!(path = %writer.path().display(), "Recording file write failed: {}", e);
+            return;
+        }
+        // Peak of each preview step across channels, extending the last one while it is still filling
+        for (i, frame) in samples.chunks(channels).enumerate() {
+            let idx = (recorded + i) / DISK_PREVIEW_STEP;
+            if idx >= buf.len() {
+                buf.resize(idx + 1, 0.0);
+            }
+            for s in frame {
+                if s.abs() > buf[idx].abs() {
+                    buf[idx] = *s;
+                }
+            }
+        }
+    }
+}
+
+/// Split interleaved input into the channel(s) `input` selects: one mono buffer, or left and right
+pub(crate) fn select_input(samples: &[f32], channels: usize, input: TrackInput) -> (Vec<f32>, Option<Vec<f32>>) {
+    let channel = |ch: u16| -> Vec<f32> {
+        samples.chunks(channels).map(|frame| frame.get(ch as usize).copied().unwrap_or(0.0)).collect()
+    };
+    match input {
+        // Track inputs never reach the monitor; they record the mono mix
+        TrackInput::MonoMix | TrackInput::Track(_) => (to_mono(samples, channels), None),
+        TrackInput::Mono(ch) => (channel(ch), None),
+        TrackInput::Stereo(left) => (channel(left), Some(channel(left + 1))),
+    }
+}
+
 impl Default for InputMonitor {
     fn default() -> Self {
         Self::new()
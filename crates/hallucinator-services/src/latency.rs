@@ -0,0 +1,24 @@
+//! Round-trip latency calibration: a pulse played on the transport is recorded back through a
+//! loopback cable, and how late it lands in the take is the offset trimmed from later recordings
+
+/// Time from the start of the calibration take to the pulse, in seconds
+pub const PULSE_DELAY_SECS: f64 = 0.25;
+/// Length of the calibration take, in seconds (room for up to 750 ms of round trip)
+pub const CALIBRATION_SECS: f64 = 1.0;
+/// Level of the single-sample calibration pulse
+pub const PULSE_LEVEL: f32 = 0.8;
+/// Quietest recorded peak accepted as the returning pulse
+const MIN_PULSE_PEAK: f32 = 0.02;
+
+/// Frames the pulse played `expected` frames into a mono take arrived late by; None when
+/// nothing came back (no loopback) or the loudest sound precedes the pulse
+pub fn measure_offset(recorded: &[f32], expected: usize) -> Option<u32> {
+    let (index, peak) = recorded.iter()
+        .map(|s| s.abs())
+        .enumerate()
+        .fold((0, 0.0f32), |best, (i, level)| if level > best.1 { (i, level) } else { best });
+    if peak < MIN_PULSE_PEAK {
+        return None;
+    }
+    index.checked_sub(expected).map(|offset| offset as u32)
+}
@@ -2,22 +2,61 @@
 
 pub mod audio_effects;
 pub mod audio_engine;
+pub mod audio_file;
 pub mod audio_input;
 pub mod audio_io;
+pub mod audio_loader;
+pub mod cue;
 pub mod input_monitor;
+pub mod latency;
+pub mod loudness;
+pub mod metronome;
+pub mod midi_clock;
+pub mod midi_input;
+pub mod notifications;
+pub mod osc;
+pub mod peak_file;
+pub mod preview;
+pub mod project;
+pub mod sample_info;
+pub mod scripting;
+pub mod spectrogram;
 pub mod wav_reader;
+pub mod wav_writer;
 
-pub use audio_effects::{AudioEffect, EffectChain, EffectParam, Instrument, SampleKit, Sampler};
-pub use audio_effects::{GainEffect, HighPassEffect, LowPassEffect, CompressorEffect, DelayEffect, ReverbEffect};
+pub use audio_effects::{AudioEffect, EffectChain, EffectParam, Instrument, SampleKit, Sampler, SignalGenerator};
+pub use audio_effects::{GainEffect, HighPassEffect, LowPassEffect, CompressorEffect, DelayEffect, ReverbEffect, GateEffect, UtilityEffect, DELAY_DIVISIONS};
+pub use audio_effects::{AnalyzerEffect, AnalyzerTap, GainReductionMeter, HardwareInsertEffect, InsertPort, LimiterEffect};
+pub use audio_effects::{DryWet, EffectSlotCopy, EffectSource, Oversampler, Oversampling};
+pub use audio_effects::{create_native_effect, NATIVE_EFFECTS};
 pub use audio_effects::{
-    NativeWindowHandle, PluginGuiManager, Vst3Effect, Vst3Error, Vst3GuiError,
-    Vst3Instrument, Vst3PluginInfo, Vst3Scanner,
+    default_vst3_dirs, GuiBackend, InstrumentLoadJob, NativeWindowHandle, OutputBus, ParamGesture, PluginGuiManager, PluginIo,
+    PluginStateReader, ScanEvent, ScanJob, Vst3Effect, Vst3Error, Vst3GuiError, Vst3InstanceHandle, Vst3Instrument,
+    Vst3PluginInfo, Vst3Scanner, VstPreset, VST_PRESET_EXTENSION,
 };
+#[cfg(target_os = "linux")]
+pub use audio_effects::{run_plugin_host, PLUGIN_HOST_ARG};
 pub use audio_effects::{
     Drum808, KICK, RIM_SHOT, SNARE, CLAP, CLOSED_HAT, OPEN_HAT, LOW_TOM,
     MID_TOM, HIGH_TOM, CRASH, COWBELL, HI_CONGA, MID_CONGA, LOW_CONGA, MARACAS, CLAVES,
 };
-pub use audio_engine::{AudioEngine, AudioEngineError, DrumPattern, DrumPatternStep, EngineState};
+pub use audio_file::{is_audio_file, read_audio, read_audio_mono, read_audio_with_progress, AUDIO_EXTENSIONS};
+pub use audio_engine::{
+    AudioEngine, AudioEngineError, DrumPattern, DrumPatternStep, EngineCommand, EngineState, LiveEvent, RenderDevices,
+};
 pub use audio_input::{AudioInputService, AudioInputError, InputDevice};
-pub use audio_io::{AudioOutputService, AudioOutputError};
-pub use input_monitor::{InputMonitor, MeterState, MonitorError, RecordedAudio};
+pub use audio_io::{AudioOutputService, AudioOutputError, OutputSettings};
+pub use audio_loader::{AudioFileLoader, DecodedAudio, LoadEvent};
+pub use cue::{CueOutput, CueSettings};
+pub use input_monitor::{InputMonitor, LiveInput, MeterState, MonitorError, RecordedAudio};
+pub use loudness::{LoudnessMeter, LoudnessState};
+pub use metronome::Metronome;
+pub use midi_clock::{ClockSync, MidiClockSender, CLOCK_PPQN};
+pub use midi_input::{LearnRequest, MidiInputError, MidiPort, MidiInputService, MidiMessage, MidiParser};
+pub use notifications::{Notification, Severity};
+pub use osc::{OscArg, OscError, OscMessage, OscServer};
+pub use preview::{Preview, PreviewSettings};
+pub use project::{Project, ProjectDevices, ProjectError, PROJECT_EXTENSION};
+pub use sample_info::{SampleInfo, SampleInfoLoader, THUMBNAIL_POINTS};
+pub use scripting::{Script, ScriptError, ScriptOutcome};
+pub use spectrogram::{Spectrogram, SpectrogramLoader};
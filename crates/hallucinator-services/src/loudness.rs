@@ -0,0 +1,277 @@
+//! EBU R128 loudness and phase-correlation metering for the master output
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// Loudness reported when there is no signal (or not enough of it)
+pub const SILENCE_LUFS: f32 = -70.0;
+
+/// Number of 100 ms sub-blocks in the 3 s short-term window
+const SHORT_TERM_BLOCKS: usize = 30;
+/// Number of 100 ms sub-blocks in the 400 ms momentary window
+const MOMENTARY_BLOCKS: usize = 4;
+/// Integrated gating histogram: 0.1 LU bins from -70 to +5 LUFS
+const HISTOGRAM_MIN: f64 = -70.0;
+const HISTOGRAM_BINS: usize = 750;
+
+/// Meter readings shared between the audio thread and the GUI
+pub struct LoudnessState {
+    momentary_raw: AtomicU32,
+    short_term_raw: AtomicU32,
+    integrated_raw: AtomicU32,
+    correlation_raw: AtomicU32,
+    reset_requested: AtomicBool,
+}
+
+impl LoudnessState {
+    fn new() -> Self {
+        Self {
+            momentary_raw: AtomicU32::new(SILENCE_LUFS.to_bits()),
+            short_term_raw: AtomicU32::new(SILENCE_LUFS.to_bits()),
+            integrated_raw: AtomicU32::new(SILENCE_LUFS.to_bits()),
+            correlation_raw: AtomicU32::new(0.0f32.to_bits()),
+            reset_requested: AtomicBool::new(false),
+        }
+    }
+
+    /// Momentary loudness (400 ms window) in LUFS
+    pub fn momentary(&self) -> f32 {
+        f32::from_bits(self.momentary_raw.load(Ordering::Relaxed))
+    }
+
+    /// Short-term loudness (3 s window) in LUFS
+    pub fn short_term(&self) -> f32 {
+        f32::from_bits(self.short_term_raw.load(Ordering::Relaxed))
+    }
+
+    /// Gated integrated loudness since the last reset in LUFS
+    pub fn integrated(&self) -> f32 {
+        f32::from_bits(self.integrated_raw.load(Ordering::Relaxed))
+    }
+
+    /// Phase correlation from -1 (out of phase) to +1 (mono)
+    pub fn correlation(&self) -> f32 {
+        f32::from_bits(self.correlation_raw.load(Ordering::Relaxed))
+    }
+
+    /// Restart integrated measurement (applied on the next audio block)
+    pub fn reset(&self) {
+        self.reset_requested.store(true, Ordering::Relaxed);
+    }
+
+    fn store(raw: &AtomicU32, value: f32) {
+        raw.store(value.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl Default for LoudnessState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Direct form I biquad
+#[derive(Clone, Copy, Default)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    x1: f64,
+    x2: f64,
+    y1: f64,
+    y2: f64,
+}
+
+impl Biquad {
+    fn tick(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2 - self.a1 * self.y1 - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+/// BS.1770 K-weighting (high shelf + RLB high-pass) for one channel
+#[derive(Clone, Copy, Default)]
+struct KWeighting {
+    shelf: Biquad,
+    highpass: Biquad,
+}
+
+impl KWeighting {
+    fn new(sample_rate: f64) -> Self {
+        use std::f64::consts::PI;
+
+        let f0 = 1681.974450955533;
+        let gain_db = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (PI * f0 / sample_rate).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad {
+            b0: (vh + vb * k / q + k * k) / a0,
+            b1: 2.0 * (k * k - vh) / a0,
+            b2: (vh - vb * k / q + k * k) / a0,
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+            ..Biquad::default()
+        };
+
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (PI * f0 / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let highpass = Biquad {
+            b0: 1.0,
+            b1: -2.0,
+            b2: 1.0,
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+            ..Biquad::default()
+        };
+
+        Self { shelf, highpass }
+    }
+
+    fn tick(&mut self, x: f32) -> f64 {
+        self.highpass.tick(self.shelf.tick(x as f64))
+    }
+}
+
+fn energy_to_lufs(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.max(1e-12).log10()
+}
+
+/// Audio-thread side of the loudness meter (no allocation after construction)
+pub struct LoudnessMeter {
+    sample_rate: f32,
+    weighting: [KWeighting; 2],
+    block_len: usize,
+    block_count: usize,
+    block_energy: f64,
+    /// Mean-square energy of the most recent 100 ms sub-blocks
+    blocks: [f64; SHORT_TERM_BLOCKS],
+    blocks_pos: usize,
+    blocks_filled: usize,
+    /// Per-bin (count, energy sum) of gating blocks for the integrated measurement
+    histogram: Box<[(u64, f64); HISTOGRAM_BINS]>,
+    sum_lr: f64,
+    sum_ll: f64,
+    sum_rr: f64,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            weighting: [KWeighting::new(sample_rate as f64); 2],
+            block_len: (sample_rate as usize / 10).max(1),
+            block_count: 0,
+            block_energy: 0.0,
+            blocks: [0.0; SHORT_TERM_BLOCKS],
+            blocks_pos: 0,
+            blocks_filled: 0,
+            histogram: Box::new([(0, 0.0); HISTOGRAM_BINS]),
+            sum_lr: 0.0,
+            sum_ll: 0.0,
+            sum_rr: 0.0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Measure interleaved output: one channel as mono, otherwise the first two as left and
+    /// right (the rest are ignored)
+    pub fn process(&mut self, buffer: &[f32], channels: usize, state: &LoudnessState) {
+        if state.reset_requested.swap(false, Ordering::Relaxed) {
+            self.histogram.fill((0, 0.0));
+            LoudnessState::store(&state.integrated_raw, SILENCE_LUFS);
+        }
+
+        // ~300 ms smoothing for the correlation meter
+        let decay = (-1.0 / (0.3 * self.sample_rate as f64)).exp();
+
+        for frame in buffer.chunks(channels.max(1)) {
+            let left = frame[0];
+            let right = frame.get(1).copied().unwrap_or(left);
+
+            self.sum_lr = self.sum_lr * decay + (left * right) as f64;
+            self.sum_ll = self.sum_ll * decay + (left * left) as f64;
+            self.sum_rr = self.sum_rr * decay + (right * right) as f64;
+
+            // BS.1770 sums the energy of each channel, so mono counts once rather than
+            // as two identical channels (3 dB hot)
+            let wl = self.weighting[0].tick(left);
+            self.block_energy += wl * wl;
+            if channels > 1 {
+                let wr = self.weighting[1].tick(right);
+                self.block_energy += wr * wr;
+            }
+            self.block_count += 1;
+
+            if self.block_count >= self.block_len {
+                self.finish_block(state);
+            }
+        }
+
+        let denom = (self.sum_ll * self.sum_rr).sqrt();
+        let correlation = if denom > 1e-9 { (self.sum_lr / denom) as f32 } else { 0.0 };
+        LoudnessState::store(&state.correlation_raw, correlation);
+    }
+
+    fn finish_block(&mut self, state: &LoudnessState) {
+        self.blocks[self.blocks_pos] = self.block_energy / self.block_count as f64;
+        self.blocks_pos = (self.blocks_pos + 1) % SHORT_TERM_BLOCKS;
+        self.blocks_filled = (self.blocks_filled + 1).min(SHORT_TERM_BLOCKS);
+        self.block_energy = 0.0;
+        self.block_count = 0;
+
+        let momentary = self.window_energy(MOMENTARY_BLOCKS);
+        let short_term = self.window_energy(SHORT_TERM_BLOCKS);
+        let momentary_lufs = energy_to_lufs(momentary);
+        LoudnessState::store(&state.momentary_raw, momentary_lufs.max(SILENCE_LUFS as f64) as f32);
+        LoudnessState::store(&state.short_term_raw, energy_to_lufs(short_term).max(SILENCE_LUFS as f64) as f32);
+
+        // Every 100 ms a new 400 ms gating block is complete (75% overlap)
+        if self.blocks_filled >= MOMENTARY_BLOCKS && momentary_lufs > HISTOGRAM_MIN {
+            let bin = (((momentary_lufs - HISTOGRAM_MIN) * 10.0) as usize).min(HISTOGRAM_BINS - 1);
+            self.histogram[bin].0 += 1;
+            self.histogram[bin].1 += momentary;
+            LoudnessState::store(&state.integrated_raw, self.integrated() as f32);
+        }
+    }
+
+    /// Mean energy over the most recent `count` sub-blocks
+    fn window_energy(&self, count: usize) -> f64 {
+        let count = count.min(self.blocks_filled).max(1);
+        let sum: f64 = (1..=count)
+            .map(|back| self.blocks[(self.blocks_pos + SHORT_TERM_BLOCKS - back) % SHORT_TERM_BLOCKS])
+            .sum();
+        sum / count as f64
+    }
+
+    /// Two-stage gated loudness: absolute gate at -70 LUFS, relative gate 10 LU below
+    fn integrated(&self) -> f64 {
+        let gated_mean = |from_bin: usize| {
+            let (count, energy) = self.histogram[from_bin..]
+                .iter()
+                .fold((0u64, 0.0f64), |(c, e), &(bc, be)| (c + bc, e + be));
+            (count > 0).then(|| energy / count as f64)
+        };
+
+        let Some(absolute) = gated_mean(0) else {
+            return SILENCE_LUFS as f64;
+        };
+        let relative_gate = energy_to_lufs(absolute) - 10.0;
+        let from_bin = ((relative_gate - HISTOGRAM_MIN) * 10.0).max(0.0) as usize;
+        gated_mean(from_bin.min(HISTOGRAM_BINS - 1))
+            .map(energy_to_lufs)
+            .unwrap_or(SILENCE_LUFS as f64)
+    }
+}
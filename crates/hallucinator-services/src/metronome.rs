@@ -0,0 +1,50 @@
+//! Metronome: a click on every beat while the transport plays, higher on the downbeat
+
+use std::f32::consts::TAU;
+
+/// Click length in seconds
+const CLICK_SECONDS: f32 = 0.04;
+/// Click pitches of the downbeat and the other beats
+const ACCENT_HZ: f32 = 1760.0;
+const BEAT_HZ: f32 = 1320.0;
+
+#[derive(Debug, Clone)]
+pub struct Metronome {
+    pub enabled: bool,
+    /// Click level, linear gain
+    pub volume: f32,
+    /// Samples into the sounding click and its pitch (None = silent)
+    click: Option<(usize, f32)>,
+}
+
+impl Default for Metronome {
+    fn default() -> Self {
+        Self { enabled: false, volume: 0.5, click: None }
+    }
+}
+
+impl Metronome {
+    /// Add the clicks of a block to mono `out`, given each frame's timeline position (None
+    /// while stopped or past the end). A click starts on the first frame of each beat.
+    pub fn render(&mut self, positions: &[Option<u64>], bpm: f64, sample_rate: u32, beats_per_bar: u8, out: &mut [f32]) {
+        let samples_per_beat = 60.0 * sample_rate as f64 / bpm.max(1.0);
+        let length = (CLICK_SECONDS * sample_rate as f32) as usize;
+
+        for (sample, position) in out.iter_mut().zip(positions) {
+            if self.enabled && let Some(p) = *position {
+                let beat = (p as f64 / samples_per_beat).floor();
+                if p == 0 || ((p - 1) as f64 / samples_per_beat).floor() < beat {
+                    let accent = (beat as u64).is_multiple_of(beats_per_bar.max(1) as u64);
+                    self.click = Some((0, if accent { ACCENT_HZ } else { BEAT_HZ }));
+                }
+            }
+            let Some((n, hz)) = self.click.as_mut() else { continue };
+            let envelope = (1.0 - *n as f32 / length as f32).powi(3);
+            *sample += (TAU * *hz * *n as f32 / sample_rate as f32).sin() * envelope * self.volume;
+            *n += 1;
+            if *n >= length {
+                self.click = None;
+            }
+        }
+    }
+}
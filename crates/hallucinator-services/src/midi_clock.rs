@@ -0,0 +1,211 @@
+//! MIDI clock sync
+//!
+//! Master: a sender thread emits 24 clock ticks per quarter note at the
+//! transport tempo, with Start/Continue/Stop following the transport.
+//! Slave: the MIDI input service feeds incoming clock to a `ClockFollower`,
+//! which drives the transport and sets the tempo from the tick rate.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use crate::audio_engine::EngineState;
+use crate::midi_input::{backend, MidiInputError, MidiMessage, MidiPort};
+
+/// MIDI clock resolution
+pub const CLOCK_PPQN: u32 = 24;
+
+/// Tempo range accepted from (and sent as) clock, matching the transport BPM field
+const MIN_BPM: f64 = 20.0;
+const MAX_BPM: f64 = 300.0;
+
+/// Ignore tempo changes smaller than this to avoid jitter-driven updates
+const BPM_DEADBAND: f64 = 0.05;
+
+const CLOCK: u8 = 0xF8;
+const START: u8 = 0xFA;
+const CONTINUE: u8 = 0xFB;
+const STOP: u8 = 0xFC;
+const SONG_POSITION: u8 = 0xF2;
+
+/// Where the transport tempo comes from
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ClockSync {
+    /// Internal tempo, no clock output
+    #[default]
+    Internal,
+    /// Internal tempo, clock sent to an output port
+    Send(MidiPort),
+    /// Follow clock arriving on any input port
+    Receive,
+}
+
+/// Slave-mode state: averages the last quarter note of tick intervals
+#[derive(Debug, Default)]
+pub(crate) struct ClockFollower {
+    last_tick: Option<Instant>,
+    intervals: VecDeque<f64>,
+}
+
+impl ClockFollower {
+    pub(crate) fn handle(&mut self, message: MidiMessage, engine: &EngineState) {
+        match message {
+            MidiMessage::Clock => {
+                if let Some(bpm) = self.tick(Instant::now())
+                    && let Ok(mut timeline) = engine.timeline.lock()
+                    && (timeline.transport.bpm - bpm).abs() > BPM_DEADBAND
+                {
+                    timeline.transport.bpm = bpm;
+                }
+            }
+            MidiMessage::Start => {
+                engine.seek(0);
+                engine.play();
+            }
+            MidiMessage::Continue => engine.play(),
+            MidiMessage::Stop => {
+                engine.pause();
+                self.last_tick = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Record a tick; returns the tempo once a full quarter note has been measured
+    fn tick(&mut self, now: Instant) -> Option<f64> {
+        let last = self.last_tick.replace(now)?;
+        let interval = now.duration_since(last).as_secs_f64();
+        // A long gap means the clock paused: start measuring again
+        if interval <= 0.0 || interval > 60.0 / (MIN_BPM * CLOCK_PPQN as f64) {
+            self.intervals.clear();
+            return None;
+        }
+        self.intervals.push_back(interval);
+        if self.intervals.len() > CLOCK_PPQN as usize {
+            self.intervals.pop_front();
+        }
+        if self.intervals.len() < CLOCK_PPQN as usize {
+            return None;
+        }
+        let average = self.intervals.iter().sum::<f64>() / self.intervals.len() as f64;
+        let bpm = 60.0 / (average * CLOCK_PPQN as f64);
+        Some(((bpm * 10.0).round() / 10.0).clamp(MIN_BPM, MAX_BPM))
+    }
+}
+
+/// Master-mode clock output on one port
+pub struct MidiClockSender {
+    port: MidiPort,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MidiClockSender {
+    /// List available MIDI output ports
+    pub fn list_ports() -> Result<Vec<MidiPort>, MidiInputError> {
+        backend::list_ports(true)
+    }
+
+    /// Open `port` and start sending clock at the transport tempo
+    pub fn start(engine: Arc<EngineState>, port: MidiPort) -> Result<Self, MidiInputError> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let port_id = port.id.clone();
+        let (ready_tx, ready_rx) = crossbeam_channel::bounded(1);
+
+        // The port is opened on the sender thread (ALSA handles are not Send)
+        let thread = std::thread::Builder::new()
+            .name("midi-clock".to_string())
+            .spawn(move || {
+                let output = match backend::open_output(&port_id) {
+                    Ok(output) => {
+                        let _ = ready_tx.send(Ok(()));
+                        output
+                    }
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+                if let Err(e) = send_clock(output, &engine, &thread_stop) {
+                    warn!("MIDI clock output on {} stopped: {}", port_id, e);
+                }
+            })
+            .map_err(|e| MidiInputError::Device(e.to_string()))?;
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Err(MidiInputError::Device("clock thread exited".to_string())),
+        }
+
+        info!("Sending MIDI clock to {}", port.name);
+        Ok(Self { port, stop, thread: Some(thread) })
+    }
+
+    pub fn port(&self) -> &MidiPort {
+        &self.port
+    }
+}
+
+impl Drop for MidiClockSender {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Clock loop: ticks at the current tempo, transport messages on play/stop changes
+fn send_clock(mut output: impl Write, engine: &EngineState, stop: &AtomicBool) -> std::io::Result<()> {
+    let mut was_playing = false;
+    let mut next_tick = Instant::now();
+
+    while !stop.load(Ordering::Relaxed) {
+        let playing = engine.playing.load(Ordering::SeqCst);
+        let (bpm, samples_per_beat) = engine
+            .timeline
+            .lock()
+            .map(|t| (t.transport.bpm, t.transport.samples_per_beat()))
+            .unwrap_or((120.0, 22050.0));
+
+        if playing != was_playing {
+            if playing {
+                let position = engine.position.load(Ordering::SeqCst);
+                if position == 0 {
+                    output.write_all(&[START])?;
+                } else {
+                    // Song position is counted in sixteenth notes
+                    let sixteenths = ((position as f64 / samples_per_beat) * 4.0) as u16 & 0x3FFF;
+                    output.write_all(&[SONG_POSITION, (sixteenths & 0x7F) as u8, (sixteenths >> 7) as u8, CONTINUE])?;
+                }
+            } else {
+                output.write_all(&[STOP])?;
+            }
+            was_playing = playing;
+        }
+
+        output.write_all(&[CLOCK])?;
+
+        let interval = Duration::from_secs_f64(60.0 / (bpm.clamp(MIN_BPM, MAX_BPM) * CLOCK_PPQN as f64));
+        next_tick += interval;
+        let now = Instant::now();
+        if next_tick > now {
+            std::thread::sleep(next_tick - now);
+        } else {
+            // Fell behind (e.g. tempo change or a stall): resync rather than burst
+            next_tick = now;
+        }
+    }
+
+    if was_playing {
+        output.write_all(&[STOP])?;
+    }
+    Ok(())
+}
@@ -0,0 +1,432 @@
+//! MIDI controller input with MIDI learn
+//!
+//! Every hardware MIDI input port is read on its own thread (ALSA raw MIDI on
+//! Linux). Control changes are matched against the project's `MidiMap` and
+//! applied straight to the engine's device parameters; everything else is
+//! queued for the GUI (control surfaces).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::JoinHandle;
+
+use crossbeam_channel::{Receiver, Sender};
+use hallucinator_core::{MidiMapping, ParamOwner, SurfaceControl};
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::audio_engine::EngineState;
+use crate::midi_clock::ClockFollower;
+use crate::notifications;
+
+#[derive(Debug, Error)]
+pub enum MidiInputError {
+    #[error("MIDI input is not supported on this platform")]
+    Unsupported,
+    #[error("No MIDI input ports found")]
+    NoPorts,
+    #[error("MIDI device error: {0}")]
+    Device(String),
+}
+
+/// A hardware MIDI port
+#[derive(Debug, Clone, PartialEq)]
+pub struct MidiPort {
+    /// Backend address, e.g. `hw:1,0,0`
+    pub id: String,
+    pub name: String,
+}
+
+/// A parsed channel voice or real-time message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidiMessage {
+    NoteOn { channel: u8, note: u8, velocity: u8 },
+    NoteOff { channel: u8, note: u8, velocity: u8 },
+    ControlChange { channel: u8, controller: u8, value: u8 },
+    /// 14-bit value, 8192 = center
+    PitchBend { channel: u8, value: u16 },
+    ChannelPressure { channel: u8, value: u8 },
+    /// Timing clock, 24 per quarter note
+    Clock,
+    Start,
+    Continue,
+    Stop,
+}
+
+impl MidiMessage {
+    /// The control a message comes from and its raw value (note velocity,
+    /// CC value or 14-bit pitch bend; note-off is 0)
+    pub fn surface_input(&self) -> Option<(SurfaceControl, u16)> {
+        match *self {
+            MidiMessage::NoteOn { channel, note, velocity } => {
+                Some((SurfaceControl::Note { channel, note }, velocity as u16))
+            }
+            MidiMessage::NoteOff { channel, note, .. } => Some((SurfaceControl::Note { channel, note }, 0)),
+            MidiMessage::ControlChange { channel, controller, value } => {
+                Some((SurfaceControl::Cc { channel, controller }, value as u16))
+            }
+            MidiMessage::PitchBend { channel, value } => Some((SurfaceControl::PitchBend { channel }, value)),
+            _ => None,
+        }
+    }
+}
+
+/// Byte-stream MIDI parser with running status (SysEx and system common messages are skipped)
+#[derive(Debug, Default)]
+pub struct MidiParser {
+    status: u8,
+    data: [u8; 2],
+    len: usize,
+}
+
+impl MidiParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, byte: u8) -> Option<MidiMessage> {
+        // Real-time bytes may appear anywhere and don't affect running status
+        if byte >= 0xF8 {
+            return match byte {
+                0xF8 => Some(MidiMessage::Clock),
+                0xFA => Some(MidiMessage::Start),
+                0xFB => Some(MidiMessage::Continue),
+                0xFC => Some(MidiMessage::Stop),
+                _ => None,
+            };
+        }
+        if byte & 0x80 != 0 {
+            // System common / SysEx cancel running status; their data is dropped
+            self.status = if byte < 0xF0 { byte } else { 0 };
+            self.len = 0;
+            return None;
+        }
+        if self.status == 0 {
+            return None;
+        }
+
+        self.data[self.len] = byte;
+        self.len += 1;
+        let needed = match self.status & 0xF0 {
+            0xC0 | 0xD0 => 1,
+            _ => 2,
+        };
+        if self.len < needed {
+            return None;
+        }
+        self.len = 0;
+
+        let channel = self.status & 0x0F;
+        let [d0, d1] = self.data;
+        match self.status & 0xF0 {
+            0x90 if d1 > 0 => Some(MidiMessage::NoteOn { channel, note: d0, velocity: d1 }),
+            0x80 | 0x90 => Some(MidiMessage::NoteOff { channel, note: d0, velocity: d1 }),
+            0xB0 => Some(MidiMessage::ControlChange { channel, controller: d0, value: d1 }),
+            0xD0 => Some(MidiMessage::ChannelPressure { channel, value: d0 }),
+            0xE0 => Some(MidiMessage::PitchBend { channel, value: (d1 as u16) << 7 | d0 as u16 }),
+            _ => None,
+        }
+    }
+}
+
+/// Parameter waiting for the next incoming CC (MIDI learn)
+#[derive(Debug, Clone, PartialEq)]
+pub struct LearnRequest {
+    pub owner: ParamOwner,
+    pub param: String,
+    pub min: f32,
+    pub max: f32,
+}
+
+/// Messages buffered for the GUI before the oldest are dropped
+const EVENT_QUEUE_SIZE: usize = 1024;
+
+/// Shared between the port threads and the GUI
+struct MidiShared {
+    engine: Arc<EngineState>,
+    learn: Mutex<Option<LearnRequest>>,
+    events: Sender<MidiMessage>,
+    /// Chase incoming MIDI clock (slave mode)
+    follow_clock: AtomicBool,
+    clock: Mutex<ClockFollower>,
+    stop: AtomicBool,
+    /// Called after each message that may change what the GUI shows
+    waker: OnceLock<Box<dyn Fn() + Send + Sync>>,
+}
+
+impl MidiShared {
+    fn handle(&self, message: MidiMessage) {
+        self.apply(message);
+        // Clock ticks come 24 a beat and show nothing by themselves
+        if message != MidiMessage::Clock
+            && let Some(wake) = self.waker.get()
+        {
+            wake();
+        }
+    }
+
+    fn apply(&self, message: MidiMessage) {
+        if matches!(message, MidiMessage::Clock | MidiMessage::Start | MidiMessage::Continue | MidiMessage::Stop) {
+            if self.follow_clock.load(Ordering::Relaxed)
+                && let Ok(mut clock) = self.clock.lock()
+            {
+                clock.handle(message, &self.engine);
+            }
+            return;
+        }
+
+        let MidiMessage::ControlChange { channel, controller, value } = message else {
+            let _ = self.events.try_send(message);
+            return;
+        };
+
+        if let Some(request) = self.learn.lock().ok().and_then(|mut l| l.take()) {
+            let mapping = MidiMapping::new(channel, controller, request.owner, &request.param, request.min, request.max);
+            info!("MIDI learn: {} -> {}", mapping.label(), request.param);
+            if let Ok(mut timeline) = self.engine.timeline.lock() {
+                timeline.midi_map.learn(mapping);
+            }
+            return;
+        }
+
+        // Resolve against the map without holding the timeline lock while touching devices
+        let targets: Vec<(ParamOwner, String)> = match self.engine.timeline.lock() {
+            Ok(mut timeline) => timeline
+                .midi_map
+                .for_controller(channel, controller)
+                .map(|m| (m.owner, m.param.clone()))
+                .collect(),
+            Err(_) => return,
+        };
+        if targets.is_empty() {
+            let _ = self.events.try_send(message);
+            return;
+        }
+
+        for (owner, param) in targets {
+            let Some(current) = self.engine.param_value(owner, &param) else { continue };
+            let resolved = self.engine.timeline.lock().ok().and_then(|mut timeline| {
+                timeline.midi_map.find_mut(owner, &param)?.resolve(value, current)
+            });
+            if let Some(new_value) = resolved {
+                self.engine.set_param(owner, &param, new_value);
+            }
+        }
+    }
+}
+
+/// Running MIDI input: one reader thread per hardware port
+pub struct MidiInputService {
+    shared: Arc<MidiShared>,
+    events: Receiver<MidiMessage>,
+    ports: Vec<MidiPort>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl MidiInputService {
+    /// List available MIDI input ports
+    pub fn list_ports() -> Result<Vec<MidiPort>, MidiInputError> {
+        backend::list_ports(false)
+    }
+
+    /// Open every input port but those named in `disabled` and start applying CC mappings
+    /// to the engine
+    pub fn start(engine: Arc<EngineState>, disabled: &[String]) -> Result<Self, MidiInputError> {
+        let mut ports = Self::list_ports()?;
+        ports.retain(|port| !disabled.contains(&port.name));
+        if ports.is_empty() {
+            return Err(MidiInputError::NoPorts);
+        }
+
+        let (events_tx, events) = crossbeam_channel::bounded(EVENT_QUEUE_SIZE);
+        let shared = Arc::new(MidiShared {
+            engine,
+            learn: Mutex::new(None),
+            events: events_tx,
+            follow_clock: AtomicBool::new(false),
+            clock: Mutex::new(ClockFollower::default()),
+            stop: AtomicBool::new(false),
+            waker: OnceLock::new(),
+        });
+
+        let mut threads = Vec::new();
+        for port in &ports {
+            let shared = shared.clone();
+            let port_id = port.id.clone();
+            let spawned = std::thread::Builder::new()
+                .name(format!("midi-in {}", port.id))
+                .spawn(move || {
+                    let result = backend::read_port(&port_id, &shared.stop, |message| shared.handle(message));
+                    if let Err(e) = result {
+                        notifications::warning(format!("MIDI port {} closed", port_id), e);
+                    }
+                });
+            match spawned {
+                Ok(handle) => threads.push(handle),
+                Err(e) => warn!("Failed to start MIDI thread for {}: {}", port.id, e),
+            }
+        }
+
+        info!(count = ports.len(), "MIDI input started");
+        Ok(Self { shared, events, ports, threads })
+    }
+
+    pub fn ports(&self) -> &[MidiPort] {
+        &self.ports
+    }
+
+    /// Called from the port threads after each message but clock ticks, e.g. to repaint
+    /// the GUI that polls them. Only the first waker set is kept
+    pub fn set_waker(&self, wake: impl Fn() + Send + Sync + 'static) {
+        let _ = self.shared.waker.set(Box::new(wake));
+    }
+
+    /// Chase incoming MIDI clock: start/stop the transport and follow its tempo
+    pub fn set_follow_clock(&self, follow: bool) {
+        self.shared.follow_clock.store(follow, Ordering::Relaxed);
+        if let Ok(mut clock) = self.shared.clock.lock() {
+            *clock = ClockFollower::default();
+        }
+    }
+
+    pub fn follows_clock(&self) -> bool {
+        self.shared.follow_clock.load(Ordering::Relaxed)
+    }
+
+    /// Messages not consumed by MIDI learn mappings, oldest first
+    pub fn poll_messages(&self) -> impl Iterator<Item = MidiMessage> + '_ {
+        self.events.try_iter()
+    }
+
+    /// Map the next incoming CC to this parameter
+    pub fn arm_learn(&self, request: LearnRequest) {
+        if let Ok(mut learn) = self.shared.learn.lock() {
+            *learn = Some(request);
+        }
+    }
+
+    pub fn cancel_learn(&self) {
+        if let Ok(mut learn) = self.shared.learn.lock() {
+            *learn = None;
+        }
+    }
+
+    /// Parameter currently waiting for a CC, if any
+    pub fn learning(&self) -> Option<LearnRequest> {
+        self.shared.learn.lock().ok()?.clone()
+    }
+}
+
+impl Drop for MidiInputService {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::SeqCst);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) mod backend {
+    use std::io::Write;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use alsa::rawmidi::Rawmidi;
+    use alsa::{Direction, PollDescriptors};
+
+    use super::{MidiInputError, MidiPort, MidiMessage, MidiParser};
+
+    /// Poll timeout, bounds how long a reader takes to notice `stop`
+    const POLL_TIMEOUT_MS: i32 = 50;
+
+    /// Input (capture) or output (playback) ports
+    pub(crate) fn list_ports(output: bool) -> Result<Vec<MidiPort>, MidiInputError> {
+        let direction = if output { Direction::Playback } else { Direction::Capture };
+        let mut ports = Vec::new();
+        for card in alsa::card::Iter::new().filter_map(Result::ok) {
+            let Ok(ctl) = alsa::Ctl::from_card(&card, false) else { continue };
+            let card_name = card.get_name().unwrap_or_else(|_| format!("Card {}", card.get_index()));
+            for info in alsa::rawmidi::Iter::new(&ctl).filter_map(Result::ok) {
+                if info.get_stream() != direction {
+                    continue;
+                }
+                let sub_name = info.get_subdevice_name().unwrap_or_default();
+                ports.push(MidiPort {
+                    id: format!("hw:{},{},{}", card.get_index(), info.get_device(), info.get_subdevice()),
+                    name: if sub_name.is_empty() { card_name.clone() } else { sub_name },
+                });
+            }
+        }
+        Ok(ports)
+    }
+
+    /// Open an output port for writing raw MIDI bytes
+    pub(crate) fn open_output(id: &str) -> Result<impl Write, MidiInputError> {
+        let midi = Rawmidi::new(id, Direction::Playback, false).map_err(|e| MidiInputError::Device(e.to_string()))?;
+        Ok(OutputPort(midi))
+    }
+
+    struct OutputPort(Rawmidi);
+
+    impl Write for OutputPort {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.io().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    pub(super) fn read_port(
+        id: &str,
+        stop: &AtomicBool,
+        mut on_message: impl FnMut(MidiMessage),
+    ) -> Result<(), MidiInputError> {
+        let midi = Rawmidi::new(id, Direction::Capture, true).map_err(|e| MidiInputError::Device(e.to_string()))?;
+        let mut fds = midi.get().map_err(|e| MidiInputError::Device(e.to_string()))?;
+        let mut parser = MidiParser::new();
+        let mut buf = [0u8; 256];
+
+        while !stop.load(Ordering::Relaxed) {
+            let ready = alsa::poll::poll(&mut fds, POLL_TIMEOUT_MS).map_err(|e| MidiInputError::Device(e.to_string()))?;
+            if ready == 0 {
+                continue;
+            }
+            // Non-blocking read: EAGAIN just means the poll woke spuriously
+            let n = match std::io::Read::read(&mut midi.io(), &mut buf) {
+                Ok(n) => n,
+                Err(e) if e.raw_os_error().map(i32::abs) == Some(11) => continue,
+                Err(e) => return Err(MidiInputError::Device(e.to_string())),
+            };
+            for &byte in &buf[..n] {
+                if let Some(message) = parser.push(byte) {
+                    on_message(message);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) mod backend {
+    use std::sync::atomic::AtomicBool;
+
+    use super::{MidiInputError, MidiPort, MidiMessage};
+
+    pub(crate) fn list_ports(_output: bool) -> Result<Vec<MidiPort>, MidiInputError> {
+        Err(MidiInputError::Unsupported)
+    }
+
+    pub(crate) fn open_output(_id: &str) -> Result<std::io::Sink, MidiInputError> {
+        Err(MidiInputError::Unsupported)
+    }
+
+    pub(super) fn read_port(
+        _id: &str,
+        _stop: &AtomicBool,
+        _on_message: impl FnMut(MidiMessage),
+    ) -> Result<(), MidiInputError> {
+        Err(MidiInputError::Unsupported)
+    }
+}
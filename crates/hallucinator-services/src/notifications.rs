@@ -0,0 +1,77 @@
+//! Failures worth telling the user about, not just logging
+//!
+//! Anything can post a notification - stream error callbacks, the plugin host
+//! watchdog, the GUI's own call sites - and the GUI drains them once a frame into
+//! its toasts. Posting logs the notification too and never blocks: when nobody
+//! drains the queue it fills up and further notifications are only logged.
+
+use std::sync::OnceLock;
+
+use crossbeam_channel::{bounded, Receiver, Sender};
+
+/// Notifications kept until the GUI drains them
+const QUEUE_LEN: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notification {
+    pub severity: Severity,
+    /// One line saying what failed
+    pub title: String,
+    /// The underlying error, shown when the notification is opened
+    pub detail: String,
+}
+
+struct Queue {
+    sender: Sender<Notification>,
+    receiver: Receiver<Notification>,
+}
+
+static QUEUE: OnceLock<Queue> = OnceLock::new();
+static WAKER: OnceLock<Box<dyn Fn() + Send + Sync>> = OnceLock::new();
+
+fn queue() -> &'static Queue {
+    QUEUE.get_or_init(|| {
+        let (sender, receiver) = bounded(QUEUE_LEN);
+        Queue { sender, receiver }
+    })
+}
+
+/// Log a notification and queue it for the GUI
+pub fn notify(severity: Severity, title: impl Into<String>, detail: impl Into<String>) {
+    let notification = Notification { severity, title: title.into(), detail: detail.into() };
+    match severity {
+        Severity::Info => tracing::info!("{}: {}", notification.title, notification.detail),
+        Severity::Warning => tracing::warn!("{}: {}", notification.title, notification.detail),
+        Severity::Error => tracing::error!("{}: {}", notification.title, notification.detail),
+    }
+    let _ = queue().sender.try_send(notification);
+    if let Some(wake) = WAKER.get() {
+        wake();
+    }
+}
+
+pub fn error(title: impl Into<String>, detail: impl ToString) {
+    notify(Severity::Error, title, detail.to_string());
+}
+
+pub fn warning(title: impl Into<String>, detail: impl ToString) {
+    notify(Severity::Warning, title, detail.to_string());
+}
+
+/// Called after each notification is queued, e.g. to repaint the GUI that drains them.
+/// Only the first waker set is kept
+pub fn set_waker(wake: impl Fn() + Send + Sync + 'static) {
+    let _ = WAKER.set(Box::new(wake));
+}
+
+/// Take every notification posted since the last call
+pub fn drain() -> Vec<Notification> {
+    queue().receiver.try_iter().collect()
+}
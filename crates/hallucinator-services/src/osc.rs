@@ -0,0 +1,397 @@
+//! OSC remote control over UDP
+//!
+//! Sending a message with a value sets it; sending the same address with no
+//! value asks for the current value, which is sent back to the sender.
+//! Tracks, effects and strips are numbered from 1. The server listens on
+//! localhost unless network access is turned on, since any sender can drive it.
+//!
+//! | Address | Arguments |
+//! |---|---|
+//! | `/transport/play`, `/transport/pause`, `/transport/stop` | |
+//! | `/transport/seek` | seconds |
+//! | `/transport/position`, `/transport/playing` | (get only) |
+//! | `/transport/bpm`, `/transport/loop` | [value] |
+//! | `/track/count` | (get only) |
+//! | `/track/{n}/volume`, `pan`, `mute`, `solo`, `arm` | [value] |
+//! | `/track/{n}/name` | (get only) |
+//! | `/track/{n}/instrument/param` | name [value] |
+//! | `/track/{n}/effect/{i}/param` | name [value] |
+//! | `/master/effect/{i}/param` | name [value] |
+//! | `.../params` (instead of `param`) | replies with every param as `.../param name value` |
+
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use hallucinator_core::ParamOwner;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+use crate::audio_engine::EngineState;
+
+/// Receive timeout, bounds how long the server takes to notice `stop`
+const RECV_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Highest track volume settable remotely (matches the track header range)
+const MAX_TRACK_VOLUME: f32 = 1.5;
+
+#[derive(Debug, Error)]
+pub enum OscError {
+    #[error("OSC socket error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Malformed OSC packet: {0}")]
+    Malformed(&'static str),
+}
+
+/// An OSC argument
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+    String(String),
+    Bool(bool),
+}
+
+impl OscArg {
+    /// Numeric value of an int, float or bool argument; NaN and infinite floats are
+    /// dropped, since they'd pass through `clamp` into the engine
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            OscArg::Int(i) => Some(*i as f32),
+            OscArg::Float(f) => f.is_finite().then_some(*f),
+            OscArg::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+            OscArg::String(_) => None,
+        }
+    }
+}
+
+/// An OSC message
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub args: Vec<OscArg>,
+}
+
+impl OscMessage {
+    pub fn new(address: impl Into<String>, args: Vec<OscArg>) -> Self {
+        Self { address: address.into(), args }
+    }
+
+    /// Decode a packet: a single message or a (possibly nested) bundle
+    pub fn decode_packet(data: &[u8]) -> Result<Vec<OscMessage>, OscError> {
+        let mut messages = Vec::new();
+        decode_into(data, &mut messages)?;
+        Ok(messages)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_string(&mut out, &self.address);
+        let mut tags = String::from(",");
+        for arg in &self.args {
+            tags.push(match arg {
+                OscArg::Int(_) => 'i',
+                OscArg::Float(_) => 'f',
+                OscArg::String(_) => 's',
+                OscArg::Bool(true) => 'T',
+                OscArg::Bool(false) => 'F',
+            });
+        }
+        write_string(&mut out, &tags);
+        for arg in &self.args {
+            match arg {
+                OscArg::Int(i) => out.extend_from_slice(&i.to_be_bytes()),
+                OscArg::Float(f) => out.extend_from_slice(&f.to_be_bytes()),
+                OscArg::String(s) => write_string(&mut out, s),
+                OscArg::Bool(_) => {}
+            }
+        }
+        out
+    }
+}
+
+/// Null-terminated string padded to a multiple of 4 bytes
+fn write_string(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(s.as_bytes());
+    out.push(0);
+    while !out.len().is_multiple_of(4) {
+        out.push(0);
+    }
+}
+
+/// Sequential reader over a packet
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], OscError> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.data.len());
+        let end = end.ok_or(OscError::Malformed("truncated packet"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn word(&mut self) -> Result<[u8; 4], OscError> {
+        let bytes = self.take(4)?;
+        Ok([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    fn string(&mut self) -> Result<String, OscError> {
+        let rest = &self.data[self.pos..];
+        let len = rest.iter().position(|&b| b == 0).ok_or(OscError::Malformed("unterminated string"))?;
+        let s = std::str::from_utf8(&rest[..len]).map_err(|_| OscError::Malformed("invalid UTF-8"))?;
+        self.take((len + 4) & !3)?;
+        Ok(s.to_string())
+    }
+}
+
+fn decode_into(data: &[u8], messages: &mut Vec<OscMessage>) -> Result<(), OscError> {
+    let mut reader = Reader { data, pos: 0 };
+
+    if data.starts_with(b"#bundle\0") {
+        reader.take(16)?; // "#bundle" and the time tag (applied immediately)
+        while reader.pos < data.len() {
+            let size = i32::from_be_bytes(reader.word()?);
+            let size = usize::try_from(size).map_err(|_| OscError::Malformed("negative bundle element size"))?;
+            decode_into(reader.take(size)?, messages)?;
+        }
+        return Ok(());
+    }
+
+    let address = reader.string()?;
+    if !address.starts_with('/') {
+        return Err(OscError::Malformed("address must start with '/'"));
+    }
+    // Type tags are optional in very old senders
+    let tags = if reader.pos < data.len() { reader.string()? } else { String::from(",") };
+    let Some(tags) = tags.strip_prefix(',') else {
+        return Err(OscError::Malformed("missing type tag string"));
+    };
+
+    let mut args = Vec::new();
+    for tag in tags.chars() {
+        let arg = match tag {
+            'i' => OscArg::Int(i32::from_be_bytes(reader.word()?)),
+            'f' => OscArg::Float(f32::from_be_bytes(reader.word()?)),
+            's' | 'S' => OscArg::String(reader.string()?),
+            'T' => OscArg::Bool(true),
+            'F' => OscArg::Bool(false),
+            'h' => {
+                let bytes: [u8; 8] = reader.take(8)?.try_into().unwrap_or_default();
+                OscArg::Int(i64::from_be_bytes(bytes).clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+            }
+            'd' => {
+                let bytes: [u8; 8] = reader.take(8)?.try_into().unwrap_or_default();
+                OscArg::Float(f64::from_be_bytes(bytes) as f32)
+            }
+            'N' | 'I' => continue,
+            _ => return Err(OscError::Malformed("unsupported argument type")),
+        };
+        args.push(arg);
+    }
+
+    messages.push(OscMessage { address, args });
+    Ok(())
+}
+
+/// Running OSC server bound to a UDP port
+pub struct OscServer {
+    local_addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl OscServer {
+    pub const DEFAULT_PORT: u16 = 9000;
+
+    /// Listen at `addr`. Binding `127.0.0.1` only takes messages from this machine;
+    /// `0.0.0.0` takes them from anyone on the network.
+    pub fn start(engine: Arc<EngineState>, addr: SocketAddr) -> Result<Self, OscError> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_read_timeout(Some(RECV_TIMEOUT))?;
+        let local_addr = socket.local_addr()?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread = std::thread::Builder::new()
+            .name("osc-server".to_string())
+            .spawn(move || serve(&socket, &engine, &thread_stop))?;
+
+        info!(%local_addr, "OSC server listening");
+        Ok(Self { local_addr, stop, thread: Some(thread) })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for OscServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn serve(socket: &UdpSocket, engine: &EngineState, stop: &AtomicBool) {
+    let mut buf = [0u8; 8192];
+    while !stop.load(Ordering::Relaxed) {
+        let (len, sender) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => continue,
+            Err(e) => {
+                warn!("OSC receive failed: {}", e);
+                continue;
+            }
+        };
+
+        let messages = match OscMessage::decode_packet(&buf[..len]) {
+            Ok(messages) => messages,
+            Err(e) => {
+                debug!("Ignoring OSC packet from {}: {}", sender, e);
+                continue;
+            }
+        };
+        for message in messages {
+            for reply in handle_message(engine, &message) {
+                if let Err(e) = socket.send_to(&reply.encode(), sender) {
+                    debug!("OSC reply to {} failed: {}", sender, e);
+                }
+            }
+        }
+    }
+}
+
+/// 1-based index segment to a 0-based index
+fn index(segment: &str) -> Option<usize> {
+    segment.parse::<usize>().ok()?.checked_sub(1)
+}
+
+/// Apply a message and return any replies
+fn handle_message(engine: &EngineState, message: &OscMessage) -> Vec<OscMessage> {
+    let parts: Vec<&str> = message.address.trim_start_matches('/').split('/').collect();
+    let value = message.args.first().and_then(OscArg::as_f32);
+    let reply = |arg: OscArg| vec![OscMessage::new(message.address.clone(), vec![arg])];
+
+    match parts.as_slice() {
+        ["transport", command] => handle_transport(engine, command, value).map(reply).unwrap_or_default(),
+        ["track", "count"] => {
+            let count = engine.timeline.lock().map(|t| t.tracks.len()).unwrap_or(0);
+            reply(OscArg::Int(count as i32))
+        }
+        ["track", n, property] => {
+            let Some(track_idx) = index(n) else { return Vec::new() };
+            handle_track(engine, track_idx, property, value).map(reply).unwrap_or_default()
+        }
+        ["track", n, "instrument", kind] => {
+            let Some(track_idx) = index(n) else { return Vec::new() };
+            let owner = engine
+                .timeline
+                .lock()
+                .ok()
+                .and_then(|t| t.tracks.get(track_idx)?.instrument_id)
+                .map(ParamOwner::Instrument);
+            handle_param(engine, owner, kind, message)
+        }
+        ["track", n, "effect", i, kind] => {
+            let (Some(track_idx), Some(effect_idx)) = (index(n), index(i)) else { return Vec::new() };
+            let owner = engine
+                .timeline
+                .lock()
+                .ok()
+                .and_then(|t| t.tracks.get(track_idx)?.effect_chain_id)
+                .map(|chain_id| ParamOwner::TrackEffect { chain_id, index: effect_idx });
+            handle_param(engine, owner, kind, message)
+        }
+        ["master", "effect", i, kind] => {
+            let Some(effect_idx) = index(i) else { return Vec::new() };
+            handle_param(engine, Some(ParamOwner::MasterEffect(effect_idx)), kind, message)
+        }
+        _ => {
+            debug!("Unknown OSC address {}", message.address);
+            Vec::new()
+        }
+    }
+}
+
+/// Transport commands; returns the value to reply with for queries
+fn handle_transport(engine: &EngineState, command: &str, value: Option<f32>) -> Option<OscArg> {
+    match (command, value) {
+        ("play", _) => engine.play(),
+        ("pause", _) => engine.pause(),
+        ("stop", _) => engine.stop_playback(),
+        ("seek", Some(seconds)) => {
+            let sample_rate = engine.timeline.lock().ok()?.transport.sample_rate;
+            engine.seek((seconds.max(0.0) as f64 * sample_rate as f64) as u64);
+        }
+        ("position", None) => {
+            let sample_rate = engine.timeline.lock().ok()?.transport.sample_rate;
+            let position = engine.position.load(Ordering::SeqCst);
+            return Some(OscArg::Float((position as f64 / sample_rate.max(1) as f64) as f32));
+        }
+        ("playing", None) => return Some(OscArg::Int(engine.playing.load(Ordering::SeqCst) as i32)),
+        ("bpm", Some(bpm)) => engine.timeline.lock().ok()?.transport.bpm = (bpm as f64).clamp(20.0, 300.0),
+        ("bpm", None) => return Some(OscArg::Float(engine.timeline.lock().ok()?.transport.bpm as f32)),
+        ("loop", Some(on)) => engine.timeline.lock().ok()?.transport.loop_enabled = on != 0.0,
+        ("loop", None) => return Some(OscArg::Int(engine.timeline.lock().ok()?.transport.loop_enabled as i32)),
+        _ => debug!("Unknown OSC transport command {}", command),
+    }
+    None
+}
+
+/// Track mixer properties; returns the value to reply with for queries
+fn handle_track(engine: &EngineState, track_idx: usize, property: &str, value: Option<f32>) -> Option<OscArg> {
+    let mut timeline = engine.timeline.lock().ok()?;
+    let track = timeline.tracks.get_mut(track_idx)?;
+    match (property, value) {
+        ("volume", Some(v)) => track.volume = v.clamp(0.0, MAX_TRACK_VOLUME),
+        ("volume", None) => return Some(OscArg::Float(track.volume)),
+        ("pan", Some(v)) => track.pan = v.clamp(-1.0, 1.0),
+        ("pan", None) => return Some(OscArg::Float(track.pan)),
+        ("mute", Some(v)) => track.mute = v != 0.0,
+        ("mute", None) => return Some(OscArg::Int(track.mute as i32)),
+        ("solo", Some(v)) => track.solo = v != 0.0,
+        ("solo", None) => return Some(OscArg::Int(track.solo as i32)),
+        ("arm", Some(v)) => track.armed = v != 0.0,
+        ("arm", None) => return Some(OscArg::Int(track.armed as i32)),
+        ("name", None) => return Some(OscArg::String(track.name.clone())),
+        _ => debug!("Unknown OSC track property {}", property),
+    }
+    None
+}
+
+/// `param` (name [value]) and `params` (list) on a device
+fn handle_param(engine: &EngineState, owner: Option<ParamOwner>, kind: &str, message: &OscMessage) -> Vec<OscMessage> {
+    let Some(owner) = owner else { return Vec::new() };
+    let param_address = format!("{}/param", message.address.trim_end_matches(kind).trim_end_matches('/'));
+    let param_reply = |name: &str, value: f32| {
+        OscMessage::new(param_address.clone(), vec![OscArg::String(name.to_string()), OscArg::Float(value)])
+    };
+
+    match kind {
+        "params" => engine
+            .params(owner)
+            .unwrap_or_default()
+            .iter()
+            .map(|p| param_reply(&p.name, p.value))
+            .collect(),
+        "param" => {
+            let Some(OscArg::String(name)) = message.args.first() else { return Vec::new() };
+            match message.args.get(1).and_then(OscArg::as_f32) {
+                Some(value) => {
+                    engine.set_param(owner, name, value);
+                    Vec::new()
+                }
+                None => engine.param_value(owner, name).map(|v| vec![param_reply(name, v)]).unwrap_or_default(),
+            }
+        }
+        _ => Vec::new(),
+    }
+}
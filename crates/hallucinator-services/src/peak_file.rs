@@ -0,0 +1,43 @@
+//! Peak files: waveform overviews of imported audio files, kept in a cache directory one
+//! file per source keyed by its path, size and modification time, so importing a long
+//! file again skips scanning every sample
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use hallucinator_core::WaveformPeaks;
+
+/// Peak file for a source, or None when the source can't be stat'ed
+fn peak_file(cache_dir: &Path, path: &Path, frames: usize, channels: u16) -> Option<PathBuf> {
+    let meta = std::fs::metadata(path).ok()?;
+    let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let mut hasher = DefaultHasher::new();
+    // The decoded length too: the same file read at another rate has other peaks
+    (path, meta.len(), mtime, frames, channels).hash(&mut hasher);
+    Some(cache_dir.join(format!("{:016x}.peaks", hasher.finish())))
+}
+
+/// Peaks of `samples`, decoded from the audio file at `path`: read from its peak file if
+/// there is one, else built and written to one
+pub fn load_or_build(cache_dir: &Path, path: &Path, samples: &[f32], channels: u16) -> WaveformPeaks {
+    let frames = samples.len() / channels.max(1) as usize;
+    let file = peak_file(cache_dir, path, frames, channels);
+    if let Some(peaks) = file.as_ref()
+        .and_then(|file| std::fs::read(file).ok())
+        .and_then(|data| WaveformPeaks::from_bytes(&data))
+        .filter(|peaks| peaks.frames() == frames)
+    {
+        return peaks;
+    }
+
+    let peaks = WaveformPeaks::from_samples(samples, channels as usize);
+    if let Some(file) = file {
+        let written = std::fs::create_dir_all(cache_dir).and_then(|_| std::fs::write(&file, peaks.to_bytes()));
+        if let Err(e) = written {
+            tracing::warn!(path = %file.display(), "Failed to write peak file: {}", e);
+        }
+    }
+    peaks
+}
@@ -0,0 +1,114 @@
+//! Browser audition: a sample played on the cue bus (after the master chain and meters),
+//! optionally transposed, and for loops varispeeded to the project tempo
+
+/// Loop lengths tried when guessing a sample's tempo, in beats
+const LOOP_BEATS: [f64; 5] = [4.0, 8.0, 16.0, 32.0, 64.0];
+/// Tempo range a loop's guessed tempo must fall in (one octave, so at most one length fits)
+const LOOP_BPM_MIN: f64 = 80.0;
+const LOOP_BPM_MAX: f64 = 160.0;
+
+/// Audition settings chosen in the browser
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PreviewSettings {
+    /// Cue level, linear gain
+    pub volume: f32,
+    /// Transpose in semitones (varispeed, so the length changes with it)
+    pub pitch: f32,
+    /// Play loops at the project tempo, repeating until stopped
+    pub sync_tempo: bool,
+}
+
+impl Default for PreviewSettings {
+    fn default() -> Self {
+        Self { volume: 0.8, pitch: 0.0, sync_tempo: false }
+    }
+}
+
+/// The sample being auditioned
+#[derive(Default)]
+pub struct Preview {
+    samples: Vec<f32>,
+    sample_rate: u32,
+    /// Read position in source samples; None when stopped
+    position: Option<f64>,
+    /// Tempo guessed from the sample's length (None for one-shots)
+    source_bpm: Option<f64>,
+    pub settings: PreviewSettings,
+}
+
+impl Preview {
+    /// Start auditioning mono `samples` recorded at `sample_rate`, replacing any current one
+    pub fn start(&mut self, samples: Vec<f32>, sample_rate: u32) {
+        self.source_bpm = loop_tempo(samples.len(), sample_rate);
+        self.samples = samples;
+        self.sample_rate = sample_rate;
+        self.position = Some(0.0);
+    }
+
+    pub fn stop(&mut self) {
+        self.position = None;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.position.is_some()
+    }
+
+    /// Tempo the current sample loops at, if its length is a whole number of bars
+    pub fn source_bpm(&self) -> Option<f64> {
+        self.source_bpm
+    }
+
+    /// Source samples advanced per output sample
+    fn rate(&self, output_rate: u32, bpm: f64) -> f64 {
+        let mut rate = self.sample_rate as f64 / output_rate.max(1) as f64 * 2f64.powf(self.settings.pitch as f64 / 12.0);
+        if let Some(source_bpm) = self.source_bpm.filter(|_| self.settings.sync_tempo) {
+            rate *= bpm / source_bpm;
+        }
+        rate
+    }
+
+    /// Add the audition into an interleaved output buffer (audio thread)
+    pub fn mix(&mut self, buffer: &mut [f32], channels: usize, output_rate: u32, bpm: f64) {
+        let Some(mut pos) = self.position else { return };
+        let rate = self.rate(output_rate, bpm);
+        let looping = self.settings.sync_tempo && self.source_bpm.is_some();
+        let len = self.samples.len();
+        let gain = self.settings.volume;
+
+        for frame in buffer.chunks_mut(channels) {
+            if pos >= len as f64 {
+                if !looping {
+                    self.position = None;
+                    return;
+                }
+                pos %= len as f64;
+            }
+            // Linear interpolation; a loop reads across its seam
+            let i = pos as usize;
+            let next = match self.samples.get(i + 1) {
+                Some(&s) => s,
+                None if looping => self.samples[0],
+                None => 0.0,
+            };
+            let frac = (pos - i as f64) as f32;
+            let sample = (self.samples[i] + (next - self.samples[i]) * frac) * gain;
+            for ch in frame.iter_mut() {
+                *ch += sample;
+            }
+            pos += rate;
+        }
+        self.position = Some(pos);
+    }
+}
+
+/// Tempo at which `len` samples make 1, 2, 4, 8 or 16 bars of 4/4 within 80-160 bpm
+pub(crate) fn loop_tempo(len: usize, sample_rate: u32) -> Option<f64> {
+    if len == 0 || sample_rate == 0 {
+        return None;
+    }
+    let minutes = len as f64 / sample_rate as f64 / 60.0;
+    LOOP_BEATS
+        .iter()
+        .map(|beats| beats / minutes)
+        .find(|bpm| (LOOP_BPM_MIN..LOOP_BPM_MAX).contains(bpm))
+}
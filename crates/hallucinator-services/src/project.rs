@@ -0,0 +1,395 @@
+//! Project files: the timeline as JSON, with what it takes to load each instrument and
+//! effect chain again, and the audio of its clips and samplers as WAV files in a media
+//! folder beside it
+//!
+//! Media files are named after a hash of their audio, so a save writes only audio that
+//! changed and never overwrites a file the previous save still points at.
+//!
+//! VST3 instruments are stored by class ID, their patch riding on the track that plays
+//! them (`Track::instrument_state`); effects are stored by ID with their parameter values.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{DefaultHasher, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use hallucinator_core::Timeline;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::audio_effects::{
+    create_native_effect, AudioEffect, Drum808, EffectChain, EffectSlotCopy, EffectSource, Instrument, Oversampling,
+    SampleKit, Sampler, SignalGenerator, Vst3Effect, Vst3Instrument, Vst3PluginInfo, Vst3Scanner, NATIVE_EFFECTS,
+};
+use crate::audio_engine::EngineState;
+use crate::audio_file::read_audio;
+use crate::wav_writer::WavWriter;
+
+/// Extension of project files
+pub const PROJECT_EXTENSION: &str = "json";
+
+#[derive(Debug, Error)]
+pub enum ProjectError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid project file: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Failed to read {}: {message}", path.display())]
+    ReadMedia { path: PathBuf, message: String },
+}
+
+/// Mono audio a sampler or kit slot plays, kept in the media folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSample {
+    pub name: String,
+    /// File name in the media folder (named by `Project::save`)
+    pub media: String,
+    #[serde(skip)]
+    pub data: Arc<Vec<f32>>,
+}
+
+/// How an instrument is loaded again, with its parameter values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SavedInstrument {
+    Drum808 { params: Vec<(String, f32)> },
+    SignalGenerator { params: Vec<(String, f32)> },
+    Sampler { sample: SavedSample, params: Vec<(String, f32)> },
+    /// Filled slots by index
+    SampleKit { slots: Vec<(usize, SavedSample)>, params: Vec<(String, f32)> },
+    /// By class ID; the plugin's state is saved on the tracks playing it
+    Vst3 { uid: String, name: String, sandboxed: bool },
+}
+
+impl SavedInstrument {
+    fn of(instrument: &Instrument) -> Self {
+        let params = || instrument.get_params().iter().map(|p| (p.name.clone(), p.value)).collect();
+        match instrument {
+            Instrument::Vst3(v) => Self::Vst3 {
+                uid: v.plugin_info().info.unique_id.clone(),
+                name: v.plugin_info().name.clone(),
+                sandboxed: v.sandbox_restarts().is_some(),
+            },
+            Instrument::Drum808(_) => Self::Drum808 { params: params() },
+            Instrument::SignalGenerator(_) => Self::SignalGenerator { params: params() },
+            Instrument::Sampler(s) => Self::Sampler {
+                sample: SavedSample {
+                    name: instrument.name().to_string(),
+                    media: String::new(),
+                    data: Arc::clone(s.sample_data()),
+                },
+                params: params(),
+            },
+            Instrument::SampleKit(k) => Self::SampleKit {
+                slots: k.slots().iter().enumerate()
+                    .filter_map(|(index, slot)| {
+                        let slot = slot.as_ref()?;
+                        Some((index, SavedSample {
+                            name: slot.name.clone(),
+                            media: String::new(),
+                            data: Arc::clone(&slot.data),
+                        }))
+                    })
+                    .collect(),
+                params: params(),
+            },
+        }
+    }
+
+    fn samples_mut(&mut self) -> Vec<&mut SavedSample> {
+        match self {
+            Self::Sampler { sample, .. } => vec![sample],
+            Self::SampleKit { slots, .. } => slots.iter_mut().map(|(_, sample)| sample).collect(),
+            Self::Drum808 { .. } | Self::SignalGenerator { .. } | Self::Vst3 { .. } => Vec::new(),
+        }
+    }
+
+    /// A new instance at `sample_rate`; samples are saved at the project's `project_rate`
+    fn load(&self, scanner: Option<&Vst3Scanner>, project_rate: u32, sample_rate: f32) -> Result<Instrument, String> {
+        let (mut instrument, params) = match self {
+            Self::Vst3 { uid, name, sandboxed } => {
+                let (rack_scanner, info) = find_plugin(scanner, uid).ok_or_else(|| name.clone())?;
+                let loaded = if *sandboxed {
+                    Vst3Instrument::new_sandboxed(rack_scanner, info, sample_rate)
+                } else {
+                    Vst3Instrument::new(rack_scanner, info, sample_rate)
+                };
+                return loaded.map(Instrument::Vst3).map_err(|e| format!("{name} ({e})"));
+            }
+            Self::Drum808 { params } => (Instrument::Drum808(Drum808::new(sample_rate)), params),
+            Self::SignalGenerator { params } => (Instrument::SignalGenerator(SignalGenerator::new(sample_rate)), params),
+            Self::Sampler { sample, params } => {
+                let sampler = Sampler::from_samples(sample.name.clone(), &sample.data, project_rate, sample_rate)
+                    .map_err(|e| format!("{} ({e})", sample.name))?;
+                (Instrument::Sampler(sampler), params)
+            }
+            Self::SampleKit { slots, params } => {
+                let mut kit = SampleKit::new(sample_rate);
+                for (index, sample) in slots {
+                    kit.set_slot(*index, sample.name.clone(), Arc::clone(&sample.data));
+                }
+                (Instrument::SampleKit(kit), params)
+            }
+        };
+        for (name, value) in params {
+            instrument.set_param(name, *value);
+        }
+        Ok(instrument)
+    }
+}
+
+/// Where a saved effect is loaded from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SavedEffectSource {
+    /// Built-in effect, by its `NATIVE_EFFECTS` id
+    Native(String),
+    Vst3 { uid: String, name: String },
+}
+
+/// An effect slot and its settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedEffect {
+    pub source: SavedEffectSource,
+    /// Parameter values by name
+    pub params: Vec<(String, f32)>,
+    pub bypass: bool,
+    pub mix: f32,
+    pub oversampling: Oversampling,
+}
+
+/// An effect chain's slots, in order
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SavedEffectChain {
+    pub effects: Vec<SavedEffect>,
+    pub bypass_all: bool,
+}
+
+impl SavedEffectChain {
+    fn of(chain: &EffectChain) -> Self {
+        let effects = chain.slot_copies().into_iter()
+            .map(|copy| SavedEffect {
+                source: match copy.source {
+                    EffectSource::Native(id) => SavedEffectSource::Native(id.to_string()),
+                    EffectSource::Vst3(info) => SavedEffectSource::Vst3 { uid: info.info.unique_id, name: info.name },
+                },
+                params: copy.params,
+                bypass: copy.bypass,
+                mix: copy.mix,
+                oversampling: copy.oversampling,
+            })
+            .collect();
+        Self { effects, bypass_all: chain.is_bypass_all() }
+    }
+
+    /// A new chain with the saved effects, leaving out (and naming in `missing`) the ones
+    /// that can't be loaded
+    fn load(&self, scanner: Option<&Vst3Scanner>, sample_rate: f32, missing: &mut Vec<String>) -> EffectChain {
+        let mut chain = EffectChain::new();
+        for saved in &self.effects {
+            let loaded: Result<(EffectSource, Box<dyn AudioEffect>), String> = match &saved.source {
+                SavedEffectSource::Native(id) => NATIVE_EFFECTS.iter()
+                    .find(|(native, _)| *native == id.as_str())
+                    .and_then(|&(id, _)| Some((EffectSource::Native(id), create_native_effect(id, sample_rate)?)))
+                    .ok_or_else(|| id.clone()),
+                SavedEffectSource::Vst3 { uid, name } => find_plugin(scanner, uid)
+                    .ok_or_else(|| name.clone())
+                    .and_then(|(rack_scanner, info)| {
+                        let effect = Vst3Effect::new(rack_scanner, info, sample_rate).map_err(|e| format!("{name} ({e})"))?;
+                        Ok((EffectSource::Vst3(info.clone()), Box::new(effect) as Box<dyn AudioEffect>))
+                    }),
+            };
+            match loaded {
+                Ok((source, effect)) => {
+                    let copy = EffectSlotCopy {
+                        source,
+                        params: saved.params.clone(),
+                        bypass: saved.bypass,
+                        mix: saved.mix,
+                        oversampling: saved.oversampling,
+                    };
+                    chain.add_copy(effect, &copy, sample_rate);
+                }
+                Err(name) => missing.push(name),
+            }
+        }
+        chain.set_bypass_all(self.bypass_all);
+        chain
+    }
+}
+
+/// The rack scanner and scanned info of the plugin with class ID `uid`
+fn find_plugin<'a>(scanner: Option<&'a Vst3Scanner>, uid: &str) -> Option<(&'a rack::Scanner, &'a Vst3PluginInfo)> {
+    let scanner = scanner?;
+    let info = scanner.plugins().iter().find(|p| p.info.unique_id == uid)?;
+    Some((scanner.scanner()?, info))
+}
+
+/// Instruments and effect chains loaded for a project, for `EngineState::open_project`
+#[derive(Default)]
+pub struct ProjectDevices {
+    pub instruments: HashMap<u64, Instrument>,
+    pub effect_chains: HashMap<u64, EffectChain>,
+    pub master_effects: EffectChain,
+    /// Devices that couldn't be loaded, by name; tracks using them play without them
+    pub missing: Vec<String>,
+}
+
+/// A project as saved: the timeline and how to rebuild the devices it plays through
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub timeline: Timeline,
+    /// Instruments by ID
+    pub instruments: BTreeMap<u64, SavedInstrument>,
+    /// Track effect chains by ID
+    pub effect_chains: BTreeMap<u64, SavedEffectChain>,
+    #[serde(default)]
+    pub master_effects: SavedEffectChain,
+    /// Media file of each audio clip, by clip ID (named by `save`)
+    #[serde(default)]
+    pub clip_media: BTreeMap<u64, String>,
+}
+
+impl Project {
+    /// Take the engine's project for saving. Plugin states are read off the audio lock, and
+    /// the audio thread's locks are only held to copy settings and the timeline, whose clip
+    /// audio is shared rather than copied.
+    pub fn capture(state: &EngineState) -> Option<Self> {
+        state.capture_plugin_states();
+        let instruments = state.instruments.lock().ok()?
+            .iter()
+            .map(|(&id, instrument)| (id, SavedInstrument::of(instrument)))
+            .collect();
+        let effect_chains = state.track_effects.lock().ok()?
+            .iter()
+            .map(|(&id, chain)| (id, SavedEffectChain::of(chain)))
+            .collect();
+        let master_effects = SavedEffectChain::of(&*state.master_effects.lock().ok()?);
+        let timeline = state.timeline.lock().ok()?.clone();
+        Some(Self { timeline, instruments, effect_chains, master_effects, clip_media: BTreeMap::new() })
+    }
+
+    /// Write the project to `path` and its audio to the media folder beside it, removing
+    /// media an earlier save left there that the project no longer uses. Audio already in
+    /// the folder isn't written again.
+    pub fn save(&mut self, path: &Path) -> Result<(), ProjectError> {
+        let media = media_dir(path);
+        std::fs::create_dir_all(&media)?;
+        let mut used = HashSet::new();
+        self.clip_media.clear();
+        for clip in self.timeline.audio_clips() {
+            let name = media_name("clip", &clip.samples, clip.sample_rate, clip.channels);
+            write_media(&media, &name, &clip.samples, clip.sample_rate, clip.channels, &clip.name)?;
+            self.clip_media.insert(clip.id.0, name.clone());
+            used.insert(name);
+        }
+        let sample_rate = self.timeline.transport.sample_rate;
+        for sample in self.instruments.values_mut().flat_map(SavedInstrument::samples_mut) {
+            sample.media = media_name("sample", &sample.data, sample_rate, 1);
+            write_media(&media, &sample.media, &sample.data, sample_rate, 1, &sample.name)?;
+            used.insert(sample.media.clone());
+        }
+
+        // Written next to the last save and moved over it, so a failed save leaves that one whole
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&partial, path)?;
+
+        for entry in std::fs::read_dir(&media)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if (name.ends_with(".wav") || name.ends_with(".partial")) && !used.contains(&name) {
+                std::fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a project written by `save`, with the audio of its clips and samplers
+    pub fn load(path: &Path) -> Result<Self, ProjectError> {
+        let mut project: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let media = media_dir(path);
+        for clip in project.timeline.audio_clips_mut() {
+            let name = project.clip_media.get(&clip.id.0).ok_or_else(|| ProjectError::ReadMedia {
+                path: media.clone(),
+                message: format!("no media for clip {}", clip.id.0),
+            })?;
+            let (samples, channels, sample_rate) = read_media(&media.join(name))?;
+            clip.samples = Arc::new(samples);
+            (clip.channels, clip.sample_rate) = (channels, sample_rate);
+            clip.build_peaks();
+        }
+        for sample in project.instruments.values_mut().flat_map(SavedInstrument::samples_mut) {
+            sample.data = Arc::new(read_media(&media.join(&sample.media))?.0);
+        }
+        Ok(project)
+    }
+
+    /// Load the project's instruments and effects at `sample_rate`, finding VST3 plugins
+    /// among those `scanner` found
+    pub fn load_devices(&self, scanner: Option<&Vst3Scanner>, sample_rate: f32) -> ProjectDevices {
+        let mut devices = ProjectDevices::default();
+        let project_rate = self.timeline.transport.sample_rate;
+        for (&id, saved) in &self.instruments {
+            match saved.load(scanner, project_rate, sample_rate) {
+                Ok(instrument) => {
+                    devices.instruments.insert(id, instrument);
+                }
+                Err(name) => devices.missing.push(name),
+            }
+        }
+        for (&id, saved) in &self.effect_chains {
+            let chain = saved.load(scanner, sample_rate, &mut devices.missing);
+            devices.effect_chains.insert(id, chain);
+        }
+        devices.master_effects = self.master_effects.load(scanner, sample_rate, &mut devices.missing);
+        devices
+    }
+
+    /// One past the highest instrument and effect chain IDs in use, for numbering new ones
+    pub fn next_device_ids(&self) -> (u64, u64) {
+        let next = |last: Option<&u64>| last.map_or(1, |id| id + 1);
+        (next(self.instruments.keys().last()), next(self.effect_chains.keys().last()))
+    }
+}
+
+/// Folder beside a project file holding its audio
+pub fn media_dir(path: &Path) -> PathBuf {
+    let stem = path.file_stem().map_or_else(|| "project".into(), |s| s.to_string_lossy());
+    path.with_file_name(format!("{stem}-media"))
+}
+
+/// Media file name for audio, from a hash of it and its format, so changed audio gets a
+/// new file (and the same audio used twice shares one)
+fn media_name(kind: &str, samples: &[f32], sample_rate: u32, channels: u16) -> String {
+    let mut hasher = DefaultHasher::new();
+    hasher.write_u32(sample_rate);
+    hasher.write_u16(channels);
+    let mut bytes = [0u8; 4096];
+    for chunk in samples.chunks(bytes.len() / 4) {
+        for (out, sample) in bytes.chunks_exact_mut(4).zip(chunk) {
+            out.copy_from_slice(&sample.to_le_bytes());
+        }
+        hasher.write(&bytes[..chunk.len() * 4]);
+    }
+    hasher.write_usize(samples.len());
+    format!("{kind}-{:016x}.wav", hasher.finish())
+}
+
+/// Write interleaved samples as a 32-bit float WAV named `name` in the media folder,
+/// unless it's already there; written under a temporary name and then renamed, so the
+/// folder never holds a partial file under a media name
+fn write_media(media: &Path, name: &str, samples: &[f32], sample_rate: u32, channels: u16, title: &str) -> Result<(), ProjectError> {
+    let path = media.join(name);
+    if path.exists() {
+        return Ok(());
+    }
+    let partial = path.with_extension("partial");
+    let mut writer = WavWriter::create(&partial, sample_rate, channels, 0, title)?;
+    writer.write(samples)?;
+    writer.finish()?;
+    std::fs::rename(&partial, &path)?;
+    Ok(())
+}
+
+fn read_media(path: &Path) -> Result<(Vec<f32>, u16, u32), ProjectError> {
+    read_audio(path).map_err(|message| ProjectError::ReadMedia { path: path.to_path_buf(), message })
+}
@@ -0,0 +1,142 @@
+//! Waveform thumbnails and metadata for the sample browser
+//!
+//! `SampleInfoLoader` analyses files on a background thread and keeps the results in a
+//! cache directory, one file per sample keyed by its path, size and modification time.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crossbeam_channel::{Receiver, Sender};
+use serde::{Deserialize, Serialize};
+
+use crate::preview::loop_tempo;
+use crate::audio_file::read_audio;
+
+/// Bumped when the analysis changes; older cache entries are no longer found
+const CACHE_VERSION: u32 = 1;
+
+/// Peak levels stored per thumbnail
+pub const THUMBNAIL_POINTS: usize = 48;
+
+/// What the browser shows about a sample file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SampleInfo {
+    pub duration_secs: f64,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Tempo from the file name (e.g. "break_174bpm") or, for loops, from the length
+    pub bpm: Option<f64>,
+    /// Peak level of each slice of the file, 0-255
+    pub peaks: Vec<u8>,
+}
+
+impl SampleInfo {
+    /// Read and analyse an audio file
+    pub fn analyse(path: &Path) -> Result<Self, String> {
+        let (samples, channels, sample_rate) = read_audio(path)?;
+        let channels_n = channels.max(1) as usize;
+        let frames = samples.len() / channels_n;
+
+        let peaks = (0..THUMBNAIL_POINTS)
+            .map(|i| {
+                let start = i * frames / THUMBNAIL_POINTS * channels_n;
+                let end = (i + 1) * frames / THUMBNAIL_POINTS * channels_n;
+                let peak = samples[start..end].iter().fold(0.0f32, |m, s| m.max(s.abs()));
+                (peak.min(1.0) * 255.0).round() as u8
+            })
+            .collect();
+
+        Ok(Self {
+            duration_secs: frames as f64 / sample_rate.max(1) as f64,
+            sample_rate,
+            channels,
+            bpm: bpm_from_name(path).or_else(|| loop_tempo(frames, sample_rate)),
+            peaks,
+        })
+    }
+}
+
+/// Tempo written in a file name as digits followed by "bpm" ("120bpm", "120 BPM", "120_bpm")
+fn bpm_from_name(path: &Path) -> Option<f64> {
+    let stem = path.file_stem()?.to_str()?.to_lowercase();
+    let idx = stem.find("bpm")?;
+    let before = stem[..idx].trim_end_matches([' ', '_', '-']);
+    let digits: String = before.chars().rev().take_while(|c| c.is_ascii_digit()).collect();
+    let bpm: f64 = digits.chars().rev().collect::<String>().parse().ok()?;
+    (40.0..=300.0).contains(&bpm).then_some(bpm)
+}
+
+/// Cache file for a sample, or None when the sample can't be stat'ed
+fn cache_file(cache_dir: &Path, path: &Path) -> Option<PathBuf> {
+    let meta = std::fs::metadata(path).ok()?;
+    let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let mut hasher = DefaultHasher::new();
+    (CACHE_VERSION, path, meta.len(), mtime).hash(&mut hasher);
+    Some(cache_dir.join(format!("{:016x}.json", hasher.finish())))
+}
+
+/// Cached info, or a fresh analysis written to the cache
+fn load_or_analyse(cache_dir: Option<&Path>, path: &Path) -> Option<SampleInfo> {
+    let cache = cache_dir.and_then(|dir| cache_file(dir, path));
+    if let Some(info) = cache.as_ref()
+        .and_then(|file| std::fs::read(file).ok())
+        .and_then(|data| serde_json::from_slice(&data).ok())
+    {
+        return Some(info);
+    }
+
+    let info = match SampleInfo::analyse(path) {
+        Ok(info) => info,
+        Err(e) => {
+            tracing::debug!(path = %path.display(), "No sample info: {}", e);
+            return None;
+        }
+    };
+    if let Some(file) = cache {
+        let written = file.parent().map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| serde_json::to_vec(&info).map_err(std::io::Error::other))
+            .and_then(|data| std::fs::write(&file, data));
+        if let Err(e) = written {
+            tracing::warn!(path = %file.display(), "Failed to cache sample info: {}", e);
+        }
+    }
+    Some(info)
+}
+
+/// Background thread answering sample info requests in order
+pub struct SampleInfoLoader {
+    requests: Sender<PathBuf>,
+    results: Receiver<(PathBuf, Option<SampleInfo>)>,
+}
+
+impl SampleInfoLoader {
+    /// Start the loader; results are cached under `cache_dir` when given.
+    /// The thread exits when the loader is dropped.
+    pub fn spawn(cache_dir: Option<PathBuf>) -> std::io::Result<Self> {
+        let (requests, incoming) = crossbeam_channel::unbounded::<PathBuf>();
+        let (sender, results) = crossbeam_channel::unbounded();
+        std::thread::Builder::new()
+            .name("sample-info".to_string())
+            .spawn(move || {
+                for path in incoming {
+                    let info = load_or_analyse(cache_dir.as_deref(), &path);
+                    if sender.send((path, info)).is_err() {
+                        break;
+                    }
+                }
+            })?;
+        Ok(Self { requests, results })
+    }
+
+    /// Queue a file for analysis
+    pub fn request(&self, path: PathBuf) {
+        let _ = self.requests.send(path);
+    }
+
+    /// Results since the last poll (None for files that couldn't be read)
+    pub fn poll(&self) -> Vec<(PathBuf, Option<SampleInfo>)> {
+        self.results.try_iter().collect()
+    }
+}
@@ -0,0 +1,327 @@
+//! Rhai scripting for batch MIDI edits
+//!
+//! A script runs on a copy of the arrangement's MIDI clips, outside the timeline lock, and
+//! its edits are applied only if it finishes without an error, so a failing script leaves
+//! the project untouched. Scripts have no file or process access and are stopped after
+//! `MAX_OPERATIONS`. Tracks and clips are numbered from 0 in arrange order; clip positions
+//! are in beats and note positions in the clip's ticks.
+//!
+//! | Function | Returns |
+//! |---|---|
+//! | `bpm()` | tempo |
+//! | `track_count()`, `track_name(t)`, `is_midi(t)` | |
+//! | `selected_track()`, `selected_clip()` | index, or -1 if nothing is selected |
+//! | `clip_count(t)`, `clip_name(t, c)`, `clip_start(t, c)`, `clip_length(t, c)`, `ppq(t, c)` | |
+//! | `notes(t, c)` | array of `#{pitch, velocity, start, length}` |
+//! | `set_notes(t, c, notes)`, `add_note(t, c, pitch, velocity, start, length)`, `clear_notes(t, c)` | |
+//! | `transpose(t, c, semitones)` | |
+//! | `new_clip(t, start_beat, length_beats)` | new clip's index |
+//! | `euclid(pulses, steps, rotation)` | array of bools |
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use hallucinator_core::{euclidean_rhythm, ClipId, MidiClip, MidiNote, Timeline, TrackId, TrackKind};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, FLOAT, INT};
+use thiserror::Error;
+
+/// Operations a script may run before it is stopped (catches endless loops)
+const MAX_OPERATIONS: u64 = 5_000_000;
+
+/// Largest array or map a script may build
+const MAX_COLLECTION_SIZE: usize = 100_000;
+
+#[derive(Debug, Error)]
+pub enum ScriptError {
+    #[error("{0}")]
+    Eval(#[from] Box<EvalAltResult>),
+}
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// MIDI clips of one track, as the script sees them
+#[derive(Debug, Clone)]
+struct ScriptTrack {
+    id: TrackId,
+    name: String,
+    midi: bool,
+    clips: Vec<MidiClip>,
+    edited: bool,
+}
+
+/// Everything a script can read and change
+#[derive(Debug)]
+struct ScriptProject {
+    tracks: Vec<ScriptTrack>,
+    bpm: f64,
+    samples_per_beat: f64,
+    selection: Option<(usize, usize)>,
+    selected_track: Option<usize>,
+    next_clip_id: u64,
+    log: Vec<String>,
+}
+
+impl ScriptProject {
+    fn track(&self, t: INT) -> ScriptResult<&ScriptTrack> {
+        usize::try_from(t).ok()
+            .and_then(|t| self.tracks.get(t))
+            .ok_or_else(|| format!("no track {t}").into())
+    }
+
+    fn clip(&self, t: INT, c: INT) -> ScriptResult<&MidiClip> {
+        usize::try_from(c).ok()
+            .and_then(|c| self.track(t).ok()?.clips.get(c))
+            .ok_or_else(|| format!("no MIDI clip {c} on track {t}").into())
+    }
+
+    /// A clip to change; its track's clips are written back when the script ends
+    fn clip_mut(&mut self, t: INT, c: INT) -> ScriptResult<&mut MidiClip> {
+        let track = usize::try_from(t).ok()
+            .and_then(|t| self.tracks.get_mut(t))
+            .ok_or_else(|| format!("no track {t}"))?;
+        track.edited = true;
+        usize::try_from(c).ok()
+            .and_then(|c| track.clips.get_mut(c))
+            .ok_or_else(|| format!("no MIDI clip {c} on track {t}").into())
+    }
+}
+
+/// Edits made by a finished script
+#[derive(Debug)]
+pub struct ScriptOutcome {
+    /// Lines the script printed
+    pub log: Vec<String>,
+    /// Clip id counter after the clips the script created
+    pub next_clip_id: u64,
+    edits: Vec<(TrackId, Vec<MidiClip>)>,
+}
+
+impl ScriptOutcome {
+    /// Number of tracks whose clips the script changed
+    pub fn edited_tracks(&self) -> usize {
+        self.edits.len()
+    }
+
+    /// Write the changed tracks' MIDI clips into the timeline. Tracks are found by id, so
+    /// tracks added, moved or deleted while the script ran don't receive the wrong clips.
+    pub fn apply(self, timeline: &mut Timeline) {
+        for (id, clips) in self.edits {
+            if let Some(track) = timeline.get_track_mut(id)
+                && track.kind == TrackKind::Midi
+            {
+                track.midi_clips = clips;
+            }
+        }
+    }
+}
+
+/// A script run prepared from the timeline: take a snapshot with the timeline locked, then
+/// `run` it without holding the lock
+pub struct Script {
+    project: ScriptProject,
+}
+
+impl Script {
+    /// Copy the MIDI clips a script works on. `selection` is the selected MIDI clip and
+    /// `selected_track` the selected track, if any.
+    pub fn snapshot(timeline: &Timeline, selection: Option<(usize, ClipId)>, selected_track: Option<usize>, next_clip_id: u64) -> Self {
+        let tracks: Vec<ScriptTrack> = timeline.tracks.iter()
+            .map(|track| ScriptTrack {
+                id: track.id,
+                name: track.name.clone(),
+                midi: track.kind == TrackKind::Midi,
+                clips: track.midi_clips.clone(),
+                edited: false,
+            })
+            .collect();
+        let selection = selection.and_then(|(t, id)| {
+            let c = tracks.get(t)?.clips.iter().position(|clip| clip.id == id)?;
+            Some((t, c))
+        });
+        let transport = &timeline.transport;
+        Self {
+            project: ScriptProject {
+                tracks,
+                bpm: transport.bpm,
+                samples_per_beat: transport.sample_rate as f64 * 60.0 / transport.bpm,
+                selection,
+                selected_track: selected_track.or(selection.map(|(t, _)| t)),
+                next_clip_id,
+                log: Vec::new(),
+            },
+        }
+    }
+
+    /// Run `source` against the snapshot
+    pub fn run(self, source: &str) -> Result<ScriptOutcome, ScriptError> {
+        let project = Rc::new(RefCell::new(self.project));
+        let engine = build_engine(&project);
+        engine.run(source)?;
+        drop(engine);
+
+        let project = Rc::try_unwrap(project)
+            .map(RefCell::into_inner)
+            .map_err(|_| Box::<EvalAltResult>::from("script state still in use"))?;
+        Ok(ScriptOutcome {
+            log: project.log,
+            next_clip_id: project.next_clip_id,
+            edits: project.tracks.into_iter()
+                .filter(|track| track.edited)
+                .map(|track| (track.id, track.clips))
+                .collect(),
+        })
+    }
+}
+
+/// An engine with the project API registered and resource limits set
+fn build_engine(project: &Rc<RefCell<ScriptProject>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_array_size(MAX_COLLECTION_SIZE);
+    engine.set_max_map_size(MAX_COLLECTION_SIZE);
+    engine.set_max_string_size(MAX_COLLECTION_SIZE);
+
+    let p = project.clone();
+    engine.on_print(move |text| p.borrow_mut().log.push(text.to_string()));
+    let p = project.clone();
+    engine.on_debug(move |text, _, pos| p.borrow_mut().log.push(format!("{pos:?}: {text}")));
+
+    let p = project.clone();
+    engine.register_fn("bpm", move || p.borrow().bpm as FLOAT);
+    let p = project.clone();
+    engine.register_fn("track_count", move || p.borrow().tracks.len() as INT);
+    let p = project.clone();
+    engine.register_fn("track_name", move |t: INT| -> ScriptResult<String> {
+        Ok(p.borrow().track(t)?.name.clone())
+    });
+    let p = project.clone();
+    engine.register_fn("is_midi", move |t: INT| -> ScriptResult<bool> { Ok(p.borrow().track(t)?.midi) });
+    let p = project.clone();
+    engine.register_fn("selected_track", move || p.borrow().selected_track.map_or(-1, |t| t as INT));
+    let p = project.clone();
+    engine.register_fn("selected_clip", move || p.borrow().selection.map_or(-1, |(_, c)| c as INT));
+
+    let p = project.clone();
+    engine.register_fn("clip_count", move |t: INT| -> ScriptResult<INT> {
+        Ok(p.borrow().track(t)?.clips.len() as INT)
+    });
+    let p = project.clone();
+    engine.register_fn("clip_name", move |t: INT, c: INT| -> ScriptResult<String> {
+        Ok(p.borrow().clip(t, c)?.name.clone())
+    });
+    let p = project.clone();
+    engine.register_fn("clip_start", move |t: INT, c: INT| -> ScriptResult<FLOAT> {
+        let project = p.borrow();
+        Ok(project.clip(t, c)?.start_sample as f64 / project.samples_per_beat)
+    });
+    let p = project.clone();
+    engine.register_fn("clip_length", move |t: INT, c: INT| -> ScriptResult<FLOAT> {
+        let project = p.borrow();
+        Ok(project.clip(t, c)?.length_samples as f64 / project.samples_per_beat)
+    });
+    let p = project.clone();
+    engine.register_fn("ppq", move |t: INT, c: INT| -> ScriptResult<INT> { Ok(p.borrow().clip(t, c)?.ppq as INT) });
+
+    let p = project.clone();
+    engine.register_fn("notes", move |t: INT, c: INT| -> ScriptResult<Array> {
+        Ok(p.borrow().clip(t, c)?.notes.iter().map(note_to_map).collect())
+    });
+    let p = project.clone();
+    engine.register_fn("set_notes", move |t: INT, c: INT, notes: Array| -> ScriptResult<()> {
+        let notes = notes.iter().map(map_to_note).collect::<ScriptResult<Vec<_>>>()?;
+        let mut project = p.borrow_mut();
+        let clip = project.clip_mut(t, c)?;
+        clip.notes.clear();
+        for note in notes {
+            clip.add_note(note);
+        }
+        Ok(())
+    });
+    let p = project.clone();
+    engine.register_fn("add_note", move |t: INT, c: INT, pitch: INT, velocity: INT, start: INT, length: INT| -> ScriptResult<()> {
+        let note = new_note(pitch, velocity, start, length)?;
+        p.borrow_mut().clip_mut(t, c)?.add_note(note);
+        Ok(())
+    });
+    let p = project.clone();
+    engine.register_fn("clear_notes", move |t: INT, c: INT| -> ScriptResult<()> {
+        p.borrow_mut().clip_mut(t, c)?.notes.clear();
+        Ok(())
+    });
+    let p = project.clone();
+    engine.register_fn("transpose", move |t: INT, c: INT, semitones: INT| -> ScriptResult<()> {
+        for note in &mut p.borrow_mut().clip_mut(t, c)?.notes {
+            note.pitch = (note.pitch as INT).saturating_add(semitones).clamp(0, 127) as u8;
+        }
+        Ok(())
+    });
+    let p = project.clone();
+    engine.register_fn("new_clip", move |t: INT, start: Dynamic, length: Dynamic| -> ScriptResult<INT> {
+        let (start, length) = (beats(&start)?, beats(&length)?);
+        if length <= 0.0 {
+            return Err("clip length must be positive".into());
+        }
+        let mut project = p.borrow_mut();
+        if !project.track(t)?.midi {
+            return Err(format!("track {t} is not a MIDI track").into());
+        }
+        let samples_per_beat = project.samples_per_beat;
+        let mut clip = MidiClip::new(ClipId(project.next_clip_id), (length * samples_per_beat) as u64);
+        clip.start_sample = (start * samples_per_beat) as u64;
+        clip.name = "Script".to_string();
+        project.next_clip_id += 1;
+
+        let track = &mut project.tracks[t as usize];
+        track.edited = true;
+        track.clips.push(clip);
+        Ok(track.clips.len() as INT - 1)
+    });
+
+    engine.register_fn("euclid", |pulses: INT, steps: INT, rotation: INT| -> Array {
+        let steps = steps.clamp(0, u8::MAX as INT) as u8;
+        let pulses = pulses.clamp(0, steps as INT) as u8;
+        let rotation = rotation.rem_euclid(steps.max(1) as INT) as u8;
+        euclidean_rhythm(steps, pulses, rotation).into_iter().map(Dynamic::from).collect()
+    });
+
+    engine
+}
+
+/// A beat position given as an integer or a float
+fn beats(value: &Dynamic) -> ScriptResult<f64> {
+    value.as_float()
+        .or_else(|_| value.as_int().map(|i| i as FLOAT))
+        .map_err(|_| "expected a number of beats".into())
+}
+
+fn new_note(pitch: INT, velocity: INT, start: INT, length: INT) -> ScriptResult<MidiNote> {
+    if !(0..=127).contains(&pitch) {
+        return Err(format!("pitch {pitch} is outside 0-127").into());
+    }
+    if start < 0 || length <= 0 {
+        return Err("notes need a start of 0 or later and a positive length".into());
+    }
+    Ok(MidiNote::new(pitch as u8, velocity.clamp(1, 127) as u8, start as u64, length as u64))
+}
+
+fn note_to_map(note: &MidiNote) -> Dynamic {
+    let mut map = Map::new();
+    map.insert("pitch".into(), (note.pitch as INT).into());
+    map.insert("velocity".into(), (note.velocity as INT).into());
+    map.insert("start".into(), (note.start_tick as INT).into());
+    map.insert("length".into(), (note.duration_ticks as INT).into());
+    map.into()
+}
+
+/// A note from a `#{pitch, velocity, start, length}` map (velocity defaults to 100)
+fn map_to_note(value: &Dynamic) -> ScriptResult<MidiNote> {
+    let map = value.clone().try_cast::<Map>().ok_or("notes must be maps of pitch, velocity, start and length")?;
+    let field = |name: &str| -> ScriptResult<INT> {
+        map.get(name)
+            .ok_or_else(|| format!("note is missing '{name}'"))?
+            .as_int()
+            .map_err(|_| format!("note '{name}' must be an integer").into())
+    };
+    let velocity = if map.contains_key("velocity") { field("velocity")? } else { 100 };
+    new_note(field("pitch")?, velocity, field("start")?, field("length")?)
+}
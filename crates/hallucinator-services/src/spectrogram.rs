@@ -0,0 +1,155 @@
+//! Spectrograms of audio clips for the clip editor's spectral view
+//!
+//! `SpectrogramLoader` runs the FFTs on a background thread. Each column is one
+//! windowed FFT, folded into bands spaced evenly in log frequency so hum at 50/60 Hz
+//! gets as much room as the top octave.
+
+use crossbeam_channel::{Receiver, Sender};
+use realfft::RealFftPlanner;
+
+/// FFT length of each column
+const FFT_SIZE: usize = 2048;
+/// Columns at most, however long the clip; longer clips get a bigger hop
+const MAX_COLUMNS: usize = 4096;
+/// Frequency bands per column
+pub const SPECTROGRAM_BANDS: usize = 256;
+/// Lowest frequency shown; the highest is Nyquist
+pub const MIN_FREQ: f32 = 20.0;
+/// Floor for band levels
+pub const FLOOR_DB: f32 = -120.0;
+
+#[derive(Debug, Clone)]
+pub struct Spectrogram {
+    pub columns: usize,
+    /// Source frames between the starts of consecutive columns
+    pub hop: usize,
+    pub sample_rate: u32,
+    /// Level in dB of each band, column by column, lowest band first
+    pub levels: Vec<f32>,
+}
+
+impl Spectrogram {
+    /// Analyse interleaved samples, mixed down to mono
+    pub fn analyse(samples: &[f32], channels: u16, sample_rate: u32) -> Self {
+        let channels = channels.max(1) as usize;
+        let mono: Vec<f32> =
+            samples.chunks(channels).map(|frame| frame.iter().sum::<f32>() / channels as f32).collect();
+        let hop = mono.len().div_ceil(MAX_COLUMNS).max(FFT_SIZE / 4);
+        let columns = mono.len().div_ceil(hop);
+
+        let fft = RealFftPlanner::<f32>::new().plan_fft_forward(FFT_SIZE);
+        let mut input = fft.make_input_vec();
+        let mut output = fft.make_output_vec();
+        let mut scratch = fft.make_scratch_vec();
+        let window: Vec<f32> = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (std::f32::consts::TAU * i as f32 / FFT_SIZE as f32).cos())
+            .collect();
+        let bands = band_bins(sample_rate);
+        // Hann window coherent gain is 0.5, so a full-scale sine reads 0 dB
+        let norm = 4.0 / FFT_SIZE as f32;
+
+        let mut levels = Vec::with_capacity(columns * SPECTROGRAM_BANDS);
+        let mut magnitudes = vec![0.0f32; output.len()];
+        for column in 0..columns {
+            // Each window is centred on its column
+            let start = (column * hop) as isize - (FFT_SIZE / 2) as isize;
+            for (i, value) in input.iter_mut().enumerate() {
+                let sample = usize::try_from(start + i as isize).ok().and_then(|idx| mono.get(idx));
+                *value = sample.copied().unwrap_or(0.0) * window[i];
+            }
+            if fft.process_with_scratch(&mut input, &mut output, &mut scratch).is_err() {
+                levels.extend(std::iter::repeat_n(FLOOR_DB, SPECTROGRAM_BANDS));
+                continue;
+            }
+            for (magnitude, value) in magnitudes.iter_mut().zip(output.iter()) {
+                *magnitude = value.norm() * norm;
+            }
+            levels.extend(bands.iter().map(|&(lo, hi)| {
+                let peak = magnitudes[lo..hi].iter().fold(0.0f32, |m, &v| m.max(v));
+                (20.0 * peak.max(1e-9).log10()).max(FLOOR_DB)
+            }));
+        }
+
+        Self { columns, hop, sample_rate, levels }
+    }
+
+    /// Level in dB of `band` in `column`
+    pub fn level(&self, column: usize, band: usize) -> f32 {
+        self.levels.get(column * SPECTROGRAM_BANDS + band).copied().unwrap_or(FLOOR_DB)
+    }
+}
+
+/// Height of `freq` on the spectrogram's frequency axis, 0 at `MIN_FREQ` and 1 at Nyquist
+pub fn frequency_position(freq: f32, sample_rate: u32) -> f32 {
+    let nyquist = sample_rate as f32 / 2.0;
+    (freq / MIN_FREQ).ln() / (nyquist / MIN_FREQ).ln()
+}
+
+/// Frequency at `position` on the spectrogram's frequency axis; the inverse of `frequency_position`
+pub fn frequency_at(position: f32, sample_rate: u32) -> f32 {
+    let nyquist = sample_rate as f32 / 2.0;
+    MIN_FREQ * (nyquist / MIN_FREQ).powf(position)
+}
+
+/// FFT bins each band takes its peak from; every band gets at least one bin
+fn band_bins(sample_rate: u32) -> Vec<(usize, usize)> {
+    let bin_width = sample_rate as f32 / FFT_SIZE as f32;
+    let last_bin = FFT_SIZE / 2;
+    (0..SPECTROGRAM_BANDS)
+        .map(|band| {
+            let lo_freq = frequency_at(band as f32 / SPECTROGRAM_BANDS as f32, sample_rate);
+            let hi_freq = frequency_at((band + 1) as f32 / SPECTROGRAM_BANDS as f32, sample_rate);
+            let lo = ((lo_freq / bin_width).round() as usize).min(last_bin);
+            let hi = ((hi_freq / bin_width).round() as usize).clamp(lo + 1, last_bin + 1);
+            (lo, hi)
+        })
+        .collect()
+}
+
+/// A clip's audio to analyse, tagged with a key the result comes back under
+struct Job {
+    key: u64,
+    samples: Vec<f32>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+/// Background thread computing spectrograms; only the latest of the requests queued
+/// while it's busy is analysed
+pub struct SpectrogramLoader {
+    requests: Sender<Job>,
+    results: Receiver<(u64, Spectrogram)>,
+}
+
+impl SpectrogramLoader {
+    /// Start the loader. The thread exits when the loader is dropped.
+    pub fn spawn() -> std::io::Result<Self> {
+        let (requests, incoming) = crossbeam_channel::unbounded::<Job>();
+        let (sender, results) = crossbeam_channel::unbounded();
+        std::thread::Builder::new()
+            .name("spectrogram".to_string())
+            .spawn(move || {
+                while let Ok(mut job) = incoming.recv() {
+                    // Skip to the newest request; the older ones are for clips no longer shown
+                    while let Ok(newer) = incoming.try_recv() {
+                        job = newer;
+                    }
+                    let spectrogram = Spectrogram::analyse(&job.samples, job.channels, job.sample_rate);
+                    if sender.send((job.key, spectrogram)).is_err() {
+                        break;
+                    }
+                }
+            })?;
+        Ok(Self { requests, results })
+    }
+
+    /// Queue interleaved samples for analysis; the result comes back from `poll` under `key`
+    pub fn request(&self, key: u64, samples: Vec<f32>, channels: u16, sample_rate: u32) {
+        let _ = self.requests.send(Job { key, samples, channels, sample_rate });
+    }
+
+    /// Spectrograms finished since the last poll
+    pub fn poll(&self) -> Vec<(u64, Spectrogram)> {
+        self.results.try_iter().collect()
+    }
+}
@@ -3,8 +3,16 @@
 use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
 
+/// Bytes of sample data read at a time between progress reports
+const READ_CHUNK: usize = 1 << 20;
+
 /// Read a WAV file to f32 samples. Returns (samples, channels, sample_rate).
 pub fn read_wav(path: &Path) -> Result<(Vec<f32>, u16, u32), String> {
+    read_wav_with_progress(path, &mut |_| {})
+}
+
+/// `read_wav`, calling `progress` with the share of the sample data read so far (0-1)
+pub fn read_wav_with_progress(path: &Path, progress: &mut dyn FnMut(f32)) -> Result<(Vec<f32>, u16, u32), String> {
     let mut f = std::fs::File::open(path).map_err(|e| format!("open: {e}"))?;
     let mut buf4 = [0u8; 4];
     let mut buf2 = [0u8; 2];
@@ -50,7 +58,12 @@ pub fn read_wav(path: &Path) -> Result<(Vec<f32>, u16, u32), String> {
 
         if &chunk_id == b"data" {
             data_bytes.resize(chunk_size as usize, 0);
-            f.read_exact(&mut data_bytes).map_err(|e| format!("data: {e}"))?;
+            let mut read = 0;
+            for chunk in data_bytes.chunks_mut(READ_CHUNK) {
+                f.read_exact(chunk).map_err(|e| format!("data: {e}"))?;
+                read += chunk.len();
+                progress(read as f32 / chunk_size as f32);
+            }
             break;
         }
 
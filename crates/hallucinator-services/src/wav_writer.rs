@@ -0,0 +1,161 @@
+//! Streaming Broadcast WAV writer for direct-to-disk recording
+//!
+//! Samples are written as 32-bit float as they arrive. The RIFF and data sizes in the
+//! header are patched about once a second, so a crash leaves a playable file holding
+//! everything up to the last update.
+
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Size of the `bext` chunk body without coding history
+const BEXT_SIZE: u32 = 602;
+/// Offset of the data chunk's size field: RIFF header, bext chunk, 16-byte fmt chunk
+const DATA_SIZE_OFFSET: u64 = 12 + 8 + BEXT_SIZE as u64 + 8 + 16 + 4;
+
+/// A WAV file being recorded
+pub struct WavWriter {
+    file: BufWriter<File>,
+    path: PathBuf,
+    channels: u16,
+    sample_rate: u32,
+    data_bytes: u64,
+    /// Data size at the last header update
+    synced_bytes: u64,
+}
+
+impl WavWriter {
+    /// Create `path` (and its folder) with a BWF header stamped with the current date and
+    /// time; `time_reference` is the recording's start position in samples.
+    pub fn create(path: &Path, sample_rate: u32, channels: u16, time_reference: u64, description: &str) -> std::io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = BufWriter::new(File::create(path)?);
+        let block_align = channels * 4;
+
+        file.write_all(b"RIFF")?;
+        file.write_all(&0u32.to_le_bytes())?;
+        file.write_all(b"WAVE")?;
+
+        let now = UtcTime::now();
+        let mut bext = Vec::with_capacity(BEXT_SIZE as usize);
+        bext.extend_from_slice(&fixed_text(description, 256));
+        bext.extend_from_slice(&fixed_text("hallucinator", 32));
+        bext.extend_from_slice(&[0; 32]); // originator reference
+        bext.extend_from_slice(format!("{:04}-{:02}-{:02}", now.year, now.month, now.day).as_bytes());
+        bext.extend_from_slice(format!("{:02}:{:02}:{:02}", now.hour, now.minute, now.second).as_bytes());
+        bext.extend_from_slice(&(time_reference as u32).to_le_bytes());
+        bext.extend_from_slice(&((time_reference >> 32) as u32).to_le_bytes());
+        bext.extend_from_slice(&1u16.to_le_bytes()); // version
+        bext.resize(BEXT_SIZE as usize, 0); // UMID, loudness, reserved
+        file.write_all(b"bext")?;
+        file.write_all(&BEXT_SIZE.to_le_bytes())?;
+        file.write_all(&bext)?;
+
+        file.write_all(b"fmt ")?;
+        file.write_all(&16u32.to_le_bytes())?;
+        file.write_all(&3u16.to_le_bytes())?; // IEEE float
+        file.write_all(&channels.to_le_bytes())?;
+        file.write_all(&sample_rate.to_le_bytes())?;
+        file.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+        file.write_all(&block_align.to_le_bytes())?;
+        file.write_all(&32u16.to_le_bytes())?;
+
+        file.write_all(b"data")?;
+        file.write_all(&0u32.to_le_bytes())?;
+
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            channels,
+            sample_rate,
+            data_bytes: 0,
+            synced_bytes: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append interleaved samples
+    pub fn write(&mut self, samples: &[f32]) -> std::io::Result<()> {
+        for sample in samples {
+            self.file.write_all(&sample.to_le_bytes())?;
+        }
+        self.data_bytes += samples.len() as u64 * 4;
+
+        let second = self.sample_rate as u64 * self.channels as u64 * 4;
+        if self.data_bytes - self.synced_bytes >= second {
+            self.update_header()?;
+        }
+        Ok(())
+    }
+
+    /// Write the current sizes into the header and flush to disk
+    fn update_header(&mut self) -> std::io::Result<()> {
+        // Sizes past 4 GiB can't be expressed; the header keeps the largest it can hold
+        let data_size = self.data_bytes.min((u32::MAX - DATA_SIZE_OFFSET as u32) as u64) as u32;
+        self.file.seek(SeekFrom::Start(4))?;
+        self.file.write_all(&(data_size + DATA_SIZE_OFFSET as u32 - 4).to_le_bytes())?;
+        self.file.seek(SeekFrom::Start(DATA_SIZE_OFFSET))?;
+        self.file.write_all(&data_size.to_le_bytes())?;
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.flush()?;
+        self.file.get_ref().sync_data()?;
+        self.synced_bytes = self.data_bytes;
+        Ok(())
+    }
+
+    /// Finalise the header and close the file, returning its path
+    pub fn finish(mut self) -> std::io::Result<PathBuf> {
+        self.update_header()?;
+        Ok(self.path)
+    }
+}
+
+/// `text` as a NUL-padded field of `len` bytes
+fn fixed_text(text: &str, len: usize) -> Vec<u8> {
+    let mut bytes: Vec<u8> = text.bytes().take(len).collect();
+    bytes.resize(len, 0);
+    bytes
+}
+
+/// A UTC calendar date and time
+struct UtcTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u64,
+    minute: u64,
+    second: u64,
+}
+
+impl UtcTime {
+    fn now() -> Self {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        // Days to civil date (Howard Hinnant's algorithm)
+        let z = (secs / 86_400) as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        let time = secs % 86_400;
+        Self { year, month, day, hour: time / 3600, minute: time / 60 % 60, second: time % 60 }
+    }
+}
+
+/// File name for a new take, from the current UTC date and time
+pub fn recording_file_name() -> String {
+    let now = UtcTime::now();
+    format!(
+        "Recording {:04}-{:02}-{:02} {:02}-{:02}-{:02}.wav",
+        now.year, now.month, now.day, now.hour, now.minute, now.second
+    )
+}
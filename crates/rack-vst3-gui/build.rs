@@ -38,6 +38,8 @@ fn main() {
             let module_linux_src = hosting_dir.join("module_linux.cpp");
             let hostclasses_src = hosting_dir.join("hostclasses.cpp");
             let plugprovider_src = hosting_dir.join("plugprovider.cpp");
+            let parameterchanges_src = hosting_dir.join("parameterchanges.cpp");
+            let eventlist_src = hosting_dir.join("eventlist.cpp");
 
             if module_src.exists() {
                 build.file(&module_src);
@@ -48,6 +50,22 @@ fn main() {
                 build.file(&module_linux_src);
                 println!("cargo:warning=Adding module_linux.cpp");
             }
+            #[cfg(target_os = "windows")]
+            {
+                let module_win32_src = hosting_dir.join("module_win32.cpp");
+                if module_win32_src.exists() {
+                    build.file(&module_win32_src);
+                    println!("cargo:warning=Adding module_win32.cpp");
+                }
+            }
+            #[cfg(target_os = "macos")]
+            {
+                let module_mac_src = hosting_dir.join("module_mac.mm");
+                if module_mac_src.exists() {
+                    build.file(&module_mac_src);
+                    println!("cargo:warning=Adding module_mac.mm");
+                }
+            }
             if hostclasses_src.exists() {
                 build.file(&hostclasses_src);
                 println!("cargo:warning=Adding hostclasses.cpp");
@@ -56,6 +74,14 @@ fn main() {
                 build.file(&plugprovider_src);
                 println!("cargo:warning=Adding plugprovider.cpp");
             }
+            if parameterchanges_src.exists() {
+                build.file(&parameterchanges_src);
+                println!("cargo:warning=Adding parameterchanges.cpp");
+            }
+            if eventlist_src.exists() {
+                build.file(&eventlist_src);
+                println!("cargo:warning=Adding eventlist.cpp");
+            }
 
             // Base sources needed for FUID, etc.
             let base_dir = sdk_path.join("base/source");
@@ -106,9 +132,21 @@ fn main() {
             println!("cargo:rustc-link-lib=dl");
         }
         #[cfg(target_os = "macos")]
-        build.define("SMTG_OS_MACOS", "1");
+        {
+            build.define("SMTG_OS_MACOS", "1");
+            // module_mac.mm is Objective-C++ and expects ARC
+            build.flag("-fobjc-arc");
+            println!("cargo:rustc-link-lib=framework=Cocoa");
+            println!("cargo:rustc-link-lib=framework=CoreFoundation");
+        }
         #[cfg(target_os = "windows")]
-        build.define("SMTG_OS_WINDOWS", "1");
+        {
+            build.define("SMTG_OS_WINDOWS", "1");
+            build.define("UNICODE", "1");
+            build.define("_UNICODE", "1");
+            println!("cargo:rustc-link-lib=ole32");
+            println!("cargo:rustc-link-lib=shell32");
+        }
 
         // Link against rack's VST3 library if available
         let lib_dir = target_dir.join("deps");
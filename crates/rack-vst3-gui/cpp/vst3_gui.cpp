@@ -9,7 +9,11 @@
  * 2. Creating the plugin's audio processor component (IComponent)
  * 3. Obtaining the edit controller (IEditController) for GUI and parameter access
  * 4. Creating and attaching the plugin's GUI view (IPlugView) to a native window
- * 5. Bidirectional parameter synchronization between host and plugin GUI
+ * 5. Bidirectional parameter synchronization between host and plugin GUI,
+ *    editor resizing in both directions (IPlugFrame / onSize), and
+ *    forwarding keyboard focus and key events the host window receives
+ * 6. Running the same component's audio processor (IAudioProcessor), so the
+ *    editor and the audio the host hears always belong to one plugin instance
  *
  * Architecture Overview:
  * ----------------------
@@ -21,23 +25,60 @@
  * to be connected via IConnectionPoint for state synchronization.
  *
  * The plugin's GUI is obtained via IEditController::createView() and then
- * attached to a native window handle (X11 Window ID on Linux).
+ * attached to a native window handle: an X11 Window ID on Linux, an HWND on
+ * Windows, an NSView on macOS.
+ *
+ * Run loops:
+ * ----------
+ * On Windows and macOS the editor is driven by the OS event loop the host
+ * already pumps on its main thread, so attach from that thread and nothing
+ * else is needed. Linux has no shared event loop: editors register their
+ * timers and file descriptors with the host's Linux::IRunLoop (offered through
+ * GuiPlugFrame), which vst3_gui_idle() services.
+ *
+ * Threading:
+ * ----------
+ * The controller and view are only touched from the host's UI thread, the
+ * processor only from the audio thread. Parameter edits cross between them
+ * through two queues:
+ *   - processorQueue: edits from the GUI (performEdit) and from the host,
+ *     delivered to the processor as IParameterChanges on the next block
+ *   - controllerQueue: host edits and processor output parameter changes,
+ *     applied to the controller by vst3_gui_idle() on the UI thread
+ * The audio thread only ever try_locks, so it never waits on the UI.
  */
 
 #include "vst3_gui.h"
 #include "public.sdk/source/vst/hosting/module.h"
 #include "public.sdk/source/vst/hosting/plugprovider.h"
 #include "public.sdk/source/vst/hosting/hostclasses.h"
+#include "public.sdk/source/vst/hosting/parameterchanges.h"
+#include "public.sdk/source/vst/hosting/eventlist.h"
 #include "public.sdk/source/common/memorystream.h"
 #include "pluginterfaces/gui/iplugview.h"
+#include "pluginterfaces/base/keycodes.h"
 #include "pluginterfaces/vst/ivsteditcontroller.h"
 #include "pluginterfaces/vst/ivstcomponent.h"
+#include "pluginterfaces/vst/ivstaudioprocessor.h"
+#include "pluginterfaces/vst/ivstevents.h"
 #include "pluginterfaces/vst/ivstmessage.h"
+#include "pluginterfaces/vst/ivstmidicontrollers.h"
+#include "pluginterfaces/vst/ivstunits.h"
 
+#include <algorithm>
+#include <atomic>
+#include <map>
 #include <string>
+#include <cstdio>
 #include <cstring>
 #include <vector>
 #include <mutex>
+#include <chrono>
+#include <deque>
+
+#if SMTG_OS_LINUX
+#include <poll.h>
+#endif
 
 using namespace VST3;
 using namespace Steinberg;
@@ -46,6 +87,7 @@ using namespace Steinberg::Vst;
 // Forward declarations
 struct Vst3GuiHandle;
 class GuiComponentHandler;
+class GuiPlugFrame;
 
 /**
  * Parse a VST3 UID string into the SDK's UID type.
@@ -106,13 +148,13 @@ public:
     // IComponentHandler interface - Parameter change notifications
 
     /** Called when user starts editing a parameter (mouse down on knob/slider) */
-    tresult PLUGIN_API beginEdit(ParamID) override { return kResultOk; }
+    tresult PLUGIN_API beginEdit(ParamID id) override;
 
     /** Called repeatedly while user is adjusting a parameter value */
     tresult PLUGIN_API performEdit(ParamID id, ParamValue valueNormalized) override;
 
     /** Called when user finishes editing a parameter (mouse up) */
-    tresult PLUGIN_API endEdit(ParamID) override { return kResultOk; }
+    tresult PLUGIN_API endEdit(ParamID id) override;
 
     /**
      * Called when plugin needs the host to restart/reconfigure something.
@@ -125,6 +167,159 @@ private:
     std::atomic<uint32> refCount_;   // COM-style reference count
 };
 
+/**
+ * GuiPlugFrame - Lets the plugin's editor ask the host to resize its window
+ *
+ * Resizable editors (e.g. Vital, Surge) call resizeView() when their size
+ * changes from inside the UI. The view is resized straight away and the new
+ * size is left for the Rust side to pick up with vst3_gui_take_resize_request()
+ * and apply to the parent window.
+ *
+ * On Linux the frame is also the editor's run loop: timers and file
+ * descriptor handlers are kept here and serviced by run_loop_tick(), which
+ * vst3_gui_idle() calls on the UI thread.
+ */
+class GuiPlugFrame : public IPlugFrame
+#if SMTG_OS_LINUX
+    , public Linux::IRunLoop
+#endif
+{
+public:
+    explicit GuiPlugFrame(Vst3GuiHandle* owner) : owner_(owner), refCount_(1) {}
+    virtual ~GuiPlugFrame() = default;
+
+    tresult PLUGIN_API queryInterface(const TUID iid, void** obj) override {
+        if (FUnknownPrivate::iidEqual(iid, IPlugFrame::iid) ||
+            FUnknownPrivate::iidEqual(iid, FUnknown::iid)) {
+            *obj = static_cast<IPlugFrame*>(this);
+            addRef();
+            return kResultOk;
+        }
+#if SMTG_OS_LINUX
+        if (FUnknownPrivate::iidEqual(iid, Linux::IRunLoop::iid)) {
+            *obj = static_cast<Linux::IRunLoop*>(this);
+            addRef();
+            return kResultOk;
+        }
+#endif
+        *obj = nullptr;
+        return kNoInterface;
+    }
+
+    uint32 PLUGIN_API addRef() override { return ++refCount_; }
+
+    uint32 PLUGIN_API release() override {
+        auto count = --refCount_;
+        if (count == 0) delete this;
+        return count;
+    }
+
+    /** Called by the editor (UI thread) when it wants a new size */
+    tresult PLUGIN_API resizeView(IPlugView* view, ViewRect* newSize) override;
+
+#if SMTG_OS_LINUX
+    tresult PLUGIN_API registerEventHandler(Linux::IEventHandler* handler, Linux::FileDescriptor fd) override {
+        if (!handler) return kInvalidArgument;
+        eventHandlers_.push_back({handler, fd});
+        return kResultTrue;
+    }
+
+    tresult PLUGIN_API unregisterEventHandler(Linux::IEventHandler* handler) override {
+        auto removed = std::remove_if(eventHandlers_.begin(), eventHandlers_.end(),
+                                      [&](const EventHandler& e) { return e.handler == handler; });
+        if (removed == eventHandlers_.end()) return kResultFalse;
+        eventHandlers_.erase(removed, eventHandlers_.end());
+        return kResultTrue;
+    }
+
+    tresult PLUGIN_API registerTimer(Linux::ITimerHandler* handler, Linux::TimerInterval milliseconds) override {
+        if (!handler) return kInvalidArgument;
+        auto interval = std::chrono::milliseconds(std::max<Linux::TimerInterval>(milliseconds, 1));
+        timers_.push_back({handler, interval, Clock::now() + interval});
+        return kResultTrue;
+    }
+
+    tresult PLUGIN_API unregisterTimer(Linux::ITimerHandler* handler) override {
+        auto removed = std::remove_if(timers_.begin(), timers_.end(),
+                                      [&](const Timer& t) { return t.handler == handler; });
+        if (removed == timers_.end()) return kResultFalse;
+        timers_.erase(removed, timers_.end());
+        return kResultTrue;
+    }
+#endif
+
+    /**
+     * Dispatch ready file descriptors and due timers (UI thread).
+     * Handlers may (un)register others while running, so work from copies.
+     */
+    void run_loop_tick() {
+#if SMTG_OS_LINUX
+        if (!eventHandlers_.empty()) {
+            auto handlers = eventHandlers_;
+            std::vector<pollfd> fds;
+            fds.reserve(handlers.size());
+            for (const auto& e : handlers) fds.push_back({e.fd, POLLIN, 0});
+            if (poll(fds.data(), fds.size(), 0) > 0) {
+                for (size_t i = 0; i < fds.size(); ++i) {
+                    if ((fds[i].revents & (POLLIN | POLLERR | POLLHUP)) && is_registered(handlers[i].handler)) {
+                        handlers[i].handler->onFDIsSet(handlers[i].fd);
+                    }
+                }
+            }
+        }
+
+        auto now = Clock::now();
+        auto timers = timers_;
+        for (auto& t : timers) {
+            if (now < t.due) continue;
+            // Reschedule before firing; skip timers an earlier handler unregistered
+            bool live = false;
+            for (auto& registered : timers_) {
+                if (registered.handler != t.handler) continue;
+                registered.due = now + registered.interval;
+                live = true;
+            }
+            if (live) t.handler->onTimer();
+        }
+#endif
+    }
+
+    /** Drop all run loop registrations (the view is gone) */
+    void clear_run_loop() {
+#if SMTG_OS_LINUX
+        eventHandlers_.clear();
+        timers_.clear();
+#endif
+    }
+
+private:
+    Vst3GuiHandle* owner_;
+    std::atomic<uint32> refCount_;
+
+#if SMTG_OS_LINUX
+    using Clock = std::chrono::steady_clock;
+
+    struct EventHandler {
+        IPtr<Linux::IEventHandler> handler;
+        Linux::FileDescriptor fd;
+    };
+
+    struct Timer {
+        IPtr<Linux::ITimerHandler> handler;
+        std::chrono::milliseconds interval;
+        Clock::time_point due;
+    };
+
+    std::vector<EventHandler> eventHandlers_;
+    std::vector<Timer> timers_;
+
+    bool is_registered(Linux::IEventHandler* handler) const {
+        return std::any_of(eventHandlers_.begin(), eventHandlers_.end(),
+                           [&](const EventHandler& e) { return e.handler == handler; });
+    }
+#endif
+};
+
 /**
  * Vst3GuiHandle - Main state container for a loaded VST3 plugin GUI
  *
@@ -151,9 +346,15 @@ struct Vst3GuiHandle {
      */
     IPtr<IEditController> controller;
 
+    /**
+     * The component's audio processor interface.
+     * Set up by vst3_gui_setup_processing(); only used from the audio thread.
+     */
+    IPtr<IAudioProcessor> processor;
+
     /**
      * The plugin's GUI view.
-     * Can be attached to a native window for display.
+     * Created on demand when the editor is opened, released when it is detached.
      */
     IPtr<IPlugView> view;
 
@@ -162,23 +363,116 @@ struct Vst3GuiHandle {
      */
     IPtr<GuiComponentHandler> componentHandler;
 
+    /** Our frame, through which the view requests resizes */
+    IPtr<GuiPlugFrame> plugFrame;
+
+    /** Size requested by the editor and not yet applied to the host window (UI thread only) */
+    bool resizePending = false;
+    int32 resizeWidth = 0;
+    int32 resizeHeight = 0;
+
     /** Whether the view is currently attached to a window */
     bool attached = false;
 
     /** Whether component and controller are separate objects */
     bool separateController = false;
 
+    /** Whether the component is active and processing */
+    bool processing = false;
+
+    /** Channel count of the main audio input bus (0 if the plugin has none) */
+    int32 inputChannels = 0;
+
+    /** Channel counts of every audio output bus, main bus first */
+    std::vector<int32> outputChannels;
+
+    /** Auxiliary output buses the host wants rendered, by bus index (applied at setup) */
+    std::vector<bool> auxOutputsEnabled;
+
+    /** Parameter IDs by index, cached so host edits never query the controller off the UI thread */
+    std::vector<ParamID> paramIds;
+
+    /**
+     * Parameter each MIDI controller drives, from the controller's IMidiMapping:
+     * 16 channels of kCountCtrlNumber controllers (CC 0-127, aftertouch, pitch
+     * bend), kNoParamId where unmapped. Cached for the audio thread.
+     */
+    std::vector<ParamID> midiMap;
+
+    /** The program-change parameter (index into paramIds, -1 if the plugin has none) */
+    int32 programParamIndex = -1;
+
+    /** Program names, in the order the program-change parameter steps through them */
+    std::vector<std::string> programNames;
+
     /**
-     * Queue of parameter changes from the GUI.
-     * Protected by mutex since GUI callbacks come from UI thread
-     * but Rust may poll from another thread.
+     * Edits from the GUI (gesture starts, value changes, gesture ends) in the
+     * order they happened, taken by vst3_gui_take_edits(). Protected by mutex
+     * since GUI callbacks come from UI thread but Rust may poll from another thread.
      */
-    std::mutex paramChangesMutex;
-    std::vector<std::pair<ParamID, ParamValue>> paramChanges;
+    std::mutex editsMutex;
+    std::deque<Vst3GuiEdit> edits;
+
+    /** Edits waiting to be delivered to the processor on the next block */
+    std::mutex processorQueueMutex;
+    std::vector<std::pair<ParamID, ParamValue>> processorQueue;
+
+    /** Values the controller must be told about on the UI thread (latest value wins) */
+    std::mutex controllerQueueMutex;
+    std::map<ParamID, ParamValue> controllerQueue;
+
+    /** Audio-thread scratch: per-block parameter changes, events and bus buffers */
+    ParameterChanges inputChanges;
+    ParameterChanges outputChanges;
+    EventList events{512};
+    std::vector<float> scratch;
+    std::vector<Sample32*> inputPtrs;
+    std::vector<std::vector<Sample32*>> outputPtrs;
+    std::vector<AudioBusBuffers> outputBuses;
+
+    /**
+     * Record an editor edit for the host. If nobody takes them the queue stops
+     * growing at kMaxQueuedEdits, dropping value changes but never gesture
+     * boundaries, so every begin still gets its end.
+     */
+    void queue_edit(int kind, ParamID id, ParamValue value) {
+        static constexpr size_t kMaxQueuedEdits = 4096;
+        auto it = std::find(paramIds.begin(), paramIds.end(), id);
+        if (it == paramIds.end()) return;
+        std::lock_guard<std::mutex> lock(editsMutex);
+        if (kind == VST3_GUI_EDIT_PERFORM && edits.size() >= kMaxQueuedEdits) return;
+        edits.push_back({kind, static_cast<int>(it - paramIds.begin()), value});
+    }
+
+    /** Queue a parameter value for the processor */
+    void queue_for_processor(ParamID id, ParamValue value) {
+        std::lock_guard<std::mutex> lock(processorQueueMutex);
+        processorQueue.push_back({id, value});
+    }
+
+    /** Queue a parameter value for the controller */
+    void queue_for_controller(ParamID id, ParamValue value) {
+        std::lock_guard<std::mutex> lock(controllerQueueMutex);
+        controllerQueue[id] = value;
+    }
+
+    /** Deactivate the processor if it is running */
+    void stop_processing() {
+        if (!processing) return;
+        if (processor) processor->setProcessing(false);
+        component->setActive(false);
+        processing = false;
+    }
 
     /** Clean up all plugin resources in proper order */
     void cleanup() {
+        if (component) {
+            stop_processing();
+        }
+        processor = nullptr;
+        if (view) view->setFrame(nullptr);
         view = nullptr;  // Release view first
+        if (plugFrame) plugFrame->clear_run_loop();
         if (controller && separateController) {
             controller->terminate();  // Only terminate if separate
         }
@@ -199,12 +493,41 @@ struct Vst3GuiHandle {
 tresult PLUGIN_API GuiComponentHandler::performEdit(ParamID id, ParamValue valueNormalized) {
     if (!owner_) return kResultOk;
 
-    std::lock_guard<std::mutex> lock(owner_->paramChangesMutex);
-    owner_->paramChanges.push_back({id, valueNormalized});
-    LOG_DEBUG("performEdit: param %u = %f", id, valueNormalized);
+    owner_->queue_edit(VST3_GUI_EDIT_PERFORM, id, valueNormalized);
+    // The editor and processor share this instance: forward the edit to the audio side
+    owner_->queue_for_processor(id, valueNormalized);
+    return kResultOk;
+}
+
+/**
+ * Gesture boundaries are only recorded: they tell the host when the user grabs
+ * and lets go of a control, so automation can tell touches from other changes.
+ */
+tresult PLUGIN_API GuiComponentHandler::beginEdit(ParamID id) {
+    if (owner_) owner_->queue_edit(VST3_GUI_EDIT_BEGIN, id, 0.0);
+    return kResultOk;
+}
+
+tresult PLUGIN_API GuiComponentHandler::endEdit(ParamID id) {
+    if (owner_) owner_->queue_edit(VST3_GUI_EDIT_END, id, 0.0);
     return kResultOk;
 }
 
+/**
+ * Handle a resize request from the plugin editor.
+ * The view is told its new size immediately; the host window follows when
+ * Rust polls vst3_gui_take_resize_request().
+ */
+tresult PLUGIN_API GuiPlugFrame::resizeView(IPlugView* view, ViewRect* newSize) {
+    if (!owner_ || !view || !newSize) return kInvalidArgument;
+
+    owner_->resizeWidth = newSize->getWidth();
+    owner_->resizeHeight = newSize->getHeight();
+    owner_->resizePending = true;
+    view->onSize(newSize);
+    return kResultTrue;
+}
+
 // ============================================================================
 // Helper functions for plugin initialization steps
 // ============================================================================
@@ -334,21 +657,52 @@ static void register_handler(Vst3GuiHandle* handle) {
     auto result = handle->controller->setComponentHandler(handle->componentHandler);
     LOG_DEBUG("vst3_gui_create: component handler %s",
               result == kResultOk ? "registered" : "registration FAILED");
+
+    // Cache parameter IDs so host edits can be queued from any thread
+    int32 count = handle->controller->getParameterCount();
+    handle->paramIds.reserve(count);
+    for (int32 i = 0; i < count; ++i) {
+        ParameterInfo info;
+        handle->paramIds.push_back(
+            handle->controller->getParameterInfo(i, info) == kResultOk ? info.id : kNoParamId);
+    }
+    handle->inputChanges.setMaxParameters(count);
+    handle->outputChanges.setMaxParameters(count);
+
+    // VST3 has no controller events; plugins bind CC, aftertouch and pitch bend to parameters
+    handle->midiMap.assign(16 * kCountCtrlNumber, kNoParamId);
+    if (FUnknownPtr<IMidiMapping> mapping(handle->controller); mapping) {
+        for (int16 channel = 0; channel < 16; ++channel) {
+            for (CtrlNumber ctrl = 0; ctrl < kCountCtrlNumber; ++ctrl) {
+                ParamID id = kNoParamId;
+                if (mapping->getMidiControllerAssignment(0, channel, ctrl, id) == kResultOk) {
+                    handle->midiMap[channel * kCountCtrlNumber + ctrl] = id;
+                }
+            }
+        }
+    }
 }
 
 /**
- * Step 6: Create the plugin's GUI view.
+ * Create the plugin's GUI view if it doesn't exist yet.
  *
  * The view is created but not yet attached to any window.
  * ViewType::kEditor requests the main editor view (vs. an aux view).
- * Some plugins don't have GUIs - they return nullptr here.
+ * Some plugins don't have GUIs - they return nullptr here, which doesn't
+ * prevent the instance from processing audio.
  */
-static bool create_view(Vst3GuiHandle* handle) {
+static bool ensure_view(Vst3GuiHandle* handle) {
+    if (handle->view) return true;
     handle->view = handle->controller->createView(ViewType::kEditor);
     if (!handle->view) {
         LOG_DEBUG("vst3_gui_create: failed to create view (no GUI support?)");
         return false;
     }
+    if (!handle->plugFrame) {
+        handle->plugFrame = owned(new GuiPlugFrame(handle));
+    }
+    handle->view->setFrame(handle->plugFrame);
+    handle->resizePending = false;
     LOG_DEBUG("vst3_gui_create: view created successfully!");
     return true;
 }
@@ -359,6 +713,156 @@ static bool create_view(Vst3GuiHandle* handle) {
 
 extern "C" {
 
+/**
+ * Activate the main audio buses (stereo where the plugin accepts it), the
+ * auxiliary outputs the host enabled and the main event input, and record the
+ * resulting channel counts.
+ */
+static void activate_buses(Vst3GuiHandle* handle) {
+    auto& component = handle->component;
+    int32 numInputs = component->getBusCount(kAudio, kInput);
+    int32 numOutputs = component->getBusCount(kAudio, kOutput);
+
+    // Ask for stereo main buses; plugins may refuse and keep their own layout
+    std::vector<SpeakerArrangement> inputArr(numInputs, SpeakerArr::kStereo);
+    std::vector<SpeakerArrangement> outputArr(numOutputs, SpeakerArr::kStereo);
+    handle->processor->setBusArrangements(inputArr.data(), numInputs, outputArr.data(), numOutputs);
+
+    BusInfo info;
+    handle->inputChannels = 0;
+    if (numInputs > 0 && component->getBusInfo(kAudio, kInput, 0, info) == kResultOk) {
+        component->activateBus(kAudio, kInput, 0, true);
+        handle->inputChannels = info.channelCount;
+    }
+    handle->outputChannels.clear();
+    for (int32 bus = 0; bus < numOutputs; ++bus) {
+        bool enabled = bus == 0 || (bus < static_cast<int32>(handle->auxOutputsEnabled.size()) &&
+                                    handle->auxOutputsEnabled[bus]);
+        component->activateBus(kAudio, kOutput, bus, enabled);
+        bool known = component->getBusInfo(kAudio, kOutput, bus, info) == kResultOk;
+        handle->outputChannels.push_back(known ? info.channelCount : 0);
+    }
+    if (component->getBusCount(kEvent, kInput) > 0) {
+        component->activateBus(kEvent, kInput, 0, true);
+    }
+}
+
+/**
+ * Copy a VST3 UTF-16 string into a NUL-terminated UTF-8 buffer.
+ * Output is truncated on a character boundary if the buffer is too small;
+ * unpaired surrogates become U+FFFD.
+ */
+static void copy_string128(const String128 src, char* dst, int dst_len) {
+    if (!dst || dst_len <= 0) return;
+    int out = 0;
+    for (int i = 0; i < 128 && src[i] != 0; ++i) {
+        uint32_t cp = static_cast<uint16_t>(src[i]);
+        if (cp >= 0xD800 && cp <= 0xDBFF && i + 1 < 128) {
+            uint32_t low = static_cast<uint16_t>(src[i + 1]);
+            if (low >= 0xDC00 && low <= 0xDFFF) {
+                cp = 0x10000 + ((cp - 0xD800) << 10) + (low - 0xDC00);
+                ++i;
+            } else {
+                cp = 0xFFFD;
+            }
+        } else if (cp >= 0xD800 && cp <= 0xDFFF) {
+            cp = 0xFFFD;
+        }
+
+        char bytes[4];
+        int n = 0;
+        if (cp < 0x80) {
+            bytes[n++] = static_cast<char>(cp);
+        } else if (cp < 0x800) {
+            bytes[n++] = static_cast<char>(0xC0 | (cp >> 6));
+            bytes[n++] = static_cast<char>(0x80 | (cp & 0x3F));
+        } else if (cp < 0x10000) {
+            bytes[n++] = static_cast<char>(0xE0 | (cp >> 12));
+            bytes[n++] = static_cast<char>(0x80 | ((cp >> 6) & 0x3F));
+            bytes[n++] = static_cast<char>(0x80 | (cp & 0x3F));
+        } else {
+            bytes[n++] = static_cast<char>(0xF0 | (cp >> 18));
+            bytes[n++] = static_cast<char>(0x80 | ((cp >> 12) & 0x3F));
+            bytes[n++] = static_cast<char>(0x80 | ((cp >> 6) & 0x3F));
+            bytes[n++] = static_cast<char>(0x80 | (cp & 0x3F));
+        }
+        if (out + n > dst_len - 1) break;
+        std::memcpy(dst + out, bytes, n);
+        out += n;
+    }
+    dst[out] = '\0';
+}
+
+/**
+ * Step 6: Cache the plugin's program list.
+ *
+ * Programs are selected through the parameter flagged kIsProgramChange
+ * (preferring the root unit's). Names come from the unit's program list when
+ * the controller implements IUnitInfo, otherwise from the parameter's step
+ * display strings.
+ */
+static void cache_programs(Vst3GuiHandle* handle) {
+    auto& controller = handle->controller;
+    UnitID programUnit = kRootUnitId;
+    for (int32 i = 0; i < static_cast<int32>(handle->paramIds.size()); ++i) {
+        ParameterInfo info;
+        if (controller->getParameterInfo(i, info) != kResultOk) continue;
+        if (!(info.flags & ParameterInfo::kIsProgramChange)) continue;
+        if (handle->programParamIndex < 0 || info.unitId == kRootUnitId) {
+            handle->programParamIndex = i;
+            programUnit = info.unitId;
+        }
+        if (info.unitId == kRootUnitId) break;
+    }
+    if (handle->programParamIndex < 0) {
+        LOG_DEBUG("vst3_gui_create: no program-change parameter");
+        return;
+    }
+
+    char buffer[256];
+    FUnknownPtr<IUnitInfo> unitInfo(controller);
+    if (unitInfo) {
+        ProgramListID listId = kNoProgramListId;
+        for (int32 i = 0; i < unitInfo->getUnitCount(); ++i) {
+            UnitInfo unit;
+            if (unitInfo->getUnitInfo(i, unit) == kResultOk && unit.id == programUnit) {
+                listId = unit.programListId;
+                break;
+            }
+        }
+        for (int32 i = 0; listId != kNoProgramListId && i < unitInfo->getProgramListCount(); ++i) {
+            ProgramListInfo list;
+            if (unitInfo->getProgramListInfo(i, list) != kResultOk || list.id != listId) continue;
+            for (int32 p = 0; p < list.programCount; ++p) {
+                String128 name;
+                if (unitInfo->getProgramName(listId, p, name) == kResultOk) {
+                    copy_string128(name, buffer, sizeof(buffer));
+                } else {
+                    std::snprintf(buffer, sizeof(buffer), "Program %d", p + 1);
+                }
+                handle->programNames.push_back(buffer);
+            }
+            break;
+        }
+    }
+
+    if (handle->programNames.empty()) {
+        ParameterInfo info;
+        controller->getParameterInfo(handle->programParamIndex, info);
+        for (int32 step = 0; step <= info.stepCount && info.stepCount > 0; ++step) {
+            String128 name;
+            ParamValue value = static_cast<double>(step) / info.stepCount;
+            if (controller->getParamStringByValue(info.id, value, name) == kResultOk) {
+                copy_string128(name, buffer, sizeof(buffer));
+            } else {
+                std::snprintf(buffer, sizeof(buffer), "Program %d", step + 1);
+            }
+            handle->programNames.push_back(buffer);
+        }
+    }
+    LOG_DEBUG("vst3_gui_create: %zu programs", handle->programNames.size());
+}
+
 /**
  * Create a VST3 GUI handle for the specified plugin.
  *
@@ -372,7 +876,10 @@ extern "C" {
  * 3. Get the edit controller
  * 4. Connect and sync component/controller if separate
  * 5. Register our parameter change handler
- * 6. Create the GUI view (not yet attached)
+ * 6. Cache the program list
+ *
+ * The GUI view is created later, when the editor is first opened, so plugins
+ * without an editor can still be used for processing.
  */
 Vst3GuiHandle* vst3_gui_create(const char* path, const char* uid) {
     if (!path || !uid) {
@@ -416,12 +923,7 @@ Vst3GuiHandle* vst3_gui_create(const char* path, const char* uid) {
 
     connect_and_sync(handle);
     register_handler(handle);
-
-    if (!create_view(handle)) {
-        handle->cleanup();
-        delete handle;
-        return nullptr;
-    }
+    cache_programs(handle);
 
     return handle;
 }
@@ -437,9 +939,10 @@ Vst3GuiHandle* vst3_gui_create(const char* path, const char* uid) {
  * If the plugin doesn't report a size, defaults to 800x600.
  */
 int vst3_gui_get_size(Vst3GuiHandle* handle, int* width, int* height) {
-    if (!handle || !handle->view || !width || !height) {
+    if (!handle || !width || !height) {
         return VST3_GUI_ERROR_INVALID_PARAM;
     }
+    if (!ensure_view(handle)) return VST3_GUI_ERROR_NO_VIEW;
 
     ViewRect rect;
     if (handle->view->getSize(&rect) != kResultOk) {
@@ -455,31 +958,240 @@ int vst3_gui_get_size(Vst3GuiHandle* handle, int* width, int* height) {
 }
 
 /**
- * Attach the plugin GUI to an X11 window.
- *
- * @param handle    Plugin handle
- * @param window_id X11 Window ID to embed the plugin GUI into
- * @return VST3_GUI_OK on success, error code on failure
+ * Attach the editor view to a host window of the given platform type.
  *
  * The window should be created by the host (Rust side) with appropriate
  * size before calling this. The plugin will render into this window.
  */
-int vst3_gui_attach_x11(Vst3GuiHandle* handle, uint32_t window_id) {
-    if (!handle || !handle->view) return VST3_GUI_ERROR_INVALID_PARAM;
+static int attach_view(Vst3GuiHandle* handle, void* parent, FIDString platform_type) {
+    if (!handle || !parent) return VST3_GUI_ERROR_INVALID_PARAM;
     if (handle->attached) return VST3_GUI_OK;  // Already attached
+    if (!ensure_view(handle)) return VST3_GUI_ERROR_NO_VIEW;
+
+    // Bring the controller up to date before the editor first draws
+    vst3_gui_idle(handle);
 
-    // Check if plugin supports X11 embedding
-    if (handle->view->isPlatformTypeSupported(kPlatformTypeX11EmbedWindowID) != kResultTrue) {
+    if (handle->view->isPlatformTypeSupported(platform_type) != kResultTrue) {
+        LOG_DEBUG("attach_view: platform type %s not supported by the editor", platform_type);
+        return VST3_GUI_ERROR_ATTACH_FAILED;
+    }
+    if (handle->view->attached(parent, platform_type) != kResultOk) {
         return VST3_GUI_ERROR_ATTACH_FAILED;
     }
 
+    handle->attached = true;
+    return VST3_GUI_OK;
+}
+
+/**
+ * Attach the plugin GUI to an X11 window.
+ *
+ * @param handle    Plugin handle
+ * @param window_id X11 Window ID to embed the plugin GUI into
+ * @return VST3_GUI_OK on success, error code on failure
+ */
+int vst3_gui_attach_x11(Vst3GuiHandle* handle, uint32_t window_id) {
     // Cast window ID to void* as expected by VST3 API
     void* parent = reinterpret_cast<void*>(static_cast<uintptr_t>(window_id));
-    if (handle->view->attached(parent, kPlatformTypeX11EmbedWindowID) != kResultOk) {
-        return VST3_GUI_ERROR_ATTACH_FAILED;
+    return attach_view(handle, parent, kPlatformTypeX11EmbedWindowID);
+}
+
+/**
+ * Attach the plugin GUI to a Win32 window.
+ *
+ * @param handle Plugin handle
+ * @param hwnd   HWND of the child window to embed into
+ * @return VST3_GUI_OK on success, error code on failure
+ *
+ * Call from the thread that owns the window (the one pumping its messages).
+ */
+int vst3_gui_attach_hwnd(Vst3GuiHandle* handle, void* hwnd) {
+    return attach_view(handle, hwnd, kPlatformTypeHWND);
+}
+
+/**
+ * Attach the plugin GUI to a Cocoa view.
+ *
+ * @param handle  Plugin handle
+ * @param ns_view NSView* to add the editor to as a subview
+ * @return VST3_GUI_OK on success, error code on failure
+ *
+ * Call from the main thread; AppKit views may not be touched elsewhere.
+ */
+int vst3_gui_attach_nsview(Vst3GuiHandle* handle, void* ns_view) {
+    return attach_view(handle, ns_view, kPlatformTypeNSView);
+}
+
+/**
+ * SDK virtual key codes by host key code (Vst3GuiKey), so the Rust side never
+ * depends on the numeric values in keycodes.h.
+ */
+static const int16 kVirtualKeys[] = {
+    0,
+    KEY_BACK,
+    KEY_TAB,
+    KEY_CLEAR,
+    KEY_RETURN,
+    KEY_PAUSE,
+    KEY_ESCAPE,
+    KEY_SPACE,
+    KEY_END,
+    KEY_HOME,
+    KEY_LEFT,
+    KEY_UP,
+    KEY_RIGHT,
+    KEY_DOWN,
+    KEY_PAGEUP,
+    KEY_PAGEDOWN,
+    KEY_SELECT,
+    KEY_PRINT,
+    KEY_ENTER,
+    KEY_INSERT,
+    KEY_DELETE,
+    KEY_HELP,
+    KEY_NUMPAD0,
+    KEY_NUMPAD1,
+    KEY_NUMPAD2,
+    KEY_NUMPAD3,
+    KEY_NUMPAD4,
+    KEY_NUMPAD5,
+    KEY_NUMPAD6,
+    KEY_NUMPAD7,
+    KEY_NUMPAD8,
+    KEY_NUMPAD9,
+    KEY_MULTIPLY,
+    KEY_ADD,
+    KEY_SEPARATOR,
+    KEY_SUBTRACT,
+    KEY_DECIMAL,
+    KEY_DIVIDE,
+    KEY_F1,
+    KEY_F2,
+    KEY_F3,
+    KEY_F4,
+    KEY_F5,
+    KEY_F6,
+    KEY_F7,
+    KEY_F8,
+    KEY_F9,
+    KEY_F10,
+    KEY_F11,
+    KEY_F12,
+    KEY_NUMLOCK,
+    KEY_SCROLL,
+    KEY_SHIFT,
+    KEY_CONTROL,
+    KEY_ALT,
+    KEY_CONTEXTMENU,
+};
+
+/**
+ * Forward a key event from the host window to the editor.
+ *
+ * @param key_down  Non-zero for a press, zero for a release
+ * @param character Unicode code point the key produces (0 for none); code
+ *                  points outside the BMP are dropped, as VST3 keys are char16
+ * @param key       Vst3GuiKey code for non-character keys (0 for none)
+ * @param modifiers VST3_GUI_MOD_* flags
+ * @return 1 if the editor handled the key, 0 if the host should, negative on error
+ *
+ * Used for keys that reach the host window rather than the editor's own
+ * child window; call from the UI thread.
+ */
+int vst3_gui_key_event(Vst3GuiHandle* handle, int key_down, uint32_t character, int key, int modifiers) {
+    if (!handle) return VST3_GUI_ERROR_INVALID_PARAM;
+    if (!handle->view || !handle->attached) return VST3_GUI_ERROR_NO_VIEW;
+
+    char16 ch = character <= 0xFFFF ? static_cast<char16>(character) : 0;
+    int16 virtualKey = 0;
+    if (key > 0 && key < static_cast<int>(sizeof(kVirtualKeys) / sizeof(kVirtualKeys[0]))) {
+        virtualKey = kVirtualKeys[key];
     }
+    int16 keyModifiers = 0;
+    if (modifiers & VST3_GUI_MOD_SHIFT) keyModifiers |= kShiftKey;
+    if (modifiers & VST3_GUI_MOD_ALT) keyModifiers |= kAlternateKey;
+    if (modifiers & VST3_GUI_MOD_COMMAND) keyModifiers |= kCommandKey;
+    if (modifiers & VST3_GUI_MOD_CONTROL) keyModifiers |= kControlKey;
+
+    tresult result = key_down ? handle->view->onKeyDown(ch, virtualKey, keyModifiers)
+                              : handle->view->onKeyUp(ch, virtualKey, keyModifiers);
+    return result == kResultTrue ? 1 : 0;
+}
 
-    handle->attached = true;
+/**
+ * Tell the editor whether its window has keyboard focus (UI thread).
+ */
+void vst3_gui_set_focus(Vst3GuiHandle* handle, int focused) {
+    if (!handle || !handle->view || !handle->attached) return;
+    handle->view->onFocus(focused ? true : false);
+}
+
+/**
+ * Whether the attached editor can be resized by the user.
+ *
+ * @return 1 if resizable, 0 if fixed size or there is no view
+ */
+int vst3_gui_can_resize(Vst3GuiHandle* handle) {
+    if (!handle || !handle->view) return 0;
+    return handle->view->canResize() == kResultTrue ? 1 : 0;
+}
+
+/**
+ * Take queued editor edits, oldest first.
+ *
+ * @param edits     Caller-allocated array
+ * @param max_edits Its length
+ * @return Number of edits copied; the rest stay queued
+ */
+int vst3_gui_take_edits(Vst3GuiHandle* handle, Vst3GuiEdit* edits, int max_edits) {
+    if (!handle || !edits || max_edits <= 0) return 0;
+    std::lock_guard<std::mutex> lock(handle->editsMutex);
+    int count = 0;
+    while (count < max_edits && !handle->edits.empty()) {
+        edits[count++] = handle->edits.front();
+        handle->edits.pop_front();
+    }
+    return count;
+}
+
+/**
+ * Take the size the editor last asked for through IPlugFrame::resizeView.
+ *
+ * @param width  Output: requested width in pixels
+ * @param height Output: requested height in pixels
+ * @return 1 if a resize was pending (and is now cleared), 0 otherwise
+ *
+ * Poll from the UI thread and resize the host window to match.
+ */
+int vst3_gui_take_resize_request(Vst3GuiHandle* handle, int* width, int* height) {
+    if (!handle || !width || !height || !handle->resizePending) return 0;
+    *width = handle->resizeWidth;
+    *height = handle->resizeHeight;
+    handle->resizePending = false;
+    return 1;
+}
+
+/**
+ * Resize the editor after the host window changed size (e.g. the user dragged its edge).
+ *
+ * @param width  In: new window width; out: width the editor accepted
+ * @param height In: new window height; out: height the editor accepted
+ * @return VST3_GUI_OK on success
+ *
+ * The size is passed through checkSizeConstraint first, so the host can
+ * snap its window to the size the plugin actually supports.
+ */
+int vst3_gui_set_size(Vst3GuiHandle* handle, int* width, int* height) {
+    if (!handle || !width || !height) return VST3_GUI_ERROR_INVALID_PARAM;
+    if (!handle->view || !handle->attached) return VST3_GUI_ERROR_NO_VIEW;
+
+    ViewRect rect(0, 0, *width, *height);
+    handle->view->checkSizeConstraint(&rect);
+    if (handle->view->onSize(&rect) != kResultOk) {
+        return VST3_GUI_ERROR_GENERIC;
+    }
+    *width = rect.getWidth();
+    *height = rect.getHeight();
     return VST3_GUI_OK;
 }
 
@@ -487,11 +1199,16 @@ int vst3_gui_attach_x11(Vst3GuiHandle* handle, uint32_t window_id) {
  * Detach the plugin GUI from its window.
  *
  * Should be called before destroying the window, or when hiding the plugin GUI.
+ * The view is released; the next attach creates a fresh one. The plugin
+ * instance itself keeps processing.
  */
 void vst3_gui_detach(Vst3GuiHandle* handle) {
     if (!handle || !handle->view || !handle->attached) return;
     handle->view->removed();
+    handle->view->setFrame(nullptr);
+    handle->view = nullptr;
     handle->attached = false;
+    if (handle->plugFrame) handle->plugFrame->clear_run_loop();
 }
 
 /**
@@ -517,8 +1234,8 @@ void vst3_gui_destroy(Vst3GuiHandle* handle) {
  * @return Parameter count, or 0 if invalid handle
  */
 int vst3_gui_get_parameter_count(Vst3GuiHandle* handle) {
-    if (!handle || !handle->controller) return 0;
-    return handle->controller->getParameterCount();
+    if (!handle) return 0;
+    return static_cast<int>(handle->paramIds.size());
 }
 
 /**
@@ -554,30 +1271,442 @@ int vst3_gui_get_parameter(Vst3GuiHandle* handle, int index, double* value) {
  * @param value  Normalized value to set
  * @return VST3_GUI_OK on success
  *
- * This updates the controller's value, which should update the GUI.
- * Note: This doesn't automatically update the audio processor - that
- * requires sending parameter changes through the proper channel.
+ * Safe to call from any thread: the value is queued for the processor's next
+ * block and for the controller (applied by vst3_gui_idle on the UI thread).
  */
 int vst3_gui_set_parameter(Vst3GuiHandle* handle, int index, double value) {
-    if (!handle || !handle->controller) return VST3_GUI_ERROR_INVALID_PARAM;
+    if (!handle) return VST3_GUI_ERROR_INVALID_PARAM;
+    if (index < 0 || index >= static_cast<int>(handle->paramIds.size())) return VST3_GUI_ERROR_INVALID_PARAM;
 
-    int param_count = handle->controller->getParameterCount();
-    if (index < 0 || index >= param_count) return VST3_GUI_ERROR_INVALID_PARAM;
+    ParamID id = handle->paramIds[index];
+    if (id == kNoParamId) return VST3_GUI_ERROR_INVALID_PARAM;
+
+    handle->queue_for_processor(id, value);
+    handle->queue_for_controller(id, value);
+    return VST3_GUI_OK;
+}
+
+/**
+ * Get a parameter's title, short title, units, step count and default normalized value.
+ *
+ * @param step_count Output: number of discrete steps (0 = continuous, 1 = toggle)
+ * @return VST3_GUI_OK on success
+ */
+int vst3_gui_get_parameter_info(Vst3GuiHandle* handle, int index, char* name, int name_len,
+                                char* short_name, int short_name_len, char* units, int units_len,
+                                int* step_count, double* default_value) {
+    if (!handle || !handle->controller) return VST3_GUI_ERROR_INVALID_PARAM;
 
-    // Get parameter info to find the ParamID
     ParameterInfo info;
     if (handle->controller->getParameterInfo(index, info) != kResultOk) {
         return VST3_GUI_ERROR_INVALID_PARAM;
     }
 
-    // Set normalized value
-    if (handle->controller->setParamNormalized(info.id, value) != kResultOk) {
+    copy_string128(info.title, name, name_len);
+    copy_string128(info.shortTitle, short_name, short_name_len);
+    copy_string128(info.units, units, units_len);
+    if (step_count) *step_count = info.stepCount;
+    if (default_value) *default_value = info.defaultNormalizedValue;
+    return VST3_GUI_OK;
+}
+
+/**
+ * Format a normalized value the way the plugin displays it (e.g. "1.2" for
+ * a cutoff in kHz; units are reported separately).
+ *
+ * @param value Normalized value to format (need not be the current one)
+ * @return VST3_GUI_OK on success
+ *
+ * Call from the UI thread.
+ */
+int vst3_gui_get_parameter_display(Vst3GuiHandle* handle, int index, double value, char* text, int text_len) {
+    if (!handle || !handle->controller || !text) return VST3_GUI_ERROR_INVALID_PARAM;
+    if (index < 0 || index >= static_cast<int>(handle->paramIds.size())) return VST3_GUI_ERROR_INVALID_PARAM;
+
+    String128 str = {};
+    if (handle->controller->getParamStringByValue(handle->paramIds[index], value, str) != kResultOk) {
+        return VST3_GUI_ERROR_GENERIC;
+    }
+    copy_string128(str, text, text_len);
+    return VST3_GUI_OK;
+}
+
+/**
+ * Get the number of audio output buses (main bus first, then auxiliary buses).
+ */
+int vst3_gui_get_output_bus_count(Vst3GuiHandle* handle) {
+    if (!handle || !handle->component) return VST3_GUI_ERROR_INVALID_PARAM;
+    return handle->component->getBusCount(kAudio, kOutput);
+}
+
+/**
+ * Describe an audio output bus.
+ *
+ * @param name     Caller-allocated buffer, NUL-terminated UTF-8 on return
+ * @param channels Channel count of the bus
+ * @param aux      Non-zero for an auxiliary bus
+ * @return VST3_GUI_OK on success
+ */
+int vst3_gui_get_output_bus_info(Vst3GuiHandle* handle, int index, char* name, int name_len, int* channels, int* aux) {
+    if (!handle || !handle->component || !name || name_len <= 0 || !channels || !aux) {
         return VST3_GUI_ERROR_INVALID_PARAM;
     }
+    BusInfo info;
+    if (handle->component->getBusInfo(kAudio, kOutput, index, info) != kResultOk) return VST3_GUI_ERROR_INVALID_PARAM;
+    copy_string128(info.name, name, name_len);
+    *channels = info.channelCount;
+    *aux = info.busType == kAux ? 1 : 0;
+    return VST3_GUI_OK;
+}
 
+/**
+ * Choose whether an auxiliary output bus is rendered.
+ *
+ * @param enabled Non-zero to activate the bus
+ * @return VST3_GUI_OK on success
+ *
+ * Takes effect at the next vst3_gui_setup_processing. The main bus is always
+ * active. Plugins typically fall back to the main bus for sounds routed to an
+ * inactive bus.
+ */
+int vst3_gui_enable_output_bus(Vst3GuiHandle* handle, int index, int enabled) {
+    if (!handle || !handle->component || index <= 0) return VST3_GUI_ERROR_INVALID_PARAM;
+    if (index >= handle->component->getBusCount(kAudio, kOutput)) return VST3_GUI_ERROR_INVALID_PARAM;
+    if (static_cast<size_t>(index) >= handle->auxOutputsEnabled.size()) {
+        handle->auxOutputsEnabled.resize(static_cast<size_t>(index) + 1, false);
+    }
+    handle->auxOutputsEnabled[index] = enabled != 0;
     return VST3_GUI_OK;
 }
 
+/**
+ * Get the number of programs (factory presets) the plugin exposes.
+ *
+ * @return Program count, or 0 if the plugin has no program-change parameter
+ */
+int vst3_gui_get_program_count(Vst3GuiHandle* handle) {
+    if (!handle) return 0;
+    return static_cast<int>(handle->programNames.size());
+}
+
+/**
+ * Get a program's name.
+ *
+ * @return VST3_GUI_OK on success
+ */
+int vst3_gui_get_program_name(Vst3GuiHandle* handle, int index, char* name, int name_len) {
+    if (!handle || !name || name_len <= 0) return VST3_GUI_ERROR_INVALID_PARAM;
+    if (index < 0 || index >= static_cast<int>(handle->programNames.size())) return VST3_GUI_ERROR_INVALID_PARAM;
+
+    std::snprintf(name, name_len, "%s", handle->programNames[index].c_str());
+    return VST3_GUI_OK;
+}
+
+/**
+ * Get the currently selected program, read from the controller.
+ *
+ * @return Program index, or a negative error if the plugin has no programs
+ *
+ * Call from the UI thread.
+ */
+int vst3_gui_get_program(Vst3GuiHandle* handle) {
+    if (!handle || !handle->controller || handle->programNames.empty()) return VST3_GUI_ERROR_INVALID_PARAM;
+
+    int last = static_cast<int>(handle->programNames.size()) - 1;
+    ParamValue value = handle->controller->getParamNormalized(handle->paramIds[handle->programParamIndex]);
+    return std::clamp(static_cast<int>(value * last + 0.5), 0, last);
+}
+
+/**
+ * Select a program.
+ *
+ * @return VST3_GUI_OK on success
+ *
+ * Call from the UI thread. The controller is switched immediately (so the
+ * parameters it reports reflect the new program) and the processor on its
+ * next block.
+ */
+int vst3_gui_set_program(Vst3GuiHandle* handle, int index) {
+    if (!handle || !handle->controller) return VST3_GUI_ERROR_INVALID_PARAM;
+    if (index < 0 || index >= static_cast<int>(handle->programNames.size())) return VST3_GUI_ERROR_INVALID_PARAM;
+
+    int last = static_cast<int>(handle->programNames.size()) - 1;
+    ParamValue value = last > 0 ? static_cast<double>(index) / last : 0.0;
+    ParamID id = handle->paramIds[handle->programParamIndex];
+    handle->controller->setParamNormalized(id, value);
+    handle->queue_for_processor(id, value);
+    return VST3_GUI_OK;
+}
+
+/**
+ * Apply queued parameter values to the controller.
+ *
+ * Must be called periodically from the UI thread. Picks up host edits and
+ * parameter changes reported by the processor, so an open editor follows
+ * automation, MIDI learn and the host's parameter windows. On Linux this
+ * is also the editor's run loop, so call it often (every frame) while an
+ * editor is open.
+ */
+void vst3_gui_idle(Vst3GuiHandle* handle) {
+    if (!handle || !handle->controller) return;
+
+    if (handle->plugFrame) handle->plugFrame->run_loop_tick();
+
+    std::map<ParamID, ParamValue> pending;
+    {
+        std::lock_guard<std::mutex> lock(handle->controllerQueueMutex);
+        pending.swap(handle->controllerQueue);
+    }
+    for (const auto& [id, value] : pending) {
+        handle->controller->setParamNormalized(id, value);
+    }
+}
+
+/**
+ * Prepare the component's processor for real-time processing.
+ *
+ * @param sample_rate    Sample rate in Hz
+ * @param max_block_size Largest block passed to vst3_gui_process
+ * @return VST3_GUI_OK on success
+ *
+ * May be called again (e.g. after a sample rate change); the processor is
+ * deactivated and set up afresh.
+ */
+int vst3_gui_setup_processing(Vst3GuiHandle* handle, double sample_rate, int max_block_size) {
+    if (!handle || !handle->component || max_block_size <= 0) return VST3_GUI_ERROR_INVALID_PARAM;
+
+    if (!handle->processor) {
+        handle->processor = U::cast<IAudioProcessor>(handle->component);
+        if (!handle->processor) {
+            LOG_DEBUG("vst3_gui_setup_processing: component has no audio processor");
+            return VST3_GUI_ERROR_GENERIC;
+        }
+    }
+
+    handle->stop_processing();
+
+    ProcessSetup setup{kRealtime, kSample32, max_block_size, sample_rate};
+    if (handle->processor->setupProcessing(setup) != kResultOk) {
+        LOG_DEBUG("vst3_gui_setup_processing: setupProcessing failed");
+        return VST3_GUI_ERROR_GENERIC;
+    }
+
+    activate_buses(handle);
+
+    if (handle->component->setActive(true) != kResultOk) {
+        LOG_DEBUG("vst3_gui_setup_processing: setActive failed");
+        return VST3_GUI_ERROR_GENERIC;
+    }
+    // Not every plugin implements setProcessing; ignore kNotImplemented
+    handle->processor->setProcessing(true);
+    handle->processing = true;
+
+    // Extra bus channels beyond the host's stereo pairs render into scratch memory
+    handle->scratch.assign(static_cast<size_t>(max_block_size), 0.0f);
+    handle->inputPtrs.assign(static_cast<size_t>(handle->inputChannels), nullptr);
+    handle->outputPtrs.clear();
+    for (int32 channels : handle->outputChannels) {
+        handle->outputPtrs.emplace_back(static_cast<size_t>(channels), nullptr);
+    }
+    handle->outputBuses.assign(handle->outputChannels.size(), AudioBusBuffers{});
+    return VST3_GUI_OK;
+}
+
+/**
+ * Queue a note event for the next vst3_gui_process call (audio thread only).
+ *
+ * @param note_on       Non-zero for note on, zero for note off
+ * @param velocity      Normalized velocity (0-1)
+ * @param sample_offset Offset of the event within the next block
+ * @return VST3_GUI_OK, or VST3_GUI_ERROR_GENERIC if the event list is full
+ */
+int vst3_gui_queue_note(Vst3GuiHandle* handle, int note_on, int channel, int pitch,
+                        float velocity, int sample_offset) {
+    if (!handle) return VST3_GUI_ERROR_INVALID_PARAM;
+
+    Event event{};
+    event.busIndex = 0;
+    event.sampleOffset = sample_offset;
+    if (note_on) {
+        event.type = Event::kNoteOnEvent;
+        event.noteOn.channel = static_cast<int16>(channel);
+        event.noteOn.pitch = static_cast<int16>(pitch);
+        event.noteOn.velocity = velocity;
+        event.noteOn.noteId = -1;
+    } else {
+        event.type = Event::kNoteOffEvent;
+        event.noteOff.channel = static_cast<int16>(channel);
+        event.noteOff.pitch = static_cast<int16>(pitch);
+        event.noteOff.velocity = velocity;
+        event.noteOff.noteId = -1;
+    }
+    return handle->events.addEvent(event) == kResultOk ? VST3_GUI_OK : VST3_GUI_ERROR_GENERIC;
+}
+
+/**
+ * Queue a MIDI controller change for the next vst3_gui_process call (audio thread only).
+ *
+ * @param controller    CC number 0-127, 128 for channel aftertouch or 129 for pitch bend
+ * @param value         Normalized value (0-1)
+ * @param sample_offset Offset of the change within the next block
+ * @return VST3_GUI_OK, or VST3_GUI_ERROR_INVALID_PARAM if the plugin doesn't map the controller
+ *
+ * Delivered as a change of the parameter the plugin maps the controller to.
+ */
+int vst3_gui_queue_controller(Vst3GuiHandle* handle, int channel, int controller,
+                              double value, int sample_offset) {
+    if (!handle || channel < 0 || channel >= 16 || controller < 0 || controller >= kCountCtrlNumber) {
+        return VST3_GUI_ERROR_INVALID_PARAM;
+    }
+    if (handle->midiMap.empty()) return VST3_GUI_ERROR_INVALID_PARAM;
+    ParamID id = handle->midiMap[channel * kCountCtrlNumber + controller];
+    if (id == kNoParamId) return VST3_GUI_ERROR_INVALID_PARAM;
+
+    int32 queueIndex = 0;
+    auto* queue = handle->inputChanges.addParameterData(id, queueIndex);
+    int32 pointIndex = 0;
+    if (!queue || queue->addPoint(sample_offset, value, pointIndex) != kResultOk) {
+        return VST3_GUI_ERROR_GENERIC;
+    }
+    return VST3_GUI_OK;
+}
+
+/**
+ * Queue a polyphonic aftertouch change for the next vst3_gui_process call (audio thread only).
+ *
+ * @param pressure      Normalized pressure (0-1)
+ * @param sample_offset Offset of the event within the next block
+ * @return VST3_GUI_OK, or VST3_GUI_ERROR_GENERIC if the event list is full
+ */
+int vst3_gui_queue_poly_pressure(Vst3GuiHandle* handle, int channel, int pitch,
+                                 float pressure, int sample_offset) {
+    if (!handle) return VST3_GUI_ERROR_INVALID_PARAM;
+
+    Event event{};
+    event.busIndex = 0;
+    event.sampleOffset = sample_offset;
+    event.type = Event::kPolyPressureEvent;
+    event.polyPressure.channel = static_cast<int16>(channel);
+    event.polyPressure.pitch = static_cast<int16>(pitch);
+    event.polyPressure.pressure = pressure;
+    event.polyPressure.noteId = -1;
+    return handle->events.addEvent(event) == kResultOk ? VST3_GUI_OK : VST3_GUI_ERROR_GENERIC;
+}
+
+/**
+ * Process one block of planar stereo audio (audio thread only).
+ *
+ * @param inputs     Two input channel buffers
+ * @param outputs    Two output channel buffers
+ * @param num_frames Block length, at most the max_block_size given at setup
+ * @return VST3_GUI_OK on success
+ *
+ * Only the main output bus is delivered; see vst3_gui_process_buses.
+ */
+int vst3_gui_process(Vst3GuiHandle* handle, float* const* inputs, float* const* outputs, int num_frames) {
+    return vst3_gui_process_buses(handle, inputs, outputs, 1, num_frames);
+}
+
+/**
+ * Process one block, delivering several output buses (audio thread only).
+ *
+ * @param inputs      Two input channel buffers
+ * @param outputs     A stereo pair of channel buffers per delivered bus, in bus order
+ * @param num_buses   Number of output buses delivered (pairs in outputs)
+ * @param num_frames  Block length, at most the max_block_size given at setup
+ * @return VST3_GUI_OK on success
+ *
+ * Buses the host doesn't take render into scratch memory. Disabled auxiliary
+ * buses are left untouched, so the host should clear its buffers first.
+ * Delivers queued parameter edits and note events, then forwards parameter
+ * changes the processor reports to the controller queue.
+ */
+int vst3_gui_process_buses(Vst3GuiHandle* handle, float* const* inputs, float* const* outputs,
+                           int num_buses, int num_frames) {
+    if (!handle || !handle->processing || !inputs || !outputs || num_buses < 1) return VST3_GUI_ERROR_INVALID_PARAM;
+    if (num_frames <= 0 || num_frames > static_cast<int>(handle->scratch.size())) return VST3_GUI_ERROR_INVALID_PARAM;
+
+    // Deliver queued edits; if the UI holds the queue, they go out next block
+    {
+        std::unique_lock<std::mutex> lock(handle->processorQueueMutex, std::try_to_lock);
+        if (lock.owns_lock()) {
+            for (const auto& [id, value] : handle->processorQueue) {
+                int32 queueIndex = 0;
+                if (auto* queue = handle->inputChanges.addParameterData(id, queueIndex)) {
+                    int32 pointIndex = 0;
+                    queue->addPoint(0, value, pointIndex);
+                }
+            }
+            handle->processorQueue.clear();
+        }
+    }
+
+    // Map bus channels onto the host's stereo pairs; extra channels and buses use scratch
+    for (int32 c = 0; c < handle->inputChannels; ++c) {
+        handle->inputPtrs[c] = c < 2 ? inputs[c] : handle->scratch.data();
+    }
+    int32 numOutputs = static_cast<int32>(handle->outputChannels.size());
+    for (int32 bus = 0; bus < numOutputs; ++bus) {
+        auto& ptrs = handle->outputPtrs[bus];
+        for (int32 c = 0; c < handle->outputChannels[bus]; ++c) {
+            ptrs[c] = bus < num_buses && c < 2 ? outputs[bus * 2 + c] : handle->scratch.data();
+        }
+        handle->outputBuses[bus].numChannels = handle->outputChannels[bus];
+        handle->outputBuses[bus].silenceFlags = 0;
+        handle->outputBuses[bus].channelBuffers32 = ptrs.data();
+    }
+    if (handle->inputChannels > 2) {
+        std::fill(handle->scratch.begin(), handle->scratch.begin() + num_frames, 0.0f);
+    }
+
+    AudioBusBuffers inputBus{};
+    inputBus.numChannels = handle->inputChannels;
+    inputBus.channelBuffers32 = handle->inputPtrs.data();
+
+    ProcessData data;
+    data.processMode = kRealtime;
+    data.symbolicSampleSize = kSample32;
+    data.numSamples = num_frames;
+    data.numInputs = handle->inputChannels > 0 ? 1 : 0;
+    data.numOutputs = numOutputs;
+    data.inputs = handle->inputChannels > 0 ? &inputBus : nullptr;
+    data.outputs = numOutputs > 0 ? handle->outputBuses.data() : nullptr;
+    data.inputParameterChanges = &handle->inputChanges;
+    data.outputParameterChanges = &handle->outputChanges;
+    data.inputEvents = &handle->events;
+
+    tresult result = handle->processor->process(data);
+
+    // A mono output bus feeds both host channels
+    for (int32 bus = 0; bus < std::min(numOutputs, static_cast<int32>(num_buses)); ++bus) {
+        if (handle->outputChannels[bus] == 1) {
+            std::memcpy(outputs[bus * 2 + 1], outputs[bus * 2], sizeof(float) * static_cast<size_t>(num_frames));
+        }
+    }
+
+    // Report processor-side parameter changes to the controller (last point wins)
+    {
+        std::unique_lock<std::mutex> lock(handle->controllerQueueMutex, std::try_to_lock);
+        if (lock.owns_lock()) {
+            for (int32 i = 0; i < handle->outputChanges.getParameterCount(); ++i) {
+                auto* queue = handle->outputChanges.getParameterData(i);
+                int32 points = queue ? queue->getPointCount() : 0;
+                int32 offset = 0;
+                ParamValue value = 0.0;
+                if (points > 0 && queue->getPoint(points - 1, offset, value) == kResultOk) {
+                    handle->controllerQueue[queue->getParameterId()] = value;
+                }
+            }
+        }
+    }
+
+    handle->inputChanges.clearQueue();
+    handle->outputChanges.clearQueue();
+    handle->events.clear();
+
+    return result == kResultOk ? VST3_GUI_OK : VST3_GUI_ERROR_GENERIC;
+}
+
 /**
  * Get the component's state (for preset saving).
  *
@@ -619,4 +1748,36 @@ int vst3_gui_get_component_state(Vst3GuiHandle* handle, uint8_t* state_out, int
     return static_cast<int>(bytesRead);
 }
 
+/**
+ * Restore the component's state (for project/preset loading).
+ *
+ * @param handle     Plugin handle
+ * @param state      State bytes previously returned by vst3_gui_get_component_state
+ * @param state_size Number of bytes in state
+ * @return VST3_GUI_OK on success
+ *
+ * Call from the UI thread. A separate controller is sent the same state so
+ * its parameters (and an open editor) match the processor.
+ */
+int vst3_gui_set_component_state(Vst3GuiHandle* handle, const uint8_t* state, int state_size) {
+    if (!handle || !handle->component || !state || state_size <= 0) return VST3_GUI_ERROR_INVALID_PARAM;
+
+    MemoryStream stream;
+    int32 written = 0;
+    if (stream.write(const_cast<uint8_t*>(state), state_size, &written) != kResultOk || written != state_size) {
+        return VST3_GUI_ERROR_GENERIC;
+    }
+
+    stream.seek(0, IBStream::kIBSeekSet, nullptr);
+    if (handle->component->setState(&stream) != kResultOk) {
+        return VST3_GUI_ERROR_GENERIC;
+    }
+
+    if (handle->separateController) {
+        stream.seek(0, IBStream::kIBSeekSet, nullptr);
+        handle->controller->setComponentState(&stream);
+    }
+    return VST3_GUI_OK;
+}
+
 } // extern "C"
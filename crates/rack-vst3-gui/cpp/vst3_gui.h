@@ -19,7 +19,73 @@ typedef struct Vst3GuiHandle Vst3GuiHandle;
 #define VST3_GUI_ERROR_INVALID_PARAM -4
 #define VST3_GUI_ERROR_GENERIC -5
 
-// Create a GUI handle for a VST3 plugin
+// Host key codes for vst3_gui_key_event, mapped to the SDK's VirtualKeyCodes
+enum Vst3GuiKey {
+    VST3_GUI_KEY_NONE = 0,
+    VST3_GUI_KEY_BACK,
+    VST3_GUI_KEY_TAB,
+    VST3_GUI_KEY_CLEAR,
+    VST3_GUI_KEY_RETURN,
+    VST3_GUI_KEY_PAUSE,
+    VST3_GUI_KEY_ESCAPE,
+    VST3_GUI_KEY_SPACE,
+    VST3_GUI_KEY_END,
+    VST3_GUI_KEY_HOME,
+    VST3_GUI_KEY_LEFT,
+    VST3_GUI_KEY_UP,
+    VST3_GUI_KEY_RIGHT,
+    VST3_GUI_KEY_DOWN,
+    VST3_GUI_KEY_PAGE_UP,
+    VST3_GUI_KEY_PAGE_DOWN,
+    VST3_GUI_KEY_SELECT,
+    VST3_GUI_KEY_PRINT,
+    VST3_GUI_KEY_ENTER,
+    VST3_GUI_KEY_INSERT,
+    VST3_GUI_KEY_DELETE,
+    VST3_GUI_KEY_HELP,
+    VST3_GUI_KEY_NUMPAD_0,
+    VST3_GUI_KEY_NUMPAD_1,
+    VST3_GUI_KEY_NUMPAD_2,
+    VST3_GUI_KEY_NUMPAD_3,
+    VST3_GUI_KEY_NUMPAD_4,
+    VST3_GUI_KEY_NUMPAD_5,
+    VST3_GUI_KEY_NUMPAD_6,
+    VST3_GUI_KEY_NUMPAD_7,
+    VST3_GUI_KEY_NUMPAD_8,
+    VST3_GUI_KEY_NUMPAD_9,
+    VST3_GUI_KEY_MULTIPLY,
+    VST3_GUI_KEY_ADD,
+    VST3_GUI_KEY_SEPARATOR,
+    VST3_GUI_KEY_SUBTRACT,
+    VST3_GUI_KEY_DECIMAL,
+    VST3_GUI_KEY_DIVIDE,
+    VST3_GUI_KEY_F1,
+    VST3_GUI_KEY_F2,
+    VST3_GUI_KEY_F3,
+    VST3_GUI_KEY_F4,
+    VST3_GUI_KEY_F5,
+    VST3_GUI_KEY_F6,
+    VST3_GUI_KEY_F7,
+    VST3_GUI_KEY_F8,
+    VST3_GUI_KEY_F9,
+    VST3_GUI_KEY_F10,
+    VST3_GUI_KEY_F11,
+    VST3_GUI_KEY_F12,
+    VST3_GUI_KEY_NUM_LOCK,
+    VST3_GUI_KEY_SCROLL,
+    VST3_GUI_KEY_SHIFT,
+    VST3_GUI_KEY_CONTROL,
+    VST3_GUI_KEY_ALT,
+    VST3_GUI_KEY_CONTEXT_MENU,
+};
+
+// Modifier flags for vst3_gui_key_event
+#define VST3_GUI_MOD_SHIFT 1
+#define VST3_GUI_MOD_ALT 2
+#define VST3_GUI_MOD_COMMAND 4
+#define VST3_GUI_MOD_CONTROL 8
+
+// Create a handle for a VST3 plugin: component, edit controller and (on demand) editor view
 // path: path to the .vst3 bundle
 // uid: plugin unique ID (hex string)
 // Returns handle or NULL on failure
@@ -34,6 +100,35 @@ int vst3_gui_get_size(Vst3GuiHandle* handle, int* width, int* height);
 // Returns 0 on success, negative on error
 int vst3_gui_attach_x11(Vst3GuiHandle* handle, uint32_t window_id);
 
+// Attach the plugin view to a Win32 window (call from the window's thread)
+// hwnd: HWND of the host window
+// Returns 0 on success, negative on error
+int vst3_gui_attach_hwnd(Vst3GuiHandle* handle, void* hwnd);
+
+// Attach the plugin view to a Cocoa view (call from the main thread)
+// ns_view: NSView* the editor is added to
+// Returns 0 on success, negative on error
+int vst3_gui_attach_nsview(Vst3GuiHandle* handle, void* ns_view);
+
+// Forward a key press (key_down != 0) or release to the attached editor
+// character: Unicode code point of the key (0 if none), key: Vst3GuiKey, modifiers: VST3_GUI_MOD_* flags
+// Returns 1 if the editor consumed the key, 0 if not, negative on error
+int vst3_gui_key_event(Vst3GuiHandle* handle, int key_down, uint32_t character, int key, int modifiers);
+
+// Tell the attached editor its window gained (focused != 0) or lost keyboard focus
+void vst3_gui_set_focus(Vst3GuiHandle* handle, int focused);
+
+// Whether the attached editor can be resized by the user (1) or has a fixed size (0)
+int vst3_gui_can_resize(Vst3GuiHandle* handle);
+
+// Take the size the editor last requested via IPlugFrame::resizeView
+// Returns 1 and fills width/height if a request was pending, 0 otherwise
+int vst3_gui_take_resize_request(Vst3GuiHandle* handle, int* width, int* height);
+
+// Resize the editor to the host window's new size (in/out: adjusted by checkSizeConstraint)
+// Returns 0 on success, negative on error
+int vst3_gui_set_size(Vst3GuiHandle* handle, int* width, int* height);
+
 // Detach the plugin view
 void vst3_gui_detach(Vst3GuiHandle* handle);
 
@@ -47,10 +142,102 @@ int vst3_gui_get_parameter_count(Vst3GuiHandle* handle);
 // Returns 0 on success, negative on error
 int vst3_gui_get_parameter(Vst3GuiHandle* handle, int index, double* value);
 
-// Set a parameter value (normalized 0-1)
-// Returns 0 on success, negative on error
+// Set a parameter value (normalized 0-1) on both the processor and the controller
+// Safe from any thread; returns 0 on success, negative on error
 int vst3_gui_set_parameter(Vst3GuiHandle* handle, int index, double value);
 
+// Get a parameter's title, short title, units, step count (0 = continuous) and default normalized value
+// name/short_name/units: caller-allocated buffers, NUL-terminated UTF-8 on return
+// Returns 0 on success, negative on error
+int vst3_gui_get_parameter_info(Vst3GuiHandle* handle, int index, char* name, int name_len,
+                                char* short_name, int short_name_len, char* units, int units_len,
+                                int* step_count, double* default_value);
+
+// Format a normalized parameter value as the plugin displays it (call from the UI thread)
+// text: caller-allocated buffer, NUL-terminated UTF-8 on return
+// Returns 0 on success, negative on error
+int vst3_gui_get_parameter_display(Vst3GuiHandle* handle, int index, double value, char* text, int text_len);
+
+// Get the number of audio output buses (main bus first, then auxiliary buses)
+int vst3_gui_get_output_bus_count(Vst3GuiHandle* handle);
+
+// Describe an output bus: name into a caller-allocated buffer (NUL-terminated),
+// its channel count, and whether it is an auxiliary bus
+// Returns 0 on success, negative on error
+int vst3_gui_get_output_bus_info(Vst3GuiHandle* handle, int index, char* name, int name_len, int* channels, int* aux);
+
+// Enable or disable an auxiliary output bus (takes effect at the next setup_processing)
+// Returns 0 on success, negative on error
+int vst3_gui_enable_output_bus(Vst3GuiHandle* handle, int index, int enabled);
+
+// Get the number of programs (factory presets); 0 if the plugin has none
+int vst3_gui_get_program_count(Vst3GuiHandle* handle);
+
+// Get a program's name into a caller-allocated buffer (NUL-terminated)
+// Returns 0 on success, negative on error
+int vst3_gui_get_program_name(Vst3GuiHandle* handle, int index, char* name, int name_len);
+
+// Get the current program index (call from the UI thread)
+// Returns the index, negative on error
+int vst3_gui_get_program(Vst3GuiHandle* handle);
+
+// Select a program on the controller and processor (call from the UI thread)
+// Returns 0 on success, negative on error
+int vst3_gui_set_program(Vst3GuiHandle* handle, int index);
+
+// Kinds of editor edits reported by vst3_gui_take_edits
+#define VST3_GUI_EDIT_BEGIN 0
+#define VST3_GUI_EDIT_PERFORM 1
+#define VST3_GUI_EDIT_END 2
+
+// One parameter edit made in the editor: a gesture start (beginEdit), a value
+// change (performEdit, value is normalized 0-1) or a gesture end (endEdit)
+typedef struct Vst3GuiEdit {
+    int kind;
+    int index;
+    double value;
+} Vst3GuiEdit;
+
+// Take the parameter edits the editor made since the last call, oldest first,
+// into a caller-allocated array of max_edits entries (call from the UI thread)
+// Returns the number copied; any that didn't fit stay queued for the next call
+int vst3_gui_take_edits(Vst3GuiHandle* handle, Vst3GuiEdit* edits, int max_edits);
+
+// Apply queued parameter values to the edit controller and, on Linux, run the
+// editor's timers and file descriptor handlers (call periodically from the UI thread)
+void vst3_gui_idle(Vst3GuiHandle* handle);
+
+// Prepare the component for real-time processing (call again on sample rate change)
+// Returns 0 on success, negative on error
+int vst3_gui_setup_processing(Vst3GuiHandle* handle, double sample_rate, int max_block_size);
+
+// Queue a note on (note_on != 0) or note off for the next process call (audio thread)
+// Returns 0 on success, negative if the event list is full
+int vst3_gui_queue_note(Vst3GuiHandle* handle, int note_on, int channel, int pitch,
+                        float velocity, int sample_offset);
+
+// Queue a MIDI controller change (CC 0-127, 128 = channel aftertouch, 129 = pitch bend,
+// value normalized 0-1) for the next process call (audio thread)
+// Returns 0 on success, negative if the plugin doesn't map the controller
+int vst3_gui_queue_controller(Vst3GuiHandle* handle, int channel, int controller,
+                              double value, int sample_offset);
+
+// Queue a polyphonic aftertouch change (pressure normalized 0-1) for the next process call (audio thread)
+// Returns 0 on success, negative if the event list is full
+int vst3_gui_queue_poly_pressure(Vst3GuiHandle* handle, int channel, int pitch,
+                                 float pressure, int sample_offset);
+
+// Process one block of planar stereo audio (audio thread)
+// inputs/outputs: two channel buffers of at least num_frames samples each
+// Returns 0 on success, negative on error
+int vst3_gui_process(Vst3GuiHandle* handle, float* const* inputs, float* const* outputs, int num_frames);
+
+// Process one block, delivering the first num_buses output buses (audio thread)
+// outputs: a stereo pair of channel buffers per bus, in bus order (2 * num_buses)
+// Returns 0 on success, negative on error
+int vst3_gui_process_buses(Vst3GuiHandle* handle, float* const* inputs, float* const* outputs,
+                           int num_buses, int num_frames);
+
 // Get the component state as a byte array
 // state_out: output buffer (caller allocated), or NULL to query size
 // state_size: size of output buffer
@@ -58,6 +245,10 @@ int vst3_gui_set_parameter(Vst3GuiHandle* handle, int index, double value);
 // Usage: call with NULL to get size, allocate buffer, call again to fill
 int vst3_gui_get_component_state(Vst3GuiHandle* handle, uint8_t* state_out, int state_size);
 
+// Restore the component state from bytes returned by vst3_gui_get_component_state
+// Returns 0 on success, negative on error
+int vst3_gui_set_component_state(Vst3GuiHandle* handle, const uint8_t* state, int state_size);
+
 #ifdef __cplusplus
 }
 #endif
@@ -1,8 +1,14 @@
 //! VST3 Native GUI support for hallucinator
 //!
-//! This crate provides native plugin GUI embedding via IPlugView.
+//! This crate provides native plugin GUI embedding via IPlugView. The same
+//! instance also runs the plugin's audio processor, so edits made in the
+//! editor (including preset loads) are what the host hears.
+//!
+//! Editors attach to an X11 window on Linux, an HWND on Windows and an NSView
+//! on macOS. Windows and macOS editors run on the OS event loop the host already
+//! pumps; on Linux `Vst3Gui::idle` also services the editor's run loop.
 
-use std::ffi::CString;
+use std::ffi::{c_char, CStr, CString};
 use std::ptr;
 
 use thiserror::Error;
@@ -33,6 +39,11 @@ impl From<i32> for Vst3GuiError {
     }
 }
 
+/// Read a NUL-terminated string written by the C side
+fn buffer_to_string(buf: &[c_char]) -> String {
+    unsafe { CStr::from_ptr(buf.as_ptr()) }.to_string_lossy().into_owned()
+}
+
 /// Convert FFI result to Result type
 fn check_result(result: i32) -> Result<(), Vst3GuiError> {
     if result == 0 { Ok(()) } else { Err(result.into()) }
@@ -47,29 +58,302 @@ mod ffi {
         _private: [u8; 0],
     }
 
+    pub const EDIT_BEGIN: i32 = 0;
+    pub const EDIT_PERFORM: i32 = 1;
+    pub const EDIT_END: i32 = 2;
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Default)]
+    pub struct Vst3GuiEdit {
+        pub kind: i32,
+        pub index: i32,
+        pub value: f64,
+    }
+
     extern "C" {
         pub fn vst3_gui_create(path: *const c_char, uid: *const c_char) -> *mut Vst3GuiHandle;
         pub fn vst3_gui_get_size(handle: *mut Vst3GuiHandle, width: *mut i32, height: *mut i32) -> i32;
         pub fn vst3_gui_attach_x11(handle: *mut Vst3GuiHandle, window_id: u32) -> i32;
+        #[cfg(target_os = "windows")]
+        pub fn vst3_gui_attach_hwnd(handle: *mut Vst3GuiHandle, hwnd: *mut std::ffi::c_void) -> i32;
+        #[cfg(target_os = "macos")]
+        pub fn vst3_gui_attach_nsview(handle: *mut Vst3GuiHandle, ns_view: *mut std::ffi::c_void) -> i32;
+        pub fn vst3_gui_key_event(
+            handle: *mut Vst3GuiHandle,
+            key_down: i32,
+            character: u32,
+            key: i32,
+            modifiers: i32,
+        ) -> i32;
+        pub fn vst3_gui_set_focus(handle: *mut Vst3GuiHandle, focused: i32);
+        pub fn vst3_gui_can_resize(handle: *mut Vst3GuiHandle) -> i32;
+        pub fn vst3_gui_take_resize_request(handle: *mut Vst3GuiHandle, width: *mut i32, height: *mut i32) -> i32;
+        pub fn vst3_gui_take_edits(handle: *mut Vst3GuiHandle, edits: *mut Vst3GuiEdit, max_edits: i32) -> i32;
+        pub fn vst3_gui_set_size(handle: *mut Vst3GuiHandle, width: *mut i32, height: *mut i32) -> i32;
         pub fn vst3_gui_detach(handle: *mut Vst3GuiHandle);
         pub fn vst3_gui_destroy(handle: *mut Vst3GuiHandle);
         pub fn vst3_gui_get_parameter_count(handle: *mut Vst3GuiHandle) -> i32;
         pub fn vst3_gui_get_parameter(handle: *mut Vst3GuiHandle, index: i32, value: *mut f64) -> i32;
         pub fn vst3_gui_set_parameter(handle: *mut Vst3GuiHandle, index: i32, value: f64) -> i32;
+        pub fn vst3_gui_get_parameter_info(
+            handle: *mut Vst3GuiHandle,
+            index: i32,
+            name: *mut c_char,
+            name_len: i32,
+            short_name: *mut c_char,
+            short_name_len: i32,
+            units: *mut c_char,
+            units_len: i32,
+            step_count: *mut i32,
+            default_value: *mut f64,
+        ) -> i32;
+        pub fn vst3_gui_get_parameter_display(
+            handle: *mut Vst3GuiHandle,
+            index: i32,
+            value: f64,
+            text: *mut c_char,
+            text_len: i32,
+        ) -> i32;
+        pub fn vst3_gui_get_output_bus_count(handle: *mut Vst3GuiHandle) -> i32;
+        pub fn vst3_gui_get_output_bus_info(
+            handle: *mut Vst3GuiHandle,
+            index: i32,
+            name: *mut c_char,
+            name_len: i32,
+            channels: *mut i32,
+            aux: *mut i32,
+        ) -> i32;
+        pub fn vst3_gui_enable_output_bus(handle: *mut Vst3GuiHandle, index: i32, enabled: i32) -> i32;
+        pub fn vst3_gui_get_program_count(handle: *mut Vst3GuiHandle) -> i32;
+        pub fn vst3_gui_get_program_name(handle: *mut Vst3GuiHandle, index: i32, name: *mut c_char, name_len: i32) -> i32;
+        pub fn vst3_gui_get_program(handle: *mut Vst3GuiHandle) -> i32;
+        pub fn vst3_gui_set_program(handle: *mut Vst3GuiHandle, index: i32) -> i32;
+        pub fn vst3_gui_idle(handle: *mut Vst3GuiHandle);
+        pub fn vst3_gui_setup_processing(handle: *mut Vst3GuiHandle, sample_rate: f64, max_block_size: i32) -> i32;
+        pub fn vst3_gui_queue_note(
+            handle: *mut Vst3GuiHandle,
+            note_on: i32,
+            channel: i32,
+            pitch: i32,
+            velocity: f32,
+            sample_offset: i32,
+        ) -> i32;
+        pub fn vst3_gui_queue_controller(
+            handle: *mut Vst3GuiHandle,
+            channel: i32,
+            controller: i32,
+            value: f64,
+            sample_offset: i32,
+        ) -> i32;
+        pub fn vst3_gui_queue_poly_pressure(
+            handle: *mut Vst3GuiHandle,
+            channel: i32,
+            pitch: i32,
+            pressure: f32,
+            sample_offset: i32,
+        ) -> i32;
+        pub fn vst3_gui_process(
+            handle: *mut Vst3GuiHandle,
+            inputs: *const *mut f32,
+            outputs: *const *mut f32,
+            num_frames: i32,
+        ) -> i32;
+        pub fn vst3_gui_process_buses(
+            handle: *mut Vst3GuiHandle,
+            inputs: *const *mut f32,
+            outputs: *const *mut f32,
+            num_buses: i32,
+            num_frames: i32,
+        ) -> i32;
         pub fn vst3_gui_get_component_state(handle: *mut Vst3GuiHandle, state_out: *mut u8, state_size: i32) -> i32;
+        pub fn vst3_gui_set_component_state(handle: *mut Vst3GuiHandle, state: *const u8, state_size: i32) -> i32;
+    }
+}
+
+/// Non-character keys an editor understands (VST3 virtual key codes)
+///
+/// The discriminants are the bridge's own codes (`Vst3GuiKey` in vst3_gui.h);
+/// the C++ side maps them to the SDK's values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum VirtualKey {
+    Back = 1,
+    Tab,
+    Clear,
+    Return,
+    Pause,
+    Escape,
+    Space,
+    End,
+    Home,
+    Left,
+    Up,
+    Right,
+    Down,
+    PageUp,
+    PageDown,
+    Select,
+    Print,
+    Enter,
+    Insert,
+    Delete,
+    Help,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    Multiply,
+    Add,
+    Separator,
+    Subtract,
+    Decimal,
+    Divide,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    NumLock,
+    Scroll,
+    Shift,
+    Control,
+    Alt,
+    ContextMenu,
+}
+
+impl VirtualKey {
+    /// Keypad digit key, 0-9
+    pub fn numpad(digit: u8) -> Option<Self> {
+        const DIGITS: [VirtualKey; 10] = [
+            VirtualKey::Numpad0,
+            VirtualKey::Numpad1,
+            VirtualKey::Numpad2,
+            VirtualKey::Numpad3,
+            VirtualKey::Numpad4,
+            VirtualKey::Numpad5,
+            VirtualKey::Numpad6,
+            VirtualKey::Numpad7,
+            VirtualKey::Numpad8,
+            VirtualKey::Numpad9,
+        ];
+        DIGITS.get(digit as usize).copied()
+    }
+
+    /// Function key F1-F12
+    pub fn function(number: u8) -> Option<Self> {
+        const KEYS: [VirtualKey; 12] = [
+            VirtualKey::F1,
+            VirtualKey::F2,
+            VirtualKey::F3,
+            VirtualKey::F4,
+            VirtualKey::F5,
+            VirtualKey::F6,
+            VirtualKey::F7,
+            VirtualKey::F8,
+            VirtualKey::F9,
+            VirtualKey::F10,
+            VirtualKey::F11,
+            VirtualKey::F12,
+        ];
+        KEYS.get((number as usize).wrapping_sub(1)).copied()
     }
 }
 
-/// Handle to a VST3 plugin's native GUI
+/// Modifier keys held during a key event
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyModifiers {
+    pub shift: bool,
+    pub alt: bool,
+    /// Command on macOS, the Super/Windows key elsewhere
+    pub command: bool,
+    pub control: bool,
+}
+
+impl KeyModifiers {
+    fn bits(self) -> i32 {
+        (self.shift as i32) | ((self.alt as i32) << 1) | ((self.command as i32) << 2) | ((self.control as i32) << 3)
+    }
+}
+
+/// Display metadata for one plugin parameter
+#[derive(Debug, Clone)]
+pub struct ParameterInfo {
+    pub name: String,
+    /// Abbreviated name for narrow displays (may be empty)
+    pub short_name: String,
+    pub units: String,
+    /// Number of discrete steps: 0 for continuous, 1 for on/off
+    pub step_count: u32,
+    /// Default value, normalized 0-1
+    pub default: f64,
+}
+
+/// One step of a parameter edit made in the editor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditGesture {
+    /// The user grabbed the control (`beginEdit`)
+    Begin,
+    /// New normalized value (`performEdit`)
+    Perform(f64),
+    /// The user let go of the control (`endEdit`)
+    End,
+}
+
+/// A parameter edit made in the editor, by parameter index
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParameterEdit {
+    pub index: usize,
+    pub gesture: EditGesture,
+}
+
+/// Most output buses `process_buses` delivers in one call
+pub const MAX_OUTPUT_BUSES: usize = 16;
+
+/// Controller number `queue_controller` takes for channel aftertouch
+pub const CONTROLLER_AFTERTOUCH: u8 = 128;
+/// Controller number `queue_controller` takes for pitch bend
+pub const CONTROLLER_PITCH_BEND: u8 = 129;
+
+/// One audio output bus of a plugin
+#[derive(Debug, Clone)]
+pub struct OutputBusInfo {
+    pub name: String,
+    pub channels: u32,
+    /// Auxiliary bus (e.g. a drum sampler's per-pad output), as opposed to the main bus
+    pub aux: bool,
+}
+
+/// Handle to a VST3 plugin instance: audio processor, edit controller and native GUI
+///
+/// Share it (e.g. in an `Arc`) between the audio engine and the editor window.
+/// The view methods (`get_size`, the `attach_*` methods, `detach`, `idle`, resizing,
+/// key and focus forwarding) belong to the UI thread; `process` and the `queue_*` methods to the audio thread and must not be
+/// called concurrently with each other. Parameter setters are safe anywhere.
 pub struct Vst3Gui {
     handle: *mut ffi::Vst3GuiHandle,
 }
 
-// Safety: The handle is thread-safe when properly synchronized
+// Safety: The C++ side keeps UI-thread state (controller, view) and audio-thread
+// state (processor, event list) apart and passes parameter edits between them
+// through mutex-protected queues; see the threading notes in vst3_gui.cpp.
 unsafe impl Send for Vst3Gui {}
+unsafe impl Sync for Vst3Gui {}
 
 impl Vst3Gui {
-    /// Create a new GUI handle for a VST3 plugin
+    /// Create a new handle for a VST3 plugin (the editor view is created on first attach)
     ///
     /// # Arguments
     /// * `path` - Path to the .vst3 bundle
@@ -103,11 +387,113 @@ impl Vst3Gui {
         check_result(result)
     }
 
+    /// Attach the plugin view to a Win32 window (from the thread that owns it)
+    ///
+    /// # Safety
+    /// `hwnd` must be a valid window handle that outlives the attachment.
+    #[cfg(target_os = "windows")]
+    pub unsafe fn attach_hwnd(&self, hwnd: *mut std::ffi::c_void) -> Result<(), Vst3GuiError> {
+        if hwnd.is_null() {
+            return Err(Vst3GuiError::InvalidParam);
+        }
+        let result = unsafe { ffi::vst3_gui_attach_hwnd(self.handle, hwnd) };
+        check_result(result)
+    }
+
+    /// Attach the plugin view to a Cocoa view (main thread only)
+    ///
+    /// # Safety
+    /// `ns_view` must point to a valid `NSView` that outlives the attachment.
+    #[cfg(target_os = "macos")]
+    pub unsafe fn attach_nsview(&self, ns_view: *mut std::ffi::c_void) -> Result<(), Vst3GuiError> {
+        if ns_view.is_null() {
+            return Err(Vst3GuiError::InvalidParam);
+        }
+        let result = unsafe { ffi::vst3_gui_attach_nsview(self.handle, ns_view) };
+        check_result(result)
+    }
+
+    /// Forward a key event the host window received to the editor (UI thread)
+    ///
+    /// `character` is the text the key produces, `key` its virtual key for
+    /// non-character keys. Returns whether the editor consumed the key;
+    /// unconsumed keys are the host's to handle.
+    pub fn key_event(
+        &self,
+        down: bool,
+        character: Option<char>,
+        key: Option<VirtualKey>,
+        modifiers: KeyModifiers,
+    ) -> bool {
+        let result = unsafe {
+            ffi::vst3_gui_key_event(
+                self.handle,
+                down as i32,
+                character.map_or(0, u32::from),
+                key.map_or(0, |k| k as i32),
+                modifiers.bits(),
+            )
+        };
+        result == 1
+    }
+
+    /// Tell the editor its window gained or lost keyboard focus (UI thread)
+    pub fn set_focus(&self, focused: bool) {
+        unsafe { ffi::vst3_gui_set_focus(self.handle, focused as i32) };
+    }
+
+    /// Whether the editor can be resized by the user
+    pub fn can_resize(&self) -> bool {
+        unsafe { ffi::vst3_gui_can_resize(self.handle) != 0 }
+    }
+
+    /// Size the editor asked for since the last call, if any (UI thread)
+    pub fn take_resize_request(&self) -> Option<(i32, i32)> {
+        let mut width = 0;
+        let mut height = 0;
+        let pending = unsafe { ffi::vst3_gui_take_resize_request(self.handle, &mut width, &mut height) };
+        (pending != 0).then_some((width, height))
+    }
+
+    /// Resize the editor to fit a resized host window; returns the size the
+    /// plugin accepted, which may be snapped by its size constraints
+    pub fn set_size(&self, width: i32, height: i32) -> Result<(i32, i32), Vst3GuiError> {
+        let mut width = width;
+        let mut height = height;
+        let result = unsafe { ffi::vst3_gui_set_size(self.handle, &mut width, &mut height) };
+        check_result(result)?;
+        Ok((width, height))
+    }
+
     /// Detach the plugin view from its window
     pub fn detach(&self) {
         unsafe { ffi::vst3_gui_detach(self.handle) };
     }
 
+    /// Parameter edits the editor made since the last call, oldest first (UI thread).
+    /// Plugins wrap the value changes of a drag in `Begin` and `End`; value changes outside
+    /// a gesture can also come from plugins that don't report them.
+    pub fn take_edits(&self) -> Vec<ParameterEdit> {
+        let mut edits = Vec::new();
+        let mut buffer = [ffi::Vst3GuiEdit::default(); 64];
+        loop {
+            let count = unsafe { ffi::vst3_gui_take_edits(self.handle, buffer.as_mut_ptr(), buffer.len() as i32) };
+            let count = count.clamp(0, buffer.len() as i32) as usize;
+            edits.extend(buffer[..count].iter().filter_map(|edit| {
+                let gesture = match edit.kind {
+                    ffi::EDIT_BEGIN => EditGesture::Begin,
+                    ffi::EDIT_PERFORM => EditGesture::Perform(edit.value),
+                    ffi::EDIT_END => EditGesture::End,
+                    _ => return None,
+                };
+                Some(ParameterEdit { index: usize::try_from(edit.index).ok()?, gesture })
+            }));
+            if count < buffer.len() {
+                return edits;
+            }
+        }
+    }
+
     /// Get the number of parameters
     pub fn parameter_count(&self) -> usize {
         let count = unsafe { ffi::vst3_gui_get_parameter_count(self.handle) };
@@ -122,7 +508,7 @@ impl Vst3Gui {
         Ok(value)
     }
 
-    /// Set a parameter value (normalized 0-1)
+    /// Set a parameter value (normalized 0-1) on the processor and the controller
     pub fn set_parameter(&self, index: usize, value: f64) -> Result<(), Vst3GuiError> {
         let result = unsafe { ffi::vst3_gui_set_parameter(self.handle, index as i32, value) };
         check_result(result)
@@ -135,6 +521,222 @@ impl Vst3Gui {
             .collect()
     }
 
+    /// Get a parameter's names, units, step count and default value
+    pub fn parameter_info(&self, index: usize) -> Result<ParameterInfo, Vst3GuiError> {
+        let mut name = [0 as c_char; 256];
+        let mut short_name = [0 as c_char; 256];
+        let mut units = [0 as c_char; 256];
+        let mut step_count = 0i32;
+        let mut default = 0.0f64;
+        let result = unsafe {
+            ffi::vst3_gui_get_parameter_info(
+                self.handle,
+                index as i32,
+                name.as_mut_ptr(),
+                name.len() as i32,
+                short_name.as_mut_ptr(),
+                short_name.len() as i32,
+                units.as_mut_ptr(),
+                units.len() as i32,
+                &mut step_count,
+                &mut default,
+            )
+        };
+        check_result(result)?;
+        Ok(ParameterInfo {
+            name: buffer_to_string(&name),
+            short_name: buffer_to_string(&short_name),
+            units: buffer_to_string(&units),
+            step_count: step_count.max(0) as u32,
+            default,
+        })
+    }
+
+    /// Format a normalized value as the plugin displays it, without units (UI thread)
+    pub fn parameter_display(&self, index: usize, value: f64) -> Result<String, Vst3GuiError> {
+        let mut text = [0 as c_char; 256];
+        let result = unsafe {
+            ffi::vst3_gui_get_parameter_display(self.handle, index as i32, value, text.as_mut_ptr(), text.len() as i32)
+        };
+        check_result(result)?;
+        Ok(buffer_to_string(&text))
+    }
+
+    /// The plugin's audio output buses, main bus first
+    pub fn output_buses(&self) -> Vec<OutputBusInfo> {
+        let count = unsafe { ffi::vst3_gui_get_output_bus_count(self.handle) };
+        (0..count.max(0))
+            .filter_map(|index| {
+                let mut name = [0 as c_char; 128];
+                let (mut channels, mut aux) = (0i32, 0i32);
+                let result = unsafe {
+                    ffi::vst3_gui_get_output_bus_info(
+                        self.handle,
+                        index,
+                        name.as_mut_ptr(),
+                        name.len() as i32,
+                        &mut channels,
+                        &mut aux,
+                    )
+                };
+                (result == 0).then(|| OutputBusInfo {
+                    name: buffer_to_string(&name),
+                    channels: channels.max(0) as u32,
+                    aux: aux != 0,
+                })
+            })
+            .collect()
+    }
+
+    /// Activate or deactivate an auxiliary output bus; takes effect at the next
+    /// `setup_processing`. The main bus (index 0) is always active.
+    pub fn enable_output_bus(&self, index: usize, enabled: bool) -> Result<(), Vst3GuiError> {
+        let result = unsafe { ffi::vst3_gui_enable_output_bus(self.handle, index as i32, enabled as i32) };
+        check_result(result)
+    }
+
+    /// Names of the plugin's programs (factory presets), empty if it has none
+    pub fn program_names(&self) -> Vec<String> {
+        let count = unsafe { ffi::vst3_gui_get_program_count(self.handle) };
+        (0..count.max(0))
+            .map(|index| {
+                let mut name = [0 as c_char; 256];
+                let result =
+                    unsafe { ffi::vst3_gui_get_program_name(self.handle, index, name.as_mut_ptr(), name.len() as i32) };
+                if result == 0 {
+                    buffer_to_string(&name)
+                } else {
+                    format!("Program {}", index + 1)
+                }
+            })
+            .collect()
+    }
+
+    /// Currently selected program (UI thread)
+    pub fn current_program(&self) -> Option<usize> {
+        let result = unsafe { ffi::vst3_gui_get_program(self.handle) };
+        usize::try_from(result).ok()
+    }
+
+    /// Select a program on both the controller and processor (UI thread)
+    pub fn set_program(&self, index: usize) -> Result<(), Vst3GuiError> {
+        let result = unsafe { ffi::vst3_gui_set_program(self.handle, index as i32) };
+        check_result(result)
+    }
+
+    /// Apply queued parameter changes to the edit controller and, on Linux, run the
+    /// editor's timers and fd handlers (UI thread, call every frame while an editor is open)
+    pub fn idle(&self) {
+        unsafe { ffi::vst3_gui_idle(self.handle) };
+    }
+
+    /// Prepare the processor; call again when the sample rate changes
+    pub fn setup_processing(&self, sample_rate: f64, max_block_size: usize) -> Result<(), Vst3GuiError> {
+        let result = unsafe { ffi::vst3_gui_setup_processing(self.handle, sample_rate, max_block_size as i32) };
+        check_result(result)
+    }
+
+    /// Queue a note on/off for the next `process` call (velocity normalized 0-1)
+    pub fn queue_note(&self, note_on: bool, channel: u8, pitch: u8, velocity: f32, sample_offset: u32) -> Result<(), Vst3GuiError> {
+        let result = unsafe {
+            ffi::vst3_gui_queue_note(
+                self.handle,
+                note_on as i32,
+                channel as i32,
+                pitch as i32,
+                velocity,
+                sample_offset as i32,
+            )
+        };
+        check_result(result)
+    }
+
+    /// Queue a MIDI controller change for the next `process` call (value normalized 0-1)
+    ///
+    /// `controller` is a CC number, `CONTROLLER_AFTERTOUCH` or `CONTROLLER_PITCH_BEND`. VST3 plugins take
+    /// these as changes of the parameter they map the controller to; an unmapped
+    /// controller is an `InvalidParam` error.
+    pub fn queue_controller(&self, channel: u8, controller: u8, value: f64, sample_offset: u32) -> Result<(), Vst3GuiError> {
+        let result = unsafe {
+            ffi::vst3_gui_queue_controller(
+                self.handle,
+                channel as i32,
+                controller as i32,
+                value,
+                sample_offset as i32,
+            )
+        };
+        check_result(result)
+    }
+
+    /// Queue a polyphonic aftertouch change for the next `process` call (pressure normalized 0-1)
+    pub fn queue_poly_pressure(&self, channel: u8, pitch: u8, pressure: f32, sample_offset: u32) -> Result<(), Vst3GuiError> {
+        let result = unsafe {
+            ffi::vst3_gui_queue_poly_pressure(
+                self.handle,
+                channel as i32,
+                pitch as i32,
+                pressure,
+                sample_offset as i32,
+            )
+        };
+        check_result(result)
+    }
+
+    /// Process one block of planar stereo audio
+    ///
+    /// Every buffer must hold at least `num_frames` samples, and `num_frames`
+    /// must not exceed the block size given to `setup_processing`.
+    pub fn process(&self, inputs: [&mut [f32]; 2], outputs: [&mut [f32]; 2], num_frames: usize) -> Result<(), Vst3GuiError> {
+        if inputs.iter().chain(outputs.iter()).any(|b| b.len() < num_frames) {
+            return Err(Vst3GuiError::InvalidParam);
+        }
+        let [in_left, in_right] = inputs;
+        let [out_left, out_right] = outputs;
+        let input_ptrs = [in_left.as_mut_ptr(), in_right.as_mut_ptr()];
+        let output_ptrs = [out_left.as_mut_ptr(), out_right.as_mut_ptr()];
+        let result = unsafe {
+            ffi::vst3_gui_process(self.handle, input_ptrs.as_ptr(), output_ptrs.as_ptr(), num_frames as i32)
+        };
+        check_result(result)
+    }
+
+    /// Process one block, delivering the first `outputs.len()` output buses as
+    /// stereo pairs (main bus first); other buses are discarded
+    ///
+    /// Disabled auxiliary buses leave their buffers untouched, so clear them first.
+    /// At most `MAX_OUTPUT_BUSES` buses are delivered.
+    pub fn process_buses(
+        &self,
+        inputs: [&mut [f32]; 2],
+        outputs: &mut [[&mut [f32]; 2]],
+        num_frames: usize,
+    ) -> Result<(), Vst3GuiError> {
+        if outputs.is_empty() || outputs.len() > MAX_OUTPUT_BUSES {
+            return Err(Vst3GuiError::InvalidParam);
+        }
+        if inputs.iter().chain(outputs.iter().flatten()).any(|b| b.len() < num_frames) {
+            return Err(Vst3GuiError::InvalidParam);
+        }
+        let [in_left, in_right] = inputs;
+        let input_ptrs = [in_left.as_mut_ptr(), in_right.as_mut_ptr()];
+        let mut output_ptrs = [ptr::null_mut::<f32>(); MAX_OUTPUT_BUSES * 2];
+        for (pair, bus) in output_ptrs.chunks_mut(2).zip(outputs.iter_mut()) {
+            pair[0] = bus[0].as_mut_ptr();
+            pair[1] = bus[1].as_mut_ptr();
+        }
+        let result = unsafe {
+            ffi::vst3_gui_process_buses(
+                self.handle,
+                input_ptrs.as_ptr(),
+                output_ptrs.as_ptr(),
+                outputs.len() as i32,
+                num_frames as i32,
+            )
+        };
+        check_result(result)
+    }
+
     /// Get the component state as a byte array
     /// This captures the full plugin state including presets, samples, etc.
     pub fn get_component_state(&self) -> Result<Vec<u8>, Vst3GuiError> {
@@ -157,6 +759,13 @@ impl Vst3Gui {
         buffer.truncate(result as usize);
         Ok(buffer)
     }
+
+    /// Restore a component state previously returned by `get_component_state`
+    pub fn set_component_state(&self, state: &[u8]) -> Result<(), Vst3GuiError> {
+        let size = i32::try_from(state.len()).map_err(|_| Vst3GuiError::InvalidParam)?;
+        let result = unsafe { ffi::vst3_gui_set_component_state(self.handle, state.as_ptr(), size) };
+        check_result(result)
+    }
 }
 
 impl Drop for Vst3Gui {